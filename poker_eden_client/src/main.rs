@@ -15,75 +15,135 @@
 //
 // Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
 
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+use chrono::Utc;
+use poker_eden_client_core::{
+    chat::parse_say,
+    display::{display_width, SuitStyle},
+    games::{describe, GameBook},
+    outbox::action_label,
+    quiz::{EquityQuiz, RECENT_QUESTIONS},
+    share::join_url,
+    trainer::{Question, Trainer},
+    session, parse_in_room_input, parse_login_input, LoginCommand, NetworkEvent, Session, SessionEvent,
 };
-use futures_util::{SinkExt, StreamExt};
 use poker_eden_core::*;
 use std::{
     error::Error,
     io,
-    str::FromStr,
-    sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::sync::mpsc;
-use tui::{
+use ratatui::{
     backend::{Backend, CrosstermBackend},
+    crossterm::{
+        event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+        execute,
+        terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    },
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::{Span, Spans, Text},
-    widgets::{
-        Block, BorderType, Borders, Cell, List, ListItem, Paragraph, Row, Table, Wrap,
-    },
+    text::{Line, Span, Text},
+    widgets::{Block, BorderType, Borders, List, ListItem, Padding, Paragraph, Sparkline},
     Frame, Terminal,
 };
-use uuid::Uuid;
+use projector::{Projector, ProjectorView, DISPLAY_NICKNAME};
+use share::qr_code;
+use theme::{TableLayout, Theme};
+use widgets::{card_spans, ActionBar, BigBoard, BigNumber, Board, ChatPanel, FollowPanel, HostDashboard, PlayersTable, Standings};
+
+mod projector;
+mod share;
+mod theme;
+mod widgets;
 
 // --- 应用程序状态 ---
 
+/// 预备弃牌后，需要在这个时间内再次输入 `f` 确认
+const FOLD_ARM_WINDOW: Duration = Duration::from_secs(5);
+
+/// 单键弃牌 `f` 的保护方式，防止聊天或输入其他指令时误触弃牌
+///
+/// 通过环境变量 `POKER_EDEN_FOLD_KEY` 或房间内的 `foldkey` 指令设置。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum FoldKey {
+    /// 输入 `f` 直接弃牌
+    #[default]
+    Single,
+    /// 先输入 `f` 预备，再输入一次 `f` 确认弃牌
+    Arm,
+    /// 关闭单键弃牌，只能输入完整的 `fold`
+    Off,
+}
+
+impl FoldKey {
+    fn from_env() -> Self {
+        std::env::var("POKER_EDEN_FOLD_KEY").ok().and_then(|v| Self::parse(&v)).unwrap_or_default()
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "single" => Some(FoldKey::Single),
+            "arm" => Some(FoldKey::Arm),
+            "off" => Some(FoldKey::Off),
+            _ => None,
+        }
+    }
+
+    /// 动作提示中弃牌的写法
+    fn hint(self) -> &'static str {
+        match self {
+            FoldKey::Single => "[f]弃牌(Fold)",
+            FoldKey::Arm => "[f 两次]弃牌(Fold)",
+            FoldKey::Off => "[fold]弃牌(Fold)",
+        }
+    }
+}
+
 /// 用于管理UI显示哪个界面的状态机
 #[derive(PartialEq, Debug)]
 enum ClientUiState {
     Login,  // 登录/选择房间界面
     InRoom, // 在房间内（包括观战和游戏）
+    Trainer, // 离线的牌力训练
+    Quiz, // 离线的胜率估算测验
 }
 
 /// 这个结构体持有客户端运行所需的所有状态。
+/// 连接和游戏相关的状态在 `session` 中，这里只保存终端界面自己的状态。
 struct App {
     /// 控制当前显示哪个UI界面。
     ui_state: ClientUiState,
-    /// 当前的游戏状态，从服务器接收。如果没有连接或游戏未开始，则为 None。
-    game_state: Option<GameState>,
-    /// 连接到的服务器地址
-    server_addr: Option<String>,
-    /// 用于向网络任务发送消息的发送器。
-    msg_sender: Option<mpsc::Sender<ClientMessage>>,
-    /// 创建房间后生成的分享信息。
-    share_info: Option<String>,
-    /// 客户端自己的玩家ID。
-    my_id: Option<PlayerId>,
-    /// 房主ID
-    host_id: Option<PlayerId>,
-
-    // 游戏过程中的状态
-    /// 客户端当前的牌型
-    hand_ranks: Vec<Option<HandRank>>,
-    /// 上一局的筹码
-    last_stack: Vec<u32>,
-    /// 当轮到自己行动时，服务器会发送过来当前合法的动作列表。
-    valid_actions: Vec<PlayerActionType>,
+    /// 与服务器的会话
+    session: Session,
+    /// 加入链接的二维码，和分享信息同时显示、同时清除
+    share_qr: Option<String>,
 
     /// 用户在输入框中输入的当前文本。
     input: String,
-    /// 从服务器收到的最后一条错误信息或提示信息。
-    last_msg: Option<String>,
     /// 是否显示日志视图的标志。
     show_log: bool,
-    /// 存储所有发送和接收的原始消息，用于调试。
-    log_messages: Vec<String>,
+    /// 是否显示牌型速查面板
+    show_cheat_sheet: bool,
+    /// 是否显示房主面板
+    show_host_panel: bool,
+    /// 观战时跟随行动: 滚动到正在行动的玩家，并显示底池赔率和全下胜率
+    follow: bool,
+    /// 就座时在玩家列表中显示自己和每位对手之间的有效筹码
+    show_effective: bool,
+    /// 单键弃牌的保护方式
+    fold_key: FoldKey,
+    /// 预备弃牌的时间，`FoldKey::Arm` 下第一次输入 `f` 时记录
+    fold_armed_at: Option<Instant>,
+    /// 花色的显示方式，终端显示 emoji 花色不对齐时可以换成符号或 ASCII
+    theme: Theme,
+    /// 投屏模式，只显示牌桌，不接受游戏输入
+    projector: Option<Projector>,
+    /// 牌力训练的进度，只在训练界面中存在
+    trainer: Option<Trainer>,
+    /// 胜率估算测验的进度，只在测验界面中存在
+    quiz: Option<EquityQuiz>,
+    /// 聊天输入模式: 输入框里的内容作为聊天消息发送
+    chat_mode: bool,
     should_refresh: bool,  // 是否需要刷新UI
 }
 
@@ -91,33 +151,67 @@ impl Default for App {
     fn default() -> Self {
         Self {
             ui_state: ClientUiState::Login, // 默认启动时是登录界面
-            game_state: None,
-            server_addr: None,
-            msg_sender: None,
-            share_info: None,
-            my_id: None,
-            host_id: None,
-            hand_ranks: vec![],
-            last_stack: vec![],
+            session: Session::default(),
+            share_qr: None,
             input: String::new(),
-            valid_actions: vec![],
-            last_msg: None,
             show_log: false,
-            log_messages: Vec::new(),
+            show_cheat_sheet: false,
+            show_host_panel: false,
+            follow: false,
+            show_effective: false,
+            fold_key: FoldKey::from_env(),
+            fold_armed_at: None,
+            theme: Theme::from_env(),
+            projector: None,
+            trainer: None,
+            quiz: None,
+            chat_mode: false,
             should_refresh: true,
         }
     }
 }
 
-/// 用于解析登录界面输入的命令
-enum LoginCommand {
-    Create { server_addr: String, nickname: String },
-    Join { server_addr: String, room_id: RoomId, nickname: String },
+impl App {
+    /// 根据会话事件更新界面状态
+    fn apply(&mut self, events: Vec<SessionEvent>) {
+        for event in events {
+            match event {
+                SessionEvent::Updated => self.should_refresh = true,
+                SessionEvent::EnteredRoom => {
+                    self.ui_state = ClientUiState::InRoom;
+                    // 房主生成分享信息时，同时显示加入链接的二维码
+                    self.share_qr = match (&self.session.share_info, &self.session.server_addr, &self.session.game_state) {
+                        (Some(_), Some(addr), Some(gs)) => qr_code(&join_url(addr, gs.room_id)),
+                        _ => None,
+                    };
+                }
+                SessionEvent::HandStarted => self.share_qr = None,
+                SessionEvent::ShowLog => self.show_log = true,
+                // 房间关闭的原因留在 last_msg 中，显示在登录界面的输入框里
+                SessionEvent::LeftRoom => {
+                    self.ui_state = ClientUiState::Login;
+                    self.share_qr = None;
+                    self.show_log = false;
+                }
+            }
+        }
+    }
 }
 
 // 应用程序的入口点
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    // --- 解析命令行参数 ---
+    let args: Vec<String> = std::env::args().collect();
+    let projector = match Projector::from_args(&args) {
+        Ok(projector) => projector,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let guest = args.iter().any(|a| a == "--guest");
+
     // --- 设置终端 ---
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -125,436 +219,252 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // --- App 状态 ---
-    let app = Arc::new(Mutex::new(App::default()));
-
-    // --- 主UI循环 ---
-    loop {
-        terminal.draw(|f| ui(f, &mut app.lock().unwrap()))?;
-
-        if event::poll(Duration::from_millis(100))? {
-            if let Event::Key(key) = event::read()? {
-                let mut app_guard = app.lock().unwrap();
-                match key.code {
-                    KeyCode::Enter => {
-                        let input = app_guard.input.drain(..).collect::<String>();
-                        match app_guard.ui_state {
-                            ClientUiState::Login => {
-                                if let Some(login_cmd) = parse_login_input(&input) {
-                                    let (tx, rx) = mpsc::channel(32);
-                                    app_guard.msg_sender = Some(tx.clone());
-
-                                    let (server_addr, initial_msg) = match login_cmd {
-                                        LoginCommand::Create { server_addr, nickname } => {
-                                            (server_addr, ClientMessage::CreateRoom { nickname })
-                                        }
-                                        LoginCommand::Join { server_addr, room_id, nickname } => {
-                                            (server_addr, ClientMessage::JoinRoom { room_id, nickname })
-                                        }
-                                    };
-
-                                    app_guard.server_addr = Some(server_addr.clone());
-                                    let app_for_network = app.clone();
-                                    tokio::spawn(network_task(app_for_network, tx.clone(), rx, server_addr));
-
-                                    // 发送第一条消息 (创建或加入)
-                                    tokio::spawn(async move {
-                                        tx.send(initial_msg).await.ok();
-                                    });
-                                }
-                            }
-                            ClientUiState::InRoom => {
-                                if let (Some(msg), Some(tx)) = (parse_in_room_input(&input, &app_guard), app_guard.msg_sender.as_ref()) {
-                                    let _ = tx.try_send(msg);
-                                }
-                            }
-                        }
-                    }
-                    KeyCode::Char(c) => app_guard.input.push(c),
-                    KeyCode::Backspace => { app_guard.input.pop(); }
-                    KeyCode::Tab => {
-                        app_guard.show_log = !app_guard.show_log;
-                        app_guard.should_refresh = true;
-                    }
-                    KeyCode::Esc => break,
-                    _ => {}
-                }
+    // 读取键盘是阻塞的，放到单独的线程里，按键通过通道交给UI循环
+    let (key_tx, mut key_rx) = mpsc::unbounded_channel::<KeyCode>();
+    std::thread::spawn(move || {
+        while let Ok(event) = event::read() {
+            if let Event::Key(key) = event
+                && key_tx.send(key.code).is_err() {
+                break;
             }
         }
+    });
+
+    let mut app = App::default();
+    // 访客模式下多人共用同一个系统账号 (例如通过 SSH 登录)，书签只保存在内存中，避免互相看到对方的牌局
+    if guest {
+        app.session.games = GameBook::default();
     }
+    app.projector = projector;
+    let result = run_app(&mut terminal, &mut key_rx, app).await;
 
     // --- 恢复终端 ---
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
     terminal.show_cursor()?;
-    Ok(())
+    result
 }
 
-/// 独立的网络任务，处理所有与服务器的通信。
-async fn network_task(app: Arc<Mutex<App>>, tx: mpsc::Sender<ClientMessage>, mut rx: mpsc::Receiver<ClientMessage>, server_addr: String) {
-    let url = url::Url::parse(&format!("ws://{}/ws", server_addr)).unwrap();
-
-    let ws_stream = match tokio_tungstenite::connect_async(url.as_str()).await {
-        Ok((stream, _)) => stream,
-        Err(e) => {
-            let mut app_guard = app.lock().unwrap();
-            app_guard.last_msg = Some(format!("连接服务器失败: {}", e));
-            return;
-        }
-    };
-    app.lock().unwrap().log_messages.push("已连接到服务器".to_string());
+/// 主UI循环
+///
+/// 渲染只依赖 ratatui 的 [`Backend`]，按键从通道读取，
+/// 因此同一套界面既可以跑在本地终端上，也可以跑在其他终端后端上 (例如远程会话的输出流)。
+/// 通道关闭或按下 Esc 时返回。
+async fn run_app<B>(terminal: &mut Terminal<B>, keys: &mut mpsc::UnboundedReceiver<KeyCode>, mut app: App) -> Result<(), Box<dyn Error>>
+where
+    B: Backend,
+    B::Error: 'static,
+{
+    // App 只由UI循环持有，网络任务通过通道把事件发过来，避免渲染和网络互相等锁
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel::<NetworkEvent>();
 
-    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
-    loop {
-        tokio::select! {
-            Some(msg_to_send) = rx.recv() => {
-                let msg_text = serde_json::to_string(&msg_to_send).unwrap();
-                app.lock().unwrap().log_messages.push(format!("[SEND_TO_SERVER] {}", msg_text));
-                if ws_sender.send(tokio_tungstenite::tungstenite::Message::Text(msg_text.into())).await.is_err() {
-                    let mut app_guard = app.lock().unwrap();
-                    app_guard.last_msg = Some("与服务器的连接已断开。".to_string());
-                    break;
-                }
-            }
-            Some(Ok(msg)) = ws_receiver.next() => {
-                if let tokio_tungstenite::tungstenite::Message::Text(text) = msg {
-                    let mut app_guard = app.lock().unwrap();
-                    app_guard.log_messages.push(format!("[RECV] {}", text));
-                    if let Ok(server_msg) = serde_json::from_str::<ServerMessage>(&text) {
-                        let ret_msgs = handle_server_message(&mut app_guard, server_msg);
-                        for msg in ret_msgs {
-                            let _ = tx.try_send(msg);
-                        }
-                    }
-                } else if msg.is_close() {
-                    let mut app_guard = app.lock().unwrap();
-                    app_guard.last_msg = Some("服务器已关闭连接。".to_string());
-                    break;
-                }
-            }
-            else => break,
-        }
+    // 投屏模式启动后直接以观众身份加入房间
+    if let Some(projector) = &app.projector {
+        let initial_msg = ClientMessage::JoinRoom { room_id: projector.room_id, nickname: DISPLAY_NICKNAME.to_string() };
+        let server_addr = projector.server_addr.clone();
+        app.session.connect(&event_tx, server_addr, initial_msg);
+    } else {
+        app.session.check_in_games(&event_tx);
     }
-}
 
-/// 处理从服务器收到的消息，并据此更新应用程序的状态。
-fn handle_server_message(app: &mut App, msg: ServerMessage) -> Vec<ClientMessage> {
-    let mut ret_msgs = vec![];
-    app.last_msg = None; // 收到任何消息都清除上一条错误
-    app.should_refresh = true;
-    match msg {
-        // 成功加入房间后，将UI状态切换到 InRoom
-        ServerMessage::RoomJoined { your_id, game_state, host_id, .. } => {
-            app.my_id = Some(your_id);
-            app.game_state = Some(game_state.clone());
-            app.host_id = Some(host_id);
-            app.ui_state = ClientUiState::InRoom; // 切换UI状态
-
-            let playing_num = game_state.hand_player_order.len();
-            app.hand_ranks = vec![None; playing_num];
-            app.last_stack = vec![0; playing_num];
-
-            // 如果是房主，生成分享链接
-            if app.my_id == app.host_id {
-                let share_addr = app.server_addr.as_ref().cloned().unwrap_or_default();
-                app.share_info = Some(format!("分享信息: join {} {}", share_addr, game_state.room_id));
-            }
-        }
-        ServerMessage::GameStateSnapshot(new_state) => app.game_state = Some(new_state),
-        ServerMessage::PlayerJoined { player } => {
-            if let Some(gs) = &mut app.game_state { gs.players.insert(player.id, player); }
+    // --- 主UI循环 ---
+    loop {
+        while let Ok(event) = event_rx.try_recv() {
+            let events = app.session.handle_network_event(event);
+            app.apply(events);
         }
-        ServerMessage::PlayerLeft { player_id } => {
-            if let Some(gs) = &mut app.game_state {
-                gs.players.get_mut(&player_id).unwrap().is_offline = true;
-            }
+        app.session.flush(Instant::now());
+        if let Some(projector) = &mut app.projector
+            && projector.advance(app.session.game_state.as_ref(), Instant::now()) {
+            app.should_refresh = true;
         }
-        ServerMessage::PlayerUpdated { player } => {
-            if let Some(gs) = &mut app.game_state {
-                // 根据玩家状态变化，更新 seated_players 列表
-                if player.state == PlayerState::Waiting {
-                    // 如果玩家不在就座列表，则加入
-                    if let Some(idx) = gs.seated_players.iter().position(|p| *p == player.id) {
-                        gs.seated_players.remove(idx);
-                        if let Some(i) = gs.player_indices.get(&player.id) {
-                            app.last_stack[*i] = player.stack;
-                        }
-                    }
-                    app.log_messages.push(format!("玩家 {} 已坐下准备游戏", player.nickname));
-                    gs.seated_players.insert(gs.find_insertion_index(player.seat_id.unwrap()), player.id);
-                } else if player.state == PlayerState::SittingOut {
-                    // 如果玩家在就座列表，则移除
-                    app.log_messages.push(format!("玩家 {} 离席", player.nickname));
-                    if let Some(idx) = gs.seated_players.iter().position(|id| id == &player.id) {
-                        gs.seated_players.remove(idx);
-                    }
-                }
 
-                // 总是更新玩家在主列表中的数据
-                if let Some(p) = gs.players.get_mut(&player.id) {
-                    *p = player;
+        terminal.draw(|f| ui(f, &mut app))?;
+
+        let key = match tokio::time::timeout(Duration::from_millis(50), keys.recv()).await {
+            Ok(Some(key)) => key,
+            Ok(None) => break,
+            Err(_) => continue,
+        };
+        match key {
+            // 离线后在房间内只能重新连接，其他输入没有意义，直接忽略
+            KeyCode::Char('r' | 'R') if app.session.offline && app.ui_state == ClientUiState::InRoom
+                && app.projector.is_none() => app.session.reconnect(&event_tx),
+            KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Enter
+                if app.session.offline && app.ui_state == ClientUiState::InRoom => {}
+            // 投屏模式是只读的，只能查看日志和退出
+            KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Enter | KeyCode::F(1) | KeyCode::F(2) | KeyCode::F(3)
+                if app.projector.is_some() => {}
+            KeyCode::Enter if app.chat_mode && app.ui_state == ClientUiState::InRoom => {
+                let text = app.input.drain(..).collect::<String>();
+                if !text.trim().is_empty() {
+                    app.session.outbox.push(ClientMessage::Chat { text: text.trim().to_string() });
                 }
             }
-        }
-        ServerMessage::HandStarted { seated_players, hand_player_order } => {
-            if let Some(gs) = &mut app.game_state {
-                app.share_info = None; // 游戏开始后清除分享信息
-                gs.seated_players = seated_players;
-                gs.hand_player_order = hand_player_order;
-                gs.player_indices = gs.hand_player_order.iter().enumerate().map(|(i, id)| (*id, i)).collect();
-                gs.phase = GamePhase::PreFlop;
-                gs.pot = 0;
-                gs.bets = vec![0; gs.hand_player_order.len()];
-                gs.last_bet = 0;
-                gs.community_cards = vec![None; 5];
-                gs.player_cards = vec![(None, None); gs.hand_player_order.len()];
-                app.hand_ranks = vec![None; gs.hand_player_order.len()];
-                for p in gs.players.values_mut() {
-                    if gs.hand_player_order.contains(&p.id) { p.state = PlayerState::Playing; }
-                }
-                for player_id in gs.seated_players.iter() {
-                    if let Some(p) = gs.players.get_mut(player_id) {
-                        if p.is_offline || p.stack == 0 {
-                            p.state = PlayerState::SittingOut;
+            KeyCode::Enter => {
+                let input = app.input.drain(..).collect::<String>();
+                match app.ui_state {
+                    ClientUiState::Login => match parse_login_input(&input) {
+                        Some(LoginCommand::Create { server_addr, nickname, config }) => {
+                            app.session.connect(&event_tx, server_addr, ClientMessage::CreateRoom { nickname, config });
                         }
-                    }
-                }
-                app.last_stack = gs.hand_player_order.iter().map(|p| {
-                    gs.players.get(&p).unwrap().stack
-                }).collect();
-                ret_msgs.push(ClientMessage::GetMyHand);
-            }
-        }
-        ServerMessage::PlayerHand { hands } => {
-            if let Some(gs) = &mut app.game_state {
-                if let Some(idx) = gs.player_indices.get(&app.my_id.unwrap()) {
-                    gs.player_cards[*idx] = (Some(hands.0), Some(hands.1))
-                }
-            }
-        }
-        ServerMessage::PlayerActed { player_id, action, total_bet: total_bet_this_round, new_stack, new_pot } => {
-            if let Some(gs) = &mut app.game_state {
-                gs.pot = new_pot;
-                if let Some(p_idx) = gs.player_indices.get(&player_id) {
-                    gs.bets[*p_idx] = total_bet_this_round;
-                    if let Some(p) = gs.players.get_mut(&player_id) {
-                        p.stack = new_stack;
-                        match action {
-                            PlayerAction::Fold => p.state = PlayerState::Folded,
-                            _ => { if p.stack == 0 && p.state != PlayerState::Folded { p.state = PlayerState::AllIn } }
+                        Some(LoginCommand::Join { server_addr, room_id, nickname }) => {
+                            app.session.connect(&event_tx, server_addr, ClientMessage::JoinRoom { room_id, nickname });
                         }
-                    }
-                }
-                gs.max_bet = gs.max_bet.max(total_bet_this_round);
-            }
-        }
-        ServerMessage::NextToAct { player_id, valid_actions } => {
-            if let Some(gs) = &mut app.game_state {
-                if let Some(idx) = gs.player_indices.get(&player_id) { gs.cur_player_idx = *idx; }
-            }
-            if app.my_id == Some(player_id) { app.valid_actions = valid_actions; } else { app.valid_actions.clear(); }
-        }
-        ServerMessage::CommunityCardsDealt { phase, cards, last_bet } => {
-            if let Some(gs) = &mut app.game_state {
-                gs.phase = phase;
-                let start_idx = match phase {
-                    GamePhase::Flop => 0,
-                    GamePhase::Turn => 3,
-                    GamePhase::River => 4,
-                    _ => return vec![],
-                };
-                gs.last_bet = last_bet;
-                if gs.community_cards.is_empty() { gs.community_cards = vec![None; 5]; }
-                for (i, card) in cards.into_iter().enumerate() { gs.community_cards[start_idx + i] = Some(card); }
-
-                // 更新玩家的牌型
-                let community_cards = gs.community_cards.iter().map_while(|card| {
-                    card.clone()
-                }).collect::<Vec<_>>();
-                for (p_idx, player_card) in gs.player_cards.iter().enumerate() {
-                    if let (Some(card1), Some(card2)) = player_card {
-                        let mut cards = community_cards.clone();
-                        cards.push(*card1);
-                        cards.push(*card2);
-                        let rank = find_best_hand(&cards);
-                        app.hand_ranks[p_idx] = Some(rank);
-                    }
-                }
-            }
-        }
-        ServerMessage::Showdown { results } => {
-            if let Some(gs) = &mut app.game_state {
-                gs.phase = GamePhase::Showdown;
-                for result in results {
-                    if let Some(p) = gs.players.get_mut(&result.player_id) {
-                        if result.winnings > 0 {
-                            p.stack += result.winnings;
-                            p.wins += 1;
+                        Some(LoginCommand::Train) => {
+                            app.trainer = Some(Trainer::new(Instant::now()));
+                            app.session.last_msg = None;
+                            app.ui_state = ClientUiState::Trainer;
+                        }
+                        Some(LoginCommand::Quiz) => {
+                            app.quiz = Some(EquityQuiz::new(Instant::now()));
+                            app.session.last_msg = None;
+                            app.ui_state = ClientUiState::Quiz;
+                        }
+                        Some(LoginCommand::Games) => app.session.check_in_games(&event_tx),
+                        Some(LoginCommand::Resume(n)) => app.session.resume_game(&event_tx, n),
+                        None => {}
+                    },
+                    ClientUiState::Trainer => {
+                        if input.trim().eq_ignore_ascii_case("quit") {
+                            app.trainer = None;
+                            app.ui_state = ClientUiState::Login;
+                        } else if let Some(trainer) = &mut app.trainer {
+                            app.session.last_msg = (!trainer.answer(&input, Instant::now()))
+                                .then(|| "无法识别的回答，请按题目提示输入".to_string());
                         }
                     }
-                    if let (Some(p_idx), Some(cards), Some(hand_rank))
-                        = (gs.player_indices.get(&result.player_id), result.cards, result.hand_rank) {
-                        gs.player_cards[*p_idx] = (Some(cards.0), Some(cards.1));
-                        app.hand_ranks[*p_idx] = Some(hand_rank);
+                    ClientUiState::Quiz => {
+                        if input.trim().eq_ignore_ascii_case("quit") {
+                            app.quiz = None;
+                            app.ui_state = ClientUiState::Login;
+                        } else if let Some(quiz) = &mut app.quiz {
+                            app.session.last_msg = (!quiz.answer(&input, Instant::now()))
+                                .then(|| "请输入 0 到 100 之间的百分比".to_string());
+                        }
                     }
-                }
-                for p in gs.hand_player_order.iter() {
-                    if let Some(p) = gs.players.get_mut(p) {
-                        if p.stack == 0 {
-                            p.losses += 1;
-                            p.is_offline = true;
-                        };
+                    ClientUiState::InRoom => {
+                        // 新手模式、花色显示方式和单键弃牌保护是客户端本地的选项，不需要发给服务器
+                        let local = input.trim().to_lowercase();
+                        // 预备弃牌只对紧接着的下一次输入有效
+                        let fold_armed = app.fold_armed_at.take().is_some_and(|at| at.elapsed() < FOLD_ARM_WINDOW);
+                        match local.as_str() {
+                            _ if let Some(text) = parse_say(&input) => {
+                                app.session.outbox.push(ClientMessage::Chat { text: text.to_string() });
+                            }
+                            "beginner on" => app.session.beginner_mode = true,
+                            "beginner off" => app.session.beginner_mode = false,
+                            "follow on" => app.follow = true,
+                            "follow off" => app.follow = false,
+                            "effective on" => app.show_effective = true,
+                            "effective off" => app.show_effective = false,
+                            "stats on" => app.session.advanced_stats = true,
+                            "stats off" => app.session.advanced_stats = false,
+                            "f" if app.fold_key == FoldKey::Off => {
+                                app.session.last_msg = Some("单键弃牌已关闭，请输入 fold 弃牌".to_string());
+                            }
+                            "f" if app.fold_key == FoldKey::Arm && !fold_armed => {
+                                app.fold_armed_at = Some(Instant::now());
+                                app.session.last_msg = Some(format!("已预备弃牌，{} 秒内再次输入 f 确认", FOLD_ARM_WINDOW.as_secs()));
+                            }
+                            _ if let Some(fold_key) = local.strip_prefix("foldkey ").and_then(FoldKey::parse) => {
+                                app.fold_key = fold_key;
+                            }
+                            _ if let Some(style) = local.strip_prefix("suits ").and_then(SuitStyle::parse) => {
+                                app.theme.suits = style;
+                                app.session.suits = style;
+                                app.should_refresh = true;
+                            }
+                            _ => if let Some(msg) = parse_in_room_input(&input, &app.session) {
+                                app.session.send(msg);
+                            }
+                        }
                     }
                 }
             }
-        }
-        ServerMessage::BetReturned { player_id, amount, new_stack } => {
-            if let Some(gs) = &mut app.game_state {
-                if let Some(p) = gs.players.get_mut(&player_id) {
-                    p.stack = new_stack;
-                }
-                gs.pot -= amount;
+            KeyCode::Char(c) => app.input.push(c),
+            KeyCode::Backspace => { app.input.pop(); }
+            KeyCode::Tab => {
+                app.show_log = !app.show_log;
+                app.should_refresh = true;
             }
-        }
-        ServerMessage::Error { message } | ServerMessage::Info { message } => app.last_msg = Some(message),
-    }
-    ret_msgs
-}
-
-/// 解析登录界面的输入
-fn parse_login_input(input: &str) -> Option<LoginCommand> {
-    let parts: Vec<&str> = input.trim().split_whitespace().collect();
-    if parts.len() < 3 { return None; }
-
-    match parts[0].to_lowercase().as_str() {
-        "create" if parts.len() == 3 => {
-            // 简单验证地址格式，但不做完整解析
-            if parts[1].contains(':') {
-                Some(LoginCommand::Create { server_addr: parts[1].to_string(), nickname: parts[2].to_string() })
-            } else { None }
-        }
-        "join" if parts.len() == 4 => {
-            if let Ok(room_id) = Uuid::from_str(parts[2]) {
-                if parts[1].contains(':') {
-                    Some(LoginCommand::Join { server_addr: parts[1].to_string(), room_id, nickname: parts[3].to_string() })
-                } else { None }
-            } else { None }
-        }
-        _ => None,
-    }
-}
-
-/// 解析在房间内的输入（坐下或游戏动作）
-fn parse_in_room_input(input: &str, app: &App) -> Option<ClientMessage> {
-    let parts: Vec<&str> = input.trim().split_whitespace().collect();
-    if parts.is_empty() { return None; }
-
-    // 检查玩家是否已经就座
-    let is_seated = app.my_id.map_or(false, |my_id| {
-        app.game_state.as_ref().map_or(false, |gs| gs.seated_players.contains(&my_id))
-    });
-
-    // 检查是否为房主、已就座、在等待阶段，以解析 "start" 命令
-    if app.my_id == app.host_id && is_seated && parts[0].to_lowercase() == "start"
-        && app.game_state.as_ref().map_or(false, |gs| {
-        gs.phase == GamePhase::WaitingForPlayers || gs.phase == GamePhase::Showdown
-    }) {
-        return Some(ClientMessage::StartHand);
-    }
-
-    let is_lose_game = app.game_state.as_ref().map_or(false, |gs| {
-        gs.players.get(&app.my_id.unwrap()).map_or(false, |p| p.is_offline)
-    });
-
-    if !is_seated || is_lose_game {
-        // 如果未就座，只解析 "seat" 命令
-        if parts[0].to_lowercase() == "seat" && parts.len() == 3 {
-            if let (Ok(seat_id), Ok(stack)) = (parts[1].parse::<u8>(), parts[2].parse::<u32>()) {
-                return Some(ClientMessage::RequestSeat { seat_id, stack });
+            KeyCode::F(1) => {
+                app.show_cheat_sheet = !app.show_cheat_sheet;
+                app.should_refresh = true;
             }
-        }
-    } else {
-        // 如果已就座，解析游戏动作
-        return match parts[0].to_lowercase().as_str() {
-            "f" | "fold" => Some(PlayerAction::Fold.into()),
-            "c" | "check" | "call" => {
-                let mut is_check = false;
-                let mut is_call = false;
-                for valid_action in app.valid_actions.iter() {
-                    match valid_action {
-                        PlayerActionType::Check => {
-                            is_check = true;
-                            break;
-                        }
-                        PlayerActionType::Call(_) => {
-                            is_call = true;
-                            break;
-                        }
-                        _ => continue,
-                    }
-                }
-                if is_check { Some(PlayerAction::Check.into()) } else if is_call { Some(PlayerAction::Call.into()) } else { None }
+            KeyCode::F(2) if app.session.is_host() => {
+                app.show_host_panel = !app.show_host_panel;
+                app.should_refresh = true;
             }
-            "b" | "r" | "bet" | "raise" => {
-                if parts.len() > 1 {
-                    if let Ok(amount) = parts[1].parse::<u32>() {
-                        Some(PlayerAction::BetOrRaise(amount).into())
-                    } else { None }
-                } else { None }
+            KeyCode::F(3) if app.ui_state == ClientUiState::InRoom => {
+                app.chat_mode = !app.chat_mode;
+                app.should_refresh = true;
             }
-            _ => None,
-        };
+            KeyCode::PageUp if app.ui_state == ClientUiState::InRoom => app.session.chat.scroll_up(3),
+            KeyCode::PageDown if app.ui_state == ClientUiState::InRoom => app.session.chat.scroll_down(3),
+            KeyCode::Esc => break,
+            _ => {}
+        }
     }
-    None
+
+    Ok(())
 }
 
 // --- UI 渲染 ---
 
 /// 主UI绘制函数，根据客户端状态选择渲染哪个界面。
-fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+fn ui(f: &mut Frame, app: &mut App) {
     if app.show_log {
         draw_log(f, app);
         return;
     }
 
     match app.ui_state {
+        _ if app.projector.is_some() => draw_projector_screen(f, app),
         ClientUiState::Login => draw_login_screen(f, app),
         ClientUiState::InRoom => draw_ingame_screen(f, app),
+        ClientUiState::Trainer => draw_trainer_screen(f, app),
+        ClientUiState::Quiz => draw_quiz_screen(f, app),
     }
 }
 
 /// 绘制登录界面
-fn draw_login_screen<B: Backend>(f: &mut Frame<B>, app: &App) {
+fn draw_login_screen(f: &mut Frame, app: &App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(2)
         .constraints([
             Constraint::Percentage(40),
-            Constraint::Length(8), // 指令
+            Constraint::Length(12), // 指令
             Constraint::Length(3), // 输入框
             Constraint::Percentage(40),
         ].as_ref())
-        .split(f.size());
+        .split(f.area());
 
     let instructions_text = vec![
-        Spans::from(Span::styled("欢迎来到德州扑克客户端", Style::default().add_modifier(Modifier::BOLD))),
-        Spans::from(""),
-        Spans::from("->创建房间: create <服务器地址:端口> <你的昵称>"),
-        Spans::from("  例如: create 127.0.0.1:25917 Alice"),
-        Spans::from(""),
-        Spans::from("->加入房间: join <服务器地址:端口> <房间ID> <你的昵称>"),
+        Line::from(Span::styled("欢迎来到德州扑克客户端", Style::default().add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from("->创建房间: create <服务器地址:端口> <你的昵称>"),
+        Line::from("  例如: create 127.0.0.1:25917 Alice"),
+        Line::from("  可以在后面设定牌桌: blinds=50/100 seats=6 buyin=2000-10000 timer=30 bank=60"),
+        Line::from(""),
+        Line::from("->加入房间: join <服务器地址:端口> <房间ID> <你的昵称>"),
+        Line::from("  或扫码得到链接后: join <加入链接> <你的昵称>"),
+        Line::from(""),
+        Line::from("->离线练习识别牌型: train"),
+        Line::from("->离线练习估算胜率: quiz"),
     ];
     let instructions = Paragraph::new(instructions_text)
         .block(Block::default().borders(Borders::ALL).title("指令").border_type(BorderType::Rounded))
         .alignment(Alignment::Left);
     f.render_widget(instructions, chunks[1]);
 
-    let input_text = if let Some(err) = &app.last_msg {
+    let input_text = if let Some(err) = &app.session.last_msg {
         err.as_str()
     } else {
         app.input.as_ref()
     };
-    let input_style = if app.last_msg.is_some() {
+    let input_style = if app.session.last_msg.is_some() {
         Style::default().fg(Color::Red)
     } else {
         Style::default().fg(Color::Yellow)
@@ -565,42 +475,305 @@ fn draw_login_screen<B: Backend>(f: &mut Frame<B>, app: &App) {
         .block(Block::default().borders(Borders::ALL).title("输入").border_type(BorderType::Rounded));
     f.render_widget(input, chunks[2]);
 
-    if app.last_msg.is_none() {
-        f.set_cursor(chunks[2].x + app.input.len() as u16 + 1, chunks[2].y + 1);
+    if !app.session.games.is_empty() {
+        let now = Utc::now();
+        let mut lines = vec![Line::from(Span::styled(
+            format!("轮到你行动的对局: {} 个", app.session.games.your_turn_count()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ))];
+        for (i, game) in app.session.games.games.iter().enumerate() {
+            let status = app.session.games.status(game.room_id);
+            let style = if status.is_some_and(|s| s.your_turn) {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            lines.push(Line::from(Span::styled(
+                format!("{}. {} @ {} 房间 {}: {}", i + 1, game.nickname, game.server_addr,
+                        &game.room_id.to_string()[..8], describe(status, now)),
+                style,
+            )));
+        }
+        let games = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded)
+                .title("通信对局 (games 刷新，resume <序号> 回到对局)"));
+        f.render_widget(games, chunks[3]);
+    }
+
+    if app.session.last_msg.is_none() {
+        f.set_cursor_position((chunks[2].x + display_width(&app.input) + 1, chunks[2].y + 1));
+    }
+}
+
+/// 绘制牌力训练界面
+fn draw_trainer_screen(f: &mut Frame, app: &App) {
+    let Some(trainer) = &app.trainer else { return };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Min(12), Constraint::Length(3)].as_ref())
+        .split(f.area());
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(40), Constraint::Length(24)].as_ref())
+        .split(chunks[0]);
+
+    let mut lines = vec![];
+    match &trainer.question {
+        Question::BestHand { cards, .. } => {
+            lines.push(Line::from("这 7 张牌能组成的最大牌型是?"));
+            lines.push(Line::default());
+            lines.push(Line::from(card_spans(cards, app.theme)));
+            lines.push(Line::default());
+            lines.push(Line::from("输入右侧的牌型序号，或写出组成它的 5 张牌 (如 As Ks Qs Js Ts)"));
+        }
+        Question::Compare { board, hands, .. } => {
+            lines.push(Line::from("同样的公共牌下，哪手底牌更大?"));
+            lines.push(Line::default());
+            let mut board_line = vec![Span::raw("公共牌 ")];
+            board_line.extend(card_spans(board, app.theme));
+            lines.push(Line::from(board_line));
+            for (i, hand) in hands.iter().enumerate() {
+                let mut hand_line = vec![Span::raw(format!("底牌 {} ", i + 1))];
+                hand_line.extend(card_spans(hand, app.theme));
+                lines.push(Line::from(hand_line));
+            }
+            lines.push(Line::default());
+            lines.push(Line::from("输入 1 或 2，平局输入 0"));
+        }
+    }
+    lines.push(Line::default());
+    if let Some((correct, solution)) = &trainer.feedback {
+        let (text, color) = if *correct { ("✔ 正确", Color::Green) } else { ("✘ 错误", Color::Red) };
+        lines.push(Line::from(Span::styled(format!("上一题 {}: {}", text, solution), Style::default().fg(color))));
+    }
+    lines.push(Line::from(format!("已答 {} 题，正确率 {:.0}%，平均用时 {:.1} 秒",
+                                  trainer.answered, trainer.accuracy(), trainer.average_secs())));
+    let question = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("牌力训练 (输入 quit 返回)").border_type(BorderType::Rounded));
+    f.render_widget(question, columns[0]);
+
+    let categories: Vec<ListItem> = HandCategory::ALL.iter().rev().enumerate()
+        .map(|(i, category)| ListItem::new(format!("{:>2}. {}", i + 1, category)))
+        .collect();
+    let list = List::new(categories)
+        .block(Block::default().borders(Borders::ALL).title("牌型大小").border_type(BorderType::Rounded));
+    f.render_widget(list, columns[1]);
+
+    let (input_text, input_style) = match &app.session.last_msg {
+        Some(msg) if app.input.is_empty() => (msg.as_str(), Style::default().fg(Color::Red)),
+        _ => (app.input.as_str(), Style::default().fg(Color::Yellow)),
+    };
+    let input = Paragraph::new(input_text)
+        .style(input_style)
+        .block(Block::default().borders(Borders::ALL).title("回答").border_type(BorderType::Rounded));
+    f.render_widget(input, chunks[1]);
+    f.set_cursor_position((chunks[1].x + display_width(&app.input) + 1, chunks[1].y + 1));
+}
+
+/// 绘制胜率估算测验界面
+fn draw_quiz_screen(f: &mut Frame, app: &App) {
+    let Some(quiz) = &app.quiz else { return };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Min(10), Constraint::Length(6), Constraint::Length(3)].as_ref())
+        .split(f.area());
+
+    let question = &quiz.question;
+    let mut hand_line = vec![Span::raw("你的底牌 ")];
+    hand_line.extend(card_spans(&question.hand, app.theme));
+    let mut board_line = vec![Span::raw("公共牌   ")];
+    if question.board.is_empty() {
+        board_line.push(Span::raw("(翻牌前)"));
+    } else {
+        board_line.extend(card_spans(&question.board, app.theme));
+    }
+    let mut lines = vec![
+        Line::from("估算你的底牌对抗对手范围的胜率 (平局算一半)"),
+        Line::default(),
+        Line::from(hand_line),
+        Line::from(board_line),
+        Line::from(format!("对手范围 {}: {}", question.range_name(), question.range_text())),
+        Line::default(),
+        Line::from("输入 0 到 100 之间的百分比，如 45"),
+        Line::default(),
+    ];
+    if let Some((estimate, actual)) = quiz.feedback {
+        let error = (estimate - actual).abs();
+        let color = if error <= 5.0 { Color::Green } else if error <= 15.0 { Color::Yellow } else { Color::Red };
+        lines.push(Line::from(Span::styled(
+            format!("上一题: 你估算 {:.0}%，实际 {:.1}%，相差 {:.1} 个百分点", estimate, actual, error),
+            Style::default().fg(color),
+        )));
     }
+    let question_block = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("胜率估算 (输入 quit 返回)").border_type(BorderType::Rounded));
+    f.render_widget(question_block, chunks[0]);
+
+    // 每题的误差，越低越好
+    let errors: Vec<u64> = quiz.errors.iter().map(|e| e.round() as u64).collect();
+    let title = format!(
+        "误差走势  已答 {} 题，平均误差 {:.1}，最近 {} 题 {:.1}，平均用时 {:.1} 秒",
+        quiz.errors.len(), quiz.average_error(), RECENT_QUESTIONS, quiz.recent_error(), quiz.average_secs(),
+    );
+    let sparkline_width = chunks[1].width.saturating_sub(2) as usize;
+    let sparkline = Sparkline::default()
+        .data(&errors[errors.len().saturating_sub(sparkline_width)..])
+        .max(50)
+        .style(Style::default().fg(Color::Cyan))
+        .block(Block::default().borders(Borders::ALL).title(title).border_type(BorderType::Rounded));
+    f.render_widget(sparkline, chunks[1]);
+
+    let (input_text, input_style) = match &app.session.last_msg {
+        Some(msg) if app.input.is_empty() => (msg.as_str(), Style::default().fg(Color::Red)),
+        _ => (app.input.as_str(), Style::default().fg(Color::Yellow)),
+    };
+    let input = Paragraph::new(input_text)
+        .style(input_style)
+        .block(Block::default().borders(Borders::ALL).title("回答").border_type(BorderType::Rounded));
+    f.render_widget(input, chunks[2]);
+    f.set_cursor_position((chunks[2].x + display_width(&app.input) + 1, chunks[2].y + 1));
 }
 
 /// 绘制游戏内界面
-fn draw_ingame_screen<B: Backend>(f: &mut Frame<B>, app: &mut App) {
+fn draw_ingame_screen(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
         .constraints([
-            Constraint::Length(3), Constraint::Length(5), Constraint::Min(10),
-            if app.share_info.is_some() || app.last_msg.is_some() { Constraint::Length(4) } else { Constraint::Length(3) },
+            Constraint::Length(3),
+            Constraint::Length(if app.theme.layout == TableLayout::Spacious { 7 } else { 5 }),
+            Constraint::Min(10),
+            if app.session.beginner_mode {
+                Constraint::Length(7)
+            } else if app.session.share_info.is_some() || app.session.last_msg.is_some() {
+                Constraint::Length(4)
+            } else {
+                Constraint::Length(3)
+            },
             Constraint::Length(3),
         ].as_ref())
-        .split(f.size());
+        .split(f.area());
 
-    if let Some(_) = &app.game_state {
+    if app.session.game_state.is_some() {
         draw_top_info(f, app, chunks[0]);
         draw_community_cards(f, app, chunks[1]);
-        draw_players_table(f, app, chunks[2]);
+        let mut table_area = chunks[2];
+        if let Some(qr) = &app.share_qr {
+            table_area = draw_share_qr(f, qr, table_area);
+        }
+        if app.follow && !app.session.is_seated() {
+            let panel_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(60), Constraint::Length(30)].as_ref())
+                .split(table_area);
+            draw_follow_panel(f, app, panel_chunks[1]);
+            table_area = panel_chunks[0];
+        }
+        if app.show_host_panel {
+            let panel_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(60), Constraint::Length(36)].as_ref())
+                .split(table_area);
+            draw_host_dashboard(f, app, panel_chunks[1]);
+            table_area = panel_chunks[0];
+        }
+        // 还没有人发言时不占用牌桌的位置
+        if app.chat_mode || !app.session.chat.is_empty() {
+            let panel_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(60), Constraint::Length(34)].as_ref())
+                .split(table_area);
+            f.render_widget(ChatPanel { chat: &app.session.chat, my_id: app.session.my_id, active: app.chat_mode }, panel_chunks[1]);
+            table_area = panel_chunks[0];
+        }
+        if app.show_cheat_sheet {
+            let table_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(60), Constraint::Length(24)].as_ref())
+                .split(table_area);
+            draw_players_table(f, app, table_chunks[0]);
+            draw_cheat_sheet(f, app, table_chunks[1]);
+        } else {
+            draw_players_table(f, app, table_area);
+        }
         draw_actions_and_input(f, app, chunks[3], chunks[4]);
         if app.should_refresh { app.should_refresh = false; }
     } else {
         let block = Block::default().title("正在加载房间信息...").borders(Borders::ALL);
-        f.render_widget(block, f.size());
+        f.render_widget(block, f.area());
     }
 }
 
-fn draw_top_info<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
-    let gs = app.game_state.as_ref().unwrap();
+/// 绘制投屏界面: 大号公共牌和奖池，底牌在摊牌前一律遮住
+fn draw_projector_screen(f: &mut Frame, app: &mut App) {
+    let (Some(gs), Some(projector)) = (&app.session.game_state, &app.projector) else {
+        let title = app.session.last_msg.as_deref().unwrap_or("正在加载房间信息...");
+        f.render_widget(Block::default().title(title).borders(Borders::ALL), f.area());
+        return;
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([Constraint::Length(3), Constraint::Min(10)].as_ref())
+        .split(f.area());
+
+    let acting = (gs.phase != GamePhase::WaitingForPlayers && gs.phase != GamePhase::Showdown)
+        .then(|| gs.current_player_id()).flatten()
+        .and_then(|id| gs.players.get(&id))
+        .map_or(String::new(), |p| format!("  轮到: {}", p.nickname));
+    let status = if app.session.offline { "  (连接已断开)" } else { "" };
+    let info = Paragraph::new(format!("{}  盲注 {}/{}{}{}", gs.phase, gs.small_blind, gs.big_blind, acting, status))
+        .style(Style::default().add_modifier(Modifier::BOLD))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title("Poker Eden").border_type(BorderType::Rounded));
+    f.render_widget(info, chunks[0]);
+
+    match projector.view {
+        ProjectorView::Table => {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+                .split(chunks[1]);
+            let top = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+                .split(rows[0]);
+            f.render_widget(BigBoard { game_state: gs, theme: app.theme }, top[0]);
+            f.render_widget(BigNumber { title: "奖池", value: gs.pot, theme: app.theme }, top[1]);
+            let table = PlayersTable {
+                game_state: gs,
+                my_id: None,
+                hand_ranks: &app.session.hand_ranks,
+                stack_changes: &app.session.stack_changes,
+                hide_cards: gs.phase != GamePhase::Showdown,
+                theme: app.theme,
+                follow: true,
+                turn_deadline: app.session.turn_deadline,
+                effective_from: None,
+            };
+            f.render_widget(table, rows[1]);
+        }
+        ProjectorView::Standings => f.render_widget(Standings { game_state: gs, theme: app.theme }, chunks[1]),
+    }
+    app.should_refresh = false;
+}
+
+fn draw_top_info(f: &mut Frame, app: &App, area: Rect) {
+    let gs = app.session.game_state.as_ref().unwrap();
     let pot_text = format!("奖池: ${}", gs.pot);
     let phase_text = format!("阶段: {}", gs.phase);
-    let owner_nickname = &gs.players.get(&app.host_id.unwrap()).unwrap().nickname;
-    let room_text = format!("房间ID: {}  房主：{}  NLH ~ {}/{}", gs.room_id,
-                            owner_nickname, gs.small_blind, gs.big_blind);
+    let owner_nickname = &gs.players.get(&app.session.host_id.unwrap()).unwrap().nickname;
+    // 混合轮换时显示当前玩法还剩几局以及下一个玩法
+    let rotation_text = match &gs.rotation {
+        Some(progress) if progress.rotation.variants.len() > 1 => {
+            format!(" (还剩 {} 局，之后 {})", progress.hands_left, progress.next_variant())
+        }
+        _ => String::new(),
+    };
+    let room_text = format!("房间ID: {}  房主：{}  {} {}{} ~ {}/{}", gs.room_id, owner_nickname,
+                            gs.variant, gs.betting_structure, rotation_text, gs.small_blind, gs.big_blind);
     let top_block = Block::default()
         .title(Span::styled(phase_text, Style::default()))
         .borders(Borders::ALL)
@@ -627,173 +800,348 @@ fn draw_top_info<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
     f.render_widget(pot_paragraph, inner_chunks[1]);
 }
 
-fn draw_community_cards<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
-    let Some(gs) = &app.game_state else { return };
-    let text = if gs.phase == GamePhase::WaitingForPlayers {
-        Spans::from(vec![])
-    } else {
-        let cards_str: Vec<String> = gs.community_cards.iter()
-            .map(|c| c.map_or("___".to_string(), |card| {
-                if app.should_refresh { "___".to_string() } else { card.to_string() }
-            })).collect();
-        Spans::from(
-            cards_str.into_iter().map(|s| {
-                let color = if s.contains('♥') || s.contains('♦') { Color::Red } else { Color::Black };
-                Span::styled(format!(" {} ", s), Style::default().fg(color).bg(Color::White).add_modifier(Modifier::BOLD))
-            }).collect::<Vec<Span>>(),
-        )
+fn draw_community_cards(f: &mut Frame, app: &App, area: Rect) {
+    let Some(gs) = &app.session.game_state else { return };
+    f.render_widget(Board { game_state: gs, hide_cards: app.should_refresh, theme: app.theme }, area);
+}
+
+fn draw_players_table(f: &mut Frame, app: &App, area: Rect) {
+    let Some(gs) = &app.session.game_state else { return };
+    let table = PlayersTable {
+        game_state: gs,
+        my_id: app.session.my_id,
+        hand_ranks: &app.session.hand_ranks,
+        stack_changes: &app.session.stack_changes,
+        hide_cards: app.should_refresh,
+        theme: app.theme,
+        follow: app.follow && !app.session.is_seated(),
+        turn_deadline: app.session.turn_deadline,
+        effective_from: app.session.my_id.filter(|_| app.show_effective && app.session.is_seated()),
     };
-    let paragraph = Paragraph::new(text)
-        .block(Block::default().title("公共牌").borders(Borders::ALL).border_type(BorderType::Rounded))
-        .alignment(Alignment::Center).wrap(Wrap { trim: true });
-    f.render_widget(paragraph, area);
+    f.render_widget(table, area);
 }
 
-// 修改了函数签名
-fn draw_players_table<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
-    let Some(gs) = &app.game_state else { return };
-    let my_id = app.my_id;
-
-    let header_cells = ["座位", "玩家", "胜", "负", "筹码", "下注", "手牌", "牌型", "状态"]
-        .iter().map(|h| Cell::from(*h).style(Style::default().fg(Color::Yellow)));
-    let header = Row::new(header_cells).style(Style::default().bg(Color::DarkGray));
-    let dealer_id = if gs.hand_player_order.is_empty() { None } else { Some(gs.hand_player_order[0]) }; // 庄家是就座列表的第一个
-    let show_stack_change = gs.phase == GamePhase::Showdown && !app.last_stack.iter().all(|x| *x == 0);
-    let rows = gs.seated_players.iter().map(|player_id| {
-        let Some(player) = gs.players.get(player_id) else {
-            return Row::new(vec![Cell::from("Error: Player not found")]);
-        };
-        let is_me = my_id == Some(*player_id);
-        let is_dealer = dealer_id == Some(*player_id);
-        let is_thinking = gs.phase != GamePhase::Showdown && gs.current_player_id() == Some(*player_id);
-        let p_idx_opt = gs.player_indices.get(player_id);
-        let bet = p_idx_opt.map_or(0, |idx| {
-            gs.bets.get(*idx).cloned().unwrap_or(0).saturating_sub(gs.last_bet)
-        });
-        let mut player_stack_str = format!("${}", player.stack);
-        if show_stack_change && let Some(idx) = p_idx_opt {
-            let change_stack = player.stack as i32 - app.last_stack[*idx] as i32;
-            if change_stack > 0 {
-                player_stack_str.push_str(format!("(+${})", change_stack).as_str());
-            } else if change_stack < 0 {
-                player_stack_str.push_str(format!("(-${})", -change_stack).as_str());
-            }
-        }
-        let cards_tuple = p_idx_opt.map_or((None, None), |idx| gs.player_cards.get(*idx).cloned().unwrap_or((None, None)));
-        let cards_spans: Vec<Span> = match cards_tuple {
-            (Some(c1), Some(c2)) if !app.should_refresh => {
-                [c1, c2].into_iter().map(|c| {
-                    let color = if c.suit == Suit::Heart || c.suit == Suit::Diamond { Color::Red } else { Color::Black };
-                    Span::styled(format!(" {} ", c), Style::default().fg(color).bg(Color::White))
-                }).collect()
-            }
-            _ => vec![Span::styled(" ___  ___ ", Style::default().fg(Color::Black).bg(Color::White))],
-        };
+/// 在区域右侧绘制加入链接的二维码，返回剩下的区域；区域放不下二维码时不绘制
+fn draw_share_qr(f: &mut Frame, qr: &str, area: Rect) -> Rect {
+    // 四周留出浅色边距作为静区
+    let width = qr.lines().next().map_or(0, display_width) + 2 + 4;
+    let height = qr.lines().count() as u16 + 2 + 2;
+    if area.height < height || area.width < width + 60 {
+        return area;
+    }
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(60), Constraint::Length(width)].as_ref())
+        .split(area);
+    let qr_area = Rect { height, ..chunks[1] };
+    let panel = Paragraph::new(qr)
+        .style(Style::default().fg(Color::Black).bg(Color::White))
+        .block(Block::default().borders(Borders::ALL).title("扫码加入").border_type(BorderType::Rounded)
+            .padding(Padding::new(2, 2, 1, 1)));
+    f.render_widget(panel, qr_area);
+    chunks[0]
+}
 
-        let cards_rank = p_idx_opt.map_or("".to_string(), |idx| {
-            match app.hand_ranks.get(*idx).unwrap() {
-                None => "".to_string(),
-                Some(rank) => format!("{}", rank),
-            }
-        });
-        let status_str = if is_thinking { "思考中...".to_string() } else { format!("{}", player.state) };
-        let mut name = "".to_string();
-        if player.is_offline { name.push_str("!离线! "); }
-        if is_me { name.push_str("[你]"); }
-        name.push_str(player.nickname.as_str());
-        if is_dealer { name.push_str(" (D)"); }
-        let row_style = if is_thinking { Style::default().bg(Color::LightCyan).fg(Color::Black) } else if is_me { Style::default().add_modifier(Modifier::BOLD) } else { Style::default() };
-        Row::new(vec![
-            Cell::from(player.seat_id.map_or("-".to_string(), |s| s.to_string())),
-            Cell::from(name),
-            Cell::from(if player.wins > 0 { format!("{}", player.wins) } else { "".to_string() }),
-            Cell::from(if player.losses > 0 { format!("{}", player.losses) } else { "".to_string() }),
-            Cell::from(player_stack_str),
-            Cell::from(format!("${}", bet)),
-            Cell::from(Spans::from(cards_spans)),
-            Cell::from(cards_rank),
-            Cell::from(status_str),
-        ]).style(row_style)
-    });
-    let table = Table::new(rows).header(header)
-        .block(Block::default().borders(Borders::ALL).title("玩家列表").border_type(BorderType::Rounded))
-        .widths(&[
-            Constraint::Percentage(5), Constraint::Percentage(17), Constraint::Percentage(4),
-            Constraint::Percentage(4), Constraint::Percentage(16), Constraint::Percentage(10),
-            Constraint::Percentage(14), Constraint::Percentage(11), Constraint::Percentage(15),
-        ]);
-    f.render_widget(table, area);
+fn draw_follow_panel(f: &mut Frame, app: &App, area: Rect) {
+    let Some(gs) = &app.session.game_state else { return };
+    f.render_widget(FollowPanel { game_state: gs, equities: &app.session.all_in_equities }, area);
+}
+
+fn draw_host_dashboard(f: &mut Frame, app: &App, area: Rect) {
+    let Some(gs) = &app.session.game_state else { return };
+    let dashboard = HostDashboard {
+        game_state: gs,
+        pending_joins: &app.session.pending_joins,
+        pending_deposits: &app.session.pending_deposits,
+        clock: &app.session.clock,
+    };
+    f.render_widget(dashboard, area);
 }
 
-fn draw_actions_and_input<B: Backend>(f: &mut Frame<B>, app: &App, actions_area: Rect, input_area: Rect) {
-    let is_seated = app.my_id.map_or(false, |my_id| {
-        app.game_state.as_ref().map_or(false, |gs| gs.seated_players.contains(&my_id))
+/// 牌型速查面板: 从大到小列出牌型；德州扑克中轮到自己时，标出根据公共牌已经不可能凑成的牌型
+fn draw_cheat_sheet(f: &mut Frame, app: &App, area: Rect) {
+    let Some(gs) = &app.session.game_state else { return };
+    let possible = (gs.variant == GameVariant::TexasHoldem && !app.session.valid_actions.is_empty()).then(|| {
+        let board: Vec<Card> = gs.community_cards[0].iter().flatten().cloned().collect();
+        possible_hand_categories(&board, 2 + gs.variant.community_card_count() - board.len())
     });
+    let items: Vec<ListItem> = HandCategory::ALL.iter().rev().enumerate().map(|(i, category)| {
+        match &possible {
+            Some(possible) if !possible.contains(category) => ListItem::new(format!("{:>2}. {} (不可能)", i + 1, category))
+                .style(Style::default().fg(Color::DarkGray)),
+            _ => ListItem::new(format!("{:>2}. {}", i + 1, category)),
+        }
+    }).collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("牌型大小 (F1)").border_type(BorderType::Rounded));
+    f.render_widget(list, area);
+}
 
-    let is_lose_game = app.game_state.as_ref().map_or(false, |gs| {
-        gs.players.get(&app.my_id.unwrap()).map_or(false, |p| p.is_offline)
+fn draw_actions_and_input(f: &mut Frame, app: &App, actions_area: Rect, input_area: Rect) {
+    let is_seated = app.session.is_seated();
+
+    let is_lose_game = app.session.game_state.as_ref().is_some_and(|gs| {
+        gs.players.get(&app.session.my_id.unwrap()).is_some_and(|p| p.is_offline)
     });
 
-    let game_phase = app.game_state.as_ref().map(|gs| gs.phase);
+    let game_phase = app.session.game_state.as_ref().map(|gs| gs.phase);
     let is_waiting_phase = game_phase == Some(GamePhase::WaitingForPlayers);
     let is_showdown_phase = game_phase == Some(GamePhase::Showdown);
 
-    // 修改了UI提示逻辑
-    let mut info_text = if !app.valid_actions.is_empty() && !is_showdown_phase {
+    // 分享信息本身有多行，按行拆开
+    let share_lines = |info: &str| -> Vec<Line> { info.lines().map(|l| Line::from(l.to_string())).collect() };
+    // 修改了UI提示逻辑，每条提示占一行
+    let mut lines = if !app.session.valid_actions.is_empty() && !is_showdown_phase {
         // Case 1: 轮到你行动
-        let parts: Vec<String> = app.valid_actions.iter().map(|a| match a {
-            PlayerActionType::Fold => "[f]弃牌(Fold)".to_string(),
+        let parts: Vec<String> = app.session.valid_actions.iter().map(|a| match a {
+            PlayerActionType::Fold => app.fold_key.hint().to_string(),
             PlayerActionType::Check => "[c]过牌(Check)".to_string(),
             PlayerActionType::Call(amount) => format!("[c]跟注(Call) ${}", amount),
-            PlayerActionType::Bet(min_amount) => format!("[b]下注(Bet) ${}+", min_amount),
-            PlayerActionType::Raise(min_amount) => format!("[r]加注(Raise) ${}+", min_amount),
+            PlayerActionType::Bet { min, max } => format!("[b]下注(Bet) ${}-{}", min, max),
+            PlayerActionType::Raise { min, max } => format!("[r]加注(Raise) ${}-{}", min, max),
+            PlayerActionType::Discard(max) => format!("[d <序号...>]换牌(Draw) 最多{}张，[d]不换", max),
         }).collect();
-        format!("轮到你! {}", parts.join(", "))
-    } else if app.my_id == app.host_id && (is_waiting_phase || is_showdown_phase) {
-        // Case 2: 你是房主，并且在等待阶段
-        let share_info_str = app.share_info.as_deref().unwrap_or("");
-        if is_seated {
-            format!("{}\n你是房主。等待玩家加入... 输入 `start` 开始游戏。", share_info_str)
+        let time_bank = app.session.game_state.as_ref()
+            .and_then(|gs| gs.players.get(&app.session.my_id.unwrap()))
+            .map_or(0, |p| p.time_bank);
+        if app.session.turn_deadline.is_some_and(|(id, _)| Some(id) == app.session.my_id) && !app.session.time_bank_active && time_bank > 0 {
+            vec![Line::from(format!("轮到你! {}, [timebank]时间银行 {}s", parts.join(", "), time_bank))]
         } else {
-            format!("{}\n你是房主。请先 `seat <座位号> <筹码>` 坐下才能开始游戏。", share_info_str)
+            vec![Line::from(format!("轮到你! {}", parts.join(", ")))]
         }
-    } else if let Some(share_info) = &app.share_info {
+    } else if app.session.my_id == app.session.host_id && (is_waiting_phase || is_showdown_phase) {
+        // Case 2: 你是房主，并且在等待阶段
+        let mut lines = share_lines(app.session.share_info.as_deref().unwrap_or(""));
+        lines.push(Line::from(if is_seated {
+            "你是房主。等待玩家加入... 输入 `start` 开始游戏，按 F2 打开房主面板。"
+        } else {
+            "你是房主。请先 `seat <座位号> <筹码>` 坐下才能开始游戏。"
+        }));
+        lines
+    } else if let Some(share_info) = &app.session.share_info {
         // Case 3: 你是普通玩家，在等待阶段
-        share_info.clone()
+        share_lines(share_info)
+    } else if !is_seated && !is_lose_game && app.session.my_player().is_some_and(|p| p.seat_id.is_some() && p.stack > 0) {
+        // 暂时离席或断线回来的玩家，座位仍然保留
+        vec![Line::from("你已暂时离席。输入 `sitin` 回到座位并等到大盲位入局，`deadblind` 补交错过的盲注立即入局。")]
     } else if !is_seated || is_lose_game {
         // Case 4: 你是旁观者
-        "您正在观战。输入 `seat <座位号> <筹码>` 来坐下。".to_string()
+        let chip_accounts = app.session.game_state.as_ref().is_some_and(|gs| gs.room_options.chip_accounts);
+        if chip_accounts {
+            let bank = app.session.game_state.as_ref()
+                .and_then(|gs| app.session.my_id.and_then(|id| gs.players.get(&id)))
+                .map_or(0, |p| p.bank + p.stack);
+            vec![Line::from(format!("您正在观战。账户余额 ${}，输入 `deposit <筹码>` 申请存入，`seat <座位号> <筹码>` 来坐下。", bank))]
+        } else {
+            let range = app.session.game_state.as_ref().and_then(|gs| gs.config.buy_in_range())
+                .map_or(String::new(), |range| format!(" ({})", range));
+            vec![Line::from(format!("您正在观战。输入 `seat <座位号> <筹码>`{} 来坐下，`follow on` 跟随行动。", range))]
+        }
     } else if is_showdown_phase {
-        "本局游戏结束，等待房主开始下一局游戏🎮".to_string()
+        vec![Line::from("本局游戏结束，等待房主开始下一局游戏🎮 输入 `sitout` 暂时离席，`leave` 可以离座观战，`cashout` 兑现离场。")]
+    } else if !is_waiting_phase && app.session.game_state.as_ref().is_some_and(|gs| {
+        app.session.my_id.is_some_and(|id| !gs.hand_player_order.contains(&id))
+    }) {
+        // Case 5: 牌局中途入座
+        vec![Line::from("你已入座，将从下一局开始自动入局。输入 `leave` 可以离座观战，`cashout` 兑现离场。")]
     } else {
-        // Case 6: 默认等待信息
-        "等待其他玩家行动...".to_string()
+        // Case 6: 默认等待信息，轮到自己之前先看到目前需要跟注多少 (自己下的盲注和抓位已经算在内)
+        let to_call = app.session.game_state.as_ref()
+            .zip(app.session.my_id)
+            .map_or(0, |(gs, id)| gs.to_call(&id));
+        if to_call > 0 {
+            vec![Line::from(format!("等待其他玩家行动... 目前需跟注 ${}", to_call))]
+        } else {
+            vec![Line::from("等待其他玩家行动...")]
+        }
     };
 
-    if let Some(err) = &app.last_msg {
-        info_text = format!("消息：{}\n{}", err.as_str(), info_text);
+    let must_post_dead_blind = app.session.game_state.as_ref().is_some_and(|gs| {
+        app.session.my_id.and_then(|id| gs.players.get(&id)).is_some_and(|p| p.waits_for_big_blind())
+    });
+    if is_seated && must_post_dead_blind {
+        lines.push(Line::from("你错过了盲注。输入 `deadblind` 补交死盲立即入局，否则需等到大盲位。"));
     }
 
-    let p_style = if app.last_msg.is_some() { Style::default().fg(Color::Red) } else { Style::default().fg(Color::White) };
-    let actions_paragraph = Paragraph::new(info_text.trim_start_matches("\n"))
-        .style(p_style)
-        .block(Block::default().borders(Borders::ALL).title("可用动作 / 信息").border_type(BorderType::Rounded))
-        .alignment(Alignment::Center);
-    f.render_widget(actions_paragraph, actions_area);
+    let straddle = app.session.game_state.as_ref().and_then(|gs| {
+        let me = app.session.my_id.and_then(|id| gs.players.get(&id))?;
+        gs.room_options.straddle.map(|position| (position, me.post_straddle))
+    });
+    match straddle {
+        Some((position, false)) if is_seated => {
+            lines.push(Line::from(format!("房主开启了{}抓位。下一局坐在{}时输入 `straddle` 抓位 (两倍大盲)。", position, position)));
+        }
+        Some((_, true)) if is_seated => {
+            lines.push(Line::from("已申请在下一局抓位。"));
+        }
+        _ => {}
+    }
 
-    let input = Paragraph::new(app.input.as_ref())
-        .style(Style::default().fg(Color::Yellow))
-        .block(Block::default().borders(Borders::ALL).title("输入").border_type(BorderType::Rounded));
+    let vote_hint = app.session.game_state.as_ref().and_then(|gs| {
+        let vote = gs.vote.as_ref()?;
+        let id = app.session.my_id?;
+        let pending = vote.electorate.contains(&id) && !vote.yes.contains(&id) && !vote.no.contains(&id);
+        pending.then(|| format!("投票进行中: {}。输入 `vote yes` 或 `vote no` 投票", session::describe_proposal(gs, &vote.proposal)))
+    });
+    if let Some(hint) = vote_hint {
+        lines.push(Line::from(hint));
+    }
+
+    let can_guess = app.session.game_state.as_ref().is_some_and(|gs| {
+        gs.room_options.spectator_guessing && gs.hand_in_progress()
+            && app.session.my_id.is_some_and(|id| !gs.player_indices.contains_key(&id) && !gs.spectator_guesses.guesses.contains_key(&id))
+    });
+    if can_guess {
+        lines.push(Line::from("观众猜赢家: 输入 `guess <昵称>` 猜本局的赢家"));
+    }
+
+    let auto_fold_below = app.session.game_state.as_ref()
+        .filter(|gs| !gs.room_options.auto_fold_disabled)
+        .and_then(|gs| app.session.my_id.and_then(|id| gs.players.get(&id)))
+        .map_or(0, |p| p.auto_fold_below);
+    if is_seated && auto_fold_below > 0 {
+        lines.push(Line::from(format!("自动弃牌已开启: 翻牌前有人加注时弃掉强度低于 {}% 的起手牌 (`autofold off` 关闭)", auto_fold_below)));
+    }
+
+    let choose_hint = app.session.game_state.as_ref().and_then(|gs| {
+        let is_button = app.session.my_id.is_some() && gs.hand_player_order.first() == app.session.my_id.as_ref();
+        if gs.room_options.dealers_choice.is_empty() || !is_button || gs.chosen_variant.is_some() {
+            return None;
+        }
+        let list: Vec<String> = gs.room_options.dealers_choice.iter().map(|v| v.to_string()).collect();
+        Some(format!("你是庄家，输入 `choose <holdem|o8|draw>` 选择下一局的玩法 (可选: {})", list.join("、")))
+    });
+    if let Some(hint) = choose_hint {
+        lines.push(Line::from(hint));
+    }
+
+    let can_reveal_folded = is_showdown_phase && app.session.game_state.as_ref().is_some_and(|gs| {
+        gs.room_options.reveal_folded_hands
+            && app.session.my_id.and_then(|id| gs.players.get(&id)).is_some_and(|p| p.state == PlayerState::Folded)
+    });
+    if can_reveal_folded {
+        lines.push(Line::from("输入 `reveal` 亮出你弃掉的底牌。"));
+    }
+
+    // 输掉摊牌的玩家按亮牌顺序依次选择
+    if app.session.my_showdown_turn() {
+        lines.push(Line::from("你的牌被压过了，输入 `show` 亮牌或 `muck` 盖牌。"));
+    }
+
+    // 其他人都弃牌后，赢家可以选择亮出底牌
+    if app.session.can_show_cards() {
+        lines.push(Line::from("其他人都弃牌了，输入 `show` 亮出底牌，`show 1` 只亮第一张。"));
+    }
+
+    // 河牌之前结束的牌局可以查看兔子牌
+    let can_rabbit_hunt = is_showdown_phase && (is_seated || app.session.is_host())
+        && app.session.game_state.as_ref().is_some_and(|gs| {
+            gs.community_cards.first().is_some_and(|board| board.iter().any(|c| c.is_none()))
+        });
+    if can_rabbit_hunt {
+        lines.push(Line::from("输入 `rabbit` 查看没有发出的公共牌 (兔子牌)。"));
+    }
+
+    if app.session.beginner_mode {
+        let explanations: Vec<String> = if is_showdown_phase {
+            app.session.showdown_explanations.clone()
+        } else {
+            app.session.game_state.as_ref().map_or(vec![], |gs| {
+                gs.explain_turn(&app.session.valid_actions).iter().map(|e| e.to_string()).collect()
+            })
+        };
+        for line in explanations {
+            lines.push(Line::from(format!("· {}", line)));
+        }
+    }
+
+    if let Some(stats) = app.session.turn_stats() {
+        lines.push(Line::from(stats));
+    }
+
+    if let Some((_, action)) = &app.session.pending_confirm {
+        lines.push(Line::from(format!("确认动作: {}？输入 `y` 确认，或重新输入动作。", action_label(action))));
+    }
+
+    if let Some(label) = app.session.outbox.sending() {
+        let queued = app.session.outbox.queued();
+        let queued_text = if queued > 0 { format!(" (另有 {} 条指令排队)", queued) } else { String::new() };
+        lines.push(Line::from(format!("发送中: {}…{}", label, queued_text)));
+    }
+
+    if let Some(err) = &app.session.last_msg {
+        lines.insert(0, Line::from(format!("消息：{}", err.as_str())));
+    }
+
+    f.render_widget(ActionBar { lines, highlight: app.session.last_msg.is_some() }, actions_area);
+
+    // 离线后输入框换成提示横幅，不再接受输入
+    if app.session.offline {
+        let banner = Paragraph::new("已与服务器断开连接。按 R 重新连接，按 Esc 退出")
+            .style(Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD))
+            .block(Block::default().borders(Borders::ALL).title("离线").border_type(BorderType::Rounded))
+            .alignment(Alignment::Center);
+        f.render_widget(banner, input_area);
+        return;
+    }
+
+    let title = if app.chat_mode { "聊天 (Enter 发送，F3 返回)" } else { "输入" };
+    let input = Paragraph::new(app.input.as_str())
+        .style(Style::default().fg(if app.chat_mode { Color::Cyan } else { Color::Yellow }))
+        .block(Block::default().borders(Borders::ALL).title(title).border_type(BorderType::Rounded));
     f.render_widget(input, input_area);
-    f.set_cursor(input_area.x + app.input.len() as u16 + 1, input_area.y + 1);
+    f.set_cursor_position((input_area.x + display_width(&app.input) + 1, input_area.y + 1));
 }
 
-fn draw_log<B: Backend>(f: &mut Frame<B>, app: &mut App) {
-    let log_items: Vec<ListItem> = app.log_messages.iter().rev()
+fn draw_log(f: &mut Frame, app: &mut App) {
+    let log_items: Vec<ListItem> = app.session.log.entries().iter().rev()
         .map(|msg| ListItem::new(Text::from(msg.as_str()))).collect();
     let log_list = List::new(log_items)
         .block(Block::default().borders(Borders::ALL).title("日志 (按 Tab 关闭)").border_type(BorderType::Rounded))
         .style(Style::default().fg(Color::White));
-    f.render_widget(log_list, f.size());
+    f.render_widget(log_list, f.area());
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    fn guest_app() -> App {
+        let mut app = App::default();
+        app.session.games = GameBook::default();
+        app
+    }
+
+    #[tokio::test]
+    async fn test_run_app_draws_to_any_backend() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let (key_tx, mut key_rx) = mpsc::unbounded_channel();
+        for c in "hello".chars() {
+            key_tx.send(KeyCode::Char(c)).unwrap();
+        }
+        key_tx.send(KeyCode::Backspace).unwrap();
+        // 按键通道关闭 (例如远程会话断开) 时界面循环结束
+        drop(key_tx);
+        run_app(&mut terminal, &mut key_rx, guest_app()).await.unwrap();
+        let screen = screen(&terminal);
+        assert!(screen.contains("hell") && !screen.contains("hello"));
+    }
+
+    #[tokio::test]
+    async fn test_run_app_exits_on_esc() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let (key_tx, mut key_rx) = mpsc::unbounded_channel();
+        key_tx.send(KeyCode::Esc).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), run_app(&mut terminal, &mut key_rx, guest_app()))
+            .await
+            .expect("按 Esc 后界面循环应当结束")
+            .unwrap();
+        drop(key_tx);
+    }
+}
@@ -0,0 +1,150 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 投屏模式
+//!
+//! 用 `--display <房间ID> [服务器地址:端口]` 启动客户端时，以观众身份加入房间，
+//! 只显示牌桌，不接受任何游戏输入，适合在线下牌局中投到电视或投影上，玩家各自用手机操作。
+//! 牌局进行中固定显示牌桌；两局之间在牌桌和筹码排行之间自动轮换。
+
+use poker_eden_core::{GamePhase, GameState, RoomId};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// 投屏模式加入房间时使用的昵称
+pub const DISPLAY_NICKNAME: &str = "显示屏";
+
+/// 未指定服务器地址时连接的默认地址
+const DEFAULT_SERVER_ADDR: &str = "127.0.0.1:25917";
+
+/// 投屏模式当前显示的画面
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectorView {
+    /// 公共牌、奖池和玩家列表
+    Table,
+    /// 按筹码排序的排行榜
+    Standings,
+}
+
+/// 投屏模式的状态
+pub struct Projector {
+    pub room_id: RoomId,
+    pub server_addr: String,
+    pub view: ProjectorView,
+    switched_at: Instant,
+    /// 两局之间每个画面停留的时间
+    interval: Duration,
+}
+
+impl Projector {
+    const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+
+    /// 从命令行参数解析投屏模式，没有 `--display` 时返回 `Ok(None)`
+    ///
+    /// 画面轮换间隔从环境变量 `POKER_EDEN_DISPLAY_INTERVAL` (秒) 读取，默认 10 秒。
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, String> {
+        let Some(pos) = args.iter().position(|a| a == "--display") else { return Ok(None) };
+        let room_id = args.get(pos + 1)
+            .and_then(|id| Uuid::from_str(id).ok())
+            .ok_or("用法: --display <房间ID> [服务器地址:端口]")?;
+        let server_addr = match args.get(pos + 2) {
+            Some(addr) if addr.contains(':') => addr.clone(),
+            Some(addr) => return Err(format!("无效的服务器地址: {}", addr)),
+            None => DEFAULT_SERVER_ADDR.to_string(),
+        };
+        let interval = std::env::var("POKER_EDEN_DISPLAY_INTERVAL").ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|secs| *secs > 0)
+            .map_or(Self::DEFAULT_INTERVAL, Duration::from_secs);
+        Ok(Some(Self::new(room_id, server_addr, interval, Instant::now())))
+    }
+
+    fn new(room_id: RoomId, server_addr: String, interval: Duration, now: Instant) -> Self {
+        Self { room_id, server_addr, view: ProjectorView::Table, switched_at: now, interval }
+    }
+
+    /// 根据牌局阶段和时间切换画面，返回画面是否发生了变化
+    pub fn advance(&mut self, game_state: Option<&GameState>, now: Instant) -> bool {
+        let in_hand = game_state.is_some_and(|gs| !matches!(gs.phase, GamePhase::WaitingForPlayers | GamePhase::Showdown));
+        let next = if in_hand {
+            ProjectorView::Table
+        } else if now.duration_since(self.switched_at) >= self.interval {
+            match self.view {
+                ProjectorView::Table => ProjectorView::Standings,
+                ProjectorView::Standings => ProjectorView::Table,
+            }
+        } else {
+            self.view
+        };
+        // 牌局进行中一直重置计时，摊牌后牌桌至少停留一个间隔
+        if in_hand || next != self.view {
+            self.switched_at = now;
+        }
+        let changed = next != self.view;
+        self.view = next;
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_display_args() {
+        assert!(Projector::from_args(&args("client")).unwrap().is_none());
+        assert!(Projector::from_args(&args("client --display")).is_err());
+        assert!(Projector::from_args(&args("client --display not-a-uuid")).is_err());
+
+        let id = Uuid::new_v4();
+        let p = Projector::from_args(&args(&format!("client --display {}", id))).unwrap().unwrap();
+        assert_eq!((p.room_id, p.server_addr.as_str()), (id, DEFAULT_SERVER_ADDR));
+        let p = Projector::from_args(&args(&format!("client --display {} 10.0.0.2:25917", id))).unwrap().unwrap();
+        assert_eq!(p.server_addr, "10.0.0.2:25917");
+        assert!(Projector::from_args(&args(&format!("client --display {} localhost", id))).is_err());
+    }
+
+    #[test]
+    fn test_views_rotate_only_between_hands() {
+        let start = Instant::now();
+        let interval = Duration::from_secs(10);
+        let mut p = Projector::new(Uuid::new_v4(), DEFAULT_SERVER_ADDR.to_string(), interval, start);
+        let mut gs = GameState::default();
+
+        // 等待玩家时按间隔轮换
+        assert!(!p.advance(Some(&gs), start + Duration::from_secs(5)));
+        assert!(p.advance(Some(&gs), start + interval));
+        assert_eq!(p.view, ProjectorView::Standings);
+
+        // 开局后立即回到牌桌，并且牌局进行中不再轮换
+        gs.phase = GamePhase::PreFlop;
+        assert!(p.advance(Some(&gs), start + Duration::from_secs(11)));
+        assert_eq!(p.view, ProjectorView::Table);
+        assert!(!p.advance(Some(&gs), start + Duration::from_secs(60)));
+
+        // 摊牌后牌桌先停留一个间隔再切换到排行榜
+        gs.phase = GamePhase::Showdown;
+        assert!(!p.advance(Some(&gs), start + Duration::from_secs(65)));
+        assert!(p.advance(Some(&gs), start + Duration::from_secs(70)));
+        assert_eq!(p.view, ProjectorView::Standings);
+    }
+}
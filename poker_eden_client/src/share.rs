@@ -0,0 +1,48 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 房间分享: 在终端中显示加入链接的二维码
+//!
+//! 加入链接由 `poker_eden_client_core::share` 生成，手机扫码后得到的就是这个链接。
+
+use qrcode::{render::unicode::Dense1x2, EcLevel, QrCode};
+
+/// 把文字编码为二维码，用上下半块字符绘制，每个字符表示上下两个模块
+///
+/// 不带静区，显示时需要在四周留出浅色的边距。深色模块用前景色绘制，应使用浅色背景显示。
+pub fn qr_code(text: &str) -> Option<String> {
+    let code = QrCode::with_error_correction_level(text, EcLevel::L).ok()?;
+    Some(code.render::<Dense1x2>().quiet_zone(false).build())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poker_eden_client_core::display::display_width;
+    use poker_eden_client_core::share::join_url;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_qr_code_is_rectangular() {
+        let qr = qr_code(&join_url("192.168.1.10:25917", Uuid::new_v4())).unwrap();
+        let lines: Vec<&str> = qr.lines().collect();
+        let width = display_width(lines[0]);
+        assert!(lines.iter().all(|line| display_width(line) == width));
+        // 每行表示两排模块
+        assert_eq!(lines.len(), (width as usize).div_ceil(2));
+    }
+}
@@ -0,0 +1,133 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 牌桌外观: 花色、牌背、桌布颜色和牌桌布局
+//!
+//! 通过环境变量配置，未设置或无法识别时使用默认值:
+//! - `POKER_EDEN_SUITS`: 花色显示方式 `emoji` / `symbol` / `ascii`
+//! - `POKER_EDEN_CARD_BACK`: 牌背样式 `plain` / `hatch` / `wave`
+//! - `POKER_EDEN_FELT`: 桌布颜色 `none` / `green` / `blue` / `red` / `purple` / `gray`
+//! - `POKER_EDEN_LAYOUT`: 牌桌布局 `compact` / `spacious`，投屏到电视上时宽松布局更易读
+
+use poker_eden_client_core::display::SuitStyle;
+use ratatui::style::{Color, Style};
+
+/// 未翻开的牌的样式。只使用 ASCII 字符，保证在所有终端里都和牌面等宽
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CardBack {
+    #[default]
+    Plain,
+    Hatch,
+    Wave,
+}
+
+impl CardBack {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "plain" => Some(CardBack::Plain),
+            "hatch" => Some(CardBack::Hatch),
+            "wave" => Some(CardBack::Wave),
+            _ => None,
+        }
+    }
+
+    /// 牌背使用的字符
+    pub fn fill(self) -> char {
+        match self {
+            CardBack::Plain => '_',
+            CardBack::Hatch => '#',
+            CardBack::Wave => '~',
+        }
+    }
+}
+
+/// 牌桌布局
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TableLayout {
+    /// 紧凑布局，适合小终端
+    #[default]
+    Compact,
+    /// 宽松布局: 牌之间、玩家之间留出更多空白
+    Spacious,
+}
+
+impl TableLayout {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "compact" => Some(TableLayout::Compact),
+            "spacious" => Some(TableLayout::Spacious),
+            _ => None,
+        }
+    }
+}
+
+/// 牌桌外观
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Theme {
+    pub suits: SuitStyle,
+    pub card_back: CardBack,
+    /// 桌布颜色，None 表示使用终端的背景色
+    pub felt: Option<Color>,
+    pub layout: TableLayout,
+}
+
+impl Theme {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok();
+        Self {
+            suits: SuitStyle::from_env(),
+            card_back: var("POKER_EDEN_CARD_BACK").and_then(|v| CardBack::parse(&v)).unwrap_or_default(),
+            felt: var("POKER_EDEN_FELT").and_then(|v| parse_felt(&v)).flatten(),
+            layout: var("POKER_EDEN_LAYOUT").and_then(|v| TableLayout::parse(&v)).unwrap_or_default(),
+        }
+    }
+
+    /// 一张翻开的牌，带两侧的留白
+    pub fn card(&self, card: &poker_eden_core::Card) -> String {
+        self.pad(&self.suits.card(card))
+    }
+
+    /// 一张未翻开的牌，和翻开的牌等宽
+    pub fn card_back(&self) -> String {
+        self.pad(&self.card_back.fill().to_string().repeat(self.suits.card_width()))
+    }
+
+    fn pad(&self, text: &str) -> String {
+        match self.layout {
+            TableLayout::Compact => format!(" {} ", text),
+            TableLayout::Spacious => format!("  {}  ", text),
+        }
+    }
+
+    /// 牌桌区域的背景
+    pub fn felt_style(&self) -> Style {
+        self.felt.map_or(Style::default(), |color| Style::default().bg(color))
+    }
+}
+
+/// 解析桌布颜色，`none` 解析为 `Some(None)`
+fn parse_felt(name: &str) -> Option<Option<Color>> {
+    match name.to_lowercase().as_str() {
+        "none" => Some(None),
+        "green" => Some(Some(Color::Green)),
+        "blue" => Some(Some(Color::Blue)),
+        "red" => Some(Some(Color::Red)),
+        "purple" => Some(Some(Color::Magenta)),
+        "gray" | "grey" => Some(Some(Color::DarkGray)),
+        _ => None,
+    }
+}
@@ -0,0 +1,921 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 游戏界面中的独立组件。
+//! 每个组件只依赖渲染所需的数据，不依赖整个 App，方便用 `TestBackend` 单独测试。
+
+use crate::theme::{TableLayout, Theme};
+use chrono::{DateTime, Utc};
+use poker_eden_client_core::chat::{wrap_to_width, ChatLog};
+use poker_eden_client_core::clock::ServerClock;
+use poker_eden_core::*;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, BorderType, Borders, Cell, Paragraph, Row, Table, TableState, Widget, Wrap},
+};
+
+fn card_color(card: &Card) -> Color {
+    if card.suit == Suit::Heart || card.suit == Suit::Diamond { Color::Red } else { Color::Black }
+}
+
+/// 一排翻开的牌，每张牌白底、按花色着色
+pub fn card_spans(cards: &[Card], theme: Theme) -> Vec<Span<'static>> {
+    cards.iter().map(|c| {
+        Span::styled(theme.card(c), Style::default().fg(card_color(c)).bg(Color::White).add_modifier(Modifier::BOLD))
+    }).collect()
+}
+
+/// 公共牌区域，每块公共牌占一行
+pub struct Board<'a> {
+    pub game_state: &'a GameState,
+    /// 刷新界面时先用牌背遮住，避免残影
+    pub hide_cards: bool,
+    pub theme: Theme,
+}
+
+impl Widget for Board<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let gs = self.game_state;
+        let card_back = self.theme.card_back();
+        let text = if gs.phase == GamePhase::WaitingForPlayers {
+            Text::default()
+        } else {
+            let mut lines = vec![];
+            for (i, board) in gs.community_cards.iter().enumerate() {
+                // 宽松布局下多块公共牌之间空一行
+                if i > 0 && self.theme.layout == TableLayout::Spacious {
+                    lines.push(Line::default());
+                }
+                lines.push(Line::from(board.iter().map(|card| match card {
+                    Some(card) if !self.hide_cards => Span::styled(
+                        self.theme.card(card),
+                        Style::default().fg(card_color(card)).bg(Color::White).add_modifier(Modifier::BOLD),
+                    ),
+                    _ => Span::styled(card_back.clone(), Style::default().fg(Color::Black).bg(Color::White).add_modifier(Modifier::BOLD)),
+                }).collect::<Vec<Span>>()));
+            }
+            Text::from(lines)
+        };
+        Paragraph::new(text)
+            .block(Block::default().title("公共牌").borders(Borders::ALL).border_type(BorderType::Rounded).style(self.theme.felt_style()))
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true })
+            .render(area, buf);
+    }
+}
+
+/// 玩家列表
+pub struct PlayersTable<'a> {
+    pub game_state: &'a GameState,
+    pub my_id: Option<PlayerId>,
+    /// 按本局玩家顺序排列的牌型
+    pub hand_ranks: &'a [Option<HandRank>],
+    /// 本局结束时各参与者的筹码变化，用于在摊牌时显示输赢
+    pub stack_changes: &'a [StackChange],
+    pub hide_cards: bool,
+    pub theme: Theme,
+    /// 跟随行动: 玩家较多、列表放不下时滚动到正在行动的玩家，并用箭头标出
+    pub follow: bool,
+    /// 开启行动计时时，正在行动的玩家的截止时间
+    pub turn_deadline: Option<(PlayerId, DateTime<Utc>)>,
+    /// 显示有效筹码列时，计算有效筹码所用的自己
+    pub effective_from: Option<PlayerId>,
+}
+
+/// 本局列: 本局投入奖池的筹码，进入第二轮下注后附上每一轮的下注 (如 `$165 (60/100)`)
+fn invested_cell(gs: &GameState, idx: usize) -> String {
+    let invested = gs.hand_invested(idx);
+    let history = gs.street_history(idx);
+    match invested {
+        0 => String::new(),
+        _ if history.len() > 1 => {
+            let rounds = history.iter().map(|b| b.to_string()).collect::<Vec<_>>().join("/");
+            format!("${} ({})", invested, rounds)
+        }
+        _ => format!("${}", invested),
+    }
+}
+
+/// 有效筹码列: 自己和弃牌的玩家留空
+fn effective_cell(gs: &GameState, me: Option<PlayerId>, player: &Player) -> String {
+    match me {
+        Some(me) if me != player.id && player.state != PlayerState::Folded => {
+            gs.effective_stack_bb(&me, &player.id).map_or(String::new(), |bb| format!("{:.1}BB", bb))
+        }
+        _ => String::new(),
+    }
+}
+
+/// 下注列: 前注和死盲显示为灰色，盲注和抓位显示为青色并标出类型，主动下注使用正常的颜色
+fn bet_line(bet: &StreetBet) -> Line<'static> {
+    let mut spans = vec![];
+    if bet.dead > 0 {
+        spans.push(Span::styled(format!("前${} ", bet.dead), Style::default().fg(Color::DarkGray)));
+    }
+    if bet.forced > 0 {
+        let label = if bet.straddle { "抓" } else { "盲" };
+        spans.push(Span::styled(format!("{}${}", label, bet.forced), Style::default().fg(Color::Cyan)));
+    }
+    if bet.voluntary > 0 {
+        let sep = if bet.forced > 0 { "+" } else { "" };
+        spans.push(Span::raw(format!("{}${}", sep, bet.voluntary)));
+    }
+    if spans.is_empty() {
+        spans.push(Span::raw("$0"));
+    }
+    Line::from(spans)
+}
+
+impl Widget for PlayersTable<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let gs = self.game_state;
+        let show_effective = self.effective_from.is_some();
+        let mut headers = vec!["座位", "玩家", "胜", "负", "筹码", "下注", "本局", "手牌", "牌型", "状态"];
+        if show_effective {
+            headers.insert(5, "有效");
+        }
+        let header_cells = headers.into_iter().map(|h| Cell::from(h).style(Style::default().fg(Color::Yellow)));
+        // 宽松布局下每行玩家之间空一行
+        let row_margin = if self.theme.layout == TableLayout::Spacious { 1 } else { 0 };
+        let header = Row::new(header_cells).style(Style::default().bg(Color::DarkGray)).bottom_margin(row_margin);
+        let dealer_id = gs.dealer_id(); // 死庄时没有人标记为庄家
+        let show_stack_change = gs.phase == GamePhase::Showdown;
+        let rows = gs.seated_players.iter().map(|player_id| {
+            let Some(player) = gs.players.get(player_id) else {
+                return Row::new(vec![Cell::from("Error: Player not found")]);
+            };
+            let is_me = self.my_id == Some(*player_id);
+            let is_dealer = dealer_id == Some(*player_id);
+            let is_thinking = gs.phase != GamePhase::Showdown && gs.current_player_id() == Some(*player_id);
+            let p_idx_opt = gs.player_indices.get(player_id);
+            let bet = p_idx_opt.map_or_else(StreetBet::default, |idx| gs.street_bet(*idx));
+            let mut player_stack_str = format!("${}", player.stack);
+            if show_stack_change && let Some(change) = self.stack_changes.iter().find(|c| c.player_id == *player_id) {
+                let change_stack = change.net();
+                if change_stack > 0 {
+                    player_stack_str.push_str(&format!("(+${})", change_stack));
+                } else if change_stack < 0 {
+                    player_stack_str.push_str(&format!("(-${})", -change_stack));
+                }
+            }
+            let hole_cards = p_idx_opt.and_then(|idx| gs.player_cards.get(*idx)).cloned().unwrap_or_default();
+            let cards_spans: Vec<Span> = if !hole_cards.is_empty() && hole_cards.iter().all(|c| c.is_some()) && !self.hide_cards {
+                hole_cards.into_iter().flatten().map(|c| {
+                    Span::styled(self.theme.card(&c), Style::default().fg(card_color(&c)).bg(Color::White))
+                }).collect()
+            } else {
+                let hidden = self.theme.card_back().repeat(gs.variant.hole_card_count());
+                vec![Span::styled(hidden, Style::default().fg(Color::Black).bg(Color::White))]
+            };
+
+            let cards_rank = p_idx_opt
+                .and_then(|idx| self.hand_ranks.get(*idx).cloned().flatten())
+                .map_or(String::new(), |rank| rank.to_string());
+            let status_str = match self.turn_deadline {
+                Some((id, deadline)) if is_thinking && id == *player_id => {
+                    format!("思考中 {}s", (deadline - Utc::now()).num_seconds().max(0))
+                }
+                _ if is_thinking => "思考中...".to_string(),
+                _ => format!("{}", player.state),
+            };
+            let mut prefix = String::new();
+            if player.is_offline { prefix.push_str("!离线! "); }
+            if is_me { prefix.push_str("[你]"); }
+            if let Some(avatar) = &player.profile.avatar { prefix.push_str(avatar); prefix.push(' '); }
+            // 玩家选择的颜色只用于昵称，正在行动的一行保持高亮的颜色
+            let nickname_style = match player.profile.color {
+                Some([r, g, b]) if !is_thinking => Style::default().fg(Color::Rgb(r, g, b)),
+                _ => Style::default(),
+            };
+            let name = Line::from(vec![
+                Span::raw(prefix),
+                Span::styled(player.nickname.as_str(), nickname_style),
+                Span::raw(if is_dealer { " (D)" } else { "" }),
+            ]);
+            let row_style = if is_thinking {
+                Style::default().bg(Color::LightCyan).fg(Color::Black)
+            } else if is_me {
+                Style::default().add_modifier(Modifier::BOLD)
+            } else {
+                Style::default()
+            };
+            let mut cells = vec![
+                Cell::from(player.seat_id.map_or("-".to_string(), |s| s.to_string())),
+                Cell::from(name),
+                Cell::from(if player.wins > 0 { format!("{}", player.wins) } else { String::new() }),
+                Cell::from(if player.losses > 0 { format!("{}", player.losses) } else { String::new() }),
+                Cell::from(player_stack_str),
+                Cell::from(bet_line(&bet)),
+                Cell::from(p_idx_opt.map_or(String::new(), |idx| invested_cell(gs, *idx))),
+                Cell::from(Line::from(cards_spans)),
+                Cell::from(cards_rank),
+                Cell::from(status_str),
+            ];
+            if show_effective {
+                cells.insert(5, Cell::from(effective_cell(gs, self.effective_from, player)));
+            }
+            Row::new(cells).style(row_style).bottom_margin(row_margin)
+        });
+        let mut widths = vec![
+            Constraint::Percentage(5), Constraint::Percentage(16), Constraint::Percentage(4),
+            Constraint::Percentage(4), Constraint::Percentage(13), Constraint::Percentage(10),
+            Constraint::Percentage(11), Constraint::Percentage(12), Constraint::Percentage(10),
+            Constraint::Percentage(11),
+        ];
+        if show_effective {
+            // 有效筹码列从筹码列和状态列各借一点宽度
+            widths[4] = Constraint::Percentage(11);
+            widths[9] = Constraint::Percentage(8);
+            widths.insert(5, Constraint::Percentage(8));
+        }
+        let table = Table::new(rows, widths).header(header)
+            .block(Block::default().borders(Borders::ALL).title("玩家列表").border_type(BorderType::Rounded).style(self.theme.felt_style()));
+        let acting_row = gs.current_player_id().filter(|_| gs.phase != GamePhase::Showdown)
+            .and_then(|id| gs.seated_players.iter().position(|p| *p == id));
+        match acting_row {
+            Some(row) if self.follow => {
+                let mut state = TableState::default().with_selected(row);
+                ratatui::widgets::StatefulWidget::render(table.highlight_symbol("▶ "), area, buf, &mut state);
+            }
+            _ => Widget::render(table, area, buf),
+        }
+    }
+}
+
+/// 动作栏: 显示可用动作、提示和服务器消息
+pub struct ActionBar<'a> {
+    /// 每条提示占一行
+    pub lines: Vec<Line<'a>>,
+    /// 有错误或提示消息时用红色显示
+    pub highlight: bool,
+}
+
+impl Widget for ActionBar<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let style = if self.highlight { Style::default().fg(Color::Red) } else { Style::default().fg(Color::White) };
+        Paragraph::new(self.lines)
+            .style(style)
+            .block(Block::default().borders(Borders::ALL).title("可用动作 / 信息").border_type(BorderType::Rounded))
+            .alignment(Alignment::Center)
+            .render(area, buf);
+    }
+}
+
+/// 跟随行动面板: 观战时显示正在行动的玩家面对的跟注额和底池赔率，以及全下摊牌时各玩家的胜率
+pub struct FollowPanel<'a> {
+    pub game_state: &'a GameState,
+    /// 全下摊牌时计算的胜率
+    pub equities: &'a [(PlayerId, f64)],
+}
+
+impl Widget for FollowPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let gs = self.game_state;
+        let title = |text: &str| Line::from(Span::styled(text.to_string(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        let mut lines = vec![];
+
+        let acting = gs.current_player_id()
+            .filter(|_| gs.hand_in_progress())
+            .and_then(|id| gs.players.get(&id));
+        match acting {
+            Some(player) => {
+                let to_call = gs.to_call(&player.id);
+                lines.push(title(&format!("轮到 {}", player.nickname)));
+                lines.push(Line::from(format!(" 奖池 ${}  筹码 ${}", gs.pot, player.stack)));
+                if to_call > 0 {
+                    // 跟注后的总奖池中，跟注额所占的比例就是跟注所需的最低胜率
+                    let needed = to_call as f64 / (gs.pot + to_call) as f64 * 100.0;
+                    lines.push(Line::from(format!(" 需跟注 ${}", to_call)));
+                    lines.push(Line::from(format!(" 底池赔率 {:.1}:1", gs.pot as f64 / to_call as f64)));
+                    lines.push(Line::from(format!(" 跟注需要 {:.1}% 胜率", needed)));
+                } else {
+                    lines.push(Line::from(" 可以过牌"));
+                }
+            }
+            None => lines.push(title("等待行动")),
+        }
+
+        if !self.equities.is_empty() {
+            lines.push(Line::default());
+            lines.push(title("全下胜率"));
+            for (player_id, equity) in self.equities {
+                let Some(p) = gs.players.get(player_id) else { continue };
+                lines.push(Line::from(format!(" {} {:.1}%", p.nickname, equity * 100.0)));
+            }
+        }
+
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("跟随行动").border_type(BorderType::Rounded))
+            .render(area, buf);
+    }
+}
+
+/// 发言者的颜色，同一个玩家始终是同一种颜色
+const SENDER_COLORS: [Color; 6] = [Color::Cyan, Color::Green, Color::Magenta, Color::Yellow, Color::LightBlue, Color::LightRed];
+
+fn sender_color(player_id: &PlayerId) -> Color {
+    SENDER_COLORS[(player_id.as_u128() % SENDER_COLORS.len() as u128) as usize]
+}
+
+/// 聊天面板: 最新的消息在底部，发言者的昵称按玩家着色
+pub struct ChatPanel<'a> {
+    pub chat: &'a ChatLog,
+    pub my_id: Option<PlayerId>,
+    /// 是否处于聊天输入模式
+    pub active: bool,
+}
+
+impl Widget for ChatPanel<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let width = area.width.saturating_sub(2) as usize;
+        let height = area.height.saturating_sub(2) as usize;
+        // 从最新的消息往回折行，直到填满面板
+        let mut lines: Vec<Line> = vec![];
+        for entry in self.chat.visible().rev() {
+            let name_style = Style::default().fg(sender_color(&entry.player_id)).add_modifier(Modifier::BOLD);
+            let name = if Some(entry.player_id) == self.my_id { format!("{}(我)", entry.nickname) } else { entry.nickname.clone() };
+            let header = Line::from(vec![
+                Span::styled(entry.at.format("%H:%M ").to_string(), Style::default().fg(Color::DarkGray)),
+                Span::styled(name, name_style),
+            ]);
+            let body = wrap_to_width(&entry.text, width.saturating_sub(1)).into_iter().map(|l| Line::from(format!(" {}", l)));
+            lines.splice(0..0, std::iter::once(header).chain(body));
+            if lines.len() >= height {
+                break;
+            }
+        }
+        let skip = lines.len().saturating_sub(height);
+        let lines: Vec<Line> = lines.into_iter().skip(skip).collect();
+
+        let title = match self.chat.scroll() {
+            0 => "聊天 (F3)".to_string(),
+            n => format!("聊天 (F3) ↑{}", n),
+        };
+        let border = if self.active { Style::default().fg(Color::Yellow) } else { Style::default() };
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title(title).border_style(border).border_type(BorderType::Rounded))
+            .render(area, buf);
+    }
+}
+
+/// 房主面板: 汇总待处理的申请、盲注级别和房间设置，并列出对应的指令
+pub struct HostDashboard<'a> {
+    pub game_state: &'a GameState,
+    /// 等待批准的加入申请
+    pub pending_joins: &'a [(PlayerId, String)],
+    /// 等待批准的存入申请
+    pub pending_deposits: &'a [(PlayerId, u32)],
+    /// 服务器时钟，用于换算级别的结束时间
+    pub clock: &'a ServerClock,
+}
+
+impl Widget for HostDashboard<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let gs = self.game_state;
+        let options = &gs.room_options;
+        let title = |text: &str| Line::from(Span::styled(text.to_string(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+        let hint = |text: String| Line::from(Span::styled(text, Style::default().fg(Color::DarkGray)));
+        let on_off = |on: bool| if on { "开" } else { "关" };
+        let mut lines = vec![];
+
+        lines.push(title(&format!("加入申请 ({})", self.pending_joins.len())));
+        for (_, nickname) in self.pending_joins {
+            lines.push(Line::from(format!(" {}", nickname)));
+            lines.push(hint(format!("   admit/deny {}", nickname)));
+        }
+        if options.chip_accounts {
+            lines.push(title(&format!("存入申请 ({})", self.pending_deposits.len())));
+            for (player_id, amount) in self.pending_deposits {
+                let Some(p) = gs.players.get(player_id) else { continue };
+                lines.push(Line::from(format!(" {} ${}", p.nickname, amount)));
+                lines.push(hint(format!("   approve/reject {}", p.nickname)));
+            }
+        }
+
+        lines.push(title("盲注"));
+        lines.push(Line::from(if options.ante > 0 {
+            let payer = if options.big_blind_ante { " (大盲交)" } else { "" };
+            format!(" 当前 {}/{}  前注 {}{}", gs.small_blind, gs.big_blind, options.ante, payer)
+        } else {
+            format!(" 当前 {}/{}", gs.small_blind, gs.big_blind)
+        }));
+        if let Some(position) = options.straddle {
+            lines.push(Line::from(format!(" 抓位 {}", position)));
+        }
+        match &options.tournament {
+            Some(t) => {
+                if t.level_secs > 0 {
+                    let remaining = match gs.level_ends_at {
+                        Some(ends_at) => {
+                            let secs = self.clock.remaining(ends_at, Utc::now()).num_seconds();
+                            format!("剩余 {}:{:02}", secs / 60, secs % 60)
+                        }
+                        None if gs.tournament_level + 1 >= t.levels.len() => "最后一级".to_string(),
+                        None => "第一局开始计时".to_string(),
+                    };
+                    lines.push(Line::from(format!(" 第 {} 级，{}", gs.tournament_level + 1, remaining)));
+                } else {
+                    lines.push(Line::from(format!(" 第 {} 级，本级已进行 {}/{} 局", gs.tournament_level + 1, gs.level_hands, t.hands_per_level)));
+                }
+                if let Some(next) = t.levels.get(gs.tournament_level + 1) {
+                    lines.push(Line::from(format!(" 下一级 {}/{}", next.small_blind, next.big_blind)));
+                }
+            }
+            None => lines.push(hint(" option tournament <结构>".to_string())),
+        }
+
+        lines.push(title("Sit & Go"));
+        match &options.sit_and_go {
+            Some(sng) => {
+                let payouts: Vec<String> = sng.payouts.iter().map(|p| p.to_string()).collect();
+                lines.push(Line::from(format!(" 买入 ${}  起始筹码 {}", sng.buy_in, sng.starting_stack)));
+                lines.push(Line::from(format!(" 奖金 {}%", payouts.join("/"))));
+                let progress = &gs.sit_and_go;
+                let entrants = progress.entrants.len();
+                lines.push(Line::from(if progress.finished {
+                    " 已结束，重新入座开始下一场".to_string()
+                } else if progress.is_running() {
+                    format!(" 剩余 {}/{} 人，奖池 ${}", entrants - progress.eliminated.len(), entrants, sng.prize_pool(entrants).unwrap_or(u32::MAX))
+                } else {
+                    " 报名中: 入座即报名".to_string()
+                }));
+            }
+            None => lines.push(hint(" option sng <买入>:<筹码>:<奖金%>".to_string())),
+        }
+
+        let spectators = gs.players.values()
+            .filter(|p| !p.is_offline && !gs.seated_players.contains(&p.id))
+            .count();
+        let spectator_limit = if options.max_spectators > 0 { options.max_spectators.to_string() } else { "不限".to_string() };
+        lines.push(title("房间设置"));
+        lines.push(Line::from(format!(" 加入审批 {}  观战 {}/{}", on_off(options.require_join_approval), spectators, spectator_limit)));
+        lines.push(Line::from(format!(" 确认动作 {}  撤回窗口 {}", on_off(options.confirm_actions),
+            if options.undo_grace_ms > 0 { format!("{}ms", options.undo_grace_ms) } else { "关".to_string() })));
+        lines.push(Line::from(format!(" 筹码账户 {}  场地费 ${}", on_off(options.chip_accounts), options.session_fee)));
+        lines.push(Line::from(format!(" 自动弃牌 {}  行动计时 {}", if options.auto_fold_disabled { "禁止" } else { "允许" },
+            if options.turn_timer_secs > 0 { format!("{}s", options.turn_timer_secs) } else { "关".to_string() })));
+        if options.turn_timer_secs > 0 {
+            lines.push(Line::from(format!(" 时间银行 {}", if options.time_bank_secs > 0 { format!("{}s", options.time_bank_secs) } else { "关".to_string() })));
+        }
+        lines.push(Line::from(format!(" 通信对局 {}  全下保险 {}", on_off(options.correspondence), on_off(options.insurance))));
+        if options.seven_deuce_bounty > 0 {
+            lines.push(Line::from(format!(" 七二奖励 ${}", options.seven_deuce_bounty)));
+        }
+        if options.rathole_window_mins > 0 {
+            lines.push(Line::from(format!(" 防抽水离场 {} 分钟", options.rathole_window_mins)));
+        }
+        lines.push(Line::from(format!(" 民主模式 {}  观众猜赢家 {}", on_off(options.democracy), on_off(options.spectator_guessing))));
+        if let Some(duplicate) = &gs.duplicate {
+            lines.push(Line::from(format!(" 复式对局第 {} 桌  已用 {} 副牌", duplicate.table + 1, duplicate.hands)));
+        }
+        lines.push(hint(" option <设置> <值>".to_string()));
+
+        lines.push(title("常用指令"));
+        let between_hands = matches!(gs.phase, GamePhase::WaitingForPlayers | GamePhase::Showdown);
+        lines.push(Line::from(if between_hands { " start 开始下一局" } else { " 牌局进行中" }));
+        lines.push(Line::from(" shuffle 重新分配座位"));
+        lines.push(Line::from(" bombpot 下一局打一次炸弹底池"));
+        lines.push(Line::from(" summary 本次游戏汇总"));
+        lines.push(Line::from(" kick <昵称> 把玩家移出房间"));
+        lines.push(Line::from(" transfer <昵称> 把房主交给该玩家"));
+        lines.push(Line::from(" duplicate new|join <编号>|off 复式对局"));
+        lines.push(Line::from(" endsession 结束本次游戏并关闭房间"));
+        lines.push(Line::from(" export [zh|en] 导出时间线和牌局历史"));
+        lines.push(Line::from(" audit 房主操作记录"));
+        lines.push(Line::from(" discord <地址>|off Discord 通知"));
+
+        Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("房主面板 (F2)").border_type(BorderType::Rounded))
+            .render(area, buf);
+    }
+}
+
+/// 投屏模式下的大号公共牌，每张牌画成一个带边框的方块，每块公共牌占一行
+pub struct BigBoard<'a> {
+    pub game_state: &'a GameState,
+    pub theme: Theme,
+}
+
+impl BigBoard<'_> {
+    const CARD_HEIGHT: u16 = 5;
+}
+
+impl Widget for BigBoard<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let gs = self.game_state;
+        let block = Block::default().title("公共牌").borders(Borders::ALL).border_type(BorderType::Rounded).style(self.theme.felt_style());
+        let inner = block.inner(area);
+        block.render(area, buf);
+        if gs.phase == GamePhase::WaitingForPlayers {
+            return;
+        }
+
+        // 牌面两侧各留两格，再加上左右边框
+        let card_width = self.theme.suits.card_width() as u16 + 6;
+        let total_height = gs.community_cards.len() as u16 * Self::CARD_HEIGHT;
+        let mut y = inner.y + inner.height.saturating_sub(total_height) / 2;
+        for board in gs.community_cards.iter() {
+            if y + Self::CARD_HEIGHT > inner.bottom() {
+                break;
+            }
+            let row_width = (board.len() as u16 * (card_width + 1)).saturating_sub(1);
+            let mut x = inner.x + inner.width.saturating_sub(row_width) / 2;
+            for card in board {
+                if x + card_width > inner.right() {
+                    break;
+                }
+                let (lines, style) = match card {
+                    Some(card) => (
+                        vec![Line::default(), Line::from(self.theme.suits.card(card))],
+                        Style::default().fg(card_color(card)).bg(Color::White).add_modifier(Modifier::BOLD),
+                    ),
+                    None => (
+                        vec![Line::from(self.theme.card_back.fill().to_string().repeat(card_width as usize - 2)); 3],
+                        Style::default().fg(Color::Black).bg(Color::White),
+                    ),
+                };
+                Paragraph::new(lines)
+                    .block(Block::default().borders(Borders::ALL).border_type(BorderType::Rounded))
+                    .style(style)
+                    .alignment(Alignment::Center)
+                    .render(Rect::new(x, y, card_width, Self::CARD_HEIGHT), buf);
+                x += card_width + 1;
+            }
+            y += Self::CARD_HEIGHT;
+        }
+    }
+}
+
+/// 用方块字符拼出的 0-9，每个数字 3 格宽、5 行高
+const BIG_DIGITS: [[&str; 5]; 10] = [
+    ["███", "█ █", "█ █", "█ █", "███"],
+    [" █ ", "██ ", " █ ", " █ ", "███"],
+    ["███", "  █", "███", "█  ", "███"],
+    ["███", "  █", "███", "  █", "███"],
+    ["█ █", "█ █", "███", "  █", "  █"],
+    ["███", "█  ", "███", "  █", "███"],
+    ["███", "█  ", "███", "█ █", "███"],
+    ["███", "  █", "  █", "  █", "  █"],
+    ["███", "█ █", "███", "█ █", "███"],
+    ["███", "█ █", "███", "  █", "███"],
+];
+
+/// 投屏模式下的大号数字 (如奖池)，区域放不下时退回普通文字
+pub struct BigNumber<'a> {
+    pub title: &'a str,
+    pub value: u32,
+    pub theme: Theme,
+}
+
+impl Widget for BigNumber<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default().title(self.title).borders(Borders::ALL).border_type(BorderType::Rounded).style(self.theme.felt_style());
+        let inner = block.inner(area);
+        let digits: Vec<usize> = self.value.to_string().bytes().map(|b| (b - b'0') as usize).collect();
+        let big_width = (digits.len() * 4 - 1) as u16;
+        let lines: Vec<Line> = if inner.width >= big_width && inner.height >= 5 {
+            let top = (inner.height - 5) / 2;
+            let mut lines = vec![Line::default(); top as usize];
+            lines.extend((0..5).map(|row| {
+                Line::from(digits.iter().map(|d| BIG_DIGITS[*d][row]).collect::<Vec<_>>().join(" "))
+            }));
+            lines
+        } else {
+            let top = inner.height.saturating_sub(1) / 2;
+            let mut lines = vec![Line::default(); top as usize];
+            lines.push(Line::from(format!("${}", self.value)));
+            lines
+        };
+        Paragraph::new(lines)
+            .block(block)
+            .style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .alignment(Alignment::Center)
+            .render(area, buf);
+    }
+}
+
+/// 投屏模式下的筹码排行榜，按筹码从多到少列出已入座的玩家
+pub struct Standings<'a> {
+    pub game_state: &'a GameState,
+    pub theme: Theme,
+}
+
+impl Widget for Standings<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let gs = self.game_state;
+        let mut players: Vec<&Player> = gs.seated_players.iter().filter_map(|id| gs.players.get(id)).collect();
+        players.sort_by_key(|p| std::cmp::Reverse(p.stack));
+        let header = Row::new(["名次", "玩家", "筹码", "胜", "负"].map(|h| Cell::from(h).style(Style::default().fg(Color::Yellow))))
+            .style(Style::default().bg(Color::DarkGray))
+            .bottom_margin(1);
+        let rows = players.iter().enumerate().map(|(i, p)| {
+            Row::new(vec![
+                Cell::from(format!("{}", i + 1)),
+                Cell::from(p.nickname.clone()),
+                Cell::from(format!("${}", p.stack)),
+                Cell::from(format!("{}", p.wins)),
+                Cell::from(format!("{}", p.losses)),
+            ]).bottom_margin(1)
+        });
+        let widths = [
+            Constraint::Percentage(10), Constraint::Percentage(40), Constraint::Percentage(30),
+            Constraint::Percentage(10), Constraint::Percentage(10),
+        ];
+        Table::new(rows, widths).header(header)
+            .block(Block::default().borders(Borders::ALL).title("筹码排行").border_type(BorderType::Rounded).style(self.theme.felt_style()))
+            .style(Style::default().add_modifier(Modifier::BOLD))
+            .render(area, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poker_eden_client_core::display::SuitStyle;
+    use crate::theme::CardBack;
+    use ratatui::{backend::TestBackend, Terminal};
+    use uuid::Uuid;
+
+    fn render(widget: impl Widget, width: u16, height: u16) -> Terminal<TestBackend> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|f| f.render_widget(widget, f.area())).unwrap();
+        terminal
+    }
+
+    /// 只比较文字内容的快照，忽略颜色等样式
+    fn assert_text_snapshot<'a>(terminal: &Terminal<TestBackend>, expected: impl IntoIterator<Item = &'a str>) {
+        let mut actual = terminal.backend().buffer().clone();
+        actual.set_style(actual.area, Style::reset());
+        assert_eq!(actual, Buffer::with_lines(expected));
+    }
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card { rank, suit }
+    }
+
+    /// 两位玩家、已发翻牌的牌局
+    fn flop_state() -> (GameState, Vec<PlayerId>) {
+        let mut gs = GameState::default();
+        let ids: Vec<PlayerId> = (0..2).map(|_| Uuid::new_v4()).collect();
+        for (i, (id, name)) in ids.iter().zip(["Alice", "Bob"]).enumerate() {
+            let mut p = Player::new(*id, name.to_string());
+            p.stack = 990;
+            p.seat_id = Some(i as u8);
+            p.state = PlayerState::Playing;
+            gs.players.insert(*id, p);
+            gs.seated_players.push_back(*id);
+            gs.hand_player_order.push(*id);
+            gs.player_indices.insert(*id, i);
+        }
+        gs.phase = GamePhase::Flop;
+        gs.bets = vec![10, 10];
+        gs.last_bet = 10;
+        gs.cur_player_idx = 1;
+        gs.community_cards = vec![vec![
+            Some(card(Rank::Ace, Suit::Spade)), Some(card(Rank::King, Suit::Heart)), Some(card(Rank::Two, Suit::Club)), None, None,
+        ]];
+        gs.player_cards = vec![vec![Some(card(Rank::Ace, Suit::Diamond)), Some(card(Rank::Ace, Suit::Club))], vec![None, None]];
+        (gs, ids)
+    }
+
+    #[test]
+    fn test_board_snapshot() {
+        let (gs, _) = flop_state();
+        let terminal = render(Board { game_state: &gs, hide_cards: false, theme: Theme::default() }, 31, 3);
+        assert_text_snapshot(&terminal, [
+            "╭公共牌───────────────────────╮",
+            "│  ♠️A  ♥️K  ♣️2  ___  ___    │",
+            "╰─────────────────────────────╯",
+        ]);
+
+        // 刷新时所有公共牌都被遮住
+        let terminal = render(Board { game_state: &gs, hide_cards: true, theme: Theme::default() }, 31, 3);
+        assert_text_snapshot(&terminal, [
+            "╭公共牌───────────────────────╮",
+            "│  ___  ___  ___  ___  ___    │",
+            "╰─────────────────────────────╯",
+        ]);
+
+        // ASCII 花色和牌背等宽，公共牌保持对齐
+        let terminal = render(Board { game_state: &gs, hide_cards: false, theme: Theme { suits: SuitStyle::Ascii, ..Theme::default() } }, 26, 3);
+        assert_text_snapshot(&terminal, [
+            "╭公共牌──────────────────╮",
+            "│   As  Kh  2c  __  __   │",
+            "╰────────────────────────╯",
+        ]);
+    }
+
+    #[test]
+    fn test_board_theme() {
+        let (gs, _) = flop_state();
+        let theme = Theme { suits: SuitStyle::Ascii, card_back: CardBack::Hatch, felt: Some(Color::Green), layout: TableLayout::Spacious };
+        let terminal = render(Board { game_state: &gs, hide_cards: false, theme }, 34, 3);
+        assert_text_snapshot(&terminal, [
+            "╭公共牌──────────────────────────╮",
+            "│  As    Kh    2c    ##    ##    │",
+            "╰────────────────────────────────╯",
+        ]);
+        // 桌布颜色填满牌桌区域，牌面本身保持白底
+        assert_eq!(terminal.backend().buffer()[(1, 1)].bg, Color::Green);
+        assert_eq!(terminal.backend().buffer()[(3, 1)].bg, Color::White);
+    }
+
+    #[test]
+    fn test_big_board_snapshot() {
+        let (gs, _) = flop_state();
+        let theme = Theme { suits: SuitStyle::Ascii, ..Theme::default() };
+        let terminal = render(BigBoard { game_state: &gs, theme }, 46, 7);
+        assert_text_snapshot(&terminal, [
+            "╭公共牌──────────────────────────────────────╮",
+            "│╭──────╮ ╭──────╮ ╭──────╮ ╭──────╮ ╭──────╮│",
+            "││      │ │      │ │      │ │______│ │______││",
+            "││  As  │ │  Kh  │ │  2c  │ │______│ │______││",
+            "││      │ │      │ │      │ │______│ │______││",
+            "│╰──────╯ ╰──────╯ ╰──────╯ ╰──────╯ ╰──────╯│",
+            "╰────────────────────────────────────────────╯",
+        ]);
+    }
+
+    #[test]
+    fn test_big_number_snapshot() {
+        let terminal = render(BigNumber { title: "奖池", value: 120, theme: Theme::default() }, 15, 7);
+        assert_text_snapshot(&terminal, [
+            "╭奖池─────────╮",
+            "│  █  ███ ███ │",
+            "│ ██    █ █ █ │",
+            "│  █  ███ █ █ │",
+            "│  █  █   █ █ │",
+            "│ ███ ███ ███ │",
+            "╰─────────────╯",
+        ]);
+        // 放不下大号数字时显示普通文字
+        let terminal = render(BigNumber { title: "奖池", value: 120, theme: Theme::default() }, 10, 3);
+        assert_text_snapshot(&terminal, [
+            "╭奖池────╮",
+            "│  $120  │",
+            "╰────────╯",
+        ]);
+    }
+
+    #[test]
+    fn test_standings_sorted_by_stack() {
+        let (mut gs, ids) = flop_state();
+        gs.players.get_mut(&ids[1]).unwrap().stack = 1500;
+        let terminal = render(Standings { game_state: &gs, theme: Theme::default() }, 40, 7);
+        let line = |y: u16| (0..40).map(|x| terminal.backend().buffer()[(x, y)].symbol()).collect::<String>().replace(' ', "");
+        assert!(line(3).starts_with("│1Bob$1500"));
+        assert!(line(5).starts_with("│2Alice$990"));
+    }
+
+    #[test]
+    fn test_host_dashboard_lists_pending_requests() {
+        let (mut gs, ids) = flop_state();
+        gs.room_options.chip_accounts = true;
+        gs.room_options.tournament = Some(TournamentStructure {
+            hands_per_level: 10,
+            level_secs: 0,
+            levels: vec![BlindLevel { small_blind: 10, big_blind: 20 }, BlindLevel { small_blind: 25, big_blind: 50 }],
+        });
+        gs.level_hands = 3;
+        let pending_joins = vec![(Uuid::new_v4(), "Carol".to_string())];
+        let pending_deposits = vec![(ids[1], 500)];
+        let dashboard = HostDashboard { game_state: &gs, pending_joins: &pending_joins, pending_deposits: &pending_deposits, clock: &ServerClock::default() };
+        let terminal = render(dashboard, 40, 30);
+        // 去掉空格后再比较，宽字符后面被占用的格子也是空格
+        let text: String = (0..30).map(|y| {
+            (0..40).map(|x| terminal.backend().buffer()[(x, y)].symbol()).collect::<String>().replace(' ', "") + "\n"
+        }).collect();
+        for expected in ["加入申请(1)", "admit/denyCarol", "存入申请(1)", "Bob$500", "approve/rejectBob",
+                         "本级已进行3/10局", "下一级25/50", "牌局进行中"] {
+            assert!(text.contains(expected), "缺少 {}:\n{}", expected, text);
+        }
+    }
+
+    #[test]
+    fn test_follow_panel_shows_pot_odds_and_equities() {
+        let (mut gs, ids) = flop_state();
+        // Bob 面对 $40 的下注，奖池 $120
+        gs.bets = vec![50, 10];
+        gs.max_bet = 50;
+        gs.pot = 120;
+        let equities = vec![(ids[0], 0.812), (ids[1], 0.188)];
+        let terminal = render(FollowPanel { game_state: &gs, equities: &equities }, 30, 12);
+        let text: String = (0..12).map(|y| {
+            (0..30).map(|x| terminal.backend().buffer()[(x, y)].symbol()).collect::<String>().replace(' ', "") + "\n"
+        }).collect();
+        for expected in ["轮到Bob", "需跟注$40", "底池赔率3.0:1", "跟注需要25.0%胜率", "Alice81.2%", "Bob18.8%"] {
+            assert!(text.contains(expected), "缺少 {}:\n{}", expected, text);
+        }
+    }
+
+    #[test]
+    fn test_invested_cell_shows_each_round() {
+        let (mut gs, _) = flop_state();
+        assert_eq!(invested_cell(&gs, 0), "$10");
+        gs.street_levels = vec![10];
+        gs.bets[0] = 40;
+        gs.dead_bets = vec![5, 0];
+        assert_eq!(invested_cell(&gs, 0), "$45 (10/30)");
+        assert_eq!(invested_cell(&gs, 1), "$10 (10/0)");
+    }
+
+    #[test]
+    fn test_effective_cell_uses_smaller_stack() {
+        let (mut gs, ids) = flop_state();
+        gs.big_blind = 20;
+        gs.players.get_mut(&ids[1]).unwrap().stack = 300;
+        let (me, other) = (gs.players[&ids[0]].clone(), gs.players[&ids[1]].clone());
+        assert_eq!(effective_cell(&gs, Some(me.id), &other), "15.0BB");
+        assert_eq!(effective_cell(&gs, Some(other.id), &me), "15.0BB");
+        assert_eq!(effective_cell(&gs, Some(me.id), &me), "");
+        gs.players.get_mut(&ids[1]).unwrap().state = PlayerState::Folded;
+        assert_eq!(effective_cell(&gs, Some(me.id), &gs.players[&ids[1]]), "");
+    }
+
+    #[test]
+    fn test_bet_line_marks_forced_bets() {
+        let line = bet_line(&StreetBet { dead: 5, forced: 40, straddle: true, voluntary: 80 });
+        let text: String = line.spans.iter().map(|s| s.content.as_ref()).collect();
+        assert_eq!(text, "前$5 抓$40+$80");
+        assert_eq!(line.spans[1].style.fg, Some(Color::Cyan));
+        assert_eq!(bet_line(&StreetBet::default()).to_string(), "$0");
+    }
+
+    #[test]
+    fn test_action_bar_snapshot() {
+        let terminal = render(ActionBar { lines: vec![Line::from("轮到你! [c]过牌(Check)")], highlight: false }, 30, 3);
+        assert_text_snapshot(&terminal, [
+            "╭可用动作 / 信息─────────────╮",
+            "│   轮到你! [c]过牌(Check)   │",
+            "╰────────────────────────────╯",
+        ]);
+        let terminal = render(ActionBar { lines: vec![Line::from("消息：错误")], highlight: true }, 30, 3);
+        assert_eq!(terminal.backend().buffer()[(12, 1)].fg, Color::Red);
+    }
+
+    #[test]
+    fn test_players_table_snapshot() {
+        let (gs, ids) = flop_state();
+        let hand_ranks = vec![None, None];
+        let table = PlayersTable {
+            game_state: &gs,
+            my_id: Some(ids[0]),
+            hand_ranks: &hand_ranks,
+            stack_changes: &[],
+            hide_cards: false,
+            theme: Theme::default(),
+            follow: false,
+            turn_deadline: None,
+            effective_from: None,
+        };
+        let terminal = render(table, 120, 5);
+        // 去掉空格后再比较，宽字符后面被占用的格子也是空格
+        let lines: Vec<String> = (0..5).map(|y| {
+            (0..120).map(|x| terminal.backend().buffer()[(x, y)].symbol()).collect::<String>().replace(' ', "")
+        }).collect();
+        // 自己的底牌可见，对手的底牌遮住；庄家和正在行动的玩家有标记
+        assert!(lines[1].contains("座位") && lines[1].contains("筹码"));
+        assert!(lines[2].contains("[你]Alice(D)") && lines[2].contains(&format!("{}", card(Rank::Ace, Suit::Diamond))));
+        assert!(lines[3].contains("Bob") && lines[3].contains("______") && lines[3].contains("思考中"));
+        assert!(lines[2].contains("$990") && lines[2].contains("$0"));
+        // 正在行动的玩家所在行高亮
+        assert_eq!(terminal.backend().buffer()[(2, 3)].bg, Color::LightCyan);
+    }
+
+    #[test]
+    fn test_chat_panel_shows_latest_messages() {
+        let mut chat = ChatLog::default();
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let at = Utc::now();
+        chat.push(alice, "Alice".to_string(), "hello".to_string(), at);
+        chat.push(bob, "Bob".to_string(), "nice hand everyone".to_string(), at);
+        let time = at.with_timezone(&chrono::Local).format("%H:%M").to_string();
+        let terminal = render(ChatPanel { chat: &chat, my_id: Some(bob), active: false }, 16, 5);
+        // 面板只有三行，最早的一条只剩正文
+        assert_text_snapshot(&terminal, [
+            "╭聊天 (F3)─────╮".to_string(),
+            format!("│{} Bob(我) │", time),
+            "│ nice hand eve│".to_string(),
+            "│ ryone        │".to_string(),
+            "╰──────────────╯".to_string(),
+        ].iter().map(String::as_str));
+        assert_eq!(sender_color(&alice), sender_color(&alice));
+    }
+}
@@ -0,0 +1,131 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 房间聊天
+//!
+//! 聊天消息单独保存，不和调试日志混在一起。普通输入模式下可以用 `/say <内容>` 发言，
+//! 界面可以往回翻看历史消息。
+
+use chrono::{DateTime, Local, Utc};
+use poker_eden_core::PlayerId;
+use std::collections::VecDeque;
+use unicode_width::UnicodeWidthChar;
+
+/// 最多保留的聊天消息条数
+const CHAT_CAPACITY: usize = 200;
+
+pub struct ChatEntry {
+    pub at: DateTime<Local>,
+    pub player_id: PlayerId,
+    pub nickname: String,
+    pub text: String,
+}
+
+#[derive(Default)]
+pub struct ChatLog {
+    entries: VecDeque<ChatEntry>,
+    /// 从最新一条往回翻了几条，0 表示停在最新的消息
+    scroll: usize,
+}
+
+impl ChatLog {
+    pub fn push(&mut self, player_id: PlayerId, nickname: String, text: String, at: DateTime<Utc>) {
+        if self.entries.len() >= CHAT_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(ChatEntry { at: at.with_timezone(&Local), player_id, nickname, text });
+        // 翻看历史时新消息不打断阅读
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + 1).min(self.entries.len() - 1);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 要显示的消息: 去掉往回翻过的部分，最新的在最后
+    pub fn visible(&self) -> impl DoubleEndedIterator<Item = &ChatEntry> {
+        self.entries.iter().take(self.entries.len() - self.scroll)
+    }
+
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn scroll_up(&mut self, n: usize) {
+        self.scroll = (self.scroll + n).min(self.entries.len().saturating_sub(1));
+    }
+
+    pub fn scroll_down(&mut self, n: usize) {
+        self.scroll = self.scroll.saturating_sub(n);
+    }
+}
+
+/// 解析普通输入模式下的发言指令 `/say <内容>`
+pub fn parse_say(input: &str) -> Option<&str> {
+    let text = input.trim_start().strip_prefix("/say ")?.trim();
+    (!text.is_empty()).then_some(text)
+}
+
+/// 按显示宽度把文字折成若干行，每行不超过 `width` 个格子
+pub fn wrap_to_width(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let mut line_width = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if line_width + w > width.max(1) && line_width > 0 {
+            lines.push(String::new());
+            line_width = 0;
+        }
+        lines.last_mut().unwrap().push(c);
+        line_width += w;
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_scrollback_stays_put_on_new_messages() {
+        let mut chat = ChatLog::default();
+        let id = Uuid::new_v4();
+        for i in 0..5 {
+            chat.push(id, "Alice".to_string(), format!("msg {}", i), Utc::now());
+        }
+        chat.scroll_up(2);
+        assert_eq!(chat.visible().last().unwrap().text, "msg 2");
+        chat.push(id, "Alice".to_string(), "msg 5".to_string(), Utc::now());
+        assert_eq!(chat.visible().last().unwrap().text, "msg 2");
+        chat.scroll_up(100);
+        assert_eq!(chat.visible().count(), 1);
+        chat.scroll_down(100);
+        assert_eq!(chat.visible().last().unwrap().text, "msg 5");
+    }
+
+    #[test]
+    fn test_parse_say_and_wrap() {
+        assert_eq!(parse_say("/say  nice hand "), Some("nice hand"));
+        assert_eq!(parse_say("/say   "), None);
+        assert_eq!(parse_say("say hi"), None);
+        assert_eq!(wrap_to_width("好牌好牌好", 4), vec!["好牌", "好牌", "好"]);
+        assert_eq!(wrap_to_width("abc", 10), vec!["abc"]);
+    }
+}
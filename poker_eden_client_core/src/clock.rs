@@ -0,0 +1,110 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+//! 服务器时钟
+//!
+//! 计时类的消息 (行动计时、盲注级别、投票) 带的是服务器时钟的截止时间。客户端和服务器的时钟可能差好几秒，
+//! 直接和本地时间比较会显示错误的倒计时。连接后客户端发送几次 `ClockSync`，按往返时间最短的一次估计两边时钟之差；
+//! 握手完成之前，先用计时消息中的服务器时间粗略估计 (不扣除网络延迟)。
+
+use chrono::{DateTime, Duration, Utc};
+
+/// 连接后进行时钟同步的次数
+pub const CLOCK_SYNC_SAMPLES: u32 = 3;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ServerClock {
+    /// 服务器时钟减去本地时钟
+    offset: Duration,
+    /// 目前采用的时钟同步的往返时间，还没有完成过同步时为 None
+    rtt: Option<Duration>,
+    /// 本次连接已经完成的同步次数
+    samples: u32,
+}
+
+impl ServerClock {
+    /// 记录一次时钟同步: `sent_at` 和 `received_at` 是本地的发送和接收时间，`server_time` 是服务器回复的时间。
+    /// 假设去程和回程的延迟相同，只采用往返时间最短的一次
+    pub fn sync(&mut self, sent_at: DateTime<Utc>, server_time: DateTime<Utc>, received_at: DateTime<Utc>) {
+        self.samples += 1;
+        let rtt = (received_at - sent_at).max(Duration::zero());
+        if self.rtt.is_some_and(|best| best <= rtt) {
+            return;
+        }
+        self.rtt = Some(rtt);
+        self.offset = server_time - (sent_at + rtt / 2);
+    }
+
+    /// 还没有完成时钟同步时，用计时消息中的服务器时间估计时差
+    pub fn observe(&mut self, server_time: DateTime<Utc>, received_at: DateTime<Utc>) {
+        // 旧版服务器不带服务器时间 (反序列化为纪元时间)，无法估计
+        if self.rtt.is_none() && server_time != DateTime::<Utc>::default() {
+            self.offset = server_time - received_at;
+        }
+    }
+
+    /// 重新连接后重新同步，之前的结果在新的同步完成前继续使用
+    pub fn reset_samples(&mut self) {
+        self.samples = 0;
+        self.rtt = None;
+    }
+
+    /// 本次连接是否还需要再同步一次
+    pub fn needs_sample(&self) -> bool {
+        self.samples < CLOCK_SYNC_SAMPLES
+    }
+
+    /// 把服务器时间换算成本地时间
+    pub fn to_local(&self, server_time: DateTime<Utc>) -> DateTime<Utc> {
+        server_time - self.offset
+    }
+
+    /// 在本地时间 `now` 时，距离服务器时间的截止时间还剩多久，不小于 0
+    pub fn remaining(&self, deadline: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+        (self.to_local(deadline) - now).max(Duration::zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_uses_fastest_round_trip() {
+        let t0 = Utc::now();
+        // 服务器时钟快 10 秒
+        let mut clock = ServerClock::default();
+        clock.observe(t0 + Duration::seconds(11), t0);
+        assert_eq!(clock.to_local(t0 + Duration::seconds(11)), t0);
+
+        // 往返 2 秒 (连接建立时的第一次同步)，估计偏差 1 秒
+        clock.sync(t0, t0 + Duration::seconds(10) + Duration::seconds(2), t0 + Duration::seconds(2));
+        assert_eq!(clock.offset, Duration::seconds(11));
+        // 往返 200 毫秒的同步更准确
+        let t1 = t0 + Duration::seconds(5);
+        clock.sync(t1, t1 + Duration::seconds(10) + Duration::milliseconds(100), t1 + Duration::milliseconds(200));
+        assert_eq!(clock.offset, Duration::seconds(10));
+        // 往返更长的同步被忽略，完成同步后计时消息也不再改变时差
+        clock.sync(t1, t1 + Duration::seconds(20), t1 + Duration::seconds(1));
+        clock.observe(t1 + Duration::seconds(30), t1);
+        assert_eq!(clock.offset, Duration::seconds(10));
+        assert!(!clock.needs_sample());
+
+        let deadline = t1 + Duration::seconds(40);
+        assert_eq!(clock.remaining(deadline, t1), Duration::seconds(30));
+        assert_eq!(clock.remaining(deadline, t1 + Duration::seconds(60)), Duration::zero());
+    }
+}
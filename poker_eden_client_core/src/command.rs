@@ -0,0 +1,585 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 指令语法
+//!
+//! 登录界面和房间内输入的文字指令，解析为要执行的命令或发给服务器的消息。
+//! 只处理与服务器相关的指令，界面本地的选项 (如花色显示方式) 由前端自己解析。
+
+use crate::session::Session;
+use crate::share::parse_join_url;
+use poker_eden_core::*;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// 用于解析登录界面输入的命令
+#[derive(Debug, PartialEq)]
+pub enum LoginCommand {
+    Create { server_addr: String, nickname: String, config: RoomConfig },
+    Join { server_addr: String, room_id: RoomId, nickname: String },
+    /// 离线的牌力训练
+    Train,
+    /// 离线的胜率估算测验
+    Quiz,
+    /// 查询通信对局是否轮到自己行动
+    Games,
+    /// 回到第 n 个 (从 1 开始) 通信对局
+    Resume(usize),
+}
+
+/// 解析登录界面的输入
+pub fn parse_login_input(input: &str) -> Option<LoginCommand> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    if parts.len() == 1 && parts[0].eq_ignore_ascii_case("train") {
+        return Some(LoginCommand::Train);
+    }
+    if parts.len() == 1 && parts[0].eq_ignore_ascii_case("quiz") {
+        return Some(LoginCommand::Quiz);
+    }
+    if parts.len() == 1 && parts[0].eq_ignore_ascii_case("games") {
+        return Some(LoginCommand::Games);
+    }
+    if parts.len() == 2 && parts[0].eq_ignore_ascii_case("resume") {
+        return parts[1].parse().ok().map(LoginCommand::Resume);
+    }
+    if parts.len() < 3 { return None; }
+
+    match parts[0].to_lowercase().as_str() {
+        "create" => {
+            // 简单验证地址格式，但不做完整解析
+            if parts[1].contains(':') {
+                let config = parse_room_config(&parts[3..])?;
+                Some(LoginCommand::Create { server_addr: parts[1].to_string(), nickname: parts[2].to_string(), config })
+            } else { None }
+        }
+        "join" if parts.len() == 3 => {
+            let (server_addr, room_id) = parse_join_url(parts[1])?;
+            Some(LoginCommand::Join { server_addr, room_id, nickname: parts[2].to_string() })
+        }
+        "join" if parts.len() == 4 => {
+            if let Ok(room_id) = Uuid::from_str(parts[2]) {
+                if parts[1].contains(':') {
+                    Some(LoginCommand::Join { server_addr: parts[1].to_string(), room_id, nickname: parts[3].to_string() })
+                } else { None }
+            } else { None }
+        }
+        _ => None,
+    }
+}
+
+/// 解析创建房间时的牌桌配置 `blinds=50/100 seats=6 buyin=2000-10000 timer=30 bank=60`，
+/// 每一项都可以省略，省略的项使用默认值
+pub fn parse_room_config(args: &[&str]) -> Option<RoomConfig> {
+    let mut config = RoomConfig::default();
+    for arg in args {
+        let (key, value) = arg.split_once('=')?;
+        match key.to_lowercase().as_str() {
+            "blinds" => {
+                let (sb, bb) = value.split_once('/')?;
+                (config.small_blind, config.big_blind) = (sb.parse().ok()?, bb.parse().ok()?);
+            }
+            "seats" => config.seats = value.parse().ok()?,
+            // `buyin=2000-10000`，`buyin=2000-` 只限制最低带入
+            "buyin" => {
+                let (min, max) = value.split_once('-')?;
+                config.min_buy_in = min.parse().ok()?;
+                config.max_buy_in = if max.is_empty() { 0 } else { max.parse().ok()? };
+            }
+            "timer" => config.turn_timer_secs = value.parse().ok()?,
+            "bank" => config.time_bank_secs = value.parse().ok()?,
+            _ => return None,
+        }
+    }
+    Some(config)
+}
+
+/// 解析玩法名称
+pub fn parse_variant(name: &str) -> Option<GameVariant> {
+    match name.to_lowercase().as_str() {
+        "holdem" => Some(GameVariant::TexasHoldem),
+        "o8" | "omaha8" => Some(GameVariant::OmahaHiLo),
+        "draw" | "5cd" => Some(GameVariant::FiveCardDraw),
+        _ => None,
+    }
+}
+
+/// 解析混合玩法轮换，例如 `holdem,o8` (每个玩法一圈) 或 `holdem,o8:6` (每个玩法 6 局)
+pub fn parse_rotation(text: &str) -> Option<VariantRotation> {
+    let (variants, hands_per_game) = match text.split_once(':') {
+        Some((variants, hands)) => (variants, hands.parse().ok().filter(|n| *n > 0)?),
+        None => (text, 0),
+    };
+    let variants = variants.split(',').map(parse_variant).collect::<Option<Vec<_>>>()?;
+    Some(VariantRotation { variants, hands_per_game })
+}
+
+/// 解析锦标赛盲注结构，例如 `10:25/50,50/100,100/200`；
+/// 每级局数写成 `15m` 时改为每 15 分钟升级
+pub fn parse_tournament(text: &str) -> Option<TournamentStructure> {
+    let (per_level, levels) = text.split_once(':')?;
+    let levels = levels.split(',').map(|level| {
+        let (sb, bb) = level.split_once('/')?;
+        Some(BlindLevel { small_blind: sb.parse().ok()?, big_blind: bb.parse().ok()? })
+    }).collect::<Option<Vec<_>>>()?;
+    match per_level.strip_suffix('m') {
+        Some(minutes) => {
+            let level_secs = minutes.parse::<u32>().ok().filter(|m| *m > 0)?.checked_mul(60)?;
+            Some(TournamentStructure { hands_per_level: 0, level_secs, levels })
+        }
+        None => Some(TournamentStructure { hands_per_level: per_level.parse().ok()?, level_secs: 0, levels }),
+    }
+}
+
+/// 解析房间选项: `<选项名> <值>`，开关类的选项用 on/off，数值类的选项用 off 或 0 关闭
+pub fn parse_room_option(name: &str, value_str: &str) -> Option<RoomOption> {
+    let value = match value_str.to_lowercase().as_str() {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    };
+    match (name.to_lowercase().as_str(), value) {
+        ("revealfolded", Some(value)) => Some(RoomOption::RevealFoldedHands(value)),
+        ("doubleboard", Some(value)) => Some(RoomOption::DoubleBoard(value)),
+        ("variant", _) => parse_variant(value_str).map(RoomOption::Variant),
+        // `option limit nl|pl`，无限注或底池限注
+        ("limit", None) => match value_str.to_lowercase().as_str() {
+            "nl" | "nolimit" => Some(RoomOption::BettingStructure(BettingStructure::NoLimit)),
+            "pl" | "potlimit" => Some(RoomOption::BettingStructure(BettingStructure::PotLimit)),
+            _ => None,
+        },
+        // `option dealerschoice holdem,o8,draw`，off 时关闭庄家选择模式
+        ("dealerschoice", Some(false)) => Some(RoomOption::DealersChoice(vec![])),
+        ("dealerschoice", None) => value_str.split(',').map(parse_variant).collect::<Option<Vec<_>>>().map(RoomOption::DealersChoice),
+        // `option rotation holdem,o8[:局数]`，不写局数时每个玩法进行一圈，off 时关闭混合轮换
+        ("rotation", Some(false)) => Some(RoomOption::Rotation(None)),
+        ("rotation", None) => parse_rotation(value_str).map(|r| RoomOption::Rotation(Some(r))),
+        // `option ante <前注>`，0 或 off 时不收前注
+        ("ante", Some(false)) => Some(RoomOption::Ante(0)),
+        ("ante", None) => value_str.parse::<u32>().ok().map(RoomOption::Ante),
+        ("bbante", Some(value)) => Some(RoomOption::BigBlindAnte(value)),
+        ("democracy", Some(value)) => Some(RoomOption::Democracy(value)),
+        ("guessing", Some(value)) => Some(RoomOption::SpectatorGuessing(value)),
+        ("insurance", Some(value)) => Some(RoomOption::Insurance(value)),
+        // `option bounty <筹码>`，0 或 off 时关闭七二奖励
+        ("bounty", Some(false)) => Some(RoomOption::SevenDeuceBounty(0)),
+        ("bounty", None) => value_str.parse::<u32>().ok().map(RoomOption::SevenDeuceBounty),
+        // `option rathole <分钟>`，0 或 off 时关闭防抽水离场
+        ("rathole", Some(false)) => Some(RoomOption::RatholeWindow(0)),
+        ("rathole", None) => value_str.parse::<u32>().ok().map(RoomOption::RatholeWindow),
+        // `option straddle utg|button|off`
+        ("straddle", Some(false)) => Some(RoomOption::Straddle(None)),
+        ("straddle", None) => match value_str.to_lowercase().as_str() {
+            "utg" => Some(RoomOption::Straddle(Some(StraddlePosition::Utg))),
+            "button" | "btn" => Some(RoomOption::Straddle(Some(StraddlePosition::Button))),
+            _ => None,
+        },
+        // `option bombpot <底注>`，底注为 0 或 off 时关闭炸弹底池
+        ("bombpot", Some(false)) => Some(RoomOption::BombPot(0)),
+        ("bombpot", None) => value_str.parse::<u32>().ok().map(RoomOption::BombPot),
+        // `option bombevery <局数>`，每隔这么多局打一次炸弹底池，0 或 off 时只在房主安排时打
+        ("bombevery", Some(false)) => Some(RoomOption::BombPotEvery(0)),
+        ("bombevery", None) => value_str.parse::<u32>().ok().map(RoomOption::BombPotEvery),
+        // `option tournament <每级局数|分钟数m>:<小盲>/<大盲>,<小盲>/<大盲>...`，off 时关闭锦标赛模式
+        ("tournament", Some(false)) => Some(RoomOption::Tournament(None)),
+        ("tournament", None) => parse_tournament(value_str).map(|t| RoomOption::Tournament(Some(t))),
+        // `option sng <买入>:<起始筹码>:<奖金百分比>`，off 时关闭 Sit & Go
+        ("sng", Some(false)) => Some(RoomOption::SitAndGo(None)),
+        ("sng", None) => parse_sit_and_go(value_str).map(|sng| RoomOption::SitAndGo(Some(sng))),
+        // `option fee <金额>`，0 或 off 时不收场地费
+        ("fee", Some(false)) => Some(RoomOption::SessionFee(0)),
+        ("fee", None) => value_str.parse::<u32>().ok().map(RoomOption::SessionFee),
+        ("confirm", Some(value)) => Some(RoomOption::ConfirmActions(value)),
+        ("accounts", Some(value)) => Some(RoomOption::ChipAccounts(value)),
+        ("approval", Some(value)) => Some(RoomOption::RequireJoinApproval(value)),
+        ("autofold", Some(value)) => Some(RoomOption::AutoFoldDisabled(!value)),
+        ("correspondence", Some(value)) => Some(RoomOption::Correspondence(value)),
+        // `option spectators <人数>`，0 或 off 时不限制观战人数
+        ("spectators", Some(false)) => Some(RoomOption::MaxSpectators(0)),
+        ("spectators", None) => value_str.parse::<u32>().ok().map(RoomOption::MaxSpectators),
+        // `option undo <毫秒>`，0 或 off 时关闭撤回窗口
+        ("undo", Some(false)) => Some(RoomOption::UndoGrace(0)),
+        ("undo", None) => value_str.parse::<u64>().ok().map(RoomOption::UndoGrace),
+        // `option timebank <秒>`，修改后所有玩家的时间银行重置为新的时长
+        ("timebank", Some(false)) => Some(RoomOption::TimeBank(0)),
+        ("timebank", None) => value_str.parse::<u32>().ok().map(RoomOption::TimeBank),
+        // `option timer <秒>`，0 或 off 时关闭行动计时
+        ("timer", Some(false)) => Some(RoomOption::TurnTimer(0)),
+        ("timer", None) => value_str.parse::<u32>().ok().map(RoomOption::TurnTimer),
+        _ => None,
+    }
+}
+
+/// 解析 Sit & Go 设置，例如 `100:1500:65/35` (买入:起始筹码:各名次奖金百分比)，
+/// 奖金百分比之和必须是 100
+pub fn parse_sit_and_go(text: &str) -> Option<SitAndGo> {
+    let mut parts = text.split(':');
+    let buy_in = parts.next()?.parse().ok()?;
+    let starting_stack = parts.next()?.parse().ok().filter(|s| *s > 0)?;
+    let payouts = parts.next()?.split('/').map(|p| p.parse().ok()).collect::<Option<Vec<u32>>>()?;
+    if parts.next().is_some() || payouts.iter().sum::<u32>() != 100 {
+        return None;
+    }
+    Some(SitAndGo { buy_in, starting_stack, payouts })
+}
+
+/// 解析 `#rrggbb` 形式的颜色，`#` 可以省略
+pub fn parse_color(hex: &str) -> Option<[u8; 3]> {
+    let hex = hex.strip_prefix('#').unwrap_or(hex);
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// 解析在房间内的输入（坐下或游戏动作）
+pub fn parse_in_room_input(input: &str, session: &Session) -> Option<ClientMessage> {
+    let parts: Vec<&str> = input.split_whitespace().collect();
+    if parts.is_empty() { return None; }
+
+    // 检查玩家是否已经就座
+    let is_seated = session.is_seated();
+
+    // 自动盖牌设置，任何时候都可以切换
+    if parts[0].to_lowercase() == "automuck" && parts.len() == 2 {
+        return match parts[1].to_lowercase().as_str() {
+            "on" => Some(ClientMessage::SetAutoMuck(true)),
+            "off" => Some(ClientMessage::SetAutoMuck(false)),
+            _ => None,
+        };
+    }
+
+    // 翻牌前自动弃牌: `autofold <百分位>` 或 `autofold off`，任何时候都可以设置
+    if parts[0].to_lowercase() == "autofold" && parts.len() == 2 {
+        return match parts[1].to_lowercase().as_str() {
+            "off" => Some(ClientMessage::SetAutoFold(0)),
+            below => below.trim_end_matches('%').parse::<u8>().ok().map(ClientMessage::SetAutoFold),
+        };
+    }
+
+    // 每赢得一个底池给房主的小费: `pottip <数量>` 或 `pottip off`，任何时候都可以设置
+    if parts[0].to_lowercase() == "pottip" && parts.len() == 2 {
+        return match parts[1].to_lowercase().as_str() {
+            "off" => Some(ClientMessage::SetPotTip(0)),
+            amount => amount.parse::<u32>().ok().map(ClientMessage::SetPotTip),
+        };
+    }
+
+    // 展示偏好: `profile lang zh|en`、`profile color #rrggbb|off`、`profile avatar <表情>|off`，任何时候都可以设置
+    if parts[0].to_lowercase() == "profile" && parts.len() == 3 {
+        let mut profile = session.my_player().map(|p| p.profile.clone()).unwrap_or_default();
+        let off = parts[2].eq_ignore_ascii_case("off");
+        match parts[1].to_lowercase().as_str() {
+            "lang" => profile.locale = Locale::from_code(parts[2])?,
+            "color" if off => profile.color = None,
+            "color" => profile.color = Some(parse_color(parts[2])?),
+            "avatar" if off => profile.avatar = None,
+            "avatar" => profile.avatar = Some(parts[2].to_string()),
+            _ => return None,
+        }
+        return Some(ClientMessage::SetProfile(profile));
+    }
+
+    // 房主切换房间选项
+    if session.is_host() && parts[0].to_lowercase() == "option" && parts.len() == 3 {
+        return parse_room_option(parts[1], parts[2]).map(ClientMessage::SetRoomOption);
+    }
+
+    // 民主模式下发起投票: `vote kick <昵称>` / `vote void` / `vote blinds <小盲>/<大盲>` / `vote option <选项> <值>` /
+    // `vote bombpot` (下一局打一次炸弹底池)，
+    // 对进行中的投票投票: `vote yes` / `vote no`
+    if parts[0].to_lowercase() == "vote" && parts.len() >= 2 {
+        let gs = session.game_state.as_ref()?;
+        let propose = |proposal| Some(ClientMessage::ProposeVote(proposal));
+        return match (parts[1].to_lowercase().as_str(), parts.len()) {
+            (answer @ ("yes" | "no"), 2) => gs.vote.as_ref()
+                .map(|v| ClientMessage::CastVote { vote_id: v.id, approve: answer == "yes" }),
+            ("kick", 3) => gs.players.values().find(|p| p.nickname == parts[2])
+                .and_then(|p| propose(VoteProposal::Kick(p.id))),
+            ("void", 2) => propose(VoteProposal::VoidHand),
+            ("bombpot", 2) => propose(VoteProposal::BombPotNextHand),
+            ("blinds", 3) => {
+                let (small_blind, big_blind) = parts[2].split_once('/')?;
+                propose(VoteProposal::SetBlinds { small_blind: small_blind.parse().ok()?, big_blind: big_blind.parse().ok()? })
+            }
+            ("option", 4) => parse_room_option(parts[2], parts[3]).and_then(|o| propose(VoteProposal::SetRoomOption(o))),
+            _ => None,
+        };
+    }
+
+    // 观众猜本局的赢家: `guess <昵称>`
+    if parts[0].to_lowercase() == "guess" && parts.len() == 2 {
+        let gs = session.game_state.as_ref()?;
+        return gs.players.values().find(|p| p.nickname == parts[1]).map(|p| ClientMessage::GuessWinner(p.id));
+    }
+
+    // 通信对局中设置或关闭行动提醒的联系方式
+    if parts[0].to_lowercase() == "notify" && parts.len() == 2 {
+        let contact = (parts[1].to_lowercase() != "off").then(|| parts[1].to_string());
+        return Some(ClientMessage::SetNotifyContact(contact));
+    }
+
+    // 查看本次游戏汇总
+    if parts[0].to_lowercase() == "summary" && parts.len() == 1 {
+        return Some(ClientMessage::GetSessionSummary);
+    }
+
+    // 开启筹码账户时申请存入筹码，入座前后都可以
+    if parts[0].to_lowercase() == "deposit" && parts.len() == 2 {
+        return parts[1].parse::<u32>().ok().map(ClientMessage::DepositChips);
+    }
+
+    // 房主处理加入申请: `admit <昵称>` / `deny <昵称>`
+    if session.is_host() && parts.len() == 2
+        && let Some((target_id, _)) = session.pending_joins.iter().find(|(_, nickname)| nickname == parts[1]) {
+        match parts[0].to_lowercase().as_str() {
+            "admit" => return Some(ClientMessage::ApproveJoin(*target_id)),
+            "deny" => return Some(ClientMessage::RejectJoin(*target_id)),
+            _ => {}
+        }
+    }
+
+    // 房主批准或拒绝玩家的存入申请: `approve <昵称>` / `reject <昵称>`
+    if session.is_host() && parts.len() == 2
+        && let Some(target_id) = session.game_state.as_ref()
+            .and_then(|gs| gs.players.values().find(|p| p.nickname == parts[1]))
+            .map(|p| p.id) {
+        match parts[0].to_lowercase().as_str() {
+            "approve" => return Some(ClientMessage::ApproveDeposit(target_id)),
+            "reject" => return Some(ClientMessage::RejectDeposit(target_id)),
+            _ => {}
+        }
+    }
+
+    // 查看兔子牌 (河牌之前结束的牌局没有发出的公共牌)，房主没有入座也可以看
+    if parts[0].to_lowercase() == "rabbit" && parts.len() == 1 && (is_seated || session.is_host()) {
+        return Some(ClientMessage::RabbitHunt);
+    }
+
+    // 全下保险报价后买保险: `insure <保额>`，`insure off` 不买
+    if parts[0].to_lowercase() == "insure" && parts.len() == 2 {
+        return match parts[1].to_lowercase().as_str() {
+            "off" | "no" => Some(ClientMessage::BuyInsurance(0)),
+            cover => cover.parse::<u32>().ok().map(ClientMessage::BuyInsurance),
+        };
+    }
+
+    // 庄家选择下一局的玩法
+    if parts[0].to_lowercase() == "choose" && parts.len() == 2 {
+        return parse_variant(parts[1]).map(ClientMessage::ChooseVariant);
+    }
+
+    // 检查是否为房主、已就座、在等待阶段，以解析 "start" 命令
+    if session.is_host() && is_seated && parts[0].to_lowercase() == "start"
+        && session.game_state.as_ref().is_some_and(|gs| {
+        gs.phase == GamePhase::WaitingForPlayers || gs.phase == GamePhase::Showdown
+    }) {
+        return Some(ClientMessage::StartHand);
+    }
+
+    // 房主结束本次游戏，牌局进行中时等本局结束
+    if session.is_host() && parts[0].to_lowercase() == "endsession" && parts.len() == 1 {
+        return Some(ClientMessage::EndSession);
+    }
+
+    // 房主把玩家移出房间: `kick <昵称>`
+    if session.is_host() && parts[0].to_lowercase() == "kick" && parts.len() == 2 {
+        let gs = session.game_state.as_ref()?;
+        return gs.players.values().find(|p| p.nickname == parts[1])
+            .map(|p| ClientMessage::KickPlayer { player_id: p.id });
+    }
+
+    // 复式对局: `duplicate new` 开始新的对局，`duplicate join <编号>` 加入其他牌桌的对局，`duplicate off` 退出
+    if session.is_host() && parts[0].to_lowercase() == "duplicate" {
+        return match (parts.get(1).map(|s| s.to_lowercase()).as_deref(), parts.len()) {
+            (Some("new"), 2) => Some(ClientMessage::StartDuplicate),
+            (Some("off"), 2) => Some(ClientMessage::StopDuplicate),
+            (Some("join"), 3) => parts[2].parse().ok().map(|match_id| ClientMessage::JoinDuplicate { match_id }),
+            _ => None,
+        };
+    }
+
+    // 房主把房主身份交给另一位玩家: `transfer <昵称>`
+    if session.is_host() && parts[0].to_lowercase() == "transfer" && parts.len() == 2 {
+        let gs = session.game_state.as_ref()?;
+        return gs.players.values().find(|p| p.nickname == parts[1])
+            .map(|p| ClientMessage::TransferHost { player_id: p.id });
+    }
+
+    // 房主在两局之间随机重新分配座位
+    if session.is_host() && parts[0].to_lowercase() == "shuffle" && parts.len() == 1 {
+        return Some(ClientMessage::ShuffleSeats);
+    }
+
+    // 房主安排下一局打一次炸弹底池
+    if session.is_host() && parts[0].to_lowercase() == "bombpot" && parts.len() == 1 {
+        return Some(ClientMessage::BombPotNextHand);
+    }
+
+    // 房主把房间时间线导出到服务器，`export en` 导出英文的牌局历史，默认为中文
+    if session.is_host() && parts[0].to_lowercase() == "export" && parts.len() <= 2 {
+        let locale = match parts.get(1) {
+            Some(code) => Locale::from_code(code)?,
+            None => Locale::default(),
+        };
+        return Some(ClientMessage::ExportTimeline(locale));
+    }
+
+    // 房主查看审计日志
+    if session.is_host() && parts[0].to_lowercase() == "audit" && parts.len() == 1 {
+        return Some(ClientMessage::GetAuditLog);
+    }
+
+    // 房主设置或关闭 Discord 通知，webhook 地址区分大小写，不能转成小写
+    if session.is_host() && parts[0].to_lowercase() == "discord" && parts.len() == 2 {
+        let channel = (parts[1].to_lowercase() != "off").then(|| parts[1].to_string());
+        return Some(ClientMessage::SetDiscordChannel(channel));
+    }
+
+    // 暂时离席的玩家回到座位: `sitin` 等到大盲位入局，`deadblind` 补交错过的盲注立即入局
+    // (已经回到座位、正在等大盲的玩家也用 `deadblind` 改为补交)
+    if parts.len() == 1 {
+        match parts[0].to_lowercase().as_str() {
+            "sitin" => return Some(ClientMessage::SitIn),
+            "deadblind" => return Some(ClientMessage::PostDeadBlind),
+            _ => {}
+        }
+    }
+
+    let is_lose_game = session.game_state.as_ref().is_some_and(|gs| {
+        gs.players.get(&session.my_id.unwrap()).is_some_and(|p| p.is_offline)
+    });
+
+    if !is_seated || is_lose_game {
+        // 如果未就座，只解析 "seat" 命令
+        if parts[0].to_lowercase() == "seat" && parts.len() == 3
+            && let (Ok(seat_id), Ok(stack)) = (parts[1].parse::<u8>(), parts[2].parse::<u32>()) {
+            return Some(ClientMessage::RequestSeat { seat_id, stack });
+        }
+    } else {
+        // 如果已就座，解析游戏动作
+        return match parts[0].to_lowercase().as_str() {
+            "straddle" => Some(ClientMessage::PostStraddle),
+            "leave" => Some(ClientMessage::LeaveSeat),
+            "cashout" => Some(ClientMessage::CashOut),
+            "sitout" => Some(ClientMessage::SitOut),
+            "timebank" => Some(ClientMessage::UseTimeBank),
+            "reveal" => Some(ClientMessage::RevealFoldedHand),
+            // 输掉摊牌、轮到自己时选择亮牌或盖牌
+            "show" if session.my_showdown_turn() => Some(ClientMessage::ShowOrMuck { show: true }),
+            // 其他人都弃牌后亮出底牌: `show` 亮出全部，`show 1` 只亮第一张 (序号从 1 开始)
+            "show" => {
+                let indices: Result<Vec<usize>, _> = parts[1..].iter().map(|s| s.parse::<usize>()).collect();
+                match indices {
+                    Ok(indices) if indices.is_empty() => {
+                        let gs = session.game_state.as_ref()?;
+                        let idx = session.my_id.and_then(|id| gs.player_indices.get(&id).copied())?;
+                        Some(ClientMessage::ShowCards((0..gs.player_cards[idx].len()).collect()))
+                    }
+                    Ok(indices) if indices.iter().all(|&i| i >= 1) => {
+                        Some(ClientMessage::ShowCards(indices.into_iter().map(|i| i - 1).collect()))
+                    }
+                    _ => None,
+                }
+            }
+            "muck" => Some(ClientMessage::ShowOrMuck { show: false }),
+            "undo" => Some(ClientMessage::UndoAction),
+            "tip" if parts.len() == 2 => parts[1].parse::<u32>().ok().map(ClientMessage::Tip),
+            "y" | "yes" => session.pending_confirm.as_ref().map(|(id, _)| ClientMessage::ConfirmAction(*id)),
+            // 换牌序号从 1 开始，与界面上底牌的顺序一致
+            "d" | "draw" => {
+                let indices: Result<Vec<usize>, _> = parts[1..].iter().map(|s| s.parse::<usize>()).collect();
+                match indices {
+                    Ok(indices) if indices.iter().all(|&i| i >= 1) => {
+                        Some(ClientMessage::DiscardCards(indices.into_iter().map(|i| i - 1).collect()))
+                    }
+                    _ => None,
+                }
+            }
+            "f" | "fold" => Some(PlayerAction::Fold.into()),
+            "c" | "check" | "call" => {
+                let mut is_check = false;
+                let mut is_call = false;
+                for valid_action in session.valid_actions.iter() {
+                    match valid_action {
+                        PlayerActionType::Check => {
+                            is_check = true;
+                            break;
+                        }
+                        PlayerActionType::Call(_) => {
+                            is_call = true;
+                            break;
+                        }
+                        _ => continue,
+                    }
+                }
+                if is_check { Some(PlayerAction::Check.into()) } else if is_call { Some(PlayerAction::Call.into()) } else { None }
+            }
+            "b" | "r" | "bet" | "raise" => {
+                if parts.len() > 1 {
+                    if let Ok(amount) = parts[1].parse::<u32>() {
+                        // 超过上限 (底池限注) 的下注在本地就拒绝，不用等服务器返回错误
+                        let over_cap = session.valid_actions.iter().any(|a| matches!(a,
+                            PlayerActionType::Bet { max, .. } | PlayerActionType::Raise { max, .. } if amount > *max));
+                        if over_cap { None } else { Some(PlayerAction::BetOrRaise(amount).into()) }
+                    } else { None }
+                } else { None }
+            }
+            _ => None,
+        };
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::join_url;
+
+    #[test]
+    fn test_parse_login_and_room_commands() {
+        let room_id = Uuid::new_v4();
+        let join = LoginCommand::Join { server_addr: "127.0.0.1:25917".to_string(), room_id, nickname: "Alice".to_string() };
+        assert_eq!(parse_login_input(&format!("join {} Alice", join_url("127.0.0.1:25917", room_id))), Some(join));
+        assert_eq!(parse_login_input("resume 2"), Some(LoginCommand::Resume(2)));
+        assert_eq!(parse_login_input("create localhost Alice"), None);
+        let Some(LoginCommand::Create { config, .. }) = parse_login_input("create 127.0.0.1:25917 Alice blinds=50/100 buyin=2000-") else {
+            panic!("create with config")
+        };
+        assert_eq!((config.small_blind, config.big_blind, config.min_buy_in, config.max_buy_in, config.seats), (50, 100, 2000, 0, MAX_SEATS));
+        assert_eq!(parse_login_input("create 127.0.0.1:25917 Alice seats"), None);
+
+        assert!(matches!(parse_room_option("limit", "pl"), Some(RoomOption::BettingStructure(BettingStructure::PotLimit))));
+        assert!(matches!(parse_room_option("ante", "off"), Some(RoomOption::Ante(0))));
+        assert!(matches!(parse_room_option("bombevery", "5"), Some(RoomOption::BombPotEvery(5))));
+        let rotation = VariantRotation { variants: vec![GameVariant::TexasHoldem, GameVariant::OmahaHiLo], hands_per_game: 6 };
+        assert_eq!(parse_rotation("holdem,o8:6"), Some(rotation));
+        assert_eq!(parse_rotation("holdem,stud"), None);
+        assert!(matches!(parse_room_option("rotation", "off"), Some(RoomOption::Rotation(None))));
+        assert_eq!(parse_sit_and_go("100:1500:65/30"), None);
+        assert_eq!(parse_color("#ff8000"), Some([255, 128, 0]));
+        assert_eq!(parse_color("ff80"), None);
+
+        // 观众只能坐下，不能行动
+        let session = Session::default();
+        assert!(matches!(parse_in_room_input("seat 1 1000", &session), Some(ClientMessage::RequestSeat { seat_id: 1, stack: 1000 })));
+        assert!(parse_in_room_input("fold", &session).is_none());
+        assert!(matches!(parse_in_room_input("sitin", &session), Some(ClientMessage::SitIn)));
+        assert!(matches!(parse_in_room_input("deadblind", &session), Some(ClientMessage::PostDeadBlind)));
+        assert!(parse_in_room_input("sitin post", &session).is_none());
+    }
+}
@@ -0,0 +1,116 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 终端显示宽度
+//!
+//! 中文和花色符号在终端里占两个格子，不能用字节数或字符数计算光标和列宽。
+//! 带 emoji 变体选择符的花色 (如 `♠️`) 在不同终端里的宽度不一致，
+//! 因此提供不带变体选择符的符号和纯 ASCII 两种备用的花色显示方式。
+
+use poker_eden_core::{Card, Suit};
+use unicode_width::UnicodeWidthStr;
+
+/// 花色的显示方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SuitStyle {
+    /// emoji 花色，如 `♠️A`
+    #[default]
+    Emoji,
+    /// 不带变体选择符的符号，如 `♠A`，在多数终端里只占一个格子
+    Symbol,
+    /// 纯 ASCII，如 `As`，适用于所有终端
+    Ascii,
+}
+
+impl SuitStyle {
+    /// 从环境变量 `POKER_EDEN_SUITS` 读取，未设置或无法识别时使用 emoji
+    pub fn from_env() -> Self {
+        std::env::var("POKER_EDEN_SUITS").ok().and_then(|v| Self::parse(&v)).unwrap_or_default()
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "emoji" => Some(SuitStyle::Emoji),
+            "symbol" => Some(SuitStyle::Symbol),
+            "ascii" => Some(SuitStyle::Ascii),
+            _ => None,
+        }
+    }
+
+    /// 一张牌的文字
+    pub fn card(self, card: &Card) -> String {
+        match self {
+            SuitStyle::Emoji => card.to_string(),
+            SuitStyle::Symbol => {
+                let suit = match card.suit {
+                    Suit::Spade => '♠',
+                    Suit::Heart => '♥',
+                    Suit::Club => '♣',
+                    Suit::Diamond => '♦',
+                };
+                format!("{}{}", suit, card.rank)
+            }
+            SuitStyle::Ascii => {
+                let suit = match card.suit {
+                    Suit::Spade => 's',
+                    Suit::Heart => 'h',
+                    Suit::Club => 'c',
+                    Suit::Diamond => 'd',
+                };
+                format!("{}{}", card.rank, suit)
+            }
+        }
+    }
+
+    /// 这种显示方式下一张牌占用的格子数，牌背按这个宽度绘制
+    pub fn card_width(self) -> usize {
+        match self {
+            SuitStyle::Emoji => 3,
+            SuitStyle::Symbol | SuitStyle::Ascii => 2,
+        }
+    }
+}
+
+/// 文字在终端中占用的格子数
+pub fn display_width(text: &str) -> u16 {
+    text.width().min(u16::MAX as usize) as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poker_eden_core::Rank;
+
+    #[test]
+    fn test_card_width_matches_style() {
+        let cards = [Card::new(Rank::Ace, Suit::Spade), Card::new(Rank::Ten, Suit::Diamond)];
+        for style in [SuitStyle::Emoji, SuitStyle::Symbol, SuitStyle::Ascii] {
+            for card in &cards {
+                assert_eq!(display_width(&style.card(card)) as usize, style.card_width(), "{:?}", style);
+            }
+        }
+        assert_eq!(SuitStyle::Ascii.card(&cards[0]), "As");
+        assert_eq!(SuitStyle::Symbol.card(&cards[1]), "♦T");
+    }
+
+    #[test]
+    fn test_display_width_counts_wide_chars() {
+        assert_eq!(display_width("bet 100"), 7);
+        assert_eq!(display_width("加注 100"), 8);
+        assert_eq!(display_width("♠️A"), 3);
+    }
+}
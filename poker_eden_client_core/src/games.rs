@@ -0,0 +1,237 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+//! 通信对局的书签和签到
+//!
+//! 加入开启了通信对局的房间后，客户端把重连凭证保存到 `POKER_EDEN_GAMES_FILE`
+//! (默认 `poker_eden_games.json`)。启动时和在登录界面输入 `games` 时，
+//! 客户端不加入房间，只向服务器查询这些对局是否轮到自己行动；输入 `resume <序号>` 回到对局。
+
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use poker_eden_core::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fs, path::PathBuf, time::Duration};
+use tokio_tungstenite::tungstenite::Message;
+
+/// 等待服务器回复签到的最长时间
+const CHECK_IN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 签到请求中的一个对局: 房间ID和重连凭证
+pub type CheckInGame = (RoomId, PlayerId, PlayerSecret);
+
+/// 一个通信对局的重连凭证
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GameBookmark {
+    pub server_addr: String,
+    pub room_id: RoomId,
+    pub player_id: PlayerId,
+    pub secret: PlayerSecret,
+    pub nickname: String,
+    /// 是否是房主，恢复对局后用来显示房主面板
+    pub host: bool,
+}
+
+/// 保存在本地的通信对局，以及最近一次签到的结果
+#[derive(Default)]
+pub struct GameBook {
+    /// 不设置时只保存在内存中
+    path: Option<PathBuf>,
+    pub games: Vec<GameBookmark>,
+    statuses: HashMap<RoomId, GameCheckIn>,
+}
+
+impl GameBook {
+    pub fn from_env() -> Self {
+        let path = std::env::var("POKER_EDEN_GAMES_FILE").ok().filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "poker_eden_games.json".to_string());
+        Self::load(PathBuf::from(path))
+    }
+
+    /// 从文件读取书签，文件不存在或无法解析时为空
+    pub fn load(path: PathBuf) -> Self {
+        let games = fs::read_to_string(&path).ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Self { path: Some(path), games, statuses: HashMap::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// 添加或更新一个对局的书签，有变化时写入文件
+    pub fn add(&mut self, bookmark: GameBookmark) {
+        match self.games.iter_mut().find(|g| g.room_id == bookmark.room_id) {
+            Some(existing) if *existing == bookmark => return,
+            Some(existing) => *existing = bookmark,
+            None => self.games.push(bookmark),
+        }
+        self.save();
+    }
+
+    /// 记录签到的结果，房间已经关闭或凭证失效的对局不再保留
+    pub fn update(&mut self, statuses: Vec<GameCheckIn>) {
+        let before = self.games.len();
+        for status in statuses {
+            if !status.active {
+                self.games.retain(|g| g.room_id != status.room_id);
+                self.statuses.remove(&status.room_id);
+            } else {
+                self.statuses.insert(status.room_id, status);
+            }
+        }
+        if self.games.len() != before {
+            self.save();
+        }
+    }
+
+    /// 删除已经关闭的房间的书签
+    pub fn remove(&mut self, room_id: RoomId) {
+        let before = self.games.len();
+        self.games.retain(|g| g.room_id != room_id);
+        self.statuses.remove(&room_id);
+        if self.games.len() != before {
+            self.save();
+        }
+    }
+
+    pub fn status(&self, room_id: RoomId) -> Option<&GameCheckIn> {
+        self.statuses.get(&room_id)
+    }
+
+    /// 轮到自己行动的对局数
+    pub fn your_turn_count(&self) -> usize {
+        self.games.iter().filter(|g| self.status(g.room_id).is_some_and(|s| s.your_turn)).count()
+    }
+
+    /// 按服务器分组的签到请求，每组最多 `MAX_CHECK_IN_GAMES` 个对局
+    pub fn requests(&self) -> Vec<(String, Vec<CheckInGame>)> {
+        let mut requests: Vec<(String, Vec<_>)> = vec![];
+        for g in &self.games {
+            let game = (g.room_id, g.player_id, g.secret);
+            match requests.iter_mut().find(|(addr, games)| *addr == g.server_addr && games.len() < MAX_CHECK_IN_GAMES) {
+                Some((_, games)) => games.push(game),
+                None => requests.push((g.server_addr.clone(), vec![game])),
+            }
+        }
+        requests
+    }
+
+    /// 写文件失败不影响游戏
+    fn save(&self) {
+        let Some(path) = &self.path else { return };
+        if let Ok(text) = serde_json::to_string_pretty(&self.games) {
+            let _ = fs::write(path, text);
+        }
+    }
+}
+
+/// 一个对局在登录界面上的状态说明
+pub fn describe(status: Option<&GameCheckIn>, now: DateTime<Utc>) -> String {
+    match status {
+        None => "查询中...".to_string(),
+        Some(s) if !s.your_turn => "等待其他玩家".to_string(),
+        Some(GameCheckIn { deadline: None, .. }) => "轮到你".to_string(),
+        Some(GameCheckIn { deadline: Some(deadline), .. }) => {
+            let mins = (*deadline - now).num_minutes().max(0);
+            if mins >= 60 {
+                format!("轮到你 (剩余 {} 小时 {} 分)", mins / 60, mins % 60)
+            } else {
+                format!("轮到你 (剩余 {} 分)", mins)
+            }
+        }
+    }
+}
+
+/// 连接服务器查询一组对局的状态
+pub async fn check_in(server_addr: &str, games: Vec<CheckInGame>) -> Result<Vec<GameCheckIn>, String> {
+    let url = format!("ws://{}/ws", server_addr);
+    let (mut ws, _) = tokio_tungstenite::connect_async(url.as_str()).await
+        .map_err(|e| format!("连接服务器 {} 失败: {}", server_addr, e))?;
+    let request = serde_json::to_string(&ClientMessage::CheckIn { games }).unwrap();
+    ws.send(Message::Text(request.into())).await.map_err(|e| e.to_string())?;
+
+    let reply = tokio::time::timeout(CHECK_IN_TIMEOUT, async {
+        while let Some(Ok(msg)) = ws.next().await {
+            let Message::Text(text) = msg else { continue };
+            match serde_json::from_str::<ServerMessage>(&text) {
+                Ok(ServerMessage::CheckInStatus { games }) => return Ok(games),
+                Ok(ServerMessage::Error { message }) => return Err(message),
+                _ => {}
+            }
+        }
+        Err(format!("服务器 {} 关闭了连接", server_addr))
+    }).await.unwrap_or_else(|_| Err(format!("服务器 {} 没有回复签到", server_addr)));
+    let _ = ws.close(None).await;
+    reply
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn bookmark(server_addr: &str) -> GameBookmark {
+        GameBookmark {
+            server_addr: server_addr.to_string(),
+            room_id: Uuid::new_v4(),
+            player_id: Uuid::new_v4(),
+            secret: Uuid::new_v4(),
+            nickname: "Alice".to_string(),
+            host: false,
+        }
+    }
+
+    #[test]
+    fn test_check_in_updates_and_drops_closed_games() {
+        let mut book = GameBook::default();
+        let (a, b) = (bookmark("127.0.0.1:25917"), bookmark("127.0.0.1:25917"));
+        book.add(a.clone());
+        book.add(b.clone());
+        book.add(GameBookmark { nickname: "Alicia".to_string(), ..a.clone() });
+        assert_eq!(book.games.len(), 2);
+        assert_eq!(book.games[0].nickname, "Alicia");
+        assert_eq!(book.requests().len(), 1);
+
+        book.update(vec![
+            GameCheckIn { room_id: a.room_id, active: true, your_turn: true, deadline: None },
+            GameCheckIn { room_id: b.room_id, active: false, your_turn: false, deadline: None },
+        ]);
+        assert_eq!(book.games, vec![GameBookmark { nickname: "Alicia".to_string(), ..a }]);
+        assert_eq!(book.your_turn_count(), 1);
+    }
+
+    #[test]
+    fn test_remove_closed_game() {
+        let mut book = GameBook::default();
+        let (a, b) = (bookmark("127.0.0.1:25917"), bookmark("127.0.0.1:25917"));
+        book.add(a.clone());
+        book.add(b.clone());
+        book.remove(a.room_id);
+        assert_eq!(book.games, vec![b]);
+    }
+
+    #[test]
+    fn test_describe_remaining_time() {
+        let now = Utc::now();
+        let status = |your_turn, deadline| GameCheckIn { room_id: Uuid::nil(), active: true, your_turn, deadline };
+        assert_eq!(describe(None, now), "查询中...");
+        assert_eq!(describe(Some(&status(false, None)), now), "等待其他玩家");
+        assert_eq!(describe(Some(&status(true, Some(now + chrono::Duration::minutes(185)))), now), "轮到你 (剩余 3 小时 5 分)");
+        assert_eq!(describe(Some(&status(true, Some(now + chrono::Duration::seconds(30)))), now), "轮到你 (剩余 0 分)");
+    }
+}
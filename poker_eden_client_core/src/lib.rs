@@ -0,0 +1,39 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! # 客户端核心库
+//!
+//! 与具体界面无关的客户端逻辑: 与服务器的连接、会话状态的维护、指令语法的解析，
+//! 以及离线的训练小游戏。终端客户端 `poker_eden_client` 只是这个库之上的一层界面，
+//! 其他前端 (图形界面、网页、移动端) 可以复用同样的连接和会话逻辑。
+
+pub mod chat;
+pub mod clock;
+pub mod command;
+pub mod display;
+pub mod games;
+pub mod log;
+pub mod network;
+pub mod outbox;
+pub mod quiz;
+pub mod session;
+pub mod share;
+pub mod trainer;
+
+pub use command::{parse_in_room_input, parse_login_input, LoginCommand};
+pub use network::NetworkEvent;
+pub use session::{Session, SessionEvent};
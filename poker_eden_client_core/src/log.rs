@@ -0,0 +1,110 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 客户端日志
+//!
+//! 记录游戏事件和发送、接收的原始消息，用于调试。
+
+use chrono::{DateTime, Local, Utc};
+use poker_eden_core::ServerMessage;
+use std::{
+    collections::VecDeque,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+/// 客户端日志
+///
+/// 内存中只保留最近的 `capacity` 条日志；如果配置了日志文件，每条日志同时追加写入文件，
+/// 文件超过 `max_file_bytes` 后轮转为 `<文件名>.1`。
+/// 状态快照这类体积很大的原始消息默认只记录类型，设置 `verbose` 后才记录完整内容。
+///
+/// 通过环境变量配置:
+/// - `POKER_EDEN_LOG_SIZE`: 内存中保留的日志条数，默认 1000
+/// - `POKER_EDEN_LOG_FILE`: 日志文件路径，不设置则不写文件
+/// - `POKER_EDEN_LOG_MAX_BYTES`: 日志文件轮转的大小，默认 1 MiB
+/// - `POKER_EDEN_LOG_VERBOSE`: 设为 1 时记录完整的状态快照
+pub struct ClientLog {
+    entries: VecDeque<String>,
+    capacity: usize,
+    file: Option<PathBuf>,
+    max_file_bytes: u64,
+    verbose: bool,
+}
+
+impl ClientLog {
+    const DEFAULT_CAPACITY: usize = 1000;
+    const DEFAULT_MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Self {
+            entries: VecDeque::new(),
+            capacity: var("POKER_EDEN_LOG_SIZE").and_then(|v| v.parse().ok()).filter(|n| *n > 0)
+                .unwrap_or(Self::DEFAULT_CAPACITY),
+            file: var("POKER_EDEN_LOG_FILE").map(PathBuf::from),
+            max_file_bytes: var("POKER_EDEN_LOG_MAX_BYTES").and_then(|v| v.parse().ok())
+                .unwrap_or(Self::DEFAULT_MAX_FILE_BYTES),
+            verbose: var("POKER_EDEN_LOG_VERBOSE").is_some_and(|v| v == "1"),
+        }
+    }
+
+    /// 内存中保留的日志，最新的在最后
+    pub fn entries(&self) -> &VecDeque<String> {
+        &self.entries
+    }
+
+    /// 以当前时间记录一条日志
+    pub fn push(&mut self, msg: impl Into<String>) {
+        self.push_at(Utc::now(), msg);
+    }
+
+    /// 以给定的 (服务器) 时间记录一条日志，显示为本地时间
+    pub fn push_at(&mut self, at: DateTime<Utc>, msg: impl Into<String>) {
+        let entry = format!("[{}] {}", at.with_timezone(&Local).format("%H:%M:%S"), msg.into());
+        self.write_file(&entry);
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// 记录从服务器收到的原始消息，非 verbose 模式下省略状态快照的内容
+    pub fn push_recv(&mut self, text: &str, msg: Option<&ServerMessage>) {
+        match msg {
+            Some(ServerMessage::GameStateSnapshot(_)) if !self.verbose => {
+                self.push("[RECV] GameStateSnapshot (省略)")
+            }
+            Some(ServerMessage::RoomJoined { .. }) if !self.verbose => self.push("[RECV] RoomJoined (省略)"),
+            _ => self.push(format!("[RECV] {}", text)),
+        }
+    }
+
+    /// 追加写入日志文件，写文件失败不影响游戏
+    fn write_file(&self, entry: &str) {
+        let Some(path) = &self.file else { return };
+        if fs::metadata(path).is_ok_and(|m| m.len() >= self.max_file_bytes) {
+            let mut rotated = path.clone().into_os_string();
+            rotated.push(".1");
+            let _ = fs::rename(path, rotated);
+        }
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            let _ = writeln!(file, "{}", entry);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 与服务器的连接
+//!
+//! 每次连接启动一个独立的网络任务，通过通道接收要发送的消息，
+//! 收到的消息在任务中反序列化后以 `NetworkEvent` 发给前端，不占用界面线程。
+
+use futures_util::{SinkExt, StreamExt};
+use poker_eden_core::*;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message;
+
+/// 超过这个时间没有收到服务器的任何消息 (包括心跳) 就认为连接已经中断
+const SERVER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 网络任务发给前端的事件
+pub enum NetworkEvent {
+    /// 已连接到服务器
+    Connected,
+    /// 连接服务器失败
+    ConnectFailed(String),
+    /// 已向服务器发送一条消息 (原始 JSON，用于日志)
+    Sent(String),
+    /// 收到服务器的消息，反序列化在网络任务中完成，不占用界面线程
+    Received { text: String, msg: Option<Box<ServerMessage>> },
+    /// 连接已断开，附带断开原因
+    Disconnected(String),
+    /// 一台服务器上的通信对局的签到结果
+    CheckedIn(Result<Vec<GameCheckIn>, String>),
+}
+
+/// 独立的网络任务，处理所有与服务器的通信。
+pub async fn network_task(events: mpsc::UnboundedSender<NetworkEvent>, mut rx: mpsc::Receiver<ClientMessage>, server_addr: String) {
+    // 声明支持压缩，服务器会把快照等大消息压缩成二进制帧发送
+    let url = url::Url::parse(&format!("ws://{}/ws?{}", server_addr, COMPRESS_QUERY)).unwrap();
+
+    let ws_stream = match tokio_tungstenite::connect_async(url.as_str()).await {
+        Ok((stream, _)) => stream,
+        Err(e) => {
+            let _ = events.send(NetworkEvent::ConnectFailed(format!("连接服务器失败: {}", e)));
+            return;
+        }
+    };
+    let _ = events.send(NetworkEvent::Connected);
+
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+    // 服务器会定时发送心跳，超过 SERVER_TIMEOUT 没有收到任何消息就认为连接已经中断
+    let mut last_seen = Instant::now();
+    let mut check_timer = tokio::time::interval(Duration::from_secs(1));
+    let reason = loop {
+        tokio::select! {
+            msg_to_send = rx.recv() => {
+                // 会话丢弃了发送器 (房间已关闭或改连其他服务器)，主动关闭连接，不再上报断线
+                let Some(msg_to_send) = msg_to_send else {
+                    let _ = ws_sender.close().await;
+                    return;
+                };
+                let msg_text = serde_json::to_string(&msg_to_send).unwrap();
+                let _ = events.send(NetworkEvent::Sent(msg_text.clone()));
+                if ws_sender.send(Message::Text(msg_text.into())).await.is_err() {
+                    break "与服务器的连接已断开。".to_string();
+                }
+            }
+            _ = check_timer.tick() => {
+                if last_seen.elapsed() > SERVER_TIMEOUT {
+                    break "长时间没有收到服务器的消息，连接已断开。".to_string();
+                }
+            }
+            msg = ws_receiver.next() => {
+                let Some(Ok(msg)) = msg else {
+                    break "与服务器的连接已断开。".to_string();
+                };
+                last_seen = Instant::now();
+                let text = match frame_text(&msg) {
+                    Ok(text) => text,
+                    Err(reason) => break reason,
+                };
+                if let Some(text) = text {
+                    let msg = serde_json::from_str::<ServerMessage>(&text).ok().map(Box::new);
+                    let _ = events.send(NetworkEvent::Received { text, msg });
+                } else if msg.is_close() {
+                    break "服务器已关闭连接。".to_string();
+                }
+            }
+        }
+    };
+
+    let _ = events.send(NetworkEvent::Disconnected(reason));
+}
+
+/// 取出服务器消息帧中的 JSON，控制帧返回 None。
+/// 压缩帧解压失败时返回断开原因: 丢掉这条消息后状态可能已经不同步，断开后由重连补发错过的事件
+fn frame_text(msg: &Message) -> Result<Option<String>, String> {
+    match msg {
+        Message::Text(text) => Ok(Some(text.to_string())),
+        Message::Binary(bytes) => inflate(bytes)
+            .map(Some)
+            .map_err(|e| format!("无法解压服务器的消息 ({})，连接已断开。", e)),
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_text_reports_inflate_failures() {
+        let json = r#"{"Info":{"message":"hi"}}"#;
+        assert_eq!(frame_text(&Message::Text(json.into())), Ok(Some(json.to_string())));
+        assert_eq!(frame_text(&Message::Binary(deflate(json).into())), Ok(Some(json.to_string())));
+        assert_eq!(frame_text(&Message::Ping(Default::default())), Ok(None));
+        assert!(frame_text(&Message::Binary(vec![0xff, 0x00, 0x13].into())).is_err());
+    }
+}
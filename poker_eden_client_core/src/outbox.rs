@@ -0,0 +1,176 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 发往服务器的消息队列
+//!
+//! 网络任务暂时忙不过来时，输入的指令先排队而不是被丢弃。
+//! 轮到自己时的动作 (下注动作和换牌) 发出后标记为"发送中"，直到服务器广播了自己的动作或返回错误，
+//! 在此之前排在后面的消息不会发出，避免在旧的状态上连续行动。
+
+use poker_eden_core::{ClientMessage, PlayerAction};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+/// 发送中的动作超过这个时间没有回应，就认为已经丢失，不再阻塞后面的消息
+const IN_FLIGHT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 动作的中文描述
+pub fn action_label(action: &PlayerAction) -> String {
+    match action {
+        PlayerAction::Fold => "弃牌".to_string(),
+        PlayerAction::Check => "过牌".to_string(),
+        PlayerAction::Call => "跟注".to_string(),
+        PlayerAction::BetOrRaise(amount) => format!("下注/加注 ${}", amount),
+    }
+}
+
+/// 是否为需要等待服务器确认的行动
+fn is_turn_action(msg: &ClientMessage) -> bool {
+    matches!(msg, ClientMessage::PerformAction(_) | ClientMessage::DiscardCards(_))
+}
+
+#[derive(Default)]
+pub struct Outbox {
+    queue: VecDeque<ClientMessage>,
+    /// 已发出、等待服务器确认的行动和发出的时间
+    in_flight: Option<(ClientMessage, Instant)>,
+}
+
+impl Outbox {
+    pub fn push(&mut self, msg: ClientMessage) {
+        self.queue.push_back(msg);
+    }
+
+    /// 尽可能多地把排队的消息交给网络任务，通道已满或有行动在等待确认时停下
+    pub fn flush(&mut self, tx: &mpsc::Sender<ClientMessage>, now: Instant) {
+        if self.in_flight.as_ref().is_some_and(|(_, at)| now.duration_since(*at) >= IN_FLIGHT_TIMEOUT) {
+            self.in_flight = None;
+        }
+        while self.in_flight.is_none() && let Some(msg) = self.queue.pop_front() {
+            let tracked = is_turn_action(&msg).then(|| msg.clone());
+            match tx.try_send(msg) {
+                Ok(()) => self.in_flight = tracked.map(|msg| (msg, now)),
+                Err(TrySendError::Full(msg)) => {
+                    self.queue.push_front(msg);
+                    break;
+                }
+                // 连接已关闭，断线事件会清空队列
+                Err(TrySendError::Closed(_)) => break,
+            }
+        }
+    }
+
+    /// 服务器确认了自己的行动
+    pub fn acknowledge(&mut self) {
+        self.in_flight = None;
+    }
+
+    /// 服务器返回了错误。如果有行动在等待确认，说明行动被拒绝，排在后面的行动也一并丢弃
+    pub fn reject(&mut self) {
+        if self.in_flight.take().is_some() {
+            self.queue.retain(|msg| !is_turn_action(msg));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.in_flight = None;
+    }
+
+    /// 发送中的行动的描述，用于在界面上提示
+    pub fn sending(&self) -> Option<String> {
+        match &self.in_flight {
+            Some((ClientMessage::PerformAction(action), _)) => Some(action_label(action)),
+            Some((ClientMessage::DiscardCards(_), _)) => Some("换牌".to_string()),
+            _ => None,
+        }
+    }
+
+    /// 还在排队、尚未发出的消息数
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions_wait_for_acknowledgement() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let now = Instant::now();
+        let mut outbox = Outbox::default();
+        outbox.push(ClientMessage::GetMyHand);
+        outbox.push(PlayerAction::Call.into());
+        outbox.push(ClientMessage::GetSessionSummary);
+        outbox.flush(&tx, now);
+
+        // 行动之前的消息直接发出，行动之后的消息等待确认
+        assert!(matches!(rx.try_recv(), Ok(ClientMessage::GetMyHand)));
+        assert!(matches!(rx.try_recv(), Ok(ClientMessage::PerformAction(PlayerAction::Call))));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(outbox.sending().as_deref(), Some("跟注"));
+        assert_eq!(outbox.queued(), 1);
+
+        outbox.acknowledge();
+        outbox.flush(&tx, now);
+        assert!(matches!(rx.try_recv(), Ok(ClientMessage::GetSessionSummary)));
+        assert_eq!(outbox.sending(), None);
+    }
+
+    #[test]
+    fn test_rejected_action_drops_queued_actions() {
+        let (tx, mut rx) = mpsc::channel(8);
+        let now = Instant::now();
+        let mut outbox = Outbox::default();
+        outbox.push(PlayerAction::BetOrRaise(100).into());
+        outbox.push(PlayerAction::Fold.into());
+        outbox.push(ClientMessage::GetSessionSummary);
+        outbox.flush(&tx, now);
+        assert!(matches!(rx.try_recv(), Ok(ClientMessage::PerformAction(PlayerAction::BetOrRaise(100)))));
+
+        outbox.reject();
+        outbox.flush(&tx, now);
+        assert!(matches!(rx.try_recv(), Ok(ClientMessage::GetSessionSummary)));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_full_channel_keeps_messages_queued() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let now = Instant::now();
+        let mut outbox = Outbox::default();
+        outbox.push(ClientMessage::GetMyHand);
+        outbox.push(ClientMessage::GetSessionSummary);
+        outbox.flush(&tx, now);
+        assert_eq!(outbox.queued(), 1);
+
+        assert!(matches!(rx.try_recv(), Ok(ClientMessage::GetMyHand)));
+        outbox.flush(&tx, now);
+        assert!(matches!(rx.try_recv(), Ok(ClientMessage::GetSessionSummary)));
+
+        // 行动一直没有回应时，超时后放行后面的消息
+        outbox.push(PlayerAction::Check.into());
+        outbox.push(ClientMessage::GetMyHand);
+        outbox.flush(&tx, now);
+        assert!(matches!(rx.try_recv(), Ok(ClientMessage::PerformAction(PlayerAction::Check))));
+        outbox.flush(&tx, now + IN_FLIGHT_TIMEOUT);
+        assert!(matches!(rx.try_recv(), Ok(ClientMessage::GetMyHand)));
+    }
+}
@@ -0,0 +1,164 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 胜率估算测验
+//!
+//! 不需要连接服务器。随机发一手底牌和 0 到 5 张公共牌，再给出对手的起手牌范围，
+//! 让玩家估算这手牌对抗该范围的胜率 (百分比)，与核心库的计算结果比较，
+//! 并记录每题的误差，观察估算能力随练习的变化。
+
+use poker_eden_core::*;
+use std::time::Instant;
+
+/// 计算正确答案时的模拟次数
+const EQUITY_SAMPLES: usize = 3000;
+
+/// 统计最近误差时使用的题数
+pub const RECENT_QUESTIONS: usize = 10;
+
+/// 出题使用的对手范围: (名称, 简写)
+pub const RANGES: [(&str, &str); 4] = [
+    ("很紧", "QQ+, AKs, AKo"),
+    ("紧", "TT+, AQs+, KQs, AQo+"),
+    ("中等", "66+, A9s+, KTs+, QTs+, JTs, ATo+, KJo+"),
+    ("宽", "22+, A2s+, K8s+, Q9s+, J9s+, T9s, 98s, 87s, 76s, A8o+, KTo+, QTo+, JTo"),
+];
+
+/// 一道题
+#[derive(Debug, Clone)]
+pub struct EquityQuestion {
+    pub hand: Vec<Card>,
+    pub board: Vec<Card>,
+    /// 对手范围在 `RANGES` 中的序号
+    pub range: usize,
+    /// 正确的胜率 (0 到 1)
+    pub equity: f64,
+}
+
+impl EquityQuestion {
+    /// 随机出一道题
+    pub fn random() -> Self {
+        loop {
+            let mut deck = generate_shuffled_deck();
+            let hand: Vec<Card> = deck.drain(..2).collect();
+            // 翻牌前、翻牌、转牌、河牌四种情况
+            let board_len = [0, 3, 4, 5][rand::random_range(0..4)];
+            let board: Vec<Card> = deck.drain(..board_len).collect();
+            let range = rand::random_range(0..RANGES.len());
+            let parsed: HandRange = RANGES[range].1.parse().expect("内置范围可以解析");
+            if let Some(equity) = range_equity(&hand, &parsed, &board, EQUITY_SAMPLES) {
+                return Self { hand, board, range, equity };
+            }
+        }
+    }
+
+    pub fn range_name(&self) -> &'static str {
+        RANGES[self.range].0
+    }
+
+    pub fn range_text(&self) -> &'static str {
+        RANGES[self.range].1
+    }
+}
+
+/// 解析估算的胜率，接受 `45`、`45%`、`45.5` 这样的百分比
+pub fn parse_estimate(input: &str) -> Option<f64> {
+    let value: f64 = input.trim().trim_end_matches('%').trim().parse().ok()?;
+    (0.0..=100.0).contains(&value).then_some(value)
+}
+
+/// 测验的进度和成绩
+pub struct EquityQuiz {
+    pub question: EquityQuestion,
+    asked_at: Instant,
+    /// 每题估算与正确答案相差的百分点，按答题顺序排列
+    pub errors: Vec<f64>,
+    /// 每题的答题时间 (秒)
+    times: Vec<f64>,
+    /// 上一题的估算和正确答案 (百分比)
+    pub feedback: Option<(f64, f64)>,
+}
+
+impl EquityQuiz {
+    pub fn new(now: Instant) -> Self {
+        Self { question: EquityQuestion::random(), asked_at: now, errors: vec![], times: vec![], feedback: None }
+    }
+
+    /// 回答当前题目，记录误差并出下一题。无法识别的回答不计入成绩，返回 false
+    pub fn answer(&mut self, input: &str, now: Instant) -> bool {
+        let Some(estimate) = parse_estimate(input) else { return false };
+        let actual = self.question.equity * 100.0;
+        self.errors.push((estimate - actual).abs());
+        self.times.push(now.duration_since(self.asked_at).as_secs_f64());
+        self.feedback = Some((estimate, actual));
+        self.question = EquityQuestion::random();
+        self.asked_at = now;
+        true
+    }
+
+    /// 所有题目的平均误差 (百分点)
+    pub fn average_error(&self) -> f64 {
+        average(&self.errors)
+    }
+
+    /// 最近 `RECENT_QUESTIONS` 题的平均误差 (百分点)
+    pub fn recent_error(&self) -> f64 {
+        average(&self.errors[self.errors.len().saturating_sub(RECENT_QUESTIONS)..])
+    }
+
+    /// 平均每题用时 (秒)
+    pub fn average_secs(&self) -> f64 {
+        average(&self.times)
+    }
+}
+
+fn average(values: &[f64]) -> f64 {
+    if values.is_empty() { 0.0 } else { values.iter().sum::<f64>() / values.len() as f64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_builtin_ranges_parse() {
+        for (name, text) in RANGES {
+            assert!(text.parse::<HandRange>().is_ok(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_estimates_and_errors() {
+        assert_eq!(parse_estimate("45"), Some(45.0));
+        assert_eq!(parse_estimate(" 45.5% "), Some(45.5));
+        assert_eq!(parse_estimate("120"), None);
+        assert_eq!(parse_estimate("abc"), None);
+
+        let start = Instant::now();
+        let mut quiz = EquityQuiz::new(start);
+        quiz.question.equity = 0.6;
+        assert!(!quiz.answer("x", start));
+        assert!(quiz.answer("50%", start + Duration::from_secs(6)));
+        quiz.question.equity = 0.25;
+        assert!(quiz.answer("25", start + Duration::from_secs(8)));
+        assert_eq!(quiz.feedback, Some((25.0, 25.0)));
+        assert!((quiz.errors[0] - 10.0).abs() < 1e-9);
+        assert!((quiz.average_error() - 5.0).abs() < 1e-9);
+        assert_eq!(quiz.average_secs(), 4.0);
+    }
+}
@@ -0,0 +1,914 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 客户端会话
+//!
+//! `Session` 持有与界面无关的客户端状态: 连接、身份和重连凭证、从服务器同步的游戏状态、
+//! 发送队列、日志和聊天记录。前端 (终端界面或其他界面) 把网络事件交给会话处理，
+//! 根据返回的 `SessionEvent` 更新自己的界面状态，再从会话中读取要显示的内容。
+
+use crate::chat::ChatLog;
+use crate::clock::ServerClock;
+use crate::display::SuitStyle;
+use crate::games::{self, GameBook, GameBookmark};
+use crate::log::ClientLog;
+use crate::network::{network_task, NetworkEvent};
+use crate::outbox::Outbox;
+use chrono::{DateTime, Local, Utc};
+use poker_eden_core::*;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+/// 全下摊牌时随机模拟胜率的次数
+const EQUITY_SAMPLES: usize = 5000;
+
+/// 会话处理消息后通知前端的事件，前端据此切换界面
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    /// 会话状态发生了变化，需要重绘界面
+    Updated,
+    /// 进入了房间 (加入房间，或凭凭证回到通信对局)
+    EnteredRoom,
+    /// 新的一局开始，房间的分享信息已清除
+    HandStarted,
+    /// 收到了汇总类的消息 (游戏汇总、操作记录、比赛结果)，前端应打开日志视图
+    ShowLog,
+    /// 房间已经关闭，会话回到了登录前的状态，前端应回到登录界面
+    LeftRoom,
+}
+
+/// 客户端与一个房间的会话
+pub struct Session {
+    /// 当前的游戏状态，从服务器接收。如果没有连接或游戏未开始，则为 None。
+    pub game_state: Option<GameState>,
+    /// 连接到的服务器地址
+    pub server_addr: Option<String>,
+    /// 用于向网络任务发送消息的发送器。
+    pub msg_sender: Option<mpsc::Sender<ClientMessage>>,
+    /// 等待发给网络任务的消息，以及发送中、等待服务器确认的行动
+    pub outbox: Outbox,
+    /// 创建房间后生成的分享信息。
+    pub share_info: Option<String>,
+    /// 客户端自己的玩家ID。
+    pub my_id: Option<PlayerId>,
+    /// 断线重连的凭证
+    pub my_secret: Option<PlayerSecret>,
+    /// 最后收到的房间事件序号，重连时服务器从这里开始补发
+    pub last_event_seq: Option<u64>,
+    /// 房主ID
+    pub host_id: Option<PlayerId>,
+    /// 房主收到的、尚未处理的加入申请
+    pub pending_joins: Vec<(PlayerId, String)>,
+    /// 房主收到的、尚未处理的存入申请
+    pub pending_deposits: Vec<(PlayerId, u32)>,
+
+    // 游戏过程中的状态
+    /// 客户端当前的牌型
+    pub hand_ranks: Vec<Option<HandRank>>,
+    /// 本局结束时服务器发来的各参与者的筹码变化，牌局进行中为空
+    pub stack_changes: Vec<StackChange>,
+    /// 当轮到自己行动时，服务器会发送过来当前合法的动作列表。
+    pub valid_actions: Vec<PlayerActionType>,
+    /// 确认动作模式下，服务器回显的待确认动作
+    pub pending_confirm: Option<(Uuid, PlayerAction)>,
+    /// 从服务器收到的最后一条错误信息或提示信息。
+    pub last_msg: Option<String>,
+    /// 与服务器的连接是否已经中断
+    pub offline: bool,
+    /// 本局全下摊牌时各玩家的胜率
+    pub all_in_equities: Vec<(PlayerId, f64)>,
+    /// 开启行动计时时，正在行动的玩家和他的截止时间 (已换算成本地时间)
+    pub turn_deadline: Option<(PlayerId, DateTime<Utc>)>,
+    /// 服务器时钟与本地时钟之差，用于换算计时消息中的截止时间
+    pub clock: ServerClock,
+    /// 正在行动的玩家是否已经启用了时间银行
+    pub time_bank_active: bool,
+    /// 新手模式: 在动作提示和摊牌结果中加入讲解
+    pub beginner_mode: bool,
+    /// 新手模式下本局摊牌结果的讲解
+    pub showdown_explanations: Vec<String>,
+    /// 进阶数据: 轮到自己时显示筹码底池比、本局已投入的比例和之后还要行动的人数
+    pub advanced_stats: bool,
+    /// 轮到自己时，这一轮在自己之后还要行动的玩家数
+    pub players_to_act: u32,
+    /// 日志中牌的写法
+    pub suits: SuitStyle,
+    /// 房间内的聊天消息，和调试日志分开保存
+    pub chat: ChatLog,
+    /// 保存在本地的通信对局和签到结果
+    pub games: GameBook,
+    /// 客户端日志，包括游戏事件和发送、接收的原始消息，用于调试。
+    pub log: ClientLog,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            game_state: None,
+            server_addr: None,
+            msg_sender: None,
+            outbox: Outbox::default(),
+            share_info: None,
+            my_id: None,
+            my_secret: None,
+            last_event_seq: None,
+            host_id: None,
+            pending_joins: vec![],
+            pending_deposits: vec![],
+            hand_ranks: vec![],
+            stack_changes: vec![],
+            valid_actions: vec![],
+            pending_confirm: None,
+            last_msg: None,
+            offline: false,
+            all_in_equities: vec![],
+            turn_deadline: None,
+            clock: ServerClock::default(),
+            time_bank_active: false,
+            beginner_mode: false,
+            showdown_explanations: vec![],
+            advanced_stats: false,
+            players_to_act: 0,
+            suits: SuitStyle::from_env(),
+            chat: ChatLog::default(),
+            games: GameBook::from_env(),
+            log: ClientLog::from_env(),
+        }
+    }
+}
+
+impl Session {
+    /// 自己是否是房主
+    pub fn is_host(&self) -> bool {
+        self.my_id.is_some() && self.my_id == self.host_id
+    }
+
+    /// 自己在房间中的玩家信息
+    pub fn my_player(&self) -> Option<&Player> {
+        self.game_state.as_ref()?.players.get(self.my_id.as_ref()?)
+    }
+
+    /// 自己是否已经就座，未就座时是观众
+    pub fn is_seated(&self) -> bool {
+        self.my_id.is_some_and(|my_id| {
+            self.game_state.as_ref().is_some_and(|gs| gs.seated_players.contains(&my_id))
+        })
+    }
+
+    /// 输掉摊牌后是否轮到自己选择亮牌或盖牌
+    pub fn my_showdown_turn(&self) -> bool {
+        self.my_id.is_some_and(|my_id| {
+            self.game_state.as_ref().is_some_and(|gs| gs.phase == GamePhase::Showdown && gs.showdown_queue.first() == Some(&my_id))
+        })
+    }
+
+    /// 开启进阶数据时，轮到自己下注的这一手的筹码底池比、已投入比例和之后还要行动的人数
+    pub fn turn_stats(&self) -> Option<String> {
+        let betting = !self.valid_actions.is_empty() && !self.valid_actions.iter().any(|a| matches!(a, PlayerActionType::Discard(_)));
+        let gs = self.game_state.as_ref().filter(|gs| self.advanced_stats && betting && gs.phase != GamePhase::Showdown)?;
+        let my_id = self.my_id?;
+        let spr = gs.stack_to_pot(&my_id).map_or("-".to_string(), |spr| format!("{:.1}", spr));
+        let committed = gs.pot_commitment(&my_id)? * 100.0;
+        Some(format!("SPR {}，本局已投入 {:.0}% 的筹码，之后还有 {} 人行动", spr, committed, self.players_to_act))
+    }
+
+    /// 其他人都弃牌、自己赢下底池后，是否还有没亮出的底牌可以主动亮出
+    pub fn can_show_cards(&self) -> bool {
+        let (Some(my_id), Some(gs)) = (self.my_id, self.game_state.as_ref()) else { return false };
+        gs.phase == GamePhase::Showdown
+            && gs.get_players_in_hand() == [my_id]
+            && gs.player_indices.get(&my_id).is_some_and(|&idx| !gs.is_shown(idx))
+    }
+
+    /// 把消息放入发送队列
+    pub fn send(&mut self, msg: ClientMessage) {
+        // 拒绝的申请不会再有后续消息，发送后直接移除
+        match &msg {
+            ClientMessage::RejectJoin(id) => self.pending_joins.retain(|(p, _)| p != id),
+            ClientMessage::RejectDeposit(id) => self.pending_deposits.retain(|(p, _)| p != id),
+            _ => {}
+        }
+        self.outbox.push(msg);
+    }
+
+    /// 把排队的消息交给网络任务
+    pub fn flush(&mut self, now: Instant) {
+        if let Some(tx) = &self.msg_sender {
+            self.outbox.flush(tx, now);
+        }
+    }
+
+    /// 启动网络任务连接服务器，连接后发送第一条消息 (创建或加入房间)，然后开始时钟同步
+    pub fn connect(&mut self, events: &mpsc::UnboundedSender<NetworkEvent>, server_addr: String, initial_msg: ClientMessage) {
+        let (tx, rx) = mpsc::channel(32);
+        self.msg_sender = Some(tx.clone());
+        self.offline = false;
+        self.clock.reset_samples();
+        self.server_addr = Some(server_addr.clone());
+        tokio::spawn(network_task(events.clone(), rx, server_addr));
+        tokio::spawn(async move {
+            tx.send(initial_msg).await.ok();
+            tx.send(ClientMessage::ClockSync { client_time: Utc::now() }).await.ok();
+        });
+    }
+
+    /// 断线后凭 RoomJoined 中的凭证重新连接到原来的房间和座位
+    pub fn reconnect(&mut self, events: &mpsc::UnboundedSender<NetworkEvent>) {
+        let (Some(server_addr), Some(player_id), Some(secret), Some(gs)) =
+            (self.server_addr.clone(), self.my_id, self.my_secret, &self.game_state) else { return };
+        let msg = ClientMessage::Reconnect { room_id: gs.room_id, player_id, secret, last_seq: self.last_event_seq };
+        self.log.push("正在重新连接...".to_string());
+        self.connect(events, server_addr, msg);
+    }
+
+    /// 在后台查询所有保存的通信对局，结果以 `NetworkEvent::CheckedIn` 返回
+    pub fn check_in_games(&self, events: &mpsc::UnboundedSender<NetworkEvent>) {
+        for (server_addr, games) in self.games.requests() {
+            let events = events.clone();
+            tokio::spawn(async move {
+                let _ = events.send(NetworkEvent::CheckedIn(games::check_in(&server_addr, games).await));
+            });
+        }
+    }
+
+    /// 凭保存的凭证回到第 n 个 (从 1 开始) 通信对局
+    pub fn resume_game(&mut self, events: &mpsc::UnboundedSender<NetworkEvent>, n: usize) {
+        let Some(game) = n.checked_sub(1).and_then(|i| self.games.games.get(i)).cloned() else {
+            self.last_msg = Some(format!("没有第 {} 个对局，输入 games 刷新列表", n));
+            return;
+        };
+        self.my_id = Some(game.player_id);
+        self.my_secret = Some(game.secret);
+        self.host_id = game.host.then_some(game.player_id);
+        self.last_event_seq = None;
+        let msg = ClientMessage::Reconnect { room_id: game.room_id, player_id: game.player_id, secret: game.secret, last_seq: None };
+        self.connect(events, game.server_addr, msg);
+    }
+
+    /// 在通信对局中时保存 (或更新) 当前对局的书签
+    fn bookmark_game(&mut self) {
+        let (Some(gs), Some(server_addr), Some(player_id), Some(secret)) =
+            (&self.game_state, &self.server_addr, self.my_id, self.my_secret) else { return };
+        if !gs.room_options.correspondence {
+            return;
+        }
+        let Some(me) = gs.players.get(&player_id) else { return };
+        self.games.add(GameBookmark {
+            server_addr: server_addr.clone(),
+            room_id: gs.room_id,
+            player_id,
+            secret,
+            nickname: me.nickname.clone(),
+            host: self.host_id == Some(player_id),
+        });
+    }
+
+    /// 房间关闭后清除房间相关的状态，回到登录前。丢弃发送器后网络任务会关闭连接。
+    /// 服务器关闭时保留通信对局的书签，重启后还能回到对局
+    fn leave_room(&mut self, reason: RoomCloseReason) {
+        if reason != RoomCloseReason::ServerShutdown
+            && let Some(gs) = &self.game_state {
+            self.games.remove(gs.room_id);
+        }
+        // 被移出的玩家只是自己离开了房间，房间并没有关闭
+        let title = if reason == RoomCloseReason::Kicked { "已离开房间" } else { "房间已关闭" };
+        self.log.push(format!("{}: {}", title, reason));
+        // 房主结束游戏前发来了汇总和结算，留在日志里
+        let hint = if reason == RoomCloseReason::HostEnded { "，汇总和结算见日志" } else { "" };
+        self.last_msg = Some(format!("{}: {}{}", title, reason, hint));
+        self.game_state = None;
+        self.msg_sender = None;
+        self.outbox.clear();
+        self.share_info = None;
+        self.my_id = None;
+        self.my_secret = None;
+        self.last_event_seq = None;
+        self.host_id = None;
+        self.pending_joins.clear();
+        self.pending_deposits.clear();
+        self.hand_ranks.clear();
+        self.stack_changes.clear();
+        self.valid_actions.clear();
+        self.pending_confirm = None;
+        self.offline = false;
+        self.all_in_equities.clear();
+        self.turn_deadline = None;
+        self.time_bank_active = false;
+        self.showdown_explanations.clear();
+        self.chat = ChatLog::default();
+    }
+
+    /// 处理网络任务发来的事件，更新会话状态
+    pub fn handle_network_event(&mut self, event: NetworkEvent) -> Vec<SessionEvent> {
+        match event {
+            NetworkEvent::Connected => self.log.push("已连接到服务器".to_string()),
+            NetworkEvent::ConnectFailed(reason) => {
+                self.last_msg = Some(reason);
+                // 房间内重新连接失败时回到离线状态，可以再次尝试
+                if self.game_state.is_some() {
+                    self.offline = true;
+                    self.msg_sender = None;
+                }
+            }
+            NetworkEvent::Sent(text) => self.log.push(format!("[SEND_TO_SERVER] {}", text)),
+            NetworkEvent::Received { text, msg } => {
+                self.log.push_recv(&text, msg.as_deref());
+                if let Some(msg) = msg {
+                    let events = self.handle_server_message(*msg);
+                    self.bookmark_game();
+                    return events;
+                }
+            }
+            NetworkEvent::Disconnected(reason) => {
+                // 标记为离线并丢弃发送器，避免输入的指令无声无息地丢失
+                self.last_msg = Some(reason);
+                self.log.push("与服务器的连接已断开".to_string());
+                self.offline = true;
+                self.msg_sender = None;
+                self.outbox.clear();
+                return vec![SessionEvent::Updated];
+            }
+            NetworkEvent::CheckedIn(Ok(games)) => {
+                self.games.update(games);
+                return vec![SessionEvent::Updated];
+            }
+            NetworkEvent::CheckedIn(Err(reason)) => self.log.push(format!("查询通信对局失败: {}", reason)),
+        }
+        vec![]
+    }
+
+    /// 处理从服务器收到的消息，并据此更新会话的状态，需要回复服务器的消息放入发送队列。
+    /// 游戏状态的变更统一由 `GameState::apply_event` 完成，这里只处理会话相关的部分。
+    pub fn handle_server_message(&mut self, msg: ServerMessage) -> Vec<SessionEvent> {
+        // 带序号的房间事件: 记下序号后按里面的事件处理
+        if let ServerMessage::Event { seq, event } = msg {
+            self.last_event_seq = Some(seq);
+            return self.handle_server_message(*event);
+        }
+        // 时钟同步的回复不影响界面，次数不够时继续同步
+        if let ServerMessage::ClockSync { client_time, server_time } = msg {
+            self.clock.sync(client_time, server_time, Utc::now());
+            if self.clock.needs_sample() {
+                self.outbox.push(ClientMessage::ClockSync { client_time: Utc::now() });
+            }
+            return vec![];
+        }
+        let mut events = vec![SessionEvent::Updated];
+        self.last_msg = None; // 收到任何消息都清除上一条错误
+
+        // 成功加入房间
+        if let ServerMessage::RoomJoined { your_id, your_secret, game_state, host_id, config } = &msg {
+            self.my_id = Some(*your_id);
+            self.my_secret = Some(*your_secret);
+            self.last_event_seq = None;
+            self.game_state = Some(game_state.clone());
+            self.host_id = Some(*host_id);
+
+            let playing_num = game_state.hand_player_order.len();
+            self.hand_ranks = vec![None; playing_num];
+            self.stack_changes.clear();
+            self.log.push(format!("牌桌设置：{}", config));
+
+            // 如果是房主，生成分享信息
+            if self.my_id == self.host_id {
+                let share_addr = self.server_addr.as_ref().cloned().unwrap_or_default();
+                self.share_info = Some(format!("分享信息: join {} {}，手机浏览器打开 http://{}/play/#{}",
+                                               share_addr, game_state.room_id, share_addr, game_state.room_id));
+            }
+            events.push(SessionEvent::EnteredRoom);
+            return events;
+        }
+
+        // 回到通信对局时，服务器先发来状态快照而不是 RoomJoined
+        if let ServerMessage::GameStateSnapshot(snapshot) = &msg
+            && self.game_state.is_none() && self.my_id.is_some() {
+            self.game_state = Some(snapshot.clone());
+            events.push(SessionEvent::EnteredRoom);
+        }
+
+        if let ServerMessage::RoomClosed { reason } = msg {
+            self.leave_room(reason);
+            events.push(SessionEvent::LeftRoom);
+            return events;
+        }
+
+        let Some(gs) = &mut self.game_state else {
+            match msg {
+                ServerMessage::Error { message } | ServerMessage::Info { message } => self.last_msg = Some(message),
+                ServerMessage::SpectatorsFull { max_spectators } => {
+                    self.last_msg = Some(format!("房间观战人数已满 (上限 {})，暂时无法加入", max_spectators));
+                }
+                _ => {}
+            }
+            return events;
+        };
+
+        // 需要在应用事件之前的状态上判断的更新
+        if let ServerMessage::PlayerUpdated { player } = &msg {
+            let already_seated = gs.seated_players.contains(&player.id)
+                && gs.players.get(&player.id).is_some_and(|p| p.seat_id == player.seat_id);
+            if player.state == PlayerState::Waiting && !already_seated {
+                self.log.push(format!("玩家 {} 已坐下准备游戏", player.nickname));
+            } else if player.state == PlayerState::SittingOut {
+                self.log.push(format!("玩家 {} 离席", player.nickname));
+            }
+            if self.my_id == Some(player.id) && gs.players.get(&player.id).is_some_and(|p| p.profile != player.profile) {
+                self.log.push(format!("已更新个人设置 (语言: {})", player.profile.locale));
+            }
+        }
+
+        gs.apply_event(&msg, self.my_id);
+
+        // 输掉摊牌的玩家依次选择亮牌或盖牌，轮到自己时提示
+        let showdown_turn_changed = matches!(msg, ServerMessage::Showdown { .. } | ServerMessage::HandShownOrMucked { .. });
+        match msg {
+            ServerMessage::HandStarted { variant, betting_structure, started_at, .. } => {
+                let deck = gs.duplicate.map_or(String::new(), |d| format!("，复式对局第 {} 副牌", d.hands));
+                self.log.push_at(started_at, format!("新的一局开始: {} ({}){}", variant, betting_structure, deck));
+                self.share_info = None; // 游戏开始后清除分享信息
+                events.push(SessionEvent::HandStarted);
+                self.all_in_equities.clear();
+                self.showdown_explanations.clear();
+                self.hand_ranks = vec![None; gs.hand_player_order.len()];
+                self.stack_changes.clear();
+                self.outbox.push(ClientMessage::GetMyHand);
+            }
+            // 重连后收到的快照可能已经是新的一局，按快照重建界面状态
+            ServerMessage::GameStateSnapshot(_) => {
+                self.valid_actions.clear();
+                self.pending_confirm = None;
+                self.all_in_equities.clear();
+                self.turn_deadline = None;
+                self.hand_ranks = vec![None; gs.hand_player_order.len()];
+                self.stack_changes.clear();
+            }
+            ServerMessage::PlayerHand { hands } => {
+                // 没有公共牌的玩法中，底牌本身就是完整的牌型
+                if let Some(idx) = gs.player_indices.get(&self.my_id.unwrap())
+                    && gs.variant.community_card_count() == 0 && hands.len() == gs.variant.hole_card_count() {
+                    self.hand_ranks[*idx] = Some(gs.variant.evaluate_high(&hands, &[]));
+                }
+            }
+            ServerMessage::PlayerActed { player_id, action, total_bet, at, .. } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    let text = match action {
+                        PlayerAction::Fold => "弃牌".to_string(),
+                        PlayerAction::Check => "过牌".to_string(),
+                        PlayerAction::Call => format!("跟注到 ${}", total_bet),
+                        PlayerAction::BetOrRaise(_) => format!("下注到 ${}", total_bet),
+                    };
+                    self.log.push_at(at, format!("玩家 {} {}", p.nickname, text));
+                }
+                if self.my_id == Some(player_id) {
+                    self.outbox.acknowledge();
+                }
+            }
+            ServerMessage::VoteStarted { vote, server_time } => {
+                self.clock.observe(server_time, Utc::now());
+                let proposer = gs.players.get(&vote.proposer).map_or("未知玩家", |p| &p.nickname);
+                self.log.push(format!(
+                    "玩家 {} 发起投票: {}，{} 前输入 `vote yes` 或 `vote no` 投票",
+                    proposer, describe_proposal(gs, &vote.proposal), self.clock.to_local(vote.deadline).with_timezone(&Local).format("%H:%M:%S"),
+                ));
+            }
+            ServerMessage::VoteCast { player_id, approve, .. } => {
+                if let (Some(p), Some(vote)) = (gs.players.get(&player_id), &gs.vote) {
+                    self.log.push(format!(
+                        "玩家 {} 投了{}票 (赞成 {}，反对 {}，共 {} 人)",
+                        p.nickname, if approve { "赞成" } else { "反对" }, vote.yes.len(), vote.no.len(), vote.electorate.len(),
+                    ));
+                }
+            }
+            ServerMessage::VoteEnded { proposal, passed, .. } => {
+                self.log.push(format!("投票{}: {}", if passed { "通过" } else { "未通过" }, describe_proposal(gs, &proposal)));
+            }
+            ServerMessage::HandVoided { .. } => {
+                self.log.push("本局已作废，投入的筹码已退还".to_string());
+            }
+            ServerMessage::BlindsChanged { small_blind, big_blind } => {
+                self.log.push(format!("盲注改为 ${}/${}", small_blind, big_blind));
+            }
+            ServerMessage::WinnerGuessed { player_id, target } => {
+                if let (Some(p), Some(t)) = (gs.players.get(&player_id), gs.players.get(&target)) {
+                    self.log.push(format!("观众 {} 猜 {} 赢下本局", p.nickname, t.nickname));
+                }
+            }
+            ServerMessage::GuessesScored { correct, .. } => {
+                let name = |id: &PlayerId| gs.players.get(id).map_or("已离开的观众".to_string(), |p| p.nickname.clone());
+                let correct: Vec<String> = correct.iter().map(name).collect();
+                if correct.is_empty() {
+                    self.log.push("本局没有观众猜中赢家".to_string());
+                } else {
+                    self.log.push(format!("猜中赢家的观众: {}", correct.join("、")));
+                }
+                let leaderboard: Vec<String> = gs.spectator_guesses.leaderboard().iter().take(5)
+                    .map(|(id, score)| format!("{} {}/{}", name(id), score.correct, score.total))
+                    .collect();
+                self.log.push(format!("观众排行榜: {}", leaderboard.join("，")));
+            }
+            ServerMessage::BombPotQueued => {
+                self.log.push(format!("下一局打炸弹底池，每人底注 ${}", gs.room_options.bomb_pot_ante));
+            }
+            ServerMessage::BombPotPosted { player_id, amount, .. } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    self.log.push(format!("玩家 {} 交炸弹底池底注 ${}", p.nickname, amount));
+                }
+            }
+            ServerMessage::StraddlePosted { player_id, amount, .. } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    self.log.push(format!("玩家 {} 抓位 ${}", p.nickname, amount));
+                }
+            }
+            ServerMessage::NextToAct { player_id, valid_actions, context } => {
+                self.pending_confirm = None;
+                self.turn_deadline = context.deadline.map(|deadline| (player_id, self.clock.to_local(deadline)));
+                if self.my_id == Some(player_id) {
+                    self.valid_actions = valid_actions;
+                    self.players_to_act = context.players_to_act;
+                } else {
+                    self.valid_actions.clear();
+                }
+            }
+            ServerMessage::TurnTimer { player_id, deadline, time_bank, server_time } => {
+                self.clock.observe(server_time, Utc::now());
+                self.turn_deadline = Some((player_id, self.clock.to_local(deadline)));
+                self.time_bank_active = time_bank;
+                if time_bank && let Some(p) = gs.players.get(&player_id) {
+                    self.log.push(format!("玩家 {} 启用了时间银行 ({} 秒)", p.nickname, p.time_bank));
+                }
+            }
+            ServerMessage::CardsDrawn { player_id, count, at } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    let text = if count == 0 { "没有换牌".to_string() } else { format!("换了 {} 张牌", count) };
+                    self.log.push_at(at, format!("玩家 {} {}", p.nickname, text));
+                }
+                if self.my_id == Some(player_id) {
+                    self.outbox.acknowledge();
+                    self.outbox.push(ClientMessage::GetMyHand);
+                }
+            }
+            // 更新玩家的牌型 (多块公共牌时只显示第一块上的牌型)
+            ServerMessage::CommunityCardsDealt { board: 0, .. } => {
+                let community_cards = gs.community_cards[0].iter().map_while(|card| {
+                    *card
+                }).collect::<Vec<_>>();
+                for (p_idx, player_card) in gs.player_cards.iter().enumerate() {
+                    let hole: Vec<Card> = player_card.iter().flatten().cloned().collect();
+                    if hole.len() == gs.variant.hole_card_count() && community_cards.len() >= 3 {
+                        self.hand_ranks[p_idx] = Some(gs.variant.evaluate_high(&hole, &community_cards));
+                    }
+                }
+            }
+            ServerMessage::Showdown { results, stacks, to_decide } => {
+                self.stack_changes = stacks;
+                if self.beginner_mode {
+                    self.showdown_explanations = gs.explain_showdown(&results).iter().map(|e| e.to_string()).collect();
+                    for line in self.showdown_explanations.iter() {
+                        self.log.push(line.clone());
+                    }
+                }
+                for result in results {
+                    if result.cards.is_none() && result.winnings == 0 && !to_decide.contains(&result.player_id)
+                        && let Some(p) = gs.players.get(&result.player_id) {
+                        self.log.push(format!("玩家 {} 盖牌", p.nickname));
+                    }
+                    if result.low_winnings > 0
+                        && let (Some(p), Some(low)) = (gs.players.get(&result.player_id), &result.low_hand) {
+                        self.log.push(format!(
+                            "玩家 {} 以{}赢得低牌奖池 ${}，高牌奖池 ${}",
+                            p.nickname, low, result.low_winnings, result.winnings - result.low_winnings,
+                        ));
+                    }
+                    if let (Some(p_idx), Some(hand_rank)) = (gs.player_indices.get(&result.player_id), result.hand_rank)
+                        && result.cards.is_some() {
+                        self.hand_ranks[*p_idx] = Some(hand_rank);
+                    }
+                }
+            }
+            ServerMessage::HandShownOrMucked { player_id, cards, hand_rank } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    match cards {
+                        Some(cards) => {
+                            let cards: Vec<String> = cards.iter().map(|c| self.suits.card(c)).collect();
+                            let rank = hand_rank.as_ref().map_or(String::new(), |r| format!(" ({})", r));
+                            self.log.push(format!("玩家 {} 亮牌 {}{}", p.nickname, cards.join(" "), rank));
+                        }
+                        None => self.log.push(format!("玩家 {} 盖牌", p.nickname)),
+                    }
+                }
+                if let (Some(&idx), Some(rank)) = (gs.player_indices.get(&player_id), hand_rank) {
+                    self.hand_ranks[idx] = Some(rank);
+                }
+            }
+            ServerMessage::RoomOptionsUpdated(options) => {
+                let on_off = |v: bool| if v { "开启" } else { "关闭" };
+                let bomb_pot = match (options.bomb_pot_ante, options.bomb_pot_every) {
+                    (0, _) => "关闭".to_string(),
+                    (ante, 0) => format!("底注 {} (房主安排时)", ante),
+                    (ante, 1) => format!("底注 {} (每局)", ante),
+                    (ante, every) => format!("底注 {} (每 {} 局)", ante, every),
+                };
+                let dealers_choice = if options.dealers_choice.is_empty() {
+                    "关闭".to_string()
+                } else {
+                    options.dealers_choice.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("、")
+                };
+                let rotation = match &options.rotation {
+                    Some(r) => {
+                        let variants = r.variants.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" → ");
+                        match r.hands_per_game {
+                            0 => format!("{} (每个玩法一圈)", variants),
+                            n => format!("{} (每个玩法 {} 局)", variants, n),
+                        }
+                    }
+                    None => "关闭".to_string(),
+                };
+                let tournament = match &options.tournament {
+                    Some(t) if t.level_secs > 0 => format!("每 {} 分钟升级 ({} 级)", t.level_secs / 60, t.levels.len()),
+                    Some(t) => format!("每 {} 局升级 ({} 级)", t.hands_per_level, t.levels.len()),
+                    None => "关闭".to_string(),
+                };
+                let sit_and_go = match &options.sit_and_go {
+                    Some(sng) => format!("买入 ${}，起始筹码 {}", sng.buy_in, sng.starting_stack),
+                    None => "关闭".to_string(),
+                };
+                let undo_grace = if options.undo_grace_ms > 0 {
+                    format!("{} 毫秒", options.undo_grace_ms)
+                } else {
+                    "关闭".to_string()
+                };
+                self.log.push(format!(
+                    "房间选项已更新：玩法 {}，下注结构 {}，庄家选择 {}，混合轮换 {}，亮出弃牌 {}，前注 {}，抓位 {}，炸弹底池 {}，双公共牌 {}，撤回窗口 {}，确认动作 {}，锦标赛 {}，Sit & Go {}，场地费 ${}，筹码账户 {}，加入审批 {}，观战上限 {}，自动弃牌 {}，行动计时 {}，时间银行 {}，通信对局 {}，民主模式 {}，观众猜赢家 {}，全下保险 {}，七二奖励 {}，防抽水离场 {}",
+                    options.variant, options.betting_structure, dealers_choice, rotation, on_off(options.reveal_folded_hands),
+                    match (options.ante, options.big_blind_ante) {
+                        (0, _) => "关闭".to_string(),
+                        (ante, true) => format!("${} (大盲交)", ante),
+                        (ante, false) => format!("${}", ante),
+                    }, options.straddle.map_or("关闭".to_string(), |p| p.to_string()), bomb_pot, on_off(options.double_board),
+                    undo_grace, on_off(options.confirm_actions), tournament, sit_and_go, options.session_fee, on_off(options.chip_accounts),
+                    on_off(options.require_join_approval),
+                    if options.max_spectators > 0 { options.max_spectators.to_string() } else { "不限".to_string() },
+                    if options.auto_fold_disabled { "禁止" } else { "允许" },
+                    if options.turn_timer_secs > 0 { format!("{} 秒", options.turn_timer_secs) } else { "关闭".to_string() },
+                    if options.time_bank_secs > 0 { format!("{} 秒", options.time_bank_secs) } else { "关闭".to_string() },
+                    on_off(options.correspondence), on_off(options.democracy), on_off(options.spectator_guessing),
+                    on_off(options.insurance),
+                    if options.seven_deuce_bounty > 0 { format!("${}", options.seven_deuce_bounty) } else { "关闭".to_string() },
+                    if options.rathole_window_mins > 0 { format!("{} 分钟", options.rathole_window_mins) } else { "关闭".to_string() },
+                ));
+            }
+            ServerMessage::PlayerJoined { player } => {
+                self.pending_joins.retain(|(id, _)| *id != player.id);
+            }
+            ServerMessage::DuplicateUpdated { duplicate } => match duplicate {
+                Some(d) => self.log.push(format!(
+                    "本桌加入了复式对局 {} (第 {} 桌)，之后每局按顺序使用和其他牌桌同样的牌。其他牌桌的房主输入 `duplicate join {}` 加入",
+                    d.match_id, d.table + 1, d.match_id,
+                )),
+                None => self.log.push("本桌退出了复式对局，恢复随机洗牌".to_string()),
+            },
+            ServerMessage::HostChanged { host_id } => {
+                self.host_id = Some(host_id);
+                if self.my_id == Some(host_id) {
+                    self.log.push("你成为了房主".to_string());
+                } else {
+                    let name = gs.players.get(&host_id).map_or_else(|| host_id.to_string(), |p| p.nickname.clone());
+                    self.log.push(format!("新房主是 {}", name));
+                    // 分享信息只显示给房主
+                    self.share_info = None;
+                }
+            }
+            ServerMessage::ChatBroadcast { player_id, text, ts } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    self.chat.push(player_id, p.nickname.clone(), text, ts);
+                }
+            }
+            ServerMessage::SeatsShuffled { .. } => {
+                self.log.push("房主随机重新分配了座位，并重新抽取了庄家位置".to_string());
+            }
+            ServerMessage::JoinRequested { player_id, nickname } => {
+                let msg = format!("玩家 {} 申请加入房间，输入 `admit {}` 批准或 `deny {}` 拒绝", nickname, nickname, nickname);
+                self.log.push(msg.clone());
+                self.last_msg = Some(msg);
+                self.pending_joins.push((player_id, nickname));
+            }
+            ServerMessage::DepositRequested { player_id, amount } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    let msg = format!("玩家 {} 申请存入 ${}，输入 `approve {}` 批准或 `reject {}` 拒绝", p.nickname, amount, p.nickname, p.nickname);
+                    self.log.push(msg.clone());
+                    self.last_msg = Some(msg);
+                }
+                self.pending_deposits.retain(|(id, _)| *id != player_id);
+                self.pending_deposits.push((player_id, amount));
+            }
+            ServerMessage::ChipsDeposited { player_id, amount, new_bank } => {
+                self.pending_deposits.retain(|(id, _)| *id != player_id);
+                if let Some(p) = gs.players.get(&player_id) {
+                    self.log.push(format!("房主批准了玩家 {} 存入 ${}，账户余额 ${}", p.nickname, amount, new_bank));
+                }
+            }
+            ServerMessage::HostLedgerUpdated { entry, .. } => {
+                if let Some(p) = gs.players.get(&entry.player_id) {
+                    match entry.kind {
+                        LedgerKind::Tip => self.log.push(format!("玩家 {} 支付了小费 ${}", p.nickname, entry.amount)),
+                        LedgerKind::SessionFee => self.log.push(format!("玩家 {} 支付了场地费 ${}", p.nickname, entry.amount)),
+                        LedgerKind::CashOut => {
+                            let net = (p.cashed_out + entry.amount) as i64 - p.buy_in as i64;
+                            self.log.push(format!("玩家 {} 兑现离场，带走 ${}，净输赢 {:+}", p.nickname, entry.amount, net));
+                        }
+                    }
+                }
+            }
+            ServerMessage::SessionSummary(summary) => {
+                self.log.push("--- 本次游戏汇总 ---".to_string());
+                for p in summary.players.iter() {
+                    self.log.push(format!(
+                        "{}: 筹码 ${}，胜 {}，负 {}，带入 ${} (单次最多 ${})，净输赢 {:+}",
+                        p.nickname, p.stack, p.wins, p.losses, p.buy_in, p.peak_buy_in, p.net,
+                    ));
+                    if p.cashed_out > 0 {
+                        self.log.push(format!("    已兑现离场 ${}", p.cashed_out));
+                    }
+                }
+                self.log.push(format!(
+                    "房主账本: 小费 ${}，场地费 ${}",
+                    summary.host_ledger.total(LedgerKind::Tip), summary.host_ledger.total(LedgerKind::SessionFee),
+                ));
+                // 汇总里的玩家可能已经离开房间，昵称从汇总中查找
+                let name = |id: &PlayerId| summary.players.iter().find(|p| p.player_id == *id).map_or_else(|| id.to_string(), |p| p.nickname.clone());
+                for s in summary.settlements.iter() {
+                    let to = s.to.as_ref().map_or_else(|| "房主账本".to_string(), name);
+                    self.log.push(format!("结算: {} 付给 {} ${}", name(&s.from), to, s.amount));
+                }
+                events.push(SessionEvent::ShowLog);
+            }
+            ServerMessage::AuditLog(entries) => {
+                self.log.push("--- 房主操作记录 ---".to_string());
+                let name = |id: &PlayerId| gs.players.get(id).map_or_else(|| id.to_string(), |p| p.nickname.clone());
+                for entry in entries.iter() {
+                    let action = match &entry.action {
+                        HostAction::StartHand => "开始新一局".to_string(),
+                        HostAction::ShuffleSeats => "重新分配座位".to_string(),
+                        HostAction::BombPotNextHand => "安排下一局打炸弹底池".to_string(),
+                        HostAction::SetRoomOption(option) => format!("修改房间选项 {:?}", option),
+                        HostAction::ApproveDeposit { player_id, amount } => format!("批准 {} 存入 ${}", name(player_id), amount),
+                        HostAction::RejectDeposit { player_id, amount } => format!("拒绝 {} 存入 ${}", name(player_id), amount),
+                        HostAction::ApproveJoin { nickname, .. } => format!("批准 {} 加入", nickname),
+                        HostAction::RejectJoin { nickname, .. } => format!("拒绝 {} 加入", nickname),
+                        HostAction::ExportTimeline => "导出房间时间线".to_string(),
+                        HostAction::SetDiscordChannel { enabled } => format!("{} Discord 通知", if *enabled { "开启" } else { "关闭" }),
+                        HostAction::HostTransferred { new_host_id } => format!("断开连接，房主转给 {}", name(new_host_id)),
+                        HostAction::EndSession => "结束本次游戏".to_string(),
+                        HostAction::KickPlayer { player_id } => format!("把 {} 移出房间", name(player_id)),
+                        HostAction::TransferHost { player_id } => format!("把房主交给 {}", name(player_id)),
+                        HostAction::SetDuplicate { match_id: Some(match_id) } => format!("加入复式对局 {}", match_id),
+                        HostAction::SetDuplicate { match_id: None } => "退出复式对局".to_string(),
+                    };
+                    self.log.push(format!(
+                        "[{}] {}: {}", entry.at.with_timezone(&Local).format("%m-%d %H:%M:%S"), name(&entry.host_id), action,
+                    ));
+                }
+                events.push(SessionEvent::ShowLog);
+            }
+            ServerMessage::BlindLevelRaised { level, small_blind, big_blind, level_ends_at, chip_race, server_time } => {
+                self.clock.observe(server_time, Utc::now());
+                let ends = level_ends_at.map_or(String::new(), |t| {
+                    format!("，{} 升到下一级", self.clock.to_local(t).with_timezone(&Local).format("%H:%M:%S"))
+                });
+                self.log.push(format!("盲注第 {} 级: ${}/${}{}", level + 1, small_blind, big_blind, ends));
+                for result in chip_race {
+                    if let Some(p) = gs.players.get(&result.player_id) {
+                        self.log.push(format!(
+                            "换色: 玩家 {} 的筹码 ${} -> ${}", p.nickname, result.old_stack, result.new_stack,
+                        ));
+                    }
+                }
+            }
+            ServerMessage::SitAndGoStarted { entrants, prize_pool } => {
+                self.log.push(format!("Sit & Go 开始: {} 名参赛者，奖池 ${}", entrants.len(), prize_pool));
+            }
+            ServerMessage::PlayerEliminated { player_id, place } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    self.log.push(format!("玩家 {} 被淘汰，获得第 {} 名", p.nickname, place));
+                }
+            }
+            ServerMessage::SitAndGoFinished { results } => {
+                self.log.push("--- Sit & Go 结束 ---".to_string());
+                for result in results {
+                    if let Some(p) = gs.players.get(&result.player_id) {
+                        self.log.push(format!("第 {} 名: {}，奖金 ${}", result.place, p.nickname, result.prize));
+                    }
+                }
+                events.push(SessionEvent::ShowLog);
+            }
+            ServerMessage::VariantChosen { player_id, variant } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    self.log.push(format!("庄家 {} 选择了下一局的玩法: {}", p.nickname, variant));
+                }
+            }
+            ServerMessage::RabbitHunt { player_id, cards } => {
+                let boards: Vec<String> = cards.iter()
+                    .map(|board| board.iter().map(|c| self.suits.card(c)).collect::<Vec<_>>().join(" "))
+                    .collect();
+                let nickname = gs.players.get(&player_id).map_or("未知玩家", |p| p.nickname.as_str());
+                self.log.push(format!("玩家 {} 查看了兔子牌: {}", nickname, boards.join(" | ")));
+            }
+            ServerMessage::VariantChanged { variant, hands, .. } => {
+                self.log.push(format!("混合轮换: 接下来 {} 局玩 {}", hands, variant));
+            }
+            ServerMessage::FoldedHandRevealed { player_id, cards } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    let cards_str: Vec<String> = cards.iter().map(|c| self.suits.card(c)).collect();
+                    self.log.push(format!("玩家 {} 亮出了弃掉的底牌 {}", p.nickname, cards_str.join(" ")));
+                }
+            }
+            ServerMessage::CardsShown { player_id, cards } => {
+                if let Some(p) = gs.players.get(&player_id) {
+                    let cards_str: Vec<String> = cards.iter().flatten().map(|c| self.suits.card(c)).collect();
+                    self.log.push(format!("玩家 {} 亮出了底牌 {}", p.nickname, cards_str.join(" ")));
+                }
+            }
+            ServerMessage::AllInHandsRevealed { hands } => {
+                let board: Vec<Card> = gs.community_cards.first().map_or(vec![], |b| b.iter().flatten().cloned().collect());
+                let cards: Vec<Vec<Card>> = hands.iter().map(|(_, cards)| cards.clone()).collect();
+                let equities = hand_equities(gs.variant, &cards, &board, EQUITY_SAMPLES);
+                self.all_in_equities = hands.iter().map(|(id, _)| *id).zip(equities).collect();
+                let text: Vec<String> = self.all_in_equities.iter()
+                    .filter_map(|(id, equity)| gs.players.get(id).map(|p| format!("{} {:.1}%", p.nickname, equity * 100.0)))
+                    .collect();
+                self.log.push(format!("全下摊牌，胜率: {}", text.join("、")));
+            }
+            ServerMessage::InsuranceOffered(offer) => {
+                let nickname = gs.players.get(&offer.buyer).map_or("未知玩家", |p| p.nickname.as_str());
+                self.log.push(format!(
+                    "{} 以 {:.1}% 的胜率领先，可以买保险，保额最多 ${} (保费 ${})",
+                    nickname, offer.equity_permille as f64 / 10.0, offer.max_cover, offer.premium(offer.max_cover),
+                ));
+                if self.my_id == Some(offer.buyer) {
+                    self.last_msg = Some(format!(
+                        "输入 insure <保额> 买保险 (最多 ${}，每 $100 保额约 ${} 保费)，insure off 不买",
+                        offer.max_cover, offer.premium(100),
+                    ));
+                }
+            }
+            ServerMessage::InsuranceDecided { player_id, policy } => {
+                let nickname = gs.players.get(&player_id).map_or("未知玩家", |p| p.nickname.as_str());
+                self.log.push(match policy {
+                    Some(policy) => format!("{} 买了保险: 保额 ${}，保费 ${}", nickname, policy.cover, policy.premium),
+                    None => format!("{} 没有买保险", nickname),
+                });
+            }
+            ServerMessage::InsuranceSettled { from, to, amount } => {
+                let name = |id| gs.players.get(id).map_or("未知玩家", |p: &Player| p.nickname.as_str());
+                self.log.push(format!("保险结算: {} 付给 {} ${}", name(&from), name(&to), amount));
+            }
+            ServerMessage::SevenDeuceBounty { winner, payments } => {
+                let name = |id| gs.players.get(id).map_or("未知玩家", |p: &Player| p.nickname.as_str());
+                let total: u32 = payments.iter().map(|(_, amount)| amount).sum();
+                self.log.push(format!("玩家 {} 用 7-2 赢下奖池，从 {} 名玩家处获得七二奖励 ${}", name(&winner), payments.len(), total));
+            }
+            ServerMessage::ActionPendingConfirm { action_id, action } => {
+                self.outbox.acknowledge();
+                self.pending_confirm = Some((action_id, action));
+            }
+            ServerMessage::Error { message } => {
+                self.outbox.reject();
+                self.last_msg = Some(message);
+            }
+            ServerMessage::Info { message } => self.last_msg = Some(message),
+            _ => {}
+        }
+        if showdown_turn_changed && self.my_showdown_turn() {
+            self.last_msg = Some("你的牌被压过了，输入 `show` 亮牌或 `muck` 盖牌".to_string());
+        }
+        events
+    }
+}
+
+/// 投票内容的描述
+pub fn describe_proposal(gs: &GameState, proposal: &VoteProposal) -> String {
+    match proposal {
+        VoteProposal::Kick(player_id) => {
+            format!("把 {} 移出房间", gs.players.get(player_id).map_or("未知玩家", |p| &p.nickname))
+        }
+        VoteProposal::VoidHand => "作废本局".to_string(),
+        VoteProposal::SetBlinds { small_blind, big_blind } => format!("盲注改为 ${}/${}", small_blind, big_blind),
+        VoteProposal::SetRoomOption(option) => format!("修改房间选项 {:?}", option),
+        VoteProposal::BombPotNextHand => "下一局打炸弹底池".to_string(),
+    }
+}
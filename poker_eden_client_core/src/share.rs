@@ -0,0 +1,61 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 房间分享的加入链接
+//!
+//! 加入链接的格式为 `poker-eden://<服务器地址:端口>/<房间ID>`，
+//! 登录界面可以直接输入 `join <加入链接> <昵称>`，手机扫码得到的也是这个链接。
+
+use poker_eden_core::RoomId;
+use std::str::FromStr;
+use uuid::Uuid;
+
+const SCHEME: &str = "poker-eden";
+
+/// 生成房间的加入链接
+pub fn join_url(server_addr: &str, room_id: RoomId) -> String {
+    format!("{}://{}/{}", SCHEME, server_addr, room_id)
+}
+
+/// 解析加入链接，返回服务器地址和房间ID
+pub fn parse_join_url(text: &str) -> Option<(String, RoomId)> {
+    let url = url::Url::parse(text).ok()?;
+    if url.scheme() != SCHEME {
+        return None;
+    }
+    let server_addr = format!("{}:{}", url.host_str()?, url.port()?);
+    let room_id = Uuid::from_str(url.path().trim_matches('/')).ok()?;
+    Some((server_addr, room_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_url_round_trip() {
+        let room_id = Uuid::new_v4();
+        let url = join_url("192.168.1.10:25917", room_id);
+        assert_eq!(parse_join_url(&url), Some(("192.168.1.10:25917".to_string(), room_id)));
+        assert_eq!(parse_join_url(&join_url("localhost:25917", room_id)), Some(("localhost:25917".to_string(), room_id)));
+
+        assert_eq!(parse_join_url(&format!("http://127.0.0.1:25917/{}", room_id)), None);
+        assert_eq!(parse_join_url(&format!("poker-eden://127.0.0.1/{}", room_id)), None);
+        assert_eq!(parse_join_url("poker-eden://127.0.0.1:25917/not-a-room"), None);
+    }
+
+}
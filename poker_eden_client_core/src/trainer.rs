@@ -0,0 +1,186 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 牌力训练小游戏
+//!
+//! 不需要连接服务器。随机出两种题目:
+//! - 找出 7 张牌中能组成的最大牌型，回答牌型序号 (1 为最大)，或直接写出组成最大牌型的 5 张牌
+//! - 同样的公共牌下比较两手底牌，回答 1、2，平局回答 0
+//!
+//! 统计答对的比例和平均答题时间。
+
+use poker_eden_core::*;
+use std::cmp::Ordering;
+use std::time::{Duration, Instant};
+
+/// 一道题
+#[derive(Debug, Clone)]
+pub enum Question {
+    /// 找出 7 张牌能组成的最大牌型
+    BestHand { cards: Vec<Card>, best: HandRank },
+    /// 比较两手底牌在同样的公共牌下谁更大
+    Compare { board: Vec<Card>, hands: [Vec<Card>; 2], result: Ordering },
+}
+
+impl Question {
+    /// 随机出一道题
+    pub fn random() -> Self {
+        let mut deck = generate_shuffled_deck();
+        if rand::random::<bool>() {
+            let cards: Vec<Card> = deck.drain(..7).collect();
+            let best = find_best_hand(&cards);
+            Question::BestHand { cards, best }
+        } else {
+            let board: Vec<Card> = deck.drain(..5).collect();
+            let hands = [deck.drain(..2).collect::<Vec<_>>(), deck.drain(..2).collect::<Vec<_>>()];
+            let rank = |hole: &Vec<Card>| GameVariant::TexasHoldem.evaluate_high(hole, &board);
+            let result = rank(&hands[0]).cmp(&rank(&hands[1]));
+            Question::Compare { board, hands, result }
+        }
+    }
+
+    /// 判断回答是否正确，无法识别的回答返回 None
+    pub fn check(&self, input: &str) -> Option<bool> {
+        let input = input.trim();
+        match self {
+            Question::BestHand { cards, best } => {
+                if let Ok(n) = input.parse::<usize>() {
+                    let category = HandCategory::ALL.iter().rev().nth(n.checked_sub(1)?)?;
+                    return Some(*category == best.category());
+                }
+                // 写出的 5 张牌必须都来自题目中的 7 张，并且组成最大牌型
+                let picked = input.split_whitespace().map(|s| s.parse::<Card>()).collect::<Result<Vec<_>, _>>().ok()?;
+                if picked.len() != 5 {
+                    return None;
+                }
+                Some(picked.iter().all(|c| cards.contains(c)) && find_best_hand(&picked) == *best)
+            }
+            Question::Compare { result, .. } => {
+                let answer = match input {
+                    "1" => Ordering::Greater,
+                    "2" => Ordering::Less,
+                    "0" | "=" => Ordering::Equal,
+                    _ => return None,
+                };
+                Some(answer == *result)
+            }
+        }
+    }
+
+    /// 正确答案的说明
+    pub fn solution(&self) -> String {
+        match self {
+            Question::BestHand { best, .. } => {
+                let n = HandCategory::ALL.iter().rev().position(|c| *c == best.category()).unwrap_or(0) + 1;
+                format!("{}. {}", n, best)
+            }
+            Question::Compare { board, hands, result } => {
+                let rank = |hole: &Vec<Card>| GameVariant::TexasHoldem.evaluate_high(hole, board);
+                let verdict = match result {
+                    Ordering::Greater => "1 更大",
+                    Ordering::Less => "2 更大",
+                    Ordering::Equal => "平局",
+                };
+                format!("{} ({} 对 {})", verdict, rank(&hands[0]), rank(&hands[1]))
+            }
+        }
+    }
+}
+
+/// 训练的进度和成绩
+pub struct Trainer {
+    pub question: Question,
+    asked_at: Instant,
+    pub answered: u32,
+    pub correct: u32,
+    total_time: Duration,
+    /// 上一题的结果，显示在题目下方
+    pub feedback: Option<(bool, String)>,
+}
+
+impl Trainer {
+    pub fn new(now: Instant) -> Self {
+        Self { question: Question::random(), asked_at: now, answered: 0, correct: 0, total_time: Duration::ZERO, feedback: None }
+    }
+
+    /// 回答当前题目，记录成绩并出下一题。无法识别的回答不计入成绩，返回 false
+    pub fn answer(&mut self, input: &str, now: Instant) -> bool {
+        let Some(correct) = self.question.check(input) else { return false };
+        self.answered += 1;
+        if correct {
+            self.correct += 1;
+        }
+        self.total_time += now.duration_since(self.asked_at);
+        self.feedback = Some((correct, self.question.solution()));
+        self.question = Question::random();
+        self.asked_at = now;
+        true
+    }
+
+    /// 答对的比例 (百分比)
+    pub fn accuracy(&self) -> f64 {
+        if self.answered == 0 { 0.0 } else { self.correct as f64 / self.answered as f64 * 100.0 }
+    }
+
+    /// 平均每题用时 (秒)
+    pub fn average_secs(&self) -> f64 {
+        if self.answered == 0 { 0.0 } else { self.total_time.as_secs_f64() / self.answered as f64 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cards(text: &str) -> Vec<Card> {
+        text.split_whitespace().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_best_hand_answers() {
+        let seven = cards("As Ks Qs Js 2d 3c Ts");
+        let question = Question::BestHand { best: find_best_hand(&seven), cards: seven };
+        // 皇家同花顺是序号 1
+        assert_eq!(question.check("1"), Some(true));
+        assert_eq!(question.check("2"), Some(false));
+        assert_eq!(question.check("As Ks Qs Js Ts"), Some(true));
+        // 牌型不是最大的，或者用了题目中没有的牌
+        assert_eq!(question.check("As Ks Qs Js 2d"), Some(false));
+        assert_eq!(question.check("Ah Ks Qs Js Ts"), Some(false));
+        assert_eq!(question.check("11"), None);
+        assert_eq!(question.check("As Ks"), None);
+    }
+
+    #[test]
+    fn test_compare_answers_and_scoring() {
+        let board = cards("2c 7d 9h Jc Kd");
+        let hands = [cards("Ah As"), cards("Kh Qs")];
+        let question = Question::Compare { board, hands, result: Ordering::Greater };
+        assert_eq!(question.check("1"), Some(true));
+        assert_eq!(question.check("0"), Some(false));
+        assert_eq!(question.check("x"), None);
+
+        let start = Instant::now();
+        let mut trainer = Trainer::new(start);
+        trainer.question = question;
+        assert!(!trainer.answer("x", start));
+        assert!(trainer.answer("1", start + Duration::from_secs(4)));
+        assert_eq!((trainer.answered, trainer.correct), (1, 1));
+        assert_eq!(trainer.average_secs(), 4.0);
+        assert!(trainer.feedback.as_ref().is_some_and(|(ok, text)| *ok && text.starts_with("1 更大")));
+    }
+}
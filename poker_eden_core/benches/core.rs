@@ -0,0 +1,133 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 牌力评估和游戏逻辑的基准测试
+//!
+//! 运行: `cargo bench -p poker_eden_core`
+//! 评估器重写和分池逻辑改动前后各跑一次，用 criterion 的报告对比结果。
+
+use std::hint::black_box;
+
+use chrono::Utc;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use poker_eden_core::testing::setup_table;
+use poker_eden_core::*;
+
+// 吞吐量测试中每批评估的手牌数
+const HANDS_PER_BATCH: usize = 1000;
+
+/// 随机抽取 `count` 张牌
+fn random_cards(count: usize) -> Vec<Card> {
+    generate_shuffled_deck().into_iter().take(count).collect()
+}
+
+/// 由 `choose` 决定每个玩家的动作，一直打到摊牌
+fn play_to_showdown(state: &mut GameState, choose: impl Fn(&GameState, PlayerId) -> PlayerAction) {
+    while state.phase != GamePhase::Showdown {
+        let player_id = state.current_player_id().expect("牌局进行中应该有行动玩家");
+        let action = choose(state, player_id);
+        state.handle_player_action(player_id, action, Utc::now()).expect("动作应该合法");
+    }
+}
+
+/// 跟注或过牌，用于模拟一局完整的牌
+fn call_or_check(state: &GameState, player_id: PlayerId) -> PlayerAction {
+    let idx = state.player_indices[&player_id];
+    if state.bets[idx] < state.max_bet { PlayerAction::Call } else { PlayerAction::Check }
+}
+
+/// 能加注就全下，否则跟注，让每个玩家以不同的筹码量全下
+fn shove(state: &GameState, player_id: PlayerId) -> PlayerAction {
+    let idx = state.player_indices[&player_id];
+    let stack = state.players[&player_id].stack;
+    if state.bets[idx] + stack > state.max_bet { PlayerAction::BetOrRaise(stack) } else { PlayerAction::Call }
+}
+
+fn bench_find_best_hand(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_best_hand");
+    for count in 5..=7 {
+        let cards = random_cards(count);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &cards, |b, cards| {
+            b.iter(|| find_best_hand(black_box(cards)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_seven_card_throughput(c: &mut Criterion) {
+    let hands: Vec<Vec<Card>> = (0..HANDS_PER_BATCH).map(|_| random_cards(7)).collect();
+    let mut group = c.benchmark_group("evaluate_7_cards");
+    group.throughput(Throughput::Elements(HANDS_PER_BATCH as u64));
+    group.bench_function("random_hands", |b| {
+        b.iter(|| {
+            for hand in &hands {
+                black_box(find_best_hand(black_box(hand)));
+            }
+        })
+    });
+    group.finish();
+}
+
+fn bench_distribute_pots(c: &mut Criterion) {
+    // 每个玩家的筹码都不同，全部全下后产生 n-1 个边池；
+    // 计时从开局到摊牌分完所有底池
+    let mut group = c.benchmark_group("distribute_pots");
+    for players in [3, 6, 10] {
+        let stacks: Vec<u32> = (1..=players).map(|i| i * 100).collect();
+        group.bench_with_input(BenchmarkId::new("all_in_side_pots", players), &stacks, |b, stacks| {
+            b.iter_batched(
+                || setup_table(stacks, RoomOptions::default()),
+                |mut state| {
+                    state.start_new_hand(Utc::now()).expect("应该可以开局");
+                    play_to_showdown(&mut state, shove);
+                    state
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_full_hand(c: &mut Criterion) {
+    // 所有玩家一路跟注或过牌到摊牌，覆盖发牌、每轮下注和比牌
+    let mut group = c.benchmark_group("full_hand");
+    for players in [2, 6, 10] {
+        let stacks = vec![1000; players];
+        group.bench_with_input(BenchmarkId::new("call_down", players), &stacks, |b, stacks| {
+            b.iter_batched(
+                || setup_table(stacks, RoomOptions::default()),
+                |mut state| {
+                    state.start_new_hand(Utc::now()).expect("应该可以开局");
+                    play_to_showdown(&mut state, call_or_check);
+                    state
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_find_best_hand,
+    bench_seven_card_throughput,
+    bench_distribute_pots,
+    bench_full_hand
+);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 把任意字节当作 JSON 解析成 `ClientMessage`: 解析不能 panic，
+//! 解析成功的消息序列化后再解析，结果必须一致。
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use poker_eden_core::ClientMessage;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(msg) = serde_json::from_slice::<ClientMessage>(data) else { return };
+    let json = serde_json::to_string(&msg).expect("解析出的消息应该能够序列化");
+    let decoded: ClientMessage = serde_json::from_str(&json).expect("序列化的消息应该能够重新解析");
+    assert_eq!(json, serde_json::to_string(&decoded).unwrap());
+});
@@ -0,0 +1,86 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 用任意字节生成牌桌和一串操作 (包括不该行动的玩家、不存在的玩家、错误阶段的动作)，
+//! 驱动 `GameState`: 任何输入都只能返回错误消息而不能 panic，且筹码始终守恒。
+
+#![no_main]
+
+use chrono::Utc;
+use libfuzzer_sys::arbitrary::{Result, Unstructured};
+use libfuzzer_sys::fuzz_target;
+use poker_eden_core::testing::{setup_table, total_chips};
+use poker_eden_core::*;
+use uuid::Uuid;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = run(&mut Unstructured::new(data));
+});
+
+fn run(u: &mut Unstructured) -> Result<()> {
+    let player_count = u.int_in_range(2..=10)?;
+    let stacks = (0..player_count).map(|_| u.int_in_range(0..=2000)).collect::<Result<Vec<u32>>>()?;
+    let variant = *u.choose(&[GameVariant::TexasHoldem, GameVariant::OmahaHiLo, GameVariant::FiveCardDraw])?;
+    let options = RoomOptions {
+        variant,
+        bomb_pot_ante: u.int_in_range(0..=50)?,
+        bomb_pot_every: u.int_in_range(0..=3)?,
+        double_board: u.arbitrary()?,
+        reveal_folded_hands: true,
+        dealers_choice: vec![GameVariant::TexasHoldem, GameVariant::FiveCardDraw],
+        ..Default::default()
+    };
+    let mut state = setup_table(&stacks, options);
+    let ids: Vec<PlayerId> = state.seated_players.iter().copied().collect();
+
+    // 小费会把筹码转入房主账本，计入总数
+    let chips = |state: &GameState| {
+        total_chips(state) + state.host_ledger.entries.iter().map(|e| e.amount as u64).sum::<u64>()
+    };
+    let expected = chips(&state);
+    let now = Utc::now();
+
+    while !u.is_empty() {
+        // 偶尔使用不在房间里的玩家
+        let player_id = match u.int_in_range(0..=ids.len())? {
+            i if i < ids.len() => ids[i],
+            _ => Uuid::new_v4(),
+        };
+        match u.int_in_range(0..=11)? {
+            0 => drop(state.start_new_hand(now)),
+            1 => drop(state.handle_player_action(player_id, PlayerAction::Fold, now)),
+            2 => drop(state.handle_player_action(player_id, PlayerAction::Check, now)),
+            3 => drop(state.handle_player_action(player_id, PlayerAction::Call, now)),
+            4 => drop(state.handle_player_action(player_id, PlayerAction::BetOrRaise(u.arbitrary()?), now)),
+            5 => drop(state.handle_discard(player_id, u.arbitrary()?, now)),
+            6 => drop(state.tick(now)),
+            7 => drop(state.reveal_folded_hand(player_id)),
+            8 => drop(state.request_dead_blind(player_id)),
+            9 => drop(state.tip_host(player_id, u.arbitrary()?)),
+            10 => drop(state.choose_variant(player_id, *u.choose(&[GameVariant::TexasHoldem, GameVariant::OmahaHiLo])?)),
+            _ => {
+                // 玩家掉线，由 tick 代为行动
+                if let Some(p) = state.players.get_mut(&player_id) {
+                    p.is_offline = true;
+                }
+                drop(state.tick(now));
+            }
+        }
+        assert_eq!(chips(&state), expected, "筹码不守恒");
+    }
+    Ok(())
+}
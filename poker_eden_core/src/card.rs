@@ -16,9 +16,12 @@
 // Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
 
 use rand::prelude::SliceRandom;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
 // --- 核心数据结构定义 ---
 
 /// 花色 (Suit)
@@ -81,6 +84,25 @@ pub enum HandRank {
     RoyalFlush,                                      // 皇家同花顺
 }
 
+/// 低牌 (Hi-Lo 玩法中的 Low)
+/// 只有五张点数互不相同且都不大于 8 的牌才有资格 (eight-or-better)，A 算作 1。
+/// 内部存储从大到小排列的五个点数。越小的低牌越好，因此 `Ord` 是反向比较的:
+/// 更好的低牌 "更大"，与 HandRank 的比较方向保持一致。
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct LowHand(pub [u8; 5]);
+
+impl Ord for LowHand {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.0.cmp(&self.0)
+    }
+}
+
+impl PartialOrd for LowHand {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 // --- 实现辅助功能 ---
 
 impl fmt::Display for Suit {
@@ -120,6 +142,61 @@ impl fmt::Display for Card {
     }
 }
 
+/// 无法解析的牌
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCardError(pub String);
+
+impl fmt::Display for ParseCardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "无法识别的牌: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseCardError {}
+
+/// 解析一张牌，支持点数在前的 ASCII 写法 (`As`、`Td`、`10c`)
+/// 和花色在前的符号写法 (`♠A`、`♥️K`，即 `Display` 的输出)，不区分大小写
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let err = || ParseCardError(text.to_string());
+        let parse_suit = |s: &str| match s.trim_end_matches('\u{fe0f}').to_lowercase().as_str() {
+            "s" | "♠" => Some(Suit::Spade),
+            "h" | "♥" => Some(Suit::Heart),
+            "c" | "♣" => Some(Suit::Club),
+            "d" | "♦" => Some(Suit::Diamond),
+            _ => None,
+        };
+        let parse_rank = Rank::from_symbol;
+
+        let text = text.trim();
+        let first = text.chars().next().ok_or_else(err)?;
+        let (rank, suit) = if first.is_ascii_alphanumeric() {
+            // 点数在前: 最后一个字符是花色
+            let (rank, suit) = text.split_at(text.char_indices().last().ok_or_else(err)?.0);
+            (parse_rank(rank), parse_suit(suit))
+        } else {
+            // 花色在前: 花色符号后面可能跟着 emoji 变体选择符
+            let rank_start = text.find(|c: char| c.is_ascii_alphanumeric()).ok_or_else(err)?;
+            (parse_rank(&text[rank_start..]), parse_suit(&text[..rank_start]))
+        };
+        match (rank, suit) {
+            (Some(rank), Some(suit)) => Ok(Card { rank, suit }),
+            _ => Err(err()),
+        }
+    }
+}
+
+impl fmt::Display for LowHand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ranks: Vec<String> = self.0.iter()
+            .map(|&v| if v == 1 { "A".to_string() } else { v.to_string() })
+            .collect();
+        write!(f, "低牌({})", ranks.join("-"))
+    }
+}
+
 impl fmt::Display for HandRank {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", match self {
@@ -137,28 +214,150 @@ impl fmt::Display for HandRank {
     }
 }
 
-// --- 随机牌组生成 ---
+/// 牌型类别 (不含点数)，从小到大排列
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub enum HandCategory {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+    RoyalFlush,
+}
 
-/// 创建一副完整的 52 张扑克牌
-fn create_deck() -> Vec<Card> {
-    let suits = [Suit::Spade, Suit::Heart, Suit::Club, Suit::Diamond];
-    let ranks = [
+impl Rank {
+    /// 所有点数，从小到大
+    pub const ALL: [Rank; 13] = [
         Rank::Two, Rank::Three, Rank::Four, Rank::Five, Rank::Six, Rank::Seven,
         Rank::Eight, Rank::Nine, Rank::Ten, Rank::Jack, Rank::Queen, Rank::King, Rank::Ace,
     ];
+
+    /// 解析点数的 ASCII 写法 (`A`、`T`、`10`、`9`)，不区分大小写
+    pub fn from_symbol(s: &str) -> Option<Rank> {
+        match s.to_uppercase().as_str() {
+            "2" => Some(Rank::Two),
+            "3" => Some(Rank::Three),
+            "4" => Some(Rank::Four),
+            "5" => Some(Rank::Five),
+            "6" => Some(Rank::Six),
+            "7" => Some(Rank::Seven),
+            "8" => Some(Rank::Eight),
+            "9" => Some(Rank::Nine),
+            "T" | "10" => Some(Rank::Ten),
+            "J" => Some(Rank::Jack),
+            "Q" => Some(Rank::Queen),
+            "K" => Some(Rank::King),
+            "A" => Some(Rank::Ace),
+            _ => None,
+        }
+    }
+}
+
+impl HandCategory {
+    /// 所有牌型类别，从小到大
+    pub const ALL: [HandCategory; 10] = [
+        HandCategory::HighCard,
+        HandCategory::OnePair,
+        HandCategory::TwoPair,
+        HandCategory::ThreeOfAKind,
+        HandCategory::Straight,
+        HandCategory::Flush,
+        HandCategory::FullHouse,
+        HandCategory::FourOfAKind,
+        HandCategory::StraightFlush,
+        HandCategory::RoyalFlush,
+    ];
+}
+
+impl HandRank {
+    pub fn category(&self) -> HandCategory {
+        match self {
+            HandRank::HighCard(..) => HandCategory::HighCard,
+            HandRank::OnePair(..) => HandCategory::OnePair,
+            HandRank::TwoPair(..) => HandCategory::TwoPair,
+            HandRank::ThreeOfAKind(..) => HandCategory::ThreeOfAKind,
+            HandRank::Straight(..) => HandCategory::Straight,
+            HandRank::Flush(..) => HandCategory::Flush,
+            HandRank::FullHouse(..) => HandCategory::FullHouse,
+            HandRank::FourOfAKind(..) => HandCategory::FourOfAKind,
+            HandRank::StraightFlush(..) => HandCategory::StraightFlush,
+            HandRank::RoyalFlush => HandCategory::RoyalFlush,
+        }
+    }
+
+    /// 决定牌型大小的主要点数 (如一对的点数、顺子和同花的最高牌)
+    pub fn high_rank(&self) -> Rank {
+        match *self {
+            HandRank::HighCard(r, ..)
+            | HandRank::OnePair(r, ..)
+            | HandRank::TwoPair(r, ..)
+            | HandRank::ThreeOfAKind(r, ..)
+            | HandRank::Straight(r)
+            | HandRank::Flush(r, ..)
+            | HandRank::FullHouse(r, _)
+            | HandRank::FourOfAKind(r, _)
+            | HandRank::StraightFlush(r) => r,
+            HandRank::RoyalFlush => Rank::Ace,
+        }
+    }
+}
+
+impl fmt::Display for HandCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            HandCategory::HighCard => "高牌",
+            HandCategory::OnePair => "一对",
+            HandCategory::TwoPair => "两对",
+            HandCategory::ThreeOfAKind => "三条",
+            HandCategory::Straight => "顺子",
+            HandCategory::Flush => "同花",
+            HandCategory::FullHouse => "葫芦",
+            HandCategory::FourOfAKind => "四条",
+            HandCategory::StraightFlush => "同花顺",
+            HandCategory::RoyalFlush => "皇家同花顺",
+        })
+    }
+}
+
+// --- 随机牌组生成 ---
+
+/// 创建一副完整的 52 张扑克牌
+pub(crate) fn create_deck() -> Vec<Card> {
+    let suits = [Suit::Spade, Suit::Heart, Suit::Club, Suit::Diamond];
     let mut deck = Vec::with_capacity(52);
     for &suit in &suits {
-        for &rank in &ranks {
+        for rank in Rank::ALL {
             deck.push(Card { rank, suit });
         }
     }
     deck
 }
 
+/// 返回一副洗好的完整牌堆 (52 张)
+pub fn generate_shuffled_deck() -> Vec<Card> {
+    let mut deck = create_deck();
+    let mut rng = rand::rng();
+    for _ in 0..3 {
+        deck.shuffle(&mut rng);
+    }
+    deck
+}
+
+/// 按种子洗好的完整牌堆 (52 张)，同一个种子总是得到同样的顺序
+pub fn seeded_deck(seed: u64) -> Vec<Card> {
+    let mut deck = create_deck();
+    deck.shuffle(&mut StdRng::seed_from_u64(seed));
+    deck
+}
+
 /// 从一副新牌中随机生成并返回 2*k+5 张牌
 pub fn generate_random_hand(k_players: usize) -> Vec<Card> {
     // 德州扑克通常支持 2 到 10 名玩家
-    assert!(k_players >= 2 && k_players <= 10, "Number of players must be between 2 and 10.");
+    assert!((2..=10).contains(&k_players), "Number of players must be between 2 and 10.");
 
     let mut deck = create_deck();
     let mut rng = rand::rng();
@@ -179,9 +378,9 @@ pub fn generate_random_hand(k_players: usize) -> Vec<Card> {
 
     // 发公共牌 (Community Cards)
     deck.pop(); // 烧掉一张牌 (Flop burn)
-    for i in (2 * k_players)..(2 * k_players + 3) {
+    for slot in cards.iter_mut().skip(2 * k_players).take(3) {
         if let Some(card) = deck.pop() {
-            cards[i] = card;
+            *slot = card;
         }
     }
     deck.pop(); // 再烧掉一张牌 (Turn burn)
@@ -205,18 +404,16 @@ pub fn generate_random_hand(k_players: usize) -> Vec<Card> {
 /// 如果牌数少于 5 或多于 7，则会 panic。
 pub fn find_best_hand(all_cards: &[Card]) -> HandRank {
     let card_count = all_cards.len();
-    assert!(card_count >= 5 && card_count <= 7, "牌数必须在5到7张之间");
+    assert!((5..=7).contains(&card_count), "牌数必须在5到7张之间");
 
     if card_count == 5 {
         return evaluate_5_card_hand(all_cards);
     }
 
-    // 通过生成所有5张牌的组合来找到最佳手牌。
+    // 通过枚举所有5张牌的组合来找到最佳手牌。
     // 这是唯一确保正确性的方法，因为贪心算法（如移除最小的牌）可能会破坏顺子或同花。
-    let combinations = get_combinations(all_cards, 5);
-
-    combinations.into_iter()
-        .map(|hand| evaluate_5_card_hand(&hand))
+    Combinations::<5>::new(card_count)
+        .map(|idx| evaluate_5_card_hand(&idx.map(|i| all_cards[i])))
         .max() // HandRank 派生了 Ord，可以直接找到最大的
         .unwrap() // 因为我们知道至少会有一个组合，所以 unwrap 是安全的
 }
@@ -227,7 +424,7 @@ fn evaluate_5_card_hand(hand: &[Card]) -> HandRank {
 
     let mut cards = hand.to_vec();
     // 从大到小排序，方便处理
-    cards.sort_by(|a, b| b.rank.cmp(&a.rank));
+    cards.sort_by_key(|c| std::cmp::Reverse(c.rank));
     let ranks: Vec<Rank> = cards.iter().map(|c| c.rank).collect();
 
     // 1. 检查同花和同花顺
@@ -297,32 +494,139 @@ fn evaluate_5_card_hand(hand: &[Card]) -> HandRank {
     }
 }
 
-/// 辅助函数：从一个切片中生成所有大小为 k 的组合
-fn get_combinations<T: Clone>(data: &[T], k: usize) -> Vec<Vec<T>> {
-    if k == 0 {
-        return vec![vec![]];
-    }
-    if data.len() < k {
-        return vec![];
+/// 奥马哈规则: 必须恰好使用 2 张底牌和 3 张公共牌组成 5 张牌
+fn omaha_combinations<'a>(hole: &'a [Card], board: &'a [Card]) -> impl Iterator<Item = [Card; 5]> + 'a {
+    assert!(hole.len() >= 2 && board.len() >= 3, "奥马哈至少需要2张底牌和3张公共牌");
+
+    Combinations::<2>::new(hole.len()).flat_map(move |h| {
+        Combinations::<3>::new(board.len()).map(move |b| {
+            [hole[h[0]], hole[h[1]], board[b[0]], board[b[1]], board[b[2]]]
+        })
+    })
+}
+
+/// 按奥马哈规则找出最佳高牌 (恰好使用 2 张底牌和 3 张公共牌)
+///
+/// # Panics
+/// 如果底牌少于 2 张或公共牌少于 3 张，则会 panic。
+pub fn find_best_omaha_hand(hole: &[Card], board: &[Card]) -> HandRank {
+    omaha_combinations(hole, board)
+        .map(|hand| evaluate_5_card_hand(&hand))
+        .max()
+        .unwrap()
+}
+
+/// 按奥马哈规则找出最佳低牌 (8 或更小)，没有合格的低牌时返回 None
+///
+/// # Panics
+/// 如果底牌少于 2 张或公共牌少于 3 张，则会 panic。
+pub fn find_best_omaha_low(hole: &[Card], board: &[Card]) -> Option<LowHand> {
+    omaha_combinations(hole, board)
+        .filter_map(|hand| evaluate_5_card_low(&hand))
+        .max() // LowHand 的 Ord 是反向的，max 即为最小的低牌
+}
+
+/// 低牌中的点数，A 算作最小的 1
+fn low_value(rank: Rank) -> u8 {
+    match rank {
+        Rank::Ace => 1,
+        _ => rank as u8 + 2,
     }
+}
 
-    let mut result = vec![];
-    let (first, rest) = data.split_at(1);
+/// 评估一手 5 张牌能否组成 8 或更小的低牌
+/// 顺子和同花不影响低牌，但对子会使其失去资格。
+fn evaluate_5_card_low(hand: &[Card]) -> Option<LowHand> {
+    assert_eq!(hand.len(), 5, "评估的牌必须是5张");
 
-    // 包含第一个元素的组合
-    let mut combinations_with_first = get_combinations(rest, k - 1);
-    for combo in &mut combinations_with_first {
-        combo.insert(0, first[0].clone());
+    let mut values: Vec<u8> = hand.iter().map(|c| low_value(c.rank)).collect();
+    values.sort_unstable_by(|a, b| b.cmp(a));
+    if values[0] > 8 || values.windows(2).any(|w| w[0] == w[1]) {
+        return None;
     }
-    result.append(&mut combinations_with_first);
+    Some(LowHand([values[0], values[1], values[2], values[3], values[4]]))
+}
+
+/// 德州扑克中，已知的公共牌加上 `unknown` 张未知的牌 (底牌和未发的公共牌)，
+/// 还有可能凑成哪些牌型 (从中任取 5 张)
+pub fn possible_hand_categories(board: &[Card], unknown: usize) -> Vec<HandCategory> {
+    const SUITS: [Suit; 4] = [Suit::Spade, Suit::Heart, Suit::Club, Suit::Diamond];
+    let ranks: Vec<Rank> = create_deck().into_iter().take(13).map(|c| c.rank).collect();
+    let rank_count = |r: Rank| board.iter().filter(|c| c.rank == r).count();
+    // 凑成某个点数至少 n 张还缺几张
+    let missing = |r: Rank, n: usize| n.saturating_sub(rank_count(r));
+    // 所有五连张的点数 (包括 A-2-3-4-5)，最后一个是 10-J-Q-K-A
+    let mut windows: Vec<Vec<Rank>> = vec![vec![Rank::Ace, Rank::Two, Rank::Three, Rank::Four, Rank::Five]];
+    windows.extend(ranks.windows(5).map(|w| w.to_vec()));
+    let straight_missing = |w: &[Rank], suit: Option<Suit>| {
+        w.iter()
+            .filter(|&&r| !board.iter().any(|c| c.rank == r && suit.is_none_or(|s| c.suit == s)))
+            .count()
+    };
+    let pairs = || ranks.iter().flat_map(|&a| ranks.iter().filter(move |&&b| b != a).map(move |&b| (a, b)));
+    let (royal, others) = windows.split_last().unwrap();
+
+    HandCategory::ALL
+        .into_iter()
+        .filter(|category| match category {
+            HandCategory::HighCard => true,
+            HandCategory::OnePair => ranks.iter().any(|&r| missing(r, 2) <= unknown),
+            HandCategory::TwoPair => pairs().any(|(a, b)| missing(a, 2) + missing(b, 2) <= unknown),
+            HandCategory::ThreeOfAKind => ranks.iter().any(|&r| missing(r, 3) <= unknown),
+            HandCategory::Straight => windows.iter().any(|w| straight_missing(w, None) <= unknown),
+            HandCategory::Flush => SUITS
+                .iter()
+                .any(|&s| 5usize.saturating_sub(board.iter().filter(|c| c.suit == s).count()) <= unknown),
+            HandCategory::FullHouse => pairs().any(|(a, b)| missing(a, 3) + missing(b, 2) <= unknown),
+            HandCategory::FourOfAKind => ranks.iter().any(|&r| missing(r, 4) <= unknown),
+            HandCategory::StraightFlush => others
+                .iter()
+                .any(|w| SUITS.iter().any(|&s| straight_missing(w, Some(s)) <= unknown)),
+            HandCategory::RoyalFlush => SUITS.iter().any(|&s| straight_missing(royal, Some(s)) <= unknown),
+        })
+        .collect()
+}
+
+/// 辅助迭代器：按字典序枚举从 n 个元素中取 K 个的所有下标组合
+///
+/// 下标保存在栈上的数组中，枚举过程不分配内存 (7 取 5 只有 21 种组合)。
+struct Combinations<const K: usize> {
+    n: usize,
+    indices: [usize; K],
+    done: bool,
+}
 
-    // 不包含第一个元素的组合
-    if data.len() > k {
-        let mut combinations_without_first = get_combinations(rest, k);
-        result.append(&mut combinations_without_first);
+impl<const K: usize> Combinations<K> {
+    fn new(n: usize) -> Self {
+        let mut indices = [0; K];
+        for (i, idx) in indices.iter_mut().enumerate() {
+            *idx = i;
+        }
+        Combinations { n, indices, done: K > n }
     }
+}
+
+impl<const K: usize> Iterator for Combinations<K> {
+    type Item = [usize; K];
 
-    result
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let current = self.indices;
+
+        // 从右往左找到第一个还能增大的下标，增大它并把右边的下标依次重置为紧挨着它的值
+        match (0..K).rev().find(|&i| self.indices[i] < self.n - K + i) {
+            Some(i) => {
+                self.indices[i] += 1;
+                for j in i + 1..K {
+                    self.indices[j] = self.indices[j - 1] + 1;
+                }
+            }
+            None => self.done = true,
+        }
+        Some(current)
+    }
 }
 
 // --- 单元测试 ---
@@ -339,6 +643,23 @@ mod tests {
         Card { rank, suit }
     }
 
+    #[test]
+    fn test_parse_card() {
+        assert_eq!("As".parse(), Ok(card(Ace, Spade)));
+        assert_eq!("td".parse(), Ok(card(Ten, Diamond)));
+        assert_eq!("10C".parse(), Ok(card(Ten, Club)));
+        assert_eq!("♥K".parse(), Ok(card(King, Heart)));
+        assert!("Ax".parse::<Card>().is_err());
+        assert!("1s".parse::<Card>().is_err());
+        assert!("".parse::<Card>().is_err());
+        assert!("♠".parse::<Card>().is_err());
+
+        // Display 的输出可以解析回原来的牌
+        for c in create_deck() {
+            assert_eq!(c.to_string().parse(), Ok(c));
+        }
+    }
+
     // --- 5张牌评估测试 ---
     #[test]
     fn test_royal_flush() {
@@ -459,6 +780,20 @@ mod tests {
     }
 
     // --- 牌力比较测试 ---
+    #[test]
+    fn test_combinations_enumerate_all_index_sets() {
+        let combos: Vec<[usize; 5]> = Combinations::<5>::new(7).collect();
+        assert_eq!(combos.len(), 21);
+        assert_eq!(combos.first(), Some(&[0, 1, 2, 3, 4]));
+        assert_eq!(combos.last(), Some(&[2, 3, 4, 5, 6]));
+        // 字典序严格递增，说明没有重复的组合
+        assert!(combos.windows(2).all(|w| w[0] < w[1]));
+
+        assert_eq!(Combinations::<3>::new(5).count(), 10);
+        assert_eq!(Combinations::<5>::new(5).count(), 1);
+        assert_eq!(Combinations::<5>::new(4).count(), 0);
+    }
+
     #[test]
     fn test_rank_comparison() {
         let full_house_kings = HandRank::FullHouse(King, Two);
@@ -471,4 +806,50 @@ mod tests {
         assert!(full_house_kings > full_house_queens); // K葫芦 > Q葫芦
         assert!(flush_king_high > flush_queen_high); // K同花 > Q同花
     }
+
+    // --- 奥马哈高低评估测试 ---
+    #[test]
+    fn test_omaha_must_use_two_hole_cards() {
+        // 公共牌有四张黑桃，但只有一张黑桃底牌，不能组成同花
+        let hole = [card(Ace, Spade), card(King, Heart), card(Queen, Heart), card(Two, Club)];
+        let board = [card(Three, Spade), card(Seven, Spade), card(Nine, Spade), card(Jack, Spade), card(Four, Diamond)];
+        assert!(!matches!(find_best_omaha_hand(&hole, &board), HandRank::Flush(..)));
+
+        let hole = [card(Ace, Spade), card(King, Spade), card(Queen, Heart), card(Two, Club)];
+        assert!(matches!(find_best_omaha_hand(&hole, &board), HandRank::Flush(Ace, ..)));
+    }
+
+    #[test]
+    fn test_omaha_low_qualifies() {
+        let hole = [card(Ace, Spade), card(Two, Heart), card(King, Heart), card(King, Club)];
+        let board = [card(Three, Spade), card(Seven, Diamond), card(Eight, Spade), card(Jack, Spade), card(Queen, Diamond)];
+        assert_eq!(find_best_omaha_low(&hole, &board), Some(LowHand([8, 7, 3, 2, 1])));
+
+        // 公共牌中只有两张小牌，无法组成低牌
+        let board = [card(Three, Spade), card(Seven, Diamond), card(Nine, Spade), card(Jack, Spade), card(Queen, Diamond)];
+        assert_eq!(find_best_omaha_low(&hole, &board), None);
+    }
+
+    #[test]
+    fn test_low_hand_comparison() {
+        // 越小的低牌越好
+        assert!(LowHand([5, 4, 3, 2, 1]) > LowHand([6, 4, 3, 2, 1]));
+        assert!(LowHand([7, 5, 4, 3, 2]) > LowHand([8, 4, 3, 2, 1]));
+    }
+
+    #[test]
+    fn test_possible_hand_categories() {
+        // 河牌圈没有对子、没有三张同花的公共牌: 葫芦、四条、同花都不可能
+        let board = [card(Two, Spade), card(Seven, Heart), card(Nine, Club), card(Jack, Diamond), card(King, Spade)];
+        let possible = possible_hand_categories(&board, 2);
+        assert!(possible.contains(&HandCategory::Straight));
+        assert!(possible.contains(&HandCategory::ThreeOfAKind));
+        assert!(!possible.contains(&HandCategory::Flush));
+        assert!(!possible.contains(&HandCategory::FullHouse));
+        assert!(!possible.contains(&HandCategory::FourOfAKind));
+        assert!(!possible.contains(&HandCategory::StraightFlush));
+
+        // 翻牌前所有牌型都有可能
+        assert_eq!(possible_hand_categories(&[], 7).len(), HandCategory::ALL.len());
+    }
 }
@@ -0,0 +1,70 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 消息压缩
+//!
+//! 客户端在连接地址上加 `?compress=deflate` 表示支持压缩。之后服务器把 JSON 超过
+//! `COMPRESS_THRESHOLD` 字节的消息 (通常是 RoomJoined 和 GameStateSnapshot) 用 deflate 压缩后
+//! 以二进制帧发送，较小的消息仍然是文本帧。没有声明支持压缩的客户端只会收到文本帧。
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+/// 连接地址上声明支持压缩的查询参数
+pub const COMPRESS_QUERY: &str = "compress=deflate";
+
+/// 超过这个字节数的消息才压缩，小消息压缩后省不了多少，反而多花 CPU
+pub const COMPRESS_THRESHOLD: usize = 1024;
+
+/// 解压后的消息最多的字节数，防止恶意构造的压缩数据耗尽内存
+pub const MAX_INFLATED_BYTES: usize = 16 * 1024 * 1024;
+
+/// 压缩一条 JSON 消息
+pub fn deflate(json: &str) -> Vec<u8> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    // 写入 Vec 不会失败
+    let _ = encoder.write_all(json.as_bytes());
+    encoder.finish().unwrap_or_default()
+}
+
+/// 解压一条二进制帧中的 JSON 消息
+pub fn inflate(bytes: &[u8]) -> std::io::Result<String> {
+    let mut json = String::new();
+    let read = DeflateDecoder::new(bytes).take(MAX_INFLATED_BYTES as u64 + 1).read_to_string(&mut json)?;
+    if read > MAX_INFLATED_BYTES {
+        return Err(std::io::Error::other("解压后的消息过大"));
+    }
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::ServerMessage;
+    use crate::state::GameState;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let json = serde_json::to_string(&ServerMessage::GameStateSnapshot(GameState::default())).unwrap();
+        let compressed = deflate(&json);
+        assert!(compressed.len() < json.len());
+        assert_eq!(inflate(&compressed).unwrap(), json);
+        assert!(inflate(b"not deflate").is_err());
+    }
+}
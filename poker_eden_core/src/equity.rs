@@ -0,0 +1,204 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 胜率计算
+//!
+//! 全下摊牌时，根据已经亮出的底牌和公共牌计算每位玩家的胜率，供观众界面显示。
+//! 也可以计算一手德州扑克底牌对抗一个起手牌范围的胜率。
+
+use crate::card::*;
+use crate::range::HandRange;
+use crate::state::GameVariant;
+use rand::prelude::{IndexedRandom, SliceRandom};
+
+/// 剩余的公共牌不超过这个张数时穷举所有可能，否则随机模拟
+const MAX_EXACT_UNKNOWN: usize = 2;
+
+/// 计算每位玩家在剩余公共牌发完后能赢得的奖池份额的期望 (平分的奖池按比例计入)
+///
+/// 剩余的公共牌不超过 2 张时穷举所有发牌结果，否则随机模拟 `samples` 次。
+/// 高低玩法中高牌和低牌各占半个奖池，没有合格的低牌时高牌赢得整个奖池。
+/// 不考虑边池，结果之和为 1。
+pub fn hand_equities(variant: GameVariant, hands: &[Vec<Card>], board: &[Card], samples: usize) -> Vec<f64> {
+    let mut totals = vec![0.0; hands.len()];
+    if hands.is_empty() {
+        return totals;
+    }
+    let mut deck = create_deck();
+    deck.retain(|c| !board.contains(c) && !hands.iter().any(|h| h.contains(c)));
+    let unknown = variant.community_card_count().saturating_sub(board.len()).min(deck.len());
+
+    let add = |runout: &[Card], totals: &mut Vec<f64>| {
+        let mut full_board = board.to_vec();
+        full_board.extend_from_slice(runout);
+        for (total, share) in totals.iter_mut().zip(showdown_shares(variant, hands, &full_board)) {
+            *total += share;
+        }
+    };
+
+    let count = if unknown <= MAX_EXACT_UNKNOWN {
+        let runouts: Vec<Vec<Card>> = match unknown {
+            0 => vec![vec![]],
+            1 => deck.iter().map(|c| vec![*c]).collect(),
+            _ => (0..deck.len())
+                .flat_map(|i| (i + 1..deck.len()).map(move |j| (i, j)))
+                .map(|(i, j)| vec![deck[i], deck[j]])
+                .collect(),
+        };
+        for runout in runouts.iter() {
+            add(runout, &mut totals);
+        }
+        runouts.len()
+    } else {
+        let mut rng = rand::rng();
+        for _ in 0..samples {
+            let (runout, _) = deck.partial_shuffle(&mut rng, unknown);
+            add(runout, &mut totals);
+        }
+        samples
+    };
+    totals.iter().map(|t| t / count.max(1) as f64).collect()
+}
+
+/// 计算一手德州扑克底牌对抗范围内随机一手牌的胜率 (平局按一半计入)
+///
+/// 范围内与底牌或公共牌冲突的组合会被排除，剩下的组合出现的概率相同。
+/// 公共牌已经发完时穷举范围内的组合，否则随机模拟 `samples` 次。
+/// 范围内没有可能的组合时返回 None。
+pub fn range_equity(hand: &[Card], range: &HandRange, board: &[Card], samples: usize) -> Option<f64> {
+    let variant = GameVariant::TexasHoldem;
+    let combos: Vec<&[Card; 2]> = range.combos().iter()
+        .filter(|combo| !combo.iter().any(|c| hand.contains(c) || board.contains(c)))
+        .collect();
+    if combos.is_empty() {
+        return None;
+    }
+    let unknown = variant.community_card_count().saturating_sub(board.len());
+    let share = |villain: &[Card; 2], full_board: &[Card]| {
+        showdown_shares(variant, &[hand.to_vec(), villain.to_vec()], full_board)[0]
+    };
+
+    if unknown == 0 {
+        let total: f64 = combos.iter().map(|combo| share(combo, board)).sum();
+        return Some(total / combos.len() as f64);
+    }
+    let mut deck = create_deck();
+    deck.retain(|c| !hand.contains(c) && !board.contains(c));
+    let mut rng = rand::rng();
+    let mut total = 0.0;
+    for _ in 0..samples {
+        let villain = *combos.choose(&mut rng)?;
+        let mut rest: Vec<Card> = deck.iter().filter(|c| !villain.contains(c)).cloned().collect();
+        let (runout, _) = rest.partial_shuffle(&mut rng, unknown);
+        let mut full_board = board.to_vec();
+        full_board.extend_from_slice(runout);
+        total += share(villain, &full_board);
+    }
+    Some(total / samples.max(1) as f64)
+}
+
+/// 一种发牌结果下每位玩家分到的奖池份额
+fn showdown_shares(variant: GameVariant, hands: &[Vec<Card>], board: &[Card]) -> Vec<f64> {
+    let highs: Vec<HandRank> = hands.iter().map(|h| variant.evaluate_high(h, board)).collect();
+    let lows: Vec<Option<LowHand>> = hands.iter().map(|h| variant.evaluate_low(h, board)).collect();
+    let best_low = lows.iter().flatten().max();
+    let high_pot = if best_low.is_some() { 0.5 } else { 1.0 };
+
+    let mut shares = vec![0.0; hands.len()];
+    if let Some(best_high) = highs.iter().max() {
+        let winners = highs.iter().filter(|h| *h == best_high).count() as f64;
+        for (share, high) in shares.iter_mut().zip(highs.iter()) {
+            if high == best_high {
+                *share += high_pot / winners;
+            }
+        }
+    }
+    if let Some(best_low) = best_low {
+        let winners = lows.iter().filter(|l| l.as_ref() == Some(best_low)).count() as f64;
+        for (share, low) in shares.iter_mut().zip(lows.iter()) {
+            if low.as_ref() == Some(best_low) {
+                *share += 0.5 / winners;
+            }
+        }
+    }
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(rank: Rank, suit: Suit) -> Card {
+        Card::new(rank, suit)
+    }
+
+    #[test]
+    fn test_exact_equity_on_the_turn() {
+        // 两对对抗只差一张 T 的顺子听牌: 44 张剩余的牌中有 4 张 T
+        let board = [card(Rank::Ace, Suit::Spade), card(Rank::King, Suit::Diamond), card(Rank::Seven, Suit::Club), card(Rank::Two, Suit::Heart)];
+        let hands = vec![
+            vec![card(Rank::Ace, Suit::Heart), card(Rank::King, Suit::Spade)],
+            vec![card(Rank::Queen, Suit::Heart), card(Rank::Jack, Suit::Heart)],
+        ];
+        let equities = hand_equities(GameVariant::TexasHoldem, &hands, &board, 0);
+        assert!((equities[1] - 4.0 / 44.0).abs() < 1e-9);
+        assert!((equities[0] + equities[1] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_split_pot_on_complete_board() {
+        // 公共牌是皇家同花顺，双方平分
+        let board = [Rank::Ace, Rank::King, Rank::Queen, Rank::Jack, Rank::Ten].map(|r| card(r, Suit::Spade));
+        let hands = vec![
+            vec![card(Rank::Two, Suit::Heart), card(Rank::Three, Suit::Heart)],
+            vec![card(Rank::Four, Suit::Club), card(Rank::Five, Suit::Club)],
+        ];
+        assert_eq!(hand_equities(GameVariant::TexasHoldem, &hands, &board, 0), vec![0.5, 0.5]);
+    }
+
+    #[test]
+    fn test_sampled_preflop_equity() {
+        // AA 对 KK 翻牌前约 82% 对 18%
+        let hands = vec![
+            vec![card(Rank::Ace, Suit::Spade), card(Rank::Ace, Suit::Heart)],
+            vec![card(Rank::King, Suit::Spade), card(Rank::King, Suit::Heart)],
+        ];
+        let equities = hand_equities(GameVariant::TexasHoldem, &hands, &[], 5000);
+        assert!((0.76..0.88).contains(&equities[0]), "{:?}", equities);
+        assert!((equities[0] + equities[1] - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_range_equity() {
+        let hand = [card(Rank::Ace, Suit::Spade), card(Rank::Ace, Suit::Heart)];
+        // 河牌已发完: 对手是 KK 时必胜，范围里的 AA 只剩一种组合且平分
+        let board = [card(Rank::Two, Suit::Club), card(Rank::Seven, Suit::Diamond), card(Rank::Nine, Suit::Heart),
+            card(Rank::Jack, Suit::Spade), card(Rank::Three, Suit::Club)];
+        let kings: HandRange = "KK".parse().unwrap();
+        assert_eq!(range_equity(&hand, &kings, &board, 0), Some(1.0));
+        let range: HandRange = "AA, KK".parse().unwrap();
+        assert_eq!(range_equity(&hand, &range, &board, 0), Some((6.0 + 0.5) / 7.0));
+
+        // 翻牌前 AA 对 KK 约 82%
+        let equity = range_equity(&hand, &kings, &[], 5000).unwrap();
+        assert!((0.76..0.88).contains(&equity), "{}", equity);
+
+        // 范围内的组合都和底牌、公共牌冲突
+        let board = [card(Rank::Ace, Suit::Club), card(Rank::Ace, Suit::Diamond), card(Rank::Two, Suit::Club)];
+        assert_eq!(range_equity(&hand, &"AA".parse().unwrap(), &board, 1000), None);
+    }
+}
@@ -0,0 +1,393 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+use crate::locale::{Describe, Locale};
+use crate::message::ServerMessage;
+use crate::state::GameVariant;
+use std::fmt;
+
+/// 游戏逻辑的错误
+///
+/// 核心库的公开 API 对非法输入 (不该行动的玩家、错误的阶段、不存在的玩家等)
+/// 和不一致的内部状态都返回错误而不是 panic，一条畸形的消息不会拖垮承载多个房间的服务器。
+/// 错误通过 `Display` 渲染成发给玩家的提示。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    /// 玩家不存在
+    PlayerNotFound,
+    /// 玩家没有座位
+    NotSeated,
+    /// 玩家不在本局的行动顺序中
+    NotInHand,
+    /// 上一局还没有结束
+    HandInProgress,
+    /// 没有进行中的牌局
+    NoHandInProgress,
+    /// 还没有轮到该玩家行动
+    NotYourTurn,
+    /// 还没有轮到该玩家换牌
+    NotYourDraw,
+    /// 换牌阶段只能换牌
+    DrawRoundPending,
+    /// 不是换牌阶段
+    NotDrawRound,
+    /// 面对下注不能过牌
+    MustCall { amount: u32 },
+    /// 下注额超过了剩余筹码
+    BetExceedsStack { stack: u32 },
+    /// 下注额小于大盲注
+    BetBelowBigBlind { big_blind: u32 },
+    /// 加注额小于最小加注
+    RaiseTooSmall { min: u32 },
+    /// 底池限注下，下注或加注额超过了底池上限
+    RaiseAbovePotLimit { max: u32 },
+    /// 换牌序号重复或越界
+    InvalidDiscard { hole_card_count: usize },
+    /// 房主没有开启亮出弃牌
+    RevealDisabled,
+    /// 本局还没结束，不能亮出弃牌
+    RevealBeforeShowdown,
+    /// 本局没有弃牌
+    NotFolded,
+    /// 不是摊牌后轮到自己选择亮牌或盖牌的玩家
+    NotYourShowdownTurn,
+    /// 只有其他人都弃牌后赢下底池的玩家才能主动亮牌
+    NotUncontestedWinner,
+    /// 要亮出的底牌位置无效
+    InvalidCardPosition,
+    /// 本局还没结束，不能查看兔子牌
+    RabbitHuntBeforeShowdown,
+    /// 公共牌已经全部发出 (或本玩法没有公共牌)，没有兔子牌可看
+    NoRabbitCards,
+    /// 等待领先的玩家决定是否购买全下保险
+    InsurancePending,
+    /// 现在没有给该玩家的保险报价
+    NoInsuranceOffer,
+    /// 保额超过了报价的上限
+    InsuranceCoverTooLarge { max: u32 },
+    /// 房主没有开启庄家选择模式
+    DealersChoiceDisabled,
+    /// 房主没有设置炸弹底池的底注，不能安排炸弹底池
+    BombPotAnteNotSet,
+    /// 只有庄家可以选择玩法
+    NotDealer,
+    /// 房主没有允许该玩法
+    VariantNotAllowed(GameVariant),
+    /// 本局的参与者不能在牌局中离座
+    LeaveSeatDuringHand,
+    /// 本局的参与者不能在牌局中兑现离场
+    CashOutDuringHand,
+    /// Sit & Go 的筹码是比赛筹码，不能兑现
+    CashOutInSitAndGo,
+    /// 房主禁止了自动弃牌
+    AutoFoldDisabled,
+    /// 自动弃牌的百分位超出范围
+    InvalidAutoFold,
+    /// 牌局中不能给小费
+    TipDuringHand,
+    /// 小费金额无效
+    InvalidTip,
+    /// 牌局中不能重新分配座位
+    ShuffleDuringHand,
+    /// 没有错过盲注
+    NoMissedBlinds,
+    /// 筹码输光的玩家不能直接回到座位，需要重新入座带入筹码
+    SitInWithoutChips,
+    /// 房主没有开启抓位
+    StraddleDisabled,
+    /// 头像太长或含有空白、控制字符
+    InvalidAvatar { max: usize },
+    /// 聊天消息是空的
+    EmptyChat,
+    /// 聊天消息太长
+    ChatTooLong { max: usize },
+    /// 筹码账户余额不足
+    InsufficientBank { available: u32 },
+    /// 离座后不久重新入座，带入的筹码少于离座时的筹码 (抽水离场)
+    RatholeMinimum { min: u32, minutes: u32 },
+    /// 带入的筹码少于房间的最低带入
+    BuyInTooSmall { min: u32 },
+    /// 带入的筹码多于房间的最高带入
+    BuyInTooLarge { max: u32 },
+    /// 创建房间时的座位数不合理
+    InvalidSeatCount { max: u8 },
+    /// 创建房间时的带入范围不合理
+    InvalidBuyInRange,
+    /// Sit & Go 已经开始，不再接受报名
+    SitAndGoStarted,
+    /// Sit & Go 进行中不能修改比赛设置
+    SitAndGoInProgress,
+    /// Sit & Go 的奖金比例为空或加起来不是 100%
+    InvalidSitAndGoPayouts,
+    /// Sit & Go 的起始筹码必须大于 0
+    InvalidSitAndGoStack,
+    /// Sit & Go 的买入太大，奖池会超出上限
+    SitAndGoBuyInTooLarge { max: u32 },
+    /// 盲注无效
+    InvalidBlinds,
+    /// 锦标赛模式下盲注由盲注结构决定
+    TournamentBlinds,
+    /// 房主没有开启民主模式
+    DemocracyDisabled,
+    /// 民主模式下房主不能单独修改房间选项
+    DemocracyRequiresVote,
+    /// 已经有投票在进行中
+    VoteInProgress,
+    /// 没有进行中的投票 (或投票已经结束)
+    NoActiveVote,
+    /// 只有发起投票时在线入座的玩家可以投票
+    NotElector,
+    /// 已经投过票了
+    AlreadyVoted,
+    /// 房主没有开启观众猜赢家
+    GuessingDisabled,
+    /// 本局的玩家不能参与猜赢家
+    PlayerInHand,
+    /// 只能猜本局还没有弃牌的玩家
+    InvalidGuess,
+    /// 本局已经猜过了
+    AlreadyGuessed,
+    /// 底牌已经亮出，本局不再接受竞猜
+    GuessingClosed,
+    /// 只有房主可以执行的操作
+    NotHost,
+    /// 房主不能移出自己
+    KickSelf,
+    /// 民主模式下移出玩家需要发起投票
+    KickRequiresVote,
+    /// 要移交给的玩家已经是房主
+    AlreadyHost,
+    /// 只能把房主交给在线的玩家
+    TransferToOffline,
+    /// 复式对局不存在或牌桌已满
+    DuplicateUnavailable,
+    /// 没有可以行动的玩家 (内部状态不一致)
+    NoNextPlayer,
+    /// 牌堆里的牌不够了 (内部状态不一致)
+    DeckExhausted,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameError::PlayerNotFound => write!(f, "玩家不存在"),
+            GameError::NotSeated => write!(f, "玩家还没有入座"),
+            GameError::NotInHand => write!(f, "你不在本局牌局中"),
+            GameError::HandInProgress => write!(f, "本局还没有结束"),
+            GameError::NoHandInProgress => write!(f, "当前没有进行中的牌局"),
+            GameError::NotYourTurn => write!(f, "当前不该你行动"),
+            GameError::NotYourDraw => write!(f, "当前不该你换牌"),
+            GameError::DrawRoundPending => write!(f, "当前是换牌阶段，请先换牌"),
+            GameError::NotDrawRound => write!(f, "当前不是换牌阶段"),
+            GameError::MustCall { amount } => write!(f, "当前有人下注 {}，你至少要下注和他相等", amount),
+            GameError::BetExceedsStack { stack } => write!(f, "你只能下注你剩余的筹码 {} 或更少", stack),
+            GameError::BetBelowBigBlind { big_blind } => write!(f, "你只能下注大盲注 {} 或更多", big_blind),
+            GameError::RaiseTooSmall { min } => write!(f, "你只能加注 {} 或更多", min),
+            GameError::RaiseAbovePotLimit { max } => write!(f, "底池限注，你最多只能投入 {}", max),
+            GameError::InvalidDiscard { hole_card_count } => {
+                write!(f, "换牌序号无效，只能选择 1 到 {} 之间不重复的牌", hole_card_count)
+            }
+            GameError::RevealDisabled => write!(f, "房主未开启亮出弃牌的选项"),
+            GameError::RevealBeforeShowdown => write!(f, "只能在本局结束后亮出弃牌"),
+            GameError::NotFolded => write!(f, "你本局没有弃牌"),
+            GameError::NotYourShowdownTurn => write!(f, "现在没有轮到你选择亮牌或盖牌"),
+            GameError::NotUncontestedWinner => write!(f, "只有其他人都弃牌后赢下底池的玩家可以亮牌"),
+            GameError::InvalidCardPosition => write!(f, "要亮出的底牌位置无效"),
+            GameError::RabbitHuntBeforeShowdown => write!(f, "只能在本局结束后查看兔子牌"),
+            GameError::NoRabbitCards => write!(f, "本局的公共牌已经全部发出，没有兔子牌"),
+            GameError::InsurancePending => write!(f, "正在等待领先的玩家决定是否购买保险"),
+            GameError::NoInsuranceOffer => write!(f, "现在没有可以购买的保险"),
+            GameError::InsuranceCoverTooLarge { max } => write!(f, "保额不能超过 {}", max),
+            GameError::DealersChoiceDisabled => write!(f, "房主未开启庄家选择模式"),
+            GameError::BombPotAnteNotSet => write!(f, "请先设置炸弹底池的底注"),
+            GameError::NotDealer => write!(f, "只有庄家可以选择下一局的玩法"),
+            GameError::VariantNotAllowed(variant) => write!(f, "房主没有允许 {} 玩法", variant),
+            GameError::LeaveSeatDuringHand => write!(f, "本局结束后才能离座"),
+            GameError::CashOutDuringHand => write!(f, "本局结束后才能兑现离场"),
+            GameError::CashOutInSitAndGo => write!(f, "Sit & Go 的比赛筹码不能兑现"),
+            GameError::AutoFoldDisabled => write!(f, "房主禁止了自动弃牌"),
+            GameError::InvalidAutoFold => write!(f, "自动弃牌的百分位只能是 0 到 100"),
+            GameError::TipDuringHand => write!(f, "本局结束后才能给小费"),
+            GameError::InvalidTip => write!(f, "小费必须大于 0 且不超过你的筹码"),
+            GameError::ShuffleDuringHand => write!(f, "只能在两局之间重新分配座位"),
+            GameError::NoMissedBlinds => write!(f, "你没有错过盲注，无需补交"),
+            GameError::SitInWithoutChips => write!(f, "你没有筹码了，请重新入座带入筹码"),
+            GameError::StraddleDisabled => write!(f, "房主未开启抓位"),
+            GameError::InvalidAvatar { max } => write!(f, "头像最多 {} 个字符，不能含有空白", max),
+            GameError::EmptyChat => write!(f, "不能发送空消息"),
+            GameError::ChatTooLong { max } => write!(f, "消息不能超过 {} 个字", max),
+            GameError::InsufficientBank { available } => {
+                write!(f, "入座失败：带入筹码不能超过账户余额 {}，请先申请存入筹码", available)
+            }
+            GameError::RatholeMinimum { min, minutes } => {
+                write!(f, "入座失败：离座后 {} 分钟内重新入座，至少要带入离座时的筹码 {}", minutes, min)
+            }
+            GameError::BuyInTooSmall { min } => write!(f, "入座失败：至少要带入 {} 筹码", min),
+            GameError::BuyInTooLarge { max } => write!(f, "入座失败：最多只能带入 {} 筹码", max),
+            GameError::InvalidSeatCount { max } => write!(f, "创建房间失败：座位数必须在 2 到 {} 之间", max),
+            GameError::InvalidBuyInRange => write!(f, "创建房间失败：最高带入不能低于最低带入或大盲注"),
+            GameError::SitAndGoStarted => write!(f, "入座失败：Sit & Go 已经开始，不再接受报名"),
+            GameError::SitAndGoInProgress => write!(f, "Sit & Go 进行中，不能修改比赛设置"),
+            GameError::InvalidSitAndGoPayouts => write!(f, "Sit & Go 的奖金比例加起来必须是 100%"),
+            GameError::InvalidSitAndGoStack => write!(f, "Sit & Go 的起始筹码必须大于 0"),
+            GameError::SitAndGoBuyInTooLarge { max } => write!(f, "Sit & Go 的买入最多为 {}", max),
+            GameError::InvalidBlinds => write!(f, "盲注无效：小盲必须大于 0 且不超过大盲"),
+            GameError::TournamentBlinds => write!(f, "锦标赛模式下盲注由盲注结构决定"),
+            GameError::DemocracyDisabled => write!(f, "房主未开启民主模式"),
+            GameError::DemocracyRequiresVote => write!(f, "民主模式下修改房间选项需要发起投票"),
+            GameError::VoteInProgress => write!(f, "已经有一个投票在进行中"),
+            GameError::NoActiveVote => write!(f, "当前没有进行中的投票"),
+            GameError::NotElector => write!(f, "只有发起投票时在线入座的玩家可以投票"),
+            GameError::AlreadyVoted => write!(f, "你已经投过票了"),
+            GameError::GuessingDisabled => write!(f, "房主未开启观众猜赢家"),
+            GameError::PlayerInHand => write!(f, "本局的玩家不能参与猜赢家"),
+            GameError::InvalidGuess => write!(f, "只能猜本局还没有弃牌的玩家"),
+            GameError::AlreadyGuessed => write!(f, "你本局已经猜过了"),
+            GameError::GuessingClosed => write!(f, "底牌已经亮出，本局不再接受竞猜"),
+            GameError::NotHost => write!(f, "只有房主可以执行此操作"),
+            GameError::KickSelf => write!(f, "房主不能移出自己"),
+            GameError::KickRequiresVote => write!(f, "民主模式下移出玩家需要发起投票 (vote kick <昵称>)"),
+            GameError::AlreadyHost => write!(f, "你已经是房主了"),
+            GameError::TransferToOffline => write!(f, "只能把房主交给在线的玩家"),
+            GameError::DuplicateUnavailable => write!(f, "复式对局不存在或牌桌已满"),
+            GameError::NoNextPlayer => write!(f, "没有可以行动的下一个玩家"),
+            GameError::DeckExhausted => write!(f, "牌堆里的牌不够了"),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+/// 英文的错误提示，发给选择了英文的玩家；中文与 `Display` 相同
+impl Describe for GameError {
+    fn describe(&self, locale: Locale) -> String {
+        if locale == Locale::Chinese {
+            return self.to_string();
+        }
+        match self {
+            GameError::PlayerNotFound => "Player not found".to_string(),
+            GameError::NotSeated => "You are not seated".to_string(),
+            GameError::NotInHand => "You are not in this hand".to_string(),
+            GameError::HandInProgress => "The current hand has not finished".to_string(),
+            GameError::NoHandInProgress => "There is no hand in progress".to_string(),
+            GameError::NotYourTurn => "It is not your turn to act".to_string(),
+            GameError::NotYourDraw => "It is not your turn to draw".to_string(),
+            GameError::DrawRoundPending => "This is a draw round, please draw first".to_string(),
+            GameError::NotDrawRound => "This is not a draw round".to_string(),
+            GameError::MustCall { amount } => format!("There is a bet of {}, you must at least call it", amount),
+            GameError::BetExceedsStack { stack } => format!("You can bet at most your remaining stack of {}", stack),
+            GameError::BetBelowBigBlind { big_blind } => format!("You must bet at least the big blind of {}", big_blind),
+            GameError::RaiseTooSmall { min } => format!("You must raise at least {}", min),
+            GameError::RaiseAbovePotLimit { max } => format!("Pot limit: you can put in at most {}", max),
+            GameError::InvalidDiscard { hole_card_count } => {
+                format!("Invalid discard, choose distinct cards between 1 and {}", hole_card_count)
+            }
+            GameError::RevealDisabled => "The host has not enabled revealing folded hands".to_string(),
+            GameError::RevealBeforeShowdown => "Folded hands can only be revealed after the hand".to_string(),
+            GameError::NotFolded => "You did not fold this hand".to_string(),
+            GameError::NotYourShowdownTurn => "It is not your turn to show or muck".to_string(),
+            GameError::NotUncontestedWinner => "Only the player who won after everyone else folded can show cards".to_string(),
+            GameError::InvalidCardPosition => "Invalid hole card position to show".to_string(),
+            GameError::RabbitHuntBeforeShowdown => "The rabbit hunt is only available after the hand".to_string(),
+            GameError::NoRabbitCards => "The whole board was dealt, there are no rabbit cards".to_string(),
+            GameError::InsurancePending => "Waiting for the leading player to decide on insurance".to_string(),
+            GameError::NoInsuranceOffer => "There is no insurance to buy".to_string(),
+            GameError::InsuranceCoverTooLarge { max } => format!("The cover cannot exceed {}", max),
+            GameError::DealersChoiceDisabled => "The host has not enabled dealer's choice".to_string(),
+            GameError::BombPotAnteNotSet => "Set a bomb pot ante first".to_string(),
+            GameError::NotDealer => "Only the dealer can choose the next game".to_string(),
+            GameError::VariantNotAllowed(variant) => format!("The host has not allowed {}", variant.describe(locale)),
+            GameError::LeaveSeatDuringHand => "You can leave your seat after the hand".to_string(),
+            GameError::CashOutDuringHand => "You can cash out after the hand".to_string(),
+            GameError::CashOutInSitAndGo => "Sit & Go tournament chips cannot be cashed out".to_string(),
+            GameError::AutoFoldDisabled => "The host has disabled auto-fold".to_string(),
+            GameError::InvalidAutoFold => "The auto-fold percentile must be between 0 and 100".to_string(),
+            GameError::TipDuringHand => "You can tip after the hand".to_string(),
+            GameError::InvalidTip => "The tip must be positive and no more than your stack".to_string(),
+            GameError::ShuffleDuringHand => "Seats can only be shuffled between hands".to_string(),
+            GameError::NoMissedBlinds => "You have not missed any blinds".to_string(),
+            GameError::SitInWithoutChips => "You have no chips left; take a seat again with a new stack".to_string(),
+            GameError::StraddleDisabled => "The host has not enabled straddles".to_string(),
+            GameError::InvalidAvatar { max } => format!("The avatar must be at most {} characters without spaces", max),
+            GameError::EmptyChat => "Cannot send an empty message".to_string(),
+            GameError::ChatTooLong { max } => format!("Messages cannot exceed {} characters", max),
+            GameError::InsufficientBank { available } => {
+                format!("Cannot sit: the buy-in exceeds your balance of {}, please request a deposit first", available)
+            }
+            GameError::RatholeMinimum { min, minutes } => {
+                format!("Cannot sit: within {} minutes of leaving you must bring at least the {} chips you left with", minutes, min)
+            }
+            GameError::BuyInTooSmall { min } => format!("Cannot sit: the minimum buy-in is {}", min),
+            GameError::BuyInTooLarge { max } => format!("Cannot sit: the maximum buy-in is {}", max),
+            GameError::InvalidSeatCount { max } => format!("Cannot create room: the seat count must be between 2 and {}", max),
+            GameError::InvalidBuyInRange => "Cannot create room: the maximum buy-in cannot be below the minimum buy-in or the big blind".to_string(),
+            GameError::SitAndGoStarted => "Cannot sit: the Sit & Go has already started".to_string(),
+            GameError::SitAndGoInProgress => "The Sit & Go settings cannot be changed during the tournament".to_string(),
+            GameError::InvalidSitAndGoPayouts => "The Sit & Go payouts must add up to 100%".to_string(),
+            GameError::InvalidSitAndGoStack => "The Sit & Go starting stack must be positive".to_string(),
+            GameError::SitAndGoBuyInTooLarge { max } => format!("The Sit & Go buy-in can be at most {}", max),
+            GameError::InvalidBlinds => "Invalid blinds: the small blind must be positive and no more than the big blind".to_string(),
+            GameError::TournamentBlinds => "In tournament mode the blinds follow the blind structure".to_string(),
+            GameError::DemocracyDisabled => "The host has not enabled democracy mode".to_string(),
+            GameError::DemocracyRequiresVote => "In democracy mode room options are changed by vote".to_string(),
+            GameError::VoteInProgress => "A vote is already in progress".to_string(),
+            GameError::NoActiveVote => "There is no vote in progress".to_string(),
+            GameError::NotElector => "Only players seated and online when the vote started can vote".to_string(),
+            GameError::AlreadyVoted => "You have already voted".to_string(),
+            GameError::GuessingDisabled => "The host has not enabled winner guessing".to_string(),
+            GameError::PlayerInHand => "Players in the hand cannot guess the winner".to_string(),
+            GameError::InvalidGuess => "You can only guess a player still in the hand".to_string(),
+            GameError::AlreadyGuessed => "You have already guessed this hand".to_string(),
+            GameError::GuessingClosed => "Hole cards have been revealed; guessing is closed for this hand".to_string(),
+            GameError::NotHost => "Only the host can do that".to_string(),
+            GameError::KickSelf => "The host cannot kick themselves".to_string(),
+            GameError::KickRequiresVote => "In democracy mode kicking a player needs a vote (vote kick <nickname>)".to_string(),
+            GameError::AlreadyHost => "You are already the host".to_string(),
+            GameError::TransferToOffline => "The host role can only go to an online player".to_string(),
+            GameError::DuplicateUnavailable => "The duplicate match does not exist or is full".to_string(),
+            GameError::NoNextPlayer => "There is no next player to act".to_string(),
+            GameError::DeckExhausted => "The deck has run out of cards".to_string(),
+        }
+    }
+}
+
+impl From<GameError> for ServerMessage {
+    fn from(error: GameError) -> Self {
+        ServerMessage::Error { message: error.to_string() }
+    }
+}
+
+/// 游戏逻辑的返回值: 成功时是需要发送的消息，失败时是错误
+pub type GameResult<T = Vec<ServerMessage>> = Result<T, GameError>;
+
+/// 把游戏逻辑的返回值转换成要发送的消息，错误转换成 `ServerMessage::Error`
+pub trait IntoMessages {
+    fn into_messages(self) -> Vec<ServerMessage>;
+
+    /// 和 `into_messages` 相同，但错误提示使用收到它的玩家的语言
+    fn into_localized_messages(self, locale: Locale) -> Vec<ServerMessage>;
+}
+
+impl IntoMessages for GameResult {
+    fn into_messages(self) -> Vec<ServerMessage> {
+        self.unwrap_or_else(|e| vec![e.into()])
+    }
+
+    fn into_localized_messages(self, locale: Locale) -> Vec<ServerMessage> {
+        self.unwrap_or_else(|e| vec![ServerMessage::Error { message: e.describe(locale) }])
+    }
+}
@@ -0,0 +1,940 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+use crate::card::Card;
+use crate::equity::hand_equities;
+use crate::locale::{describe_cards, Describe, Locale};
+use crate::message::{AuditEntry, HostAction, PlayerActionType, ServerMessage};
+use crate::state::*;
+use crate::RoomId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 游戏事件: 服务器广播的状态变更消息，只是 `ServerMessage` 的别名
+///
+/// 客户端同步、牌局历史和回放都通过 `GameState::apply_event` 把事件应用到净化后的状态上。
+/// 服务器处理玩家动作、前注和炸弹底池底注时也是先生成事件，再用 `apply_event` 修改自己的状态，
+/// 事件之外只维护客户端看不到的部分 (如最小加注额、谁还需要行动)；其他的状态变更 (发牌、结算等)
+/// 仍由游戏逻辑直接修改，`apply_event` 对这些事件只是客户端一侧的同步代码。
+/// 事件中的数值尽量带上变更后的结果 (如 `new_stack`、`new_pot`)，中途同步的客户端也不会算错。
+pub type GameEvent = ServerMessage;
+
+/// 事后计算全下摊牌胜率时，剩余公共牌较多的街道上随机模拟的次数
+const HISTORY_EQUITY_SAMPLES: usize = 5000;
+
+/// 房间时间线最多保留的事件数，超出后最早的事件并入初始状态
+pub const MAX_TIMELINE_ENTRIES: usize = 50_000;
+
+/// 审计日志最多保留的条数，超出后丢弃最早的记录
+pub const MAX_AUDIT_ENTRIES: usize = 5_000;
+
+impl GameState {
+    /// 将一个事件应用到游戏状态上: 客户端和回放应用到净化后的状态，服务器应用到自己的完整状态
+    ///
+    /// `viewer` 是观察者自己的玩家ID，用于应用只发给本人的私密事件 (如 PlayerHand)。
+    /// 不涉及游戏状态的事件 (如 Error、Info) 会被忽略。
+    pub fn apply_event(&mut self, event: &GameEvent, viewer: Option<PlayerId>) {
+        match event {
+            ServerMessage::Event { event, .. } => self.apply_event(event, viewer),
+            ServerMessage::RoomJoined { game_state, .. } => *self = game_state.clone(),
+            ServerMessage::GameStateSnapshot(state) | ServerMessage::SpectatorSnapshot(state) => *self = state.clone(),
+            ServerMessage::PlayerJoined { player } => {
+                self.players.insert(player.id, player.clone());
+            }
+            ServerMessage::PlayerLeft { player_id } => {
+                if let Some(idx) = self.seated_players.iter().position(|id| id == player_id) {
+                    self.seated_players.remove(idx);
+                }
+                self.players.remove(player_id);
+            }
+            ServerMessage::PlayerUpdated { player } => {
+                // 已在同一座位就座的玩家只是更新了设置，无需重新入座
+                let already_seated = self.seated_players.contains(&player.id)
+                    && self.players.get(&player.id).is_some_and(|p| p.seat_id == player.seat_id);
+                // 根据玩家状态变化，更新 seated_players 列表
+                if player.state == PlayerState::Waiting && !already_seated {
+                    if let Some(idx) = self.seated_players.iter().position(|p| *p == player.id) {
+                        self.seated_players.remove(idx);
+                    }
+                    if let Some(p) = self.players.get_mut(&player.id) {
+                        p.seat_id = player.seat_id;
+                    }
+                    if let Some(seat_id) = player.seat_id
+                        && let Ok(idx) = self.find_insertion_index(seat_id) {
+                        self.seated_players.insert(idx, player.id);
+                    }
+                } else if player.state == PlayerState::SittingOut
+                    && let Some(idx) = self.seated_players.iter().position(|id| id == &player.id) {
+                    self.seated_players.remove(idx);
+                }
+                self.players.insert(player.id, player.clone());
+            }
+            ServerMessage::HandStarted { seated_players, hand_player_order, variant, betting_structure, button, .. } => {
+                let n = hand_player_order.len();
+                self.button = *button;
+                self.seated_players = seated_players.clone();
+                self.hand_player_order = hand_player_order.clone();
+                self.player_indices = hand_player_order.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+                self.phase = GamePhase::PreFlop;
+                self.pot = 0;
+                self.max_bet = 0;
+                self.last_bet = 0;
+                self.last_aggressor = None;
+                self.bets = vec![0; n];
+                self.dead_bets = vec![0; n];
+                self.forced_bets.clear();
+                self.street_levels.clear();
+                self.dead_money = 0;
+                self.straddle = 0;
+                self.mucked = vec![false; n];
+                self.shown = vec![false; n];
+                self.showdown_queue.clear();
+                self.variant = *variant;
+                self.betting_structure = *betting_structure;
+                self.chosen_variant = None;
+                self.insurance = None;
+                self.bomb_pot = false;
+                self.hands_since_bomb_pot += 1;
+                if self.room_options.tournament.is_some() {
+                    self.level_hands += 1;
+                }
+                if let Some(duplicate) = &mut self.duplicate {
+                    duplicate.hands += 1;
+                }
+                if self.room_options.rotation.is_none() {
+                    self.rotation = None;
+                } else if let Some(progress) = &mut self.rotation {
+                    progress.hands_left = progress.hands_left.saturating_sub(1);
+                }
+                self.community_cards = vec![vec![None; variant.community_card_count()]];
+                self.player_cards = vec![vec![None; variant.hole_card_count()]; n];
+                for p in self.players.values_mut() {
+                    p.post_straddle = false;
+                    if hand_player_order.contains(&p.id) {
+                        p.state = PlayerState::Playing;
+                    }
+                }
+                for player_id in self.seated_players.iter() {
+                    if let Some(p) = self.players.get_mut(player_id)
+                        && (p.is_offline || p.stack == 0) {
+                        p.state = PlayerState::SittingOut;
+                    }
+                }
+            }
+            ServerMessage::PlayerHand { hands } => {
+                if let Some(idx) = viewer.and_then(|id| self.player_indices.get(&id)) {
+                    self.player_cards[*idx] = hands.iter().cloned().map(Some).collect();
+                }
+            }
+            ServerMessage::PlayerActed { player_id, action, total_bet, new_stack, new_pot, .. } => {
+                self.pot = *new_pot;
+                if let Some(idx) = self.player_indices.get(player_id) {
+                    self.bets[*idx] = *total_bet;
+                }
+                if let Some(p) = self.players.get_mut(player_id) {
+                    p.stack = *new_stack;
+                    match action {
+                        PlayerAction::Fold => p.state = PlayerState::Folded,
+                        _ if p.stack == 0 => p.state = PlayerState::AllIn,
+                        _ => {}
+                    }
+                }
+                if *total_bet > self.max_bet {
+                    self.max_bet = *total_bet;
+                    self.last_aggressor = Some(*player_id);
+                }
+            }
+            ServerMessage::AntePosted { player_id, amount, new_stack, new_pot } => {
+                self.pot = *new_pot;
+                if self.room_options.big_blind_ante {
+                    self.dead_money += *amount;
+                } else if let Some(idx) = self.player_indices.get(player_id) {
+                    self.dead_bets[*idx] += *amount;
+                }
+                if let Some(p) = self.players.get_mut(player_id) {
+                    p.stack = *new_stack;
+                    if p.stack == 0 {
+                        p.state = PlayerState::AllIn;
+                    }
+                }
+            }
+            ServerMessage::BombPotPosted { player_id, amount, new_stack, new_pot } => {
+                self.bomb_pot = true;
+                self.bomb_pot_queued = false;
+                self.hands_since_bomb_pot = 0;
+                self.pot = *new_pot;
+                if let Some(idx) = self.player_indices.get(player_id) {
+                    self.bets[*idx] = *amount;
+                }
+                self.max_bet = self.max_bet.max(*amount);
+                if let Some(p) = self.players.get_mut(player_id) {
+                    p.stack = *new_stack;
+                    if p.stack == 0 {
+                        p.state = PlayerState::AllIn;
+                    }
+                }
+            }
+            ServerMessage::DeadBlindPosted { player_id, dead_amount, live_amount, new_stack, new_pot } => {
+                self.pot = *new_pot;
+                if let Some(idx) = self.player_indices.get(player_id) {
+                    self.bets[*idx] = *live_amount;
+                    self.dead_bets[*idx] += *dead_amount;
+                }
+                if let Some(p) = self.players.get_mut(player_id) {
+                    p.stack = *new_stack;
+                    p.missed_blinds = MissedBlinds::None;
+                    p.post_dead_blind = false;
+                    if p.stack == 0 {
+                        p.state = PlayerState::AllIn;
+                    }
+                }
+            }
+            ServerMessage::VoteStarted { vote, .. } => {
+                self.vote = Some(vote.clone());
+            }
+            ServerMessage::VoteCast { vote_id, player_id, approve } => {
+                if let Some(vote) = self.vote.as_mut().filter(|v| v.id == *vote_id) {
+                    if *approve {
+                        vote.yes.push(*player_id);
+                    } else {
+                        vote.no.push(*player_id);
+                    }
+                }
+            }
+            ServerMessage::VoteEnded { .. } => {
+                self.vote = None;
+            }
+            ServerMessage::HandVoided { stacks } => {
+                for (player_id, stack) in stacks {
+                    if let Some(p) = self.players.get_mut(player_id) {
+                        p.stack = *stack;
+                    }
+                }
+                self.clear_voided_hand();
+            }
+            ServerMessage::BlindsChanged { small_blind, big_blind } => {
+                self.small_blind = *small_blind;
+                self.big_blind = *big_blind;
+            }
+            ServerMessage::WinnerGuessed { player_id, target } => {
+                self.spectator_guesses.guesses.insert(*player_id, *target);
+            }
+            ServerMessage::GuessesScored { winners, .. } => {
+                self.spectator_guesses.settle(winners);
+            }
+            ServerMessage::StraddlePosted { player_id, amount, new_stack, new_pot } => {
+                self.pot = *new_pot;
+                if let Some(idx) = self.player_indices.get(player_id) {
+                    self.bets[*idx] = *amount;
+                }
+                if let Some(p) = self.players.get_mut(player_id) {
+                    p.stack = *new_stack;
+                    if p.stack == 0 {
+                        p.state = PlayerState::AllIn;
+                    }
+                }
+                if *amount > self.max_bet {
+                    // 与服务器一致: 完整的抓位 (两个大盲) 才算一次加注，改变最小加注额
+                    if *amount == self.big_blind * 2 {
+                        self.last_raise_amount = *amount;
+                    }
+                    self.max_bet = *amount;
+                    self.straddle = *amount;
+                    self.last_aggressor = Some(*player_id);
+                }
+            }
+            ServerMessage::NextToAct { player_id, valid_actions, .. } => {
+                if let Some(idx) = self.player_indices.get(player_id) {
+                    self.cur_player_idx = *idx;
+                }
+                // 第一次轮到玩家行动前的下注都是强制下注 (与牌局历史中区分盲注的方式相同)
+                if self.forced_bets.is_empty() && self.phase == GamePhase::PreFlop {
+                    self.forced_bets = self.bets.clone();
+                }
+                // 没有公共牌的街道没有单独的消息，根据合法动作推断进入了换牌轮或下一轮下注
+                let is_draw = valid_actions.iter().any(|a| matches!(a, PlayerActionType::Discard(_)));
+                let cur_round = self.variant.street(self.phase).map(|s| s.round);
+                if is_draw != (cur_round == Some(RoundKind::Draw)) {
+                    self.last_bet = self.max_bet;
+                    self.last_aggressor = None;
+                    self.enter_street(self.variant.next_phase(self.phase));
+                }
+            }
+            ServerMessage::CardsDrawn { .. } => {}
+            ServerMessage::TimeBankUpdated { player_id, remaining } => {
+                if let Some(p) = self.players.get_mut(player_id) {
+                    p.time_bank = *remaining;
+                }
+            }
+            ServerMessage::CommunityCardsDealt { phase, board, cards, last_bet } => {
+                self.last_bet = *last_bet;
+                self.last_aggressor = None;
+                self.enter_street(*phase);
+                let start = self.variant.community_offset(*phase);
+                while self.community_cards.len() <= *board {
+                    self.community_cards.push(vec![None; self.variant.community_card_count()]);
+                }
+                for (i, card) in cards.iter().enumerate() {
+                    self.community_cards[*board][start + i] = Some(*card);
+                }
+            }
+            ServerMessage::BetReturned { player_id, amount, new_stack, new_pot } => {
+                if let Some(p) = self.players.get_mut(player_id) {
+                    p.stack = *new_stack;
+                }
+                if let Some(bet) = self.player_indices.get(player_id).and_then(|idx| self.bets.get_mut(*idx)) {
+                    *bet = bet.saturating_sub(*amount);
+                }
+                self.pot = *new_pot;
+            }
+            ServerMessage::Showdown { results, to_decide, .. } => {
+                self.phase = GamePhase::Showdown;
+                for result in results {
+                    if let Some(p) = self.players.get_mut(&result.player_id)
+                        && result.winnings > 0 {
+                        p.stack += result.winnings;
+                        p.wins += 1;
+                    }
+                    if let Some(&idx) = self.player_indices.get(&result.player_id) {
+                        match &result.cards {
+                            Some(cards) => {
+                                self.player_cards[idx] = cards.iter().cloned().map(Some).collect();
+                                self.mark_shown(idx);
+                            }
+                            None if result.winnings == 0 && !to_decide.contains(&result.player_id) => self.mucked[idx] = true,
+                            None => {}
+                        }
+                    }
+                }
+                self.showdown_queue = to_decide.clone();
+                self.pot = 0;
+                self.dead_money = 0;
+                for id in self.hand_player_order.iter() {
+                    if let Some(p) = self.players.get_mut(id)
+                        && p.stack == 0 {
+                        p.losses += 1;
+                        p.is_offline = true;
+                    }
+                }
+            }
+            ServerMessage::BlindLevelRaised { level, small_blind, big_blind, level_ends_at, chip_race, .. } => {
+                // 只开始级别计时的消息不改变盲注，也不重新计算本级的局数
+                if (self.small_blind, self.big_blind) != (*small_blind, *big_blind) {
+                    self.level_hands = 0;
+                }
+                self.tournament_level = *level;
+                self.level_ends_at = *level_ends_at;
+                self.small_blind = *small_blind;
+                self.big_blind = *big_blind;
+                for result in chip_race {
+                    if let Some(p) = self.players.get_mut(&result.player_id) {
+                        p.stack = result.new_stack;
+                    }
+                }
+            }
+            ServerMessage::RoomOptionsUpdated(options) => self.room_options = options.clone(),
+            ServerMessage::DuplicateUpdated { duplicate } => self.duplicate = *duplicate,
+            ServerMessage::VariantChosen { variant, .. } => self.chosen_variant = Some(*variant),
+            ServerMessage::BombPotQueued => self.bomb_pot_queued = true,
+            ServerMessage::VariantChanged { index, hands, .. } => {
+                self.rotation = self.room_options.rotation.clone()
+                    .map(|rotation| RotationProgress { rotation, index: *index, hands_left: *hands });
+            }
+            ServerMessage::AllInHandsRevealed { hands } => {
+                for (player_id, cards) in hands {
+                    if let Some(&idx) = self.player_indices.get(player_id) {
+                        self.player_cards[idx] = cards.iter().cloned().map(Some).collect();
+                        self.mark_shown(idx);
+                    }
+                }
+            }
+            ServerMessage::InsuranceOffered(offer) => {
+                if let Some(idx) = self.player_indices.get(&offer.buyer) {
+                    self.cur_player_idx = *idx;
+                }
+                self.insurance = Some(Insurance::Offered(*offer));
+            }
+            ServerMessage::InsuranceDecided { policy, .. } => {
+                self.insurance = Some(policy.map_or(Insurance::Declined, Insurance::Bought));
+            }
+            ServerMessage::InsuranceSettled { from, to, amount } => {
+                if let Some(p) = self.players.get_mut(from) {
+                    p.stack = p.stack.saturating_sub(*amount);
+                }
+                // 服务器在判断输光筹码之前结算保险，Showdown 中按输光处理的玩家拿到保额后恢复
+                if let Some(p) = self.players.get_mut(to) {
+                    if p.stack == 0 {
+                        p.losses = p.losses.saturating_sub(1);
+                        p.is_offline = false;
+                    }
+                    p.stack += amount;
+                }
+            }
+            ServerMessage::SevenDeuceBounty { winner, payments } => {
+                for (payer, amount) in payments {
+                    if let Some(p) = self.players.get_mut(payer) {
+                        p.stack = p.stack.saturating_sub(*amount);
+                    }
+                }
+                if let Some(p) = self.players.get_mut(winner) {
+                    p.stack += payments.iter().map(|(_, amount)| amount).sum::<u32>();
+                }
+            }
+            ServerMessage::FoldedHandRevealed { player_id, cards } => {
+                if let Some(&idx) = self.player_indices.get(player_id) {
+                    self.player_cards[idx] = cards.iter().cloned().map(Some).collect();
+                    self.mark_shown(idx);
+                }
+            }
+            ServerMessage::CardsShown { player_id, cards } => {
+                if let Some(&idx) = self.player_indices.get(player_id) {
+                    let positions: Vec<usize> = cards.iter().enumerate().filter(|(_, c)| c.is_some()).map(|(pos, _)| pos).collect();
+                    for (slot, card) in self.player_cards[idx].iter_mut().zip(cards) {
+                        if card.is_some() {
+                            *slot = *card;
+                        }
+                    }
+                    self.mark_cards_shown(*player_id, &positions);
+                }
+            }
+            ServerMessage::HandShownOrMucked { player_id, cards, .. } => {
+                self.showdown_queue.retain(|id| id != player_id);
+                if let Some(&idx) = self.player_indices.get(player_id) {
+                    match cards {
+                        Some(cards) => {
+                            self.player_cards[idx] = cards.iter().cloned().map(Some).collect();
+                            self.mark_shown(idx);
+                        }
+                        None => self.mucked[idx] = true,
+                    }
+                }
+            }
+            ServerMessage::SeatsShuffled { seated_players, seats } => {
+                for (id, seat_id) in seats {
+                    if let Some(p) = self.players.get_mut(id) {
+                        p.seat_id = Some(*seat_id);
+                    }
+                }
+                self.seated_players = seated_players.clone();
+                self.button = None;
+            }
+            ServerMessage::HostLedgerUpdated { entry, new_stack } => {
+                if let Some(p) = self.players.get_mut(&entry.player_id) {
+                    p.stack = *new_stack;
+                }
+                self.host_ledger.entries.push(entry.clone());
+            }
+            ServerMessage::ChipsDeposited { player_id, amount, new_bank } => {
+                if let Some(p) = self.players.get_mut(player_id) {
+                    p.bank = *new_bank;
+                    p.buy_in += amount;
+                }
+            }
+            ServerMessage::SitAndGoStarted { entrants, .. } => {
+                let buy_in = self.room_options.sit_and_go.as_ref().map_or(0, |sng| sng.buy_in);
+                self.sit_and_go = SitAndGoProgress { entrants: entrants.clone(), ..Default::default() };
+                if self.room_options.chip_accounts {
+                    for id in entrants {
+                        if let Some(p) = self.players.get_mut(id) {
+                            p.bank = p.bank.saturating_sub(buy_in);
+                        }
+                    }
+                }
+            }
+            ServerMessage::PlayerEliminated { player_id, .. } => self.sit_and_go.eliminated.push(*player_id),
+            ServerMessage::SitAndGoFinished { results } => {
+                self.sit_and_go.finished = true;
+                for result in results {
+                    if let Some(p) = self.players.get_mut(&result.player_id) {
+                        p.stack = 0;
+                        if self.room_options.chip_accounts {
+                            p.bank += result.prize;
+                        }
+                    }
+                }
+            }
+            // 兔子牌只供查看，不改变公共牌
+            ServerMessage::ActionPendingConfirm { .. }
+            | ServerMessage::RabbitHunt { .. }
+            | ServerMessage::ChatBroadcast { .. }
+            | ServerMessage::TurnTimer { .. }
+            | ServerMessage::DepositRequested { .. }
+            | ServerMessage::JoinRequested { .. }
+            | ServerMessage::SpectatorsFull { .. }
+            | ServerMessage::RoomClosed { .. }
+            | ServerMessage::HostChanged { .. }
+            | ServerMessage::SessionSummary(_)
+            | ServerMessage::AuditLog(_)
+            | ServerMessage::CheckInStatus { .. }
+                | ServerMessage::ClockSync { .. }
+            | ServerMessage::Info { .. }
+            | ServerMessage::Error { .. } => {}
+        }
+    }
+
+    /// 进入新的街道。双公共牌时每块公共牌各有一条发牌消息，只在第一次进入时记录下注轮的分界
+    fn enter_street(&mut self, phase: GamePhase) {
+        if phase == self.phase {
+            return;
+        }
+        self.phase = phase;
+        if self.variant.street(phase).is_some_and(|s| s.round == RoundKind::Bet) {
+            self.street_levels.push(self.last_bet);
+        }
+    }
+}
+
+/// 一局牌的历史记录: 开局前的 (净化后的) 状态快照，加上本局产生的所有事件。
+/// 从快照开始依次应用事件即可回放到牌局中的任意时刻。
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HandHistory {
+    pub initial: GameState,
+    pub events: Vec<GameEvent>,
+    /// 全下摊牌的胜率变化，记录到本局的摊牌结果时计算一次，见 `equity_progression`
+    #[serde(default)]
+    pub equity: Vec<(GamePhase, Vec<(PlayerId, f64)>)>,
+}
+
+impl HandHistory {
+    pub fn new(initial: GameState) -> Self {
+        Self { initial, events: vec![], equity: vec![] }
+    }
+
+    /// 记录一个事件，错误和提示消息不属于牌局历史。记录到摊牌结果时本局结束，随即算出胜率变化
+    pub fn record(&mut self, event: GameEvent) {
+        let hand_ended = matches!(event, ServerMessage::Showdown { .. });
+        if !matches!(
+            event,
+            ServerMessage::Error { .. }
+                | ServerMessage::Info { .. }
+                | ServerMessage::ActionPendingConfirm { .. }
+                | ServerMessage::TurnTimer { .. }
+                | ServerMessage::DepositRequested { .. }
+                | ServerMessage::JoinRequested { .. }
+                | ServerMessage::SpectatorsFull { .. }
+                | ServerMessage::RoomClosed { .. }
+                | ServerMessage::HostChanged { .. }
+                | ServerMessage::SessionSummary(_)
+                | ServerMessage::AuditLog(_)
+                | ServerMessage::CheckInStatus { .. }
+                | ServerMessage::ClockSync { .. }
+        ) {
+            self.events.push(event);
+        }
+        if hand_ended {
+            self.equity = self.equity_progression();
+        }
+    }
+
+    /// 回放前 `n` 个事件后的状态
+    pub fn replay(&self, n: usize, viewer: Option<PlayerId>) -> GameState {
+        let mut state = self.initial.clone();
+        for event in self.events.iter().take(n) {
+            state.apply_event(event, viewer);
+        }
+        state
+    }
+
+    /// 全下摊牌的胜率变化: 亮牌时和之后每发一条街的公共牌，各位亮牌玩家的胜率 (平分的奖池按比例计入)
+    ///
+    /// 只计算还有公共牌没发的街道，双公共牌时按第一块计算。没有全下摊牌时返回空列表。
+    /// 剩余公共牌较多时随机模拟，每次调用的结果略有不同，结束的牌局直接使用 `equity` 中保存的结果。
+    pub fn equity_progression(&self) -> Vec<(GamePhase, Vec<(PlayerId, f64)>)> {
+        let mut state = self.initial.clone();
+        let mut hands: Vec<(PlayerId, Vec<Card>)> = vec![];
+        let mut progression = vec![];
+        for event in self.events.iter() {
+            state.apply_event(event, None);
+            match event {
+                ServerMessage::AllInHandsRevealed { hands: revealed } => hands = revealed.clone(),
+                ServerMessage::CommunityCardsDealt { board: 0, .. } if !hands.is_empty() => {}
+                _ => continue,
+            }
+            let board: Vec<Card> = state.community_cards.first().map_or(vec![], |b| b.iter().flatten().cloned().collect());
+            if board.len() >= state.variant.community_card_count() {
+                continue;
+            }
+            let cards: Vec<Vec<Card>> = hands.iter().map(|(_, cards)| cards.clone()).collect();
+            let equities = hand_equities(state.variant, &cards, &board, HISTORY_EQUITY_SAMPLES);
+            progression.push((state.phase, hands.iter().map(|(id, _)| *id).zip(equities).collect()));
+        }
+        progression
+    }
+}
+
+/// 文字版的牌局历史，每行一个动作，英文版的格式与常见牌谱软件的导入格式相近
+impl Describe for HandHistory {
+    fn describe(&self, locale: Locale) -> String {
+        let zh = locale == Locale::Chinese;
+        let mut state = self.initial.clone();
+        let mut lines: Vec<String> = vec![];
+        // 第一次轮到玩家行动之前的下注都是盲注
+        let mut blinds = true;
+        for event in self.events.iter() {
+            let name = |id: &PlayerId| state.players.get(id).map_or_else(|| id.to_string(), |p| p.nickname.clone());
+            let prev_bet = |id: &PlayerId| state.player_indices.get(id).and_then(|&i| state.bets.get(i)).copied().unwrap_or(0);
+            match event {
+                ServerMessage::HandStarted { hand_player_order, variant, betting_structure, button, started_at, .. } => {
+                    let time = started_at.format("%Y-%m-%d %H:%M:%S");
+                    lines.push(format!(
+                        "{} {} (${}/${}) - {} UTC",
+                        variant.describe(locale), betting_structure.describe(locale), state.small_blind, state.big_blind, time,
+                    ));
+                    for id in hand_player_order.iter() {
+                        let seat = state.players.get(id).and_then(|p| p.seat_id).map_or("-".to_string(), |s| s.to_string());
+                        let stack = state.players.get(id).map_or(0, |p| p.stack);
+                        lines.push(if zh {
+                            format!("座位 {}: {} (${})", seat, name(id), stack)
+                        } else {
+                            format!("Seat {}: {} (${} in chips)", seat, name(id), stack)
+                        });
+                    }
+                    let seat_of = |id: &PlayerId| state.players.get(id).and_then(|p| p.seat_id);
+                    match (hand_player_order.first(), button) {
+                        (Some(first), Some(b)) if seat_of(first) != Some(b.button) => {
+                            lines.push(if zh { format!("座位 {} 是死庄", b.button) } else { format!("Seat #{} is a dead button", b.button) });
+                        }
+                        (Some(first), _) => {
+                            lines.push(if zh { format!("{} 是庄家", name(first)) } else { format!("{} is the button", name(first)) });
+                        }
+                        _ => {}
+                    }
+                    lines.push(format!("*** {} ***", GamePhase::PreFlop.describe(locale)));
+                    blinds = true;
+                }
+                ServerMessage::AntePosted { player_id, amount, .. } => {
+                    lines.push(if zh {
+                        format!("{}: 交前注 ${}", name(player_id), amount)
+                    } else {
+                        format!("{}: posts the ante ${}", name(player_id), amount)
+                    });
+                }
+                ServerMessage::BombPotPosted { player_id, amount, .. } => {
+                    lines.push(if zh {
+                        format!("{}: 交炸弹底池底注 ${}", name(player_id), amount)
+                    } else {
+                        format!("{}: posts the bomb pot ante ${}", name(player_id), amount)
+                    });
+                }
+                ServerMessage::StraddlePosted { player_id, amount, .. } => {
+                    lines.push(if zh {
+                        format!("{}: 下抓位注 ${}", name(player_id), amount)
+                    } else {
+                        format!("{}: posts straddle ${}", name(player_id), amount)
+                    });
+                }
+                ServerMessage::DeadBlindPosted { player_id, dead_amount, live_amount, .. } => {
+                    lines.push(match (zh, live_amount) {
+                        (true, 0) => format!("{}: 补交死盲 ${}", name(player_id), dead_amount),
+                        (true, _) => format!("{}: 补交死盲 ${} 和大盲 ${}", name(player_id), dead_amount, live_amount),
+                        (false, 0) => format!("{}: posts dead blind ${}", name(player_id), dead_amount),
+                        (false, _) => format!("{}: posts dead blind ${} and big blind ${}", name(player_id), dead_amount, live_amount),
+                    });
+                }
+                ServerMessage::NextToAct { .. } => blinds = false,
+                ServerMessage::PlayerActed { player_id, action, total_bet, new_stack, .. } => {
+                    let amount = total_bet.saturating_sub(prev_bet(player_id));
+                    let text = match action {
+                        PlayerAction::Fold => if zh { "弃牌".to_string() } else { "folds".to_string() },
+                        PlayerAction::Check => if zh { "过牌".to_string() } else { "checks".to_string() },
+                        PlayerAction::Call => if zh { format!("跟注 ${}", amount) } else { format!("calls ${}", amount) },
+                        PlayerAction::BetOrRaise(_) if blinds => {
+                            if zh { format!("下盲注 ${}", amount) } else { format!("posts blind ${}", amount) }
+                        }
+                        PlayerAction::BetOrRaise(_) if state.max_bet == state.last_bet => {
+                            if zh { format!("下注 ${}", amount) } else { format!("bets ${}", amount) }
+                        }
+                        PlayerAction::BetOrRaise(_) => {
+                            let to = total_bet.saturating_sub(state.last_bet);
+                            if zh { format!("加注到 ${}", to) } else { format!("raises to ${}", to) }
+                        }
+                    };
+                    let all_in = match (*new_stack, action) {
+                        (0, PlayerAction::Call | PlayerAction::BetOrRaise(_)) => if zh { "，全下" } else { " and is all-in" },
+                        _ => "",
+                    };
+                    lines.push(format!("{}: {}{}", name(player_id), text, all_in));
+                }
+                ServerMessage::CommunityCardsDealt { phase, board, cards, .. } => {
+                    let board = match (*board, zh) {
+                        (0, _) => String::new(),
+                        (b, true) => format!(" (第 {} 块)", b + 1),
+                        (b, false) => format!(" (board {})", b + 1),
+                    };
+                    lines.push(format!("*** {}{} *** {}", phase.describe(locale), board, describe_cards(cards, locale)));
+                }
+                ServerMessage::CardsDrawn { player_id, count, .. } => {
+                    lines.push(if zh {
+                        format!("{}: 换了 {} 张牌", name(player_id), count)
+                    } else {
+                        format!("{}: discards {} cards", name(player_id), count)
+                    });
+                }
+                ServerMessage::BetReturned { player_id, amount, .. } => {
+                    lines.push(if zh {
+                        format!("未被跟注的 ${} 退还给 {}", amount, name(player_id))
+                    } else {
+                        format!("Uncalled bet (${}) returned to {}", amount, name(player_id))
+                    });
+                }
+                ServerMessage::AllInHandsRevealed { hands } => {
+                    for (player_id, cards) in hands {
+                        let verb = if zh { "亮牌" } else { "shows" };
+                        lines.push(format!("{}: {} {}", name(player_id), verb, describe_cards(cards, locale)));
+                    }
+                }
+                ServerMessage::InsuranceDecided { player_id, policy: Some(policy) } => {
+                    lines.push(if zh {
+                        format!("{}: 买保险，保额 ${}，保费 ${}", name(player_id), policy.cover, policy.premium)
+                    } else {
+                        format!("{}: buys insurance (${} cover for ${})", name(player_id), policy.cover, policy.premium)
+                    });
+                }
+                ServerMessage::InsuranceSettled { from, to, amount } => {
+                    lines.push(if zh {
+                        format!("保险结算: {} 付给 {} ${}", name(from), name(to), amount)
+                    } else {
+                        format!("Insurance: {} pays {} ${}", name(from), name(to), amount)
+                    });
+                }
+                ServerMessage::SevenDeuceBounty { winner, payments } => {
+                    let total: u32 = payments.iter().map(|(_, amount)| amount).sum();
+                    lines.push(if zh {
+                        format!("七二奖励: {} 从其他玩家处获得 ${}", name(winner), total)
+                    } else {
+                        format!("Seven-deuce bounty: {} collects ${}", name(winner), total)
+                    });
+                }
+                ServerMessage::FoldedHandRevealed { player_id, cards } => {
+                    let verb = if zh { "亮出弃掉的牌" } else { "shows folded hand" };
+                    lines.push(format!("{}: {} {}", name(player_id), verb, describe_cards(cards, locale)));
+                }
+                ServerMessage::CardsShown { player_id, cards } => {
+                    let cards: Vec<_> = cards.iter().flatten().cloned().collect();
+                    let verb = if zh { "亮出" } else { "shows" };
+                    lines.push(format!("{}: {} {}", name(player_id), verb, describe_cards(&cards, locale)));
+                }
+                ServerMessage::RabbitHunt { cards, .. } => {
+                    for board in cards {
+                        let label = if zh { "兔子牌" } else { "RABBIT HUNT" };
+                        lines.push(format!("*** {} *** {}", label, describe_cards(board, locale)));
+                    }
+                }
+                ServerMessage::Showdown { results, to_decide, .. } => {
+                    lines.push(format!("*** {} ***", GamePhase::Showdown.describe(locale)));
+                    for result in results {
+                        let player = name(&result.player_id);
+                        if let Some(cards) = &result.cards {
+                            let mut ranks: Vec<String> = result.hand_rank.iter().map(|r| r.describe(locale)).collect();
+                            ranks.extend(result.low_hand.iter().map(|l| l.describe(locale)));
+                            let verb = if zh { "亮牌" } else { "shows" };
+                            lines.push(format!("{}: {} {} ({})", player, verb, describe_cards(cards, locale), ranks.join(", ")));
+                        } else if result.winnings == 0 && !to_decide.contains(&result.player_id) {
+                            lines.push(format!("{}: {}", player, if zh { "盖牌" } else { "mucks hand" }));
+                        }
+                        if result.winnings > 0 {
+                            lines.push(if zh {
+                                format!("{} 赢得 ${}", player, result.winnings)
+                            } else {
+                                format!("{} collected ${}", player, result.winnings)
+                            });
+                        }
+                    }
+                }
+                ServerMessage::HandShownOrMucked { player_id, cards, hand_rank } => match cards {
+                    Some(cards) => {
+                        let rank = hand_rank.as_ref().map_or(String::new(), |r| format!(" ({})", r.describe(locale)));
+                        let verb = if zh { "亮牌" } else { "shows" };
+                        lines.push(format!("{}: {} {}{}", name(player_id), verb, describe_cards(cards, locale), rank));
+                    }
+                    None => lines.push(format!("{}: {}", name(player_id), if zh { "盖牌" } else { "mucks hand" })),
+                },
+                ServerMessage::HandVoided { .. } => {
+                    lines.push(if zh { "本局作废，退还所有投入".to_string() } else { "Hand voided, all bets returned".to_string() });
+                }
+                _ => {}
+            }
+            state.apply_event(event, None);
+        }
+
+        // 全下摊牌的牌局最后附上每条街的胜率变化
+        let progression = &self.equity;
+        if let Some((_, first)) = progression.first() {
+            lines.push(if zh { "*** 胜率变化 ***".to_string() } else { "*** EQUITY ***".to_string() });
+            for (player_id, _) in first {
+                let streets: Vec<String> = progression.iter()
+                    .filter_map(|(phase, equities)| {
+                        let (_, equity) = equities.iter().find(|(id, _)| id == player_id)?;
+                        Some(format!("{} {:.1}%", phase.describe(locale), equity * 100.0))
+                    })
+                    .collect();
+                let name = state.players.get(player_id).map_or_else(|| player_id.to_string(), |p| p.nickname.clone());
+                lines.push(format!("{}: {}", name, streets.join(" → ")));
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// 房间时间线中的一条记录
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TimelineEntry {
+    pub at: DateTime<Utc>,
+    pub event: GameEvent,
+}
+
+/// 整个房间的时间线: 房间创建时的 (净化后的) 状态，加上之后广播给整个房间的所有事件，
+/// 包括玩家加入和离开、入座、每一局的过程、房间设置的修改和房主的操作。
+/// 只记录公开的事件，并去掉玩家的私人设置，回放得到的是观众看到的状态。
+/// 事件超过 `MAX_TIMELINE_ENTRIES` 条时，最早的事件应用到 `initial` 上后丢弃，回放结果不变。
+/// 记录的时间由调用者传入，核心逻辑不读取时钟。
+///
+/// 导出后可以把一整晚的牌局存档，也可以用 `hands` 拆分成每一局的 `HandHistory` 交给回放工具。
+/// `audit_log` 是只追加的审计日志，记录房主的每个管理操作，导出时一并写入。
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RoomTimeline {
+    pub room_id: RoomId,
+    pub created_at: DateTime<Utc>,
+    pub initial: GameState,
+    pub entries: Vec<TimelineEntry>,
+    #[serde(default)]
+    pub audit_log: Vec<AuditEntry>,
+}
+
+impl RoomTimeline {
+    /// `initial` 应当是净化后的状态，例如 `GameState::for_client(Audience::Spectator)`
+    pub fn new(initial: GameState, created_at: DateTime<Utc>) -> Self {
+        Self { room_id: initial.room_id, created_at, initial, entries: vec![], audit_log: vec![] }
+    }
+
+    /// 在审计日志末尾追加一条房主的管理操作
+    pub fn audit(&mut self, host_id: PlayerId, action: HostAction, at: DateTime<Utc>) {
+        if self.audit_log.len() >= MAX_AUDIT_ENTRIES {
+            self.audit_log.drain(..self.audit_log.len() + 1 - MAX_AUDIT_ENTRIES);
+        }
+        self.audit_log.push(AuditEntry { at, host_id, action });
+    }
+
+    /// 记录一个广播给房间的事件。只有改变公开状态的事件属于时间线，
+    /// 错误、提示和只发给个别玩家的消息都不记录
+    pub fn record(&mut self, event: GameEvent, at: DateTime<Utc>) {
+        if !Self::is_public(&event) {
+            return;
+        }
+        let event = event.public_view().map_or(event, |(_, public)| public);
+        if self.entries.len() >= MAX_TIMELINE_ENTRIES {
+            // 一次并入十分之一，避免每条新事件都移动整个数组
+            let folded = MAX_TIMELINE_ENTRIES / 10;
+            for entry in self.entries.drain(..folded) {
+                self.initial.apply_event(&entry.event, None);
+            }
+        }
+        self.entries.push(TimelineEntry { at, event });
+    }
+
+    /// 事件是否属于时间线
+    fn is_public(event: &GameEvent) -> bool {
+        matches!(
+            event,
+            ServerMessage::PlayerJoined { .. }
+                | ServerMessage::PlayerLeft { .. }
+                | ServerMessage::PlayerUpdated { .. }
+                | ServerMessage::HostChanged { .. }
+                | ServerMessage::HandStarted { .. }
+                | ServerMessage::PlayerActed { .. }
+                | ServerMessage::DeadBlindPosted { .. }
+                | ServerMessage::AntePosted { .. }
+                | ServerMessage::BombPotPosted { .. }
+                | ServerMessage::BombPotQueued
+                | ServerMessage::VoteStarted { .. }
+                | ServerMessage::VoteCast { .. }
+                | ServerMessage::VoteEnded { .. }
+                | ServerMessage::HandVoided { .. }
+                | ServerMessage::BlindsChanged { .. }
+                | ServerMessage::WinnerGuessed { .. }
+                | ServerMessage::GuessesScored { .. }
+                | ServerMessage::StraddlePosted { .. }
+                | ServerMessage::NextToAct { .. }
+                | ServerMessage::TimeBankUpdated { .. }
+                | ServerMessage::CardsDrawn { .. }
+                | ServerMessage::CommunityCardsDealt { .. }
+                | ServerMessage::BetReturned { .. }
+                | ServerMessage::Showdown { .. }
+                | ServerMessage::HandShownOrMucked { .. }
+                | ServerMessage::AllInHandsRevealed { .. }
+                | ServerMessage::InsuranceOffered(_)
+                | ServerMessage::InsuranceDecided { .. }
+                | ServerMessage::InsuranceSettled { .. }
+                | ServerMessage::SevenDeuceBounty { .. }
+                | ServerMessage::RoomOptionsUpdated(_)
+                | ServerMessage::DuplicateUpdated { .. }
+                | ServerMessage::VariantChosen { .. }
+                | ServerMessage::VariantChanged { .. }
+                | ServerMessage::FoldedHandRevealed { .. }
+                | ServerMessage::CardsShown { .. }
+                | ServerMessage::RabbitHunt { .. }
+                | ServerMessage::ChatBroadcast { .. }
+                | ServerMessage::SeatsShuffled { .. }
+                | ServerMessage::HostLedgerUpdated { .. }
+                | ServerMessage::ChipsDeposited { .. }
+                | ServerMessage::BlindLevelRaised { .. }
+                | ServerMessage::SitAndGoStarted { .. }
+                | ServerMessage::PlayerEliminated { .. }
+                | ServerMessage::SitAndGoFinished { .. }
+        )
+    }
+
+    /// 回放前 `n` 条记录后的状态
+    pub fn replay(&self, n: usize) -> GameState {
+        let mut state = self.initial.clone();
+        for entry in self.entries.iter().take(n) {
+            state.apply_event(&entry.event, None);
+        }
+        state
+    }
+
+    /// 所有牌局的文字版历史，用于导出给牌谱软件或其他读者
+    pub fn describe_hands(&self, locale: Locale) -> String {
+        let hands: Vec<String> = self
+            .hands()
+            .iter()
+            .enumerate()
+            .map(|(i, hand)| match locale {
+                Locale::Chinese => format!("Poker Eden 第 {} 局: {}", i + 1, hand.describe(locale)),
+                Locale::English => format!("Poker Eden Hand #{}: {}", i + 1, hand.describe(locale)),
+            })
+            .collect();
+        hands.join("\n\n")
+    }
+
+    /// 把时间线拆分成每一局的牌局历史。一局从 HandStarted 开始，到下一局开始之前结束，
+    /// 快照是开局前的状态
+    pub fn hands(&self) -> Vec<HandHistory> {
+        let mut state = self.initial.clone();
+        let mut hands: Vec<HandHistory> = vec![];
+        for entry in self.entries.iter() {
+            if matches!(entry.event, ServerMessage::HandStarted { .. }) {
+                hands.push(HandHistory::new(state.clone()));
+            }
+            if let Some(hand) = hands.last_mut() {
+                hand.record(entry.event.clone());
+            }
+            state.apply_event(&entry.event, None);
+        }
+        hands
+    }
+}
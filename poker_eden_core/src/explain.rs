@@ -0,0 +1,132 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+use crate::card::{HandCategory, LowHand, Rank};
+use crate::message::{PlayerActionType, ShowdownResult};
+use crate::state::*;
+use std::fmt;
+
+/// 新手模式下的讲解
+///
+/// 讲解是带类型的数据，由 `GameState` 根据事件和状态生成，
+/// 界面层通过 `Display` (或自己的翻译) 渲染成文字，便于本地化。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Explanation {
+    /// 没有人下注，可以免费过牌
+    CanCheck,
+    /// 至少要跟注 `amount`，因为 `aggressor` 下注或加注了 (`blind` 为 true 时是大盲注)
+    MustCall { amount: u32, aggressor: Option<String>, blind: bool },
+    /// 下注至少为大盲注
+    MinBet { amount: u32 },
+    /// 加注至少需要投入的筹码
+    MinRaise { amount: u32 },
+    /// 换牌阶段最多可以换的张数
+    CanDraw { max: usize },
+    /// 摊牌赢得奖池 (高牌部分)
+    Wins { player: String, category: HandCategory, high: Rank, amount: u32 },
+    /// 赢得低牌奖池
+    WinsLow { player: String, low: LowHand, amount: u32 },
+    /// 其他玩家都弃牌，无需摊牌赢得奖池
+    WinsUncontested { player: String, amount: u32 },
+}
+
+impl GameState {
+    /// 讲解轮到自己时的合法动作
+    pub fn explain_turn(&self, valid_actions: &[PlayerActionType]) -> Vec<Explanation> {
+        valid_actions
+            .iter()
+            .filter_map(|action| match *action {
+                PlayerActionType::Check => Some(Explanation::CanCheck),
+                PlayerActionType::Call(amount) => Some(Explanation::MustCall {
+                    amount,
+                    aggressor: self.last_aggressor.and_then(|id| self.players.get(&id)).map(|p| p.nickname.clone()),
+                    blind: self.phase == GamePhase::PreFlop && self.max_bet <= self.big_blind,
+                }),
+                PlayerActionType::Bet { min, .. } => Some(Explanation::MinBet { amount: min }),
+                PlayerActionType::Raise { min, .. } => Some(Explanation::MinRaise { amount: min }),
+                PlayerActionType::Discard(max) => Some(Explanation::CanDraw { max }),
+                PlayerActionType::Fold => None,
+            })
+            .collect()
+    }
+
+    /// 讲解摊牌结果: 每位赢家赢了多少、凭什么牌型赢
+    pub fn explain_showdown(&self, results: &[ShowdownResult]) -> Vec<Explanation> {
+        let mut explanations = vec![];
+        let uncontested = self
+            .hand_player_order
+            .iter()
+            .filter(|id| self.players.get(id).is_some_and(|p| p.state != PlayerState::Folded))
+            .count()
+            <= 1;
+        for result in results.iter().filter(|r| r.winnings > 0) {
+            let Some(player) = self.players.get(&result.player_id).map(|p| p.nickname.clone()) else { continue };
+            let high_amount = result.winnings - result.low_winnings;
+            match &result.hand_rank {
+                _ if uncontested => explanations.push(Explanation::WinsUncontested {
+                    player: player.clone(),
+                    amount: result.winnings,
+                }),
+                Some(rank) if high_amount > 0 => explanations.push(Explanation::Wins {
+                    player: player.clone(),
+                    category: rank.category(),
+                    high: rank.high_rank(),
+                    amount: high_amount,
+                }),
+                _ => {}
+            }
+            if let Some(low) = &result.low_hand
+                && result.low_winnings > 0 {
+                explanations.push(Explanation::WinsLow { player, low: low.clone(), amount: result.low_winnings });
+            }
+        }
+        explanations
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Explanation::CanCheck => write!(f, "没有人下注，你可以免费过牌"),
+            Explanation::MustCall { amount, aggressor: Some(name), blind: true } => {
+                write!(f, "你至少要跟注 ${}，因为 {} 下了大盲注", amount, name)
+            }
+            Explanation::MustCall { amount, aggressor: Some(name), blind: false } => {
+                write!(f, "你至少要跟注 ${}，因为 {} 下注或加注了", amount, name)
+            }
+            Explanation::MustCall { amount, aggressor: None, .. } => write!(f, "你至少要跟注 ${}", amount),
+            Explanation::MinBet { amount } => write!(f, "下注至少为 ${} (大盲注)", amount),
+            Explanation::MinRaise { amount } => {
+                write!(f, "加注至少需要投入 ${}：先补齐跟注，再至少加上一次加注的差额", amount)
+            }
+            Explanation::CanDraw { max } => write!(f, "你可以弃掉最多 {} 张底牌并补牌，也可以不换", max),
+            Explanation::Wins { player, category, high, amount } => match category {
+                HandCategory::RoyalFlush => write!(f, "{} 以{}赢得 ${}", player, category, amount),
+                HandCategory::OnePair
+                | HandCategory::TwoPair
+                | HandCategory::ThreeOfAKind
+                | HandCategory::FullHouse
+                | HandCategory::FourOfAKind => write!(f, "{} 以{} ({}) 赢得 ${}", player, category, high, amount),
+                _ => write!(f, "{} 以{} ({} 大) 赢得 ${}", player, category, high, amount),
+            },
+            Explanation::WinsLow { player, low, amount } => write!(f, "{} 以{}赢得低牌奖池 ${}", player, low, amount),
+            Explanation::WinsUncontested { player, amount } => {
+                write!(f, "其他玩家都弃牌，{} 无需摊牌赢得 ${}", player, amount)
+            }
+        }
+    }
+}
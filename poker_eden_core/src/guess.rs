@@ -0,0 +1,119 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+use crate::error::{GameError, GameResult};
+use crate::message::ServerMessage;
+use crate::state::{GamePhase, GameState, PlayerId, PlayerState};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 观众猜赢家的战绩
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GuessScore {
+    /// 猜中的局数
+    pub correct: u32,
+    /// 参与竞猜的局数
+    pub total: u32,
+}
+
+/// 观众猜赢家小游戏: 本局的竞猜和本次会话中每位观众的战绩。竞猜不影响牌局本身
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct SpectatorGuesses {
+    /// 本局的竞猜: 观众 -> 猜的赢家
+    pub guesses: HashMap<PlayerId, PlayerId>,
+    /// 本次会话中每位观众的战绩
+    pub scores: HashMap<PlayerId, GuessScore>,
+}
+
+impl SpectatorGuesses {
+    /// 按猜中局数从多到少排列的排行榜，猜中局数相同时参与局数少的靠前
+    pub fn leaderboard(&self) -> Vec<(PlayerId, GuessScore)> {
+        let mut board: Vec<(PlayerId, GuessScore)> = self.scores.iter().map(|(id, s)| (*id, *s)).collect();
+        board.sort_by(|a, b| b.1.correct.cmp(&a.1.correct).then(a.1.total.cmp(&b.1.total)).then(a.0.cmp(&b.0)));
+        board
+    }
+
+    /// 用本局的赢家结算本局的竞猜，返回猜中的观众
+    pub(crate) fn settle(&mut self, winners: &[PlayerId]) -> Vec<PlayerId> {
+        let mut correct = Vec::new();
+        for (spectator, guess) in self.guesses.drain() {
+            let score = self.scores.entry(spectator).or_default();
+            score.total += 1;
+            if winners.contains(&guess) {
+                score.correct += 1;
+                correct.push(spectator);
+            }
+        }
+        correct.sort();
+        correct
+    }
+}
+
+impl GameState {
+    /// 没有参与本局的玩家在摊牌前猜本局的赢家，每局只能猜一次。
+    /// 全下后亮出了底牌或已经摊牌时胜负摆在桌面上，不再接受竞猜
+    pub fn guess_winner(&mut self, player_id: PlayerId, target: PlayerId) -> GameResult {
+        if !self.room_options.spectator_guessing {
+            return Err(GameError::GuessingDisabled);
+        }
+        if self.phase == GamePhase::Showdown {
+            return Err(GameError::GuessingClosed);
+        }
+        if !self.hand_in_progress() {
+            return Err(GameError::NoHandInProgress);
+        }
+        let revealed = self.hand_player_order.iter().enumerate()
+            .any(|(idx, id)| self.is_shown(idx) && self.players.get(id).is_some_and(|p| p.state != PlayerState::Folded));
+        if revealed {
+            return Err(GameError::GuessingClosed);
+        }
+        if self.player_indices.contains_key(&player_id) {
+            return Err(GameError::PlayerInHand);
+        }
+        let in_hand = self.player_indices.contains_key(&target)
+            && self.players.get(&target).is_some_and(|p| p.state != PlayerState::Folded);
+        if !in_hand {
+            return Err(GameError::InvalidGuess);
+        }
+        if self.spectator_guesses.guesses.contains_key(&player_id) {
+            return Err(GameError::AlreadyGuessed);
+        }
+        self.spectator_guesses.guesses.insert(player_id, target);
+        Ok(vec![ServerMessage::WinnerGuessed { player_id, target }])
+    }
+
+    /// 根据本局的摊牌结果结算观众的竞猜，赢得筹码的玩家都算作赢家。本局没有人竞猜时不产生消息
+    pub(crate) fn score_guesses(&mut self, messages: &[ServerMessage]) -> Vec<ServerMessage> {
+        if self.spectator_guesses.guesses.is_empty() {
+            return vec![];
+        }
+        let mut winners: Vec<PlayerId> = messages
+            .iter()
+            .filter_map(|m| match m {
+                ServerMessage::Showdown { results, .. } => Some(results),
+                _ => None,
+            })
+            .flatten()
+            .filter(|r| r.winnings > 0)
+            .map(|r| r.player_id)
+            .collect();
+        winners.sort();
+        winners.dedup();
+        let correct = self.spectator_guesses.settle(&winners);
+        vec![ServerMessage::GuessesScored { winners, correct }]
+    }
+}
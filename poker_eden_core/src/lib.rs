@@ -23,13 +23,48 @@
 //! 使其可以被任何上层应用复用。
 
 mod card;
+mod compression;
+mod equity;
+mod error;
+mod event;
+mod explain;
+mod guess;
+mod locale;
 mod logic;
 mod message;
+mod overlay;
+mod preflop;
+mod range;
 mod state;
+mod vote;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 pub use card::*;
 
+pub use compression::*;
+
+pub use equity::*;
+
+pub use error::*;
+
+pub use event::*;
+
+pub use explain::*;
+
+pub use guess::*;
+
+pub use locale::*;
+
 pub use message::*;
 
+pub use overlay::*;
+
+pub use preflop::*;
+
+pub use range::*;
+
 pub use state::*;
 
+pub use vote::*;
+
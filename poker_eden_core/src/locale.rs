@@ -0,0 +1,290 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+use crate::card::{Card, HandCategory, HandRank, LowHand, Rank, Suit};
+use crate::message::RoomCloseReason;
+use crate::state::{BettingStructure, GamePhase, GameVariant};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// 导出牌局历史时使用的语言。
+/// 中文使用 `黑桃A`、`红心10` 这样的写法；英文使用牌谱软件通用的 `As`、`Th` 写法
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum Locale {
+    #[default]
+    Chinese,
+    English,
+}
+
+impl Locale {
+    /// 解析语言代码 (`zh`、`en`)，不区分大小写
+    pub fn from_code(code: &str) -> Option<Locale> {
+        match code.to_lowercase().as_str() {
+            "zh" | "cn" | "chinese" => Some(Locale::Chinese),
+            "en" | "english" => Some(Locale::English),
+            _ => None,
+        }
+    }
+
+    /// 语言代码，用于导出的文件名
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::Chinese => "zh",
+            Locale::English => "en",
+        }
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Locale::Chinese => write!(f, "中文"),
+            Locale::English => write!(f, "English"),
+        }
+    }
+}
+
+/// 按指定的语言描述牌、点数和牌型。`Display` 是客户端界面用的简写，导出时使用 `describe`
+pub trait Describe {
+    fn describe(&self, locale: Locale) -> String;
+}
+
+/// 用空格连接的一组牌，例如 `[As Kd 7c]`
+pub fn describe_cards(cards: &[Card], locale: Locale) -> String {
+    let cards: Vec<String> = cards.iter().map(|c| c.describe(locale)).collect();
+    format!("[{}]", cards.join(" "))
+}
+
+impl Describe for Suit {
+    fn describe(&self, locale: Locale) -> String {
+        match (locale, self) {
+            (Locale::Chinese, Suit::Spade) => "黑桃",
+            (Locale::Chinese, Suit::Heart) => "红心",
+            (Locale::Chinese, Suit::Club) => "梅花",
+            (Locale::Chinese, Suit::Diamond) => "方块",
+            (Locale::English, Suit::Spade) => "Spades",
+            (Locale::English, Suit::Heart) => "Hearts",
+            (Locale::English, Suit::Club) => "Clubs",
+            (Locale::English, Suit::Diamond) => "Diamonds",
+        }
+        .to_string()
+    }
+}
+
+impl Describe for Rank {
+    fn describe(&self, locale: Locale) -> String {
+        match locale {
+            Locale::Chinese => match self {
+                Rank::Ten => "10".to_string(),
+                rank => rank.to_string(),
+            },
+            Locale::English => match self {
+                Rank::Two => "Deuce",
+                Rank::Three => "Three",
+                Rank::Four => "Four",
+                Rank::Five => "Five",
+                Rank::Six => "Six",
+                Rank::Seven => "Seven",
+                Rank::Eight => "Eight",
+                Rank::Nine => "Nine",
+                Rank::Ten => "Ten",
+                Rank::Jack => "Jack",
+                Rank::Queen => "Queen",
+                Rank::King => "King",
+                Rank::Ace => "Ace",
+            }
+            .to_string(),
+        }
+    }
+}
+
+/// 英文点数的复数形式 (`Sixes`、`Aces`)
+fn plural(rank: Rank) -> String {
+    match rank {
+        Rank::Six => "Sixes".to_string(),
+        rank => format!("{}s", rank.describe(Locale::English)),
+    }
+}
+
+impl Describe for Card {
+    fn describe(&self, locale: Locale) -> String {
+        match locale {
+            Locale::Chinese => format!("{}{}", self.suit.describe(locale), self.rank.describe(locale)),
+            Locale::English => {
+                let suit = match self.suit {
+                    Suit::Spade => 's',
+                    Suit::Heart => 'h',
+                    Suit::Club => 'c',
+                    Suit::Diamond => 'd',
+                };
+                format!("{}{}", self.rank, suit)
+            }
+        }
+    }
+}
+
+impl Describe for HandRank {
+    fn describe(&self, locale: Locale) -> String {
+        let r = |rank: &Rank| rank.describe(locale);
+        match locale {
+            Locale::Chinese => match self {
+                HandRank::HighCard(r1, ..) => format!("高牌 {}", r(r1)),
+                HandRank::OnePair(r1, ..) => format!("一对 {}", r(r1)),
+                HandRank::TwoPair(r1, r2, _) => format!("两对 {} 和 {}", r(r1), r(r2)),
+                HandRank::ThreeOfAKind(r1, ..) => format!("三条 {}", r(r1)),
+                HandRank::Straight(r1) => format!("顺子 (最大 {})", r(r1)),
+                HandRank::Flush(r1, ..) => format!("同花 (最大 {})", r(r1)),
+                HandRank::FullHouse(r1, r2) => format!("葫芦 ({} 带 {})", r(r1), r(r2)),
+                HandRank::FourOfAKind(r1, _) => format!("四条 {}", r(r1)),
+                HandRank::StraightFlush(r1) => format!("同花顺 (最大 {})", r(r1)),
+                HandRank::RoyalFlush => "皇家同花顺".to_string(),
+            },
+            Locale::English => match self {
+                HandRank::HighCard(r1, ..) => format!("high card {}", r(r1)),
+                HandRank::OnePair(r1, ..) => format!("a pair of {}", plural(*r1)),
+                HandRank::TwoPair(r1, r2, _) => format!("two pair, {} and {}", plural(*r1), plural(*r2)),
+                HandRank::ThreeOfAKind(r1, ..) => format!("three of a kind, {}", plural(*r1)),
+                HandRank::Straight(r1) => format!("a straight, {} high", r(r1)),
+                HandRank::Flush(r1, ..) => format!("a flush, {} high", r(r1)),
+                HandRank::FullHouse(r1, r2) => format!("a full house, {} full of {}", plural(*r1), plural(*r2)),
+                HandRank::FourOfAKind(r1, _) => format!("four of a kind, {}", plural(*r1)),
+                HandRank::StraightFlush(r1) => format!("a straight flush, {} high", r(r1)),
+                HandRank::RoyalFlush => "a Royal Flush".to_string(),
+            },
+        }
+    }
+}
+
+impl Describe for HandCategory {
+    fn describe(&self, locale: Locale) -> String {
+        match locale {
+            Locale::Chinese => self.to_string(),
+            Locale::English => match self {
+                HandCategory::HighCard => "High Card",
+                HandCategory::OnePair => "One Pair",
+                HandCategory::TwoPair => "Two Pair",
+                HandCategory::ThreeOfAKind => "Three of a Kind",
+                HandCategory::Straight => "Straight",
+                HandCategory::Flush => "Flush",
+                HandCategory::FullHouse => "Full House",
+                HandCategory::FourOfAKind => "Four of a Kind",
+                HandCategory::StraightFlush => "Straight Flush",
+                HandCategory::RoyalFlush => "Royal Flush",
+            }
+            .to_string(),
+        }
+    }
+}
+
+impl Describe for LowHand {
+    fn describe(&self, locale: Locale) -> String {
+        let ranks: Vec<String> = self.0.iter()
+            .map(|&v| if v == 1 { "A".to_string() } else { v.to_string() })
+            .collect();
+        match locale {
+            Locale::Chinese => format!("低牌 {}", ranks.join("-")),
+            Locale::English => format!("a low of {}", ranks.join("-")),
+        }
+    }
+}
+
+impl Describe for GameVariant {
+    fn describe(&self, locale: Locale) -> String {
+        match locale {
+            Locale::Chinese => self.to_string(),
+            Locale::English => match self {
+                GameVariant::TexasHoldem => "Hold'em",
+                GameVariant::OmahaHiLo => "Omaha Hi/Lo",
+                GameVariant::FiveCardDraw => "Five Card Draw",
+            }
+            .to_string(),
+        }
+    }
+}
+
+impl Describe for BettingStructure {
+    fn describe(&self, locale: Locale) -> String {
+        match locale {
+            Locale::Chinese => self.to_string(),
+            Locale::English => match self {
+                BettingStructure::NoLimit => "No Limit",
+                BettingStructure::PotLimit => "Pot Limit",
+            }
+            .to_string(),
+        }
+    }
+}
+
+impl Describe for RoomCloseReason {
+    fn describe(&self, locale: Locale) -> String {
+        match locale {
+            Locale::Chinese => self.to_string(),
+            Locale::English => match self {
+                RoomCloseReason::HostEnded => "The host ended the game",
+                RoomCloseReason::Expired => "The room expired after a long period of inactivity",
+                RoomCloseReason::ServerShutdown => "The server is shutting down",
+                RoomCloseReason::Kicked => "You were removed from the room",
+            }
+            .to_string(),
+        }
+    }
+}
+
+/// 街道的名称，用于牌局历史中每条街道的标题
+impl Describe for GamePhase {
+    fn describe(&self, locale: Locale) -> String {
+        match (locale, self) {
+            (Locale::Chinese, GamePhase::WaitingForPlayers) => "等待玩家",
+            (Locale::Chinese, GamePhase::PreFlop) => "翻牌前",
+            (Locale::Chinese, GamePhase::Flop) => "翻牌",
+            (Locale::Chinese, GamePhase::Turn) => "转牌",
+            (Locale::Chinese, GamePhase::River) => "河牌",
+            (Locale::Chinese, GamePhase::Draw) => "换牌",
+            (Locale::Chinese, GamePhase::AfterDraw) => "换牌后",
+            (Locale::Chinese, GamePhase::Showdown) => "摊牌",
+            (Locale::English, GamePhase::WaitingForPlayers) => "WAITING",
+            (Locale::English, GamePhase::PreFlop) => "PRE-FLOP",
+            (Locale::English, GamePhase::Flop) => "FLOP",
+            (Locale::English, GamePhase::Turn) => "TURN",
+            (Locale::English, GamePhase::River) => "RIVER",
+            (Locale::English, GamePhase::Draw) => "DRAW",
+            (Locale::English, GamePhase::AfterDraw) => "AFTER DRAW",
+            (Locale::English, GamePhase::Showdown) => "SHOW DOWN",
+        }
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_cards_and_hands() {
+        let cards = [Card::new(Rank::Ace, Suit::Spade), Card::new(Rank::Ten, Suit::Heart)];
+        assert_eq!(describe_cards(&cards, Locale::English), "[As Th]");
+        assert_eq!(describe_cards(&cards, Locale::Chinese), "[黑桃A 红心10]");
+
+        let full_house = HandRank::FullHouse(Rank::King, Rank::Six);
+        assert_eq!(full_house.describe(Locale::English), "a full house, Kings full of Sixes");
+        assert_eq!(full_house.describe(Locale::Chinese), "葫芦 (K 带 6)");
+        assert_eq!(HandRank::OnePair(Rank::Two, Rank::Ace, Rank::King, Rank::Queen).describe(Locale::English), "a pair of Deuces");
+        assert_eq!(LowHand([8, 5, 4, 2, 1]).describe(Locale::English), "a low of 8-5-4-2-A");
+        assert_eq!(Locale::from_code("EN"), Some(Locale::English));
+        assert_eq!(Locale::from_code("fr"), None);
+    }
+}
@@ -16,34 +16,46 @@
 // Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
 
 use crate::card::*;
-use crate::message::{ServerMessage, ShowdownResult};
+use crate::equity::hand_equities;
+use crate::error::{GameError, GameResult};
+use crate::message::{ChipRaceResult, PlayerSummary, ServerMessage, SessionSummary, Settlement, ShowdownResult, SitAndGoResult, StackChange, TurnContext, MAX_AVATAR_CHARS, MAX_CHAT_CHARS};
+use crate::preflop::preflop_percentile;
 use crate::state::*;
 use crate::PlayerActionType;
+use rand::prelude::SliceRandom;
+use rand::Rng;
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 
+/// 计算全下保险报价时，还有超过两张公共牌没发时随机模拟的次数
+const INSURANCE_EQUITY_SAMPLES: usize = 5000;
+
 impl GameState {
     /// 查找新玩家应该插入到 seated_players 中的索引位置
     /// 这个算法能够正确处理 VecDeque 经过旋转后的循环有序状态
-    pub fn find_insertion_index(&self, new_player_seat_id: u8) -> usize {
+    ///
+    /// 已就座的玩家必须都有座位号，否则返回错误
+    pub fn find_insertion_index(&self, new_player_seat_id: u8) -> GameResult<usize> {
         // 情况 1: 房间里还没有玩家，直接插入到开头
         if self.seated_players.is_empty() {
-            return 0;
+            return Ok(0);
         }
 
+        let seat_of = |id: &PlayerId| -> GameResult<u8> {
+            self.players.get(id).ok_or(GameError::PlayerNotFound)?.seat_id.ok_or(GameError::NotSeated)
+        };
         // 获取队列头部的玩家座位ID作为参考点（锚点）。
         // 所有的比较都将围绕这个锚点进行，以处理循环。
-        // unwrap是安全的，因为我们已经检查过 is_empty。
-        let start_sid = self.players.get(&self.seated_players[0]).unwrap().seat_id.unwrap();
+        let start_sid = seat_of(&self.seated_players[0])?;
 
         if self.seated_players.len() == 1 {
-            return if new_player_seat_id < start_sid { 0 } else { 1 };
+            return Ok(if new_player_seat_id < start_sid { 0 } else { 1 });
         }
 
+        let existing_sids = self.seated_players.iter().skip(1).map(seat_of).collect::<GameResult<Vec<u8>>>()?;
         // 使用 position() 查找第一个满足插入条件的索引。
         // 插入条件是：`new_player_seat_id` 在循环顺序中应该位于 `existing_sid` 之前。
-        let maybe_index = self.seated_players.iter().skip(1).position(|&existing_id| {
-            let existing_player = self.players.get(&existing_id).unwrap();
-            let existing_sid = existing_player.seat_id.unwrap();
+        let maybe_index = existing_sids.into_iter().position(|existing_sid| {
 
             // 根据新玩家和现有玩家的 seat_id 是否大于等于锚点，判断它们属于哪个逻辑部分。
             let new_is_in_first_part = new_player_seat_id > start_sid;
@@ -63,7 +75,508 @@ impl GameState {
 
         // 如果 position() 找到了一个位置，说明新玩家应该插入到那个位置。
         // 如果没找到（返回 None），说明新玩家比所有现有玩家都“大”，应该插入到队列的末尾。
-        maybe_index.map(|i| i + 1).unwrap_or(self.seated_players.len())
+        Ok(maybe_index.map(|i| i + 1).unwrap_or(self.seated_players.len()))
+    }
+
+    /// 设置玩家的自动盖牌偏好
+    pub fn set_auto_muck(&mut self, player_id: PlayerId, auto_muck: bool) -> GameResult {
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.auto_muck = auto_muck;
+        Ok(vec![ServerMessage::PlayerUpdated { player: player.clone() }])
+    }
+
+    /// 设置翻牌前自动弃牌: 有人加注时，百分位低于 `below` 的起手牌自动弃牌，0 表示关闭
+    pub fn set_auto_fold(&mut self, player_id: PlayerId, below: u8) -> GameResult {
+        if below > 0 && self.room_options.auto_fold_disabled {
+            return Err(GameError::AutoFoldDisabled);
+        }
+        if below > 100 {
+            return Err(GameError::InvalidAutoFold);
+        }
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.auto_fold_below = below;
+        Ok(vec![ServerMessage::PlayerUpdated { player: player.clone() }])
+    }
+
+    /// 房主修改时间银行设置后，把所有玩家的时间银行重置为新的时长
+    pub fn reset_time_banks(&mut self) -> Vec<ServerMessage> {
+        let secs = self.room_options.time_bank_secs;
+        self.players.values_mut().map(|p| {
+            p.time_bank = secs;
+            ServerMessage::TimeBankUpdated { player_id: p.id, remaining: secs }
+        }).collect()
+    }
+
+    /// 从玩家的时间银行中扣除用掉的 `secs` 秒
+    pub fn charge_time_bank(&mut self, player_id: PlayerId, secs: u32) -> GameResult {
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.time_bank = player.time_bank.saturating_sub(secs);
+        Ok(vec![ServerMessage::TimeBankUpdated { player_id, remaining: player.time_bank }])
+    }
+
+    /// 当前行动的玩家是否应该自动弃牌: 德州扑克翻牌前有人加注，且起手牌低于玩家设置的百分位
+    fn should_auto_fold(&self, player_id: PlayerId) -> bool {
+        if self.room_options.auto_fold_disabled || self.phase != GamePhase::PreFlop
+            || self.max_bet <= self.big_blind.max(self.straddle) {
+            return false;
+        }
+        let Some(player) = self.players.get(&player_id).filter(|p| p.auto_fold_below > 0) else { return false };
+        let hole: Vec<Card> = self.player_indices.get(&player_id)
+            .and_then(|idx| self.player_cards.get(*idx))
+            .map(|cards| cards.iter().flatten().cloned().collect())
+            .unwrap_or_default();
+        preflop_percentile(&hole).is_some_and(|p| p < player.auto_fold_below as f64)
+    }
+
+    /// 一局结束后，弃牌的玩家亮出弃掉的底牌
+    pub fn reveal_folded_hand(&mut self, player_id: PlayerId) -> GameResult {
+        if !self.room_options.reveal_folded_hands {
+            return Err(GameError::RevealDisabled);
+        }
+        if self.phase != GamePhase::Showdown {
+            return Err(GameError::RevealBeforeShowdown);
+        }
+        let folded = self
+            .players
+            .get(&player_id)
+            .is_some_and(|p| p.state == PlayerState::Folded);
+        match self.player_indices.get(&player_id) {
+            Some(&idx) if folded => {
+                let cards: Vec<Card> = self.player_cards[idx].iter().flatten().cloned().collect();
+                self.mark_shown(idx);
+                Ok(vec![ServerMessage::FoldedHandRevealed { player_id, cards }])
+            }
+            _ => Err(GameError::NotFolded),
+        }
+    }
+
+    /// 其他人都弃牌后，赢下底池的玩家主动亮出手牌中 `positions` 位置的底牌，可以先亮一张再亮另一张
+    pub fn show_cards(&mut self, player_id: PlayerId, positions: Vec<usize>) -> GameResult {
+        if self.phase != GamePhase::Showdown || self.get_players_in_hand() != [player_id] {
+            return Err(GameError::NotUncontestedWinner);
+        }
+        let &idx = self.player_indices.get(&player_id).ok_or(GameError::NotInHand)?;
+        let hand_len = self.player_cards[idx].len();
+        if positions.is_empty() || positions.iter().any(|&pos| pos >= hand_len) {
+            return Err(GameError::InvalidCardPosition);
+        }
+        self.mark_cards_shown(player_id, &positions);
+        let cards = self.player_cards[idx]
+            .iter()
+            .enumerate()
+            .map(|(pos, card)| card.filter(|_| positions.contains(&pos)))
+            .collect();
+        Ok(vec![ServerMessage::CardsShown { player_id, cards }])
+    }
+
+    /// 摊牌后轮到的玩家选择亮牌或盖牌。输掉的玩家按亮牌顺序依次选择，没有选择的玩家在下一局开始时视为盖牌
+    pub fn show_or_muck(&mut self, player_id: PlayerId, show: bool) -> GameResult {
+        if self.phase != GamePhase::Showdown || self.showdown_queue.first() != Some(&player_id) {
+            return Err(GameError::NotYourShowdownTurn);
+        }
+        let &idx = self.player_indices.get(&player_id).ok_or(GameError::NotInHand)?;
+        self.showdown_queue.remove(0);
+        if !show {
+            self.mucked[idx] = true;
+            return Ok(vec![ServerMessage::HandShownOrMucked { player_id, cards: None, hand_rank: None }]);
+        }
+        self.mark_shown(idx);
+        let cards: Vec<Card> = self.player_cards[idx].iter().flatten().cloned().collect();
+        let board: Vec<Card> = self.community_cards.first().map_or(vec![], |b| b.iter().flatten().cloned().collect());
+        let hand_rank = Some(self.variant.evaluate_high(&cards, &board));
+        Ok(vec![ServerMessage::HandShownOrMucked { player_id, cards: Some(cards), hand_rank }])
+    }
+
+    /// 兔子牌: 牌局在河牌之前结束后，按原来的发牌顺序查看剩下的公共牌。
+    /// 牌堆保留到下一局开始，这里只读取、不改变牌堆，所以多次查看的结果相同
+    pub fn rabbit_hunt(&self, player_id: PlayerId) -> GameResult {
+        if self.phase != GamePhase::Showdown {
+            return Err(GameError::RabbitHuntBeforeShowdown);
+        }
+        let dealt = self.community_cards.first().map_or(0, |board| board.iter().take_while(|c| c.is_some()).count());
+        let mut deck = self.deck.clone();
+        let mut cards = vec![vec![]; self.community_cards.len()];
+        let mut offset = 0;
+        for street in self.variant.streets() {
+            if street.deal > 0 && offset >= dealt {
+                // 与 deal_to_boards 相同: 每条街道依次给每块公共牌发牌
+                for board in cards.iter_mut() {
+                    for _ in 0..street.deal {
+                        board.push(deck.pop().ok_or(GameError::DeckExhausted)?);
+                    }
+                }
+            }
+            offset += street.deal;
+        }
+        if cards.iter().all(Vec::is_empty) {
+            return Err(GameError::NoRabbitCards);
+        }
+        Ok(vec![ServerMessage::RabbitHunt { player_id, cards }])
+    }
+
+    /// 庄家选择模式下，本局 (或上一局) 的庄家为下一局选择玩法
+    pub fn choose_variant(&mut self, player_id: PlayerId, variant: GameVariant) -> GameResult {
+        if self.room_options.dealers_choice.is_empty() {
+            return Err(GameError::DealersChoiceDisabled);
+        }
+        if self.hand_player_order.first() != Some(&player_id) {
+            return Err(GameError::NotDealer);
+        }
+        if !self.room_options.dealers_choice.contains(&variant) {
+            return Err(GameError::VariantNotAllowed(variant));
+        }
+        self.chosen_variant = Some(variant);
+        Ok(vec![ServerMessage::VariantChosen { player_id, variant }])
+    }
+
+    /// 房主安排下一局打一次炸弹底池，不改变之后各局的玩法
+    pub fn queue_bomb_pot(&mut self) -> GameResult {
+        if self.room_options.bomb_pot_ante == 0 {
+            return Err(GameError::BombPotAnteNotSet);
+        }
+        self.bomb_pot_queued = true;
+        Ok(vec![ServerMessage::BombPotQueued])
+    }
+
+    /// 玩家在房间里发言。换行等控制字符替换成空格，去掉首尾空白后不能为空，也不能超过 `MAX_CHAT_CHARS` 个字
+    pub fn chat(&self, player_id: PlayerId, text: &str, now: DateTime<Utc>) -> GameResult {
+        if !self.players.contains_key(&player_id) {
+            return Err(GameError::PlayerNotFound);
+        }
+        let text: String = text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(GameError::EmptyChat);
+        }
+        if text.chars().count() > MAX_CHAT_CHARS {
+            return Err(GameError::ChatTooLong { max: MAX_CHAT_CHARS });
+        }
+        Ok(vec![ServerMessage::ChatBroadcast { player_id, text: text.to_string(), ts: now }])
+    }
+
+    /// 设置玩家的展示偏好。头像去掉首尾空白后为空时视为不使用头像
+    pub fn set_profile(&mut self, player_id: PlayerId, mut profile: PlayerProfile) -> GameResult {
+        profile.avatar = profile.avatar.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+        if let Some(avatar) = &profile.avatar
+            && (avatar.chars().count() > MAX_AVATAR_CHARS || avatar.chars().any(|c| c.is_whitespace() || c.is_control())) {
+            return Err(GameError::InvalidAvatar { max: MAX_AVATAR_CHARS });
+        }
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.profile = profile;
+        Ok(vec![ServerMessage::PlayerUpdated { player: player.clone() }])
+    }
+
+    /// 玩家从自己的筹码中给房主小费，只能在不参与牌局时进行
+    pub fn tip_host(&mut self, player_id: PlayerId, amount: u32) -> GameResult {
+        if self.hand_in_progress() && self.hand_player_order.contains(&player_id) {
+            return Err(GameError::TipDuringHand);
+        }
+        match self.players.get_mut(&player_id) {
+            Some(player) if amount > 0 && amount <= player.stack => {
+                player.stack -= amount;
+                let entry = LedgerEntry { player_id, kind: LedgerKind::Tip, amount };
+                self.host_ledger.entries.push(entry.clone());
+                Ok(vec![ServerMessage::HostLedgerUpdated { entry, new_stack: player.stack }])
+            }
+            _ => Err(GameError::InvalidTip),
+        }
+    }
+
+    /// 设置每赢得一个底池 (主池或边池) 给房主的小费，0 表示关闭
+    pub fn set_pot_tip(&mut self, player_id: PlayerId, amount: u32) -> GameResult {
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.pot_tip = amount;
+        Ok(vec![ServerMessage::PlayerUpdated { player: player.clone() }])
+    }
+
+    /// 玩家离座进入观战: 筹码保留在玩家身上，座位空出，从 `seated_players` 中移除。
+    /// 本局的参与者要等本局结束后才能离座。`now` 记为离座时间，用于防止抽水离场的窗口期
+    pub fn leave_seat(&mut self, player_id: PlayerId, now: DateTime<Utc>) -> GameResult {
+        if self.hand_in_progress() && self.hand_player_order.contains(&player_id) {
+            return Err(GameError::LeaveSeatDuringHand);
+        }
+        let idx = self.seated_players.iter().position(|id| *id == player_id).ok_or(GameError::NotSeated)?;
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.state = PlayerState::SittingOut;
+        player.seat_id = None;
+        self.seated_players.remove(idx);
+        let record = self.seat_ledger.entry(player_id).or_default();
+        record.left_with = player.stack;
+        record.left_at = Some(now);
+        Ok(vec![ServerMessage::PlayerUpdated { player: player.clone() }])
+    }
+
+    /// 把玩家移出房间 (被房主或投票移出): 还坐着的先离座，然后从玩家列表中删除。
+    /// 玩家还在进行中的牌局里时 (包括已经弃牌) 要等本局结束
+    pub fn remove_player(&mut self, player_id: PlayerId, now: DateTime<Utc>) -> GameResult {
+        let mut msgs = vec![];
+        if self.seated_players.contains(&player_id) {
+            msgs.extend(self.leave_seat(player_id, now)?);
+        } else if self.hand_in_progress() && self.hand_player_order.contains(&player_id) {
+            return Err(GameError::LeaveSeatDuringHand);
+        }
+        self.players.remove(&player_id).ok_or(GameError::PlayerNotFound)?;
+        msgs.push(ServerMessage::PlayerLeft { player_id });
+        Ok(msgs)
+    }
+
+    /// 玩家兑现离场: 桌上和账户中的筹码全部记入账本，净输赢就此锁定，然后离座观战。
+    /// 之后再入座相当于重新带入
+    pub fn cash_out(&mut self, player_id: PlayerId, now: DateTime<Utc>) -> GameResult {
+        if self.room_options.sit_and_go.is_some() {
+            return Err(GameError::CashOutInSitAndGo);
+        }
+        if self.hand_in_progress() && self.hand_player_order.contains(&player_id) {
+            return Err(GameError::CashOutDuringHand);
+        }
+        self.leave_seat(player_id, now)?;
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        let amount = player.stack + player.bank;
+        player.cashed_out += amount;
+        player.stack = 0;
+        player.bank = 0;
+        let entry = LedgerEntry { player_id, kind: LedgerKind::CashOut, amount };
+        self.host_ledger.entries.push(entry.clone());
+        Ok(vec![
+            ServerMessage::HostLedgerUpdated { entry, new_stack: 0 },
+            ServerMessage::PlayerUpdated { player: player.clone() },
+        ])
+    }
+
+    /// 玩家入座后收取场地费，每位玩家本次游戏只收一次
+    pub fn charge_session_fee(&mut self, player_id: PlayerId) -> Vec<ServerMessage> {
+        let fee = self.room_options.session_fee;
+        if fee == 0 || self.host_ledger.has_paid_fee(&player_id) {
+            return vec![];
+        }
+        let Some(player) = self.players.get_mut(&player_id) else { return vec![] };
+        let amount = fee.min(player.stack);
+        player.stack -= amount;
+        let entry = LedgerEntry { player_id, kind: LedgerKind::SessionFee, amount };
+        self.host_ledger.entries.push(entry.clone());
+        vec![ServerMessage::HostLedgerUpdated { entry, new_stack: player.stack }]
+    }
+
+    /// 房主批准存入申请后，把筹码记入玩家的账户
+    pub fn deposit_chips(&mut self, player_id: PlayerId, amount: u32) -> GameResult {
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.bank += amount;
+        player.buy_in += amount;
+        Ok(vec![ServerMessage::ChipsDeposited { player_id, amount, new_bank: player.bank }])
+    }
+
+    /// 开启筹码账户时，玩家入座带入 `stack` 筹码: 桌上原有的筹码先退回账户，再从账户中取出。
+    /// 账户余额不足时返回错误，不修改状态
+    ///
+    /// Sit & Go 中忽略 `stack`，入座即报名并拿到起始筹码 (买入在第一局开始时扣除)；
+    /// 比赛开始后只有还没被淘汰的参赛者可以回到座位，筹码不变
+    pub fn take_seat_stack(&mut self, player_id: PlayerId, stack: u32, now: DateTime<Utc>) -> GameResult<()> {
+        let rathole_min = self.rathole_minimum(&player_id, now);
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        if let Some(sng) = &self.room_options.sit_and_go {
+            let progress = &self.sit_and_go;
+            if progress.is_running() {
+                let alive = progress.entrants.contains(&player_id) && !progress.eliminated.contains(&player_id);
+                return if alive { Ok(()) } else { Err(GameError::SitAndGoStarted) };
+            }
+            if self.room_options.chip_accounts && player.bank < sng.buy_in {
+                return Err(GameError::InsufficientBank { available: player.bank });
+            }
+            player.stack = sng.starting_stack;
+            return Ok(());
+        }
+        if let Some(min) = rathole_min
+            && stack < min
+        {
+            return Err(GameError::RatholeMinimum { min, minutes: self.room_options.rathole_window_mins });
+        }
+        // 离座不久重新入座时，带回离座时的筹码总是允许的，即使超出了带入范围
+        if rathole_min != Some(stack) {
+            self.config.check_buy_in(stack)?;
+        }
+        if !self.room_options.chip_accounts {
+            // 没有筹码账户时，重新入座相当于补充或带走两次带入之间的差额
+            player.buy_in = (player.buy_in + stack).saturating_sub(player.stack);
+        } else {
+            let available = player.bank + player.stack;
+            if stack > available {
+                return Err(GameError::InsufficientBank { available });
+            }
+            player.bank = available - stack;
+        }
+        player.stack = stack;
+        let record = self.seat_ledger.entry(player_id).or_default();
+        record.peak_buy_in = record.peak_buy_in.max(stack);
+        record.left_at = None;
+        Ok(())
+    }
+
+    /// 防止抽水离场: 离座后窗口期内重新入座时最少要带入的筹码，没有限制时为 None。
+    /// 已经就座的玩家换座时不能借机带走桌上的筹码。`now` 是服务器的当前时间
+    pub fn rathole_minimum(&self, player_id: &PlayerId, now: DateTime<Utc>) -> Option<u32> {
+        let window = self.room_options.rathole_window_mins;
+        if window > 0 && self.seated_players.contains(player_id) {
+            return self.players.get(player_id).map(|p| p.stack).filter(|stack| *stack > 0);
+        }
+        let record = self.seat_ledger.get(player_id)?;
+        let left_at = record.left_at?;
+        let within = now - left_at < chrono::Duration::minutes(window as i64);
+        (window > 0 && within && record.left_with > 0).then_some(record.left_with)
+    }
+
+    /// 本次游戏的汇总: 每位玩家的筹码、输赢次数和净输赢，房主账本，以及结算转账
+    pub fn session_summary(&self) -> SessionSummary {
+        let mut players: Vec<PlayerSummary> = self
+            .players
+            .values()
+            .map(|p| PlayerSummary {
+                player_id: p.id,
+                nickname: p.nickname.clone(),
+                stack: p.stack,
+                wins: p.wins,
+                losses: p.losses,
+                buy_in: p.buy_in,
+                peak_buy_in: self.seat_ledger.get(&p.id).map_or(0, |r| r.peak_buy_in),
+                cashed_out: p.cashed_out,
+                net: (p.stack + p.bank + p.cashed_out) as i64 - p.buy_in as i64,
+            })
+            .collect();
+        players.sort_by_key(|p| std::cmp::Reverse(p.stack));
+        let settlements = settle(&players);
+        SessionSummary { players, host_ledger: self.host_ledger.clone(), settlements }
+    }
+
+    /// 在两局之间加入或退出复式对局。广播的消息中不包含洗牌的种子
+    pub fn set_duplicate(&mut self, duplicate: Option<DuplicateDeal>) -> GameResult {
+        if self.hand_in_progress() {
+            return Err(GameError::HandInProgress);
+        }
+        self.duplicate = duplicate;
+        let public = duplicate.map(|d| DuplicateDeal { seed: 0, ..d });
+        Ok(vec![ServerMessage::DuplicateUpdated { duplicate: public }])
+    }
+
+    /// 在两局之间随机重新分配就座玩家的座位，并重新随机抽取庄家位置
+    pub fn shuffle_seats(&mut self) -> GameResult {
+        if self.hand_in_progress() {
+            return Err(GameError::ShuffleDuringHand);
+        }
+        let mut rng = rand::rng();
+        let seat_ids: Vec<u8> = self
+            .seated_players
+            .iter()
+            .filter_map(|id| self.players.get(id).and_then(|p| p.seat_id))
+            .collect();
+        let mut ids: Vec<PlayerId> = self.seated_players.iter().cloned().collect();
+        ids.shuffle(&mut rng);
+        for (id, seat_id) in ids.iter().zip(seat_ids) {
+            self.players.get_mut(id).ok_or(GameError::PlayerNotFound)?.seat_id = Some(seat_id);
+        }
+
+        // 按新的座位号排列，然后随机选择一个起始位置作为新的庄家抽签结果
+        ids.sort_by_key(|id| self.players.get(id).and_then(|p| p.seat_id));
+        if !ids.is_empty() {
+            let start = rng.random_range(0..ids.len());
+            ids.rotate_left(start);
+        }
+        self.seated_players = ids.into();
+        // 重新抽取庄家后，下一局按新的就座顺序确定庄家和盲注
+        self.button = None;
+
+        let seats = self
+            .seated_players
+            .iter()
+            .filter_map(|id| self.players.get(id)?.seat_id.map(|s| (*id, s)))
+            .collect();
+        Ok(vec![ServerMessage::SeatsShuffled {
+            seated_players: self.seated_players.clone(),
+            seats,
+        }])
+    }
+
+    /// 错过盲注的玩家选择补交死盲，下一局开始时将立即入局。暂时离席的玩家同时回到座位
+    pub fn request_dead_blind(&mut self, player_id: PlayerId) -> GameResult {
+        let sit_out = match self.players.get(&player_id) {
+            Some(player) if player.seat_id.is_some() && player.missed_blinds != MissedBlinds::None => player.sit_out,
+            _ => return Err(GameError::NoMissedBlinds),
+        };
+        if sit_out {
+            self.sit_in(player_id)?;
+        }
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.post_dead_blind = true;
+        Ok(vec![ServerMessage::PlayerUpdated { player: player.clone() }])
+    }
+
+    /// 玩家暂时离席，保留座位和筹码。本局的参与者打完本局，从下一局开始离席
+    pub fn sit_out(&mut self, player_id: PlayerId) -> GameResult {
+        let in_hand = self.hand_in_progress() && self.hand_player_order.contains(&player_id);
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        if player.seat_id.is_none() {
+            return Err(GameError::NotSeated);
+        }
+        player.sit_out = true;
+        player.post_dead_blind = false;
+        if !in_hand {
+            player.state = PlayerState::SittingOut;
+            self.seated_players.retain(|id| *id != player_id);
+        }
+        Ok(vec![ServerMessage::PlayerUpdated { player: player.clone() }])
+    }
+
+    /// 离席的玩家回到原来的座位，从下一局开始入局。
+    /// 错过了盲注时等到大盲位再入局，补交死盲立即入局见 `request_dead_blind`
+    pub fn sit_in(&mut self, player_id: PlayerId) -> GameResult {
+        let player = self.players.get(&player_id).ok_or(GameError::PlayerNotFound)?;
+        let seat_id = player.seat_id.ok_or(GameError::NotSeated)?;
+        if player.stack == 0 {
+            return Err(GameError::SitInWithoutChips);
+        }
+        let insert_at = if self.seated_players.contains(&player_id) {
+            None
+        } else {
+            Some(self.find_insertion_index(seat_id).unwrap_or(self.seated_players.len()))
+        };
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.sit_out = false;
+        player.post_dead_blind = false;
+        if let Some(idx) = insert_at {
+            player.state = PlayerState::Waiting;
+            self.seated_players.insert(idx, player_id);
+        }
+        Ok(vec![ServerMessage::PlayerUpdated { player: player.clone() }])
+    }
+
+    /// 检查新的盲注是否有效，锦标赛模式下盲注由盲注结构决定
+    pub fn check_blinds(&self, small_blind: u32, big_blind: u32) -> GameResult<()> {
+        if self.room_options.tournament.is_some() {
+            return Err(GameError::TournamentBlinds);
+        }
+        if small_blind == 0 || small_blind > big_blind {
+            return Err(GameError::InvalidBlinds);
+        }
+        Ok(())
+    }
+
+    /// 修改盲注，新的盲注从下一局开始收取
+    pub fn set_blinds(&mut self, small_blind: u32, big_blind: u32) -> GameResult {
+        self.check_blinds(small_blind, big_blind)?;
+        self.small_blind = small_blind;
+        self.big_blind = big_blind;
+        Ok(vec![ServerMessage::BlindsChanged { small_blind, big_blind }])
+    }
+
+    /// 玩家申请在下一局抓位，开局时不在抓位位置的申请作废
+    pub fn request_straddle(&mut self, player_id: PlayerId) -> GameResult {
+        if self.room_options.straddle.is_none() {
+            return Err(GameError::StraddleDisabled);
+        }
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        if player.seat_id.is_none() {
+            return Err(GameError::NotSeated);
+        }
+        player.post_straddle = true;
+        Ok(vec![ServerMessage::PlayerUpdated { player: player.clone() }])
     }
 }
 
@@ -80,42 +593,54 @@ impl GameState {
     ///
     /// # Returns
     /// 返回一个消息列表，描述新牌局开始时发生的事件 (如：盲注、轮到谁行动等)。
-    /// # Panics
-    /// 如果活跃玩家少于2人，则会 panic，因为游戏无法开始。
-    pub fn start_new_hand(&mut self) -> Vec<ServerMessage> {
+    /// 上一局还没有结束时返回错误；活跃玩家少于 2 人时不会开局，阶段保持为 WaitingForPlayers。
+    /// `now` 是服务器的当前时间，记为开局时间，按时间升级的锦标赛据此判断本级盲注是否到时。
+    pub fn start_new_hand(&mut self, now: DateTime<Utc>) -> GameResult {
+        if self.hand_in_progress() {
+            return Err(GameError::HandInProgress);
+        }
         let mut messages = Vec::new();
 
-
         // 在新一局开始前，将所有离线玩家的状态变更为离席
         let mut sitting_out_indices = vec![];
         for (i, player_id) in self.seated_players.iter().enumerate() {
-            if let Some(p) = self.players.get_mut(player_id) {
-                if p.is_offline || p.stack == 0 {
-                    p.state = PlayerState::SittingOut;
-                    sitting_out_indices.push(i);
-                }
+            if let Some(p) = self.players.get_mut(player_id)
+                && (p.is_offline || p.sit_out || p.stack == 0) {
+                p.state = PlayerState::SittingOut;
+                sitting_out_indices.push(i);
             }
         }
         for i in sitting_out_indices.iter().rev() {
             self.seated_players.remove(*i);
         }
 
-        // 验证游戏开始的条件 (从轮换后的新顺序中过滤)
-        self.hand_player_order = self
+        // 验证游戏开始的条件，按死庄规则确定庄家和盲注
+        let candidates: Vec<PlayerId> = self
             .seated_players
             .iter()
             .filter(|id| {
                 self.players
                     .get(id)
-                    .map_or(false, |p| p.state != PlayerState::SittingOut && p.stack > 0)
+                    .is_some_and(|p| p.state != PlayerState::SittingOut && p.stack > 0)
             })
             .cloned()
             .collect();
+        let prev_button = self.button;
+        let (hand_player_order, button) = match self.move_button(&candidates) {
+            Some(moved) => moved,
+            None => self.first_button(candidates),
+        };
+        self.hand_player_order = hand_player_order;
 
         let active_player_count = self.hand_player_order.len();
         if active_player_count < 2 {
             self.phase = GamePhase::WaitingForPlayers;
-            return messages; // 无法开始，返回空消息列表
+            return Ok(messages); // 无法开始，返回空消息列表
+        }
+        self.button = button;
+        // 庄家排在就座顺序的第一位
+        if let Some(pos) = self.seated_players.iter().position(|id| *id == self.hand_player_order[0]) {
+            self.seated_players.rotate_left(pos);
         }
 
         // 更新 PlayerId -> index 的映射
@@ -126,84 +651,169 @@ impl GameState {
             .map(|(i, id)| (*id, i))
             .collect();
 
+        messages.extend(self.start_sit_and_go());
+
+        // 锦标赛模式下按局数升级盲注
+        messages.extend(self.update_blind_level(now)?);
+
+        // 确定本局玩法: 庄家选择模式下优先使用上一局庄家选择的玩法，其次是混合轮换中的玩法
+        let chosen = self
+            .chosen_variant
+            .take()
+            .filter(|v| self.room_options.dealers_choice.contains(v));
+        let (rotated, rotation_messages) = self.advance_rotation();
+        messages.extend(rotation_messages);
+        self.variant = chosen.or(rotated).unwrap_or(self.room_options.variant);
+        self.betting_structure = self.room_options.betting_structure;
+
+        // 本局是否为炸弹底池: 房主安排的一次，或者每隔几局一次
+        let every = self.room_options.bomb_pot_every;
+        let queued = std::mem::take(&mut self.bomb_pot_queued);
+        self.bomb_pot = self.room_options.bomb_pot_ante > 0
+            && (queued || (every > 0 && self.hands_since_bomb_pot + 1 >= every));
+        self.hands_since_bomb_pot = if self.bomb_pot { 0 } else { self.hands_since_bomb_pot + 1 };
+
         // 发送新牌局开始的消息
         messages.push(ServerMessage::HandStarted {
             seated_players: self.seated_players.clone(),
             hand_player_order: self.hand_player_order.clone(),
+            variant: self.variant,
+            betting_structure: self.betting_structure,
+            button: self.button,
+            started_at: now,
         });
+        messages.extend(self.record_missed_blinds(prev_button));
 
         // 重置状态
         self.pot = 0;
-        self.community_cards = vec![None; 5];
+        self.community_cards = vec![vec![None; self.variant.community_card_count()]; self.board_count()];
         self.max_bet = 0;
         self.last_bet = 0;
+        self.last_aggressor = None;
 
         // 初始化基于Vec的结构
-        self.player_cards = vec![(None, None); active_player_count];
+        let hole_card_count = self.variant.hole_card_count();
+        self.player_cards = vec![vec![None; hole_card_count]; active_player_count];
         self.bets = vec![0; active_player_count];
+        self.dead_bets = vec![0; active_player_count];
+        self.forced_bets.clear();
+        self.street_levels.clear();
+        self.starting_stacks = self.hand_player_order.iter()
+            .map(|id| self.players.get(id).map_or(0, |p| p.stack))
+            .collect();
+        self.dead_money = 0;
+        self.straddle = 0;
+        self.mucked = vec![false; active_player_count];
+        self.shown = vec![false; active_player_count];
+        self.shown_cards.clear();
+        self.showdown_queue.clear();
+        self.insurance = None;
         // 初始化 player_has_acted 状态，所有人都未行动
         self.player_has_acted = vec![false; active_player_count];
         // 初始化最小加注额为大盲注
         self.last_raise_amount = self.big_blind;
 
-        // 洗牌
-        self.deck = generate_random_hand(active_player_count);
+        // 洗牌 (多块公共牌或多张底牌需要更多的牌，直接使用整副牌)；复式对局按种子使用这一局约定的牌
+        let mut rotation = 0;
+        self.deck = if let Some(duplicate) = &mut self.duplicate {
+            rotation = duplicate.table as usize % active_player_count;
+            duplicate.hands += 1;
+            seeded_deck(duplicate.hand_seed(duplicate.hands))
+        } else if self.community_cards.len() > 1 || hole_card_count > 2 {
+            generate_shuffled_deck()
+        } else {
+            generate_random_hand(active_player_count)
+        };
 
-        // 发底牌并设置玩家状态
-        for (idx, player_id) in self.hand_player_order.iter().enumerate() {
+        // 发底牌并设置玩家状态。复式对局中按牌桌的序号轮转底牌，第 i 位拿到第 i + rotation 位的牌
+        for player_id in self.hand_player_order.iter() {
             if let Some(player) = self.players.get_mut(player_id) {
                 player.state = PlayerState::Playing;
-                let card1 = self.deck.pop().unwrap();
-                let card2 = self.deck.pop().unwrap();
-                self.player_cards[idx] = (Some(card1), Some(card2));
+            }
+        }
+        for k in 0..active_player_count {
+            let idx = (k + active_player_count - rotation) % active_player_count;
+            for slot in self.player_cards[idx].iter_mut() {
+                *slot = self.deck.pop();
             }
         }
 
+        // 抓位申请只对本局有效
+        let straddlers: Vec<PlayerId> = self.players.values_mut()
+            .filter_map(|p| std::mem::take(&mut p.post_straddle).then_some(p.id))
+            .collect();
+
+        // 炸弹底池: 所有人下底注，跳过翻牌前下注直接发翻牌。底注代替了盲注，入局玩家错过的盲注视为已经补齐
+        if self.bomb_pot {
+            for player_id in self.hand_player_order.iter() {
+                let player = self.players.get_mut(player_id).ok_or(GameError::PlayerNotFound)?;
+                if player.missed_blinds != MissedBlinds::None {
+                    player.missed_blinds = MissedBlinds::None;
+                    player.post_dead_blind = false;
+                    messages.push(ServerMessage::PlayerUpdated { player: player.clone() });
+                }
+            }
+            messages.extend(self.post_bomb_pot_antes()?);
+            return Ok(messages);
+        }
+
+        // 前注在盲注之前收取
+        messages.extend(self.post_antes()?);
+
         // 5. 处理盲注，增加两人单挑(Heads-up)的特殊逻辑
         let sb_idx;
         let bb_idx;
-        let first_to_act_idx;
+        let mut first_to_act_idx;
 
         if active_player_count == 2 {
             // 两人单挑规则:
             // - 庄家 (index 0) 是小盲, 翻牌前先行动
             // - 另一个玩家 (index 1) 是大盲
-            sb_idx = 0;
+            sb_idx = Some(0);
             bb_idx = 1;
             first_to_act_idx = 0;
+        } else if self.button.is_some_and(|b| b.dead_small_blind) {
+            // 死小盲: 庄家 (index 0) 之后直接是大盲 (index 1)，本局不收小盲
+            sb_idx = None;
+            bb_idx = 1;
+            first_to_act_idx = 2 % active_player_count;
         } else {
             // 3人及以上规则:
             // - 庄家 (index 0)
             // - 小盲 (index 1)
             // - 大盲 (index 2)
             // - 枪口位 (大盲后，index 3) 先行动
-            sb_idx = 1 % active_player_count;
+            sb_idx = Some(1 % active_player_count);
             bb_idx = 2 % active_player_count;
             first_to_act_idx = (bb_idx + 1) % active_player_count;
         }
 
         // 小盲注
-        let sb_id = self.hand_player_order[sb_idx];
-        let sb_player = self.players.get_mut(&sb_id).unwrap();
-        let sb_amount = self.small_blind.min(sb_player.stack);
-        sb_player.stack -= sb_amount;
-        self.pot += sb_amount;
-        self.bets[sb_idx] = sb_amount;
-        if sb_player.stack == 0 {
-            sb_player.state = PlayerState::AllIn;
-        }
-        // 为小盲注生成 PlayerActed 消息
-        messages.push(ServerMessage::PlayerActed {
-            player_id: sb_id,
-            action: PlayerAction::BetOrRaise(sb_amount),
-            total_bet: self.bets[sb_idx],
-            new_stack: self.players.get(&sb_id).unwrap().stack,
-            new_pot: self.pot,
-        });
+        if let Some(sb_idx) = sb_idx {
+            let sb_id = self.hand_player_order[sb_idx];
+            let sb_player = self.players.get_mut(&sb_id).ok_or(GameError::PlayerNotFound)?;
+            let sb_amount = self.small_blind.min(sb_player.stack);
+            sb_player.stack -= sb_amount;
+            self.pot += sb_amount;
+            self.bets[sb_idx] = sb_amount;
+            if sb_player.stack == 0 {
+                sb_player.state = PlayerState::AllIn;
+            }
+            let sb_stack = sb_player.stack;
+            // 为小盲注生成 PlayerActed 消息
+            messages.push(ServerMessage::PlayerActed {
+                player_id: sb_id,
+                action: PlayerAction::BetOrRaise(sb_amount),
+                total_bet: self.bets[sb_idx],
+                new_stack: sb_stack,
+                new_pot: self.pot,
+                at: now,
+            });
+        }
 
         // 大盲注
         let bb_id = self.hand_player_order[bb_idx];
-        let bb_player = self.players.get_mut(&bb_id).unwrap();
+        let bb_player = self.players.get_mut(&bb_id).ok_or(GameError::PlayerNotFound)?;
         let bb_amount = self.big_blind.min(bb_player.stack);
         bb_player.stack -= bb_amount;
         self.pot += bb_amount;
@@ -211,216 +821,650 @@ impl GameState {
         if bb_player.stack == 0 {
             bb_player.state = PlayerState::AllIn;
         }
+        let bb_stack = bb_player.stack;
         // 为大盲注生成 PlayerActed 消息
         messages.push(ServerMessage::PlayerActed {
             player_id: bb_id,
             action: PlayerAction::BetOrRaise(bb_amount),
             total_bet: self.bets[bb_idx],
-            new_stack: self.players.get(&bb_id).unwrap().stack,
+            new_stack: bb_stack,
             new_pot: self.pot,
+            at: now,
         });
 
         self.max_bet = self.big_blind;
+        self.last_aggressor = Some(bb_id);
+        messages.extend(self.post_big_blind_ante(bb_idx)?);
+
+        // 补交死盲: 错过盲注的玩家交一个死的小盲 (不计入下注额)，错过了大盲时再加一个活的大盲
+        for idx in 0..active_player_count {
+            let player_id = self.hand_player_order[idx];
+            let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+            let missed = std::mem::take(&mut player.missed_blinds);
+            if missed == MissedBlinds::None {
+                continue;
+            }
+            player.post_dead_blind = false;
+            // 本局正好轮到盲注位的玩家正常下盲注即可
+            if Some(idx) == sb_idx || idx == bb_idx {
+                messages.push(ServerMessage::PlayerUpdated { player: player.clone() });
+                continue;
+            }
+
+            let dead_amount = self.small_blind.min(player.stack);
+            player.stack -= dead_amount;
+            let live_amount = if missed == MissedBlinds::Big { self.big_blind.min(player.stack) } else { 0 };
+            player.stack -= live_amount;
+            self.pot += dead_amount + live_amount;
+            self.dead_bets[idx] += dead_amount;
+            self.bets[idx] = live_amount;
+            if player.stack == 0 {
+                player.state = PlayerState::AllIn;
+            }
+            messages.push(ServerMessage::DeadBlindPosted {
+                player_id,
+                dead_amount,
+                live_amount,
+                new_stack: player.stack,
+                new_pot: self.pot,
+            });
+        }
+
+        // 抓位: 两人单挑时没有抓位位置
+        let straddle_idx = match self.room_options.straddle {
+            _ if active_player_count < 3 => None,
+            Some(StraddlePosition::Utg) => Some((bb_idx + 1) % active_player_count),
+            Some(StraddlePosition::Button) => Some(0),
+            None => None,
+        };
+        if let Some(idx) = straddle_idx
+            && straddlers.contains(&self.hand_player_order[idx])
+            && let Some(msg) = self.post_straddle(idx)? {
+            messages.push(msg);
+            first_to_act_idx = (idx + 1) % active_player_count;
+        }
+
+        // 盲注、死盲和抓位都已下完，之后的下注都是玩家主动下的
+        self.forced_bets = self.bets.clone();
 
-        // 设置游戏阶段和第一个行动者
+        // 设置游戏阶段，从第一个行动者开始寻找可以行动的玩家:
+        // 盲注可能让玩家全下，需要跳过他们，甚至没有人能行动而直接发完公共牌
         self.phase = GamePhase::PreFlop;
-        self.cur_player_idx = first_to_act_idx;
-
-        // 增加轮到谁行动的消息
-        messages.push(ServerMessage::NextToAct {
-            player_id: self.hand_player_order[self.cur_player_idx],
-            valid_actions: vec![
-                PlayerActionType::Call(self.max_bet - self.bets[self.cur_player_idx]),
-                PlayerActionType::Raise(self.last_raise_amount),
-                PlayerActionType::Fold
-            ],
-        });
+        self.cur_player_idx = (first_to_act_idx + active_player_count - 1) % active_player_count;
+        if self.check_betting_round_over() {
+            messages.extend(self.advance_to_next_phase()?);
+        } else {
+            messages.extend(self.advance_to_next_player()?);
+        }
 
-        messages
+        Ok(messages)
     }
 
-    /// 处理自动玩家（如离线玩家）的行动。
-    ///
-    /// 服务器可以在一个循环中调用此函数，直到它返回 false。
-    /// 当轮到一个需要人类输入的玩家时，它会返回 false。
+    /// 没有上一局的位置 (第一局、重新抽取庄家后或玩家没有座位号) 时，按就座顺序确定庄家和盲注:
+    /// 第一位是庄家，之后依次是小盲和大盲。错过盲注且没有选择补交死盲的玩家只能在大盲位入局
+    fn first_button(&self, mut order: Vec<PlayerId>) -> (Vec<PlayerId>, Option<ButtonPositions>) {
+        // 移除玩家会改变大盲位置，因此反复过滤直到结果稳定
+        loop {
+            let before = order.len();
+            let bb_pos = if before == 2 { 1 } else { 2 % before.max(1) };
+            let mut pos = 0;
+            order.retain(|id| {
+                let keep = pos == bb_pos
+                    || self.players.get(id).is_some_and(|p| !p.waits_for_big_blind());
+                pos += 1;
+                keep
+            });
+            if order.len() == before {
+                break;
+            }
+        }
+        let seats: Option<Vec<u8>> = order.iter().map(|id| self.players.get(id)?.seat_id).collect();
+        let button = match seats.as_deref() {
+            Some(&[button, big_blind]) => Some(ButtonPositions { button, small_blind: button, big_blind, dead_small_blind: false }),
+            Some(&[button, small_blind, big_blind, ..]) => Some(ButtonPositions { button, small_blind, big_blind, dead_small_blind: false }),
+            _ => None,
+        };
+        (order, button)
+    }
+
+    /// 按死庄规则从上一局的位置移动庄家和盲注，返回本局的玩家顺序 (庄家在第一位) 和位置。
+    /// 没有上一局的位置或有玩家没有座位号时返回 None
     ///
-    /// # Returns
-    /// - `(bool, Vec<ServerMessage>)`: 元组的第一个元素表示是否执行了自动行动，
-    ///   第二个元素是该行动产生的消息列表。
-    pub fn tick(&mut self) -> (bool, Vec<ServerMessage>) {
-        // 游戏结束、等待或没有轮到任何人行动
-        if matches!(
-            self.phase,
-            GamePhase::WaitingForPlayers | GamePhase::Showdown
-        ) {
-            return (false, vec![]);
+    /// 大盲移到上一局大盲之后的第一位玩家；小盲位是上一局的大盲位，庄位是上一局的小盲位，座位上没有玩家时是死小盲或死庄。
+    /// 错过盲注的玩家只能在大盲位入局，新坐到庄位和大盲之间的玩家要等庄家移过去之后才能入局
+    fn move_button(&self, candidates: &[PlayerId]) -> Option<(Vec<PlayerId>, Option<ButtonPositions>)> {
+        let prev = self.button?;
+        let seated: Vec<(PlayerId, u8)> = candidates.iter()
+            .map(|id| Some((*id, self.players.get(id)?.seat_id?)))
+            .collect::<Option<_>>()?;
+        // 从 `from` 顺时针数到 `seat` 的距离，`from` 自己最远
+        let after = |from: u8, seat: u8| seat.wrapping_sub(from).wrapping_sub(1);
+        let (bb, bb_seat) = *seated.iter().min_by_key(|(_, seat)| after(prev.big_blind, *seat))?;
+        let mut seated: Vec<(PlayerId, u8)> = seated.into_iter()
+            .filter(|(id, _)| *id == bb || self.players.get(id).is_some_and(|p| !p.waits_for_big_blind()))
+            .collect();
+
+        if seated.len() > 2 {
+            let (button_seat, sb_seat) = (prev.small_blind, prev.big_blind);
+            seated.retain(|(_, seat)| {
+                *seat == sb_seat || after(button_seat, *seat) >= after(button_seat, bb_seat)
+            });
+        }
+        if seated.len() < 2 {
+            return Some((seated.into_iter().map(|(id, _)| id).collect(), None));
+        }
+        if seated.len() == 2 {
+            // 两人单挑: 大盲之外的玩家是庄家兼小盲
+            let (button, button_seat) = *seated.iter().find(|(id, _)| *id != bb)?;
+            let positions = ButtonPositions { button: button_seat, small_blind: button_seat, big_blind: bb_seat, dead_small_blind: false };
+            return Some((vec![button, bb], Some(positions)));
         }
 
-        let player_id = self.current_player_id().unwrap();
-        let is_auto_action = self
-            .players
-            .get(&player_id)
-            .map_or(false, |p| p.is_offline);
+        let (button_seat, sb_seat) = (prev.small_blind, prev.big_blind);
+        let positions = ButtonPositions {
+            button: button_seat,
+            small_blind: sb_seat,
+            big_blind: bb_seat,
+            dead_small_blind: seated.iter().all(|(_, seat)| *seat != sb_seat),
+        };
+        // 庄位上没有玩家时，由庄位右手边最近的玩家排在第一位，翻牌后最后行动
+        let (_, first_seat) = *seated.iter().max_by_key(|(_, seat)| after(button_seat, *seat))?;
+        seated.sort_by_key(|(_, seat)| seat.wrapping_sub(first_seat));
+        Some((seated.into_iter().map(|(id, _)| id).collect(), Some(positions)))
+    }
 
-        if is_auto_action {
-            let player_idx = *self.player_indices.get(&player_id).unwrap();
-            let amount_to_call = self.max_bet - self.bets[player_idx];
-            let action = if amount_to_call == 0 {
-                PlayerAction::Check
-            } else {
-                PlayerAction::Fold
+    /// 盲注移过离席玩家 (保留座位且还有筹码) 的座位时，记为错过了对应的盲注，有变化的玩家生成 `PlayerUpdated`。
+    /// 没有上一局或本局的位置时无法判断盲注经过了哪些座位，离席的玩家都记为错过大盲
+    fn record_missed_blinds(&mut self, prev: Option<ButtonPositions>) -> Vec<ServerMessage> {
+        let current = self.button;
+        let after = |from: u8, seat: u8| seat.wrapping_sub(from).wrapping_sub(1);
+        // 盲注从 `from` 移到 `to` 时是否经过了 `seat`
+        let passes = |from: u8, to: u8, seat: u8| from != to && after(from, seat) <= after(from, to);
+        let mut messages = Vec::new();
+        for player in self.players.values_mut() {
+            let Some(seat) = player.seat_id else { continue };
+            if player.state != PlayerState::SittingOut || player.stack == 0 {
+                continue;
+            }
+            let missed = match (prev, current) {
+                (Some(prev), Some(cur)) if passes(prev.big_blind, cur.big_blind, seat) => MissedBlinds::Big,
+                (Some(prev), Some(cur)) if passes(prev.small_blind, cur.small_blind, seat) => MissedBlinds::Small,
+                (Some(_), Some(_)) => MissedBlinds::None,
+                _ => MissedBlinds::Big,
             };
-
-            // 调用 handle_player_action 并捕获其返回的消息
-            let messages = self.handle_player_action(player_id, action);
-            (true, messages)
-        } else {
-            (false, vec![])
+            if missed > player.missed_blinds {
+                player.missed_blinds = missed;
+                messages.push(ServerMessage::PlayerUpdated { player: player.clone() });
+            }
         }
+        messages
     }
 
-    /// 处理单个玩家的动作
-    ///
-    /// 这是游戏逻辑的核心驱动函数之一。它接收一个玩家的动作，
-    /// 验证其合法性，然后更新游戏状态。
-    /// - 扣除筹码，增加奖池。
-    /// - 更改玩家状态 (如 Folded, AllIn)。
-    /// - 更新当前轮的最大下注额。
-    ///
-    /// 在处理完动作后，它会检查当前下注轮是否结束。
-    /// 如果是，则推进到下一个游戏阶段 (e.g., Flop -> Turn)。
-    /// 如果否，则将行动权转移给下一个玩家。
-    ///
-    /// # Returns
-    /// 返回一个消息列表，描述该动作引发的所有状态变更。
-    pub fn handle_player_action(
-        &mut self,
-        player_id: PlayerId,
-        action: PlayerAction,
-    ) -> Vec<ServerMessage> {
+    /// 每位玩家交前注。前注记为死筹码，参与边池的计算但不计入下注额；
+    /// 筹码不够交前注的玩家交出全部筹码并全下
+    fn post_antes(&mut self) -> GameResult {
+        let ante = self.room_options.ante;
+        if ante == 0 || self.room_options.big_blind_ante {
+            return Ok(vec![]);
+        }
         let mut messages = Vec::new();
-        if self.current_player_id() != Some(player_id) {
-            messages.push(ServerMessage::Error {
-                message: "当前不该你行动".to_string(),
-            });
-            return messages;
+        for player_id in self.hand_player_order.clone() {
+            let stack = self.players.get(&player_id).ok_or(GameError::PlayerNotFound)?.stack;
+            let amount = ante.min(stack);
+            let event = ServerMessage::AntePosted { player_id, amount, new_stack: stack - amount, new_pot: self.pot + amount };
+            self.emit(event, &mut messages);
         }
+        Ok(messages)
+    }
 
-        let player_idx = *self.player_indices.get(&player_id).unwrap();
-        let player_total_bet = self.bets[player_idx];
-        let amount_to_call = self.max_bet - player_total_bet;
+    /// 大盲前注: 大盲下完盲注后用剩余的筹码替整桌交前注。前注记为不属于任何玩家的死筹码，
+    /// 结算时并入主池，不会让大盲多出一个只有自己能争夺的边池
+    fn post_big_blind_ante(&mut self, bb_idx: usize) -> GameResult {
+        let ante = self.room_options.ante;
+        if ante == 0 || !self.room_options.big_blind_ante {
+            return Ok(vec![]);
+        }
+        let bb_id = self.hand_player_order[bb_idx];
+        let stack = self.players.get(&bb_id).ok_or(GameError::PlayerNotFound)?.stack;
+        let amount = ante.min(stack);
+        if amount == 0 {
+            return Ok(vec![]);
+        }
+        let mut messages = Vec::new();
+        let event = ServerMessage::AntePosted { player_id: bb_id, amount, new_stack: stack - amount, new_pot: self.pot + amount };
+        self.emit(event, &mut messages);
+        Ok(messages)
+    }
 
-        {
-            let player = self.players.get_mut(&player_id).unwrap();
-            match action {
-                PlayerAction::Fold => {
-                    player.state = PlayerState::Folded;
-                }
-                PlayerAction::Check => {
-                    // 必须是无人下注（或大盲注无人加注）时才能过牌
-                    if amount_to_call != 0 {
-                        messages.push(ServerMessage::Error {
-                            message: format!("当前有人下注 {}，你至少要下注和他相等", amount_to_call),
-                        });
-                        return messages;
-                    }
+    /// 服务器自己也通过 `apply_event` 应用生成的事件，再把事件加入要广播的消息，
+    /// 这样服务器和客户端共用同一份状态变更代码
+    fn emit(&mut self, event: ServerMessage, messages: &mut Vec<ServerMessage>) {
+        self.apply_event(&event, None);
+        messages.push(event);
+    }
+
+    /// 抓位玩家下两倍大盲的活注，成为翻牌前最后行动的玩家。完整的抓位之后，最小加注额等于抓位注；
+    /// 已经全下或刚补交了死盲的玩家不再抓位
+    fn post_straddle(&mut self, idx: usize) -> GameResult<Option<ServerMessage>> {
+        let player_id = self.hand_player_order[idx];
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        if player.state != PlayerState::Playing || self.bets[idx] > 0 {
+            return Ok(None);
+        }
+        let amount = (self.big_blind * 2).min(player.stack);
+        player.stack -= amount;
+        self.pot += amount;
+        self.bets[idx] = amount;
+        if player.stack == 0 {
+            player.state = PlayerState::AllIn;
+        }
+        if amount > self.max_bet {
+            if amount == self.big_blind * 2 {
+                self.last_raise_amount = amount;
+            }
+            self.max_bet = amount;
+            self.straddle = amount;
+            self.last_aggressor = Some(player_id);
+        }
+        Ok(Some(ServerMessage::StraddlePosted { player_id, amount, new_stack: player.stack, new_pot: self.pot }))
+    }
+
+    /// 炸弹底池开局: 每位玩家下相同的底注，然后直接进入翻牌圈
+    fn post_bomb_pot_antes(&mut self) -> GameResult {
+        let mut messages = Vec::new();
+        let ante = self.room_options.bomb_pot_ante;
+        for player_id in self.hand_player_order.clone() {
+            let stack = self.players.get(&player_id).ok_or(GameError::PlayerNotFound)?.stack;
+            let amount = ante.min(stack);
+            let event = ServerMessage::BombPotPosted { player_id, amount, new_stack: stack - amount, new_pot: self.pot + amount };
+            self.emit(event, &mut messages);
+        }
+        self.phase = GamePhase::PreFlop;
+        messages.extend(self.advance_to_next_phase()?);
+        Ok(messages)
+    }
+
+    /// Sit & Go 的第一局开始时，本局发牌的玩家成为参赛者，开启筹码账户时从账户中扣除买入
+    fn start_sit_and_go(&mut self) -> Vec<ServerMessage> {
+        let Some(sng) = &self.room_options.sit_and_go else { return vec![] };
+        if self.sit_and_go.is_running() {
+            return vec![];
+        }
+        let entrants = self.hand_player_order.clone();
+        let prize_pool = sng.prize_pool(entrants.len()).unwrap_or(u32::MAX);
+        if self.room_options.chip_accounts {
+            for id in entrants.iter() {
+                if let Some(p) = self.players.get_mut(id) {
+                    p.bank = p.bank.saturating_sub(sng.buy_in);
                 }
-                PlayerAction::Call => {
-                    if amount_to_call > 0 {
-                        let call_amount = amount_to_call.min(player.stack);
-                        player.stack -= call_amount;
-                        self.pot += call_amount;
-                        self.bets[player_idx] += call_amount;
-                        if player.stack == 0 {
-                            player.state = PlayerState::AllIn;
-                        }
-                    }
+            }
+        }
+        self.sit_and_go = SitAndGoProgress { entrants: entrants.clone(), ..Default::default() };
+        vec![ServerMessage::SitAndGoStarted { entrants, prize_pool }]
+    }
+
+    /// 一局结束后淘汰输光筹码的参赛者。同一局被淘汰的玩家中，本局开始时筹码多的名次靠前。
+    /// 只剩一名参赛者时比赛结束: 按名次分配奖池 (开启筹码账户时记入账户)，比赛筹码清零
+    fn eliminate_busted_players(&mut self) -> Vec<ServerMessage> {
+        let Some(sng) = &self.room_options.sit_and_go else { return vec![] };
+        if !self.sit_and_go.is_running() {
+            return vec![];
+        }
+        let mut busted: Vec<(u32, PlayerId)> = self
+            .hand_player_order
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| {
+                self.sit_and_go.entrants.contains(id)
+                    && !self.sit_and_go.eliminated.contains(id)
+                    && self.players.get(id).is_some_and(|p| p.stack == 0)
+            })
+            .map(|(i, id)| (self.bets[i] + self.dead_bets.get(i).copied().unwrap_or(0), *id))
+            .collect();
+        busted.sort_by_key(|(contributed, _)| *contributed);
+
+        let mut messages = vec![];
+        for (_, player_id) in busted {
+            self.sit_and_go.eliminated.push(player_id);
+            let place = self.sit_and_go.place_of(&player_id).unwrap_or(0);
+            messages.push(ServerMessage::PlayerEliminated { player_id, place });
+        }
+
+        let alive: Vec<PlayerId> = self
+            .sit_and_go
+            .entrants
+            .iter()
+            .filter(|id| !self.sit_and_go.eliminated.contains(id))
+            .cloned()
+            .collect();
+        if alive.len() != 1 {
+            return messages;
+        }
+        let entrants = self.sit_and_go.entrants.len();
+        let prizes = sng.prizes(sng.prize_pool(entrants).unwrap_or(u32::MAX), entrants);
+        let standings = alive.into_iter().chain(self.sit_and_go.eliminated.iter().rev().cloned());
+        let results: Vec<SitAndGoResult> = standings
+            .enumerate()
+            .map(|(i, player_id)| SitAndGoResult {
+                player_id,
+                place: i as u32 + 1,
+                prize: prizes.get(i).copied().unwrap_or(0),
+            })
+            .collect();
+        for result in results.iter() {
+            if let Some(p) = self.players.get_mut(&result.player_id) {
+                p.stack = 0;
+                if self.room_options.chip_accounts {
+                    p.bank += result.prize;
                 }
-                PlayerAction::BetOrRaise(raise_amount) => {
-                    // raise_amount 是本次行动额外增加的筹码
-
-                    // 基本条件: 增加的额度 > 0，且小于等于自己的总筹码
-                    if raise_amount == 0 || raise_amount > player.stack {
-                        messages.push(ServerMessage::Error {
-                            message: format!("你只能下注你剩余的筹码 {} 或更少", player.stack),
-                        });
-                        return messages;
-                    }
+            }
+        }
+        self.sit_and_go.finished = true;
+        messages.push(ServerMessage::SitAndGoFinished { results });
+        messages
+    }
+
+    /// 锦标赛模式下，每进行 `hands_per_level` 局升级一次盲注 (按时间升级时检查本级是否到时)。
+    /// 盲注与当前级别不一致时 (如刚开启锦标赛模式) 也会立即应用当前级别的盲注。
+    fn update_blind_level(&mut self, now: DateTime<Utc>) -> GameResult {
+        let Some(structure) = &self.room_options.tournament else { return Ok(vec![]) };
+        let last_level = structure.levels.len().saturating_sub(1);
+        if structure.level_secs == 0 && self.level_hands >= structure.hands_per_level.max(1) && self.tournament_level < last_level {
+            self.tournament_level += 1;
+            self.level_hands = 0;
+        }
+        let messages = self.apply_blind_level(now)?;
+        self.level_hands += 1;
+        Ok(messages)
+    }
 
-                    let new_total_bet = player_total_bet + raise_amount;
+    /// 混合玩法轮换: 当前玩法的局数用完时换到下一个玩法。返回本局按轮换进行的玩法和换玩法的消息，
+    /// 没有开启轮换时返回 None。房主修改了轮换设置时从第一个玩法重新开始
+    fn advance_rotation(&mut self) -> (Option<GameVariant>, Vec<ServerMessage>) {
+        let Some(rotation) = self.room_options.rotation.clone().filter(|r| !r.variants.is_empty()) else {
+            self.rotation = None;
+            return (None, vec![]);
+        };
+        let next_index = match &self.rotation {
+            Some(progress) if progress.rotation == rotation && progress.hands_left > 0 => None,
+            Some(progress) if progress.rotation == rotation => Some((progress.index + 1) % rotation.variants.len()),
+            _ => Some(0),
+        };
+        let mut messages = vec![];
+        if let Some(index) = next_index {
+            let hands = match rotation.hands_per_game {
+                0 => self.hand_player_order.len() as u32,
+                n => n,
+            };
+            messages.push(ServerMessage::VariantChanged { variant: rotation.variants[index], index, hands });
+            self.rotation = Some(RotationProgress { rotation, index, hands_left: hands });
+        }
+        let progress = self.rotation.as_mut().expect("轮换进度已经初始化");
+        progress.hands_left -= 1;
+        (Some(progress.rotation.variants[progress.index]), messages)
+    }
 
-                    // 如果是翻牌后的第一轮下注 (Bet)，下注额必须大于等于大盲注 (除非是All-in)
-                    if self.max_bet == player_total_bet {
-                        if raise_amount < self.big_blind && player.stack > raise_amount {
-                            messages.push(ServerMessage::Error {
-                                message: format!("你只能下注大盲注 {} 或更多", self.big_blind),
-                            });
-                            return messages;
-                        }
-                    }
-                    // 如果是加注 (Raise)
-                    else {
-                        // 新的总下注额必须大于当前最高下注额
-                        if new_total_bet <= self.max_bet {
-                            messages.push(ServerMessage::Error {
-                                message: format!("你只能加注 {} 或更多", amount_to_call + self.last_raise_amount),
-                            });
-                            return messages;
-                        }
+    /// 按时间升级时，服务器在本级到时后调用。两局之间立即升级盲注；
+    /// 牌局进行中时不做任何事，等下一局开始时再升级
+    pub fn advance_blind_level(&mut self, now: DateTime<Utc>) -> GameResult {
+        if self.hand_in_progress() {
+            return Ok(vec![]);
+        }
+        self.apply_blind_level(now)
+    }
 
-                        // 验证加注额是否符合最小加注规则
-                        let raise_diff = new_total_bet - self.max_bet;
-                        // 加注的差额必须大于等于上一个加注的差额 (All-in除外)
-                        if raise_diff < self.last_raise_amount && player.stack > raise_amount {
-                            messages.push(ServerMessage::Error {
-                                message: format!("你只能加注 {} 或更多", amount_to_call + self.last_raise_amount),
-                            });
-                            return messages;
-                        }
-                    }
+    /// 应用当前级别的盲注。按时间升级时，本级到时就升到下一级，并开始新一级的计时
+    fn apply_blind_level(&mut self, now: DateTime<Utc>) -> GameResult {
+        let Some(structure) = &self.room_options.tournament else { return Ok(vec![]) };
+        let Some(last_level) = structure.levels.len().checked_sub(1) else { return Ok(vec![]) };
+        let old_ends_at = self.level_ends_at;
+        if structure.level_secs > 0 {
+            let expired = self.level_ends_at.is_some_and(|t| now >= t);
+            if expired && self.tournament_level < last_level {
+                self.tournament_level += 1;
+            }
+            if expired || self.level_ends_at.is_none() {
+                self.level_ends_at = Some(now + chrono::Duration::seconds(structure.level_secs as i64));
+            }
+        }
+        self.tournament_level = self.tournament_level.min(last_level);
+        // 最后一级一直持续，不再计时
+        if structure.level_secs == 0 || self.tournament_level == last_level {
+            self.level_ends_at = None;
+        }
+        let level = structure.levels[self.tournament_level];
 
-                    // 更新状态
-                    player.stack -= raise_amount;
-                    self.pot += raise_amount;
-                    self.bets[player_idx] = new_total_bet;
+        let blinds_changed = (self.small_blind, self.big_blind) != (level.small_blind, level.big_blind);
+        if !blinds_changed && self.level_ends_at == old_ends_at {
+            return Ok(vec![]);
+        }
+        let mut chip_race = vec![];
+        if blinds_changed {
+            let old_unit = BlindLevel { small_blind: self.small_blind, big_blind: self.big_blind }.chip_unit();
+            self.small_blind = level.small_blind;
+            self.big_blind = level.big_blind;
+            self.level_hands = 0;
+            if level.chip_unit() > old_unit {
+                chip_race = self.chip_race(level.chip_unit())?;
+            }
+        }
+        Ok(vec![ServerMessage::BlindLevelRaised {
+            level: self.tournament_level,
+            small_blind: level.small_blind,
+            big_blind: level.big_blind,
+            level_ends_at: self.level_ends_at,
+            chip_race,
+            server_time: now,
+        }])
+    }
 
-                    // 如果产生了新的最高下注，则更新 cur_max_bet 和 last_raise_amount
-                    if new_total_bet > self.max_bet {
-                        // 只有在不是全下的情况下才更新最小加注额, "不足额"的all-in加注不改变最小加注额
-                        if player.stack > 0 {
-                            self.last_raise_amount = new_total_bet - self.max_bet;
-                        }
-                        self.max_bet = new_total_bet;
-                    }
+    /// 换色 (chip race): 移除就座玩家筹码中不足 `unit` 的零头。
+    ///
+    /// 所有零头合计按 `unit` 换成新的筹码 (过半进一)，每位玩家最多得到一枚，
+    /// 零头越多的玩家越优先 (代替传统的按发牌大小决定)。
+    /// 只剩零头的玩家不会因换色出局，至少得到一枚。
+    /// 按时间升级时换色可能发生在两局之间，因此按就座玩家而不是本局玩家计算。
+    fn chip_race(&mut self, unit: u32) -> GameResult<Vec<ChipRaceResult>> {
+        let mut odd: Vec<(PlayerId, u32, u32)> = self
+            .seated_players
+            .iter()
+            .filter_map(|id| {
+                let stack = self.players.get(id)?.stack;
+                let remainder = stack % unit;
+                (remainder > 0).then_some((*id, stack, remainder))
+            })
+            .collect();
+        let total: u32 = odd.iter().map(|(_, _, r)| r).sum();
+        let awards = ((total + unit / 2) / unit) as usize;
+        // 稳定排序，零头相同时按座位顺序
+        odd.sort_by_key(|&(_, stack, remainder)| (stack != remainder, std::cmp::Reverse(remainder)));
+
+        odd.into_iter()
+            .enumerate()
+            .map(|(i, (player_id, old_stack, remainder))| {
+                let mut new_stack = old_stack - remainder;
+                if i < awards || new_stack == 0 {
+                    new_stack += unit;
+                }
+                self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?.stack = new_stack;
+                Ok(ChipRaceResult { player_id, old_stack, new_stack })
+            })
+            .collect()
+    }
+
+    /// 处理自动玩家（如离线玩家）的行动。
+    ///
+    /// 服务器可以在一个循环中调用此函数，直到它返回 false。
+    /// 当轮到一个需要人类输入的玩家时，它会返回 false。
+    ///
+    /// # Returns
+    /// - `(bool, Vec<ServerMessage>)`: 元组的第一个元素表示是否执行了自动行动，
+    ///   第二个元素是该行动产生的消息列表。
+    pub fn tick(&mut self, now: DateTime<Utc>) -> GameResult<(bool, Vec<ServerMessage>)> {
+        // 游戏结束、等待或没有轮到任何人行动
+        if !self.hand_in_progress() {
+            return Ok((false, vec![]));
+        }
+
+        let player_id = self.current_player_id().ok_or(GameError::NoNextPlayer)?;
+        let is_auto_action = self
+            .players
+            .get(&player_id)
+            .is_some_and(|p| p.is_offline);
+
+        if is_auto_action {
+            Ok((true, self.default_action(player_id, now)?))
+        } else if !self.insurance_pending() && self.should_auto_fold(player_id) {
+            Ok((true, self.handle_player_action(player_id, PlayerAction::Fold, now)?))
+        } else {
+            Ok((false, vec![]))
+        }
+    }
+
+    /// 行动计时结束时，替仍未行动的玩家过牌或弃牌
+    pub fn time_out(&mut self, player_id: PlayerId, now: DateTime<Utc>) -> GameResult<Vec<ServerMessage>> {
+        if !self.hand_in_progress() || self.current_player_id() != Some(player_id) {
+            return Err(GameError::NotYourTurn);
+        }
+        self.default_action(player_id, now)
+    }
+
+    /// 玩家无法自己行动时的默认动作: 不买保险，换牌轮不换牌，下注轮能过牌就过牌，否则弃牌
+    fn default_action(&mut self, player_id: PlayerId, now: DateTime<Utc>) -> GameResult<Vec<ServerMessage>> {
+        if self.insurance_pending() {
+            return self.buy_insurance(player_id, 0);
+        }
+        if self.is_draw_round() {
+            return self.handle_discard(player_id, vec![], now);
+        }
+        let player_idx = *self.player_indices.get(&player_id).ok_or(GameError::NotInHand)?;
+        let action = if self.max_bet == self.bets[player_idx] {
+            PlayerAction::Check
+        } else {
+            PlayerAction::Fold
+        };
+        self.handle_player_action(player_id, action, now)
+    }
+
+    /// 处理单个玩家的动作
+    ///
+    /// 这是游戏逻辑的核心驱动函数之一。它接收一个玩家的动作，
+    /// 验证其合法性，然后更新游戏状态。
+    /// - 扣除筹码，增加奖池。
+    /// - 更改玩家状态 (如 Folded, AllIn)。
+    /// - 更新当前轮的最大下注额。
+    ///
+    /// 在处理完动作后，它会检查当前下注轮是否结束。
+    /// 如果是，则推进到下一个游戏阶段 (e.g., Flop -> Turn)。
+    /// 如果否，则将行动权转移给下一个玩家。
+    ///
+    /// `now` 是服务器的当前时间，记为动作生效的时间。
+    ///
+    /// # Returns
+    /// 返回一个消息列表，描述该动作引发的所有状态变更。
+    pub fn handle_player_action(
+        &mut self,
+        player_id: PlayerId,
+        action: PlayerAction,
+        now: DateTime<Utc>,
+    ) -> GameResult {
+        if !self.hand_in_progress() {
+            return Err(GameError::NoHandInProgress);
+        }
+        if self.is_draw_round() {
+            return Err(GameError::DrawRoundPending);
+        }
+        if self.insurance_pending() {
+            return Err(GameError::InsurancePending);
+        }
+        if self.current_player_id() != Some(player_id) {
+            return Err(GameError::NotYourTurn);
+        }
+        let &player_idx = self.player_indices.get(&player_id).ok_or(GameError::NotInHand)?;
+        let player_total_bet = *self.bets.get(player_idx).ok_or(GameError::NotInHand)?;
+        let amount_to_call = self.max_bet - player_total_bet;
+        let raise_cap = self.raise_cap(amount_to_call);
+        let stack = self.players.get(&player_id).ok_or(GameError::PlayerNotFound)?.stack;
+
+        // 先检查动作是否合法，算出这次投入的筹码
+        let chips = match action {
+            PlayerAction::Fold => 0,
+            PlayerAction::Check => {
+                // 必须是无人下注（或大盲注无人加注）时才能过牌
+                if amount_to_call != 0 {
+                    return Err(GameError::MustCall { amount: amount_to_call });
+                }
+                0
+            }
+            PlayerAction::Call => amount_to_call.min(stack),
+            PlayerAction::BetOrRaise(raise_amount) => {
+                // raise_amount 是本次行动额外增加的筹码
+
+                // 基本条件: 增加的额度 > 0，且小于等于自己的总筹码
+                if raise_amount == 0 || raise_amount > stack {
+                    return Err(GameError::BetExceedsStack { stack });
+                }
 
-                    if player.stack == 0 {
-                        player.state = PlayerState::AllIn;
+                let new_total_bet = player_total_bet + raise_amount;
+
+                // 如果是翻牌后的第一轮下注 (Bet)，下注额必须大于等于大盲注 (除非是All-in)
+                if self.max_bet == player_total_bet {
+                    if raise_amount < self.big_blind && stack > raise_amount {
+                        return Err(GameError::BetBelowBigBlind { big_blind: self.big_blind });
+                    }
+                }
+                // 如果是加注 (Raise)
+                else {
+                    // 新的总下注额必须大于当前最高下注额
+                    if new_total_bet <= self.max_bet {
+                        return Err(GameError::RaiseTooSmall { min: amount_to_call + self.last_raise_amount });
                     }
 
-                    // 当有人加注时，其他所有未弃牌的玩家都需要重新行动一轮。
-                    for (i, p_id) in self.hand_player_order.iter().enumerate() {
-                        if p_id != &player_id {
-                            if let Some(p) = self.players.get(p_id) {
-                                if p.state != PlayerState::Folded && p.state != PlayerState::AllIn {
-                                    self.player_has_acted[i] = false;
-                                }
-                            }
-                        }
+                    // 验证加注额是否符合最小加注规则
+                    let raise_diff = new_total_bet - self.max_bet;
+                    // 加注的差额必须大于等于上一个加注的差额 (All-in除外)
+                    if raise_diff < self.last_raise_amount && stack > raise_amount {
+                        return Err(GameError::RaiseTooSmall { min: amount_to_call + self.last_raise_amount });
                     }
                 }
+
+                // 底池限注: 投入的筹码不能超过上限 (全下也不例外)
+                if raise_amount > raise_cap {
+                    return Err(GameError::RaiseAbovePotLimit { max: raise_cap });
+                }
+                raise_amount
             }
-        }
+        };
 
-        // 创建 PlayerActed 消息
-        let player = self.players.get(&player_id).unwrap();
-        messages.push(ServerMessage::PlayerActed {
+        // 生成 PlayerActed 事件并应用: 更新筹码、底池、下注额、玩家状态和最高下注
+        let mut messages = Vec::new();
+        let prev_max_bet = self.max_bet;
+        let total_bet = player_total_bet + chips;
+        let event = ServerMessage::PlayerActed {
             player_id,
             action, // 将传入的 action 克隆或复制到消息中
-            total_bet: self.bets[player_idx],
-            new_stack: player.stack,
-            new_pot: self.pot,
-        });
+            total_bet,
+            new_stack: stack - chips,
+            new_pot: self.pot + chips,
+            at: now,
+        };
+        self.emit(event, &mut messages);
 
+        // 事件之外只有服务器需要的状态: 最小加注额和谁还需要行动
+        if total_bet > prev_max_bet {
+            // 只有在不是全下的情况下才更新最小加注额, "不足额"的all-in加注不改变最小加注额
+            if stack > chips {
+                self.last_raise_amount = total_bet - prev_max_bet;
+            }
+            // 当有人加注时，其他所有未弃牌的玩家都需要重新行动一轮。
+            for (i, p_id) in self.hand_player_order.iter().enumerate() {
+                if p_id != &player_id
+                    && let Some(p) = self.players.get(p_id)
+                    && p.state != PlayerState::Folded && p.state != PlayerState::AllIn {
+                    self.player_has_acted[i] = false;
+                }
+            }
+        }
         self.player_has_acted[player_idx] = true;
 
         // 检查是否只剩一人未弃牌
@@ -430,55 +1474,214 @@ impl GameState {
             .filter(|id| {
                 self.players
                     .get(id)
-                    .map_or(false, |p| p.state != PlayerState::Folded)
+                    .is_some_and(|p| p.state != PlayerState::Folded)
             })
             .cloned()
             .collect();
 
         if players_in_hand.len() <= 1 {
             // 如果是，直接分配底池，结束这局
-            self.phase = GamePhase::Showdown;
-            messages.extend(self.distribute_pot_to_single_winner_group(players_in_hand));
-            return messages;
+            messages.extend(self.end_uncontested(players_in_hand)?);
+            return Ok(messages);
         }
 
         if self.check_betting_round_over() {
-            messages.extend(self.advance_to_next_phase());
+            messages.extend(self.advance_to_next_phase()?);
         } else {
-            messages.extend(self.advance_to_next_player());
+            messages.extend(self.advance_to_next_player()?);
         }
-        messages
+        Ok(messages)
+    }
+
+    /// 只剩一位玩家没有弃牌时，把奖池分给他并结束本局
+    fn end_uncontested(&mut self, players_in_hand: Vec<PlayerId>) -> GameResult {
+        self.phase = GamePhase::Showdown;
+        let mut messages = self.distribute_pot_to_single_winner_group(players_in_hand)?;
+        let scored = self.score_guesses(&messages);
+        messages.extend(scored);
+        messages.extend(self.eliminate_busted_players());
+        Ok(messages)
+    }
+
+    /// 房主把玩家移出房间时弃掉他在本局的牌，不必等轮到他。
+    /// 轮到他下注时按正常的弃牌处理；轮到他换牌或决定保险时先按默认动作处理，再弃牌。
+    /// 弃牌后只剩一位玩家时结束本局。不在本局中或已经弃牌时什么也不做
+    pub fn force_fold(&mut self, player_id: PlayerId, now: DateTime<Utc>) -> GameResult {
+        let in_hand = self.hand_in_progress()
+            && self.players.get(&player_id).is_some_and(|p| matches!(p.state, PlayerState::Playing | PlayerState::AllIn));
+        let Some(&idx) = self.player_indices.get(&player_id).filter(|_| in_hand) else { return Ok(vec![]) };
+        let mut messages = vec![];
+        if self.current_player_id() == Some(player_id) {
+            if !self.is_draw_round() && !self.insurance_pending() {
+                return self.handle_player_action(player_id, PlayerAction::Fold, now);
+            }
+            messages.extend(self.default_action(player_id, now)?);
+            if !self.hand_in_progress() {
+                return Ok(messages);
+            }
+        }
+        // 保险只在两位玩家之间报价，其中一位弃牌后报价作废
+        self.insurance = None;
+        let player = self.players.get_mut(&player_id).ok_or(GameError::PlayerNotFound)?;
+        player.state = PlayerState::Folded;
+        messages.push(ServerMessage::PlayerActed {
+            player_id,
+            action: PlayerAction::Fold,
+            total_bet: self.bets.get(idx).copied().unwrap_or(0),
+            new_stack: player.stack,
+            new_pot: self.pot,
+            at: now,
+        });
+        let players_in_hand: Vec<_> = self.hand_player_order.iter()
+            .filter(|id| self.players.get(id).is_some_and(|p| p.state != PlayerState::Folded))
+            .cloned()
+            .collect();
+        if players_in_hand.len() <= 1 {
+            messages.extend(self.end_uncontested(players_in_hand)?);
+        }
+        Ok(messages)
+    }
+
+    /// 处理换牌阶段玩家的换牌
+    ///
+    /// `indices` 是要弃掉的底牌序号 (从 0 开始)，为空表示不换牌 (stand pat)。
+    /// 弃掉的牌从牌堆补齐；牌堆不足时保留原来的牌。
+    ///
+    /// # Returns
+    /// 返回一个消息列表，新的底牌不会广播，玩家需要通过 GetMyHand 获取。
+    pub fn handle_discard(&mut self, player_id: PlayerId, indices: Vec<usize>, now: DateTime<Utc>) -> GameResult {
+        if !self.hand_in_progress() || !self.is_draw_round() {
+            return Err(GameError::NotDrawRound);
+        }
+        if self.current_player_id() != Some(player_id) {
+            return Err(GameError::NotYourDraw);
+        }
+        let &player_idx = self.player_indices.get(&player_id).ok_or(GameError::NotInHand)?;
+        let hole_card_count = self.player_cards.get(player_idx).ok_or(GameError::NotInHand)?.len();
+        let mut sorted = indices.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if sorted.len() != indices.len() || sorted.iter().any(|&i| i >= hole_card_count) {
+            return Err(GameError::InvalidDiscard { hole_card_count });
+        }
+
+        let mut count = 0;
+        for i in sorted {
+            if let Some(card) = self.deck.pop() {
+                self.player_cards[player_idx][i] = Some(card);
+                count += 1;
+            }
+        }
+        self.player_has_acted[player_idx] = true;
+
+        let mut messages = vec![ServerMessage::CardsDrawn { player_id, count, at: now }];
+        messages.extend(self.advance_to_next_drawer()?);
+        Ok(messages)
     }
 
     // --- 辅助逻辑函数 ---
 
     /// 将行动权转移给下一位合法的玩家
-    fn advance_to_next_player(&mut self) -> Vec<ServerMessage> {
+    fn advance_to_next_player(&mut self) -> GameResult {
         let mut current_idx = self.cur_player_idx;
 
         // 循环查找下一个可以行动的玩家
         for _ in 0..self.hand_player_order.len() {
             current_idx = (current_idx + 1) % self.hand_player_order.len();
             let next_player_id = self.hand_player_order[current_idx];
-            if let Some(player) = self.players.get(&next_player_id) {
-                if (player.state == PlayerState::Playing) && !self.player_has_acted[current_idx] {
-                    // 找到后...
-                    self.cur_player_idx = current_idx;
-                    let need_call_amount = self.max_bet - self.bets[current_idx];
-                    let need_raise_amount = need_call_amount + self.last_raise_amount;
-                    // 返回 NextToAct 消息
-                    return vec![ServerMessage::NextToAct {
-                        player_id: self.hand_player_order[current_idx],
-                        valid_actions: vec![
-                            if need_call_amount > 0 { PlayerActionType::Call(need_call_amount) } else { PlayerActionType::Check },
-                            if need_call_amount > 0 { PlayerActionType::Raise(need_raise_amount) } else { PlayerActionType::Bet(need_raise_amount) },
-                            PlayerActionType::Fold
-                        ],
-                    }];
-                }
+            if let Some(player) = self.players.get(&next_player_id)
+                && (player.state == PlayerState::Playing) && !self.player_has_acted[current_idx] {
+                // 找到后...
+                self.cur_player_idx = current_idx;
+                // 返回 NextToAct 消息
+                let valid_actions = self.betting_actions(current_idx, player.stack);
+                return Ok(vec![self.next_to_act(current_idx, valid_actions)]);
             }
         }
-        vec![ServerMessage::Error { message: "没有可以行动的下一个玩家".to_string() }]
+        Err(GameError::NoNextPlayer)
+    }
+
+    /// 需要跟注 `amount_to_call` 的玩家本次下注或加注最多可以投入的筹码 (不考虑自己的筹码)。
+    /// 底池限注下为先跟注、再加注跟注后的整个底池；无限注下没有上限
+    fn raise_cap(&self, amount_to_call: u32) -> u32 {
+        match self.betting_structure {
+            BettingStructure::NoLimit => u32::MAX,
+            BettingStructure::PotLimit => amount_to_call.saturating_mul(2).saturating_add(self.pot),
+        }
+    }
+
+    /// 下注轮中，手中有 `stack` 筹码的第 `idx` 位玩家可以执行的动作
+    fn betting_actions(&self, idx: usize, stack: u32) -> Vec<PlayerActionType> {
+        let need_call_amount = self.max_bet - self.bets[idx];
+        let max = self.raise_cap(need_call_amount).min(stack);
+        let min = (need_call_amount + self.last_raise_amount).min(max);
+        let mut valid_actions = vec![
+            if need_call_amount > 0 { PlayerActionType::Call(need_call_amount) } else { PlayerActionType::Check },
+        ];
+        // 筹码不够跟注时只能跟注全下，不能再加注
+        if stack > need_call_amount {
+            valid_actions.push(if need_call_amount > 0 {
+                PlayerActionType::Raise { min, max }
+            } else {
+                PlayerActionType::Bet { min, max }
+            });
+        }
+        valid_actions.push(PlayerActionType::Fold);
+        valid_actions
+    }
+
+    /// 重新生成当前行动玩家的 NextToAct 消息，用于断线重连后恢复行动提示。
+    /// 没有牌局进行或没有轮到任何人时返回 None。
+    pub fn current_turn(&self) -> Option<ServerMessage> {
+        if !self.hand_in_progress() {
+            return None;
+        }
+        let player_id = self.current_player_id()?;
+        let player = self.players.get(&player_id)?;
+        let valid_actions = if self.is_draw_round() {
+            vec![PlayerActionType::Discard(self.variant.hole_card_count())]
+        } else if player.state == PlayerState::Playing {
+            self.betting_actions(self.cur_player_idx, player.stack)
+        } else {
+            return None;
+        };
+        Some(self.next_to_act(self.cur_player_idx, valid_actions))
+    }
+
+    /// 生成第 `idx` 位玩家的 NextToAct 消息，附带做决定需要的局面信息
+    fn next_to_act(&self, idx: usize, valid_actions: Vec<PlayerActionType>) -> ServerMessage {
+        let player_id = self.hand_player_order[idx];
+        let bet = self.bets.get(idx).copied().unwrap_or(0);
+        let stack = self.players.get(&player_id).map_or(0, |p| p.stack);
+        let raise_range = valid_actions.iter().find_map(|a| match a {
+            PlayerActionType::Bet { min, max } | PlayerActionType::Raise { min, max } => Some((*min, *max)),
+            _ => None,
+        });
+        let effective_stacks = self.hand_player_order.iter().enumerate()
+            .filter(|&(i, _)| i != idx)
+            .filter_map(|(i, id)| {
+                let p = self.players.get(id).filter(|p| matches!(p.state, PlayerState::Playing | PlayerState::AllIn))?;
+                let theirs = p.stack + self.bets.get(i).copied().unwrap_or(0);
+                Some((*id, theirs.min(stack + bet)))
+            })
+            .collect();
+        // 还没行动过或者还没跟上最高下注额的玩家都还要再行动
+        let players_to_act = self.hand_player_order.iter().enumerate()
+            .filter(|&(i, id)| {
+                i != idx && self.players.get(id).is_some_and(|p| p.state == PlayerState::Playing)
+                    && (!self.player_has_acted.get(i).copied().unwrap_or(false) || self.bets.get(i).copied().unwrap_or(0) < self.max_bet)
+            })
+            .count() as u32;
+        let context = TurnContext {
+            pot: self.pot,
+            to_call: self.max_bet.saturating_sub(bet),
+            raise_range,
+            stack,
+            effective_stacks,
+            deadline: None,
+            players_to_act,
+        };
+        ServerMessage::NextToAct { player_id, valid_actions, context }
     }
 
     /// 检查当前下注轮是否结束
@@ -514,74 +1717,94 @@ impl GameState {
         }
 
         // 检查这些玩家是否都已经行动过
-        let all_have_acted = players_to_act
+        players_to_act
             .iter()
-            .all(|(idx, _)| self.player_has_acted[*idx]);
-
-        all_have_acted
+            .all(|(idx, _)| self.player_has_acted[*idx])
     }
 
     /// 推进到下一个游戏阶段
     ///
     /// 在一轮下注结束后调用。
-    /// - 根据当前阶段，发出公共牌 (Flop, Turn, River)。
+    /// - 根据本局玩法的街道顺序 (`GameVariant::streets`) 确定下一条街道，并发出该街道的公共牌。
+    /// - 换牌街道改为开始换牌轮。
     /// - 重置新一轮的下注状态。
     /// - 确定下一轮第一个行动的玩家 (通常是庄家左边的第一个未弃牌玩家)。
-    /// - 如果已是 River 结束，则进入 Showdown (摊牌)阶段。
-    fn advance_to_next_phase(&mut self) -> Vec<ServerMessage> {
+    /// - 如果已是最后一轮下注结束，则进入 Showdown (摊牌)阶段。
+    fn advance_to_next_phase(&mut self) -> GameResult {
         let mut messages = Vec::new();
         // 为新一轮下注重置所有玩家的行动状态
         self.player_has_acted.fill(false);
         // 重置最小加注额为大盲注，用于下一轮下注
         self.last_raise_amount = self.big_blind;
         self.last_bet = self.max_bet;
-
-        fn preflop_to_flop(state: &mut GameState, messages: &mut Vec<ServerMessage>) {
-            state.phase = GamePhase::Flop;
-            let c1 = state.deck.pop().unwrap();
-            let c2 = state.deck.pop().unwrap();
-            let c3 = state.deck.pop().unwrap();
-            state.community_cards[0..3].copy_from_slice(&[Some(c1), Some(c2), Some(c3)]);
-            messages.push(ServerMessage::CommunityCardsDealt {
-                phase: state.phase,
-                cards: vec![c1, c2, c3],
-                last_bet: state.last_bet,
-            });
+        // 最后一条街的最后加注者保留到摊牌，决定谁先亮牌
+        if self.variant.next_phase(self.phase) != GamePhase::Showdown {
+            self.last_aggressor = None;
         }
 
-        fn flop_to_turn(state: &mut GameState, messages: &mut Vec<ServerMessage>) {
-            state.phase = GamePhase::Turn;
-            let c = state.deck.pop().unwrap();
-            state.community_cards[3] = Some(c);
-            messages.push(ServerMessage::CommunityCardsDealt {
-                phase: state.phase,
-                cards: vec![c],
-                last_bet: state.last_bet,
-            });
+        // 保险报价时已经亮过底牌，决定之后继续发牌时不再重复
+        if self.insurance.is_none() && let Some(reveal) = self.all_in_runout_reveal() {
+            if let ServerMessage::AllInHandsRevealed { hands } = &reveal {
+                for (id, _) in hands {
+                    self.mark_shown(self.player_indices[id]);
+                }
+            }
+            messages.push(reveal);
+            if let Some(offer) = self.insurance_offer() {
+                self.cur_player_idx = self.player_indices[&offer.buyer];
+                self.insurance = Some(Insurance::Offered(offer));
+                messages.push(ServerMessage::InsuranceOffered(offer));
+                return Ok(messages);
+            }
         }
 
-        fn turn_to_river(state: &mut GameState, messages: &mut Vec<ServerMessage>) {
-            state.phase = GamePhase::River;
-            let c = state.deck.pop().unwrap();
-            state.community_cards[4] = Some(c);
-            messages.push(ServerMessage::CommunityCardsDealt {
-                phase: state.phase,
-                cards: vec![c],
-                last_bet: state.last_bet,
-            });
+        // 向每块公共牌的 [start, start + count) 位置发牌
+        fn deal_to_boards(state: &mut GameState, messages: &mut Vec<ServerMessage>, start: usize, count: usize) -> GameResult<()> {
+            for board in 0..state.community_cards.len() {
+                let cards = (0..count)
+                    .map(|_| state.deck.pop().ok_or(GameError::DeckExhausted))
+                    .collect::<GameResult<Vec<Card>>>()?;
+                for (i, c) in cards.iter().enumerate() {
+                    state.community_cards[board][start + i] = Some(*c);
+                }
+                messages.push(ServerMessage::CommunityCardsDealt {
+                    phase: state.phase,
+                    board,
+                    cards,
+                    last_bet: state.last_bet,
+                });
+            }
+            Ok(())
         }
 
-        // 根据当前阶段推进
-        match self.phase {
-            GamePhase::PreFlop => preflop_to_flop(self, &mut messages),
-            GamePhase::Flop => flop_to_turn(self, &mut messages),
-            GamePhase::Turn => turn_to_river(self, &mut messages),
-            GamePhase::River => {
-                self.phase = GamePhase::Showdown;
-                messages.extend(self.handle_showdown());
-                return messages;
+        // 进入下一条街道，返回是否开始了下注轮 (false 表示已进入摊牌或换牌阶段)
+        fn next_street(state: &mut GameState, messages: &mut Vec<ServerMessage>) -> GameResult<bool> {
+            let next_phase = state.variant.next_phase(state.phase);
+            let Some(street) = state.variant.street(next_phase) else {
+                state.phase = GamePhase::Showdown;
+                messages.extend(state.handle_showdown()?);
+                return Ok(false);
+            };
+
+            state.phase = street.phase;
+            if street.round == RoundKind::Bet {
+                state.street_levels.push(state.last_bet);
+            }
+            if street.deal > 0 {
+                let start = state.variant.community_offset(street.phase);
+                deal_to_boards(state, messages, start, street.deal)?;
+            }
+            match street.round {
+                RoundKind::Bet => Ok(true),
+                RoundKind::Draw => {
+                    messages.extend(state.start_draw_round()?);
+                    Ok(false)
+                }
             }
-            _ => return messages,
+        }
+
+        if !self.hand_in_progress() || !next_street(self, &mut messages)? {
+            return Ok(messages);
         }
 
         // 确定下一轮有多少玩家可以行动 (未弃牌且未全下)
@@ -589,75 +1812,267 @@ impl GameState {
             .chain(0..1)
             .filter(|&i| {
                 let player_id = self.hand_player_order[i];
-                self.players.get(&player_id).map_or(false, |p| {
+                self.players.get(&player_id).is_some_and(|p| {
                     !matches!(p.state, PlayerState::Folded | PlayerState::AllIn)
                 })
             })
             .collect();
 
         // 如果可以行动的玩家少于2人（0或1），则没有更多下注轮，直接发完所有公共牌进入摊牌
+        // (换牌玩法中，换牌阶段仍然需要玩家参与)
         if potential_actors.len() < 2 {
-            loop {
-                match self.phase {
-                    GamePhase::PreFlop => preflop_to_flop(self, &mut messages),
-                    GamePhase::Flop => flop_to_turn(self, &mut messages),
-                    GamePhase::Turn => turn_to_river(self, &mut messages),
-                    _ => break,
-                }
-            }
-
-            self.phase = GamePhase::Showdown;
-            messages.extend(self.handle_showdown());
+            while next_street(self, &mut messages)? {}
         } else {
             // 否则，正常开始下一轮，设置第一个可以行动的玩家
             self.cur_player_idx = potential_actors[0];
-            messages.push(ServerMessage::NextToAct {
-                player_id: self.hand_player_order[self.cur_player_idx],
-                valid_actions: vec![
-                    PlayerActionType::Check,
-                    PlayerActionType::Bet(self.last_raise_amount),
-                    PlayerActionType::Fold,
-                ],
-            });
+            let player_id = self.hand_player_order[self.cur_player_idx];
+            let stack = self.players.get(&player_id).ok_or(GameError::PlayerNotFound)?.stack;
+            let valid_actions = self.betting_actions(self.cur_player_idx, stack);
+            messages.push(self.next_to_act(self.cur_player_idx, valid_actions));
         }
 
-        messages
+        Ok(messages)
     }
 
-    /// 处理摊牌逻辑
-    ///
-    /// - 找出所有未弃牌的玩家。
-    /// - 调用新的分池函数来处理奖金分配
-    fn handle_showdown(&mut self) -> Vec<ServerMessage> {
-        let mut m = Vec::new();
-        m.extend(self.return_uncalled_bets());
-        m.extend(self.distribute_pots());
-        m
+    /// 全下摊牌: 仍在牌局中的玩家至少两人、但能继续下注的不到两人，且还有公共牌没发时，
+    /// 亮出这些玩家的底牌。换牌玩法中全下的玩家仍然要换牌，不提前亮牌。
+    fn all_in_runout_reveal(&self) -> Option<ServerMessage> {
+        if self.variant.streets().iter().any(|s| s.round == RoundKind::Draw)
+            || self.variant.next_phase(self.phase) == GamePhase::Showdown {
+            return None;
+        }
+        let in_hand: Vec<(usize, PlayerId, PlayerState)> = self.hand_player_order.iter().enumerate()
+            .filter_map(|(idx, id)| self.players.get(id).map(|p| (idx, *id, p.state.clone())))
+            .filter(|(_, _, state)| matches!(state, PlayerState::Playing | PlayerState::AllIn))
+            .collect();
+        let can_act = in_hand.iter().filter(|(_, _, state)| *state == PlayerState::Playing).count();
+        if in_hand.len() < 2 || can_act >= 2 {
+            return None;
+        }
+        let hands = in_hand.iter()
+            .map(|(idx, id, _)| (*id, self.player_cards[*idx].iter().flatten().cloned().collect()))
+            .collect();
+        Some(ServerMessage::AllInHandsRevealed { hands })
     }
 
-    /// 在摊牌前，返还任何玩家未被跟注的下注部分 (逻辑已修正)
-    /// 例如: P1下注500，P2只有200并跟注All-in。P1未被跟注的300将在这里返还。
-    fn return_uncalled_bets(&mut self) -> Vec<ServerMessage> {
-        let mut players_in_showdown: Vec<_> = self
-            .hand_player_order
-            .iter()
-            .enumerate()
-            .filter(|(_, id)| {
-                let p = self.players.get(id).unwrap();
-                !matches!(p.state, PlayerState::Folded | PlayerState::SittingOut)
-            })
-            .map(|(idx, id)| (idx, id, self.bets[idx]))
-            .collect();
+    /// 是否正在等待领先的玩家决定是否购买保险
+    fn insurance_pending(&self) -> bool {
+        matches!(self.insurance, Some(Insurance::Offered(_)))
+    }
 
-        if players_in_showdown.len() < 2 {
-            return vec![];
+    /// 全下保险报价: 房主开启了保险，只有两位玩家争夺一块公共牌、高牌玩法、还有公共牌没发，
+    /// 且一方领先 (胜率高于一半但还没锁定胜局) 时，由领先的一方向落后的一方购买。
+    /// 保额不超过双方投入中较小的一方，即买方输掉时会输给卖方的筹码
+    fn insurance_offer(&self) -> Option<InsuranceOffer> {
+        if !self.room_options.insurance || self.community_cards.len() != 1 || self.variant.is_hi_lo() {
+            return None;
+        }
+        let in_hand: Vec<(usize, PlayerId)> = self.hand_player_order.iter().enumerate()
+            .filter(|(_, id)| self.players.get(id)
+                .is_some_and(|p| matches!(p.state, PlayerState::Playing | PlayerState::AllIn)))
+            .map(|(idx, id)| (idx, *id))
+            .collect();
+        let [(a_idx, a), (b_idx, b)] = in_hand[..] else { return None };
+        let hands = [a_idx, b_idx].map(|idx| self.player_cards[idx].iter().flatten().cloned().collect::<Vec<_>>());
+        let board: Vec<Card> = self.community_cards[0].iter().flatten().cloned().collect();
+        let equities = hand_equities(self.variant, &hands, &board, INSURANCE_EQUITY_SAMPLES);
+        let ((buyer_idx, buyer, equity), (seller_idx, seller)) = if equities[0] > equities[1] {
+            ((a_idx, a, equities[0]), (b_idx, b))
+        } else {
+            ((b_idx, b, equities[1]), (a_idx, a))
+        };
+        let equity_permille = (equity * 1000.0).round() as u32;
+        let contribution = |idx: usize| self.bets[idx] + self.dead_bets[idx];
+        let max_cover = contribution(buyer_idx).min(contribution(seller_idx));
+        (equity_permille > 500 && equity_permille < 1000 && max_cover > 0)
+            .then_some(InsuranceOffer { buyer, seller, equity_permille, max_cover })
+    }
+
+    /// 领先的玩家购买保额为 `cover` 的全下保险 (0 表示不买)，然后继续发完公共牌
+    pub fn buy_insurance(&mut self, player_id: PlayerId, cover: u32) -> GameResult {
+        let Some(Insurance::Offered(offer)) = self.insurance.filter(|_| self.hand_in_progress()) else {
+            return Err(GameError::NoInsuranceOffer);
+        };
+        if offer.buyer != player_id {
+            return Err(GameError::NoInsuranceOffer);
+        }
+        if cover > offer.max_cover {
+            return Err(GameError::InsuranceCoverTooLarge { max: offer.max_cover });
+        }
+        let policy = (cover > 0).then(|| InsurancePolicy {
+            buyer: offer.buyer,
+            seller: offer.seller,
+            cover,
+            premium: offer.premium(cover),
+        });
+        self.insurance = Some(policy.map_or(Insurance::Declined, Insurance::Bought));
+        let mut messages = vec![ServerMessage::InsuranceDecided { player_id, policy }];
+        messages.extend(self.advance_to_next_phase()?);
+        Ok(messages)
+    }
+
+    /// 摊牌结算后按保险合约在买卖双方之间转移筹码
+    fn settle_insurance(&mut self) -> GameResult {
+        let Some(Insurance::Bought(policy)) = self.insurance else { return Ok(vec![]) };
+        let board: Vec<Card> = self.community_cards[0].iter().flatten().cloned().collect();
+        let rank = |state: &GameState, id: &PlayerId| -> GameResult<HandRank> {
+            let idx = *state.player_indices.get(id).ok_or(GameError::NotInHand)?;
+            let hole: Vec<Card> = state.player_cards[idx].iter().flatten().cloned().collect();
+            Ok(state.variant.evaluate_high(&hole, &board))
+        };
+        let (from, to, amount) = match rank(self, &policy.buyer)?.cmp(&rank(self, &policy.seller)?) {
+            std::cmp::Ordering::Greater => (policy.buyer, policy.seller, policy.premium),
+            std::cmp::Ordering::Less => (policy.seller, policy.buyer, policy.cover),
+            std::cmp::Ordering::Equal => (policy.seller, policy.buyer, (policy.cover - policy.premium) / 2),
+        };
+        if amount == 0 {
+            return Ok(vec![]);
+        }
+        let from_player = self.players.get_mut(&from).ok_or(GameError::PlayerNotFound)?;
+        // 保额和保费都不超过对方在这一局中赢到的筹码，这里只是防御
+        let amount = amount.min(from_player.stack);
+        from_player.stack -= amount;
+        self.players.get_mut(&to).ok_or(GameError::PlayerNotFound)?.stack += amount;
+        Ok(vec![ServerMessage::InsuranceSettled { from, to, amount }])
+    }
+
+    /// 开始换牌轮: 从庄家左边开始，每位仍在牌局中的玩家 (包括已全下的) 依次换牌
+    fn start_draw_round(&mut self) -> GameResult {
+        self.player_has_acted.fill(false);
+        self.advance_to_next_drawer()
+    }
+
+    /// 本局需要发几块公共牌: 只有炸弹底池可以发两块
+    pub fn board_count(&self) -> usize {
+        if self.bomb_pot && self.room_options.double_board { 2 } else { 1 }
+    }
+
+    /// 是否有正在进行 (还没有摊牌) 的牌局
+    pub fn hand_in_progress(&self) -> bool {
+        !matches!(self.phase, GamePhase::WaitingForPlayers | GamePhase::Showdown)
+    }
+
+    /// 作废进行中的牌局: 退还每位玩家本局投入的全部筹码 (包括前注和死盲，大盲前注退还给大盲)，
+    /// 回到等待开局的状态
+    pub fn void_hand(&mut self) -> GameResult {
+        if !self.hand_in_progress() {
+            return Err(GameError::NoHandInProgress);
+        }
+        let bb_idx = self.big_blind_idx();
+        let mut stacks = Vec::with_capacity(self.hand_player_order.len());
+        for (idx, player_id) in self.hand_player_order.iter().enumerate() {
+            let mut refund = self.bets[idx] + self.dead_bets[idx];
+            if idx == bb_idx {
+                refund += self.dead_money;
+            }
+            let player = self.players.get_mut(player_id).ok_or(GameError::PlayerNotFound)?;
+            player.stack += refund;
+            stacks.push((*player_id, player.stack));
+        }
+        self.clear_voided_hand();
+        Ok(vec![ServerMessage::HandVoided { stacks }])
+    }
+
+    /// 清空被作废的牌局，本局的玩家回到等待开局的状态
+    pub(crate) fn clear_voided_hand(&mut self) {
+        self.phase = GamePhase::WaitingForPlayers;
+        self.pot = 0;
+        self.bets.fill(0);
+        self.dead_bets.fill(0);
+        self.forced_bets.clear();
+        self.street_levels.clear();
+        self.dead_money = 0;
+        self.straddle = 0;
+        self.max_bet = 0;
+        self.last_bet = 0;
+        self.last_aggressor = None;
+        self.spectator_guesses.guesses.clear();
+        self.insurance = None;
+        for board in self.community_cards.iter_mut() {
+            board.fill(None);
+        }
+        for cards in self.player_cards.iter_mut() {
+            cards.fill(None);
+        }
+        for player_id in self.hand_player_order.iter() {
+            if let Some(p) = self.players.get_mut(player_id)
+                && matches!(p.state, PlayerState::Playing | PlayerState::AllIn | PlayerState::Folded) {
+                p.state = PlayerState::Waiting;
+            }
+        }
+    }
+
+    /// 当前阶段是否为换牌轮
+    fn is_draw_round(&self) -> bool {
+        self.variant
+            .street(self.phase)
+            .is_some_and(|s| s.round == RoundKind::Draw)
+    }
+
+    /// 将换牌权交给下一位还没换牌的玩家；所有人都换完后进入下一轮下注
+    fn advance_to_next_drawer(&mut self) -> GameResult {
+        let next = (1..self.hand_player_order.len()).chain(0..1).find(|&i| {
+            !self.player_has_acted[i]
+                && self.players.get(&self.hand_player_order[i]).is_some_and(|p| {
+                    matches!(p.state, PlayerState::Playing | PlayerState::AllIn)
+                })
+        });
+        match next {
+            Some(idx) => {
+                self.cur_player_idx = idx;
+                let valid_actions = vec![PlayerActionType::Discard(self.variant.hole_card_count())];
+                Ok(vec![self.next_to_act(idx, valid_actions)])
+            }
+            None => self.advance_to_next_phase(),
+        }
+    }
+
+    /// 处理摊牌逻辑
+    ///
+    /// - 找出所有未弃牌的玩家。
+    /// - 调用新的分池函数来处理奖金分配
+    fn handle_showdown(&mut self) -> GameResult {
+        let mut m = Vec::new();
+        m.extend(self.return_uncalled_bets()?);
+        m.extend(self.distribute_pots()?);
+        let scored = self.score_guesses(&m);
+        m.extend(scored);
+        m.extend(self.eliminate_busted_players());
+        Ok(m)
+    }
+
+    /// 在摊牌前，返还任何玩家未被跟注的下注部分 (逻辑已修正)
+    /// 例如: P1下注500，P2只有200并跟注All-in。P1未被跟注的300将在这里返还。
+    fn return_uncalled_bets(&mut self) -> GameResult {
+        let mut players_in_showdown: Vec<_> = self
+            .hand_player_order
+            .iter()
+            .enumerate()
+            .filter(|(_, id)| {
+                self.players
+                    .get(id)
+                    .is_some_and(|p| !matches!(p.state, PlayerState::Folded | PlayerState::SittingOut))
+            })
+            .map(|(idx, id)| (idx, id, self.bets[idx]))
+            .collect();
+
+        if players_in_showdown.len() < 2 {
+            return Ok(vec![]);
         }
 
         // 按下注额从高到低排序
-        players_in_showdown.sort_by(|a, b| b.2.cmp(&a.2));
+        players_in_showdown.sort_by_key(|p| std::cmp::Reverse(p.2));
 
         let highest_bet_info = &players_in_showdown[0];
-        let second_highest_bet = players_in_showdown[1].2;
+        // 已弃牌玩家的下注同样跟注了最高下注的一部分，也要参与比较
+        let second_highest_bet = self
+            .bets
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| *idx != highest_bet_info.0)
+            .map(|(_, bet)| *bet)
+            .max()
+            .unwrap_or(0);
 
         // 只有当最高下注额 > 第二高下注额时，才存在未被跟注的情况
         if highest_bet_info.2 > second_highest_bet {
@@ -670,14 +2085,15 @@ impl GameState {
                 self.pot -= amount_to_return;
                 self.bets[player_idx] = second_highest_bet;
                 // 创建一个消息来通知筹码返还
-                return vec![ServerMessage::BetReturned {
+                return Ok(vec![ServerMessage::BetReturned {
                     player_id: *player_id,
                     amount: amount_to_return,
                     new_stack: player.stack,
-                }];
+                    new_pot: self.pot,
+                }]);
             }
         }
-        vec![]
+        Ok(vec![])
     }
 
     /// 处理包含边池的复杂奖池分配
@@ -691,32 +2107,45 @@ impl GameState {
     ///    - 处理下一个额度（如200），形成边池。投入额为 (200-50)=150。所有下注额大于等于200的玩家都向此池投入150。
     ///    - 找出有资格争夺此边池的赢家，分配奖金。
     /// 4. 循环此过程，直到所有奖池分配完毕。
-    fn distribute_pots(&mut self) -> Vec<ServerMessage> {
+    fn distribute_pots(&mut self) -> GameResult {
         if self.pot == 0 {
-            return vec![];
+            return Ok(vec![]);
         }
+        let contributed = self.hand_contributions();
 
         #[derive(Debug, Clone)]
         struct Contributor {
             id: PlayerId,
             bet_amount: u32,
-            rank: Option<HandRank>,
+            // 在每块公共牌上的牌力，弃牌的玩家为 None
+            ranks: Option<Vec<HandRank>>,
+            // 高低玩法中在每块公共牌上的低牌
+            lows: Vec<Option<LowHand>>,
         }
 
         // 1. 收集所有玩家信息
-        let mut player_hand_ranks = HashMap::new();
-        let revealed_community_cards: Vec<Card> =
-            self.community_cards.iter().flatten().cloned().collect();
+        let mut player_hand_ranks: HashMap<PlayerId, Vec<HandRank>> = HashMap::new();
+        let mut player_low_hands: HashMap<PlayerId, Vec<Option<LowHand>>> = HashMap::new();
+        let boards: Vec<Vec<Card>> = self
+            .community_cards
+            .iter()
+            .map(|board| board.iter().flatten().cloned().collect())
+            .collect();
 
         for (idx, player_id) in self.hand_player_order.iter().enumerate() {
-            let player = self.players.get(player_id).unwrap();
-            if !matches!(player.state, PlayerState::Folded) {
-                if let (Some(card1), Some(card2)) = self.player_cards[idx] {
-                    let mut all_cards = revealed_community_cards.clone();
-                    all_cards.push(card1);
-                    all_cards.push(card2);
-                    player_hand_ranks.insert(*player_id, find_best_hand(&all_cards));
-                }
+            let player = self.players.get(player_id).ok_or(GameError::PlayerNotFound)?;
+            let hole: Vec<Card> = self.player_cards[idx].iter().flatten().cloned().collect();
+            if !matches!(player.state, PlayerState::Folded) && !hole.is_empty() {
+                let ranks = boards
+                    .iter()
+                    .map(|board| self.variant.evaluate_high(&hole, board))
+                    .collect();
+                let lows = boards
+                    .iter()
+                    .map(|board| self.variant.evaluate_low(&hole, board))
+                    .collect();
+                player_hand_ranks.insert(*player_id, ranks);
+                player_low_hands.insert(*player_id, lows);
             }
         }
 
@@ -726,8 +2155,10 @@ impl GameState {
             .enumerate()
             .map(|(idx, id)| Contributor {
                 id: *id,
-                bet_amount: self.bets[idx],
-                rank: player_hand_ranks.get(id).cloned(),
+                // 死筹码同样是该玩家对奖池的投入
+                bet_amount: self.bets[idx] + self.dead_bets.get(idx).copied().unwrap_or(0),
+                ranks: player_hand_ranks.get(id).cloned(),
+                lows: player_low_hands.get(id).cloned().unwrap_or_default(),
             })
             .collect();
 
@@ -739,14 +2170,25 @@ impl GameState {
             .collect();
         bet_levels.sort_unstable();
         bet_levels.dedup();
+        // 弃牌玩家超过最高有效下注的部分是死筹码，没有人能单独争夺，并入最后一个池
+        let max_live_bet = contributors
+            .iter()
+            .filter(|c| c.ranks.is_some())
+            .map(|c| c.bet_amount)
+            .max()
+            .unwrap_or(0);
+        bet_levels.retain(|&b| b <= max_live_bet);
 
         let mut last_level = 0;
-        // 收集每个玩家的总赢款
+        // 收集每个玩家的总赢款，以及其中通过低牌赢得的部分
         let mut total_winnings: HashMap<PlayerId, u32> = HashMap::new();
+        let mut low_winnings: HashMap<PlayerId, u32> = HashMap::new();
+        // 每个池中各位赢家分到的筹码，底池小费按池收取
+        let mut pot_shares: Vec<(PlayerId, u32)> = vec![];
 
         // 3. 遍历每个下注额度，形成并分配主池/边池
         for level in bet_levels {
-            let pot_slice_amount = level - last_level;
+            let pot_slice_amount = if level == max_live_bet { u32::MAX } else { level - last_level };
             let mut current_pot = 0;
             let mut eligible_for_this_pot = Vec::new();
 
@@ -755,53 +2197,69 @@ impl GameState {
                 if c.bet_amount > last_level {
                     current_pot += pot_slice_amount.min(c.bet_amount - last_level);
                 }
-                if c.bet_amount >= level && c.rank.is_some() {
+                if c.bet_amount >= level && c.ranks.is_some() {
                     eligible_for_this_pot.push(c.clone());
                 }
             }
 
+            // 大盲前注不属于任何玩家的投入，并入主池
+            if last_level == 0 {
+                current_pot += self.dead_money;
+            }
             if current_pot == 0 {
                 last_level = level;
                 continue;
             }
 
-            // 5. 从有资格的玩家中找出赢家
-            let mut winners: Vec<PlayerId> = Vec::new();
-            let mut best_rank: Option<&HandRank> = None;
-            for p in &eligible_for_this_pot {
-                let rank = p.rank.as_ref().unwrap();
-                match best_rank {
-                    None => {
-                        best_rank = Some(rank);
-                        winners.clear();
-                        winners.push(p.id);
+            // 多块公共牌时，每个池在各块公共牌之间平分，零头归第一块
+            let mut shares: Vec<(PlayerId, u32)> = vec![];
+            let board_count = boards.len() as u32;
+            for board in 0..boards.len() {
+                let board_pot = current_pot / board_count
+                    + if board == 0 { current_pot % board_count } else { 0 };
+
+                // 5. 从有资格的玩家中找出该块公共牌上的高牌赢家和低牌赢家
+                let mut high_winners = find_winners(
+                    eligible_for_this_pot.iter().filter_map(|p| Some((p.id, p.ranks.as_ref()?.get(board)?))),
+                );
+                let mut low_winners = find_winners(
+                    eligible_for_this_pot
+                        .iter()
+                        .filter_map(|p| p.lows.get(board)?.as_ref().map(|low| (p.id, low))),
+                );
+                self.sort_from_button(&mut high_winners);
+                self.sort_from_button(&mut low_winners);
+
+                // 6. 分配奖金: 有合格低牌时高低各分一半，零头归高牌
+                let (high_pot, low_pot) = if low_winners.is_empty() {
+                    (board_pot, 0)
+                } else {
+                    (board_pot - board_pot / 2, board_pot / 2)
+                };
+                for (winners, pot, is_low) in [(&high_winners, high_pot, false), (&low_winners, low_pot, true)] {
+                    if winners.is_empty() || pot == 0 {
+                        continue;
                     }
-                    Some(br) => {
-                        if rank > br {
-                            best_rank = Some(rank);
-                            winners.clear();
-                            winners.push(p.id);
-                        } else if rank == br {
-                            winners.push(p.id);
+                    for (i, winner_id) in winners.iter().enumerate() {
+                        if let Some(player) = self.players.get_mut(winner_id) {
+                            let win_amount = split_share(pot, winners.len(), i);
+                            player.stack += win_amount;
+                            *total_winnings.entry(*winner_id).or_insert(0) += win_amount;
+                            if is_low {
+                                *low_winnings.entry(*winner_id).or_insert(0) += win_amount;
+                            }
+                            match shares.iter_mut().find(|(id, _)| id == winner_id) {
+                                Some((_, share)) => *share += win_amount,
+                                None => shares.push((*winner_id, win_amount)),
+                            }
                         }
                     }
                 }
             }
-
-            // 6. 分配奖金
-            if !winners.is_empty() {
-                let win_amount = current_pot / winners.len() as u32;
-                let remainder = current_pot % winners.len() as u32;
-                for (i, winner_id) in winners.iter().enumerate() {
-                    if let Some(player) = self.players.get_mut(winner_id) {
-                        let win_amount = win_amount + if i == 0 { remainder } else { 0 };
-                        player.stack += win_amount;
-                        *total_winnings.entry(*winner_id).or_insert(0) += win_amount;
-                    }
-                }
-            }
+            pot_shares.extend(shares);
             last_level = level;
         }
+        let tips = self.collect_pot_tips(&pot_shares);
 
         // 7. 更新所有赢家的胜利次数
         for winner_id in total_winnings.keys() {
@@ -809,49 +2267,115 @@ impl GameState {
                 player.wins += 1;
             }
         }
+        let insurance = self.settle_insurance()?;
         for player_id in self.hand_player_order.iter() {
-            if let Some(player) = self.players.get_mut(player_id) {
-                if player.stack == 0 {
-                    player.losses += 1;
-                    player.is_offline = true;
-                }
+            if let Some(player) = self.players.get_mut(player_id)
+                && player.stack == 0 {
+                player.losses += 1;
+                player.is_offline = true;
             }
         }
-
-        // 构建 ShowdownResult
-        let results: Vec<ShowdownResult> = player_hand_ranks
-            .into_iter()
-            .map(|(id, rank)| {
-                let player_idx = self.player_indices[&id];
-                let (c1, c2) = self.player_cards[player_idx];
-                ShowdownResult {
+        let winners: Vec<PlayerId> = self.hand_player_order.iter().filter(|id| total_winnings.contains_key(id)).cloned().collect();
+        let bounties = self.collect_seven_deuce_bounties(&winners)?;
+
+        // 构建 ShowdownResult，按亮牌顺序决定谁必须亮牌:
+        // 赢得筹码的玩家、已经在全下摊牌时亮过牌的玩家，以及没有被之前亮出的牌压过的玩家亮牌；
+        // 其余输掉的玩家开启了自动盖牌或已断线时直接盖牌，否则排队依次选择亮牌还是盖牌
+        let mut order: Vec<PlayerId> = player_hand_ranks.keys().cloned().collect();
+        let first = self.last_aggressor
+            .filter(|id| player_hand_ranks.contains_key(id))
+            .and_then(|id| self.player_indices.get(&id).copied())
+            .unwrap_or(1);
+        self.sort_clockwise_from(first, &mut order);
+        let mut best_highs: Vec<Option<HandRank>> = vec![None; boards.len()];
+        let mut best_lows: Vec<Option<LowHand>> = vec![None; boards.len()];
+        let mut to_decide = vec![];
+        let mut results = Vec::with_capacity(order.len());
+        for id in order {
+            let &player_idx = self.player_indices.get(&id).ok_or(GameError::NotInHand)?;
+            let player = self.players.get(&id).ok_or(GameError::PlayerNotFound)?;
+            let winnings = total_winnings.get(&id).cloned().unwrap_or(0);
+            let ranks = player_hand_ranks.remove(&id).unwrap_or_default();
+            let lows = player_low_hands.remove(&id).unwrap_or_default();
+            let contends = ranks.iter().zip(&best_highs).any(|(rank, best)| best.as_ref().is_none_or(|b| rank >= b))
+                || lows.iter().zip(&best_lows).any(|(low, best)| low.as_ref().is_some_and(|l| best.as_ref().is_none_or(|b| l >= b)));
+            if winnings == 0 && !contends && !self.is_shown(player_idx) {
+                if player.auto_muck || player.is_offline {
+                    self.mucked[player_idx] = true;
+                } else {
+                    to_decide.push(id);
+                }
+                results.push(ShowdownResult {
                     player_id: id,
-                    hand_rank: Some(rank),
-                    cards: Some((c1.unwrap(), c2.unwrap())),
-                    winnings: total_winnings.get(&id).cloned().unwrap_or(0),
+                    hand_rank: None,
+                    board_ranks: vec![],
+                    low_hand: None,
+                    cards: None,
+                    winnings,
+                    low_winnings: 0,
+                });
+                continue;
+            }
+            for (best, rank) in best_highs.iter_mut().zip(&ranks) {
+                if best.as_ref().is_none_or(|b| rank > b) {
+                    *best = Some(rank.clone());
                 }
-            })
-            .collect();
+            }
+            for (best, low) in best_lows.iter_mut().zip(&lows) {
+                if let Some(low) = low && best.as_ref().is_none_or(|b| low > b) {
+                    *best = Some(low.clone());
+                }
+            }
+            self.mark_shown(player_idx);
+            results.push(ShowdownResult {
+                player_id: id,
+                hand_rank: ranks.first().cloned(),
+                low_hand: lows.first().cloned().flatten(),
+                board_ranks: if ranks.len() > 1 { ranks } else { vec![] },
+                cards: Some(self.player_cards[player_idx].iter().flatten().cloned().collect()),
+                winnings,
+                low_winnings: low_winnings.get(&id).cloned().unwrap_or(0),
+            });
+        }
+        self.showdown_queue = to_decide.clone();
 
         self.pot = 0;
+        self.dead_money = 0;
+
+        // 返回 Showdown 消息，以及随后的保险结算和七二奖励
+        let stacks = self.stack_changes(contributed);
+        let mut messages = vec![ServerMessage::Showdown { results, stacks, to_decide }];
+        messages.extend(tips);
+        messages.extend(insurance);
+        messages.extend(bounties);
+        Ok(messages)
+    }
+
+    /// 按标准的零头规则排列平分奖池的赢家: 从庄家左手边的第一位玩家开始顺时针 (庄家排在最后)，
+    /// 零头依次发给排在前面的赢家，同样的牌局总是同样地分钱
+    fn sort_from_button(&self, winners: &mut [PlayerId]) {
+        self.sort_clockwise_from(1, winners);
+    }
 
-        // 返回单个 Showdown 消息
-        vec![ServerMessage::Showdown { results }]
+    /// 从本局玩家顺序中的第 `start` 位开始顺时针排列玩家
+    fn sort_clockwise_from(&self, start: usize, ids: &mut [PlayerId]) {
+        let n = self.hand_player_order.len().max(1);
+        let start = start % n;
+        ids.sort_by_key(|id| self.player_indices.get(id).map_or(usize::MAX, |&i| (i + n - start) % n));
     }
 
     fn distribute_pot_to_single_winner_group(
         &mut self,
-        winners: Vec<PlayerId>,
-    ) -> Vec<ServerMessage> {
+        mut winners: Vec<PlayerId>,
+    ) -> GameResult {
         if winners.is_empty() || self.pot == 0 {
-            return vec![];
+            return Ok(vec![]);
         }
+        let contributed = self.hand_contributions();
+        self.sort_from_button(&mut winners);
+        let pot = self.pot;
 
-        let win_amount_per_player = self.pot / winners.len() as u32;
-        let remainder = self.pot % winners.len() as u32;
-
-        let community = self
-            .community_cards
+        let community = self.community_cards[0]
             .iter()
             .flatten()
             .cloned()
@@ -861,39 +2385,179 @@ impl GameState {
             .iter()
             .enumerate()
             .map(|(i, winner_id)| {
-                let player = self.players.get_mut(winner_id).unwrap();
-                let winnings = win_amount_per_player + if i == 0 { remainder } else { 0 };
+                let player = self.players.get_mut(winner_id).ok_or(GameError::PlayerNotFound)?;
+                let winnings = split_share(pot, winners.len(), i);
                 player.stack += winnings;
                 player.wins += 1;
-                if community.len() >= 3 {
-                    let player_idx = self.player_indices[winner_id];
-                    let (Some(c1), Some(c2)) = self.player_cards[player_idx] else {
-                        unreachable!()
-                    };
-                    let mut all_cards = community.clone();
-                    all_cards.push(c1);
-                    all_cards.push(c2);
-
-                    ShowdownResult {
+                if self.holds_seven_deuce(winner_id) {
+                    // 没有人跟注时底牌默认不亮，但要亮出 7-2 才能领取七二奖励
+                    let &player_idx = self.player_indices.get(winner_id).ok_or(GameError::NotInHand)?;
+                    let hole: Vec<Card> = self.player_cards[player_idx].iter().flatten().cloned().collect();
+                    Ok(ShowdownResult {
                         player_id: *winner_id,
-                        hand_rank: Some(find_best_hand(&all_cards)),
-                        cards: Some((c1, c2)),
+                        hand_rank: (community.len() >= 3).then(|| self.variant.evaluate_high(&hole, &community)),
+                        board_ranks: vec![],
+                        low_hand: None,
+                        cards: Some(hole),
                         winnings,
-                    }
+                        low_winnings: 0,
+                    })
                 } else {
-                    ShowdownResult {
+                    // 赢家可以之后用 `show_cards` 主动亮出部分或全部底牌
+                    Ok(ShowdownResult {
                         player_id: *winner_id,
                         hand_rank: None,
+                        board_ranks: vec![],
+                        low_hand: None,
                         cards: None,
                         winnings,
-                    }
+                        low_winnings: 0,
+                    })
                 }
             })
-            .collect();
+            .collect::<GameResult<Vec<ShowdownResult>>>()?;
+        for result in results.iter().filter(|r| r.cards.is_some()) {
+            self.mark_shown(self.player_indices[&result.player_id]);
+        }
 
         self.pot = 0;
-        vec![ServerMessage::Showdown { results }]
+        self.dead_money = 0;
+        let shares: Vec<(PlayerId, u32)> = results.iter().map(|r| (r.player_id, r.winnings)).collect();
+        let tips = self.collect_pot_tips(&shares);
+        let bounties = self.collect_seven_deuce_bounties(&winners)?;
+        let stacks = self.stack_changes(contributed);
+        let mut messages = vec![ServerMessage::Showdown { results, stacks, to_decide: vec![] }];
+        messages.extend(tips);
+        messages.extend(bounties);
+        Ok(messages)
+    }
+
+    /// 玩家的底牌是否是不同花色的 7 和 2 (只适用于开启了七二奖励的德州扑克)
+    fn holds_seven_deuce(&self, player_id: &PlayerId) -> bool {
+        if self.room_options.seven_deuce_bounty == 0 || self.variant != GameVariant::TexasHoldem {
+            return false;
+        }
+        let Some(&idx) = self.player_indices.get(player_id) else { return false };
+        match self.player_cards[idx].as_slice() {
+            [Some(a), Some(b)] => {
+                let mut ranks = [a.rank, b.rank];
+                ranks.sort();
+                ranks == [Rank::Two, Rank::Seven] && a.suit != b.suit
+            }
+            _ => false,
+        }
+    }
+
+    /// 收取赢家设置的底池小费: `shares` 是每个池中各位赢家分到的筹码，每个池单独收取，
+    /// 在一个池中赢得的筹码少于设置的小费时只给赢得的部分
+    fn collect_pot_tips(&mut self, shares: &[(PlayerId, u32)]) -> Vec<ServerMessage> {
+        let mut messages = vec![];
+        for &(player_id, won) in shares {
+            let Some(player) = self.players.get_mut(&player_id) else { continue };
+            let amount = player.pot_tip.min(won);
+            if amount == 0 {
+                continue;
+            }
+            player.stack -= amount;
+            let entry = LedgerEntry { player_id, kind: LedgerKind::Tip, amount };
+            self.host_ledger.entries.push(entry.clone());
+            messages.push(ServerMessage::HostLedgerUpdated { entry, new_stack: player.stack });
+        }
+        messages
+    }
+
+    /// 向用 7-2 赢下奖池的玩家支付七二奖励: 其他每位就座的玩家各付 `seven_deuce_bounty`，筹码不足时付出全部筹码
+    fn collect_seven_deuce_bounties(&mut self, winners: &[PlayerId]) -> GameResult {
+        let bounty = self.room_options.seven_deuce_bounty;
+        let mut messages = vec![];
+        let bounty_winners: Vec<PlayerId> = winners.iter().filter(|id| self.holds_seven_deuce(id)).cloned().collect();
+        for winner in &bounty_winners {
+            let mut payments = vec![];
+            for payer in self.seated_players.iter().filter(|id| *id != winner) {
+                let player = self.players.get_mut(payer).ok_or(GameError::PlayerNotFound)?;
+                let amount = bounty.min(player.stack);
+                if amount > 0 {
+                    player.stack -= amount;
+                    payments.push((*payer, amount));
+                }
+            }
+            let total: u32 = payments.iter().map(|(_, amount)| amount).sum();
+            self.players.get_mut(winner).ok_or(GameError::PlayerNotFound)?.stack += total;
+            messages.push(ServerMessage::SevenDeuceBounty { winner: *winner, payments });
+        }
+        Ok(messages)
     }
+
+    /// 结算之前，每位参与者本局投入奖池的筹码 (开局时的筹码减去现在的筹码)
+    fn hand_contributions(&self) -> Vec<u32> {
+        self.hand_player_order.iter().zip(&self.starting_stacks)
+            .map(|(id, before)| before.saturating_sub(self.players.get(id).map_or(0, |p| p.stack)))
+            .collect()
+    }
+
+    /// 结算之后，每位参与者本局的筹码变化
+    fn stack_changes(&self, contributed: Vec<u32>) -> Vec<StackChange> {
+        self.hand_player_order.iter().zip(&self.starting_stacks).zip(contributed)
+            .map(|((id, &before), contributed)| StackChange {
+                player_id: *id,
+                before,
+                after: self.players.get(id).map_or(0, |p| p.stack),
+                contributed,
+            })
+            .collect()
+    }
+}
+
+/// 平分 `amount` 筹码时排在第 `i` 位的赢家分到的筹码: 除不尽的零头每人一枚，从排在最前面的赢家开始发
+fn split_share(amount: u32, winners: usize, i: usize) -> u32 {
+    let winners = winners as u32;
+    amount / winners + u32::from((i as u32) < amount % winners)
+}
+
+/// 从 (玩家, 牌力) 中找出牌力最大的所有玩家 (平局时有多个)
+fn find_winners<'a, T: Ord + 'a>(candidates: impl Iterator<Item = (PlayerId, &'a T)>) -> Vec<PlayerId> {
+    let mut winners: Vec<PlayerId> = Vec::new();
+    let mut best_rank: Option<&T> = None;
+    for (id, rank) in candidates {
+        match best_rank {
+            Some(br) if rank < br => {}
+            Some(br) if rank == br => winners.push(id),
+            _ => {
+                best_rank = Some(rank);
+                winners.clear();
+                winners.push(id);
+            }
+        }
+    }
+    winners
+}
+
+/// 由每位玩家的净输赢算出结算转账: 输得最多的玩家先付给赢得最多的玩家，转账次数不超过玩家数。
+/// 输家付清所有赢家后还欠的部分是交给房主账本的小费和场地费
+fn settle(players: &[PlayerSummary]) -> Vec<Settlement> {
+    let mut winners: Vec<(PlayerId, i64)> = players.iter().filter(|p| p.net > 0).map(|p| (p.player_id, p.net)).collect();
+    let mut losers: Vec<(PlayerId, i64)> = players.iter().filter(|p| p.net < 0).map(|p| (p.player_id, -p.net)).collect();
+    winners.sort_by_key(|(_, net)| std::cmp::Reverse(*net));
+    losers.sort_by_key(|(_, net)| std::cmp::Reverse(*net));
+
+    let mut settlements = vec![];
+    let mut winners = winners.into_iter().peekable();
+    for (from, mut owed) in losers {
+        while owed > 0 {
+            let Some((to, due)) = winners.peek_mut() else {
+                settlements.push(Settlement { from, to: None, amount: owed as u32 });
+                break;
+            };
+            let amount = owed.min(*due);
+            settlements.push(Settlement { from, to: Some(*to), amount: amount as u32 });
+            owed -= amount;
+            *due -= amount;
+            if *due == 0 {
+                winners.next();
+            }
+        }
+    }
+    settlements
 }
 
 // --- 单元测试 ---
@@ -902,41 +2566,22 @@ impl GameState {
 mod tests {
     use super::*;
     use crate::card::{Rank, Suit};
+    use crate::event::{HandHistory, RoomTimeline, MAX_TIMELINE_ENTRIES};
+    use crate::error::IntoMessages;
+    use crate::message::HostAction;
+    use crate::overlay::{OverlayAction, TableOverlay};
+    use crate::explain::Explanation;
+    use crate::guess::GuessScore;
+    use crate::locale::{describe_cards, Describe, Locale};
     use crate::state::Player;
-    use std::collections::VecDeque;
     use uuid::Uuid;
 
     // 辅助函数：创建用于测试的GameState
     fn setup_test_game(player_stacks: &[u32]) -> (GameState, Vec<PlayerId>) {
-        let mut players = HashMap::new();
-        let mut seated_players = VecDeque::new();
-        let mut player_ids = Vec::new();
-
-        for &stack in player_stacks {
-            let player_id = Uuid::new_v4();
-            let player = Player {
-                id: player_id,
-                nickname: format!("Player_{}", player_id.simple()),
-                stack,
-                wins: 0,
-                losses: 0,
-                state: PlayerState::Waiting,
-                seat_id: None,
-                is_offline: false,
-            };
-            players.insert(player_id, player);
-            seated_players.push_back(player_id);
-            player_ids.push(player_id);
-        }
-
-        let state = GameState {
-            players,
-            seated_players,
-            small_blind: 10,
-            big_blind: 20,
-            ..Default::default()
-        };
-
+        let mut state = crate::testing::setup_table(player_stacks, RoomOptions::default());
+        // 这里的测试不关心座位号，庄家按就座的顺序轮换
+        state.players.values_mut().for_each(|p| p.seat_id = None);
+        let player_ids = state.seated_players.iter().copied().collect();
         (state, player_ids)
     }
 
@@ -944,7 +2589,7 @@ mod tests {
     fn test_start_new_hand_normal() {
         // 测试正常情况下的开局
         let (mut state, _p_ids) = setup_test_game(&[1000, 1000, 1000, 1000]);
-        state.start_new_hand();
+        state.start_new_hand(Utc::now()).unwrap();
 
         // 验证玩家顺序和数量
         assert_eq!(state.hand_player_order.len(), 4);
@@ -971,7 +2616,7 @@ mod tests {
     fn test_player_action_fold_and_win() {
         // 测试玩家弃牌和最终一人获胜
         let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
-        state.start_new_hand(); // p0=庄家, p1=SB, p2=BB. 轮到p0行动
+        state.start_new_hand(Utc::now()).unwrap(); // p0=庄家, p1=SB, p2=BB. 轮到p0行动
 
         let p0_id = state.hand_player_order[0];
         let p1_id = state.hand_player_order[1];
@@ -979,14 +2624,14 @@ mod tests {
         // p0行动 (第一个行动者是p0)
         // Note: 3人局，BB(p2)之后是Dealer(p0)行动
         state.cur_player_idx = 0;
-        state.handle_player_action(p0_id, PlayerAction::Fold);
+        state.handle_player_action(p0_id, PlayerAction::Fold, Utc::now()).unwrap();
         assert_eq!(
             state.players.get(&p0_id).unwrap().state,
             PlayerState::Folded
         );
 
         // p1行动
-        state.handle_player_action(p1_id, PlayerAction::Fold);
+        state.handle_player_action(p1_id, PlayerAction::Fold, Utc::now()).unwrap();
         assert_eq!(
             state.players.get(&p1_id).unwrap().state,
             PlayerState::Folded
@@ -1002,7 +2647,7 @@ mod tests {
     fn test_betting_round_ends_and_advances_to_flop() {
         // 测试一轮下注结束并进入Flop阶段
         let (mut state, _p_ids) = setup_test_game(&[1000, 1000, 1000]);
-        state.start_new_hand(); // p0=D, p1=SB, p2=BB. 轮到p0行动.
+        state.start_new_hand(Utc::now()).unwrap(); // p0=D, p1=SB, p2=BB. 轮到p0行动.
 
         let p0_id = state.hand_player_order[0];
         let p1_id = state.hand_player_order[1];
@@ -1010,14 +2655,14 @@ mod tests {
 
         // 3人局，行动顺序是 p0 -> p1 -> p2
         assert_eq!(state.cur_player_idx, 0);
-        state.handle_player_action(p0_id, PlayerAction::Call); // p0跟20
-        state.handle_player_action(p1_id, PlayerAction::Call); // p1补10
-        state.handle_player_action(p2_id, PlayerAction::Check); // p2过牌
+        state.handle_player_action(p0_id, PlayerAction::Call, Utc::now()).unwrap(); // p0跟20
+        state.handle_player_action(p1_id, PlayerAction::Call, Utc::now()).unwrap(); // p1补10
+        state.handle_player_action(p2_id, PlayerAction::Check, Utc::now()).unwrap(); // p2过牌
 
         // 验证阶段推进
         assert_eq!(state.phase, GamePhase::Flop);
         assert_eq!(state.pot, 60);
-        assert_eq!(state.community_cards.iter().flatten().count(), 3);
+        assert_eq!(state.community_cards[0].iter().flatten().count(), 3);
 
         // 验证Flop轮第一个行动者是SB (如果还在牌局中)
         assert_eq!(state.current_player_id(), Some(p1_id));
@@ -1027,41 +2672,41 @@ mod tests {
     fn test_showdown_logic_simple_winner() {
         // 测试摊牌逻辑
         let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
-        state.start_new_hand();
+        state.start_new_hand(Utc::now()).unwrap();
 
         let p0_id = p_ids[0]; // Dealer / SB
         let p1_id = p_ids[1]; // BB
 
         // Pre-flop action: p0 calls, p1 checks
-        state.handle_player_action(p0_id, PlayerAction::Call);
-        state.handle_player_action(p1_id, PlayerAction::Check);
+        state.handle_player_action(p0_id, PlayerAction::Call, Utc::now()).unwrap();
+        state.handle_player_action(p1_id, PlayerAction::Check, Utc::now()).unwrap();
 
         // Manually set phase and cards for showdown
         state.phase = GamePhase::Showdown;
         assert_eq!(state.pot, 40); // SB 20 + BB 20
 
-        state.community_cards = vec![
+        state.community_cards = vec![vec![
             Some(Card::new(Rank::Ace, Suit::Spade)),
             Some(Card::new(Rank::King, Suit::Spade)),
             Some(Card::new(Rank::Queen, Suit::Spade)),
             Some(Card::new(Rank::Two, Suit::Heart)),
             Some(Card::new(Rank::Three, Suit::Heart)),
-        ];
+        ]];
         // p0: 同花顺
-        state.player_cards[0] = (
+        state.player_cards[0] = vec![
             Some(Card::new(Rank::Jack, Suit::Spade)),
             Some(Card::new(Rank::Ten, Suit::Spade)),
-        );
+        ];
         // p1: 三条A
-        state.player_cards[1] = (
+        state.player_cards[1] = vec![
             Some(Card::new(Rank::Ace, Suit::Club)),
             Some(Card::new(Rank::Ace, Suit::Diamond)),
-        );
+        ];
 
         state.players.get_mut(&p0_id).unwrap().state = PlayerState::Playing;
         state.players.get_mut(&p1_id).unwrap().state = PlayerState::Playing;
 
-        state.handle_showdown();
+        state.handle_showdown().unwrap();
 
         assert_eq!(state.phase, GamePhase::Showdown);
         let p0_stack = state.players.get(&p0_id).unwrap().stack;
@@ -1078,7 +2723,7 @@ mod tests {
     fn test_start_new_hand_heads_up_rules() {
         // 测试两人单挑(Heads-up)的特殊规则
         let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
-        state.start_new_hand();
+        state.start_new_hand(Utc::now()).unwrap();
 
         let dealer_id = p_ids[0]; // 庄家
         let bb_id = p_ids[1]; // 大盲
@@ -1093,12 +2738,12 @@ mod tests {
         assert_eq!(state.current_player_id(), Some(dealer_id));
 
         // 庄家跟注
-        state.handle_player_action(dealer_id, PlayerAction::Call);
+        state.handle_player_action(dealer_id, PlayerAction::Call, Utc::now()).unwrap();
         // 轮到大盲行动
         assert_eq!(state.current_player_id(), Some(bb_id));
 
         // 大盲过牌，进入翻牌圈
-        state.handle_player_action(bb_id, PlayerAction::Check);
+        state.handle_player_action(bb_id, PlayerAction::Check, Utc::now()).unwrap();
         assert_eq!(state.phase, GamePhase::Flop);
 
         // 翻牌后，大盲(p1)先行动
@@ -1109,7 +2754,7 @@ mod tests {
     fn test_walk_bb_wins_blinds() {
         // 测试所有人都弃牌，大盲直接获胜 (Walk)
         let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
-        state.start_new_hand(); // p0=D, p1=SB, p2=BB
+        state.start_new_hand(Utc::now()).unwrap(); // p0=D, p1=SB, p2=BB
 
         let p0_id = p_ids[0];
         let p1_id = p_ids[1];
@@ -1117,8 +2762,8 @@ mod tests {
 
         // 行动顺序 p0 -> p1 -> p2
         state.cur_player_idx = 0;
-        state.handle_player_action(p0_id, PlayerAction::Fold);
-        state.handle_player_action(p1_id, PlayerAction::Fold);
+        state.handle_player_action(p0_id, PlayerAction::Fold, Utc::now()).unwrap();
+        state.handle_player_action(p1_id, PlayerAction::Fold, Utc::now()).unwrap();
 
         // 此时只剩大盲，牌局应结束
         assert_eq!(state.phase, GamePhase::Showdown);
@@ -1131,7 +2776,7 @@ mod tests {
     fn test_full_betting_round_with_raise_and_reraise() {
         // 测试包含加注和再加注的完整下注轮
         let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000, 1000]);
-        state.start_new_hand(); // p0=D, p1=SB, p2=BB, p3=UTG
+        state.start_new_hand(Utc::now()).unwrap(); // p0=D, p1=SB, p2=BB, p3=UTG
 
         let p1_id = p_ids[1]; // SB
         let p2_id = p_ids[2]; // BB
@@ -1139,21 +2784,21 @@ mod tests {
         let p0_id = p_ids[0]; // D
 
         // p3 (UTG) 加注到 60
-        state.handle_player_action(p3_id, PlayerAction::BetOrRaise(60));
+        state.handle_player_action(p3_id, PlayerAction::BetOrRaise(60), Utc::now()).unwrap();
         assert_eq!(state.max_bet, 60);
         assert_eq!(state.players.get(&p3_id).unwrap().stack, 940);
 
         // p0 (Dealer) 跟注 60
-        state.handle_player_action(p0_id, PlayerAction::Call);
+        state.handle_player_action(p0_id, PlayerAction::Call, Utc::now()).unwrap();
         assert_eq!(state.players.get(&p0_id).unwrap().stack, 940);
 
         // p1 (SB) 再加注到 180
-        state.handle_player_action(p1_id, PlayerAction::BetOrRaise(170));
+        state.handle_player_action(p1_id, PlayerAction::BetOrRaise(170), Utc::now()).unwrap();
         assert_eq!(state.max_bet, 180);
         assert_eq!(state.players.get(&p1_id).unwrap().stack, 820); // 1000 - 180
 
         // p2 (BB) 弃牌
-        state.handle_player_action(p2_id, PlayerAction::Fold);
+        state.handle_player_action(p2_id, PlayerAction::Fold, Utc::now()).unwrap();
         assert_eq!(
             state.players.get(&p2_id).unwrap().state,
             PlayerState::Folded
@@ -1161,12 +2806,12 @@ mod tests {
 
         // 轮回到 p3，他需要补齐差额 (180 - 60 = 120)
         assert_eq!(state.current_player_id(), Some(p3_id));
-        state.handle_player_action(p3_id, PlayerAction::Call);
+        state.handle_player_action(p3_id, PlayerAction::Call, Utc::now()).unwrap();
         assert_eq!(state.players.get(&p3_id).unwrap().stack, 940 - 120);
 
         // 轮回到 p0，他也需要补齐差额 (180 - 60 = 120)
         assert_eq!(state.current_player_id(), Some(p0_id));
-        state.handle_player_action(p0_id, PlayerAction::Call);
+        state.handle_player_action(p0_id, PlayerAction::Call, Utc::now()).unwrap();
         assert_eq!(state.players.get(&p0_id).unwrap().stack, 940 - 120);
 
         // p1 是最后一个加注者，他之后所有人都跟注了，下注轮结束
@@ -1179,7 +2824,7 @@ mod tests {
     fn test_player_all_in_on_blind() {
         // 测试玩家在下盲注时就All-in
         let (mut state, p_ids) = setup_test_game(&[1000, 15, 1000]); // p1 只有 15
-        state.start_new_hand(); // p0=D, p1=SB, p2=BB
+        state.start_new_hand(Utc::now()).unwrap(); // p0=D, p1=SB, p2=BB
 
         let p1_id = p_ids[1];
         // p1 下小盲注10，还剩5
@@ -1187,10 +2832,10 @@ mod tests {
 
         // 轮到p0行动，他跟注20
         let p0_id = p_ids[0];
-        state.handle_player_action(p0_id, PlayerAction::Call);
+        state.handle_player_action(p0_id, PlayerAction::Call, Utc::now()).unwrap();
 
         // 轮到p1行动，他跟注剩下的5，All-in
-        state.handle_player_action(p1_id, PlayerAction::Call);
+        state.handle_player_action(p1_id, PlayerAction::Call, Utc::now()).unwrap();
         assert_eq!(state.players.get(&p1_id).unwrap().stack, 0);
         assert_eq!(state.players.get(&p1_id).unwrap().state, PlayerState::AllIn);
         assert_eq!(state.bets[1], 15); // SB 10 + Call 5
@@ -1200,35 +2845,35 @@ mod tests {
     fn test_multiple_all_ins_auto_showdown() {
         // 测试多于一个玩家All-in，游戏自动发完牌并进入摊牌
         let (mut state, p_ids) = setup_test_game(&[50, 100, 1000]); // p0, p1 筹码较少
-        state.start_new_hand(); // p0=D, p1=SB, p2=BB
+        state.start_new_hand(Utc::now()).unwrap(); // p0=D, p1=SB, p2=BB
 
         let p0_id = p_ids[0];
         let p1_id = p_ids[1];
         let p2_id = p_ids[2];
 
         // p0 (D) all-in 50
-        state.handle_player_action(p0_id, PlayerAction::BetOrRaise(50));
+        state.handle_player_action(p0_id, PlayerAction::BetOrRaise(50), Utc::now()).unwrap();
         assert_eq!(state.players.get(&p0_id).unwrap().state, PlayerState::AllIn);
 
         // p1 (SB) all-in 100
-        state.handle_player_action(p1_id, PlayerAction::BetOrRaise(90));
+        state.handle_player_action(p1_id, PlayerAction::BetOrRaise(90), Utc::now()).unwrap();
         assert_eq!(state.players.get(&p1_id).unwrap().state, PlayerState::AllIn);
 
         // p2 (BB) call 100
-        state.handle_player_action(p2_id, PlayerAction::Call);
+        state.handle_player_action(p2_id, PlayerAction::Call, Utc::now()).unwrap();
 
         // 因为除了p2之外所有人都all-in了，没有后续下注轮
         // 游戏应该直接发完所有公共牌并进入摊牌
         assert_eq!(state.phase, GamePhase::Showdown);
-        assert_eq!(state.community_cards.iter().all(|c| c.is_some()), true);
-        assert_eq!(state.community_cards.iter().flatten().count(), 5);
+        assert!(state.community_cards[0].iter().all(|c| c.is_some()));
+        assert_eq!(state.community_cards[0].iter().flatten().count(), 5);
     }
 
     #[test]
     fn test_full_multi_stage_hand_flow() {
         // 测试一个完整的多人、多阶段牌局流程
         let (mut state, p_ids) = setup_test_game(&[2000, 2000, 2000, 2000]);
-        state.start_new_hand();
+        state.start_new_hand(Utc::now()).unwrap();
 
         let p0_id = p_ids[0]; // D
         let p1_id = p_ids[1]; // SB
@@ -1237,13 +2882,13 @@ mod tests {
 
         // --- 翻牌前 (Pre-flop) ---
         // UTG 加注到 60
-        state.handle_player_action(p3_id, PlayerAction::BetOrRaise(60));
+        state.handle_player_action(p3_id, PlayerAction::BetOrRaise(60), Utc::now()).unwrap();
         // D 弃牌
-        state.handle_player_action(p0_id, PlayerAction::Fold);
+        state.handle_player_action(p0_id, PlayerAction::Fold, Utc::now()).unwrap();
         // SB 跟注 (补50)
-        state.handle_player_action(p1_id, PlayerAction::Call);
+        state.handle_player_action(p1_id, PlayerAction::Call, Utc::now()).unwrap();
         // BB 跟注 (补40)
-        state.handle_player_action(p2_id, PlayerAction::Call);
+        state.handle_player_action(p2_id, PlayerAction::Call, Utc::now()).unwrap();
 
         assert_eq!(state.phase, GamePhase::Flop);
         assert_eq!(state.pot, 180); // 60 * 3
@@ -1251,15 +2896,15 @@ mod tests {
 
         // --- 翻牌圈 (Flop) ---
         // SB 过牌
-        state.handle_player_action(p1_id, PlayerAction::Check);
+        state.handle_player_action(p1_id, PlayerAction::Check, Utc::now()).unwrap();
         // BB 过牌
-        state.handle_player_action(p2_id, PlayerAction::Check);
+        state.handle_player_action(p2_id, PlayerAction::Check, Utc::now()).unwrap();
         // UTG 下注 90
-        state.handle_player_action(p3_id, PlayerAction::BetOrRaise(90));
+        state.handle_player_action(p3_id, PlayerAction::BetOrRaise(90), Utc::now()).unwrap();
         // SB 弃牌
-        state.handle_player_action(p1_id, PlayerAction::Fold);
+        state.handle_player_action(p1_id, PlayerAction::Fold, Utc::now()).unwrap();
         // BB 跟注 90
-        state.handle_player_action(p2_id, PlayerAction::Call);
+        state.handle_player_action(p2_id, PlayerAction::Call, Utc::now()).unwrap();
 
         assert_eq!(state.phase, GamePhase::Turn);
         assert_eq!(state.pot, 180 + 90 + 90); // 360
@@ -1267,9 +2912,9 @@ mod tests {
 
         // --- 转牌圈 (Turn) ---
         // BB 过牌
-        state.handle_player_action(p2_id, PlayerAction::Check);
+        state.handle_player_action(p2_id, PlayerAction::Check, Utc::now()).unwrap();
         // UTG 过牌
-        state.handle_player_action(p3_id, PlayerAction::Check);
+        state.handle_player_action(p3_id, PlayerAction::Check, Utc::now()).unwrap();
 
         assert_eq!(state.phase, GamePhase::River);
         assert_eq!(state.pot, 360);
@@ -1277,29 +2922,29 @@ mod tests {
 
         // --- 摊牌 (Showdown) ---
         // 手动设置牌面，让 p2 获胜
-        state.community_cards = vec![
+        state.community_cards = vec![vec![
             Some(Card::new(Rank::Ace, Suit::Spade)),
             Some(Card::new(Rank::King, Suit::Spade)),
             Some(Card::new(Rank::Two, Suit::Heart)),
             Some(Card::new(Rank::Three, Suit::Heart)),
             Some(Card::new(Rank::Four, Suit::Club)),
-        ];
+        ]];
         // p2 (BB): 一对A
-        state.player_cards[2] = (
+        state.player_cards[2] = vec![
             Some(Card::new(Rank::Ace, Suit::Club)),
             Some(Card::new(Rank::Queen, Suit::Diamond)),
-        );
+        ];
         // p3 (UTG): 一对K
-        state.player_cards[3] = (
+        state.player_cards[3] = vec![
             Some(Card::new(Rank::King, Suit::Club)),
             Some(Card::new(Rank::Queen, Suit::Spade)),
-        );
+        ];
 
         // --- 河牌圈 (River) ---
         // BB 下注 200
-        state.handle_player_action(p2_id, PlayerAction::BetOrRaise(200));
+        state.handle_player_action(p2_id, PlayerAction::BetOrRaise(200), Utc::now()).unwrap();
         // UTG 跟注 200
-        state.handle_player_action(p3_id, PlayerAction::Call);
+        state.handle_player_action(p3_id, PlayerAction::Call, Utc::now()).unwrap();
 
         assert_eq!(state.phase, GamePhase::Showdown);
         let p2_final_stack = state.players.get(&p2_id).unwrap().stack;
@@ -1324,7 +2969,7 @@ mod tests {
         state.phase = GamePhase::Showdown;
         state.hand_player_order = p_ids.clone();
         state.player_indices = p_ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
-        state.player_cards = vec![(None, None); 3];
+        state.player_cards = vec![vec![None, None]; 3];
 
         // 模拟下注: P0 all-in 50, P1 all-in 200, P2 跟注 200
         state.pot = 450;
@@ -1340,27 +2985,27 @@ mod tests {
         state.players.get_mut(&p2_id).unwrap().state = PlayerState::Playing;
 
         // 设置牌力: P0 (最强) > P2 (中等) > P1 (最弱)
-        state.community_cards = vec![
+        state.community_cards = vec![vec![
             Some(Card::new(Rank::Ace, Suit::Spade)),
             Some(Card::new(Rank::Ace, Suit::Heart)),
             Some(Card::new(Rank::King, Suit::Club)),
             Some(Card::new(Rank::Queen, Suit::Diamond)),
             Some(Card::new(Rank::Two, Suit::Spade)),
-        ];
-        state.player_cards[0] = (
+        ]];
+        state.player_cards[0] = vec![
             Some(Card::new(Rank::King, Suit::Spade)),
             Some(Card::new(Rank::King, Suit::Heart)),
-        ); // P0: 葫芦 (A, K)
-        state.player_cards[1] = (
+        ]; // P0: 葫芦 (A, K)
+        state.player_cards[1] = vec![
             Some(Card::new(Rank::Queen, Suit::Spade)),
             Some(Card::new(Rank::Jack, Suit::Club)),
-        ); // P1: 两对 (A, Q)
-        state.player_cards[2] = (
+        ]; // P1: 两对 (A, Q)
+        state.player_cards[2] = vec![
             Some(Card::new(Rank::Ace, Suit::Diamond)),
             Some(Card::new(Rank::Ten, Suit::Club)),
-        ); // P2: 三条 (A)
+        ]; // P2: 三条 (A)
 
-        state.handle_showdown();
+        state.handle_showdown().unwrap();
 
         // 验证奖池分配结果
         // 1. 主池 (Main Pot): P0, P1, P2 各出50，共150。P0牌最大，赢得主池。
@@ -1392,7 +3037,7 @@ mod tests {
         state.phase = GamePhase::Showdown;
         state.hand_player_order = p_ids.clone();
         state.player_indices = p_ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
-        state.player_cards = vec![(None, None); 2];
+        state.player_cards = vec![vec![None, None]; 2];
 
         // 模拟下注: P0下注500, P1跟注all-in 300
         state.pot = 500 + 300;
@@ -1403,31 +3048,31 @@ mod tests {
         state.players.get_mut(&p0_id).unwrap().state = PlayerState::Playing;
         state.players.get_mut(&p1_id).unwrap().state = PlayerState::AllIn;
         // P0 牌更好
-        state.community_cards = vec![
+        state.community_cards = vec![vec![
             Some(Card::new(Rank::Ten, Suit::Spade)),
             Some(Card::new(Rank::Jack, Suit::Spade)),
             Some(Card::new(Rank::Queen, Suit::Spade)),
             Some(Card::new(Rank::Two, Suit::Heart)),
             Some(Card::new(Rank::Three, Suit::Club)),
-        ];
-        state.player_cards[0] = (
+        ]];
+        state.player_cards[0] = vec![
             Some(Card::new(Rank::Ace, Suit::Spade)),
             Some(Card::new(Rank::Ace, Suit::Heart)),
-        );
-        state.player_cards[1] = (
+        ];
+        state.player_cards[1] = vec![
             Some(Card::new(Rank::King, Suit::Spade)),
             Some(Card::new(Rank::King, Suit::Heart)),
-        );
+        ];
 
         // 在摊牌前，P0未被跟注的200应该被退回
-        state.return_uncalled_bets();
+        state.return_uncalled_bets().unwrap();
         assert_eq!(state.pot, 600); // 300 from P0, 300 from P1
         assert_eq!(state.bets, vec![300, 300]);
         // P0 初始1000, 下注500, 退回200. 剩余 700
         assert_eq!(state.players.get(&p0_id).unwrap().stack, 700);
 
         // P0赢得底池600
-        state.distribute_pots();
+        state.distribute_pots().unwrap();
         assert_eq!(state.players.get(&p0_id).unwrap().stack, 700 + 600);
         assert_eq!(state.players.get(&p1_id).unwrap().stack, 0);
     }
@@ -1443,7 +3088,7 @@ mod tests {
         state.phase = GamePhase::Showdown;
         state.hand_player_order = p_ids.clone();
         state.player_indices = p_ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
-        state.player_cards = vec![(None, None); 3];
+        state.player_cards = vec![vec![None, None]; 3];
 
         // 模拟下注: P0 all-in 50, P1 和 P2 都跟注到了500
         state.pot = 50 + 500 + 500;
@@ -1457,27 +3102,27 @@ mod tests {
         state.players.get_mut(&p2_id).unwrap().state = PlayerState::Playing;
 
         // P0 (皇家同花顺) > P1 (同花顺) == P2 (同花顺)
-        state.community_cards = vec![
+        state.community_cards = vec![vec![
             Some(Card::new(Rank::Ten, Suit::Spade)),
             Some(Card::new(Rank::Jack, Suit::Spade)),
             Some(Card::new(Rank::Queen, Suit::Spade)),
             Some(Card::new(Rank::Two, Suit::Heart)),
             Some(Card::new(Rank::Three, Suit::Club)),
-        ];
-        state.player_cards[0] = (
+        ]];
+        state.player_cards[0] = vec![
             Some(Card::new(Rank::Ace, Suit::Spade)),
             Some(Card::new(Rank::King, Suit::Spade)),
-        );
-        state.player_cards[1] = (
+        ];
+        state.player_cards[1] = vec![
             Some(Card::new(Rank::Nine, Suit::Spade)),
             Some(Card::new(Rank::Eight, Suit::Spade)),
-        );
-        state.player_cards[2] = (
+        ];
+        state.player_cards[2] = vec![
             Some(Card::new(Rank::Nine, Suit::Spade)),
             Some(Card::new(Rank::Eight, Suit::Spade)),
-        );
+        ];
 
-        state.handle_showdown();
+        state.handle_showdown().unwrap();
 
         // 主池: 50 * 3 = 150. P0 赢.
         // P0 初始 0 (all-in 50), 赢得 150.
@@ -1490,11 +3135,53 @@ mod tests {
         assert_eq!(state.players.get(&p2_id).unwrap().stack, 450);
     }
 
+    #[test]
+    fn test_odd_chips_go_clockwise_from_button() {
+        // 公共牌是皇家同花顺，摊牌的玩家平分奖池，除不尽的零头来自大盲前注
+        let setup = |dead_money: u32| {
+            let (mut state, p_ids) = setup_test_game(&[0, 0, 0]);
+            state.phase = GamePhase::Showdown;
+            state.hand_player_order = p_ids.clone();
+            state.player_indices = p_ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+            state.player_cards = vec![vec![Some(Card::new(Rank::Two, Suit::Club)), Some(Card::new(Rank::Three, Suit::Diamond))]; 3];
+            state.community_cards = vec![[Rank::Ace, Rank::King, Rank::Queen, Rank::Jack, Rank::Ten]
+                .map(|rank| Some(Card::new(rank, Suit::Spade)))
+                .to_vec()];
+            state.bets = vec![100; 3];
+            state.dead_money = dead_money;
+            state.pot = 300 + dead_money;
+            for id in &p_ids {
+                state.players.get_mut(id).unwrap().state = PlayerState::AllIn;
+            }
+            (state, p_ids)
+        };
+        let stacks = |state: &GameState, p_ids: &[PlayerId]| p_ids.iter().map(|id| state.players[id].stack).collect::<Vec<_>>();
+
+        // 一枚零头给庄家左手边的第一位赢家 (小盲)
+        let (mut state, p_ids) = setup(1);
+        state.handle_showdown().unwrap();
+        assert_eq!(stacks(&state, &p_ids), vec![100, 101, 100]);
+
+        // 两枚零头从小盲开始顺时针每人一枚，庄家排在最后
+        let (mut state, p_ids) = setup(2);
+        state.handle_showdown().unwrap();
+        assert_eq!(stacks(&state, &p_ids), vec![100, 101, 101]);
+
+        // 小盲弃牌时，零头给顺时针的下一位赢家 (大盲)，和赢家的排列顺序无关
+        let (mut state, p_ids) = setup(1);
+        state.players.get_mut(&p_ids[1]).unwrap().state = PlayerState::Folded;
+        state.handle_showdown().unwrap();
+        assert_eq!(stacks(&state, &p_ids), vec![150, 0, 151]);
+        let mut winners = vec![p_ids[0], p_ids[2]];
+        state.sort_from_button(&mut winners);
+        assert_eq!(winners, vec![p_ids[2], p_ids[0]]);
+    }
+
     #[test]
     fn test_big_blind_option_to_raise() {
         // 测试当所有人只是跟注到大盲，行动返回给大盲时，他可以选择加注
         let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
-        state.start_new_hand(); // p0=D, p1=SB(10), p2=BB(20)
+        state.start_new_hand(Utc::now()).unwrap(); // p0=D, p1=SB(10), p2=BB(20)
 
         let p0_id = p_ids[0];
         let p1_id = p_ids[1];
@@ -1502,18 +3189,18 @@ mod tests {
 
         // 行动顺序 p0 -> p1 -> p2
         // p0 跟注20
-        state.handle_player_action(p0_id, PlayerAction::Call);
+        state.handle_player_action(p0_id, PlayerAction::Call, Utc::now()).unwrap();
         assert_eq!(state.current_player_id(), Some(p1_id));
 
         // p1 跟注 (补10)
-        state.handle_player_action(p1_id, PlayerAction::Call);
+        state.handle_player_action(p1_id, PlayerAction::Call, Utc::now()).unwrap();
         assert_eq!(state.current_player_id(), Some(p2_id));
 
         // 此时行动回到大盲p2，他可以选择check或raise。下注轮并未结束。
-        assert_eq!(state.check_betting_round_over(), false);
+        assert!(!state.check_betting_round_over());
 
         // p2 加注，额外增加40 (总额到60)
-        state.handle_player_action(p2_id, PlayerAction::BetOrRaise(40));
+        state.handle_player_action(p2_id, PlayerAction::BetOrRaise(40), Utc::now()).unwrap();
         assert_eq!(state.max_bet, 60);
 
         // 因为p2加注了，行动权应该回到p0
@@ -1524,9 +3211,9 @@ mod tests {
         );
 
         // p0 弃牌
-        state.handle_player_action(p0_id, PlayerAction::Fold);
+        state.handle_player_action(p0_id, PlayerAction::Fold, Utc::now()).unwrap();
         // p1 跟注60
-        state.handle_player_action(p1_id, PlayerAction::Call);
+        state.handle_player_action(p1_id, PlayerAction::Call, Utc::now()).unwrap();
 
         // PreFlop轮结束，进入Flop
         assert_eq!(state.phase, GamePhase::Flop);
@@ -1546,7 +3233,7 @@ mod tests {
         state.players.get_mut(&p1_id).unwrap().state = PlayerState::Playing;
         state.players.get_mut(&p2_id).unwrap().state = PlayerState::Playing;
 
-        state.start_new_hand();
+        state.start_new_hand(Utc::now()).unwrap();
 
         // 因为活跃玩家（筹码>0）只有一个，游戏无法开始
         assert_eq!(state.hand_player_order.len(), 1);
@@ -1561,7 +3248,7 @@ mod tests {
         // 旋转玩家顺序，让 p0 是庄家, p1 是小盲, p2 是大盲
         // 这样在3人局中，第一个行动的是 p0
         state.seated_players.rotate_left(0);
-        state.start_new_hand();
+        state.start_new_hand(Utc::now()).unwrap();
 
         // 确认第一个行动的是p0
         let p0_id = state.hand_player_order[0];
@@ -1572,8 +3259,8 @@ mod tests {
         state.players.get_mut(&p0_id).unwrap().is_offline = true;
 
         // 调用tick。因为p0需要跟大盲注20，所以他应该自动弃牌。
-        // tick()执行了自动操作，所以返回true
-        assert_eq!(state.tick().0, true);
+        // tick(Utc::now())执行了自动操作，所以返回true
+        assert!(state.tick(Utc::now()).unwrap().0);
 
         // 验证p0已弃牌
         assert_eq!(
@@ -1585,7 +3272,7 @@ mod tests {
         assert_eq!(state.current_player_id(), Some(p1_id));
 
         // 再次调用tick。因为p1是在线的，所以tick()不执行任何操作，返回false
-        assert_eq!(state.tick().0, false);
+        assert!(!state.tick(Utc::now()).unwrap().0);
     }
 
     #[test]
@@ -1599,7 +3286,7 @@ mod tests {
         let p_bb = p_ids[2]; // Big Blind
 
         // 1. 开始游戏
-        let messages = state.start_new_hand();
+        let messages = state.start_new_hand(Utc::now()).unwrap();
         assert_eq!(messages.len(), 4);
         assert!(matches!(messages[0], ServerMessage::HandStarted { .. }));
         assert!(
@@ -1614,7 +3301,7 @@ mod tests {
         assert_eq!(state.pot, 300);
 
         // 2. Dealer (UTG) 弃牌
-        let messages = state.handle_player_action(p_dealer, PlayerAction::Fold);
+        let messages = state.handle_player_action(p_dealer, PlayerAction::Fold, Utc::now()).unwrap();
         assert_eq!(messages.len(), 2);
         assert!(
             matches!(messages[0], ServerMessage::PlayerActed { player_id, action: PlayerAction::Fold, .. } if player_id == p_dealer)
@@ -1622,7 +3309,7 @@ mod tests {
         assert!(matches!(messages[1], ServerMessage::NextToAct { player_id, .. } if player_id == p_sb)); // 轮到SB行动
 
         // 3. SB 弃牌
-        let messages = state.handle_player_action(p_sb, PlayerAction::Fold);
+        let messages = state.handle_player_action(p_sb, PlayerAction::Fold, Utc::now()).unwrap();
         // BB 是唯一的赢家
         assert_eq!(messages.len(), 2);
         assert!(
@@ -1630,13 +3317,19 @@ mod tests {
         );
 
         // 验证Showdown消息
-        if let ServerMessage::Showdown { results } = &messages[1] {
+        if let ServerMessage::Showdown { results, stacks, .. } = &messages[1] {
             assert_eq!(results.len(), 1);
             let winner_result = &results[0];
             assert_eq!(winner_result.player_id, p_bb);
             assert_eq!(winner_result.winnings, 300); // 赢得盲注
             assert!(winner_result.cards.is_none()); // 不必展示牌
             assert!(winner_result.hand_rank.is_none());
+            // 筹码变化包括弃牌的玩家，按本局玩家顺序排列
+            let change = |id: PlayerId| stacks.iter().find(|c| c.player_id == id).map(|c| (c.before, c.after, c.contributed, c.net()));
+            assert_eq!(stacks.len(), 3);
+            assert_eq!(change(p_dealer), Some((10000, 10000, 0, 0)));
+            assert_eq!(change(p_sb), Some((10000, 9900, 100, -100)));
+            assert_eq!(change(p_bb), Some((10000, 10100, 200, 100)));
         } else {
             panic!("Expected a Showdown message");
         }
@@ -1658,7 +3351,7 @@ mod tests {
         state.players.get_mut(&p_bb).unwrap().stack = 150;
 
         // 1. 开始游戏
-        let messages = state.start_new_hand();
+        let messages = state.start_new_hand(Utc::now()).unwrap();
         assert_eq!(messages.len(), 4);
         // SB 下小盲注 100
         assert!(
@@ -1674,30 +3367,43 @@ mod tests {
         assert_eq!(state.max_bet, 200); // BB All-in 后，最高下注是150 （但是后续玩家仍应该投注200）
 
         // 2. SB 跟注
-        let messages = state.handle_player_action(p_sb, PlayerAction::Call);
+        let mut client = state.for_client(Audience::Spectator);
+        let messages = state.handle_player_action(p_sb, PlayerAction::Call, Utc::now()).unwrap();
+        for m in &messages {
+            client.apply_event(m, None);
+        }
+        assert_eq!((client.pot, &client.bets), (state.pot, &state.bets));
         // 因为有人All-in, 并且下注轮结束，应该直接发完所有公共牌并进入摊牌
-        assert_eq!(messages.len(), 6); // Call, Flop, Turn, River, BetReturned, Showdown
+        assert_eq!(messages.len(), 7); // Call, AllInHandsRevealed, Flop, Turn, River, BetReturned, Showdown
 
         // 验证 Call
         assert!(
             matches!(messages[0], ServerMessage::PlayerActed { player_id, action: PlayerAction::Call, new_stack: 9800, .. } if player_id == p_sb)
         );
+        // 发公共牌之前亮出双方的底牌
+        assert!(
+            matches!(&messages[1], ServerMessage::AllInHandsRevealed { hands } if hands.len() == 2 && hands.iter().all(|(_, cards)| cards.len() == 2))
+        );
         // 验证公共牌
         assert!(
-            matches!(messages[1].clone(), ServerMessage::CommunityCardsDealt { phase: GamePhase::Flop, cards, .. } if cards.len() == 3)
+            matches!(messages[2].clone(), ServerMessage::CommunityCardsDealt { phase: GamePhase::Flop, cards, .. } if cards.len() == 3)
         );
         assert!(
-            matches!(messages[2].clone(), ServerMessage::CommunityCardsDealt { phase: GamePhase::Turn, cards, .. } if cards.len() == 1)
+            matches!(messages[3].clone(), ServerMessage::CommunityCardsDealt { phase: GamePhase::Turn, cards, .. } if cards.len() == 1)
         );
         assert!(
-            matches!(messages[3].clone(), ServerMessage::CommunityCardsDealt { phase: GamePhase::River, cards, .. } if cards.len() == 1)
+            matches!(messages[4].clone(), ServerMessage::CommunityCardsDealt { phase: GamePhase::River, cards, .. } if cards.len() == 1)
         );
         assert!(
-            matches!(messages[4].clone(), ServerMessage::BetReturned { player_id, amount: 50, new_stack: 9850, .. } if player_id == p_sb)
+            matches!(messages[5].clone(), ServerMessage::BetReturned { player_id, amount: 50, new_stack: 9850, new_pot: 300 } if player_id == p_sb)
         );
+        // 中途加入、奖池还没同步的客户端不会因为减去返还的筹码而溢出
+        let mut late = GameState::default();
+        late.apply_event(&messages[5], None);
+        assert_eq!(late.pot, 300);
 
         // 验证摊牌
-        if let ServerMessage::Showdown { results } = &messages[5] {
+        if let ServerMessage::Showdown { results, .. } = &messages[6] {
             assert_eq!(results.len(), 2); // 两个玩家都参与了摊牌
             assert!(results.iter().any(|r| r.player_id == p_sb));
             assert!(results.iter().any(|r| r.player_id == p_bb));
@@ -1713,4 +3419,2128 @@ mod tests {
             panic!("Expected a Showdown message");
         }
     }
+
+    #[test]
+    fn test_dead_blind_posted_and_won() {
+        // 场景: 4人游戏, UTG 位置的玩家错过了盲注并选择补交死盲
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000, 1000]);
+        let p_utg = p_ids[3];
+        {
+            let p = state.players.get_mut(&p_utg).unwrap();
+            p.seat_id = Some(3);
+            p.missed_blinds = MissedBlinds::Big;
+        }
+        let msgs = state.request_dead_blind(p_utg).unwrap();
+        assert!(matches!(msgs[0], ServerMessage::PlayerUpdated { .. }));
+
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.hand_player_order.len(), 4);
+        assert!(messages.iter().any(|m| matches!(m,
+            ServerMessage::DeadBlindPosted { player_id, dead_amount: 10, live_amount: 20, new_stack: 970, new_pot: 60 }
+            if *player_id == p_utg)));
+        assert_eq!(state.dead_bets[3], 10);
+        assert_eq!(state.bets[3], 20);
+        assert_eq!(state.pot, 60);
+        let p = state.players.get(&p_utg).unwrap();
+        assert!(p.missed_blinds == MissedBlinds::None && !p.post_dead_blind);
+
+        // 活的大盲计入下注额，UTG 可以直接过牌
+        state.handle_player_action(p_utg, PlayerAction::Check, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[2], PlayerAction::Fold, Utc::now()).unwrap();
+
+        // UTG 赢下包括死筹码在内的整个奖池
+        assert_eq!(state.players.get(&p_utg).unwrap().stack, 970 + 60);
+    }
+
+    #[test]
+    fn test_antes_posted_before_blinds() {
+        // P1 是小盲，筹码只够交一部分前注，交完前注后全下
+        let (mut state, p_ids) = setup_test_game(&[1000, 5, 1000]);
+        state.room_options.apply(RoomOption::Ante(10));
+        let mut client = state.clone();
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        let antes: Vec<_> = messages.iter().filter_map(|m| match m {
+            ServerMessage::AntePosted { player_id, amount, new_stack, .. } => Some((*player_id, *amount, *new_stack)),
+            _ => None,
+        }).collect();
+        assert_eq!(antes, vec![(p_ids[0], 10, 990), (p_ids[1], 5, 0), (p_ids[2], 10, 990)]);
+        // 前注先于盲注，且不计入下注额
+        let first_blind = messages.iter().position(|m| matches!(m, ServerMessage::PlayerActed { .. })).unwrap();
+        assert!(messages.iter().rposition(|m| matches!(m, ServerMessage::AntePosted { .. })).unwrap() < first_blind);
+        assert_eq!(state.players[&p_ids[1]].state, PlayerState::AllIn);
+        assert_eq!(state.bets, vec![0, 0, 20]);
+        assert_eq!(state.dead_bets, vec![10, 5, 10]);
+        assert_eq!(state.pot, 45);
+        for m in messages.iter() {
+            client.apply_event(m, None);
+        }
+        assert_eq!((client.pot, client.dead_bets.clone()), (state.pot, state.dead_bets.clone()));
+
+        state.handle_player_action(p_ids[0], PlayerAction::Call, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[2], PlayerAction::Check, Utc::now()).unwrap();
+        assert_eq!(state.pot, 65);
+
+        // P1 牌最大，但只能赢得各出 5 的主池；P0 和 P2 各多出的 25 组成边池
+        state.community_cards = vec![vec![
+            Some(Card::new(Rank::Ace, Suit::Spade)),
+            Some(Card::new(Rank::Ace, Suit::Heart)),
+            Some(Card::new(Rank::King, Suit::Club)),
+            Some(Card::new(Rank::Queen, Suit::Diamond)),
+            Some(Card::new(Rank::Two, Suit::Spade)),
+        ]];
+        state.player_cards = vec![
+            vec![Some(Card::new(Rank::Three, Suit::Club)), Some(Card::new(Rank::Four, Suit::Diamond))],
+            vec![Some(Card::new(Rank::Ace, Suit::Diamond)), Some(Card::new(Rank::Ace, Suit::Club))],
+            vec![Some(Card::new(Rank::King, Suit::Spade)), Some(Card::new(Rank::King, Suit::Heart))],
+        ];
+        state.handle_showdown().unwrap();
+        assert_eq!(state.players[&p_ids[1]].stack, 15);
+        assert_eq!(state.players[&p_ids[2]].stack, 970 + 50);
+        assert_eq!(state.players[&p_ids[0]].stack, 970);
+    }
+
+    #[test]
+    fn test_utg_straddle_changes_preflop_order() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000, 1000]);
+        for (i, id) in p_ids.iter().enumerate() {
+            state.players.get_mut(id).unwrap().seat_id = Some(i as u8);
+        }
+        assert_eq!(state.request_straddle(p_ids[3]).unwrap_err(), GameError::StraddleDisabled);
+        state.room_options.apply(RoomOption::Straddle(Some(StraddlePosition::Utg)));
+        state.request_straddle(p_ids[3]).unwrap();
+        let mut client = state.clone();
+
+        // 枪口位抓位 40，最小加注到 80，从抓位玩家的左手边 (庄家) 开始行动
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert!(messages.iter().any(|m| matches!(m,
+            ServerMessage::StraddlePosted { player_id, amount: 40, new_stack: 960, .. } if *player_id == p_ids[3])));
+        assert_eq!(state.bets, vec![0, 10, 20, 40]);
+        assert_eq!((state.max_bet, state.last_raise_amount, state.straddle), (40, 40, 40));
+        assert!(matches!(messages.last(), Some(ServerMessage::NextToAct { player_id, valid_actions, .. })
+            if *player_id == p_ids[0] && valid_actions.contains(&PlayerActionType::Raise { min: 80, max: 1000 })));
+        for m in messages.iter() {
+            client.apply_event(m, None);
+        }
+        assert_eq!((client.bets.clone(), client.max_bet, client.pot), (state.bets.clone(), state.max_bet, state.pot));
+        assert_eq!(client.last_raise_amount, state.last_raise_amount);
+        assert!(!state.players[&p_ids[3]].post_straddle && !client.players[&p_ids[3]].post_straddle);
+
+        // 其他人跟注后，抓位玩家仍有选择权
+        for id in &p_ids[..3] {
+            state.handle_player_action(*id, PlayerAction::Call, Utc::now()).unwrap();
+        }
+        assert_eq!(state.phase, GamePhase::PreFlop);
+        assert_eq!(state.current_player_id(), Some(p_ids[3]));
+        state.handle_player_action(p_ids[3], PlayerAction::Check, Utc::now()).unwrap();
+        assert_eq!(state.phase, GamePhase::Flop);
+        assert_eq!(state.pot, 160);
+    }
+
+    #[test]
+    fn test_street_bet_separates_forced_bets() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000, 1000]);
+        for (i, id) in p_ids.iter().enumerate() {
+            state.players.get_mut(id).unwrap().seat_id = Some(i as u8);
+        }
+        state.room_options.apply(RoomOption::Ante(5));
+        state.room_options.apply(RoomOption::Straddle(Some(StraddlePosition::Utg)));
+        state.request_straddle(p_ids[3]).unwrap();
+        let mut client = state.for_client(Audience::Spectator);
+        for m in state.start_new_hand(Utc::now()).unwrap().iter() {
+            client.apply_event(m, None);
+        }
+        let blind = StreetBet { dead: 5, forced: 10, straddle: false, voluntary: 0 };
+        assert_eq!(state.street_bet(1), blind);
+        assert_eq!(state.street_bet(3), StreetBet { dead: 5, forced: 40, straddle: true, voluntary: 0 });
+        assert_eq!(client.forced_bets, state.forced_bets);
+
+        // 庄家加注到 120，小盲跟注: 小盲的下注中 10 是盲注，110 是主动跟注
+        for (id, action) in [(p_ids[0], PlayerAction::BetOrRaise(120)), (p_ids[1], PlayerAction::Call)] {
+            for m in state.handle_player_action(id, action, Utc::now()).unwrap().iter() {
+                client.apply_event(m, None);
+            }
+        }
+        assert_eq!(client.street_bet(0), StreetBet { dead: 5, forced: 0, straddle: false, voluntary: 120 });
+        assert_eq!(client.street_bet(1), StreetBet { voluntary: 110, ..blind });
+        // 前注不计入下注额，大盲还需要跟注 100
+        assert_eq!(client.to_call(&p_ids[2]), 100);
+
+        for id in [p_ids[2], p_ids[3]] {
+            for m in state.handle_player_action(id, PlayerAction::Call, Utc::now()).unwrap().iter() {
+                client.apply_event(m, None);
+            }
+        }
+        assert_eq!(client.phase, GamePhase::Flop);
+        assert!((0..4).all(|i| client.street_bet(i) == StreetBet::default()));
+    }
+
+    #[test]
+    fn test_street_history_tracks_each_round() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::Ante(5));
+        let mut client = state.for_client(Audience::Spectator);
+        let play = |state: &mut GameState, client: &mut GameState, id: PlayerId, action: PlayerAction| {
+            for m in state.handle_player_action(id, action, Utc::now()).unwrap().iter() {
+                client.apply_event(m, None);
+            }
+        };
+        for m in state.start_new_hand(Utc::now()).unwrap().iter() {
+            client.apply_event(m, None);
+        }
+        // 翻牌前庄家加注到 60，两个盲注跟注；翻牌小盲下注 100，大盲弃牌，庄家跟注
+        play(&mut state, &mut client, p_ids[0], PlayerAction::BetOrRaise(60));
+        play(&mut state, &mut client, p_ids[1], PlayerAction::Call);
+        play(&mut state, &mut client, p_ids[2], PlayerAction::Call);
+        play(&mut state, &mut client, p_ids[1], PlayerAction::BetOrRaise(100));
+        play(&mut state, &mut client, p_ids[2], PlayerAction::Fold);
+        play(&mut state, &mut client, p_ids[0], PlayerAction::Call);
+        assert_eq!(client.phase, GamePhase::Turn);
+        assert_eq!(client.street_levels, state.street_levels);
+        assert_eq!(client.street_history(0), vec![60, 100, 0]);
+        assert_eq!(client.street_history(2), vec![60, 0, 0]);
+        // 投入包括前注
+        assert_eq!((0..3).map(|i| client.hand_invested(i)).collect::<Vec<_>>(), vec![165, 165, 65]);
+    }
+
+    #[test]
+    fn test_button_straddle_small_blind_acts_first() {
+        let (mut state, p_ids) = setup_test_game(&[30, 1000, 1000]);
+        for (i, id) in p_ids.iter().enumerate() {
+            state.players.get_mut(id).unwrap().seat_id = Some(i as u8);
+        }
+        state.room_options.apply(RoomOption::Straddle(Some(StraddlePosition::Button)));
+        state.request_straddle(p_ids[0]).unwrap();
+        // 不在抓位位置的申请作废
+        state.request_straddle(p_ids[1]).unwrap();
+
+        // 庄家筹码不够两倍大盲，抓位全下；不完整的抓位不改变最小加注额
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.bets, vec![30, 10, 20]);
+        assert_eq!(state.players[&p_ids[0]].state, PlayerState::AllIn);
+        assert_eq!((state.max_bet, state.last_raise_amount), (30, 20));
+        assert_eq!(state.current_player_id(), Some(p_ids[1]));
+        assert!(!state.players[&p_ids[1]].post_straddle);
+    }
+
+    #[test]
+    fn test_pot_limit_caps_raises() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::BettingStructure(BettingStructure::PotLimit));
+
+        // 庄家先行动: 跟注 20 后底池为 50，最多加注到 70 (投入 70)
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.betting_structure, BettingStructure::PotLimit);
+        assert!(matches!(messages.last(), Some(ServerMessage::NextToAct { valid_actions, .. })
+            if valid_actions.contains(&PlayerActionType::Raise { min: 40, max: 70 })));
+        assert_eq!(
+            state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(71), Utc::now()).unwrap_err(),
+            GameError::RaiseAbovePotLimit { max: 70 }
+        );
+        state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(70), Utc::now()).unwrap();
+        // 小盲已投入 10: 跟注 60 后底池为 160，最多投入 60 + 160 = 220
+        assert_eq!(state.current_turn().map(|m| match m {
+            ServerMessage::NextToAct { valid_actions, .. } => valid_actions,
+            _ => vec![],
+        }), Some(vec![PlayerActionType::Call(60), PlayerActionType::Raise { min: 110, max: 220 }, PlayerActionType::Fold]));
+        state.handle_player_action(p_ids[1], PlayerAction::Call, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[2], PlayerAction::Call, Utc::now()).unwrap();
+
+        // 翻牌后下注最多为整个底池
+        assert_eq!(state.phase, GamePhase::Flop);
+        assert_eq!(state.current_turn().map(|m| match m {
+            ServerMessage::NextToAct { valid_actions, .. } => valid_actions,
+            _ => vec![],
+        }), Some(vec![PlayerActionType::Check, PlayerActionType::Bet { min: 20, max: 210 }, PlayerActionType::Fold]));
+    }
+
+    #[test]
+    fn test_void_hand_refunds_contributions() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::Ante(5));
+        let mut client = state.clone();
+        let mut events = state.start_new_hand(Utc::now()).unwrap();
+        events.extend(state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap());
+        events.extend(state.handle_player_action(p_ids[1], PlayerAction::Call, Utc::now()).unwrap());
+        assert_eq!(state.pot, 155);
+
+        // 前注、盲注和下注全部退还，回到等待开局的状态
+        events.extend(state.void_hand().unwrap());
+        for id in p_ids.iter() {
+            assert_eq!(state.players[id].stack, 1000);
+            assert_eq!(state.players[id].state, PlayerState::Waiting);
+        }
+        assert_eq!((state.phase, state.pot, state.max_bet), (GamePhase::WaitingForPlayers, 0, 0));
+        assert_eq!(state.void_hand().unwrap_err(), GameError::NoHandInProgress);
+        for m in events.iter() {
+            client.apply_event(m, None);
+        }
+        assert_eq!((client.phase, client.pot), (state.phase, state.pot));
+        assert!(p_ids.iter().all(|id| client.players[id].stack == 1000));
+
+        // 作废后可以正常开始下一局；锦标赛模式下不能直接修改盲注
+        state.set_blinds(25, 50).unwrap();
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.bets, vec![0, 25, 50]);
+        assert_eq!(state.check_blinds(50, 25).unwrap_err(), GameError::InvalidBlinds);
+        state.room_options.tournament = Some(TournamentStructure { hands_per_level: 5, level_secs: 0, levels: vec![] });
+        assert_eq!(state.check_blinds(25, 50).unwrap_err(), GameError::TournamentBlinds);
+    }
+
+    #[test]
+    fn test_spectators_guess_the_winner() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        let spectators: Vec<PlayerId> = (0..2).map(|_| Uuid::new_v4()).collect();
+        for (i, id) in spectators.iter().enumerate() {
+            state.players.insert(*id, Player::new(*id, format!("Rail_{}", i)));
+        }
+        assert_eq!(state.guess_winner(spectators[0], p_ids[0]).unwrap_err(), GameError::GuessingDisabled);
+        state.room_options.apply(RoomOption::SpectatorGuessing(true));
+        assert_eq!(state.guess_winner(spectators[0], p_ids[0]).unwrap_err(), GameError::NoHandInProgress);
+
+        let mut client = state.clone();
+        let mut events = state.start_new_hand(Utc::now()).unwrap();
+        let order = state.hand_player_order.clone();
+        assert_eq!(state.guess_winner(order[0], order[2]).unwrap_err(), GameError::PlayerInHand);
+        events.extend(state.guess_winner(spectators[0], order[2]).unwrap());
+        events.extend(state.handle_player_action(order[0], PlayerAction::Fold, Utc::now()).unwrap());
+        // 已经弃牌的玩家不能被猜，每局只能猜一次
+        assert_eq!(state.guess_winner(spectators[1], order[0]).unwrap_err(), GameError::InvalidGuess);
+        events.extend(state.guess_winner(spectators[1], order[1]).unwrap());
+        assert_eq!(state.guess_winner(spectators[0], order[1]).unwrap_err(), GameError::AlreadyGuessed);
+
+        // 小盲弃牌，大盲赢下本局
+        events.extend(state.handle_player_action(order[1], PlayerAction::Fold, Utc::now()).unwrap());
+        assert!(events.iter().any(|m| matches!(m, ServerMessage::GuessesScored { winners, correct }
+            if *winners == vec![order[2]] && *correct == vec![spectators[0]])));
+        assert!(state.spectator_guesses.guesses.is_empty());
+        let leaderboard = state.spectator_guesses.leaderboard();
+        assert_eq!(leaderboard[0], (spectators[0], GuessScore { correct: 1, total: 1 }));
+        assert_eq!(leaderboard[1], (spectators[1], GuessScore { correct: 0, total: 1 }));
+
+        for m in events.iter() {
+            client.apply_event(m, None);
+        }
+        assert_eq!(client.spectator_guesses.leaderboard(), leaderboard);
+        assert!(p_ids.iter().all(|id| state.players[id].stack == client.players[id].stack));
+    }
+
+    #[test]
+    fn test_guessing_closes_once_hands_are_revealed() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        let spectator = Uuid::new_v4();
+        state.players.insert(spectator, Player::new(spectator, "Rail".to_string()));
+        state.room_options.apply(RoomOption::SpectatorGuessing(true));
+        state.start_new_hand(Utc::now()).unwrap();
+
+        // 弃牌的玩家亮牌不影响竞猜；还在局中的玩家的底牌亮出后不再接受竞猜
+        state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap();
+        state.mark_shown(0);
+        assert!(state.clone().guess_winner(spectator, p_ids[1]).is_ok());
+        state.mark_shown(2);
+        assert_eq!(state.guess_winner(spectator, p_ids[1]).unwrap_err(), GameError::GuessingClosed);
+
+        // 全下跟注后亮牌、发完公共牌直接摊牌
+        state.shown = vec![];
+        state.handle_player_action(p_ids[1], PlayerAction::BetOrRaise(990), Utc::now()).unwrap();
+        let messages = state.handle_player_action(p_ids[2], PlayerAction::Call, Utc::now()).unwrap();
+        assert!(messages.iter().any(|m| matches!(m, ServerMessage::AllInHandsRevealed { .. })));
+        assert_eq!(state.guess_winner(spectator, p_ids[1]).unwrap_err(), GameError::GuessingClosed);
+    }
+
+    #[test]
+    fn test_big_blind_ante_is_dead_money() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::Ante(20));
+        state.room_options.apply(RoomOption::BigBlindAnte(true));
+        let mut client = state.clone();
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        let antes: Vec<_> = messages.iter().filter_map(|m| match m {
+            ServerMessage::AntePosted { player_id, amount, .. } => Some((*player_id, *amount)),
+            _ => None,
+        }).collect();
+        // 只有大盲交前注，且在大盲注之后
+        assert_eq!(antes, vec![(p_ids[2], 20)]);
+        assert!(matches!(messages.iter().rev().find(|m| matches!(m, ServerMessage::AntePosted { .. } | ServerMessage::PlayerActed { .. })),
+            Some(ServerMessage::AntePosted { .. })));
+        assert_eq!((state.pot, state.dead_money), (50, 20));
+        assert_eq!(state.bets, vec![0, 10, 20]);
+        assert_eq!(state.players[&p_ids[2]].stack, 960);
+        for m in messages.iter() {
+            client.apply_event(m, None);
+        }
+        assert_eq!((client.pot, client.dead_money, client.dead_bets.clone()), (state.pot, state.dead_money, state.dead_bets.clone()));
+    }
+
+    #[test]
+    fn test_short_big_blind_ante_goes_to_main_pot() {
+        // 大盲只有 30: 先交大盲 20，剩下的 10 交前注后全下
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 30]);
+        state.room_options.apply(RoomOption::Ante(20));
+        state.room_options.apply(RoomOption::BigBlindAnte(true));
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.bets[2], 20);
+        assert_eq!((state.pot, state.dead_money), (40, 10));
+        assert_eq!(state.players[&p_ids[2]].state, PlayerState::AllIn);
+
+        // P0 的牌最大。发完的公共牌与顺序无关
+        let card = |rank, suit| Some(Card::new(rank, suit));
+        state.player_cards = vec![
+            vec![card(Rank::Ace, Suit::Spade), card(Rank::Ace, Suit::Heart)],
+            vec![card(Rank::Four, Suit::Club), card(Rank::Five, Suit::Club)],
+            vec![card(Rank::King, Suit::Spade), card(Rank::Queen, Suit::Heart)],
+        ];
+        state.deck = vec![
+            Card::new(Rank::Two, Suit::Club),
+            Card::new(Rank::Seven, Suit::Diamond),
+            Card::new(Rank::Nine, Suit::Heart),
+            Card::new(Rank::Jack, Suit::Spade),
+            Card::new(Rank::Three, Suit::Spade),
+        ];
+        state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(200), Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        assert_eq!(state.phase, GamePhase::Showdown);
+
+        // 前注并入主池由 P0 赢走，大盲不会拿回只有自己投入的那部分
+        assert_eq!(state.players[&p_ids[0]].stack, 1000 - 20 + 40 + 20);
+        assert_eq!(state.players[&p_ids[2]].stack, 0);
+        assert_eq!(state.players[&p_ids[1]].stack, 990);
+    }
+
+    #[test]
+    fn test_missed_blinds_waits_for_big_blind() {
+        // 场景: 错过盲注但未补交死盲的玩家不发牌，直到轮到大盲位
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000, 1000]);
+        state.players.get_mut(&p_ids[3]).unwrap().missed_blinds = MissedBlinds::Big;
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.hand_player_order, vec![p_ids[0], p_ids[1], p_ids[2]]);
+        assert_eq!(state.players.get(&p_ids[3]).unwrap().missed_blinds, MissedBlinds::Big);
+
+        // 正好处于大盲位时正常入局，并清除错过盲注的标记
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000, 1000]);
+        state.players.get_mut(&p_ids[2]).unwrap().missed_blinds = MissedBlinds::Big;
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.hand_player_order.len(), 4);
+        assert!(!messages.iter().any(|m| matches!(m, ServerMessage::DeadBlindPosted { .. })));
+        assert_eq!(state.players.get(&p_ids[2]).unwrap().missed_blinds, MissedBlinds::None);
+    }
+
+    #[test]
+    fn test_request_dead_blind_without_missed_blinds() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        assert_eq!(state.request_dead_blind(p_ids[0]).unwrap_err(), GameError::NoMissedBlinds);
+    }
+
+    #[test]
+    fn test_out_of_phase_input_returns_error() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1]);
+
+        // 牌局开始前
+        assert_eq!(state.handle_player_action(p_ids[0], PlayerAction::Call, Utc::now()).unwrap_err(), GameError::NoHandInProgress);
+        assert_eq!(state.handle_discard(p_ids[0], vec![0], Utc::now()).unwrap_err(), GameError::NotDrawRound);
+
+        // 牌局进行中不能重新开局
+        state.start_new_hand(Utc::now()).unwrap();
+        let pot = state.pot;
+        assert_eq!(state.start_new_hand(Utc::now()).unwrap_err(), GameError::HandInProgress);
+        assert_eq!(state.pot, pot);
+
+        // 不在本局中的玩家
+        assert_eq!(state.handle_player_action(Uuid::new_v4(), PlayerAction::Fold, Utc::now()).unwrap_err(), GameError::NotYourTurn);
+
+        // 人数不足无法开局后，上一局的行动顺序不能再被使用
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        state.start_new_hand(Utc::now()).unwrap();
+        state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap();
+        state.players.get_mut(&p_ids[0]).unwrap().is_offline = true;
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.phase, GamePhase::WaitingForPlayers);
+        assert_eq!(state.handle_player_action(p_ids[1], PlayerAction::Check, Utc::now()).unwrap_err(), GameError::NoHandInProgress);
+    }
+
+    #[test]
+    fn test_preflop_skips_blind_that_is_all_in() {
+        // 庄家 (小盲) 只有 10，下完小盲就全下，不能再轮到他行动
+        let (mut state, p_ids) = setup_test_game(&[10, 1000]);
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert!(!messages.iter().any(|m| matches!(m, ServerMessage::NextToAct { player_id, .. } if *player_id == p_ids[0])));
+        assert_ne!(state.current_player_id(), Some(p_ids[0]));
+        assert_eq!(crate::testing::total_chips(&state), 1010);
+    }
+
+    #[test]
+    fn test_no_raise_offered_when_call_is_all_in() {
+        let (mut state, p_ids) = setup_test_game(&[15, 1000, 1000]);
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        let valid_actions = messages.iter().find_map(|m| match m {
+            ServerMessage::NextToAct { player_id, valid_actions, .. } if *player_id == p_ids[0] => Some(valid_actions.clone()),
+            _ => None,
+        }).unwrap();
+        // 跟注 20 就超过了 15 的筹码，只能跟注全下或弃牌
+        assert!(!valid_actions.iter().any(|a| matches!(a, PlayerActionType::Raise { .. } | PlayerActionType::Bet { .. })));
+    }
+
+    #[test]
+    fn test_uncalled_bet_counts_folded_bets() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 300]);
+        state.start_new_hand(Utc::now()).unwrap();
+        state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(600), Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Call, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[2], PlayerAction::Call, Utc::now()).unwrap();
+        // 翻牌圈小盲全下，庄家弃牌: 未被跟注的只有超过庄家 600 的部分
+        state.handle_player_action(p_ids[1], PlayerAction::BetOrRaise(400), Utc::now()).unwrap();
+        let messages = state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap();
+        assert!(messages.iter().any(|m| matches!(m,
+            ServerMessage::BetReturned { player_id, amount: 400, .. } if *player_id == p_ids[1])));
+        assert!(!state.hand_in_progress());
+        assert_eq!(crate::testing::total_chips(&state), 2300);
+        // 小盲至少拿回未跟注的 400，再赢下只有他能争夺的边池 (600 - 300) * 2
+        assert!(state.players[&p_ids[1]].stack >= 1000);
+    }
+
+    #[test]
+    fn test_inconsistent_state_returns_error() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        assert_eq!(state.deposit_chips(Uuid::new_v4(), 100).unwrap_err(), GameError::PlayerNotFound);
+        // 测试牌桌上的玩家没有座位号
+        assert_eq!(state.find_insertion_index(3).unwrap_err(), GameError::NotSeated);
+
+        // 牌堆不够发公共牌时返回错误而不是 panic
+        state.start_new_hand(Utc::now()).unwrap();
+        state.deck.clear();
+        state.handle_player_action(p_ids[0], PlayerAction::Call, Utc::now()).unwrap();
+        assert_eq!(state.handle_player_action(p_ids[1], PlayerAction::Check, Utc::now()).unwrap_err(), GameError::DeckExhausted);
+    }
+
+    #[test]
+    fn test_auto_muck_hides_losing_hand() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        let p0_id = p_ids[0];
+        let p1_id = p_ids[1];
+        state.set_auto_muck(p1_id, true).unwrap();
+        state.start_new_hand(Utc::now()).unwrap();
+        state.handle_player_action(p0_id, PlayerAction::Call, Utc::now()).unwrap();
+        state.handle_player_action(p1_id, PlayerAction::Check, Utc::now()).unwrap();
+
+        state.community_cards = vec![vec![
+            Some(Card::new(Rank::Ace, Suit::Spade)),
+            Some(Card::new(Rank::King, Suit::Spade)),
+            Some(Card::new(Rank::Queen, Suit::Spade)),
+            Some(Card::new(Rank::Two, Suit::Heart)),
+            Some(Card::new(Rank::Three, Suit::Heart)),
+        ]];
+        // p0: 同花顺, p1: 三条A 且开启了自动盖牌
+        state.player_cards[0] = vec![
+            Some(Card::new(Rank::Jack, Suit::Spade)),
+            Some(Card::new(Rank::Ten, Suit::Spade)),
+        ];
+        state.player_cards[1] = vec![
+            Some(Card::new(Rank::Ace, Suit::Club)),
+            Some(Card::new(Rank::Ace, Suit::Diamond)),
+        ];
+        // p0 在河牌下注，先亮牌；p1 的牌被压过，可以盖牌
+        state.last_aggressor = Some(p0_id);
+
+        let messages = state.handle_showdown().unwrap();
+        state.phase = GamePhase::Showdown;
+        let Some(ServerMessage::Showdown { results, .. }) = messages.last() else {
+            panic!("Expected a Showdown message");
+        };
+        let r0 = results.iter().find(|r| r.player_id == p0_id).unwrap();
+        let r1 = results.iter().find(|r| r.player_id == p1_id).unwrap();
+        assert!(r0.cards.is_some());
+        assert!(r1.cards.is_none() && r1.hand_rank.is_none());
+
+        // 盖掉的底牌对其他玩家不可见，但自己仍然可见
+        assert_eq!(state.for_client(Audience::Player(p0_id)).player_cards[1], vec![None, None]);
+        assert!(state.for_client(Audience::Player(p1_id)).player_cards[1][0].is_some());
+
+        // 旁观者只能看到亮出的底牌，也看不到玩家的自动盖牌设置；其他玩家同样看不到，只有自己能看到
+        let spectator = state.for_client(Audience::Spectator);
+        assert!(spectator.player_cards[0][0].is_some());
+        assert_eq!(spectator.player_cards[1], vec![None, None]);
+        assert!(!spectator.players[&p1_id].auto_muck);
+        assert!(!state.for_client(Audience::Player(p0_id)).players[&p1_id].auto_muck);
+        assert!(state.for_client(Audience::Player(p1_id)).players[&p1_id].auto_muck);
+
+        // 设置变化的广播也一样: 公开版本去掉了私人设置
+        let update = ServerMessage::PlayerUpdated { player: state.players[&p1_id].clone() };
+        let Some((owner, ServerMessage::PlayerUpdated { player })) = update.public_view() else { panic!("应当有公开版本") };
+        assert_eq!(owner, p1_id);
+        assert!(!player.auto_muck);
+        assert!(ServerMessage::Info { message: String::new() }.public_view().is_none());
+    }
+
+    #[test]
+    fn test_showdown_reveal_order_and_mucking() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.start_new_hand(Utc::now()).unwrap();
+        state.handle_player_action(p_ids[0], PlayerAction::Call, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Call, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[2], PlayerAction::Check, Utc::now()).unwrap();
+
+        state.community_cards = vec![vec![
+            Some(Card::new(Rank::Ace, Suit::Spade)),
+            Some(Card::new(Rank::King, Suit::Spade)),
+            Some(Card::new(Rank::Queen, Suit::Spade)),
+            Some(Card::new(Rank::Two, Suit::Heart)),
+            Some(Card::new(Rank::Three, Suit::Heart)),
+        ]];
+        // p1 最后下注先亮出一对K，p2 的一对Q被压过可以选择，p0 的同花顺赢下奖池必须亮牌
+        state.player_cards[0] = vec![Some(Card::new(Rank::Jack, Suit::Spade)), Some(Card::new(Rank::Ten, Suit::Spade))];
+        state.player_cards[1] = vec![Some(Card::new(Rank::King, Suit::Diamond)), Some(Card::new(Rank::Nine, Suit::Club))];
+        state.player_cards[2] = vec![Some(Card::new(Rank::Queen, Suit::Diamond)), Some(Card::new(Rank::Eight, Suit::Club))];
+        state.last_aggressor = Some(p_ids[1]);
+
+        let messages = state.handle_showdown().unwrap();
+        state.phase = GamePhase::Showdown;
+        let Some(ServerMessage::Showdown { results, to_decide, .. }) = messages.last() else {
+            panic!("Expected a Showdown message");
+        };
+        let order: Vec<PlayerId> = results.iter().map(|r| r.player_id).collect();
+        assert_eq!(order, vec![p_ids[1], p_ids[2], p_ids[0]]);
+        let shown: Vec<bool> = results.iter().map(|r| r.cards.is_some()).collect();
+        assert_eq!(shown, vec![true, false, true]);
+        assert_eq!(to_decide, &vec![p_ids[2]]);
+
+        // 还没决定的底牌对其他人不可见，只有排在队首的玩家可以选择
+        assert_eq!(state.for_client(Audience::Spectator).player_cards[2], vec![None, None]);
+        assert!(state.for_client(Audience::Spectator).player_cards[1][0].is_some());
+        assert_eq!(state.show_or_muck(p_ids[0], true).unwrap_err(), GameError::NotYourShowdownTurn);
+
+        let mut spectator = state.for_client(Audience::Spectator);
+        let msgs = state.show_or_muck(p_ids[2], true).unwrap();
+        assert!(matches!(&msgs[0], ServerMessage::HandShownOrMucked { player_id, cards: Some(cards), .. }
+            if *player_id == p_ids[2] && cards.len() == 2));
+        spectator.apply_event(&msgs[0], None);
+        assert!(spectator.player_cards[2][0].is_some() && spectator.showdown_queue.is_empty());
+        assert!(state.for_client(Audience::Spectator).player_cards[2][0].is_some());
+        assert_eq!(state.show_or_muck(p_ids[2], false).unwrap_err(), GameError::NotYourShowdownTurn);
+    }
+
+    #[test]
+    fn test_show_cards_after_uncontested_win() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.start_new_hand(Utc::now()).unwrap();
+        state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        let messages = state.handle_player_action(p_ids[2], PlayerAction::Fold, Utc::now()).unwrap();
+        // 没有人跟注时赢家的底牌默认不亮
+        let Some(ServerMessage::Showdown { results, .. }) = messages.last() else { panic!() };
+        assert!(results[0].cards.is_none() && results[0].hand_rank.is_none());
+
+        assert_eq!(state.show_cards(p_ids[1], vec![0]).unwrap_err(), GameError::NotUncontestedWinner);
+        assert_eq!(state.show_cards(p_ids[0], vec![2]).unwrap_err(), GameError::InvalidCardPosition);
+
+        // 只亮第二张: 其他玩家和旁观者只能看到这一张
+        let idx = state.player_indices[&p_ids[0]];
+        let hole = state.player_cards[idx].clone();
+        let msgs = state.show_cards(p_ids[0], vec![1]).unwrap();
+        assert!(matches!(&msgs[0], ServerMessage::CardsShown { player_id, cards } if *player_id == p_ids[0] && *cards == vec![None, hole[1]]));
+        assert_eq!(state.for_client(Audience::Player(p_ids[1])).player_cards[idx], vec![None, hole[1]]);
+        assert_eq!(state.for_client(Audience::Spectator).player_cards[idx], vec![None, hole[1]]);
+        assert!(!state.is_shown(idx));
+
+        let mut client = state.for_client(Audience::Player(p_ids[2]));
+        let msgs = state.show_cards(p_ids[0], vec![0]).unwrap();
+        client.apply_event(&msgs[0], Some(p_ids[2]));
+        assert!(state.is_shown(idx));
+        assert_eq!(client.player_cards[idx], hole);
+        assert!(client.is_shown(idx));
+    }
+
+    #[test]
+    fn test_reveal_folded_hand() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.start_new_hand(Utc::now()).unwrap();
+        state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        assert_eq!(state.phase, GamePhase::Showdown);
+
+        // 房主未开启选项时不能亮牌
+        assert_eq!(state.reveal_folded_hand(p_ids[0]).unwrap_err(), GameError::RevealDisabled);
+
+        state.room_options.apply(RoomOption::RevealFoldedHands(true));
+        let msgs = state.reveal_folded_hand(p_ids[0]).unwrap();
+        let expected: Vec<Card> = state.player_cards[0].iter().flatten().cloned().collect();
+        assert!(matches!(&msgs[0], ServerMessage::FoldedHandRevealed { player_id, cards }
+            if *player_id == p_ids[0] && *cards == expected));
+
+        // 没有弃牌的玩家不能使用该功能
+        assert_eq!(state.reveal_folded_hand(p_ids[2]).unwrap_err(), GameError::NotFolded);
+    }
+
+    #[test]
+    fn test_bomb_pot_skips_preflop() {
+        let (mut state, _p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::BombPot(50));
+        state.room_options.apply(RoomOption::BombPotEvery(1));
+        state.room_options.apply(RoomOption::DoubleBoard(true));
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+
+        assert_eq!(state.pot, 150);
+        assert_eq!(state.phase, GamePhase::Flop);
+        assert_eq!(state.community_cards.len(), 2);
+        for board in &state.community_cards {
+            assert_eq!(board.iter().flatten().count(), 3);
+        }
+        // 两块公共牌的翻牌各发一条消息
+        let flops = messages.iter()
+            .filter(|m| matches!(m, ServerMessage::CommunityCardsDealt { phase: GamePhase::Flop, .. }))
+            .count();
+        assert_eq!(flops, 2);
+        assert!(matches!(messages.last(), Some(ServerMessage::NextToAct { .. })));
+    }
+
+    #[test]
+    fn test_bomb_pot_posts_antes_and_settles_missed_blinds() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 30]);
+        state.room_options.apply(RoomOption::BombPot(50));
+        state.room_options.apply(RoomOption::BombPotEvery(1));
+        {
+            let p = state.players.get_mut(&p_ids[1]).unwrap();
+            p.missed_blinds = MissedBlinds::Big;
+            p.post_dead_blind = true;
+        }
+        let mut client = state.clone();
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+
+        // 底注用专门的消息，不会被当成玩家主动的下注
+        assert!(!messages.iter().any(|m| matches!(m, ServerMessage::PlayerActed { .. })));
+        let mut antes: Vec<_> = messages.iter().filter_map(|m| match m {
+            ServerMessage::BombPotPosted { player_id, amount, .. } => Some((*player_id, *amount)),
+            _ => None,
+        }).collect();
+        antes.sort_by_key(|(_, amount)| *amount);
+        assert_eq!(antes.len(), 3);
+        assert_eq!(antes[0], (p_ids[2], 30));
+        assert_eq!(state.players[&p_ids[2]].state, PlayerState::AllIn);
+
+        // 底注代替了盲注，错过的盲注不会留到下一局再补
+        let p = &state.players[&p_ids[1]];
+        assert!(p.missed_blinds == MissedBlinds::None && !p.post_dead_blind);
+
+        for m in messages.iter() {
+            client.apply_event(m, None);
+        }
+        assert_eq!((client.pot, client.max_bet), (state.pot, state.max_bet));
+        assert_eq!(client.players[&p_ids[1]].missed_blinds, MissedBlinds::None);
+        assert!(p_ids.iter().all(|id| state.players[id].stack == client.players[id].stack));
+    }
+
+    #[test]
+    fn test_queued_bomb_pot_plays_once() {
+        let (mut state, _p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::DoubleBoard(true));
+        assert!(matches!(state.queue_bomb_pot(), Err(GameError::BombPotAnteNotSet)));
+
+        // 只设置底注不会让每局都变成炸弹底池，双公共牌也只用于炸弹底池
+        state.room_options.apply(RoomOption::BombPot(50));
+        let mut client = state.clone();
+        let mut run = |state: &mut GameState, messages: Vec<ServerMessage>| {
+            messages.iter().for_each(|m| client.apply_event(m, None));
+            assert_eq!(client.bomb_pot, state.bomb_pot);
+            (state.bomb_pot, state.community_cards.len())
+        };
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(run(&mut state, messages), (false, 1));
+        assert_eq!(state.pot, 30);
+
+        // 房主在牌局中安排的炸弹底池从下一局开始，只打一次
+        let messages = state.queue_bomb_pot().unwrap();
+        assert!(matches!(messages[..], [ServerMessage::BombPotQueued]));
+        run(&mut state, messages);
+        let messages = state.void_hand().unwrap();
+        run(&mut state, messages);
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(run(&mut state, messages), (true, 2));
+        assert_eq!(state.pot, 150);
+        assert!(!state.bomb_pot_queued);
+
+        let messages = state.void_hand().unwrap();
+        run(&mut state, messages);
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(run(&mut state, messages), (false, 1));
+    }
+
+    #[test]
+    fn test_bomb_pot_every_n_hands() {
+        let (mut state, _p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::BombPot(50));
+        state.room_options.apply(RoomOption::BombPotEvery(3));
+        let mut bomb_pots = vec![];
+        for _ in 0..6 {
+            state.start_new_hand(Utc::now()).unwrap();
+            bomb_pots.push(state.bomb_pot);
+            state.void_hand().unwrap();
+        }
+        assert_eq!(bomb_pots, vec![false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_double_board_splits_pot() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        state.room_options.apply(RoomOption::BombPot(100));
+        state.room_options.apply(RoomOption::BombPotEvery(1));
+        state.room_options.apply(RoomOption::DoubleBoard(true));
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.pot, 200);
+
+        state.community_cards = vec![
+            vec![
+                Some(Card::new(Rank::Ace, Suit::Spade)),
+                Some(Card::new(Rank::King, Suit::Spade)),
+                Some(Card::new(Rank::Queen, Suit::Spade)),
+                Some(Card::new(Rank::Two, Suit::Heart)),
+                Some(Card::new(Rank::Three, Suit::Heart)),
+            ],
+            vec![
+                Some(Card::new(Rank::Nine, Suit::Club)),
+                Some(Card::new(Rank::Nine, Suit::Heart)),
+                Some(Card::new(Rank::Four, Suit::Diamond)),
+                Some(Card::new(Rank::Five, Suit::Club)),
+                Some(Card::new(Rank::Seven, Suit::Heart)),
+            ],
+        ];
+        // p0 在第一块公共牌上是同花顺，p1 在第二块公共牌上是四条9
+        state.player_cards[0] = vec![
+            Some(Card::new(Rank::Jack, Suit::Spade)),
+            Some(Card::new(Rank::Ten, Suit::Spade)),
+        ];
+        state.player_cards[1] = vec![
+            Some(Card::new(Rank::Nine, Suit::Spade)),
+            Some(Card::new(Rank::Nine, Suit::Diamond)),
+        ];
+
+        let messages = state.handle_showdown().unwrap();
+        assert_eq!(state.players.get(&p_ids[0]).unwrap().stack, 900 + 100);
+        assert_eq!(state.players.get(&p_ids[1]).unwrap().stack, 900 + 100);
+        let Some(ServerMessage::Showdown { results, .. }) = messages.last() else {
+            panic!("Expected a Showdown message");
+        };
+        assert!(results.iter().all(|r| r.board_ranks.len() == 2));
+    }
+
+    #[test]
+    fn test_omaha_hi_lo_quartered_pot() {
+        // 场景: p2 赢得高牌半池，p0 和 p1 拿着相同的低牌平分低牌半池 (各得四分之一)
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::Variant(GameVariant::OmahaHiLo));
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.player_cards[0].len(), 4);
+
+        state.bets = vec![100, 100, 100];
+        state.pot = 300;
+        state.community_cards = vec![vec![
+            Some(Card::new(Rank::Three, Suit::Spade)),
+            Some(Card::new(Rank::Five, Suit::Diamond)),
+            Some(Card::new(Rank::Eight, Suit::Club)),
+            Some(Card::new(Rank::King, Suit::Heart)),
+            Some(Card::new(Rank::King, Suit::Spade)),
+        ]];
+        state.player_cards[0] = vec![
+            Some(Card::new(Rank::Ace, Suit::Heart)),
+            Some(Card::new(Rank::Two, Suit::Heart)),
+            Some(Card::new(Rank::Nine, Suit::Club)),
+            Some(Card::new(Rank::Ten, Suit::Diamond)),
+        ];
+        state.player_cards[1] = vec![
+            Some(Card::new(Rank::Ace, Suit::Diamond)),
+            Some(Card::new(Rank::Two, Suit::Club)),
+            Some(Card::new(Rank::Jack, Suit::Club)),
+            Some(Card::new(Rank::Queen, Suit::Diamond)),
+        ];
+        state.player_cards[2] = vec![
+            Some(Card::new(Rank::King, Suit::Diamond)),
+            Some(Card::new(Rank::Queen, Suit::Heart)),
+            Some(Card::new(Rank::Jack, Suit::Heart)),
+            Some(Card::new(Rank::Nine, Suit::Diamond)),
+        ];
+
+        let messages = state.handle_showdown().unwrap();
+        let Some(ServerMessage::Showdown { results, .. }) = messages.last() else {
+            panic!("Expected a Showdown message");
+        };
+        let result = |id: PlayerId| results.iter().find(|r| r.player_id == id).unwrap();
+        assert_eq!(result(p_ids[2]).winnings, 150);
+        assert_eq!(result(p_ids[2]).low_winnings, 0);
+        assert_eq!(result(p_ids[0]).winnings, 75);
+        assert_eq!(result(p_ids[0]).low_winnings, 75);
+        assert_eq!(result(p_ids[1]).low_winnings, 75);
+        assert_eq!(result(p_ids[0]).low_hand, Some(LowHand([8, 5, 3, 2, 1])));
+    }
+
+    #[test]
+    fn test_omaha_hi_lo_no_low_high_scoops() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        state.room_options.apply(RoomOption::Variant(GameVariant::OmahaHiLo));
+        state.start_new_hand(Utc::now()).unwrap();
+
+        state.bets = vec![100, 100];
+        state.pot = 200;
+        // 公共牌只有两张小牌，没有合格的低牌
+        state.community_cards = vec![vec![
+            Some(Card::new(Rank::Three, Suit::Spade)),
+            Some(Card::new(Rank::Five, Suit::Diamond)),
+            Some(Card::new(Rank::Jack, Suit::Club)),
+            Some(Card::new(Rank::King, Suit::Heart)),
+            Some(Card::new(Rank::King, Suit::Spade)),
+        ]];
+        state.player_cards[0] = vec![
+            Some(Card::new(Rank::Ace, Suit::Heart)),
+            Some(Card::new(Rank::Two, Suit::Heart)),
+            Some(Card::new(Rank::Nine, Suit::Club)),
+            Some(Card::new(Rank::Ten, Suit::Diamond)),
+        ];
+        state.player_cards[1] = vec![
+            Some(Card::new(Rank::King, Suit::Diamond)),
+            Some(Card::new(Rank::Queen, Suit::Heart)),
+            Some(Card::new(Rank::Jack, Suit::Heart)),
+            Some(Card::new(Rank::Nine, Suit::Diamond)),
+        ];
+
+        state.handle_showdown().unwrap();
+        assert_eq!(state.players.get(&p_ids[1]).unwrap().stack, 1000 - 20 + 200);
+    }
+
+    #[test]
+    fn test_five_card_draw_flow() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::Variant(GameVariant::FiveCardDraw));
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.player_cards[0].len(), 5);
+        assert!(state.community_cards[0].is_empty());
+
+        // 第一轮下注
+        state.handle_player_action(p_ids[0], PlayerAction::Call, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Call, Utc::now()).unwrap();
+        let messages = state.handle_player_action(p_ids[2], PlayerAction::Check, Utc::now()).unwrap();
+        assert_eq!(state.phase, GamePhase::Draw);
+        assert!(matches!(messages.last(), Some(ServerMessage::NextToAct { player_id, valid_actions, .. })
+            if *player_id == p_ids[1] && valid_actions == &vec![PlayerActionType::Discard(5)]));
+
+        // 换牌阶段不能下注，序号无效时报错
+        assert_eq!(state.handle_player_action(p_ids[1], PlayerAction::Check, Utc::now()).unwrap_err(), GameError::DrawRoundPending);
+        assert_eq!(state.handle_discard(p_ids[1], vec![5], Utc::now()).unwrap_err(), GameError::InvalidDiscard { hole_card_count: 5 });
+
+        let kept = state.player_cards[1][2..].to_vec();
+        let msgs = state.handle_discard(p_ids[1], vec![0, 1], Utc::now()).unwrap();
+        assert!(matches!(msgs[0], ServerMessage::CardsDrawn { count: 2, .. }));
+        assert_eq!(state.player_cards[1][2..], kept[..]);
+        state.handle_discard(p_ids[2], vec![], Utc::now()).unwrap();
+        let messages = state.handle_discard(p_ids[0], vec![4], Utc::now()).unwrap();
+
+        // 所有人换完牌后进入第二轮下注
+        assert_eq!(state.phase, GamePhase::AfterDraw);
+        assert!(matches!(messages.last(), Some(ServerMessage::NextToAct { player_id, .. }) if *player_id == p_ids[1]));
+        state.handle_player_action(p_ids[1], PlayerAction::Check, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[2], PlayerAction::Check, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[0], PlayerAction::Check, Utc::now()).unwrap();
+        assert_eq!(state.phase, GamePhase::Showdown);
+        let total: u32 = p_ids.iter().map(|id| state.players[id].stack).sum();
+        assert_eq!(total, 3000);
+    }
+
+    #[test]
+    fn test_next_to_act_context() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 500, 300]);
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        let Some(ServerMessage::NextToAct { context, .. }) = messages.last() else { panic!() };
+        assert_eq!(context, &TurnContext {
+            pot: 30,
+            to_call: 20,
+            raise_range: Some((40, 1000)),
+            stack: 1000,
+            effective_stacks: vec![(p_ids[1], 500), (p_ids[2], 300)],
+            deadline: None,
+            players_to_act: 2,
+        });
+        // 枪口位的有效筹码是对手中最多的 490 (小盲)，大盲已经投入了 20/300
+        assert_eq!(state.stack_to_pot(&p_ids[0]), Some(490.0 / 30.0));
+        assert_eq!(state.pot_commitment(&p_ids[2]), Some(20.0 / 300.0));
+
+        // 小盲面对加注: 有效筹码按双方剩余筹码加本轮下注计算
+        let messages = state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap();
+        let Some(ServerMessage::NextToAct { player_id, context, .. }) = messages.last() else { panic!() };
+        assert_eq!(*player_id, p_ids[1]);
+        assert_eq!((context.pot, context.to_call, context.stack), (90, 50, 490));
+        assert_eq!(context.raise_range, Some((90, 490)));
+        assert_eq!(context.effective_stacks, vec![(p_ids[0], 500), (p_ids[2], 300)]);
+        assert_eq!(context.players_to_act, 1);
+    }
+
+    #[test]
+    fn test_current_turn_matches_next_to_act() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        let assert_current_turn = |state: &GameState, messages: &[ServerMessage]| {
+            let Some(ServerMessage::NextToAct { player_id, valid_actions, context }) = messages.last() else { panic!() };
+            let Some(ServerMessage::NextToAct { player_id: id, valid_actions: actions, context: ctx }) = state.current_turn() else { panic!() };
+            assert_eq!((id, &actions, &ctx), (*player_id, valid_actions, context));
+        };
+
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_current_turn(&state, &messages);
+        let messages = state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap();
+        assert_current_turn(&state, &messages);
+        state.handle_player_action(p_ids[1], PlayerAction::Call, Utc::now()).unwrap();
+        let messages = state.handle_player_action(p_ids[2], PlayerAction::Call, Utc::now()).unwrap();
+        assert_eq!(state.phase, GamePhase::Flop);
+        assert_current_turn(&state, &messages);
+
+        state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[2], PlayerAction::Fold, Utc::now()).unwrap();
+        assert!(state.current_turn().is_none());
+    }
+
+    #[test]
+    fn test_leave_seat_between_hands() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.leave_seat(p_ids[0], Utc::now()).unwrap_err(), GameError::LeaveSeatDuringHand);
+
+        state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        assert_eq!(state.phase, GamePhase::Showdown);
+        let stack = state.players[&p_ids[0]].stack;
+        let mut client = state.for_client(Audience::Player(p_ids[1]));
+        let messages = state.leave_seat(p_ids[0], Utc::now()).unwrap();
+        assert!(!state.seated_players.contains(&p_ids[0]));
+        let player = &state.players[&p_ids[0]];
+        assert_eq!(player.state, PlayerState::SittingOut);
+        assert_eq!((player.seat_id, player.stack), (None, stack));
+        assert_eq!(state.leave_seat(p_ids[0], Utc::now()).unwrap_err(), GameError::NotSeated);
+
+        // 客户端应用 PlayerUpdated 后同样把玩家移出座位
+        client.apply_event(&messages[0], None);
+        assert_eq!(client.seated_players, state.seated_players);
+
+        // 离座的玩家不再参与下一局
+        state.start_new_hand(Utc::now()).unwrap();
+        assert!(!state.hand_player_order.contains(&p_ids[0]));
+    }
+
+    #[test]
+    fn test_auto_fold_trash_facing_raise() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.set_auto_fold(p_ids[1], 30).unwrap();
+        state.set_auto_fold(p_ids[2], 30).unwrap();
+        state.start_new_hand(Utc::now()).unwrap();
+        state.player_cards[1] = vec![Some("7c".parse().unwrap()), Some("2d".parse().unwrap())];
+        state.player_cards[2] = vec![Some("As".parse().unwrap()), Some("Ah".parse().unwrap())];
+
+        // 没有人加注时不会自动弃牌
+        assert!(!state.tick(Utc::now()).unwrap().0);
+        state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap();
+        let (acted, messages) = state.tick(Utc::now()).unwrap();
+        assert!(acted);
+        assert!(matches!(messages[0], ServerMessage::PlayerActed { player_id, action: PlayerAction::Fold, .. } if player_id == p_ids[1]));
+        // 强牌不受影响
+        assert!(!state.tick(Utc::now()).unwrap().0);
+
+        // 房主禁止后不能再开启，已有的设置也不再生效
+        state.room_options.apply(RoomOption::AutoFoldDisabled(true));
+        assert_eq!(state.set_auto_fold(p_ids[0], 30).unwrap_err(), GameError::AutoFoldDisabled);
+        state.player_cards[2] = state.player_cards[1].clone();
+        assert!(!state.tick(Utc::now()).unwrap().0);
+        state.room_options.apply(RoomOption::AutoFoldDisabled(false));
+        assert_eq!(state.set_auto_fold(p_ids[0], 101).unwrap_err(), GameError::InvalidAutoFold);
+    }
+
+    #[test]
+    fn test_time_out_checks_or_folds() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.time_out(p_ids[1], Utc::now()).unwrap_err(), GameError::NotYourTurn);
+
+        // 面对大盲注时超时弃牌
+        let messages = state.time_out(p_ids[0], Utc::now()).unwrap();
+        assert!(matches!(messages[0], ServerMessage::PlayerActed { player_id, action: PlayerAction::Fold, .. } if player_id == p_ids[0]));
+        state.handle_player_action(p_ids[1], PlayerAction::Call, Utc::now()).unwrap();
+        // 不需要跟注时超时过牌
+        let messages = state.time_out(p_ids[2], Utc::now()).unwrap();
+        assert!(matches!(messages[0], ServerMessage::PlayerActed { player_id, action: PlayerAction::Check, .. } if player_id == p_ids[2]));
+        assert_eq!(state.phase, GamePhase::Flop);
+    }
+
+    #[test]
+    fn test_force_fold_out_of_turn() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.current_player_id(), Some(p_ids[0]));
+
+        // 没轮到的玩家被弃牌后，行动权不变
+        let messages = state.force_fold(p_ids[1], Utc::now()).unwrap();
+        assert!(matches!(messages[0], ServerMessage::PlayerActed { player_id, action: PlayerAction::Fold, .. } if player_id == p_ids[1]));
+        assert_eq!(state.players[&p_ids[1]].state, PlayerState::Folded);
+        assert_eq!(state.current_player_id(), Some(p_ids[0]));
+        assert!(state.force_fold(p_ids[1], Utc::now()).unwrap().is_empty());
+
+        // 只剩一位玩家时本局直接结束
+        let pot = state.pot;
+        state.force_fold(p_ids[0], Utc::now()).unwrap();
+        assert!(!state.hand_in_progress());
+        assert_eq!(state.players[&p_ids[2]].stack, 1000 - 20 + pot);
+    }
+
+    #[test]
+    fn test_time_bank_reset_and_charge() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        state.room_options.apply(RoomOption::TimeBank(30));
+        let messages = state.reset_time_banks();
+        assert_eq!(messages.len(), 2);
+        assert!(state.players.values().all(|p| p.time_bank == 30));
+
+        let messages = state.charge_time_bank(p_ids[0], 12).unwrap();
+        assert!(matches!(messages[0], ServerMessage::TimeBankUpdated { remaining: 18, .. }));
+        state.charge_time_bank(p_ids[0], 40).unwrap();
+        assert_eq!(state.players[&p_ids[0]].time_bank, 0);
+        assert_eq!(state.players[&p_ids[1]].time_bank, 30);
+    }
+
+    #[test]
+    fn test_chat_validation() {
+        let (state, p_ids) = setup_test_game(&[1000, 1000]);
+        let messages = state.chat(p_ids[0], "  nice\nhand ", Utc::now()).unwrap();
+        assert!(matches!(&messages[0], ServerMessage::ChatBroadcast { player_id, text, .. } if *player_id == p_ids[0] && text == "nice hand"));
+        assert_eq!(state.chat(p_ids[0], " \t ", Utc::now()).unwrap_err(), GameError::EmptyChat);
+        assert_eq!(state.chat(p_ids[0], &"好".repeat(MAX_CHAT_CHARS + 1), Utc::now()).unwrap_err(), GameError::ChatTooLong { max: MAX_CHAT_CHARS });
+        assert!(state.chat(p_ids[0], &"好".repeat(MAX_CHAT_CHARS), Utc::now()).is_ok());
+        assert_eq!(state.chat(Uuid::new_v4(), "hi", Utc::now()).unwrap_err(), GameError::PlayerNotFound);
+    }
+
+    #[test]
+    fn test_player_profile() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        let profile = PlayerProfile { locale: Locale::English, color: Some([255, 128, 0]), avatar: Some(" 🇨🇳 ".to_string()) };
+        let msgs = state.set_profile(p_ids[0], profile).unwrap();
+        assert!(matches!(&msgs[0], ServerMessage::PlayerUpdated { player } if player.profile.avatar.as_deref() == Some("🇨🇳")));
+        let bad = PlayerProfile { avatar: Some("a b".to_string()), ..Default::default() };
+        assert_eq!(state.set_profile(p_ids[0], bad).unwrap_err(), GameError::InvalidAvatar { max: MAX_AVATAR_CHARS });
+        assert_eq!(state.players[&p_ids[0]].profile.locale, Locale::English);
+
+        // 错误提示按玩家的语言翻译
+        let result = state.handle_player_action(p_ids[1], PlayerAction::Check, Utc::now());
+        assert!(matches!(&result.into_localized_messages(Locale::English)[0], ServerMessage::Error { message } if message.starts_with("There is no hand")));
+    }
+
+    #[test]
+    fn test_rabbit_hunt() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.rabbit_hunt(p_ids[0]).unwrap_err(), GameError::RabbitHuntBeforeShowdown);
+
+        // 翻牌前所有人弃牌，五张公共牌都是兔子牌，而且就是本来会发出的牌
+        let expected: Vec<Card> = state.deck.iter().rev().take(5).cloned().collect();
+        state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        let messages = state.rabbit_hunt(p_ids[0]).unwrap();
+        assert!(matches!(&messages[0], ServerMessage::RabbitHunt { cards, .. } if cards == &vec![expected.clone()]));
+        // 查看不会改变牌堆，第二次看到的还是同样的牌
+        assert!(matches!(&state.rabbit_hunt(p_ids[1]).unwrap()[0], ServerMessage::RabbitHunt { cards, .. } if cards[0] == expected));
+
+        // 翻牌后结束时只有转牌和河牌
+        state.start_new_hand(Utc::now()).unwrap();
+        while state.phase == GamePhase::PreFlop {
+            let player_id = state.current_player_id().unwrap();
+            if state.handle_player_action(player_id, PlayerAction::Call, Utc::now()).is_err() {
+                state.handle_player_action(player_id, PlayerAction::Check, Utc::now()).unwrap();
+            }
+        }
+        let bettor = state.current_player_id().unwrap();
+        state.handle_player_action(bettor, PlayerAction::BetOrRaise(100), Utc::now()).unwrap();
+        while state.phase != GamePhase::Showdown {
+            let player_id = state.current_player_id().unwrap();
+            state.handle_player_action(player_id, PlayerAction::Fold, Utc::now()).unwrap();
+        }
+        assert!(matches!(&state.rabbit_hunt(p_ids[0]).unwrap()[0], ServerMessage::RabbitHunt { cards, .. } if cards[0].len() == 2));
+
+        // 发完河牌的牌局没有兔子牌
+        state.community_cards = vec![state.community_cards[0].iter().map(|_| Some("As".parse().unwrap())).collect()];
+        assert_eq!(state.rabbit_hunt(p_ids[0]).unwrap_err(), GameError::NoRabbitCards);
+    }
+
+    #[test]
+    fn test_dead_button() {
+        let (mut state, p) = setup_test_game(&[1000; 5]);
+        for (i, id) in p.iter().enumerate() {
+            state.players.get_mut(id).unwrap().seat_id = Some(i as u8);
+        }
+        let fold_around = |state: &mut GameState| {
+            while state.hand_in_progress() {
+                let id = state.current_player_id().unwrap();
+                state.handle_player_action(id, PlayerAction::Fold, Utc::now()).unwrap();
+            }
+        };
+        let bet = |state: &GameState, id: &PlayerId| state.bets[state.player_indices[id]];
+
+        // 前两局庄家和盲注正常移动
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.button, Some(ButtonPositions { button: 0, small_blind: 1, big_blind: 2, dead_small_blind: false }));
+        fold_around(&mut state);
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.hand_player_order, vec![p[1], p[2], p[3], p[4], p[0]]);
+        fold_around(&mut state);
+
+        // 上一局的小盲离开: 庄位空着 (死庄)，上一局的大盲交小盲
+        state.leave_seat(p[2], Utc::now()).unwrap();
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.button, Some(ButtonPositions { button: 2, small_blind: 3, big_blind: 4, dead_small_blind: false }));
+        assert_eq!(state.hand_player_order, vec![p[1], p[3], p[4], p[0]]);
+        assert_eq!((bet(&state, &p[3]), bet(&state, &p[4])), (10, 20));
+        assert_eq!(state.dealer_id(), None);
+        let mut client = GameState { button: None, ..state.clone() };
+        client.apply_event(&messages[0], None);
+        assert_eq!(client.button, state.button);
+        fold_around(&mut state);
+
+        // 上一局的大盲离开: 没有小盲 (死小盲)，大盲之后的玩家先行动
+        state.leave_seat(p[4], Utc::now()).unwrap();
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.button, Some(ButtonPositions { button: 3, small_blind: 4, big_blind: 0, dead_small_blind: true }));
+        assert_eq!(state.hand_player_order, vec![p[3], p[0], p[1]]);
+        assert_eq!((bet(&state, &p[3]), bet(&state, &p[0]), bet(&state, &p[1])), (0, 20, 0));
+        assert_eq!(state.current_player_id(), Some(p[1]));
+        assert_eq!(state.dealer_id(), Some(p[3]));
+    }
+
+    #[test]
+    fn test_sit_out_missed_blinds() {
+        let (mut state, p) = setup_test_game(&[1000; 5]);
+        for (i, id) in p.iter().enumerate() {
+            state.players.get_mut(id).unwrap().seat_id = Some(i as u8);
+        }
+        let fold_around = |state: &mut GameState| {
+            while state.hand_in_progress() {
+                let id = state.current_player_id().unwrap();
+                state.handle_player_action(id, PlayerAction::Fold, Utc::now()).unwrap();
+            }
+        };
+        state.start_new_hand(Utc::now()).unwrap();
+        fold_around(&mut state);
+
+        // 上一局的大盲暂时离席: 小盲移到他的座位上 (死小盲)，只错过了小盲
+        state.sit_out(p[2]).unwrap();
+        assert!(!state.seated_players.contains(&p[2]));
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.players[&p[2]].missed_blinds, MissedBlinds::Small);
+        assert!(messages.iter().any(|m| matches!(m, ServerMessage::PlayerUpdated { player } if player.id == p[2])));
+        assert!(!state.hand_player_order.contains(&p[2]));
+        fold_around(&mut state);
+
+        // 回来时选择补交: 同时回到座位，只交一个死的小盲
+        state.request_dead_blind(p[2]).unwrap();
+        assert!(state.players[&p[2]].post_dead_blind);
+        assert!(!state.players[&p[2]].sit_out);
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.hand_player_order[0], p[2]);
+        assert!(messages.iter().any(|m| matches!(m,
+            ServerMessage::DeadBlindPosted { player_id, dead_amount: 10, live_amount: 0, .. } if *player_id == p[2])));
+        assert_eq!(state.players[&p[2]].missed_blinds, MissedBlinds::None);
+
+        // 牌局中申请离席的玩家打完本局，大盲移过他的座位后记为错过大盲
+        state.sit_out(p[0]).unwrap();
+        assert!(state.hand_player_order.contains(&p[0]));
+        fold_around(&mut state);
+        state.start_new_hand(Utc::now()).unwrap();
+        assert!(!state.hand_player_order.contains(&p[0]));
+        assert_eq!(state.players[&p[0]].state, PlayerState::SittingOut);
+        assert_eq!(state.players[&p[0]].missed_blinds, MissedBlinds::Big);
+
+        // 输光的玩家不能直接回到座位
+        state.players.get_mut(&p[0]).unwrap().stack = 0;
+        assert_eq!(state.sit_in(p[0]).unwrap_err(), GameError::SitInWithoutChips);
+        assert_eq!(state.request_dead_blind(p[0]).unwrap_err(), GameError::SitInWithoutChips);
+    }
+
+    #[test]
+    fn test_missed_big_blind_posts_dead_small_and_live_big() {
+        let (mut state, p) = setup_test_game(&[1000; 5]);
+        for (i, id) in p.iter().enumerate() {
+            state.players.get_mut(id).unwrap().seat_id = Some(i as u8);
+        }
+        let play_out = |state: &mut GameState, checker: PlayerId| {
+            while state.hand_in_progress() {
+                let id = state.current_player_id().unwrap();
+                let action = if id == checker { PlayerAction::Check } else { PlayerAction::Fold };
+                state.handle_player_action(id, action, Utc::now()).unwrap();
+            }
+        };
+        state.start_new_hand(Utc::now()).unwrap();
+        play_out(&mut state, Uuid::nil());
+
+        // 下一局的大盲位暂时离席，错过了大盲
+        state.sit_out(p[3]).unwrap();
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.players[&p[3]].missed_blinds, MissedBlinds::Big);
+        play_out(&mut state, Uuid::nil());
+
+        // 回来时补交。正好坐在庄位和盲注之间，先等庄家移过去
+        state.request_dead_blind(p[3]).unwrap();
+        state.start_new_hand(Utc::now()).unwrap();
+        assert!(!state.hand_player_order.contains(&p[3]));
+        play_out(&mut state, Uuid::nil());
+
+        // 死的小盲直接进奖池，活的大盲计入下注额
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert!(messages.iter().any(|m| matches!(m,
+            ServerMessage::DeadBlindPosted { player_id, dead_amount: 10, live_amount: 20, new_stack: 970, new_pot: 60 }
+            if *player_id == p[3])));
+        let idx = state.player_indices[&p[3]];
+        assert_eq!((state.dead_bets[idx], state.bets[idx]), (10, 20));
+
+        // 没有人加注时活的大盲已经够跟注，可以直接过牌，赢下包括死筹码在内的整个奖池
+        play_out(&mut state, p[3]);
+        assert_eq!(state.players[&p[3]].stack, 970 + 60);
+        assert_eq!(crate::testing::total_chips(&state), 5000);
+    }
+
+    #[test]
+    fn test_short_stack_dead_blind_posts_all_in() {
+        let (mut state, p) = setup_test_game(&[1000, 1000, 1000, 25]);
+        let player = state.players.get_mut(&p[3]).unwrap();
+        player.seat_id = Some(3);
+        player.missed_blinds = MissedBlinds::Big;
+        state.request_dead_blind(p[3]).unwrap();
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        // 先交死的小盲，剩下的筹码不够完整的大盲时全部作为活盲注
+        assert!(messages.iter().any(|m| matches!(m,
+            ServerMessage::DeadBlindPosted { player_id, dead_amount: 10, live_amount: 15, new_stack: 0, .. }
+            if *player_id == p[3])));
+        assert_eq!(state.players[&p[3]].state, PlayerState::AllIn);
+    }
+
+    #[test]
+    fn test_seven_deuce_bounty() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::SevenDeuceBounty(50));
+        state.start_new_hand(Utc::now()).unwrap();
+        // 庄家 (枪口位) 拿不同花色的 72，加注后其他人弃牌
+        let seven_deuce: Vec<Card> = ["7c", "2d"].map(|c| c.parse().unwrap()).to_vec();
+        state.player_cards[state.player_indices[&p_ids[0]]] = seven_deuce.iter().cloned().map(Some).collect();
+        let mut client = state.clone();
+
+        let mut messages = state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap();
+        messages.extend(state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap());
+        messages.extend(state.handle_player_action(p_ids[2], PlayerAction::Fold, Utc::now()).unwrap());
+        let Some(ServerMessage::Showdown { results, stacks, .. }) = messages.iter().find(|m| matches!(m, ServerMessage::Showdown { .. })) else {
+            panic!("{:?}", messages)
+        };
+        // 没有人跟注也要亮出 72，结算中的筹码包括奖励
+        assert_eq!(results[0].cards.as_deref(), Some(seven_deuce.as_slice()));
+        assert_eq!(stacks.iter().find(|c| c.player_id == p_ids[0]).map(|c| c.after), Some(1130));
+        let bounty = messages.iter().find_map(|m| match m {
+            ServerMessage::SevenDeuceBounty { winner, payments } => Some((*winner, payments.clone())),
+            _ => None,
+        });
+        assert_eq!(bounty, Some((p_ids[0], vec![(p_ids[1], 50), (p_ids[2], 50)])));
+        assert_eq!(state.players[&p_ids[0]].stack, 1000 - 60 + 90 + 100);
+        assert_eq!(state.players[&p_ids[1]].stack, 1000 - 10 - 50);
+        assert_eq!(state.players[&p_ids[2]].stack, 1000 - 20 - 50);
+
+        // 客户端按事件回放得到同样的筹码
+        for msg in &messages {
+            client.apply_event(msg, None);
+        }
+        for id in &p_ids {
+            assert_eq!(client.players[id].stack, state.players[id].stack);
+        }
+
+        // 同花色的 72 不算
+        state.player_cards[state.player_indices[&p_ids[0]]] = ["7c", "2c"].map(|c| Some(c.parse().unwrap())).to_vec();
+        assert!(!state.holds_seven_deuce(&p_ids[0]));
+    }
+
+    #[test]
+    fn test_all_in_insurance() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 600]);
+        state.room_options.apply(RoomOption::Insurance(true));
+        state.start_new_hand(Utc::now()).unwrap();
+        // 固定底牌: 庄家 (小盲) 拿 AA，大盲拿 72
+        let aces: Vec<Card> = ["As", "Ah"].map(|c| c.parse().unwrap()).to_vec();
+        let trash: Vec<Card> = ["7c", "2d"].map(|c| c.parse().unwrap()).to_vec();
+        // 公共牌 7h 2c 7s Kd 9s (从牌堆末尾发出)，72 拿到葫芦
+        state.deck = ["9s", "Kd", "7s", "2c", "7h"].map(|c| c.parse().unwrap()).to_vec();
+        state.player_cards[state.player_indices[&p_ids[0]]] = aces.iter().cloned().map(Some).collect();
+        state.player_cards[state.player_indices[&p_ids[1]]] = trash.iter().cloned().map(Some).collect();
+        let mut client = state.clone();
+        let total = |state: &GameState| state.players.values().map(|p| p.stack).sum::<u32>();
+
+        let mut messages = state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(990), Utc::now()).unwrap();
+        messages.extend(state.handle_player_action(p_ids[1], PlayerAction::Call, Utc::now()).unwrap());
+        // 亮牌后暂停发牌，等待领先的一方决定是否买保险
+        let Some(ServerMessage::InsuranceOffered(offer)) = messages.last().cloned() else { panic!("{:?}", messages) };
+        assert_eq!((offer.buyer, offer.seller, offer.max_cover), (p_ids[0], p_ids[1], 600));
+        assert!((820..940).contains(&offer.equity_permille), "{}", offer.equity_permille);
+        assert_eq!(state.phase, GamePhase::PreFlop);
+        assert_eq!(state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap_err(), GameError::InsurancePending);
+        assert_eq!(state.buy_insurance(p_ids[1], 100).unwrap_err(), GameError::NoInsuranceOffer);
+        assert_eq!(state.buy_insurance(p_ids[0], 601).unwrap_err(), GameError::InsuranceCoverTooLarge { max: 600 });
+
+        // 买下全部保额后发完公共牌并结算
+        let decided = state.buy_insurance(p_ids[0], 600).unwrap();
+        let premium = offer.premium(600);
+        assert!(matches!(decided[0], ServerMessage::InsuranceDecided { policy: Some(InsurancePolicy { cover: 600, premium: p, .. }), .. } if p == premium));
+        assert_eq!(state.phase, GamePhase::Showdown);
+        let settled = decided.iter().find_map(|m| match m {
+            ServerMessage::InsuranceSettled { from, to, amount } => Some((*from, *to, *amount)),
+            _ => None,
+        });
+        // 买方输掉摊牌，卖方赔付保额，买方拿回未被跟注的 400 和保额，不算输光
+        assert_eq!(settled, Some((p_ids[1], p_ids[0], 600)));
+        assert_eq!(state.players[&p_ids[0]].stack, 1000);
+        assert_eq!(state.players[&p_ids[0]].losses, 0);
+        assert_eq!(state.players[&p_ids[1]].stack, 600);
+        assert_eq!(total(&state), 1600);
+
+        // 客户端按事件回放得到同样的筹码
+        for msg in messages.iter().chain(decided.iter()) {
+            client.apply_event(msg, None);
+        }
+        for id in &p_ids {
+            assert_eq!(client.players[id].stack, state.players[id].stack);
+            assert_eq!(client.players[id].losses, state.players[id].losses);
+        }
+
+        // 关闭保险时直接发完公共牌
+        state.room_options.apply(RoomOption::Insurance(false));
+        for p in state.players.values_mut() {
+            p.stack = 800;
+            p.is_offline = false;
+        }
+        state.start_new_hand(Utc::now()).unwrap();
+        let first = state.current_player_id().unwrap();
+        let stack = state.players[&first].stack;
+        state.handle_player_action(first, PlayerAction::BetOrRaise(stack), Utc::now()).unwrap();
+        let second = state.current_player_id().unwrap();
+        let messages = state.handle_player_action(second, PlayerAction::Call, Utc::now()).unwrap();
+        assert!(!messages.iter().any(|m| matches!(m, ServerMessage::InsuranceOffered(_))));
+        assert_eq!(state.phase, GamePhase::Showdown);
+    }
+
+    #[test]
+    fn test_all_in_equity_progression() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        let mut history = HandHistory::new(state.for_client(Audience::Player(p_ids[0])));
+        let mut messages = state.start_new_hand(Utc::now()).unwrap();
+        // AA 对 72，公共牌 7h 2c 7s Kd 9s，72 在翻牌拿到葫芦
+        state.deck = ["9s", "Kd", "7s", "2c", "7h"].map(|c| c.parse().unwrap()).to_vec();
+        state.player_cards[state.player_indices[&p_ids[0]]] = ["As", "Ah"].map(|c| Some(c.parse().unwrap())).to_vec();
+        state.player_cards[state.player_indices[&p_ids[1]]] = ["7c", "2d"].map(|c| Some(c.parse().unwrap())).to_vec();
+        messages.extend(state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(990), Utc::now()).unwrap());
+        messages.extend(state.handle_player_action(p_ids[1], PlayerAction::Call, Utc::now()).unwrap());
+        messages.into_iter().for_each(|m| history.record(m));
+
+        // 本局结束时已经算好并保存，之后的文字版历史都使用这份结果
+        assert!(matches!(history.events.last(), Some(ServerMessage::Showdown { .. })));
+        let progression = history.equity.clone();
+        let phases: Vec<GamePhase> = progression.iter().map(|(phase, _)| *phase).collect();
+        assert_eq!(phases, vec![GamePhase::PreFlop, GamePhase::Flop, GamePhase::Turn]);
+        // 转牌后 AA 只剩两张 A 能反超
+        let (_, turn) = &progression[2];
+        assert_eq!(turn[0], (p_ids[0], 2.0 / 44.0));
+        assert_eq!(turn[1], (p_ids[1], 42.0 / 44.0));
+
+        let text = history.describe(Locale::Chinese);
+        assert_eq!(history.describe(Locale::Chinese), text);
+        let name = |i: usize| state.players[&p_ids[i]].nickname.clone();
+        assert!(text.lines().any(|l| l.starts_with(&format!("{}: 翻牌前 ", name(1))) && l.ends_with("→ 转牌 95.5%")), "{}", text);
+    }
+
+    #[test]
+    fn test_dealers_choice_variant() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::DealersChoice(vec![GameVariant::TexasHoldem, GameVariant::OmahaHiLo]));
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.variant, GameVariant::TexasHoldem);
+
+        // 只有庄家可以选择，且只能选择房主允许的玩法
+        assert_eq!(state.choose_variant(p_ids[1], GameVariant::OmahaHiLo).unwrap_err(), GameError::NotDealer);
+        assert_eq!(state.choose_variant(p_ids[0], GameVariant::FiveCardDraw).unwrap_err(), GameError::VariantNotAllowed(GameVariant::FiveCardDraw));
+        let msgs = state.choose_variant(p_ids[0], GameVariant::OmahaHiLo).unwrap();
+        assert!(matches!(msgs[0], ServerMessage::VariantChosen { variant: GameVariant::OmahaHiLo, .. }));
+
+        state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap();
+        state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        state.seated_players.rotate_left(1);
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert!(matches!(messages[0], ServerMessage::HandStarted { variant: GameVariant::OmahaHiLo, .. }));
+        assert_eq!(state.player_cards[0].len(), 4);
+        assert_eq!(state.chosen_variant, None);
+    }
+
+    #[test]
+    fn test_variant_rotation() {
+        let (mut state, _) = setup_test_game(&[1000, 1000, 1000]);
+        let rotation = VariantRotation { variants: vec![GameVariant::TexasHoldem, GameVariant::OmahaHiLo], hands_per_game: 0 };
+        state.room_options.apply(RoomOption::Rotation(Some(rotation.clone())));
+        let mut client = state.clone();
+        let mut played = vec![];
+        for _ in 0..7 {
+            let messages = state.start_new_hand(Utc::now()).unwrap();
+            for m in &messages {
+                client.apply_event(m, None);
+            }
+            played.push((state.variant, messages.iter().any(|m| matches!(m, ServerMessage::VariantChanged { .. }))));
+            assert_eq!(client.rotation, state.rotation);
+            while state.phase != GamePhase::Showdown {
+                let player_id = state.current_player_id().unwrap();
+                state.handle_player_action(player_id, PlayerAction::Fold, Utc::now()).unwrap();
+            }
+        }
+        // 每个玩法进行一圈 (三局)，换玩法时广播 VariantChanged
+        let (holdem, omaha) = (GameVariant::TexasHoldem, GameVariant::OmahaHiLo);
+        assert_eq!(played, vec![
+            (holdem, true), (holdem, false), (holdem, false),
+            (omaha, true), (omaha, false), (omaha, false),
+            (holdem, true),
+        ]);
+
+        // 修改设置后从第一个玩法重新开始，关闭后恢复房间的默认玩法
+        state.room_options.apply(RoomOption::Rotation(Some(VariantRotation { variants: vec![GameVariant::OmahaHiLo], hands_per_game: 2 })));
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert!(matches!(messages[0], ServerMessage::VariantChanged { variant: GameVariant::OmahaHiLo, index: 0, hands: 2 }));
+        while state.phase != GamePhase::Showdown {
+            let player_id = state.current_player_id().unwrap();
+            state.handle_player_action(player_id, PlayerAction::Fold, Utc::now()).unwrap();
+        }
+        state.room_options.apply(RoomOption::Rotation(None));
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!((state.variant, state.rotation.as_ref()), (GameVariant::TexasHoldem, None));
+    }
+
+    #[test]
+    fn test_variant_street_sequence() {
+        let holdem = GameVariant::TexasHoldem;
+        assert_eq!(holdem.community_card_count(), 5);
+        assert_eq!(holdem.community_offset(GamePhase::Turn), 3);
+        assert_eq!(holdem.next_phase(GamePhase::River), GamePhase::Showdown);
+
+        let draw = GameVariant::FiveCardDraw;
+        assert_eq!(draw.community_card_count(), 0);
+        assert_eq!(draw.next_phase(GamePhase::PreFlop), GamePhase::Draw);
+        assert_eq!(draw.street(GamePhase::Draw).unwrap().round, RoundKind::Draw);
+        assert!(draw.street(GamePhase::Flop).is_none());
+    }
+
+    #[test]
+    fn test_hand_history_replay_matches_server_state() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        let mut history = HandHistory::new(state.for_client(Audience::Player(p_ids[0])));
+        let mut record = |msgs: Vec<ServerMessage>| msgs.into_iter().for_each(|m| history.record(m));
+
+        record(state.start_new_hand(Utc::now()).unwrap());
+        let preflop_phase = state.phase;
+        record(state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap());
+        record(state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap());
+        record(state.handle_player_action(p_ids[2], PlayerAction::Call, Utc::now()).unwrap());
+        assert_eq!(state.phase, GamePhase::Flop);
+        let flop_pot = state.pot;
+        for _ in 0..3 {
+            record(state.handle_player_action(p_ids[2], PlayerAction::Check, Utc::now()).unwrap());
+            record(state.handle_player_action(p_ids[0], PlayerAction::Check, Utc::now()).unwrap());
+        }
+        assert_eq!(state.phase, GamePhase::Showdown);
+
+        // 从快照开始应用全部事件，应与服务器状态一致
+        let replayed = history.replay(history.events.len(), Some(p_ids[0]));
+        assert_eq!(replayed.phase, state.phase);
+        assert_eq!(replayed.pot, state.pot);
+        assert_eq!(replayed.community_cards, state.community_cards);
+        assert_eq!(replayed.hand_player_order, state.hand_player_order);
+        for id in p_ids.iter() {
+            assert_eq!(replayed.players[id].stack, state.players[id].stack);
+            assert_eq!(replayed.players[id].state, state.players[id].state);
+        }
+
+        // 回放到翻牌发出时的状态
+        let n = history.events.iter()
+            .position(|e| matches!(e, ServerMessage::CommunityCardsDealt { phase: GamePhase::Flop, .. }))
+            .unwrap() + 1;
+        let at_flop = history.replay(n, None);
+        assert_eq!(history.replay(1, None).phase, preflop_phase);
+        assert_eq!(at_flop.phase, GamePhase::Flop);
+        assert_eq!(at_flop.pot, flop_pot);
+        assert_eq!(at_flop.community_cards[0].iter().flatten().count(), 3);
+    }
+
+    #[test]
+    fn test_hand_history_text_in_both_locales() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        let mut timeline = RoomTimeline::new(state.for_client(Audience::Player(p_ids[0])), Utc::now());
+        let mut record = |msgs: Vec<ServerMessage>| msgs.into_iter().for_each(|m| timeline.record(m, Utc::now()));
+
+        record(state.start_new_hand(Utc::now()).unwrap());
+        record(state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap());
+        record(state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap());
+        record(state.handle_player_action(p_ids[2], PlayerAction::Call, Utc::now()).unwrap());
+        let flop = describe_cards(&state.community_cards[0].iter().flatten().cloned().collect::<Vec<_>>(), Locale::English);
+        record(state.handle_player_action(p_ids[2], PlayerAction::BetOrRaise(100), Utc::now()).unwrap());
+        record(state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap());
+
+        let name = |i: usize| state.players[&p_ids[i]].nickname.clone();
+        let en = timeline.describe_hands(Locale::English);
+        assert!(en.starts_with("Poker Eden Hand #1: Hold'em No Limit ($10/$20)"));
+        for line in [
+            format!("{} is the button", name(0)),
+            format!("{}: posts blind $20", name(2)),
+            format!("{}: raises to $60", name(0)),
+            format!("{}: calls $40", name(2)),
+            format!("*** FLOP *** {}", flop),
+            format!("{}: bets $100", name(2)),
+            format!("{} collected $230", name(2)),
+        ] {
+            assert!(en.lines().any(|l| l == line), "missing line: {}", line);
+        }
+
+        let zh = timeline.describe_hands(Locale::Chinese);
+        assert!(zh.starts_with("Poker Eden 第 1 局: 德州扑克 无限注"));
+        assert!(zh.lines().any(|l| l == format!("{}: 加注到 $60", name(0))));
+        assert!(zh.lines().any(|l| l == format!("{} 赢得 $230", name(2))));
+    }
+
+    #[test]
+    fn test_room_timeline_splits_hands() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        let mut timeline = RoomTimeline::new(state.for_client(Audience::Player(p_ids[0])), Utc::now());
+        let mut record = |msgs: Vec<ServerMessage>| msgs.into_iter().for_each(|m| timeline.record(m, Utc::now()));
+
+        record(state.start_new_hand(Utc::now()).unwrap());
+        record(state.handle_player_action(p_ids[0], PlayerAction::Fold, Utc::now()).unwrap());
+        record(state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap());
+        let after_first_hand: Vec<u32> = p_ids.iter().map(|id| state.players[id].stack).collect();
+        state.room_options.apply(RoomOption::BombPot(10));
+        state.room_options.apply(RoomOption::BombPotEvery(1));
+        record(vec![ServerMessage::RoomOptionsUpdated(state.room_options.clone()), ServerMessage::Error { message: "x".into() }]);
+        record(state.start_new_hand(Utc::now()).unwrap());
+        for _ in 0..3 {
+            record(state.handle_player_action(p_ids[1], PlayerAction::Check, Utc::now()).unwrap());
+            record(state.handle_player_action(p_ids[2], PlayerAction::Check, Utc::now()).unwrap());
+            record(state.handle_player_action(p_ids[0], PlayerAction::Check, Utc::now()).unwrap());
+        }
+        assert_eq!(state.phase, GamePhase::Showdown);
+
+        // 错误消息不进入时间线，整条时间线回放后与服务器状态一致
+        assert!(!timeline.entries.iter().any(|e| matches!(e.event, ServerMessage::Error { .. })));
+        let replayed = timeline.replay(timeline.entries.len());
+        assert_eq!(replayed.room_options, state.room_options);
+        for id in p_ids.iter() {
+            assert_eq!(replayed.players[id].stack, state.players[id].stack);
+        }
+
+        // 拆分出的第二局从第一局结束后的状态开始，包括两局之间修改的房间设置
+        let hands = timeline.hands();
+        assert_eq!(hands.len(), 2);
+        assert_eq!(hands[1].initial.room_options.bomb_pot_ante, 10);
+        let stacks: Vec<u32> = p_ids.iter().map(|id| hands[1].initial.players[id].stack).collect();
+        assert_eq!(stacks, after_first_hand);
+        let final_state = hands[1].replay(hands[1].events.len(), None);
+        assert_eq!(final_state.phase, GamePhase::Showdown);
+        assert_eq!(final_state.community_cards, state.community_cards);
+    }
+
+    #[test]
+    fn test_audit_log_survives_export() {
+        let (state, p_ids) = setup_test_game(&[1000, 1000]);
+        let mut timeline = RoomTimeline::new(state.for_client(Audience::Player(p_ids[0])), Utc::now());
+        timeline.audit(p_ids[0], HostAction::SetRoomOption(RoomOption::Ante(25)), Utc::now());
+        timeline.audit(p_ids[0], HostAction::HostTransferred { new_host_id: p_ids[1] }, Utc::now());
+
+        // 审计日志不进入回放的事件，导出后按顺序读回
+        assert!(timeline.entries.is_empty());
+        let json = serde_json::to_string(&timeline).unwrap();
+        let restored: RoomTimeline = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.audit_log.len(), 2);
+        assert!(matches!(restored.audit_log[0].action, HostAction::SetRoomOption(RoomOption::Ante(25))));
+        assert_eq!(restored.audit_log[1].host_id, p_ids[0]);
+
+        // 没有审计日志的旧存档也能读回
+        let mut old = serde_json::to_value(&timeline).unwrap();
+        old.as_object_mut().unwrap().remove("audit_log");
+        let restored: RoomTimeline = serde_json::from_value(old).unwrap();
+        assert!(restored.audit_log.is_empty());
+    }
+
+    #[test]
+    fn test_room_timeline_records_only_public_events() {
+        let (state, p_ids) = setup_test_game(&[1000, 1000]);
+        let at = DateTime::parse_from_rfc3339("2025-01-01T20:00:00Z").unwrap().with_timezone(&Utc);
+        let mut timeline = RoomTimeline::new(state.for_client(Audience::Spectator), at);
+
+        let mut player = state.players[&p_ids[0]].clone();
+        player.bank = 500;
+        player.auto_fold_below = 3;
+        timeline.record(ServerMessage::PlayerUpdated { player: player.clone() }, at);
+        timeline.record(ServerMessage::Info { message: "x".into() }, at);
+        timeline.record(ServerMessage::SpectatorSnapshot(state.clone()), at);
+
+        // 只记录了公开的事件，且去掉了玩家的私人设置；时间是调用者传入的
+        assert_eq!(timeline.entries.len(), 1);
+        assert_eq!(timeline.entries[0].at, at);
+        let ServerMessage::PlayerUpdated { player: recorded } = &timeline.entries[0].event else { panic!("应为 PlayerUpdated") };
+        assert_eq!((recorded.bank, recorded.auto_fold_below), (0, 0));
+
+        // 超过上限后最早的事件并入初始状态，回放结果不变
+        for stack in 1..=MAX_TIMELINE_ENTRIES as u32 {
+            player.stack = stack;
+            timeline.record(ServerMessage::PlayerUpdated { player: player.clone() }, at);
+        }
+        assert!(timeline.entries.len() <= MAX_TIMELINE_ENTRIES);
+        assert_eq!(timeline.initial.players[&p_ids[0]].stack, (MAX_TIMELINE_ENTRIES / 10) as u32 - 1);
+        let replayed = timeline.replay(timeline.entries.len());
+        assert_eq!(replayed.players[&p_ids[0]].stack, MAX_TIMELINE_ENTRIES as u32);
+    }
+
+    #[test]
+    fn test_table_overlay_hides_hole_cards() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        let mut events = state.start_new_hand(Utc::now()).unwrap();
+        events.extend(state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap());
+
+        let overlay = TableOverlay::new(&state, events.iter());
+        assert_eq!(overlay.pot, 90);
+        assert!(overlay.players.iter().all(|p| p.cards.is_empty()));
+        assert!(overlay.players[0].dealer && overlay.players[1].acting);
+        assert!(matches!(overlay.last_action, Some(OverlayAction { player_id, action: PlayerAction::BetOrRaise(60), total_bet: 60, .. })
+            if player_id == p_ids[0]));
+
+        // 摊牌后亮出仍在牌局中的玩家的底牌，没有经过摊牌就赢下的玩家不亮牌
+        let mut uncontested = state.clone();
+        uncontested.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        uncontested.handle_player_action(p_ids[2], PlayerAction::Fold, Utc::now()).unwrap();
+        assert_eq!(uncontested.phase, GamePhase::Showdown);
+        assert!(TableOverlay::new(&uncontested, events.iter()).players.iter().all(|p| p.cards.is_empty()));
+
+        events.extend(state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap());
+        events.extend(state.handle_player_action(p_ids[2], PlayerAction::Call, Utc::now()).unwrap());
+        for _ in 0..3 {
+            events.extend(state.handle_player_action(p_ids[2], PlayerAction::Check, Utc::now()).unwrap());
+            events.extend(state.handle_player_action(p_ids[0], PlayerAction::Check, Utc::now()).unwrap());
+        }
+        // p2 先亮牌; p0 输掉时要等他自己选择亮牌后才能看到
+        if state.showdown_queue.contains(&p_ids[0]) {
+            assert!(TableOverlay::new(&state, events.iter()).players[0].cards.is_empty());
+            events.extend(state.show_or_muck(p_ids[0], true).unwrap());
+        }
+        let overlay = TableOverlay::new(&state, events.iter());
+        assert_eq!(overlay.boards[0].len(), 5);
+        let revealed: Vec<usize> = overlay.players.iter().map(|p| p.cards.len()).collect();
+        assert_eq!(revealed, vec![2, 0, 2]);
+        assert!(overlay.boards[0][0].parse::<Card>().is_ok());
+    }
+
+    #[test]
+    fn test_tournament_blind_level_chip_race() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.room_options.apply(RoomOption::Tournament(Some(TournamentStructure {
+            hands_per_level: 1,
+            level_secs: 0,
+            levels: vec![
+                BlindLevel { small_blind: 10, big_blind: 20 },
+                BlindLevel { small_blind: 100, big_blind: 200 },
+            ],
+        })));
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        assert!(!messages.iter().any(|m| matches!(m, ServerMessage::BlindLevelRaised { .. })));
+
+        // 下一局升级到 100/200，面值 25 的零头被换色
+        state.phase = GamePhase::Showdown;
+        state.players.get_mut(&p_ids[0]).unwrap().stack = 1250;
+        state.players.get_mut(&p_ids[1]).unwrap().stack = 1230;
+        state.players.get_mut(&p_ids[2]).unwrap().stack = 40;
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        let Some(ServerMessage::BlindLevelRaised { level, small_blind, big_blind, chip_race, .. }) = messages.first() else {
+            panic!("应先发出盲注升级消息");
+        };
+        assert_eq!((*level, *small_blind, *big_blind), (1, 100, 200));
+        assert_eq!(state.big_blind, 200);
+        // 零头合计 120，过半进一换成一枚 100；只剩零头的玩家优先得到这一枚
+        let new_stacks: HashMap<_, _> = chip_race.iter().map(|r| (r.player_id, r.new_stack)).collect();
+        assert_eq!(new_stacks[&p_ids[2]], 100);
+        assert_eq!(new_stacks[&p_ids[0]], 1200);
+        assert_eq!(new_stacks[&p_ids[1]], 1200);
+    }
+
+    #[test]
+    fn test_sit_and_go_eliminations_and_payouts() {
+        let (mut state, p_ids) = setup_test_game(&[0, 0, 0]);
+        state.room_options.apply(RoomOption::ChipAccounts(true));
+        state.room_options.apply(RoomOption::SitAndGo(Some(SitAndGo {
+            buy_in: 100,
+            starting_stack: 1000,
+            payouts: vec![70, 30],
+        })));
+        for id in p_ids.iter() {
+            state.players.get_mut(id).unwrap().bank = 150;
+            state.take_seat_stack(*id, 5000, Utc::now()).unwrap();
+            assert_eq!(state.players[id].stack, 1000);
+        }
+        let outsider = Uuid::new_v4();
+        state.players.insert(outsider, Player { bank: 50, ..Player::new(outsider, "Dave".to_string()) });
+        assert_eq!(state.take_seat_stack(outsider, 1000, Utc::now()), Err(GameError::InsufficientBank { available: 50 }));
+
+        let mut client = state.clone();
+        let messages = state.start_new_hand(Utc::now()).unwrap();
+        let Some(ServerMessage::SitAndGoStarted { entrants, prize_pool }) = messages.first() else {
+            panic!("第一局开始时应发出 SitAndGoStarted");
+        };
+        assert_eq!((entrants.len(), *prize_pool), (3, 300));
+        assert!(p_ids.iter().all(|id| state.players[id].bank == 50));
+        state.players.get_mut(&outsider).unwrap().bank = 500;
+        assert_eq!(state.take_seat_stack(outsider, 1000, Utc::now()), Err(GameError::SitAndGoStarted));
+
+        // p1 和 p2 先后输光，p0 获得第一名
+        let mut events = messages;
+        state.players.get_mut(&p_ids[1]).unwrap().stack = 0;
+        events.extend(state.eliminate_busted_players());
+        assert!(matches!(events.last(), Some(ServerMessage::PlayerEliminated { place: 3, .. })));
+        assert_eq!(state.take_seat_stack(p_ids[1], 1000, Utc::now()), Err(GameError::SitAndGoStarted));
+        state.players.get_mut(&p_ids[2]).unwrap().stack = 0;
+        events.extend(state.eliminate_busted_players());
+        let Some(ServerMessage::SitAndGoFinished { results }) = events.last() else {
+            panic!("只剩一名参赛者时比赛应结束");
+        };
+        let standings: Vec<_> = results.iter().map(|r| (r.player_id, r.place, r.prize)).collect();
+        assert_eq!(standings, vec![(p_ids[0], 1, 210), (p_ids[2], 2, 90), (p_ids[1], 3, 0)]);
+        assert_eq!(state.players[&p_ids[0]].bank, 260);
+        assert_eq!(state.players[&p_ids[0]].stack, 0);
+        assert!(!state.sit_and_go.is_running());
+
+        for event in events.iter() {
+            client.apply_event(event, None);
+        }
+        assert_eq!(client.sit_and_go, state.sit_and_go);
+        assert!(p_ids.iter().all(|id| client.players[id].bank == state.players[id].bank));
+    }
+
+    #[test]
+    fn test_sit_and_go_prizes_remainder_to_first() {
+        let sng = SitAndGo { buy_in: 10, starting_stack: 1500, payouts: vec![50, 30, 20] };
+        assert_eq!(sng.prizes(70, 7), vec![35, 21, 14]);
+        assert_eq!(sng.prizes(55, 2), vec![39, 16]);
+    }
+
+    #[test]
+    fn test_sit_and_go_rejects_invalid_settings() {
+        let sng = SitAndGo { buy_in: 10, starting_stack: 1500, payouts: vec![65, 35] };
+        assert_eq!(sng.validate(), Ok(()));
+        assert_eq!(SitAndGo { payouts: vec![], ..sng.clone() }.validate(), Err(GameError::InvalidSitAndGoPayouts));
+        assert_eq!(SitAndGo { payouts: vec![65, 30], ..sng.clone() }.validate(), Err(GameError::InvalidSitAndGoPayouts));
+        assert_eq!(SitAndGo { payouts: vec![u32::MAX, 101], ..sng.clone() }.validate(), Err(GameError::InvalidSitAndGoPayouts));
+        assert_eq!(SitAndGo { starting_stack: 0, ..sng.clone() }.validate(), Err(GameError::InvalidSitAndGoStack));
+        let huge = SitAndGo { buy_in: u32::MAX, ..sng.clone() };
+        assert_eq!(huge.validate(), Err(GameError::SitAndGoBuyInTooLarge { max: SitAndGo::MAX_BUY_IN }));
+        // 奖池溢出时不再回绕
+        assert_eq!(huge.prize_pool(2), None);
+        assert_eq!(SitAndGo { buy_in: SitAndGo::MAX_BUY_IN, ..sng }.prize_pool(MAX_SEATS as usize), Some(SitAndGo::MAX_BUY_IN * 10));
+    }
+
+    #[test]
+    fn test_tournament_timed_blind_levels() {
+        let (mut state, _) = setup_test_game(&[1000, 1000]);
+        state.room_options.apply(RoomOption::Tournament(Some(TournamentStructure {
+            hands_per_level: 0,
+            level_secs: 600,
+            levels: vec![
+                BlindLevel { small_blind: 10, big_blind: 20 },
+                BlindLevel { small_blind: 20, big_blind: 40 },
+            ],
+        })));
+        // 第一局开始时应用第一级的盲注，从传入的开局时间开始计时
+        let start = DateTime::<Utc>::default() + chrono::Duration::days(1);
+        let messages = state.start_new_hand(start).unwrap();
+        let Some(ServerMessage::BlindLevelRaised { level: 0, level_ends_at: Some(ends_at), .. }) = messages.first() else {
+            panic!("应先发出盲注级别消息");
+        };
+        let ends_at = *ends_at;
+        assert_eq!(ends_at, start + chrono::Duration::seconds(600));
+        assert!(messages.iter().any(|m| matches!(m, ServerMessage::HandStarted { started_at, .. } if *started_at == start)));
+
+        // 牌局进行中到时不升级，也没到时的话两局之间同样不升级
+        assert!(state.advance_blind_level(ends_at).unwrap().is_empty());
+        state.phase = GamePhase::Showdown;
+        assert!(state.advance_blind_level(ends_at - chrono::Duration::seconds(1)).unwrap().is_empty());
+
+        // 两局之间到时立即升级，最后一级不再计时
+        let messages = state.advance_blind_level(ends_at).unwrap();
+        let Some(ServerMessage::BlindLevelRaised { level: 1, small_blind: 20, big_blind: 40, level_ends_at: None, .. }) = messages.first() else {
+            panic!("应升到第二级");
+        };
+        assert_eq!((state.big_blind, state.level_ends_at), (40, None));
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.level_hands, 1);
+    }
+
+    #[test]
+    fn test_event_times_use_injected_now() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        let start = DateTime::<Utc>::default() + chrono::Duration::days(1);
+        let messages = state.start_new_hand(start).unwrap();
+        // 盲注动作使用开局时间
+        assert!(messages.iter().any(|m| matches!(m, ServerMessage::PlayerActed { at, .. } if *at == start)));
+
+        // 玩家动作、自动动作和聊天都使用调用方传入的时间
+        let now = start + chrono::Duration::seconds(5);
+        let messages = state.handle_player_action(p_ids[0], PlayerAction::Fold, now).unwrap();
+        assert!(messages.iter().any(|m| matches!(m, ServerMessage::PlayerActed { at, .. } if *at == now)));
+        let later = now + chrono::Duration::seconds(5);
+        let actor = state.current_player_id().unwrap();
+        let messages = state.time_out(actor, later).unwrap();
+        assert!(messages.iter().any(|m| matches!(m, ServerMessage::PlayerActed { at, .. } if *at == later)));
+        let messages = state.chat(p_ids[0], "gg", later).unwrap();
+        assert!(matches!(messages[0], ServerMessage::ChatBroadcast { ts, .. } if ts == later));
+    }
+
+    #[test]
+    fn test_beginner_explanations() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000]);
+        state.start_new_hand(Utc::now()).unwrap();
+        let messages = state.handle_player_action(p_ids[0], PlayerAction::BetOrRaise(60), Utc::now()).unwrap();
+        let Some(ServerMessage::NextToAct { valid_actions, .. }) = messages.last() else { panic!() };
+        let explanations = state.explain_turn(valid_actions);
+        let raiser = state.players[&p_ids[0]].nickname.clone();
+        assert_eq!(explanations[0], Explanation::MustCall { amount: 50, aggressor: Some(raiser), blind: false });
+
+        state.handle_player_action(p_ids[1], PlayerAction::Fold, Utc::now()).unwrap();
+        let messages = state.handle_player_action(p_ids[2], PlayerAction::Fold, Utc::now()).unwrap();
+        let Some(ServerMessage::Showdown { results, .. }) = messages.last() else { panic!() };
+        assert!(matches!(state.explain_showdown(results)[..], [Explanation::WinsUncontested { amount: 90, .. }]));
+    }
+
+    #[test]
+    fn test_host_ledger_tips_and_session_fee() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000]);
+        state.room_options.apply(RoomOption::SessionFee(50));
+        state.charge_session_fee(p_ids[0]);
+        // 场地费每位玩家只收一次
+        assert!(state.charge_session_fee(p_ids[0]).is_empty());
+        assert_eq!(state.players[&p_ids[0]].stack, 950);
+
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.tip_host(p_ids[1], 10).unwrap_err(), GameError::TipDuringHand);
+        state.handle_player_action(state.current_player_id().unwrap(), PlayerAction::Fold, Utc::now()).unwrap();
+        let msgs = state.tip_host(p_ids[1], 10).unwrap();
+        assert!(matches!(msgs[0], ServerMessage::HostLedgerUpdated { .. }));
+
+        let summary = state.session_summary();
+        assert_eq!(summary.host_ledger.total(LedgerKind::SessionFee), 50);
+        assert_eq!(summary.host_ledger.total(LedgerKind::Tip), 10);
+        // 筹码总数加上房主账本等于带入的总数
+        let total: u32 = summary.players.iter().map(|p| p.stack).sum();
+        assert_eq!(total + 60, 2000);
+    }
+
+    #[test]
+    fn test_pot_tip_charged_per_pot_won() {
+        // P1 同时赢下主池 (150) 和边池 (900)，小费 200 在主池中只能收赢得的 150
+        let (mut state, p_ids) = setup_test_game(&[50, 500, 500]);
+        state.phase = GamePhase::Showdown;
+        state.hand_player_order = p_ids.clone();
+        state.player_indices = p_ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        state.pot = 50 + 500 + 500;
+        state.bets = vec![50, 500, 500];
+        state.mucked = vec![false; 3];
+        state.shown = vec![false; 3];
+        for (i, id) in p_ids.iter().enumerate() {
+            let p = state.players.get_mut(id).unwrap();
+            p.stack = 0;
+            p.pot_tip = if i == 1 { 200 } else { 5 };
+            p.state = if i == 0 { PlayerState::AllIn } else { PlayerState::Playing };
+        }
+        let cards = |s: [&str; 2]| s.map(|c| Some(c.parse().unwrap())).to_vec();
+        state.community_cards = vec![["Ts", "Js", "Qs", "2h", "3c"].map(|c| Some(c.parse().unwrap())).to_vec()];
+        state.player_cards = vec![cards(["Kh", "Kd"]), cards(["Ks", "9s"]), cards(["7c", "8d"])];
+
+        let msgs = state.handle_showdown().unwrap();
+        let tips: Vec<u32> = msgs.iter().filter_map(|m| match m {
+            ServerMessage::HostLedgerUpdated { entry, .. } if entry.kind == LedgerKind::Tip => Some(entry.amount),
+            _ => None,
+        }).collect();
+        assert_eq!(tips, vec![150, 200]);
+        assert_eq!(state.players[&p_ids[1]].stack, 1050 - 350);
+        // 没有赢得底池的玩家不收小费
+        assert!(state.host_ledger.entries.iter().all(|e| e.player_id == p_ids[1]));
+    }
+
+    #[test]
+    fn test_session_settlement() {
+        let (mut state, p_ids) = setup_test_game(&[0, 0, 0]);
+        for id in p_ids.iter() {
+            state.take_seat_stack(*id, 1000, Utc::now()).unwrap();
+        }
+        // 重新入座带入更多筹码，相当于补充了差额
+        state.take_seat_stack(p_ids[2], 1200, Utc::now()).unwrap();
+        assert_eq!(state.players[&p_ids[2]].buy_in, 1200);
+
+        state.room_options.apply(RoomOption::SessionFee(20));
+        state.charge_session_fee(p_ids[0]);
+        state.players.get_mut(&p_ids[0]).unwrap().stack = 1580;
+        state.players.get_mut(&p_ids[1]).unwrap().stack = 400;
+        state.players.get_mut(&p_ids[2]).unwrap().stack = 1200;
+
+        let summary = state.session_summary();
+        let net = |id| summary.players.iter().find(|p| p.player_id == id).unwrap().net;
+        assert_eq!((net(p_ids[0]), net(p_ids[1]), net(p_ids[2])), (580, -600, 0));
+        assert_eq!(summary.settlements, vec![
+            Settlement { from: p_ids[1], to: Some(p_ids[0]), amount: 580 },
+            Settlement { from: p_ids[1], to: None, amount: 20 },
+        ]);
+    }
+
+    #[test]
+    fn test_cash_out_locks_result() {
+        let (mut state, p_ids) = setup_test_game(&[0, 0]);
+        for id in p_ids.iter() {
+            state.take_seat_stack(*id, 1000, Utc::now()).unwrap();
+        }
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.cash_out(p_ids[0], Utc::now()).unwrap_err(), GameError::CashOutDuringHand);
+        state.handle_player_action(state.current_player_id().unwrap(), PlayerAction::Fold, Utc::now()).unwrap();
+
+        let winner = *p_ids.iter().max_by_key(|id| state.players[*id].stack).unwrap();
+        let msgs = state.cash_out(winner, Utc::now()).unwrap();
+        assert!(matches!(&msgs[0], ServerMessage::HostLedgerUpdated { entry, new_stack: 0 } if entry.kind == LedgerKind::CashOut && entry.amount == 1010));
+        assert_eq!(state.cash_out(winner, Utc::now()).unwrap_err(), GameError::NotSeated);
+        assert!(!state.seated_players.contains(&winner));
+
+        // 兑现后留下观战，结算仍按锁定的结果计算
+        let summary = state.session_summary();
+        let me = summary.players.iter().find(|p| p.player_id == winner).unwrap();
+        assert_eq!((me.stack, me.cashed_out, me.net), (0, 1010, 10));
+        assert_eq!(summary.settlements.iter().map(|s| s.amount).sum::<u32>(), 10);
+    }
+
+    #[test]
+    fn test_rathole_minimum_after_leaving() {
+        let (mut state, p_ids) = setup_test_game(&[0, 0]);
+        state.room_options.apply(RoomOption::RatholeWindow(30));
+        for id in p_ids.iter() {
+            state.take_seat_stack(*id, 1000, Utc::now()).unwrap();
+        }
+        state.start_new_hand(Utc::now()).unwrap();
+        state.handle_player_action(state.current_player_id().unwrap(), PlayerAction::Fold, Utc::now()).unwrap();
+        let winner = *p_ids.iter().max_by_key(|id| state.players[*id].stack).unwrap();
+        let loser = *p_ids.iter().find(|id| **id != winner).unwrap();
+
+        // 就座时换座也不能带走筹码
+        assert_eq!(state.take_seat_stack(winner, 1000, Utc::now()).unwrap_err(), GameError::RatholeMinimum { min: 1010, minutes: 30 });
+        state.leave_seat(winner, Utc::now()).unwrap();
+        state.leave_seat(loser, Utc::now()).unwrap();
+        assert_eq!(state.take_seat_stack(winner, 500, Utc::now()).unwrap_err(), GameError::RatholeMinimum { min: 1010, minutes: 30 });
+        assert!(state.take_seat_stack(loser, 990, Utc::now()).is_ok());
+        assert!(state.take_seat_stack(winner, 1200, Utc::now()).is_ok());
+        assert_eq!(state.players[&winner].stack, 1200);
+
+        // 窗口期内一直限制，过后不再限制
+        state.seated_players.push_back(winner);
+        let left_at = Utc::now();
+        state.leave_seat(winner, left_at).unwrap();
+        let min = Some(1200);
+        assert_eq!(state.rathole_minimum(&winner, left_at + chrono::Duration::minutes(29)), min);
+        assert_eq!(state.rathole_minimum(&winner, left_at + chrono::Duration::minutes(30)), None);
+        assert!(state.take_seat_stack(winner, 100, left_at + chrono::Duration::minutes(31)).is_ok());
+
+        let summary = state.session_summary();
+        let me = summary.players.iter().find(|p| p.player_id == winner).unwrap();
+        assert_eq!(me.peak_buy_in, 1200);
+    }
+
+    #[test]
+    fn test_room_config_limits_buy_in() {
+        let config = RoomConfig { small_blind: 50, big_blind: 100, seats: 6, min_buy_in: 2000, max_buy_in: 10000, ..RoomConfig::default() };
+        assert!(config.validate().is_ok());
+        assert_eq!(RoomConfig { small_blind: 200, ..config }.validate().unwrap_err(), GameError::InvalidBlinds);
+        assert_eq!(RoomConfig { seats: 1, ..config }.validate().unwrap_err(), GameError::InvalidSeatCount { max: MAX_SEATS });
+        assert_eq!(RoomConfig { max_buy_in: 1000, ..config }.validate().unwrap_err(), GameError::InvalidBuyInRange);
+
+        let (mut state, p_ids) = setup_test_game(&[0, 0]);
+        state.configure(config);
+        assert_eq!((state.small_blind, state.big_blind, state.seats), (50, 100, 6));
+        assert_eq!(state.take_seat_stack(p_ids[0], 1000, Utc::now()).unwrap_err(), GameError::BuyInTooSmall { min: 2000 });
+        assert_eq!(state.take_seat_stack(p_ids[0], 20000, Utc::now()).unwrap_err(), GameError::BuyInTooLarge { max: 10000 });
+        assert!(state.take_seat_stack(p_ids[0], 10000, Utc::now()).is_ok());
+
+        // 防抽水离场时带回离座时的筹码，即使超出了带入上限
+        state.room_options.apply(RoomOption::RatholeWindow(30));
+        state.players.get_mut(&p_ids[0]).unwrap().stack = 15000;
+        state.leave_seat(p_ids[0], Utc::now()).unwrap();
+        assert!(state.take_seat_stack(p_ids[0], 15000, Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn test_chip_accounts_limit_seat_stack() {
+        let (mut state, p_ids) = setup_test_game(&[0, 1000]);
+        // 未开启筹码账户时，带入任意筹码
+        assert!(state.take_seat_stack(p_ids[0], 500, Utc::now()).is_ok());
+        assert_eq!(state.players[&p_ids[0]].stack, 500);
+
+        state.room_options.apply(RoomOption::ChipAccounts(true));
+        state.players.get_mut(&p_ids[0]).unwrap().stack = 0;
+        assert!(state.take_seat_stack(p_ids[0], 500, Utc::now()).is_err());
+        let msgs = state.deposit_chips(p_ids[0], 800).unwrap();
+        assert!(matches!(msgs[0], ServerMessage::ChipsDeposited { new_bank: 800, .. }));
+        assert!(state.take_seat_stack(p_ids[0], 900, Utc::now()).is_err());
+        assert!(state.take_seat_stack(p_ids[0], 500, Utc::now()).is_ok());
+        assert_eq!((state.players[&p_ids[0]].stack, state.players[&p_ids[0]].bank), (500, 300));
+        // 换座时桌上的筹码先退回账户
+        assert!(state.take_seat_stack(p_ids[0], 800, Utc::now()).is_ok());
+        assert_eq!((state.players[&p_ids[0]].stack, state.players[&p_ids[0]].bank), (800, 0));
+    }
+
+    #[test]
+    fn test_duplicate_deal_rotates_hole_cards() {
+        let deal = DuplicateDeal { match_id: Uuid::new_v4(), seed: 42, table: 0, hands: 0 };
+        let (mut first, _) = setup_test_game(&[1000, 1000, 1000]);
+        let (mut second, _) = setup_test_game(&[1000, 1000, 1000]);
+        let updated = first.set_duplicate(Some(deal)).unwrap();
+        // 广播的消息中不包含种子
+        assert!(matches!(updated[0], ServerMessage::DuplicateUpdated { duplicate: Some(DuplicateDeal { seed: 0, .. }) }));
+        second.set_duplicate(Some(DuplicateDeal { table: 1, ..deal })).unwrap();
+        let mut client = second.for_client(Audience::Spectator);
+        assert_eq!(client.duplicate.map(|d| d.seed), Some(0));
+
+        for hand in 1..=2 {
+            first.start_new_hand(Utc::now()).unwrap();
+            let messages = second.start_new_hand(Utc::now()).unwrap();
+            messages.iter().for_each(|m| client.apply_event(m, None));
+            assert_eq!(client.duplicate.map(|d| d.hands), Some(hand));
+            // 第二桌第 i 位拿到第一桌第 i + 1 位的底牌，剩下的牌堆相同
+            for i in 0..3 {
+                assert_eq!(second.player_cards[i], first.player_cards[(i + 1) % 3]);
+            }
+            assert_eq!(second.deck, first.deck);
+            for state in [&mut first, &mut second] {
+                let order = state.hand_player_order.clone();
+                state.handle_player_action(order[0], PlayerAction::Fold, Utc::now()).unwrap();
+                state.handle_player_action(order[1], PlayerAction::Fold, Utc::now()).unwrap();
+            }
+        }
+
+        // 退出后恢复随机洗牌
+        assert_eq!(first.set_duplicate(None).unwrap().len(), 1);
+        assert!(first.duplicate.is_none());
+        first.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(first.set_duplicate(Some(deal)).unwrap_err(), GameError::HandInProgress);
+    }
+
+    #[test]
+    fn test_shuffle_seats() {
+        let (mut state, p_ids) = setup_test_game(&[1000, 1000, 1000, 1000]);
+        for (i, id) in p_ids.iter().enumerate() {
+            state.players.get_mut(id).unwrap().seat_id = Some(i as u8 * 2);
+        }
+        let msgs = state.shuffle_seats().unwrap();
+        assert!(matches!(msgs[0], ServerMessage::SeatsShuffled { .. }));
+
+        // 座位号集合不变，就座顺序按座位号循环排列
+        let mut seats: Vec<u8> = p_ids.iter().map(|id| state.players[id].seat_id.unwrap()).collect();
+        seats.sort();
+        assert_eq!(seats, vec![0, 2, 4, 6]);
+        let order: Vec<u8> = state.seated_players.iter().map(|id| state.players[id].seat_id.unwrap()).collect();
+        let start = order.iter().position(|&s| s == 0).unwrap();
+        assert!((1..order.len()).all(|i| order[(start + i - 1) % 4] < order[(start + i) % 4]));
+
+        state.start_new_hand(Utc::now()).unwrap();
+        assert_eq!(state.shuffle_seats().unwrap_err(), GameError::ShuffleDuringHand);
+    }
 }
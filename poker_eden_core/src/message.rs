@@ -15,15 +15,27 @@
 //
 // Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
 
-use crate::card::{Card, HandRank};
-use crate::state::{GamePhase, GameState, Player, PlayerAction, PlayerId};
+use crate::card::{Card, HandRank, LowHand};
+use crate::locale::Locale;
+use crate::state::{BettingStructure, ButtonPositions, DuplicateDeal, GameVariant, GamePhase, GameState, HostLedger, InsuranceOffer, InsurancePolicy, LedgerEntry, Player, PlayerAction, PlayerId, PlayerProfile, RoomConfig, RoomOption, RoomOptions};
+use crate::vote::{Vote, VoteProposal};
 use crate::RoomId;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use uuid::Uuid;
 
 pub type PlayerSecret = Uuid;
 
+/// 一条聊天消息最多的字数
+pub const MAX_CHAT_CHARS: usize = 200;
+
+/// 头像最多的字符数 (国旗等表情由多个字符组成)
+pub const MAX_AVATAR_CHARS: usize = 8;
+
+/// 一次 CheckIn 最多查询的对局数
+pub const MAX_CHECK_IN_GAMES: usize = 20;
+
 // --- 客户端 -> 服务器 的消息 ---
 // 这些是客户端可以发送给服务器的指令或动作。
 
@@ -32,11 +44,29 @@ pub enum ClientMessage {
     // ！房间加入时
     // --- 房间管理消息 ---
     // 房主
-    /// 客户端请求创建一个新房间
-    CreateRoom { nickname: String },
+    /// 客户端请求创建一个新房间，`config` 是房主设定的盲注、座位数、带入范围和计时
+    CreateRoom {
+        nickname: String,
+        #[serde(default)]
+        config: RoomConfig,
+    },
     // 玩家
     /// 客户端请求加入一个已存在的房间
     JoinRoom { room_id: RoomId, nickname: String },
+    /// 断线的玩家凭 RoomJoined 中的凭证重新连接到原来的身份和座位。
+    /// `last_seq` 是断线前最后收到的房间事件的序号，服务器据此补发错过的事件，为 None 时只发送状态快照
+    Reconnect {
+        room_id: RoomId,
+        player_id: PlayerId,
+        secret: PlayerSecret,
+        #[serde(default)]
+        last_seq: Option<u64>,
+    },
+    /// 不加入房间，查询自己在多个通信对局中是否轮到行动 (最多 `MAX_CHECK_IN_GAMES` 个)
+    CheckIn { games: Vec<(RoomId, PlayerId, PlayerSecret)> },
+    /// 时钟同步: 不需要加入房间，服务器立即回复自己的当前时间。
+    /// `client_time` 是客户端发送时的本地时间，原样带回，用于计算往返时间
+    ClockSync { client_time: DateTime<Utc> },
 
     // ！游戏设置和游戏中消息
     // --- 游戏内消息 ---
@@ -46,10 +76,92 @@ pub enum ClientMessage {
     RequestSeat { seat_id: u8, stack: u32 },
     /// 玩家从座位上站起 (进入观战)
     LeaveSeat,
+    /// 玩家在两局之间兑现离场: 桌上和账户中的筹码记入账本并锁定结果，然后进入观战
+    CashOut,
     /// 玩家在轮到自己时执行的游戏动作
     PerformAction(PlayerAction),
     /// 获取自己的手牌
     GetMyHand,
+    /// 换牌阶段弃掉指定序号 (从 0 开始) 的底牌并补牌，为空表示不换牌
+    DiscardCards(Vec<usize>),
+    /// 错过盲注的玩家选择补交死盲，从下一局起立即入局。暂时离席的玩家同时回到座位
+    PostDeadBlind,
+    /// 暂时离席: 保留座位和筹码，从下一局开始不再入局。离席期间盲注移过自己的座位会记为错过盲注
+    SitOut,
+    /// 离席的玩家回到座位。错过了盲注时等到大盲位再入局，要立即入局则改用 `PostDeadBlind`
+    SitIn,
+    /// 申请在下一局抓位 (房主开启抓位后)，开局时正好坐在抓位位置才会下抓位注
+    PostStraddle,
+    /// 设置摊牌输掉时是否自动盖牌
+    SetAutoMuck(bool),
+    /// 设置翻牌前有人加注时自动弃牌的起手牌百分位 (1 到 100)，0 表示关闭
+    SetAutoFold(u8),
+    /// 设置自己的语言、昵称颜色和头像
+    SetProfile(PlayerProfile),
+    /// 轮到自己行动时提前启用时间银行
+    UseTimeBank,
+    /// 一局结束后，弃牌的玩家亮出自己弃掉的底牌 (需房主开启该选项)
+    RevealFoldedHand,
+    /// 输掉摊牌的玩家轮到自己时选择亮牌 (true) 或盖牌 (false)
+    ShowOrMuck { show: bool },
+    /// 其他人都弃牌后，赢下底池的玩家亮出手牌中这些位置 (从 0 开始) 的底牌，可以只亮一张
+    ShowCards(Vec<usize>),
+    /// 牌局在河牌之前结束后，就座的玩家或房主查看没有发出的公共牌 (兔子牌)
+    RabbitHunt,
+    /// 全下保险报价后，领先的玩家买下保额为这么多的保险，为 0 表示不买
+    BuyInsurance(u32),
+    /// 庄家选择模式下，庄家从房主允许的列表中选择下一局的玩法
+    ChooseVariant(GameVariant),
+    /// 不在牌局中时，从自己的筹码中给房主小费
+    Tip(u32),
+    /// 设置每赢得一个底池 (主池或边池) 给房主的小费，0 表示关闭
+    SetPotTip(u32),
+    /// 获取本次游戏的汇总 (玩家筹码和房主账本)
+    GetSessionSummary,
+    /// 房主结束本次游戏: 牌局进行中时等本局结束，然后发出汇总和结算并关闭房间
+    EndSession,
+    /// 房主把玩家移出房间: 牌局中弃掉他的牌，断开他的连接并作废重连凭证
+    KickPlayer { player_id: PlayerId },
+    /// 房主把房主身份交给另一位在线的玩家
+    TransferHost { player_id: PlayerId },
+    /// 房主在两局之间开始一场新的复式对局，这张牌桌是第 0 桌
+    StartDuplicate,
+    /// 房主在两局之间让这张牌桌加入已有的复式对局，按顺序使用同样的牌
+    JoinDuplicate { match_id: Uuid },
+    /// 房主在两局之间让这张牌桌退出复式对局，恢复随机洗牌
+    StopDuplicate,
+    /// 房主把整个房间的时间线导出到服务器上的文件，同时按指定的语言导出文字版的牌局历史
+    ExportTimeline(Locale),
+    /// 房主查看房间的审计日志 (房主做过的所有管理操作)
+    GetAuditLog,
+    /// 房主设置接收开局提醒和牌局结果的 Discord 频道 (webhook 地址或频道 ID)，None 表示关闭
+    SetDiscordChannel(Option<String>),
+    /// 通信对局中，设置轮到自己行动时接收提醒的联系方式 (邮箱、手机号等)，None 表示不再提醒
+    SetNotifyContact(Option<String>),
+    /// 房主在两局之间随机重新分配座位
+    ShuffleSeats,
+    /// 房主安排下一局打一次炸弹底池，底注使用房间选项中的炸弹底池底注
+    BombPotNextHand,
+    /// 民主模式下，入座的玩家发起投票
+    ProposeVote(VoteProposal),
+    /// 对进行中的投票投赞成票或反对票
+    CastVote { vote_id: Uuid, approve: bool },
+    /// 没有参与本局的玩家猜本局的赢家
+    GuessWinner(PlayerId),
+    /// 开启筹码账户时，申请向自己的账户存入筹码，需要房主批准
+    DepositChips(u32),
+    /// 房主批准玩家的存入申请
+    ApproveDeposit(PlayerId),
+    /// 房主拒绝玩家的存入申请
+    RejectDeposit(PlayerId),
+    /// 开启加入审批时，房主批准玩家加入房间
+    ApproveJoin(PlayerId),
+    /// 开启加入审批时，房主拒绝玩家加入房间
+    RejectJoin(PlayerId),
+    /// 在撤回窗口内撤回自己刚提交、尚未生效的动作
+    UndoAction,
+    /// 确认动作模式下，确认服务器回显的待确认动作
+    ConfirmAction(Uuid),
 
     // 房主
     /// 玩家请求开始新的一局游戏 (通常由房主或自动触发)
@@ -60,6 +172,10 @@ pub enum ClientMessage {
         big_blind: u32,
         seats: u8,
     },
+    /// 房主切换房间选项
+    SetRoomOption(RoomOption),
+    /// 在房间里发言
+    Chat { text: String },
 }
 
 // --- 服务器 -> 客户端 的消息 ---
@@ -74,14 +190,24 @@ pub enum ServerMessage {
         your_secret: PlayerSecret, // 用于断线重连的凭证
         game_state: GameState, // 净化后的初始游戏状态
         host_id: PlayerId, // 房主ID
+        #[serde(default)]
+        config: RoomConfig, // 牌桌配置，客户端据此显示盲注和带入范围
     },
 
     // --- 游戏状态更新消息 ---
+    /// 带序号的房间事件。服务器按房间顺序给广播的事件编号后发给玩家，
+    /// 客户端记下最后收到的序号，断线重连时带上它，服务器从缓冲区中补发之后错过的事件
+    Event { seq: u64, event: Box<ServerMessage> },
+
     /// 完整游戏状态的快照。
     /// 通常在玩家刚加入房间或需要强制同步状态时发送。
-    /// 发送时会调用 state.for_client(client_id) 来隐藏敏感信息。
+    /// 发送时会调用 state.for_client(Audience::Player(client_id)) 来隐藏敏感信息。
     GameStateSnapshot(GameState),
 
+    /// 旁观者 (事件流订阅者等) 视角的状态快照，由 state.for_client(Audience::Spectator) 生成:
+    /// 不包含任何没亮出的底牌，也不包含玩家的账户余额和自动动作设置
+    SpectatorSnapshot(GameState),
+
     /// 一个新玩家加入了房间
     PlayerJoined { player: Player },
 
@@ -91,11 +217,24 @@ pub enum ServerMessage {
     /// 一个玩家的状态更新了（例如：昵称，筹码，离线状态等）
     PlayerUpdated { player: Player },
 
+    /// 房主换人了: 房主主动移交，或原房主断开后由服务器自动转给其他玩家
+    HostChanged { host_id: PlayerId },
+
     /// 新的一局开始
     HandStarted {
         seated_players: VecDeque<PlayerId>,
         /// 本局参与玩家的顺序
         hand_player_order: Vec<PlayerId>,
+        /// 本局的玩法
+        variant: GameVariant,
+        /// 本局的下注结构
+        #[serde(default)]
+        betting_structure: BettingStructure,
+        /// 本局庄家和盲注的座位
+        #[serde(default)]
+        button: Option<ButtonPositions>,
+        /// 开局时间 (UTC)
+        started_at: DateTime<Utc>,
     },
 
     /// 玩家执行了一个动作
@@ -108,17 +247,108 @@ pub enum ServerMessage {
         new_stack: u32,
         /// 执行动作后，总奖池金额
         new_pot: u32,
+        /// 动作生效的时间 (UTC)
+        at: DateTime<Utc>,
+    },
+
+    /// 玩家补交了死盲 (死的小盲不计入下注额，活的大盲计入；只错过了小盲时没有活的大盲，`live_amount` 为 0)
+    DeadBlindPosted {
+        player_id: PlayerId,
+        /// 直接进入奖池的死筹码
+        dead_amount: u32,
+        /// 计入本轮下注额的活盲注
+        live_amount: u32,
+        new_stack: u32,
+        new_pot: u32,
+    },
+
+    /// 开局时玩家交了前注。前注是死筹码，直接进入奖池，不计入下注额；
+    /// 筹码不够交前注的玩家交出全部筹码并全下。大盲前注时只有大盲交，金额是整桌的前注
+    AntePosted {
+        player_id: PlayerId,
+        amount: u32,
+        new_stack: u32,
+        new_pot: u32,
+    },
+
+    /// 炸弹底池开局时玩家交了底注。底注计入下注额，所有人交完后直接发翻牌；
+    /// 筹码不够底注的玩家交出全部筹码并全下
+    BombPotPosted {
+        player_id: PlayerId,
+        amount: u32,
+        new_stack: u32,
+        new_pot: u32,
+    },
+
+    /// 下一局将打一次炸弹底池 (房主安排或投票通过)
+    BombPotQueued,
+
+    /// 有玩家发起了投票
+    VoteStarted {
+        vote: Vote,
+        /// 发出消息时的服务器时间，客户端据此把截止时间换算成本地时间
+        #[serde(default)]
+        server_time: DateTime<Utc>,
+    },
+    /// 有玩家投了票
+    VoteCast { vote_id: Uuid, player_id: PlayerId, approve: bool },
+    /// 投票结束: 赞成票过半数时通过，之后广播执行该操作产生的事件；超时或反对票过多时否决
+    VoteEnded { vote_id: Uuid, proposal: VoteProposal, passed: bool },
+    /// 进行中的牌局被作废，每位玩家本局投入的筹码已退还，`stacks` 是退还后的筹码
+    HandVoided { stacks: Vec<(PlayerId, u32)> },
+    /// 盲注被修改
+    BlindsChanged { small_blind: u32, big_blind: u32 },
+    /// 观众猜了本局的赢家
+    WinnerGuessed { player_id: PlayerId, target: PlayerId },
+    /// 本局结束，结算观众的竞猜: `winners` 是本局赢得筹码的玩家，`correct` 是猜中的观众
+    GuessesScored { winners: Vec<PlayerId>, correct: Vec<PlayerId> },
+
+    /// 开局时玩家下了抓位注 (活注，计入下注额)。筹码不够两倍大盲的玩家交出全部筹码并全下
+    StraddlePosted {
+        player_id: PlayerId,
+        amount: u32,
+        new_stack: u32,
+        new_pot: u32,
     },
 
     /// 轮到下一个玩家行动
     NextToAct {
         player_id: PlayerId,
         valid_actions: Vec<PlayerActionType>, // 新增：告诉客户端哪些动作是合法的
+        /// 做决定需要的奖池、跟注额和截止时间等信息，机器人和轻量客户端不需要维护完整的牌局状态
+        #[serde(default)]
+        context: TurnContext,
+    },
+
+    /// 开启行动计时时，轮到玩家行动后的截止时间，超时后服务器替他过牌或弃牌
+    TurnTimer {
+        player_id: PlayerId,
+        deadline: DateTime<Utc>,
+        /// 是否已经启用时间银行，启用后截止时间包含剩余的时间银行
+        time_bank: bool,
+        /// 发出消息时的服务器时间，客户端据此把截止时间换算成本地时间
+        #[serde(default)]
+        server_time: DateTime<Utc>,
+    },
+
+    /// 玩家的时间银行余额 (秒) 发生了变化
+    TimeBankUpdated {
+        player_id: PlayerId,
+        remaining: u32,
+    },
+
+    /// 玩家在换牌阶段换了几张牌 (新牌只有本人可以通过 GetMyHand 获取)
+    CardsDrawn {
+        player_id: PlayerId,
+        count: usize,
+        /// 换牌的时间 (UTC)
+        at: DateTime<Utc>,
     },
 
     /// 发出公共牌 (翻牌、转牌、河牌)
     CommunityCardsDealt {
         phase: GamePhase, // Flop, Turn, or River
+        board: usize, // 发到第几块公共牌上
         cards: Vec<Card>,
         last_bet: u32,
     },
@@ -128,16 +358,207 @@ pub enum ServerMessage {
         player_id: PlayerId,
         amount: u32,
         new_stack: u32,
+        /// 返还后的总奖池
+        #[serde(default)]
+        new_pot: u32,
     },
 
-    /// 摊牌阶段，公布结果
+    /// 摊牌阶段，公布结果。`results` 按亮牌顺序排列: 最后一条街最后下注或加注的玩家先亮，
+    /// 没有人下注时从庄家左手边第一位开始顺时针。赢得筹码的玩家和没有被之前亮出的牌压过的玩家必须亮牌，
+    /// 其余输掉的玩家盖牌 (开启了自动盖牌或已断线) 或进入 `to_decide`，之后依次选择亮牌还是盖牌
     Showdown {
         results: Vec<ShowdownResult>,
+        /// 本局每位参与者 (包括弃牌的) 的筹码变化，按本局玩家顺序排列
+        #[serde(default)]
+        stacks: Vec<StackChange>,
+        /// 还没有决定亮牌还是盖牌的玩家，按亮牌顺序排列
+        #[serde(default)]
+        to_decide: Vec<PlayerId>,
+    },
+
+    /// 输掉摊牌的玩家选择了亮牌 (`cards` 为亮出的底牌) 或盖牌 (`cards` 为 None)
+    HandShownOrMucked {
+        player_id: PlayerId,
+        cards: Option<Vec<Card>>,
+        hand_rank: Option<HandRank>,
+    },
+
+    /// 不会再有下注 (全下摊牌) 时，在发出剩余的公共牌之前亮出仍在牌局中的玩家的底牌
+    AllInHandsRevealed {
+        hands: Vec<(PlayerId, Vec<Card>)>,
+    },
+
+    /// 全下保险报价，在亮出底牌之后、发出剩余的公共牌之前广播。
+    /// 发牌暂停，直到领先的玩家买下或放弃保险
+    InsuranceOffered(InsuranceOffer),
+
+    /// 领先的玩家决定了是否购买保险，`policy` 为 None 表示放弃，之后继续发牌
+    InsuranceDecided {
+        player_id: PlayerId,
+        policy: Option<InsurancePolicy>,
+    },
+
+    /// 按保险合约从 `from` 转给 `to` 的筹码，紧跟在 Showdown 之后广播。
+    /// 保险在判断输光筹码之前结算，输掉摊牌但拿到保额的玩家不算输光
+    InsuranceSettled {
+        from: PlayerId,
+        to: PlayerId,
+        amount: u32,
+    },
+
+    /// 七二奖励: `winner` 用 7-2 赢下奖池，`payments` 是其他就座玩家各自付给他的筹码，紧跟在 Showdown 之后广播。
+    /// 奖励在判断输光筹码之后收取，付完奖励后筹码为 0 的玩家下一局离席
+    SevenDeuceBounty {
+        winner: PlayerId,
+        payments: Vec<(PlayerId, u32)>,
     },
 
     /// 玩家的手牌
     PlayerHand {
-        hands: (Card, Card),
+        hands: Vec<Card>,
+    },
+
+    /// 房间选项发生了变化
+    RoomOptionsUpdated(RoomOptions),
+
+    /// 牌桌加入或退出了复式对局 (不包含洗牌的种子)
+    DuplicateUpdated { duplicate: Option<DuplicateDeal> },
+
+    /// 庄家为下一局选择了玩法
+    VariantChosen {
+        player_id: PlayerId,
+        variant: GameVariant,
+    },
+
+    /// 混合玩法轮换换到了下一个玩法，在这一局的 HandStarted 之前广播。
+    /// `index` 是玩法在轮换列表中的位置，`hands` 是这个玩法连续进行的局数
+    VariantChanged {
+        variant: GameVariant,
+        index: usize,
+        hands: u32,
+    },
+
+    /// 弃牌的玩家亮出了弃掉的底牌，与摊牌结果分开广播，不影响结算
+    FoldedHandRevealed {
+        player_id: PlayerId,
+        cards: Vec<Card>,
+    },
+
+    /// 其他人都弃牌后，赢下底池的玩家主动亮出了底牌，没有亮出的位置为 None
+    CardsShown {
+        player_id: PlayerId,
+        cards: Vec<Option<Card>>,
+    },
+
+    /// 兔子牌: 牌局在河牌之前结束时本来会发出的公共牌，只供查看，不影响本局结果。
+    /// `cards` 中每块公共牌一组，按发牌顺序排列
+    RabbitHunt {
+        player_id: PlayerId,
+        cards: Vec<Vec<Card>>,
+    },
+
+    /// 房间里的聊天消息
+    ChatBroadcast {
+        player_id: PlayerId,
+        text: String,
+        /// 发言的时间 (UTC)
+        ts: DateTime<Utc>,
+    },
+
+    /// 房主随机重新分配了座位
+    SeatsShuffled {
+        /// 新的就座顺序，包含重新抽取的庄家位置
+        seated_players: VecDeque<PlayerId>,
+        /// 每位玩家的新座位号
+        seats: Vec<(PlayerId, u8)>,
+    },
+
+    /// 房主账本新增了一笔收入 (小费或场地费)，并从该玩家的筹码中扣除
+    HostLedgerUpdated {
+        entry: LedgerEntry,
+        new_stack: u32,
+    },
+
+    /// 房间已经关闭，之后不会再有任何消息。客户端应回到登录界面并显示关闭的原因
+    RoomClosed {
+        reason: RoomCloseReason,
+    },
+
+    /// 房间观战人数已满，拒绝加入
+    SpectatorsFull {
+        max_spectators: u32,
+    },
+
+    /// 有新玩家申请加入房间，只发给房主
+    JoinRequested {
+        player_id: PlayerId,
+        nickname: String,
+    },
+
+    /// 玩家申请存入筹码，只发给房主
+    DepositRequested {
+        player_id: PlayerId,
+        amount: u32,
+    },
+
+    /// 房主批准了存入申请，筹码记入玩家的账户
+    ChipsDeposited {
+        player_id: PlayerId,
+        amount: u32,
+        new_bank: u32,
+    },
+
+    /// 本次游戏的汇总，只发给请求者
+    SessionSummary(SessionSummary),
+
+    /// 房间的审计日志，只发给请求的房主
+    AuditLog(Vec<AuditEntry>),
+
+    /// 锦标赛盲注升级。最小面值变大时会先进行换色 (chip race)，调整玩家的零头筹码。
+    /// 按时间升级时，级别计时开始 (盲注不变) 也会发出
+    BlindLevelRaised {
+        level: usize,
+        small_blind: u32,
+        big_blind: u32,
+        /// 按时间升级时本级结束的时间，最后一级或按局数升级时为 None
+        level_ends_at: Option<DateTime<Utc>>,
+        /// 换色后筹码发生变化的玩家
+        chip_race: Vec<ChipRaceResult>,
+        /// 发出消息时的服务器时间，客户端据此把截止时间换算成本地时间
+        #[serde(default)]
+        server_time: DateTime<Utc>,
+    },
+
+    /// Sit & Go 的第一局开始，本局发牌的玩家成为参赛者并交买入
+    SitAndGoStarted {
+        entrants: Vec<PlayerId>,
+        prize_pool: u32,
+    },
+
+    /// Sit & Go 的参赛者输光筹码被淘汰，`place` 是他的最终名次
+    PlayerEliminated {
+        player_id: PlayerId,
+        place: u32,
+    },
+
+    /// Sit & Go 决出第一名，所有参赛者的名次和奖金 (从第一名开始)
+    SitAndGoFinished {
+        results: Vec<SitAndGoResult>,
+    },
+
+    /// 确认动作模式下，服务器把玩家提交的动作回显给本人，等待确认
+    ActionPendingConfirm {
+        action_id: Uuid,
+        action: PlayerAction,
+    },
+
+    /// 对 CheckIn 的回复，顺序与请求相同
+    CheckInStatus { games: Vec<GameCheckIn> },
+
+    /// 对 ClockSync 的回复: 带回客户端的发送时间和服务器收到时的时间
+    ClockSync {
+        client_time: DateTime<Utc>,
+        server_time: DateTime<Utc>,
     },
 
     /// 服务器向特定客户端发送错误信息
@@ -145,16 +566,186 @@ pub enum ServerMessage {
     Error { message: String },
 }
 
+/// 在 CheckInStatus 消息中，描述自己在一个通信对局中的状态
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct GameCheckIn {
+    pub room_id: RoomId,
+    /// 房间已经关闭或凭证无效时为 false
+    pub active: bool,
+    pub your_turn: bool,
+    /// 轮到自己行动且开启了行动计时时的截止时间
+    pub deadline: Option<DateTime<Utc>>,
+}
+
+/// 本次游戏的汇总
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionSummary {
+    pub players: Vec<PlayerSummary>,
+    pub host_ledger: HostLedger,
+    /// 结算: 输家按这些转账付给赢家，付清所有人的输赢
+    #[serde(default)]
+    pub settlements: Vec<Settlement>,
+}
+
+/// 结算中的一笔转账。小费和场地费已经从玩家的筹码中扣除，对应的部分付给房主账本
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Settlement {
+    pub from: PlayerId,
+    /// 收款的玩家，None 表示付给房主账本
+    pub to: Option<PlayerId>,
+    pub amount: u32,
+}
+
+/// 审计日志中的一条记录: 谁在什么时候做了哪个管理操作
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub host_id: PlayerId,
+    pub action: HostAction,
+}
+
+/// 房主的管理操作。只记录成功的操作，Discord 频道的地址不写入日志
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum HostAction {
+    StartHand,
+    ShuffleSeats,
+    BombPotNextHand,
+    SetRoomOption(RoomOption),
+    ApproveDeposit { player_id: PlayerId, amount: u32 },
+    RejectDeposit { player_id: PlayerId, amount: u32 },
+    ApproveJoin { player_id: PlayerId, nickname: String },
+    RejectJoin { player_id: PlayerId, nickname: String },
+    ExportTimeline,
+    SetDiscordChannel { enabled: bool },
+    /// 原房主离开后，房主身份转给了 `new_host_id` (由服务器自动完成)
+    HostTransferred { new_host_id: PlayerId },
+    /// 房主结束了本次游戏，房间随之关闭
+    EndSession,
+    /// 房主把玩家移出了房间
+    KickPlayer { player_id: PlayerId },
+    /// 房主把房主身份交给了 `player_id`
+    TransferHost { player_id: PlayerId },
+    /// 房主让牌桌加入 (新开或加入已有的) 复式对局，None 表示退出
+    SetDuplicate { match_id: Option<Uuid> },
+}
+
+/// 在 SessionSummary 中，描述单个玩家的情况
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerSummary {
+    pub player_id: PlayerId,
+    pub nickname: String,
+    pub stack: u32,
+    pub wins: u32,
+    pub losses: u32,
+    /// 累计带入的筹码
+    #[serde(default)]
+    pub buy_in: u32,
+    /// 单次入座带入的最多筹码
+    #[serde(default)]
+    pub peak_buy_in: u32,
+    /// 兑现离场时锁定的筹码
+    #[serde(default)]
+    pub cashed_out: u32,
+    /// 本次游戏的净输赢: 桌上的筹码加账户余额和兑现的筹码减去带入
+    #[serde(default)]
+    pub net: i64,
+}
+
+/// 在 BlindLevelRaised 消息中，描述单个玩家换色前后的筹码
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChipRaceResult {
+    pub player_id: PlayerId,
+    pub old_stack: u32,
+    pub new_stack: u32,
+}
+
+/// 在 SitAndGoFinished 消息中，描述单个参赛者的名次和奖金
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SitAndGoResult {
+    pub player_id: PlayerId,
+    pub place: u32,
+    pub prize: u32,
+}
+
 /// 在 Showdown 消息中，用于描述单个玩家的结果
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ShowdownResult {
     pub player_id: PlayerId,
-    /// 玩家的最终牌型
+    /// 玩家的最终牌型 (多块公共牌时为第一块上的牌型)
     pub hand_rank: Option<HandRank>,
-    /// 玩家用于组成最佳牌型的底牌
-    pub cards: Option<(Card, Card)>,
+    /// 多块公共牌时，玩家在每块公共牌上的牌型；只有一块公共牌时为空
+    pub board_ranks: Vec<HandRank>,
+    /// 高低玩法中玩家的最佳低牌 (多块公共牌时为第一块上的低牌)，没有合格的低牌时为 None
+    pub low_hand: Option<LowHand>,
+    /// 玩家用于组成最佳牌型的底牌，盖牌时为 None
+    pub cards: Option<Vec<Card>>,
     /// 该玩家赢得的筹码数量
     pub winnings: u32,
+    /// 其中通过低牌赢得的筹码数量
+    pub low_winnings: u32,
+}
+
+/// 在 Showdown 消息中，描述一位参与者在本局中的筹码变化
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StackChange {
+    pub player_id: PlayerId,
+    /// 本局开始时 (下前注和盲注之前) 的筹码
+    pub before: u32,
+    /// 结算之后 (包括全下保险) 的筹码
+    pub after: u32,
+    /// 本局投入奖池的筹码总数，包括前注、盲注和死盲
+    pub contributed: u32,
+}
+
+impl StackChange {
+    /// 本局的输赢，赢为正
+    pub fn net(&self) -> i64 {
+        self.after as i64 - self.before as i64
+    }
+}
+
+/// 房间关闭的原因
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoomCloseReason {
+    /// 房主结束了本次游戏
+    HostEnded,
+    /// 房间长时间没有任何操作，已过期
+    Expired,
+    /// 服务器正在关闭 (通信对局会在服务器重启后恢复)
+    ServerShutdown,
+    /// 只发给被移出的玩家: 房主或投票把你移出了房间
+    Kicked,
+}
+
+impl std::fmt::Display for RoomCloseReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoomCloseReason::HostEnded => write!(f, "房主结束了本次游戏"),
+            RoomCloseReason::Expired => write!(f, "房间长时间没有操作，已过期"),
+            RoomCloseReason::ServerShutdown => write!(f, "服务器正在关闭"),
+            RoomCloseReason::Kicked => write!(f, "你被移出了房间"),
+        }
+    }
+}
+
+/// 在 NextToAct 消息中，描述轮到行动的玩家面对的局面
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct TurnContext {
+    /// 奖池总额，包括本轮已经下注的筹码
+    pub pot: u32,
+    /// 跟注还需要投入的筹码，为 0 时可以过牌
+    pub to_call: u32,
+    /// 下注或加注可以投入的筹码范围 (和 `BetOrRaise` 一样是本次新投入的筹码，包括跟注的部分)，不能加注时为 None
+    pub raise_range: Option<(u32, u32)>,
+    /// 行动玩家剩余的筹码
+    pub stack: u32,
+    /// 和每位还没弃牌的对手的有效筹码: 双方剩余筹码加本轮已下注额中较小的一个，按行动顺序排列
+    pub effective_stacks: Vec<(PlayerId, u32)>,
+    /// 开启行动计时时的截止时间 (服务器时钟，不包括时间银行)，由服务器广播前填入
+    pub deadline: Option<DateTime<Utc>>,
+    /// 这一轮在行动玩家之后还要行动的玩家数 (行动玩家只是跟注或过牌时)
+    #[serde(default)]
+    pub players_to_act: u32,
 }
 
 // 用于告知客户端当前合法的动作类型，简化客户端UI逻辑
@@ -163,8 +754,26 @@ pub enum PlayerActionType {
     Fold,
     Check,
     Call(u32),   // 需要跟注的金额
-    Bet(u32),    // 最小需要下注的金额
-    Raise(u32),  // 最小需要加注的金额
+    Bet { min: u32, max: u32 },   // 下注可以投入的筹码范围，max 为全下或底池限注的上限
+    Raise { min: u32, max: u32 }, // 加注可以投入的筹码范围 (包括跟注的部分)
+    Discard(usize), // 换牌阶段最多可以换的张数
+}
+
+impl ServerMessage {
+    /// 带有玩家私人设置的事件 (`PlayerJoined`、`PlayerUpdated`) 返回设置的所有者和去掉私人设置后的公开版本。
+    /// 只有所有者收到原来的事件，其他人、事件流和时间线都使用公开版本；其他事件返回 None
+    pub fn public_view(&self) -> Option<(PlayerId, ServerMessage)> {
+        match self {
+            ServerMessage::PlayerJoined { player } | ServerMessage::PlayerUpdated { player } => {
+                let mut public = self.clone();
+                if let ServerMessage::PlayerJoined { player } | ServerMessage::PlayerUpdated { player } = &mut public {
+                    player.scrub_private();
+                }
+                Some((player.id, public))
+            }
+            _ => None,
+        }
+    }
 }
 
 impl From<PlayerAction> for ClientMessage {
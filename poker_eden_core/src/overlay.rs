@@ -0,0 +1,133 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 直播叠加层
+//!
+//! 给 OBS 浏览器源等直播工具轮询的牌桌摘要: 只包含观众能看到的信息，
+//! 牌用 ASCII 写法 (`As`、`Td`)，方便网页直接显示或映射成图片。
+
+use crate::card::{Card, Suit};
+use crate::message::ServerMessage;
+use crate::state::*;
+use crate::RoomId;
+use serde::{Deserialize, Serialize};
+
+/// 牌桌摘要
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TableOverlay {
+    pub room_id: RoomId,
+    pub variant: GameVariant,
+    pub phase: GamePhase,
+    pub small_blind: u32,
+    pub big_blind: u32,
+    pub pot: u32,
+    /// 每块公共牌上已经翻开的牌
+    pub boards: Vec<Vec<String>>,
+    /// 按座位顺序排列的就座玩家
+    pub players: Vec<OverlayPlayer>,
+    /// 本局最近的一个动作
+    pub last_action: Option<OverlayAction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OverlayPlayer {
+    pub id: PlayerId,
+    pub nickname: String,
+    pub seat: Option<u8>,
+    pub stack: u32,
+    /// 本轮下注额
+    pub bet: u32,
+    pub state: PlayerState,
+    pub dealer: bool,
+    /// 是否轮到该玩家行动
+    pub acting: bool,
+    /// 摊牌时亮出的底牌，其他时候为空
+    pub cards: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OverlayAction {
+    pub player_id: PlayerId,
+    pub nickname: String,
+    pub action: PlayerAction,
+    pub total_bet: u32,
+}
+
+impl TableOverlay {
+    /// 从房间状态生成摘要，`events` 是本局到目前为止的事件，用于找出最近的动作。
+    /// 状态先按观众的视角净化，不会带出任何未亮出的底牌
+    pub fn new<'a>(state: &GameState, events: impl DoubleEndedIterator<Item = &'a ServerMessage>) -> Self {
+        let state = state.for_client(Audience::Spectator);
+        let hand_in_progress = !matches!(state.phase, GamePhase::WaitingForPlayers | GamePhase::Showdown);
+        let dealer = state.dealer_id();
+        let acting = if hand_in_progress { state.current_player_id() } else { None };
+
+        let players: Vec<OverlayPlayer> = state.seated_players.iter().filter_map(|id| state.players.get(id)).map(|p| {
+            let idx = state.player_indices.get(&p.id).copied();
+            OverlayPlayer {
+                id: p.id,
+                nickname: p.nickname.clone(),
+                seat: p.seat_id,
+                stack: p.stack,
+                bet: idx.and_then(|i| state.bets.get(i)).map_or(0, |b| b.saturating_sub(state.last_bet)),
+                state: p.state.clone(),
+                dealer: dealer == Some(p.id),
+                acting: acting == Some(p.id),
+                cards: idx.and_then(|i| state.player_cards.get(i))
+                    .map(|cards| cards.iter().flatten().map(card_code).collect())
+                    .unwrap_or_default(),
+            }
+        }).collect();
+
+        let last_action = events.rev()
+            .take_while(|e| !matches!(e, ServerMessage::HandStarted { .. }))
+            .find_map(|e| match e {
+                ServerMessage::PlayerActed { player_id, action, total_bet, .. } => Some(OverlayAction {
+                    player_id: *player_id,
+                    nickname: state.players.get(player_id).map_or(String::new(), |p| p.nickname.clone()),
+                    action: action.clone(),
+                    total_bet: *total_bet,
+                }),
+                _ => None,
+            });
+
+        TableOverlay {
+            room_id: state.room_id,
+            variant: state.variant,
+            phase: state.phase,
+            small_blind: state.small_blind,
+            big_blind: state.big_blind,
+            pot: state.pot,
+            boards: state.community_cards.iter()
+                .map(|board| board.iter().flatten().map(card_code).collect())
+                .collect(),
+            players,
+            last_action,
+        }
+    }
+}
+
+/// 点数在前的 ASCII 写法，可以被 `Card::from_str` 解析
+fn card_code(card: &Card) -> String {
+    let suit = match card.suit {
+        Suit::Spade => 's',
+        Suit::Heart => 'h',
+        Suit::Club => 'c',
+        Suit::Diamond => 'd',
+    };
+    format!("{}{}", card.rank, suit)
+}
@@ -0,0 +1,132 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 德州扑克起手牌强度
+//!
+//! 用 Chen 公式给 169 种起手牌打分，再按每种起手牌的组合数换算成百分位:
+//! 百分位表示比这手牌弱的起手牌组合所占的比例，最弱的 72o 为 0，AA 接近 100。
+
+use crate::card::*;
+use std::sync::OnceLock;
+
+/// 所有起手牌组合的数量 (52 选 2)
+const TOTAL_COMBOS: f64 = 1326.0;
+
+/// 单张牌在 Chen 公式中的分数，取两张底牌中较大的一张计分
+fn card_points(rank: Rank) -> f64 {
+    match rank {
+        Rank::Ace => 10.0,
+        Rank::King => 8.0,
+        Rank::Queen => 7.0,
+        Rank::Jack => 6.0,
+        // T 到 2 为点数的一半
+        _ => (rank as u8 + 2) as f64 / 2.0,
+    }
+}
+
+/// 起手牌的 Chen 分数，越高越强 (AA 为 20，72o 为 -1)
+pub fn chen_score(high: Rank, low: Rank, suited: bool) -> i32 {
+    let (high, low) = (high.max(low), high.min(low));
+    if high == low {
+        // 对子分数翻倍，最少 5 分
+        return (card_points(high) * 2.0).max(5.0).ceil() as i32;
+    }
+    let mut score = card_points(high);
+    if suited {
+        score += 2.0;
+    }
+    let gap = high as i32 - low as i32 - 1;
+    score -= match gap {
+        0 => 0.0,
+        1 => 1.0,
+        2 => 2.0,
+        3 => 4.0,
+        _ => 5.0,
+    };
+    // 小于 Q 的连张或一间隔牌更容易做成顺子
+    if gap <= 1 && high < Rank::Queen {
+        score += 1.0;
+    }
+    score.ceil() as i32
+}
+
+/// 每种 Chen 分数以下的起手牌组合数，按分数升序排列: (分数, 分数更低的组合数)
+fn score_table() -> &'static Vec<(i32, u32)> {
+    static TABLE: OnceLock<Vec<(i32, u32)>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut scores: Vec<(i32, u32)> = vec![];
+        for (i, &high) in Rank::ALL.iter().enumerate() {
+            for &low in &Rank::ALL[..=i] {
+                if high == low {
+                    scores.push((chen_score(high, low, false), 6));
+                } else {
+                    scores.push((chen_score(high, low, true), 4));
+                    scores.push((chen_score(high, low, false), 12));
+                }
+            }
+        }
+        scores.sort_unstable();
+        let mut table: Vec<(i32, u32)> = vec![];
+        let mut below = 0;
+        for (score, combos) in scores {
+            if table.last().is_none_or(|(s, _)| *s != score) {
+                table.push((score, below));
+            }
+            below += combos;
+        }
+        table
+    })
+}
+
+/// 德州扑克起手牌的百分位 (0 到 100)，即比它弱的起手牌组合所占的比例。
+/// 不是两张底牌时返回 None
+pub fn preflop_percentile(hole: &[Card]) -> Option<f64> {
+    let [a, b] = hole else { return None };
+    let score = chen_score(a.rank, b.rank, a.suit == b.suit);
+    let below = score_table().iter().find(|(s, _)| *s == score).map_or(0, |(_, below)| *below);
+    Some(below as f64 / TOTAL_COMBOS * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hole(text: &str) -> Vec<Card> {
+        text.split_whitespace().map(|s| s.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_chen_score() {
+        assert_eq!(chen_score(Rank::Ace, Rank::Ace, false), 20);
+        assert_eq!(chen_score(Rank::Two, Rank::Two, false), 5);
+        assert_eq!(chen_score(Rank::Ace, Rank::King, true), 12);
+        assert_eq!(chen_score(Rank::Jack, Rank::Ten, true), 9);
+        assert_eq!(chen_score(Rank::Seven, Rank::Two, false), -1);
+        assert_eq!(chen_score(Rank::Two, Rank::Seven, false), -1);
+    }
+
+    #[test]
+    fn test_preflop_percentile() {
+        let aces = preflop_percentile(&hole("As Ah")).unwrap();
+        let suited_connector = preflop_percentile(&hole("Js Ts")).unwrap();
+        let trash = preflop_percentile(&hole("7c 2d")).unwrap();
+        assert!(aces > 99.0, "{}", aces);
+        assert!(aces > suited_connector && suited_connector > trash);
+        assert_eq!(trash, 0.0);
+        assert_eq!(preflop_percentile(&hole("As Ah Kd")), None);
+    }
+}
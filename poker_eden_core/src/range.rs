@@ -0,0 +1,152 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 起手牌范围
+//!
+//! 用常见的简写描述德州扑克的一组起手牌，如 `TT+, AQs+, AKo, 76s`:
+//! - `AA`: 一对，`TT+` 表示 TT 及以上的所有对子
+//! - `AKs` / `AKo`: 同花 / 不同花的两张牌，不写后缀时两者都包括
+//! - `ATs+`: 保持大牌不变，小牌从 T 升到比大牌小一级 (ATs、AJs、AQs、AKs)
+
+use crate::card::*;
+use std::fmt;
+use std::str::FromStr;
+
+const SUITS: [Suit; 4] = [Suit::Spade, Suit::Heart, Suit::Club, Suit::Diamond];
+
+/// 一组具体的起手牌组合 (每种组合是两张确定花色的牌)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandRange {
+    combos: Vec<[Card; 2]>,
+}
+
+impl HandRange {
+    /// 范围内的所有组合
+    pub fn combos(&self) -> &[[Card; 2]] {
+        &self.combos
+    }
+
+    pub fn len(&self) -> usize {
+        self.combos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.combos.is_empty()
+    }
+
+    /// 加入一种起手牌的所有花色组合，已经在范围内的组合不会重复加入
+    fn add(&mut self, high: Rank, low: Rank, suited: Option<bool>) {
+        for (i, &s1) in SUITS.iter().enumerate() {
+            for (j, &s2) in SUITS.iter().enumerate() {
+                // 对子的两张牌花色不同且无序；非对子按大牌、小牌的顺序排列
+                let wanted = if high == low {
+                    i < j
+                } else {
+                    suited.is_none_or(|suited| suited == (s1 == s2))
+                };
+                let combo = [Card { rank: high, suit: s1 }, Card { rank: low, suit: s2 }];
+                if wanted && !self.combos.contains(&combo) {
+                    self.combos.push(combo);
+                }
+            }
+        }
+    }
+}
+
+/// 无法解析的范围
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRangeError(pub String);
+
+impl fmt::Display for ParseRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "无法识别的范围: {}", self.0)
+    }
+}
+
+impl std::error::Error for ParseRangeError {}
+
+/// 解析逗号或空格分隔的范围简写，不区分点数的大小写
+impl FromStr for HandRange {
+    type Err = ParseRangeError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut range = HandRange { combos: vec![] };
+        for token in text.split(|c: char| c == ',' || c.is_whitespace()).filter(|t| !t.is_empty()) {
+            let err = || ParseRangeError(token.to_string());
+            let chars: Vec<char> = token.chars().collect();
+            if chars.len() < 2 || chars.len() > 4 {
+                return Err(err());
+            }
+            let rank = |c: char| Rank::from_symbol(&c.to_string()).ok_or_else(err);
+            let (first, second) = (rank(chars[0])?, rank(chars[1])?);
+            let (high, low) = (first.max(second), first.min(second));
+            let (suited, plus) = match &chars[2..] {
+                [] => (None, false),
+                ['+'] => (None, true),
+                ['s'] => (Some(true), false),
+                ['o'] => (Some(false), false),
+                ['s', '+'] => (Some(true), true),
+                ['o', '+'] => (Some(false), true),
+                _ => return Err(err()),
+            };
+            if high == low && suited.is_some() {
+                return Err(err());
+            }
+
+            let lows: Vec<Rank> = if !plus {
+                vec![low]
+            } else if high == low {
+                Rank::ALL.into_iter().filter(|r| *r >= low).collect()
+            } else {
+                Rank::ALL.into_iter().filter(|r| *r >= low && *r < high).collect()
+            };
+            for low in lows {
+                // 对子的 `+` 同时提高两张牌
+                let high = if first == second { low } else { high };
+                range.add(high, low, suited);
+            }
+        }
+        if range.is_empty() {
+            return Err(ParseRangeError(text.to_string()));
+        }
+        Ok(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        let range: HandRange = "TT+, AKs, AQo".parse().unwrap();
+        // 5 种对子各 6 种组合，同花 4 种，不同花 12 种
+        assert_eq!(range.len(), 5 * 6 + 4 + 12);
+        assert_eq!("A2s+".parse::<HandRange>().unwrap().len(), 12 * 4);
+        assert_eq!("kq".parse::<HandRange>().unwrap().len(), 16);
+        // 重复的写法不会重复计入组合
+        assert_eq!("AK AKs".parse::<HandRange>().unwrap().len(), 16);
+
+        let pair: HandRange = "22".parse().unwrap();
+        assert!(pair.combos().iter().all(|[a, b]| a.rank == Rank::Two && b.rank == Rank::Two && a.suit != b.suit));
+
+        assert!("AX".parse::<HandRange>().is_err());
+        assert!("AAs".parse::<HandRange>().is_err());
+        assert!("AK+s".parse::<HandRange>().is_err());
+        assert_eq!("".parse::<HandRange>().unwrap_err(), ParseRangeError("".to_string()));
+    }
+}
@@ -15,7 +15,12 @@
 //
 // Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
 
-use crate::card::Card;
+use crate::card::{find_best_hand, find_best_omaha_hand, find_best_omaha_low, Card, HandRank, LowHand};
+use crate::error::{GameError, GameResult};
+use crate::guess::SpectatorGuesses;
+use crate::locale::Locale;
+use crate::vote::Vote;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Display;
@@ -32,12 +37,36 @@ pub struct GameState {
     pub small_blind: u32, // 小盲注金额
     pub big_blind: u32, // 大盲注金额
     pub seats: u8, // 房间总座位数
+    pub room_options: RoomOptions, // 房主可配置的房间选项
+    #[serde(default)]
+    pub config: RoomConfig, // 创建房间时设定的牌桌配置，之后不再改变
+    pub tournament_level: usize, // 锦标赛模式下当前的盲注级别
+    pub level_hands: u32, // 当前盲注级别已经进行的局数
+    pub level_ends_at: Option<DateTime<Utc>>, // 按时间升级时，当前盲注级别结束的时间
+    pub host_ledger: HostLedger, // 房主账本: 小费和场地费，不属于任何玩家的筹码
+    #[serde(default)]
+    pub sit_and_go: SitAndGoProgress, // Sit & Go 模式下的参赛者和淘汰顺序
+    #[serde(default)]
+    pub vote: Option<Vote>, // 民主模式下进行中的投票
+    #[serde(default)]
+    pub spectator_guesses: SpectatorGuesses, // 观众猜赢家: 本局的竞猜和本次会话的战绩
+    #[serde(default)]
+    pub seat_ledger: HashMap<PlayerId, SeatRecord>, // 本次游戏每位玩家的带入记录，用于防止抽水离场，只在服务器上维护
+    #[serde(default)]
+    pub duplicate: Option<DuplicateDeal>, // 复式对局: 和其他牌桌按顺序使用同样的牌，种子只在服务器上保存
+    #[serde(default)]
+    pub bomb_pot_queued: bool, // 房主为下一局安排了一次炸弹底池
+    #[serde(default)]
+    pub hands_since_bomb_pot: u32, // 上一次炸弹底池之后已经进行的局数
 
     // ！本局开始时同步的状态
     // 轮换的、包含所有就座玩家的列表。每局开始时轮换。
     pub seated_players: VecDeque<PlayerId>,
-    // 当前牌局的玩家顺序，不包含观战者
+    // 当前牌局的玩家顺序，不包含观战者。第一位是庄家 (死庄时是庄位右手边最近的玩家)
     pub hand_player_order: Vec<PlayerId>,
+    // 本局庄家和盲注所在的座位，下一局按死庄规则从这里移动；玩家没有座位号时为 None
+    #[serde(default)]
+    pub button: Option<ButtonPositions>,
     // 方便通过PlayerId快速查找其在hand_player_order中的索引
     pub player_indices: HashMap<PlayerId, usize>,
     // 服务端持有的完整牌堆，不会发给客户端。
@@ -46,17 +75,65 @@ pub struct GameState {
 
     // ！游戏过程中随时同步的状态
     pub phase: GamePhase,
+    // 本局的玩法
+    pub variant: GameVariant,
+    // 本局的下注结构
+    #[serde(default)]
+    pub betting_structure: BettingStructure,
+    // 本局是否为炸弹底池
+    #[serde(default)]
+    pub bomb_pot: bool,
+    // 庄家选择模式下，上一局的庄家为下一局选择的玩法
+    pub chosen_variant: Option<GameVariant>,
+    // 混合玩法轮换的进度，未开启轮换时为 None
+    #[serde(default)]
+    pub rotation: Option<RotationProgress>,
+    // 本局的全下保险，没有报价时为 None
+    #[serde(default)]
+    pub insurance: Option<Insurance>,
     // 总奖池金额
     pub pot: u32,
     // 每个玩家的总下注额，其索引对应 hand_player_order 中的索引
     pub bets: Vec<u32>,
+    // 每个玩家投入奖池、但不计入下注额的死筹码（如死盲），其索引对应 hand_player_order 中的索引
+    pub dead_bets: Vec<u32>,
+    // 每个玩家翻牌前被强制下的活注 (盲注、补交的大盲、抓位)，其索引对应 hand_player_order 中的索引。
+    // 第一次轮到玩家行动时记录，之前为空
+    #[serde(default)]
+    pub forced_bets: Vec<u32>,
+    // 本局第二轮起每一轮下注开始时的下注额 (`last_bet`)，第一轮从 0 开始不记录，换牌轮没有下注也不记录。
+    // 玩家的累计下注按这些分界拆开就是每一轮的下注
+    #[serde(default)]
+    pub street_levels: Vec<u32>,
+    // 每个玩家在本局开始时 (下前注和盲注之前) 的筹码，其索引对应 hand_player_order 中的索引
+    #[serde(default)]
+    pub starting_stacks: Vec<u32>,
+    // 不属于任何玩家投入的死筹码（如大盲前注），结算时并入主池
+    #[serde(default)]
+    pub dead_money: u32,
+    // 本局抓位玩家下的抓位注，没有人抓位时为 0
+    #[serde(default)]
+    pub straddle: u32,
 
-    // 公共牌数组，长度为5。已发的牌是 Some(card)，未发的牌是 None
-    pub community_cards: Vec<Option<Card>>,
-    // 服务端存有所有玩家的真实底牌 (Some(c1), Some(c2))
-    // 客户端只知道自己的真实底牌，其他玩家的底牌为 (None, None)
-    // 玩家手牌，其索引对应 hand_player_order 中的索引
-    pub player_cards: Vec<(Option<Card>, Option<Card>)>,
+    // 公共牌，每块公共牌 (board) 长度为5。已发的牌是 Some(card)，未发的牌是 None
+    // 通常只有一块公共牌，炸弹底池开启双公共牌时为两块
+    pub community_cards: Vec<Vec<Option<Card>>>,
+    // 服务端存有所有玩家的真实底牌 [Some(c1), Some(c2), ...]
+    // 客户端只知道自己的真实底牌，其他玩家的底牌为 [None, None, ...]
+    // 玩家手牌，其索引对应 hand_player_order 中的索引，底牌张数由本局玩法决定
+    pub player_cards: Vec<Vec<Option<Card>>>,
+    // 摊牌时是否盖牌（不亮出底牌），其索引对应 hand_player_order 中的索引
+    pub mucked: Vec<bool>,
+    // 本局已经公开亮出底牌的玩家 (摊牌亮牌、全下摊牌或亮出弃牌)，其索引对应 hand_player_order 中的索引。
+    // 发给客户端的状态中只保留亮出过的底牌
+    #[serde(default)]
+    pub shown: Vec<bool>,
+    // 其他人都弃牌后，赢下底池的玩家主动亮出的底牌在手牌中的位置 (可以只亮一张)
+    #[serde(default)]
+    pub shown_cards: HashMap<PlayerId, Vec<usize>>,
+    // 输掉摊牌、还没有决定亮牌还是盖牌的玩家，按亮牌顺序排列，队首的玩家正在选择
+    #[serde(default)]
+    pub showdown_queue: Vec<PlayerId>,
 
     // ！游戏中间变量
     // 在每轮下注开始时重置为 all false
@@ -67,6 +144,7 @@ pub struct GameState {
     pub max_bet: u32, // 下注的最高金额
     pub last_bet: u32, // 上轮最终下注金额
     pub last_raise_amount: u32,  // 最小加注额
+    pub last_aggressor: Option<PlayerId>, // 本轮最后一个下注或加注的玩家 (翻牌前为大盲)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -79,6 +157,638 @@ pub struct Player {
     pub state: PlayerState,
     pub seat_id: Option<u8>,  // 座位号（总共若干座位）由用户自己选择座位
     pub is_offline: bool,  // 是否离线
+    #[serde(default)]
+    pub missed_blinds: MissedBlinds,  // 离席期间错过的盲注
+    pub post_dead_blind: bool,  // 是否选择补交死盲以立即入局
+    #[serde(default)]
+    pub sit_out: bool,  // 是否选择暂时离席 (保留座位)，从下一局开始不再入局
+    pub post_straddle: bool,  // 是否申请在下一局抓位 (开局时正好在抓位位置才生效)
+    pub auto_muck: bool,  // 摊牌输掉时是否自动盖牌，不亮出底牌
+    pub auto_fold_below: u8,  // 翻牌前有人加注时，自动弃掉百分位低于该值的起手牌，0 表示关闭
+    #[serde(default)]
+    pub pot_tip: u32,  // 每赢得一个底池 (主池或边池) 给房主的小费，超过在该池赢得的筹码时只给赢得的部分，0 表示关闭
+    pub bank: u32,  // 开启筹码账户时，已存入但还没有带上桌的筹码
+    pub time_bank: u32,  // 行动计时用完后还可以使用的时间银行 (秒)
+    #[serde(default)]
+    pub buy_in: u32,  // 本次游戏累计带入的筹码 (带走的部分已扣除)，用于结算输赢
+    #[serde(default)]
+    pub cashed_out: u32,  // 兑现离场时锁定的筹码，之后留下观战也不再变化
+    #[serde(default)]
+    pub profile: PlayerProfile,  // 语言、颜色、头像等展示偏好
+}
+
+/// 玩家的展示偏好，和其他玩家状态一样通过 `PlayerUpdated` 同步给房间里的所有人
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlayerProfile {
+    /// 玩家使用的语言，服务器按它翻译只发给这名玩家的提示
+    pub locale: Locale,
+    /// 昵称的显示颜色 (RGB)，None 表示由客户端决定
+    pub color: Option<[u8; 3]>,
+    /// 显示在昵称前的头像，通常是一个表情或国旗，最多 `MAX_AVATAR_CHARS` 个字符
+    pub avatar: Option<String>,
+}
+
+/// 玩家离席期间错过的盲注。盲注移过离席玩家的座位时记录，回来时补交死盲或等到大盲位入局
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MissedBlinds {
+    /// 没有错过盲注
+    #[default]
+    None,
+    /// 只错过了小盲: 补交一个死的小盲
+    Small,
+    /// 错过了大盲: 补交一个死的小盲和一个活的大盲
+    Big,
+}
+
+/// 游戏玩法
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum GameVariant {
+    /// 德州扑克
+    #[default]
+    TexasHoldem,
+    /// 奥马哈高低 (O8): 四张底牌，必须用两张底牌和三张公共牌，8 或更小的低牌平分奖池
+    OmahaHiLo,
+    /// 五张换牌: 五张底牌，没有公共牌，两轮下注之间可以换牌
+    FiveCardDraw,
+}
+
+impl GameVariant {
+    /// 每位玩家的底牌张数
+    pub fn hole_card_count(&self) -> usize {
+        match self {
+            GameVariant::TexasHoldem => 2,
+            GameVariant::OmahaHiLo => 4,
+            GameVariant::FiveCardDraw => 5,
+        }
+    }
+
+    /// 本玩法的街道顺序。牌局从第一条街道开始，最后一条街道结束后进入 Showdown。
+    /// 新增玩法只需要在这里描述它的街道，推进逻辑由引擎统一处理。
+    pub fn streets(&self) -> &'static [Street] {
+        const HOLDEM: &[Street] = &[
+            Street { phase: GamePhase::PreFlop, deal: 0, round: RoundKind::Bet },
+            Street { phase: GamePhase::Flop, deal: 3, round: RoundKind::Bet },
+            Street { phase: GamePhase::Turn, deal: 1, round: RoundKind::Bet },
+            Street { phase: GamePhase::River, deal: 1, round: RoundKind::Bet },
+        ];
+        const DRAW: &[Street] = &[
+            Street { phase: GamePhase::PreFlop, deal: 0, round: RoundKind::Bet },
+            Street { phase: GamePhase::Draw, deal: 0, round: RoundKind::Draw },
+            Street { phase: GamePhase::AfterDraw, deal: 0, round: RoundKind::Bet },
+        ];
+        match self {
+            GameVariant::TexasHoldem | GameVariant::OmahaHiLo => HOLDEM,
+            GameVariant::FiveCardDraw => DRAW,
+        }
+    }
+
+    /// 某个阶段对应的街道，不在本玩法中时返回 None
+    pub fn street(&self, phase: GamePhase) -> Option<&'static Street> {
+        self.streets().iter().find(|s| s.phase == phase)
+    }
+
+    /// 每块公共牌的张数
+    pub fn community_card_count(&self) -> usize {
+        self.streets().iter().map(|s| s.deal).sum()
+    }
+
+    /// 进入某个阶段之前，每块公共牌上已经发出的张数
+    pub fn community_offset(&self, phase: GamePhase) -> usize {
+        self.streets()
+            .iter()
+            .take_while(|s| s.phase != phase)
+            .map(|s| s.deal)
+            .sum()
+    }
+
+    /// 本玩法中某个阶段之后的下一个阶段，最后一条街道之后是 Showdown
+    pub fn next_phase(&self, phase: GamePhase) -> GamePhase {
+        let streets = self.streets();
+        streets
+            .iter()
+            .position(|s| s.phase == phase)
+            .and_then(|i| streets.get(i + 1))
+            .map_or(GamePhase::Showdown, |s| s.phase)
+    }
+
+    /// 是否为高低平分奖池的玩法
+    pub fn is_hi_lo(&self) -> bool {
+        matches!(self, GameVariant::OmahaHiLo)
+    }
+
+    /// 按本玩法的规则评估底牌和公共牌组成的最佳高牌
+    ///
+    /// # Panics
+    /// 公共玩法中公共牌少于 3 张时会 panic。
+    pub fn evaluate_high(&self, hole: &[Card], board: &[Card]) -> HandRank {
+        match self {
+            GameVariant::TexasHoldem => {
+                let mut all_cards = board.to_vec();
+                all_cards.extend_from_slice(hole);
+                find_best_hand(&all_cards)
+            }
+            GameVariant::OmahaHiLo => find_best_omaha_hand(hole, board),
+            GameVariant::FiveCardDraw => find_best_hand(hole),
+        }
+    }
+
+    /// 按本玩法的规则评估最佳低牌，不是高低玩法或没有合格的低牌时返回 None
+    pub fn evaluate_low(&self, hole: &[Card], board: &[Card]) -> Option<LowHand> {
+        match self {
+            GameVariant::TexasHoldem | GameVariant::FiveCardDraw => None,
+            GameVariant::OmahaHiLo => find_best_omaha_low(hole, board),
+        }
+    }
+}
+
+impl Display for GameVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameVariant::TexasHoldem => write!(f, "德州扑克"),
+            GameVariant::OmahaHiLo => write!(f, "奥马哈高低"),
+            GameVariant::FiveCardDraw => write!(f, "五张换牌"),
+        }
+    }
+}
+
+/// 街道上进行的一轮行动
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RoundKind {
+    /// 一轮下注
+    Bet,
+    /// 换牌轮: 每位玩家依次弃牌并补牌
+    Draw,
+}
+
+/// 玩法中的一条街道: 进入该阶段时先向每块公共牌发牌，然后进行一轮行动
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Street {
+    pub phase: GamePhase,
+    /// 进入该阶段时向每块公共牌发的牌数
+    pub deal: usize,
+    pub round: RoundKind,
+}
+
+/// 房间最多的座位数
+pub const MAX_SEATS: u8 = 10;
+
+/// 房主创建房间时设定的牌桌配置: 盲注、座位数、带入范围和计时。
+/// 之后盲注只会随锦标赛升盲改变，计时可以用房间选项调整
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoomConfig {
+    pub small_blind: u32,
+    pub big_blind: u32,
+    pub seats: u8,
+    /// 入座时最少带入的筹码，0 表示不限
+    pub min_buy_in: u32,
+    /// 入座时最多带入的筹码，0 表示不限
+    pub max_buy_in: u32,
+    /// 行动计时 (秒)，0 表示关闭
+    pub turn_timer_secs: u32,
+    /// 行动计时之外，每位玩家整局可用的时间银行 (秒)
+    pub time_bank_secs: u32,
+}
+
+impl Default for RoomConfig {
+    fn default() -> Self {
+        Self {
+            small_blind: 100,
+            big_blind: 200,
+            seats: MAX_SEATS,
+            min_buy_in: 0,
+            max_buy_in: 0,
+            turn_timer_secs: 0,
+            time_bank_secs: 0,
+        }
+    }
+}
+
+impl RoomConfig {
+    /// 检查配置是否合理，服务器创建房间前调用
+    pub fn validate(&self) -> GameResult<()> {
+        if self.small_blind == 0 || self.small_blind > self.big_blind {
+            return Err(GameError::InvalidBlinds);
+        }
+        if !(2..=MAX_SEATS).contains(&self.seats) {
+            return Err(GameError::InvalidSeatCount { max: MAX_SEATS });
+        }
+        if self.max_buy_in > 0 && self.max_buy_in < self.min_buy_in.max(self.big_blind) {
+            return Err(GameError::InvalidBuyInRange);
+        }
+        Ok(())
+    }
+
+    /// 带入范围的文字 (如 `$2000 - $10000`)，不限带入时为 None
+    pub fn buy_in_range(&self) -> Option<String> {
+        match (self.min_buy_in, self.max_buy_in) {
+            (0, 0) => None,
+            (min, 0) => Some(format!("至少 ${}", min)),
+            (min, max) => Some(format!("${} - ${}", min, max)),
+        }
+    }
+
+    /// 检查入座时带入的筹码是否在带入范围内
+    pub fn check_buy_in(&self, stack: u32) -> GameResult<()> {
+        if stack < self.min_buy_in {
+            return Err(GameError::BuyInTooSmall { min: self.min_buy_in });
+        }
+        if self.max_buy_in > 0 && stack > self.max_buy_in {
+            return Err(GameError::BuyInTooLarge { max: self.max_buy_in });
+        }
+        Ok(())
+    }
+}
+
+impl Display for RoomConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let buy_in = self.buy_in_range().unwrap_or_else(|| "不限".to_string());
+        write!(f, "盲注 {}/{}，{} 个座位，带入 {}", self.small_blind, self.big_blind, self.seats, buy_in)?;
+        if self.turn_timer_secs > 0 {
+            write!(f, "，行动计时 {} 秒", self.turn_timer_secs)?;
+        }
+        Ok(())
+    }
+}
+
+/// 房主可以在房间内切换的选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoomOptions {
+    /// 一局结束后，弃牌的玩家可以选择亮出自己弃掉的底牌 (娱乐/教学局)
+    pub reveal_folded_hands: bool,
+    /// 炸弹底池的底注，为 0 时不打炸弹底池。炸弹底池中所有玩家下底注后直接发翻牌
+    pub bomb_pot_ante: u32,
+    /// 大于 0 时每隔这么多局打一次炸弹底池 (1 表示每局都打)，为 0 时只在房主安排时打
+    #[serde(default)]
+    pub bomb_pot_every: u32,
+    /// 前注，大于 0 时每局开始、下盲注之前每位玩家交前注 (死筹码，不计入下注额)
+    pub ante: u32,
+    /// 大盲前注: 前注改由大盲一人替整桌交纳，先交大盲、剩余筹码再交前注
+    pub big_blind_ante: bool,
+    /// 允许抓位的位置，为 None 表示不允许抓位
+    pub straddle: Option<StraddlePosition>,
+    /// 炸弹底池发两块公共牌，奖池在两块公共牌各自的赢家之间平分
+    pub double_board: bool,
+    /// 下一局使用的玩法
+    pub variant: GameVariant,
+    /// 下一局使用的下注结构
+    pub betting_structure: BettingStructure,
+    /// 庄家选择模式下房主允许的玩法列表，为空表示关闭该模式
+    pub dealers_choice: Vec<GameVariant>,
+    /// 混合玩法轮换，为 None 表示不轮换
+    #[serde(default)]
+    pub rotation: Option<VariantRotation>,
+    /// 撤回窗口 (毫秒)，大于 0 时非全下的动作会延迟生效，期间玩家可以撤回误操作
+    pub undo_grace_ms: u64,
+    /// 确认动作模式: 服务器先把动作回显给玩家，玩家确认后才生效，防止重复发送和过期的动作
+    pub confirm_actions: bool,
+    /// 锦标赛盲注结构，为 None 表示普通现金局
+    pub tournament: Option<TournamentStructure>,
+    /// 固定场地费，玩家本次游戏第一次入座时从带入的筹码中扣除，记入房主账本
+    pub session_fee: u32,
+    /// 筹码账户: 玩家的筹码需要先存入并经房主批准，入座带入的筹码不能超过账户余额
+    pub chip_accounts: bool,
+    /// 加入审批: 新玩家加入房间需要房主批准，防止房间号泄露后被陌生人加入
+    pub require_join_approval: bool,
+    /// 同时在线的观战者 (未入座的玩家) 上限，为 0 表示不限制
+    pub max_spectators: u32,
+    /// 禁止玩家使用翻牌前自动弃牌的辅助功能
+    pub auto_fold_disabled: bool,
+    /// 行动计时 (秒)，大于 0 时轮到玩家行动就开始倒计时，超时后服务器替他过牌或弃牌
+    pub turn_timer_secs: u32,
+    /// 每位玩家的时间银行 (秒)，行动计时用完后自动启用，实际用掉的时间从中扣除
+    pub time_bank_secs: u32,
+    /// 通信对局: 断线的玩家不会离席或被代打，只由行动计时处理；房间在所有人断开后继续保留，
+    /// 服务器重启后恢复，轮到断线的玩家行动时通过通知钩子提醒他
+    pub correspondence: bool,
+    /// Sit & Go 的买入、起始筹码和奖金分配，为 None 表示不是 Sit & Go
+    pub sit_and_go: Option<SitAndGo>,
+    /// 民主模式: 踢人、作废本局、修改盲注和房间选项都需要入座玩家投票过半数通过，房主不能单独修改房间选项
+    pub democracy: bool,
+    /// 观众猜赢家: 没有参与本局的玩家可以在摊牌前猜本局的赢家，按猜中的局数排行
+    pub spectator_guessing: bool,
+    /// 全下保险: 两位玩家全下、公共牌还没发完时，领先的一方可以按公平赔率向落后的一方买保险
+    #[serde(default)]
+    pub insurance: bool,
+    /// 七二奖励: 用不同花色的 7 和 2 赢下奖池的玩家，从其他每位就座的玩家处各得到这么多筹码，0 表示关闭
+    #[serde(default)]
+    pub seven_deuce_bounty: u32,
+    /// 防止抽水离场 (ratholing): 离座后这么多分钟内重新入座，带入的筹码不能少于离座时的筹码，0 表示关闭
+    #[serde(default)]
+    pub rathole_window_mins: u32,
+}
+
+/// 一局中庄家、小盲和大盲所在的座位号。
+///
+/// 按死庄规则移动: 大盲每局移到上一局大盲之后的第一位玩家，小盲位是上一局的大盲位，庄位是上一局的小盲位。
+/// 上一局的大盲离开后本局没有小盲 (死小盲)，上一局的小盲离开后庄位上没有人 (死庄)，这样每位玩家每圈都正好交一次大盲
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ButtonPositions {
+    pub button: u8,
+    pub small_blind: u8,
+    pub big_blind: u8,
+    /// 小盲位上没有玩家，本局不收小盲
+    pub dead_small_blind: bool,
+}
+
+/// 抓位 (straddle) 的位置。抓位的玩家在发牌前下两倍大盲的活注，
+/// 翻牌前从抓位玩家的左手边开始行动，抓位玩家最后行动
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum StraddlePosition {
+    /// 枪口位 (大盲左手边)
+    Utg,
+    /// 庄家位，翻牌前由小盲先行动
+    Button,
+}
+
+impl Display for StraddlePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StraddlePosition::Utg => write!(f, "枪口位"),
+            StraddlePosition::Button => write!(f, "庄家位"),
+        }
+    }
+}
+
+/// 下注结构
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum BettingStructure {
+    /// 无限注: 最多可以全下
+    #[default]
+    NoLimit,
+    /// 底池限注: 每次最多先跟注、再加注跟注后的整个底池
+    PotLimit,
+}
+
+impl Display for BettingStructure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BettingStructure::NoLimit => write!(f, "无限注"),
+            BettingStructure::PotLimit => write!(f, "底池限注"),
+        }
+    }
+}
+
+/// Sit & Go: 入座即报名，每人买入相同、起始筹码相同，第一局开始后不再接受报名。
+/// 筹码输光的参赛者被淘汰，只剩一名参赛者时比赛结束，奖池按名次的百分比分配
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SitAndGo {
+    pub buy_in: u32,
+    pub starting_stack: u32,
+    /// 各名次分得奖池的百分比，从第一名开始，例如 `[65, 35]`
+    pub payouts: Vec<u32>,
+}
+
+impl SitAndGo {
+    /// 买入的上限: 坐满时的奖池也不会超出 u32
+    pub const MAX_BUY_IN: u32 = u32::MAX / MAX_SEATS as u32;
+
+    /// 检查比赛设置: 奖金比例不为空且加起来是 100%，起始筹码大于 0，买入不超过上限
+    pub fn validate(&self) -> GameResult<()> {
+        if self.payouts.is_empty() || self.payouts.iter().map(|&p| p as u64).sum::<u64>() != 100 {
+            return Err(GameError::InvalidSitAndGoPayouts);
+        }
+        if self.starting_stack == 0 {
+            return Err(GameError::InvalidSitAndGoStack);
+        }
+        if self.buy_in > Self::MAX_BUY_IN {
+            return Err(GameError::SitAndGoBuyInTooLarge { max: Self::MAX_BUY_IN });
+        }
+        Ok(())
+    }
+
+    /// `entrants` 位参赛者的奖池，超出 u32 时返回 None
+    pub fn prize_pool(&self, entrants: usize) -> Option<u32> {
+        self.buy_in.checked_mul(u32::try_from(entrants).ok()?)
+    }
+
+    /// 按名次分配奖池，返回从第一名开始的奖金。除不尽的零头归第一名
+    pub fn prizes(&self, prize_pool: u32, entrants: usize) -> Vec<u32> {
+        let mut prizes: Vec<u32> = self.payouts.iter().take(entrants)
+            .map(|pct| (prize_pool as u64 * *pct as u64 / 100) as u32)
+            .collect();
+        let paid: u32 = prizes.iter().sum();
+        if let Some(first) = prizes.first_mut() {
+            *first += prize_pool.saturating_sub(paid);
+        }
+        prizes
+    }
+}
+
+/// 玩家本次游戏的带入记录
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SeatRecord {
+    /// 单次入座带入的最多筹码
+    pub peak_buy_in: u32,
+    /// 最近一次离座时桌上的筹码
+    pub left_with: u32,
+    /// 最近一次离座的时间，重新入座后为 None
+    pub left_at: Option<DateTime<Utc>>,
+}
+
+/// 复式对局中这张牌桌的发牌安排
+///
+/// 同一场复式对局的牌桌使用同一个种子，第 n 局都用同样洗好的一副牌。
+/// 第 `table` 张牌桌把底牌按行动顺序轮转 `table` 个位置: 第 i 位拿到第 0 张牌桌上第 i + table 位的底牌，
+/// 同一批玩家坐同样的座位再打一遍时，每人拿到的是别人上次的牌。两桌人数相同时公共牌也完全相同。
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DuplicateDeal {
+    /// 复式对局的编号，其他牌桌的房主用它加入
+    pub match_id: Uuid,
+    /// 洗牌的种子，发给客户端时清零
+    pub seed: u64,
+    /// 这张牌桌在对局中的序号，从 0 开始
+    pub table: u8,
+    /// 加入对局后已经开始的局数，决定下一局用哪一副牌
+    pub hands: u32,
+}
+
+impl DuplicateDeal {
+    /// 第 `hand` 局使用的牌堆的种子
+    pub fn hand_seed(&self, hand: u32) -> u64 {
+        self.seed ^ (hand as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+/// Sit & Go 的进度
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SitAndGoProgress {
+    /// 第一局开始时发牌的玩家，每人交了一份买入
+    pub entrants: Vec<PlayerId>,
+    /// 按淘汰的先后排列的参赛者
+    pub eliminated: Vec<PlayerId>,
+    /// 比赛已经决出第一名
+    pub finished: bool,
+}
+
+impl SitAndGoProgress {
+    /// 比赛已经开始且还没有结束
+    pub fn is_running(&self) -> bool {
+        !self.entrants.is_empty() && !self.finished
+    }
+
+    /// 淘汰的参赛者的名次: 第一个被淘汰的是最后一名
+    pub fn place_of(&self, player_id: &PlayerId) -> Option<u32> {
+        let i = self.eliminated.iter().position(|id| id == player_id)?;
+        Some((self.entrants.len() - i) as u32)
+    }
+}
+
+/// 锦标赛的盲注结构: 每进行 `hands_per_level` 局，盲注升到下一级。
+/// `level_secs` 大于 0 时改为按时间升级，级别到时后在两局之间升级
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TournamentStructure {
+    pub hands_per_level: u32,
+    #[serde(default)]
+    pub level_secs: u32,
+    pub levels: Vec<BlindLevel>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlindLevel {
+    pub small_blind: u32,
+    pub big_blind: u32,
+}
+
+impl BlindLevel {
+    /// 该级别仍然需要的最小筹码面值，更小的筹码在升级时通过换色 (chip race) 移除
+    pub fn chip_unit(&self) -> u32 {
+        let (mut a, mut b) = (self.small_blind, self.big_blind);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        a.max(1)
+    }
+}
+
+/// 混合玩法轮换: 按顺序轮流进行 `variants` 中的玩法 (如德州扑克和奥马哈交替)。
+/// `hands_per_game` 大于 0 时每个玩法进行这么多局，为 0 时每个玩法进行一圈 (每位玩家当一次庄家)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct VariantRotation {
+    pub variants: Vec<GameVariant>,
+    pub hands_per_game: u32,
+}
+
+/// 混合玩法轮换的进度
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RotationProgress {
+    /// 开始这一段轮换时的设置，房主修改设置后从头开始轮换
+    pub rotation: VariantRotation,
+    /// 当前玩法在 `rotation.variants` 中的位置
+    pub index: usize,
+    /// 当前玩法还剩几局
+    pub hands_left: u32,
+}
+
+impl RotationProgress {
+    /// 当前玩法用完后的下一个玩法
+    pub fn next_variant(&self) -> GameVariant {
+        self.rotation.variants[(self.index + 1) % self.rotation.variants.len()]
+    }
+}
+
+/// 全下保险的报价: 领先的一方 (`buyer`) 可以向落后的一方 (`seller`) 买不超过 `max_cover` 的保额。
+/// `equity_permille` 是发牌前领先一方的胜率 (千分比，平分按一半计入)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InsuranceOffer {
+    pub buyer: PlayerId,
+    pub seller: PlayerId,
+    pub equity_permille: u32,
+    pub max_cover: u32,
+}
+
+impl InsuranceOffer {
+    /// 保额 `cover` 按公平赔率对应的保费 (向上取整): 保费 × 胜率 = 保额 × (1 - 胜率)
+    pub fn premium(&self, cover: u32) -> u32 {
+        let e = self.equity_permille as u64;
+        (cover as u64 * (1000 - e)).div_ceil(e) as u32
+    }
+}
+
+/// 买下的保险: 摊牌时买方输了，卖方付给买方 `cover`；买方赢了，买方付给卖方 `premium`；
+/// 平分奖池时双方各承担一半，卖方付给买方 (`cover` - `premium`) / 2
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InsurancePolicy {
+    pub buyer: PlayerId,
+    pub seller: PlayerId,
+    pub cover: u32,
+    pub premium: u32,
+}
+
+/// 本局全下保险的状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Insurance {
+    /// 等待领先的一方决定是否购买，期间暂停发牌
+    Offered(InsuranceOffer),
+    Bought(InsurancePolicy),
+    Declined,
+}
+
+/// 单个房间选项的修改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RoomOption {
+    RevealFoldedHands(bool),
+    BombPot(u32),
+    BombPotEvery(u32),
+    Ante(u32),
+    BigBlindAnte(bool),
+    Straddle(Option<StraddlePosition>),
+    DoubleBoard(bool),
+    Variant(GameVariant),
+    BettingStructure(BettingStructure),
+    DealersChoice(Vec<GameVariant>),
+    Rotation(Option<VariantRotation>),
+    UndoGrace(u64),
+    ConfirmActions(bool),
+    Tournament(Option<TournamentStructure>),
+    SessionFee(u32),
+    ChipAccounts(bool),
+    RequireJoinApproval(bool),
+    MaxSpectators(u32),
+    AutoFoldDisabled(bool),
+    TurnTimer(u32),
+    TimeBank(u32),
+    Correspondence(bool),
+    SitAndGo(Option<SitAndGo>),
+    Democracy(bool),
+    SpectatorGuessing(bool),
+    Insurance(bool),
+    SevenDeuceBounty(u32),
+    RatholeWindow(u32),
+}
+
+impl RoomOptions {
+    pub fn apply(&mut self, option: RoomOption) {
+        match option {
+            RoomOption::RevealFoldedHands(v) => self.reveal_folded_hands = v,
+            RoomOption::BombPot(ante) => self.bomb_pot_ante = ante,
+            RoomOption::BombPotEvery(hands) => self.bomb_pot_every = hands,
+            RoomOption::Ante(ante) => self.ante = ante,
+            RoomOption::BigBlindAnte(v) => self.big_blind_ante = v,
+            RoomOption::Straddle(position) => self.straddle = position,
+            RoomOption::DoubleBoard(v) => self.double_board = v,
+            RoomOption::Variant(v) => self.variant = v,
+            RoomOption::BettingStructure(v) => self.betting_structure = v,
+            RoomOption::DealersChoice(list) => self.dealers_choice = list,
+            RoomOption::Rotation(rotation) => self.rotation = rotation,
+            RoomOption::UndoGrace(ms) => self.undo_grace_ms = ms,
+            RoomOption::ConfirmActions(v) => self.confirm_actions = v,
+            RoomOption::Tournament(structure) => self.tournament = structure,
+            RoomOption::SessionFee(fee) => self.session_fee = fee,
+            RoomOption::ChipAccounts(v) => self.chip_accounts = v,
+            RoomOption::RequireJoinApproval(v) => self.require_join_approval = v,
+            RoomOption::MaxSpectators(n) => self.max_spectators = n,
+            RoomOption::AutoFoldDisabled(v) => self.auto_fold_disabled = v,
+            RoomOption::TurnTimer(secs) => self.turn_timer_secs = secs,
+            RoomOption::TimeBank(secs) => self.time_bank_secs = secs,
+            RoomOption::Correspondence(v) => self.correspondence = v,
+            RoomOption::SitAndGo(sng) => self.sit_and_go = sng,
+            RoomOption::Democracy(v) => self.democracy = v,
+            RoomOption::SpectatorGuessing(v) => self.spectator_guessing = v,
+            RoomOption::Insurance(v) => self.insurance = v,
+            RoomOption::SevenDeuceBounty(v) => self.seven_deuce_bounty = v,
+            RoomOption::RatholeWindow(mins) => self.rathole_window_mins = mins,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -88,6 +798,8 @@ pub enum GamePhase {
     Flop,
     Turn,
     River,
+    Draw,      // 换牌阶段 (换牌玩法)
+    AfterDraw, // 换牌后的下注轮 (换牌玩法)
     Showdown, // 一局结束，结算完成
 }
 
@@ -99,6 +811,8 @@ impl Display for GamePhase {
             GamePhase::Flop => write!(f, "发牌"),
             GamePhase::Turn => write!(f, "转牌"),
             GamePhase::River => write!(f, "河牌"),
+            GamePhase::Draw => write!(f, "换牌"),
+            GamePhase::AfterDraw => write!(f, "换牌后"),
             GamePhase::Showdown => write!(f, "摊牌"),
         }
     }
@@ -112,6 +826,41 @@ pub enum PlayerAction {
     Fold,      // 弃牌
 }
 
+/// 房主账本，记录玩家自愿给的小费和场地费 (用于零食、场地等开销)，以及玩家兑现离场时带走的筹码
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HostLedger {
+    pub entries: Vec<LedgerEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LedgerEntry {
+    pub player_id: PlayerId,
+    pub kind: LedgerKind,
+    pub amount: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum LedgerKind {
+    /// 玩家自愿给的小费
+    Tip,
+    /// 固定场地费
+    SessionFee,
+    /// 玩家兑现离场带走的筹码，不是房主的收入
+    CashOut,
+}
+
+impl HostLedger {
+    /// 某一类收入的合计
+    pub fn total(&self, kind: LedgerKind) -> u32 {
+        self.entries.iter().filter(|e| e.kind == kind).map(|e| e.amount).sum()
+    }
+
+    /// 玩家是否已经交过场地费
+    pub fn has_paid_fee(&self, player_id: &PlayerId) -> bool {
+        self.entries.iter().any(|e| e.kind == LedgerKind::SessionFee && e.player_id == *player_id)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum PlayerState {
     /// 等待新牌局: 已入座，等待下一局开始后发牌。
@@ -138,6 +887,69 @@ impl Display for PlayerState {
     }
 }
 
+impl Player {
+    /// 创建一个刚进入房间、尚未入座的玩家
+    pub fn new(id: PlayerId, nickname: String) -> Self {
+        Self {
+            id,
+            nickname,
+            stack: 0,
+            wins: 0,
+            losses: 0,
+            state: PlayerState::SittingOut,
+            seat_id: None,
+            is_offline: false,
+            missed_blinds: MissedBlinds::None,
+            post_dead_blind: false,
+            sit_out: false,
+            post_straddle: false,
+            auto_muck: false,
+            auto_fold_below: 0,
+            pot_tip: 0,
+            bank: 0,
+            time_bank: 0,
+            buy_in: 0,
+            cashed_out: 0,
+            profile: PlayerProfile::default(),
+        }
+    }
+
+    /// 错过了盲注且没有选择补交死盲的玩家只能在大盲位入局
+    pub fn waits_for_big_blind(&self) -> bool {
+        self.missed_blinds != MissedBlinds::None && !self.post_dead_blind
+    }
+
+    /// 去掉只有玩家自己能看到的信息: 账户余额和可能透露牌力的自动弃牌、自动盖牌设置
+    pub fn scrub_private(&mut self) {
+        self.bank = 0;
+        self.auto_fold_below = 0;
+        self.auto_muck = false;
+    }
+}
+
+/// 玩家本轮下注额的构成
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreetBet {
+    /// 不计入下注额的死筹码 (前注、死的小盲)
+    pub dead: u32,
+    /// 强制下的活注 (盲注、补交的大盲、抓位)
+    pub forced: u32,
+    /// 强制下注是否是抓位
+    pub straddle: bool,
+    /// 玩家主动跟注、下注或加注的部分
+    pub voluntary: u32,
+}
+
+/// 状态快照的接收者，决定快照中可以包含哪些信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audience {
+    /// 房间里的玩家 (包括还没入座的)，能看到自己的底牌和自己的私人设置
+    Player(PlayerId),
+    /// 事件流订阅者、直播叠加层等旁观者: 看不到任何没亮出的底牌，
+    /// 也看不到玩家的账户余额、自动弃牌和自动盖牌等可能透露牌力的设置
+    Spectator,
+}
+
 // --- GameState 的实现方法 ---
 
 impl Default for GameState {
@@ -149,60 +961,232 @@ impl Default for GameState {
             hand_player_order: vec![],
             player_indices: HashMap::new(),
             phase: GamePhase::WaitingForPlayers,
+            variant: GameVariant::default(),
+            betting_structure: BettingStructure::default(),
+            bomb_pot: false,
+            chosen_variant: None,
+            rotation: None,
+            insurance: None,
             pot: 0,
-            community_cards: vec![None; 5],
+            community_cards: vec![vec![None; 5]],
             deck: vec![],
-            player_cards: vec![(None, None); 5],
+            player_cards: vec![vec![None; 2]; 5],
+            mucked: vec![],
+            shown: vec![],
+            shown_cards: HashMap::new(),
+            showdown_queue: vec![],
             bets: vec![],
+            dead_bets: vec![],
+            forced_bets: vec![],
+            street_levels: vec![],
+            starting_stacks: vec![],
+            dead_money: 0,
+            straddle: 0,
             player_has_acted: vec![],
             cur_player_idx: 0,
             max_bet: 0,
             last_bet: 0,
             last_raise_amount: 0,
+            last_aggressor: None,
+            button: None,
             small_blind: 100,
             big_blind: 200,
             seats: 10,
+            room_options: RoomOptions::default(),
+            config: RoomConfig::default(),
+            tournament_level: 0,
+            level_hands: 0,
+            level_ends_at: None,
+            host_ledger: HostLedger::default(),
+            sit_and_go: SitAndGoProgress::default(),
+            seat_ledger: HashMap::new(),
+            duplicate: None,
+            bomb_pot_queued: false,
+            hands_since_bomb_pot: 0,
+            vote: None,
+            spectator_guesses: SpectatorGuesses::default(),
         }
     }
 }
 
 impl GameState {
+    /// 按创建房间时的配置设置盲注、座位数和计时
+    pub fn configure(&mut self, config: RoomConfig) {
+        self.small_blind = config.small_blind;
+        self.big_blind = config.big_blind;
+        self.seats = config.seats;
+        self.room_options.turn_timer_secs = config.turn_timer_secs;
+        self.room_options.time_bank_secs = config.time_bank_secs;
+        self.config = config;
+    }
+
     /// 获取当前行动的玩家ID (如果存在)
     pub fn current_player_id(&self) -> Option<PlayerId> {
         self.hand_player_order.get(self.cur_player_idx).copied()
     }
 
+    /// 坐在庄位上的玩家。死庄时庄位上没有玩家，返回 None
+    pub fn dealer_id(&self) -> Option<PlayerId> {
+        let first = *self.hand_player_order.first()?;
+        match self.button {
+            Some(button) if self.players.get(&first)?.seat_id != Some(button.button) => None,
+            _ => Some(first),
+        }
+    }
+
     pub fn get_players_in_hand(&self) -> Vec<PlayerId> {
         self.hand_player_order
             .iter()
             .filter(|id| {
-                let player = self.players.get(id).unwrap();
-                matches!(player.state, PlayerState::Playing | PlayerState::AllIn)
+                self.players
+                    .get(id)
+                    .is_some_and(|p| matches!(p.state, PlayerState::Playing | PlayerState::AllIn))
             })
             .cloned()
             .collect()
     }
 
-    pub fn for_client(&self, client_id: &PlayerId) -> Self {
+    /// 第 `idx` 位玩家本轮的下注中，强制下注和主动下注各占多少，客户端的下注列据此分开显示
+    pub fn street_bet(&self, idx: usize) -> StreetBet {
+        let bet = self.bets.get(idx).copied().unwrap_or(0).saturating_sub(self.last_bet);
+        // 强制下注只出现在第一轮下注中，之后每一轮都从 0 开始
+        let first_round = self.last_bet == 0;
+        let forced = if first_round { self.forced_bets.get(idx).copied().unwrap_or(0).min(bet) } else { 0 };
+        StreetBet {
+            dead: if first_round { self.dead_bets.get(idx).copied().unwrap_or(0) } else { 0 },
+            forced,
+            straddle: forced > 0 && forced == self.straddle,
+            voluntary: bet - forced,
+        }
+    }
+
+    /// 第 `idx` 位玩家本局每一轮下注的金额，按下注轮的顺序排列到当前这一轮，不含前注和死盲
+    pub fn street_history(&self, idx: usize) -> Vec<u32> {
+        let bet = self.bets.get(idx).copied().unwrap_or(0);
+        let mut lower = 0;
+        let mut history = vec![];
+        for &level in self.street_levels.iter() {
+            history.push(bet.min(level).saturating_sub(lower));
+            lower = level;
+        }
+        history.push(bet.saturating_sub(lower));
+        history
+    }
+
+    /// 第 `idx` 位玩家本局投入奖池的全部筹码: 每一轮的下注加上前注和死盲，大盲前注算在大盲身上
+    pub fn hand_invested(&self, idx: usize) -> u32 {
+        let bet = self.bets.get(idx).copied().unwrap_or(0) + self.dead_bets.get(idx).copied().unwrap_or(0);
+        if idx == self.big_blind_idx() { bet + self.dead_money } else { bet }
+    }
+
+    /// 本局玩家顺序中大盲的位置
+    pub(crate) fn big_blind_idx(&self) -> usize {
+        let n = self.hand_player_order.len();
+        if n == 2 { 1 } else { 2 % n.max(1) }
+    }
+
+    /// 玩家现在需要跟注的金额，不超过他剩余的筹码。前注和死盲不计入下注额，不会减少跟注额
+    pub fn to_call(&self, player_id: &PlayerId) -> u32 {
+        let (Some(idx), Some(player)) = (self.player_indices.get(player_id), self.players.get(player_id)) else { return 0 };
+        self.max_bet.saturating_sub(self.bets.get(*idx).copied().unwrap_or(0)).min(player.stack)
+    }
+
+    /// 两位玩家之间的有效筹码 (双方剩余筹码中较小的一方)，以大盲为单位。
+    /// 任一玩家不存在或没有大盲时返回 None
+    pub fn effective_stack_bb(&self, a: &PlayerId, b: &PlayerId) -> Option<f64> {
+        let (Some(a), Some(b)) = (self.players.get(a), self.players.get(b)) else { return None };
+        (self.big_blind > 0).then(|| a.stack.min(b.stack) as f64 / self.big_blind as f64)
+    }
+
+    /// 玩家的筹码底池比 (SPR): 有效筹码 (自己剩余的筹码，不超过牌局中其他玩家剩余筹码的最大值) 除以奖池。
+    /// 玩家不在牌局中或奖池为空时返回 None
+    pub fn stack_to_pot(&self, player_id: &PlayerId) -> Option<f64> {
+        let stack = self.players.get(player_id).filter(|_| self.player_indices.contains_key(player_id))?.stack;
+        let covered = self.hand_player_order.iter()
+            .filter(|id| *id != player_id)
+            .filter_map(|id| self.players.get(id))
+            .filter(|p| matches!(p.state, PlayerState::Playing | PlayerState::AllIn))
+            .map(|p| p.stack)
+            .max()
+            .unwrap_or(0);
+        (self.pot > 0).then(|| stack.min(covered) as f64 / self.pot as f64)
+    }
+
+    /// 玩家本局已经投入奖池的筹码占开局时筹码的比例 (0 到 1)，玩家不在牌局中时返回 None
+    pub fn pot_commitment(&self, player_id: &PlayerId) -> Option<f64> {
+        let idx = *self.player_indices.get(player_id)?;
+        let invested = self.hand_invested(idx);
+        let total = invested + self.players.get(player_id)?.stack;
+        (total > 0).then(|| invested as f64 / total as f64)
+    }
+
+    /// 本局玩家顺序中第 `idx` 位玩家的底牌是否已经公开亮出
+    pub fn is_shown(&self, idx: usize) -> bool {
+        self.shown.get(idx).copied().unwrap_or(false)
+    }
+
+    /// 记录第 `idx` 位玩家公开亮出了底牌
+    pub(crate) fn mark_shown(&mut self, idx: usize) {
+        if self.shown.len() <= idx {
+            self.shown.resize(self.hand_player_order.len().max(idx + 1), false);
+        }
+        self.shown[idx] = true;
+    }
+
+    /// 记录玩家亮出了手牌中 `positions` 位置的底牌，全部亮出时等同于亮出整手牌
+    pub(crate) fn mark_cards_shown(&mut self, player_id: PlayerId, positions: &[usize]) {
+        let Some(&idx) = self.player_indices.get(&player_id) else { return };
+        let shown = self.shown_cards.entry(player_id).or_default();
+        for &pos in positions {
+            if !shown.contains(&pos) {
+                shown.push(pos);
+            }
+        }
+        let hand_len = self.player_cards.get(idx).map_or(0, Vec::len);
+        if (0..hand_len).all(|pos| shown.contains(&pos)) {
+            self.mark_shown(idx);
+        }
+    }
+
+    /// 剩余的牌堆，服务器保存进行中的牌局时加密写入存档
+    pub fn deck(&self) -> &[Card] {
+        &self.deck
+    }
+
+    /// 从存档恢复进行中的牌局时放回剩余的牌堆
+    pub fn restore_deck(&mut self, deck: Vec<Card>) {
+        self.deck = deck;
+    }
+
+    /// 按接收者的视角净化状态: 去掉牌堆、接收者不应该看到的底牌和其他玩家的私人设置
+    pub fn for_client(&self, audience: Audience) -> Self {
         let mut client_state = self.clone();
         client_state.deck.clear();
+        client_state.seat_ledger.clear();
+        if let Some(duplicate) = &mut client_state.duplicate {
+            duplicate.seed = 0;
+        }
 
         // 获取当前客户端在牌局中的索引
-        let client_idx_opt = self.player_indices.get(client_id).copied();
-
-        if self.phase == GamePhase::Showdown {
-            let players_in_hand_set: std::collections::HashSet<_> = self.get_players_in_hand().into_iter().collect();
-
-            for (i, cards) in client_state.player_cards.iter_mut().enumerate() {
-                let player_id = &self.hand_player_order[i];
-                if !players_in_hand_set.contains(player_id) && Some(i) != client_idx_opt {
-                    *cards = (None, None);
-                }
+        let client_id = match audience {
+            Audience::Player(client_id) => Some(client_id),
+            Audience::Spectator => None,
+        };
+        let client_idx_opt = client_id.and_then(|id| self.player_indices.get(&id).copied());
+        for player in client_state.players.values_mut() {
+            if Some(player.id) != client_id {
+                player.scrub_private();
             }
-        } else {
-            for (i, cards) in client_state.player_cards.iter_mut().enumerate() {
-                if Some(i) != client_idx_opt {
-                    *cards = (None, None);
+        }
+
+        // 只保留自己的底牌和已经公开亮出的底牌，摊牌时盖掉或还没决定亮不亮的牌都不可见
+        for (i, cards) in client_state.player_cards.iter_mut().enumerate() {
+            if Some(i) != client_idx_opt && !self.is_shown(i) {
+                let shown = self.hand_player_order.get(i).and_then(|id| self.shown_cards.get(id));
+                for (pos, card) in cards.iter_mut().enumerate() {
+                    if !shown.is_some_and(|s| s.contains(&pos)) {
+                        *card = None;
+                    }
                 }
             }
         }
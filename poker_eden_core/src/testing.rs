@@ -0,0 +1,232 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 基于 proptest 的属性测试工具 (需要开启 `testing` feature)
+//!
+//! 生成随机的牌桌和合法动作序列，驱动 `GameState` 打完一局，并在每一步检查不变量:
+//! - 筹码守恒: 所有玩家的筹码加上底池始终等于开局时的总筹码
+//! - 合法动作: `NextToAct` 给出的动作都能被执行，且给出的玩家就是当前行动的玩家
+//! - 必然结束: 有限步之内到达摊牌
+//!
+//! 下游可以用自己的 `RoomOptions` (玩法、炸弹底池等) 调用 [`play_hand`] 验证自己的配置。
+//! 锦标赛的筹码比赛和房主账本会改变玩家的总筹码，不适用于筹码守恒检查。
+
+use chrono::{DateTime, Utc};
+use crate::error::{GameError, GameResult};
+use crate::message::{PlayerActionType, ServerMessage};
+use crate::state::*;
+use proptest::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use uuid::Uuid;
+
+/// 一局牌最多执行的步数，超过即认为牌局无法结束
+pub const MAX_STEPS: usize = 1000;
+
+/// 随机动作序列中的一步。实际执行的动作取决于当时的合法动作:
+/// `pick` 选择合法动作中的一个，`amount` 决定下注额，`discard_mask` 决定换哪几张牌
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionChoice {
+    pub pick: usize,
+    pub amount: u32,
+    pub discard_mask: u8,
+}
+
+/// 打牌过程中发现的不变量违规
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    /// 开局失败
+    StartFailed(GameError),
+    /// 筹码总数发生了变化
+    ChipsNotConserved { expected: u64, actual: u64 },
+    /// 牌局进行中，却没有告知下一个行动的玩家
+    NoNextToAct,
+    /// `NextToAct` 中的玩家不是当前行动的玩家
+    WrongActor { expected: Option<PlayerId>, actual: PlayerId },
+    /// `NextToAct` 给出的合法动作被拒绝
+    LegalActionRejected { action: PlayerActionType, error: GameError },
+    /// 超过 `MAX_STEPS` 步仍未结束
+    NoTermination,
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvariantViolation::StartFailed(error) => write!(f, "开局失败: {}", error),
+            InvariantViolation::ChipsNotConserved { expected, actual } => {
+                write!(f, "筹码不守恒: 应为 {}，实际为 {}", expected, actual)
+            }
+            InvariantViolation::NoNextToAct => write!(f, "牌局进行中，但没有轮到任何玩家行动"),
+            InvariantViolation::WrongActor { expected, actual } => {
+                write!(f, "轮到 {:?} 行动，但 NextToAct 给出的是 {}", expected, actual)
+            }
+            InvariantViolation::LegalActionRejected { action, error } => {
+                write!(f, "合法动作 {:?} 被拒绝: {}", action, error)
+            }
+            InvariantViolation::NoTermination => write!(f, "{} 步之内牌局没有结束", MAX_STEPS),
+        }
+    }
+}
+
+/// 创建一张已就座、等待开局的牌桌，盲注为 10/20
+pub fn setup_table(stacks: &[u32], room_options: RoomOptions) -> GameState {
+    let mut players = HashMap::new();
+    let mut seated_players = VecDeque::new();
+    for (seat, &stack) in stacks.iter().enumerate() {
+        let player_id = Uuid::new_v4();
+        let player = Player {
+            stack,
+            state: PlayerState::Waiting,
+            seat_id: Some(seat as u8),
+            ..Player::new(player_id, format!("Player_{}", seat))
+        };
+        players.insert(player_id, player);
+        seated_players.push_back(player_id);
+    }
+    GameState {
+        players,
+        seated_players,
+        small_blind: 10,
+        big_blind: 20,
+        room_options,
+        ..Default::default()
+    }
+}
+
+/// 所有玩家的筹码加上底池
+pub fn total_chips(state: &GameState) -> u64 {
+    state.players.values().map(|p| p.stack as u64).sum::<u64>() + state.pot as u64
+}
+
+/// 开始新的一局，按 `choices` 依次选择合法动作打到摊牌，每一步都检查不变量。
+/// `choices` 用完后从头循环使用；牌局没能开始 (如人数不足) 时直接返回 `Ok`。
+pub fn play_hand(state: &mut GameState, choices: &[ActionChoice]) -> Result<(), InvariantViolation> {
+    let expected = total_chips(state);
+    let now = Utc::now();
+    let mut messages = state.start_new_hand(now).map_err(InvariantViolation::StartFailed)?;
+    check_chips(state, expected)?;
+
+    for step in 0..MAX_STEPS {
+        if matches!(state.phase, GamePhase::WaitingForPlayers | GamePhase::Showdown) {
+            return Ok(());
+        }
+        let Some((player_id, valid_actions)) = last_next_to_act(&messages) else {
+            return Err(InvariantViolation::NoNextToAct);
+        };
+        if state.current_player_id() != Some(player_id) {
+            return Err(InvariantViolation::WrongActor { expected: state.current_player_id(), actual: player_id });
+        }
+
+        let choice = choices.get(step % choices.len().max(1)).copied().unwrap_or(ActionChoice {
+            pick: 0,
+            amount: 0,
+            discard_mask: 0,
+        });
+        let action = valid_actions[choice.pick % valid_actions.len()].clone();
+        messages = perform(state, player_id, &action, choice, now)
+            .map_err(|error| InvariantViolation::LegalActionRejected { action, error })?;
+        check_chips(state, expected)?;
+    }
+    Err(InvariantViolation::NoTermination)
+}
+
+/// 把合法的动作类型转换成具体的动作并执行
+fn perform(state: &mut GameState, player_id: PlayerId, action: &PlayerActionType, choice: ActionChoice, now: DateTime<Utc>) -> GameResult {
+    let action = match *action {
+        PlayerActionType::Fold => PlayerAction::Fold,
+        PlayerActionType::Check => PlayerAction::Check,
+        PlayerActionType::Call(_) => PlayerAction::Call,
+        // 在最小额和上限 (全下或底池限注) 之间随机选择
+        PlayerActionType::Bet { min, max } | PlayerActionType::Raise { min, max } => {
+            PlayerAction::BetOrRaise(min + choice.amount % (max - min + 1))
+        }
+        PlayerActionType::Discard(max) => {
+            let indices = (0..max).filter(|i| choice.discard_mask & (1 << i) != 0).collect();
+            return state.handle_discard(player_id, indices, now);
+        }
+    };
+    state.handle_player_action(player_id, action, now)
+}
+
+fn last_next_to_act(messages: &[ServerMessage]) -> Option<(PlayerId, Vec<PlayerActionType>)> {
+    messages.iter().rev().find_map(|msg| match msg {
+        ServerMessage::NextToAct { player_id, valid_actions, .. } => Some((*player_id, valid_actions.clone())),
+        _ => None,
+    })
+}
+
+fn check_chips(state: &GameState, expected: u64) -> Result<(), InvariantViolation> {
+    let actual = total_chips(state);
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(InvariantViolation::ChipsNotConserved { expected, actual })
+    }
+}
+
+/// 随机的动作选择
+pub fn action_choice() -> impl Strategy<Value = ActionChoice> {
+    (0..8usize, any::<u32>(), any::<u8>()).prop_map(|(pick, amount, discard_mask)| ActionChoice {
+        pick,
+        amount,
+        discard_mask,
+    })
+}
+
+/// 随机的动作序列
+pub fn action_choices() -> impl Strategy<Value = Vec<ActionChoice>> {
+    prop::collection::vec(action_choice(), 1..64)
+}
+
+/// 2 到 `max_players` 名玩家的随机筹码，包含筹码少于盲注的短码
+pub fn table_stacks(max_players: usize) -> impl Strategy<Value = Vec<u32>> {
+    prop::collection::vec(1..5000u32, 2..=max_players)
+}
+
+/// 随机的玩法配置: 三种玩法，可能开启炸弹底池和双牌面
+pub fn room_options() -> impl Strategy<Value = RoomOptions> {
+    let variant = prop_oneof![
+        Just(GameVariant::TexasHoldem),
+        Just(GameVariant::OmahaHiLo),
+        Just(GameVariant::FiveCardDraw),
+    ];
+    (variant, prop_oneof![Just(0u32), 1..100u32], any::<bool>()).prop_map(|(variant, bomb_pot_ante, double_board)| {
+        RoomOptions { variant, bomb_pot_ante, bomb_pot_every: 1, double_board, ..Default::default() }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn prop_random_hands_keep_invariants(
+            stacks in table_stacks(10),
+            options in room_options(),
+            hands in prop::collection::vec(action_choices(), 1..4),
+        ) {
+            // 连续打几局，后面的局会遇到筹码变化后的短码和离席玩家
+            let mut state = setup_table(&stacks, options);
+            for choices in &hands {
+                if let Err(violation) = play_hand(&mut state, choices) {
+                    prop_assert!(false, "{}", violation);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+use crate::error::{GameError, GameResult};
+use crate::message::ServerMessage;
+use crate::state::{GameState, PlayerId, RoomOption};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 投票的时限 (秒)，到时还没有结果的投票视为否决
+pub const VOTE_DURATION_SECS: i64 = 60;
+
+/// 民主模式下需要投票决定的操作
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum VoteProposal {
+    /// 把玩家移出房间，被移出的玩家不能再用原来的凭证重连
+    Kick(PlayerId),
+    /// 作废进行中的牌局，退还每位玩家本局投入的筹码
+    VoidHand,
+    /// 修改盲注
+    SetBlinds { small_blind: u32, big_blind: u32 },
+    /// 修改房间选项
+    SetRoomOption(RoomOption),
+    /// 下一局打一次炸弹底池
+    BombPotNextHand,
+}
+
+/// 一次投票。只有发起时在线入座的玩家可以投票，发起者自动投赞成票
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Vote {
+    pub id: Uuid,
+    pub proposer: PlayerId,
+    pub proposal: VoteProposal,
+    pub electorate: Vec<PlayerId>,
+    pub yes: Vec<PlayerId>,
+    pub no: Vec<PlayerId>,
+    pub deadline: DateTime<Utc>,
+}
+
+impl Vote {
+    /// 赞成票过半数时通过，反对票多到不可能过半数时否决，否则还没有结果
+    pub fn outcome(&self) -> Option<bool> {
+        let majority = self.electorate.len() / 2 + 1;
+        if self.yes.len() >= majority {
+            Some(true)
+        } else if self.electorate.len() - self.no.len() < majority {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+impl GameState {
+    /// 在线入座的玩家发起投票。同一时间只能有一个投票
+    pub fn propose_vote(&mut self, player_id: PlayerId, proposal: VoteProposal, now: DateTime<Utc>) -> GameResult {
+        if !self.room_options.democracy {
+            return Err(GameError::DemocracyDisabled);
+        }
+        if self.vote.is_some() {
+            return Err(GameError::VoteInProgress);
+        }
+        let electorate: Vec<PlayerId> = self.players.values()
+            .filter(|p| p.seat_id.is_some() && !p.is_offline)
+            .map(|p| p.id)
+            .collect();
+        if !electorate.contains(&player_id) {
+            return Err(GameError::NotSeated);
+        }
+        match &proposal {
+            VoteProposal::Kick(target) if !self.players.contains_key(target) => return Err(GameError::PlayerNotFound),
+            VoteProposal::VoidHand if !self.hand_in_progress() => return Err(GameError::NoHandInProgress),
+            VoteProposal::SetBlinds { small_blind, big_blind } => self.check_blinds(*small_blind, *big_blind)?,
+            VoteProposal::SetRoomOption(RoomOption::SitAndGo(Some(sng))) => sng.validate()?,
+            _ => {}
+        }
+        let vote = Vote {
+            id: Uuid::new_v4(),
+            proposer: player_id,
+            proposal,
+            electorate,
+            yes: vec![player_id],
+            no: vec![],
+            deadline: now + Duration::seconds(VOTE_DURATION_SECS),
+        };
+        self.vote = Some(vote.clone());
+        Ok(vec![ServerMessage::VoteStarted { vote, server_time: now }])
+    }
+
+    /// 对进行中的投票投票，每人只能投一次
+    pub fn cast_vote(&mut self, player_id: PlayerId, vote_id: Uuid, approve: bool) -> GameResult {
+        let vote = self.vote.as_mut().filter(|v| v.id == vote_id).ok_or(GameError::NoActiveVote)?;
+        if !vote.electorate.contains(&player_id) {
+            return Err(GameError::NotElector);
+        }
+        if vote.yes.contains(&player_id) || vote.no.contains(&player_id) {
+            return Err(GameError::AlreadyVoted);
+        }
+        if approve {
+            vote.yes.push(player_id);
+        } else {
+            vote.no.push(player_id);
+        }
+        Ok(vec![ServerMessage::VoteCast { vote_id, player_id, approve }])
+    }
+
+    /// 进行中的投票是否已经有结果
+    pub fn vote_outcome(&self) -> Option<bool> {
+        self.vote.as_ref().and_then(Vote::outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{Player, SitAndGo};
+
+    fn seated_state(n: usize) -> (GameState, Vec<PlayerId>) {
+        let mut state = GameState::default();
+        let ids: Vec<PlayerId> = (0..n).map(|_| Uuid::new_v4()).collect();
+        for (i, id) in ids.iter().enumerate() {
+            let player = Player { seat_id: Some(i as u8), stack: 1000, ..Player::new(*id, format!("P{}", i)) };
+            state.players.insert(*id, player);
+        }
+        state.room_options.democracy = true;
+        (state, ids)
+    }
+
+    #[test]
+    fn test_vote_needs_majority_of_seated_players() {
+        let (mut state, ids) = seated_state(4);
+        state.players.get_mut(&ids[3]).unwrap().is_offline = true;
+        let proposal = VoteProposal::SetBlinds { small_blind: 25, big_blind: 50 };
+        state.propose_vote(ids[0], proposal.clone(), Utc::now()).unwrap();
+        assert_eq!(state.propose_vote(ids[1], proposal, Utc::now()).unwrap_err(), GameError::VoteInProgress);
+
+        // 离线的玩家不能投票，三名在线玩家中需要两票赞成
+        let vote_id = state.vote.as_ref().unwrap().id;
+        assert_eq!(state.vote.as_ref().unwrap().electorate.len(), 3);
+        assert_eq!(state.cast_vote(ids[3], vote_id, true).unwrap_err(), GameError::NotElector);
+        assert_eq!(state.cast_vote(ids[0], vote_id, true).unwrap_err(), GameError::AlreadyVoted);
+        assert_eq!(state.vote_outcome(), None);
+        state.cast_vote(ids[1], vote_id, true).unwrap();
+        assert_eq!(state.vote_outcome(), Some(true));
+    }
+
+    #[test]
+    fn test_vote_rejected_when_majority_impossible() {
+        let (mut state, ids) = seated_state(4);
+        assert_eq!(state.propose_vote(ids[0], VoteProposal::VoidHand, Utc::now()).unwrap_err(), GameError::NoHandInProgress);
+        state.propose_vote(ids[0], VoteProposal::Kick(ids[3]), Utc::now()).unwrap();
+        let vote_id = state.vote.as_ref().unwrap().id;
+        state.cast_vote(ids[3], vote_id, false).unwrap();
+        assert_eq!(state.vote_outcome(), None);
+        // 四人中两票反对，赞成票不可能达到三票
+        state.cast_vote(ids[2], vote_id, false).unwrap();
+        assert_eq!(state.vote_outcome(), Some(false));
+
+        state.vote = None;
+        // 不合法的 Sit & Go 设置不能发起投票
+        let sng = SitAndGo { buy_in: 10, starting_stack: 1500, payouts: vec![60, 30] };
+        let proposal = VoteProposal::SetRoomOption(RoomOption::SitAndGo(Some(sng)));
+        assert_eq!(state.propose_vote(ids[0], proposal, Utc::now()).unwrap_err(), GameError::InvalidSitAndGoPayouts);
+
+        state.room_options.democracy = false;
+        assert_eq!(state.propose_vote(ids[0], VoteProposal::VoidHand, Utc::now()).unwrap_err(), GameError::DemocracyDisabled);
+    }
+}
@@ -0,0 +1,105 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 牌面绘制: 用矢量图形画出扑克牌，不依赖图片资源，窗口缩放时保持清晰
+
+use eframe::egui::{Align2, Color32, FontId, Painter, Rect, Response, Sense, Stroke, StrokeKind, Ui, Vec2};
+use poker_eden_core::{Card, Rank, Suit};
+
+/// 牌的高宽比
+const CARD_ASPECT: f32 = 1.4;
+
+/// 牌背的颜色
+const CARD_BACK: Color32 = Color32::from_rgb(40, 70, 140);
+
+/// 牌角上的点数，10 写成两位数，和实体牌一样
+pub fn rank_label(rank: Rank) -> String {
+    match rank {
+        Rank::Ten => "10".to_string(),
+        rank => rank.to_string(),
+    }
+}
+
+/// 花色符号，不带 emoji 变体选择符，避免按彩色 emoji 渲染
+pub fn suit_symbol(suit: Suit) -> &'static str {
+    match suit {
+        Suit::Spade => "♠",
+        Suit::Heart => "♥",
+        Suit::Club => "♣",
+        Suit::Diamond => "♦",
+    }
+}
+
+/// 红心和方块是红色，黑桃和梅花是黑色
+pub fn suit_color(suit: Suit) -> Color32 {
+    match suit {
+        Suit::Heart | Suit::Diamond => Color32::from_rgb(200, 30, 40),
+        Suit::Spade | Suit::Club => Color32::from_rgb(20, 20, 20),
+    }
+}
+
+/// 一张牌在给定宽度下的大小
+pub fn card_size(width: f32) -> Vec2 {
+    Vec2::new(width, width * CARD_ASPECT)
+}
+
+/// 在布局中放一张可以点击的牌。None 表示看不到的牌，画成牌背；
+/// `selected` 的牌向上抬起并加上高亮边框 (换牌时选中的牌)
+pub fn card(ui: &mut Ui, card: Option<Card>, width: f32, selected: bool) -> Response {
+    let (rect, response) = ui.allocate_exact_size(card_size(width), Sense::click());
+    if ui.is_rect_visible(rect) {
+        let rect = if selected { rect.translate(Vec2::new(0.0, -width * 0.15)) } else { rect };
+        paint_card(ui.painter(), rect, card, selected);
+    }
+    response
+}
+
+/// 在指定的位置画一张牌
+pub fn paint_card(painter: &Painter, rect: Rect, card: Option<Card>, selected: bool) {
+    let radius = rect.width() * 0.1;
+    match card {
+        Some(card) => {
+            let color = suit_color(card.suit);
+            painter.rect_filled(rect, radius, Color32::WHITE);
+            painter.text(rect.left_top() + Vec2::splat(rect.width() * 0.08), Align2::LEFT_TOP,
+                         rank_label(card.rank), FontId::proportional(rect.width() * 0.32), color);
+            painter.text(rect.center() + Vec2::new(0.0, rect.height() * 0.1), Align2::CENTER_CENTER,
+                         suit_symbol(card.suit), FontId::proportional(rect.width() * 0.55), color);
+        }
+        None => {
+            painter.rect_filled(rect, radius, CARD_BACK);
+            painter.rect_stroke(rect.shrink(rect.width() * 0.1), radius * 0.6,
+                                Stroke::new(1.0, Color32::from_rgb(200, 200, 230)), StrokeKind::Inside);
+        }
+    }
+    let border = if selected { Stroke::new(2.5, Color32::GOLD) } else { Stroke::new(1.0, Color32::DARK_GRAY) };
+    painter.rect_stroke(rect, radius, border, StrokeKind::Inside);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_face() {
+        assert_eq!(rank_label(Rank::Ten), "10");
+        assert_eq!(rank_label(Rank::Ace), "A");
+        assert_eq!(suit_color(Suit::Diamond), suit_color(Suit::Heart));
+        assert_ne!(suit_color(Suit::Club), suit_color(Suit::Heart));
+        assert_eq!(card_size(50.0), Vec2::new(50.0, 70.0));
+    }
+}
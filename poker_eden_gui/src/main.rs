@@ -0,0 +1,486 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 图形界面客户端
+//!
+//! 连接、会话状态和指令解析都来自 `poker_eden_client_core`，这里只负责窗口和绘制:
+//! 矢量绘制的扑克牌、可以点击的动作按钮和可以缩放的窗口，适合不习惯终端界面的玩家。
+
+use cards::card;
+use eframe::egui::{self, Color32, RichText};
+use poker_eden_client_core::{
+    games::describe, outbox::action_label, parse_in_room_input, share::parse_join_url, NetworkEvent, Session, SessionEvent,
+};
+use poker_eden_core::*;
+use std::{
+    error::Error,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+mod cards;
+mod table;
+
+/// 默认连接的服务器地址
+const DEFAULT_SERVER_ADDR: &str = "127.0.0.1:25917";
+
+/// 网络事件不会唤醒界面，没有输入时也按这个间隔重绘
+const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 常见系统上的中文字体，egui 自带的字体不包含汉字
+const CJK_FONT_PATHS: &[&str] = &[
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "/System/Library/Fonts/STHeiti Light.ttc",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simhei.ttf",
+];
+
+struct GuiApp {
+    session: Session,
+    event_tx: mpsc::UnboundedSender<NetworkEvent>,
+    event_rx: mpsc::UnboundedReceiver<NetworkEvent>,
+    /// 登录界面的输入
+    server_addr: String,
+    nickname: String,
+    join_link: String,
+    /// 创建房间时的牌桌配置
+    room_config: RoomConfig,
+    /// 房间内的指令输入框，语法与终端客户端相同
+    command: String,
+    /// 聊天输入框
+    chat: String,
+    /// 下注或加注的金额
+    amount: u32,
+    /// 换牌阶段选中要换掉的底牌
+    discards: Vec<bool>,
+    /// 是否显示日志窗口
+    show_log: bool,
+}
+
+impl GuiApp {
+    fn new() -> Self {
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let session = Session::default();
+        session.check_in_games(&event_tx);
+        Self {
+            session,
+            event_tx,
+            event_rx,
+            server_addr: DEFAULT_SERVER_ADDR.to_string(),
+            nickname: String::new(),
+            join_link: String::new(),
+            room_config: RoomConfig::default(),
+            command: String::new(),
+            chat: String::new(),
+            amount: 0,
+            discards: vec![],
+            show_log: false,
+        }
+    }
+
+    /// 根据会话事件更新界面状态
+    fn apply(&mut self, events: Vec<SessionEvent>) {
+        for event in events {
+            match event {
+                SessionEvent::HandStarted => self.discards.clear(),
+                SessionEvent::ShowLog => self.show_log = true,
+                SessionEvent::LeftRoom => {
+                    self.discards.clear();
+                    self.show_log = false;
+                }
+                SessionEvent::Updated | SessionEvent::EnteredRoom => {}
+            }
+        }
+    }
+
+    fn login_screen(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.add_space(40.0);
+                ui.heading(RichText::new("Poker Eden 扑克乐园").size(32.0));
+                ui.add_space(20.0);
+            });
+            egui::Grid::new("login").num_columns(2).spacing([12.0, 8.0]).show(ui, |ui| {
+                ui.label("服务器地址");
+                ui.text_edit_singleline(&mut self.server_addr);
+                ui.end_row();
+                ui.label("昵称");
+                ui.text_edit_singleline(&mut self.nickname);
+                ui.end_row();
+                ui.label("加入链接");
+                ui.add(egui::TextEdit::singleline(&mut self.join_link).hint_text("poker-eden://<地址>:<端口>/<房间ID>"));
+                ui.end_row();
+            });
+            // 创建房间时使用的牌桌配置，带入和计时为 0 表示不限或关闭
+            egui::CollapsingHeader::new("牌桌设置").show(ui, |ui| {
+                let config = &mut self.room_config;
+                egui::Grid::new("room_config").num_columns(2).spacing([12.0, 8.0]).show(ui, |ui| {
+                    ui.label("盲注");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut config.small_blind).range(1..=u32::MAX));
+                        ui.label("/");
+                        ui.add(egui::DragValue::new(&mut config.big_blind).range(1..=u32::MAX));
+                    });
+                    ui.end_row();
+                    ui.label("座位数");
+                    ui.add(egui::DragValue::new(&mut config.seats).range(2..=MAX_SEATS));
+                    ui.end_row();
+                    ui.label("带入范围");
+                    ui.horizontal(|ui| {
+                        ui.add(egui::DragValue::new(&mut config.min_buy_in));
+                        ui.label("-");
+                        ui.add(egui::DragValue::new(&mut config.max_buy_in));
+                    });
+                    ui.end_row();
+                    ui.label("行动计时 (秒)");
+                    ui.add(egui::DragValue::new(&mut config.turn_timer_secs));
+                    ui.end_row();
+                    ui.label("时间银行 (秒)");
+                    ui.add(egui::DragValue::new(&mut config.time_bank_secs));
+                    ui.end_row();
+                });
+            });
+            ui.add_space(8.0);
+
+            let nickname = self.nickname.trim().to_string();
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!nickname.is_empty(), egui::Button::new("创建房间")).clicked() {
+                    let msg = ClientMessage::CreateRoom { nickname: nickname.clone(), config: self.room_config };
+                    self.session.connect(&self.event_tx, self.server_addr.trim().to_string(), msg);
+                }
+                let can_join = !nickname.is_empty() && !self.join_link.trim().is_empty();
+                if ui.add_enabled(can_join, egui::Button::new("加入房间")).clicked() {
+                    match parse_join_url(self.join_link.trim()) {
+                        Some((server_addr, room_id)) => {
+                            let msg = ClientMessage::JoinRoom { room_id, nickname: nickname.clone() };
+                            self.session.connect(&self.event_tx, server_addr, msg);
+                        }
+                        None => self.session.last_msg = Some("无法识别的加入链接".to_string()),
+                    }
+                }
+            });
+            if let Some(msg) = &self.session.last_msg {
+                ui.colored_label(Color32::LIGHT_RED, msg);
+            }
+
+            // 保存在本地的通信对局
+            if !self.session.games.is_empty() {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("通信对局").strong());
+                    if ui.small_button("刷新").clicked() {
+                        self.session.check_in_games(&self.event_tx);
+                    }
+                });
+                let now = chrono::Utc::now();
+                let games: Vec<String> = self.session.games.games.iter().map(|game| {
+                    format!("{} ({})  {}", game.nickname, game.server_addr, describe(self.session.games.status(game.room_id), now))
+                }).collect();
+                for (i, game) in games.into_iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(game);
+                        if ui.small_button("继续").clicked() {
+                            self.session.resume_game(&self.event_tx, i + 1);
+                        }
+                    });
+                }
+            }
+        });
+    }
+
+    fn room_screen(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("top_info").show(ctx, |ui| self.top_info(ui));
+        egui::TopBottomPanel::bottom("actions").show(ctx, |ui| self.action_bar(ui));
+        egui::SidePanel::right("chat").resizable(true).default_width(260.0).show(ctx, |ui| self.chat_panel(ui));
+        egui::CentralPanel::default().show(ctx, |ui| table::show(ui, &self.session));
+        egui::Window::new("日志").open(&mut self.show_log).default_size([520.0, 400.0]).show(ctx, |ui| {
+            egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+                for entry in self.session.log.entries() {
+                    ui.label(entry);
+                }
+            });
+        });
+    }
+
+    fn top_info(&mut self, ui: &mut egui::Ui) {
+        let Some(gs) = self.session.game_state.as_ref() else { return };
+        let host = self.session.host_id.and_then(|id| gs.players.get(&id)).map_or("", |p| p.nickname.as_str());
+        // 混合轮换时显示当前玩法还剩几局以及下一个玩法
+        let rotation = match &gs.rotation {
+            Some(progress) if progress.rotation.variants.len() > 1 => {
+                format!(" (还剩 {} 局，之后 {})", progress.hands_left, progress.next_variant())
+            }
+            _ => String::new(),
+        };
+        ui.horizontal(|ui| {
+            ui.label(RichText::new(format!("{} {}{}", gs.variant, gs.betting_structure, rotation)).strong());
+            ui.separator();
+            ui.label(format!("盲注 {}/{}", gs.small_blind, gs.big_blind));
+            ui.separator();
+            ui.label(format!("阶段: {}", gs.phase));
+            ui.separator();
+            ui.label(format!("房主: {}", host));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui.button("日志").clicked() {
+                    self.show_log = !self.show_log;
+                }
+                ui.checkbox(&mut self.session.advanced_stats, "进阶数据");
+                if ui.button("复制加入链接").clicked() {
+                    let link = self.session.server_addr.as_deref()
+                        .map(|addr| poker_eden_client_core::share::join_url(addr, gs.room_id));
+                    if let Some(link) = link {
+                        ui.ctx().copy_text(link);
+                    }
+                }
+            });
+        });
+    }
+
+    /// 动作栏: 轮到自己时显示可以点击的动作按钮，其他时候显示提示；下方是指令输入框
+    fn action_bar(&mut self, ui: &mut egui::Ui) {
+        if let Some(msg) = &self.session.last_msg {
+            ui.colored_label(Color32::LIGHT_RED, format!("消息：{}", msg));
+        }
+
+        // 离线后只能重新连接
+        if self.session.offline {
+            ui.horizontal(|ui| {
+                ui.colored_label(Color32::LIGHT_RED, "已与服务器断开连接");
+                if ui.button("重新连接").clicked() {
+                    self.session.reconnect(&self.event_tx);
+                }
+            });
+            return;
+        }
+
+        if let Some((_, action)) = self.session.pending_confirm.clone() {
+            ui.horizontal(|ui| {
+                ui.label(format!("确认动作: {}？", action_label(&action)));
+                if ui.button("确认").clicked()
+                    && let Some(msg) = parse_in_room_input("y", &self.session) {
+                    self.session.send(msg);
+                }
+            });
+        }
+
+        // 输掉摊牌后轮到自己选择亮牌或盖牌
+        if self.session.my_showdown_turn() {
+            ui.horizontal(|ui| {
+                if ui.button("亮牌").clicked() {
+                    self.session.send(ClientMessage::ShowOrMuck { show: true });
+                }
+                if ui.button("盖牌").clicked() {
+                    self.session.send(ClientMessage::ShowOrMuck { show: false });
+                }
+            });
+        }
+
+        // 其他人都弃牌后，赢家可以亮出一张或全部底牌
+        if self.session.can_show_cards() {
+            let hand_len = self.session.my_id
+                .and_then(|id| self.session.game_state.as_ref().and_then(|gs| gs.player_indices.get(&id).map(|&idx| gs.player_cards[idx].len())))
+                .unwrap_or(0);
+            ui.horizontal(|ui| {
+                for pos in 0..hand_len {
+                    if ui.button(format!("亮第 {} 张", pos + 1)).clicked() {
+                        self.session.send(ClientMessage::ShowCards(vec![pos]));
+                    }
+                }
+                if ui.button("全部亮出").clicked() {
+                    self.session.send(ClientMessage::ShowCards((0..hand_len).collect()));
+                }
+            });
+        }
+
+        self.my_hand(ui);
+        self.action_buttons(ui);
+
+        if let Some(label) = self.session.outbox.sending() {
+            ui.label(format!("发送中: {}…", label));
+        }
+
+        // 其他指令 (入座、开局、房间选项等) 和终端客户端一样输入
+        ui.horizontal(|ui| {
+            let response = ui.add(egui::TextEdit::singleline(&mut self.command)
+                .hint_text("指令，如 seat 1 1000、start、option timer 30")
+                .desired_width(ui.available_width() - 60.0));
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.button("发送").clicked() || submitted) && !self.command.trim().is_empty() {
+                match parse_in_room_input(&self.command, &self.session) {
+                    Some(msg) => {
+                        self.session.send(msg);
+                        self.command.clear();
+                    }
+                    None => self.session.last_msg = Some(format!("无法识别的指令: {}", self.command.trim())),
+                }
+                response.request_focus();
+            }
+        });
+    }
+
+    /// 自己的底牌，换牌阶段点击选中要换掉的牌
+    fn my_hand(&mut self, ui: &mut egui::Ui) {
+        let Some(gs) = self.session.game_state.as_ref() else { return };
+        let Some(cards) = self.session.my_id
+            .and_then(|id| gs.player_indices.get(&id))
+            .and_then(|idx| gs.player_cards.get(*idx))
+            .filter(|cards| cards.iter().any(|c| c.is_some())) else { return };
+        let can_discard = self.session.valid_actions.iter().any(|a| matches!(a, PlayerActionType::Discard(_)));
+        self.discards.resize(cards.len(), false);
+        ui.horizontal(|ui| {
+            for (i, c) in cards.iter().enumerate() {
+                let selected = can_discard && self.discards[i];
+                if card(ui, *c, 56.0, selected).clicked() && can_discard {
+                    self.discards[i] = !self.discards[i];
+                }
+            }
+        });
+    }
+
+    fn action_buttons(&mut self, ui: &mut egui::Ui) {
+        let is_showdown = self.session.game_state.as_ref().is_some_and(|gs| gs.phase == GamePhase::Showdown);
+        if self.session.valid_actions.is_empty() || is_showdown {
+            return;
+        }
+        let mut sent = None;
+        ui.horizontal(|ui| {
+            ui.label(RichText::new("轮到你!").color(Color32::LIGHT_GREEN).strong());
+            if let Some(stats) = self.session.turn_stats() {
+                ui.label(RichText::new(stats).weak());
+            }
+            for action in self.session.valid_actions.clone() {
+                match action {
+                    PlayerActionType::Fold => if ui.button("弃牌").clicked() {
+                        sent = Some(PlayerAction::Fold.into());
+                    },
+                    PlayerActionType::Check => if ui.button("过牌").clicked() {
+                        sent = Some(PlayerAction::Check.into());
+                    },
+                    PlayerActionType::Call(amount) => if ui.button(format!("跟注 ${}", amount)).clicked() {
+                        sent = Some(PlayerAction::Call.into());
+                    },
+                    PlayerActionType::Bet { min, max } | PlayerActionType::Raise { min, max } => {
+                        self.amount = self.amount.clamp(min, max);
+                        ui.add(egui::Slider::new(&mut self.amount, min..=max).logarithmic(true));
+                        let label = if matches!(action, PlayerActionType::Bet { .. }) { "下注" } else { "加注到" };
+                        if ui.button(format!("{} ${}", label, self.amount)).clicked() {
+                            sent = Some(PlayerAction::BetOrRaise(self.amount).into());
+                        }
+                    }
+                    PlayerActionType::Discard(max) => {
+                        let selected: Vec<usize> = self.discards.iter().enumerate().filter(|(_, s)| **s).map(|(i, _)| i).collect();
+                        let label = if selected.is_empty() { "不换".to_string() } else { format!("换掉 {} 张", selected.len()) };
+                        ui.label(format!("点击底牌选择要换的牌 (最多 {} 张)", max));
+                        if ui.add_enabled(selected.len() <= max, egui::Button::new(label)).clicked() {
+                            sent = Some(ClientMessage::DiscardCards(selected));
+                            self.discards.clear();
+                        }
+                    }
+                }
+            }
+        });
+        if let Some(msg) = sent {
+            self.session.send(msg);
+        }
+    }
+
+    fn chat_panel(&mut self, ui: &mut egui::Ui) {
+        ui.heading("聊天");
+        ui.separator();
+        let input_height = 36.0;
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - input_height)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for entry in self.session.chat.visible() {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(RichText::new(entry.at.format("%H:%M").to_string()).weak());
+                        ui.label(RichText::new(format!("{}:", entry.nickname)).strong());
+                        ui.label(&entry.text);
+                    });
+                }
+            });
+        ui.separator();
+        let response = ui.text_edit_singleline(&mut self.chat);
+        if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+            let text = self.chat.trim().to_string();
+            if !text.is_empty() {
+                self.session.send(ClientMessage::Chat { text });
+            }
+            self.chat.clear();
+            response.request_focus();
+        }
+    }
+}
+
+impl eframe::App for GuiApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        while let Ok(event) = self.event_rx.try_recv() {
+            let events = self.session.handle_network_event(event);
+            self.apply(events);
+        }
+        self.session.flush(Instant::now());
+
+        if self.session.game_state.is_some() {
+            self.room_screen(ctx);
+        } else {
+            self.login_screen(ctx);
+        }
+        ctx.request_repaint_after(REFRESH_INTERVAL);
+    }
+}
+
+/// 加载中文字体: 优先使用 `POKER_EDEN_FONT` 指定的字体文件，否则在常见的系统字体中查找
+fn install_cjk_font(ctx: &egui::Context) {
+    let from_env = std::env::var("POKER_EDEN_FONT").ok();
+    let font = from_env.iter().map(String::as_str).chain(CJK_FONT_PATHS.iter().copied())
+        .find_map(|path| std::fs::read(path).ok());
+    let Some(font) = font else {
+        eprintln!("没有找到中文字体，可以用 POKER_EDEN_FONT 指定字体文件");
+        return;
+    };
+    let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert("cjk".to_string(), Arc::new(egui::FontData::from_owned(font)));
+    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+        fonts.families.entry(family).or_default().push("cjk".to_string());
+    }
+    ctx.set_fonts(fonts);
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // 网络任务跑在后台的 tokio 运行时上，界面在主线程上运行
+    let runtime = tokio::runtime::Runtime::new()?;
+    let _guard = runtime.enter();
+
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("Poker Eden")
+            .with_inner_size([1100.0, 760.0])
+            .with_min_inner_size([720.0, 520.0]),
+        ..Default::default()
+    };
+    eframe::run_native("Poker Eden", options, Box::new(|cc| {
+        install_cjk_font(&cc.egui_ctx);
+        Ok(Box::new(GuiApp::new()))
+    }))?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 牌桌: 椭圆形的桌面，公共牌和奖池在中间，玩家按座位号围坐一圈，自己的座位总在最下方
+
+use crate::cards::{card_size, paint_card};
+use eframe::egui::{Align2, Color32, FontId, Painter, Pos2, Rect, Sense, Shape, Stroke, StrokeKind, Ui, Vec2};
+use poker_eden_client_core::Session;
+use poker_eden_core::*;
+use std::f32::consts::TAU;
+
+/// 桌面的颜色
+const FELT: Color32 = Color32::from_rgb(30, 110, 60);
+/// 正在行动的玩家的高亮颜色
+const ACTIVE: Color32 = Color32::from_rgb(120, 220, 240);
+/// 名牌的大小
+const PLATE_SIZE: Vec2 = Vec2::new(130.0, 48.0);
+
+/// 在剩余的空间里画出整张牌桌
+pub fn show(ui: &mut Ui, session: &Session) {
+    let Some(gs) = session.game_state.as_ref() else { return };
+    let (rect, response) = ui.allocate_exact_size(ui.available_size(), Sense::hover());
+    let painter = ui.painter_at(rect);
+    let center = rect.center();
+    let radius = Vec2::new(rect.width() * 0.36, rect.height() * 0.30);
+    painter.add(Shape::ellipse_filled(center, radius + Vec2::splat(10.0), Color32::from_rgb(90, 60, 30)));
+    painter.add(Shape::ellipse_filled(center, radius, FELT));
+
+    let card_width = (rect.width() * 0.05).clamp(28.0, 64.0);
+    paint_board(&painter, gs, center, card_width);
+
+    // 自己的座位转到正下方，观众按座位号的顺序看
+    let my_seat = session.my_id.and_then(|id| gs.players.get(&id)).and_then(|p| p.seat_id).unwrap_or(0);
+    let seats = gs.seats.max(1);
+    let mut hovered = None;
+    for seat in 0..seats {
+        let turn = ((seat + seats - my_seat) % seats) as f32 / seats as f32;
+        let angle = TAU / 4.0 + turn * TAU;
+        let pos = center + Vec2::new(radius.x * 1.2 * angle.cos(), radius.y * 1.35 * angle.sin());
+        let player = gs.players.values().find(|p| p.seat_id == Some(seat) && gs.seated_players.contains(&p.id));
+        match player {
+            Some(player) => {
+                paint_seat(&painter, session, gs, player, pos, center, card_width * 0.7);
+                if response.hover_pos().is_some_and(|p| Rect::from_center_size(pos, PLATE_SIZE).contains(p)) {
+                    hovered = Some(player.id);
+                }
+            }
+            None => {
+                painter.text(pos, Align2::CENTER_CENTER, format!("空座 {}", seat), FontId::proportional(14.0), Color32::GRAY);
+            }
+        }
+    }
+
+    // 就座时把鼠标移到对手的名牌上，显示和他之间的有效筹码
+    let me = session.my_id.filter(|_| session.is_seated());
+    if let (Some(me), Some(other)) = (me, hovered)
+        && me != other
+        && let Some(bb) = gs.effective_stack_bb(&me, &other)
+    {
+        response.on_hover_text_at_pointer(format!("有效筹码 {:.1}BB", bb));
+    }
+}
+
+/// 公共牌和奖池。双公共牌时两块公共牌上下排列
+fn paint_board(painter: &Painter, gs: &GameState, center: Pos2, card_width: f32) {
+    let size = card_size(card_width);
+    let gap = card_width * 0.15;
+    let boards = gs.community_cards.iter().filter(|board| !board.is_empty()).collect::<Vec<_>>();
+    let total_height = boards.len() as f32 * (size.y + gap);
+    for (row, board) in boards.iter().enumerate() {
+        let row_width = board.len() as f32 * (size.x + gap) - gap;
+        let top = center.y - total_height / 2.0 - size.y * 0.3 + row as f32 * (size.y + gap);
+        for (i, card) in board.iter().enumerate() {
+            let min = Pos2::new(center.x - row_width / 2.0 + i as f32 * (size.x + gap), top);
+            let rect = Rect::from_min_size(min, size);
+            match card {
+                Some(card) => paint_card(painter, rect, Some(*card), false),
+                // 还没发的公共牌只画一个空位
+                None => {
+                    painter.rect_stroke(rect, card_width * 0.1, Stroke::new(1.0, Color32::from_white_alpha(60)), StrokeKind::Inside);
+                }
+            }
+        }
+    }
+    let pot_pos = Pos2::new(center.x, center.y + total_height / 2.0 + size.y * 0.1);
+    painter.text(pot_pos, Align2::CENTER_TOP, format!("奖池 ${}", gs.pot), FontId::proportional(18.0), Color32::WHITE);
+}
+
+/// 一个座位: 昵称、筹码和状态，底牌放在名牌上方，本轮的下注朝着桌子中间
+fn paint_seat(painter: &Painter, session: &Session, gs: &GameState, player: &Player, pos: Pos2, center: Pos2, card_width: f32) {
+    let idx = gs.player_indices.get(&player.id).copied();
+    let is_me = session.my_id == Some(player.id);
+    let is_thinking = gs.hand_in_progress() && gs.current_player_id() == Some(player.id);
+    let is_dealer = gs.dealer_id() == Some(player.id);
+
+    // 名牌
+    let plate = Rect::from_center_size(pos, PLATE_SIZE);
+    let fill = if is_thinking { ACTIVE } else { Color32::from_gray(35) };
+    let text_color = if is_thinking { Color32::BLACK } else { Color32::WHITE };
+    painter.rect_filled(plate, 8.0, fill);
+    if is_me {
+        painter.rect_stroke(plate, 8.0, Stroke::new(2.0, Color32::GOLD), StrokeKind::Outside);
+    }
+    let mut name = match &player.profile.avatar {
+        Some(avatar) => format!("{} {}", avatar, player.nickname),
+        None => player.nickname.clone(),
+    };
+    if player.is_offline {
+        name = format!("{} (离线)", name);
+    }
+    // 玩家选择的昵称颜色，正在行动时保持和名牌对比明显的黑色
+    let name_color = match player.profile.color {
+        Some([r, g, b]) if !is_thinking => Color32::from_rgb(r, g, b),
+        _ => text_color,
+    };
+    painter.text(plate.center_top() + Vec2::new(0.0, 4.0), Align2::CENTER_TOP, name, FontId::proportional(15.0), name_color);
+    let status = match session.turn_deadline {
+        Some((id, deadline)) if is_thinking && id == player.id => {
+            format!("${}  思考中 {}s", player.stack, (deadline - chrono::Utc::now()).num_seconds().max(0))
+        }
+        _ => format!("${}  {}", player.stack, player.state),
+    };
+    painter.text(plate.center_bottom() - Vec2::new(0.0, 4.0), Align2::CENTER_BOTTOM, status, FontId::proportional(13.0), text_color);
+
+    // 庄家按钮
+    if is_dealer {
+        let button = plate.right_top() + Vec2::new(4.0, 4.0);
+        painter.circle_filled(button, 10.0, Color32::WHITE);
+        painter.text(button, Align2::CENTER_CENTER, "D", FontId::proportional(13.0), Color32::BLACK);
+    }
+
+    let Some(idx) = idx else { return };
+
+    // 底牌: 看不到的牌画成牌背，弃牌后不再显示
+    let cards = gs.player_cards.get(idx).cloned().unwrap_or_default();
+    if player.state != PlayerState::Folded && !cards.is_empty() {
+        let size = card_size(card_width);
+        let step = size.x * 0.75;
+        let width = step * (cards.len() - 1) as f32 + size.x;
+        for (i, card) in cards.iter().enumerate() {
+            let min = Pos2::new(pos.x - width / 2.0 + i as f32 * step, plate.top() - size.y - 4.0);
+            paint_card(painter, Rect::from_min_size(min, size), *card, false);
+        }
+    }
+
+    // 本轮下注: 盲注和抓位用蓝色筹码并标出类型，主动下注用红色筹码，前注和死盲用灰色的小字
+    let bet = gs.street_bet(idx);
+    let chip = pos + (center - pos) * 0.4;
+    if bet.forced + bet.voluntary > 0 {
+        let (color, text) = match (bet.forced, bet.voluntary) {
+            (0, voluntary) => (Color32::from_rgb(220, 60, 60), format!("${}", voluntary)),
+            (forced, 0) => (Color32::from_rgb(60, 120, 220), format!("{} ${}", if bet.straddle { "抓位" } else { "盲注" }, forced)),
+            (forced, voluntary) => (Color32::from_rgb(220, 60, 60), format!("${} + ${}", forced, voluntary)),
+        };
+        painter.circle_filled(chip, 8.0, color);
+        painter.text(chip + Vec2::new(12.0, 0.0), Align2::LEFT_CENTER, text, FontId::proportional(14.0), Color32::WHITE);
+    }
+    if bet.dead > 0 {
+        painter.text(chip + Vec2::new(12.0, 16.0), Align2::LEFT_CENTER, format!("前注 ${}", bet.dead),
+                     FontId::proportional(12.0), Color32::GRAY);
+    }
+    // 进入第二轮下注后，在筹码上方标出本局的总投入和每一轮的下注
+    let history = gs.street_history(idx);
+    let invested = gs.hand_invested(idx);
+    if history.len() > 1 && invested > 0 {
+        let rounds = history.iter().map(|b| b.to_string()).collect::<Vec<_>>().join("/");
+        painter.text(chip + Vec2::new(12.0, -16.0), Align2::LEFT_CENTER, format!("本局 ${} ({})", invested, rounds),
+                     FontId::proportional(12.0), Color32::LIGHT_GRAY);
+    }
+
+    // 牌型
+    if let Some(rank) = session.hand_ranks.get(idx).cloned().flatten() {
+        painter.text(plate.center_bottom() + Vec2::new(0.0, 4.0), Align2::CENTER_TOP, rank.to_string(),
+                     FontId::proportional(13.0), Color32::YELLOW);
+    }
+}
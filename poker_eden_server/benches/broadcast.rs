@@ -0,0 +1,86 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 广播扇出的基准测试
+//!
+//! 运行: `cargo bench -p poker_eden_server`
+//! 模拟 10 位玩家加不同数量观众的房间: 一条消息序列化一次后发给所有连接，
+//! 每个连接的发送任务再把它转换成 WebSocket 帧 (开启压缩时所有连接共享一份压缩结果)。
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use poker_eden_core::{GameState, Player, PlayerId, PlayerState, ServerMessage};
+use poker_eden_server::{broadcast_encoded, encode, MessageSender, Payload};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+const PLAYERS: usize = 10;
+
+/// 一个 10 人牌桌的状态快照，序列化后超过压缩阈值
+fn snapshot() -> ServerMessage {
+    let mut state = GameState::default();
+    for seat in 0..PLAYERS {
+        let id = Uuid::new_v4();
+        let player = Player {
+            stack: 1000,
+            seat_id: Some(seat as u8),
+            state: PlayerState::Waiting,
+            ..Player::new(id, format!("Player_{}", seat))
+        };
+        state.players.insert(id, player);
+        state.seated_players.push_back(id);
+    }
+    ServerMessage::GameStateSnapshot(state)
+}
+
+/// 房间里的所有连接: 10 位玩家和 `spectators` 位观众
+fn connections(spectators: usize) -> (Vec<(PlayerId, MessageSender)>, Vec<mpsc::Receiver<Payload>>) {
+    (0..PLAYERS + spectators)
+        .map(|_| {
+            let (tx, rx) = MessageSender::channel(32);
+            ((Uuid::new_v4(), tx), rx)
+        })
+        .unzip()
+}
+
+fn bench_fanout(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let message = snapshot();
+    let mut group = c.benchmark_group("broadcast_fanout");
+    for spectators in [0, 50, 200] {
+        let (targets, mut receivers) = connections(spectators);
+        group.throughput(Throughput::Elements(targets.len() as u64));
+        for compress in [false, true] {
+            let name = if compress { "deflate" } else { "text" };
+            group.bench_with_input(BenchmarkId::new(name, spectators), &spectators, |b, _| {
+                b.iter(|| {
+                    runtime.block_on(broadcast_encoded(&targets, encode(&message), None));
+                    // 各连接的发送任务取出消息并转换成帧
+                    for rx in &mut receivers {
+                        let payload = rx.try_recv().unwrap();
+                        black_box(payload.to_frame(compress));
+                    }
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_fanout);
+criterion_main!(benches);
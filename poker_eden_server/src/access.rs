@@ -0,0 +1,274 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+//! 按 IP 和国家限制连接
+//!
+//! 有地区限制的运营者可以按来源 IP 拒绝连接，WebSocket、事件流、叠加层和网页客户端都会检查。通过环境变量配置:
+//! - `POKER_EDEN_IP_DENY`: 拒绝的 IP 或网段，逗号分隔，如 `203.0.113.0/24,198.51.100.7`
+//! - `POKER_EDEN_IP_ALLOW`: 只允许的 IP 或网段，不设置时允许所有不在拒绝列表中的地址
+//! - `POKER_EDEN_BLOCKED_COUNTRIES`: 拒绝的国家或地区 (ISO 3166 代码)，如 `US,KP`
+//! - `POKER_EDEN_GEOIP_DB`: 离线的 GeoIP2/GeoLite2 Country 数据库 (`.mmdb`) 路径，按国家拒绝时必须设置
+//! - `POKER_EDEN_ACCESS_LOG`: 审计日志文件，每次拒绝追加一行 JSON；不设置时只写入服务器日志
+//!
+//! 判断顺序为: 拒绝列表、允许列表、国家。数据库中查不到国家的地址 (如局域网地址) 不按国家拒绝。
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use ipnet::IpNet;
+use maxminddb::{geoip2, Reader};
+use serde_json::json;
+use tokio::{fs::OpenOptions, io::AsyncWriteExt, sync::mpsc};
+use tracing::warn;
+
+use crate::SharedState;
+
+/// 被拒绝时返回给客户端的提示
+pub const FORBIDDEN_MESSAGE: &str = "你所在的网络或地区不能连接本服务器";
+
+// 等待写入审计日志的记录上限，写入跟不上时丢弃新的记录 (服务器日志中仍然有)
+const AUDIT_QUEUE_SIZE: usize = 1024;
+
+/// 连接被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    /// 在拒绝列表中
+    Denied,
+    /// 配置了允许列表，但不在其中
+    NotAllowed,
+    /// 来自被限制的国家或地区
+    Country(String),
+}
+
+impl Rejection {
+    fn reason(&self) -> String {
+        match self {
+            Rejection::Denied => "IP 在拒绝列表中".to_string(),
+            Rejection::NotAllowed => "IP 不在允许列表中".to_string(),
+            Rejection::Country(code) => format!("来自被限制的国家或地区 {}", code),
+        }
+    }
+}
+
+pub struct AccessPolicy {
+    allow: Vec<IpNet>,
+    deny: Vec<IpNet>,
+    blocked_countries: Vec<String>,
+    geoip: Option<Reader<Vec<u8>>>,
+    // 审计日志由单独的任务写入，检查连接时不会阻塞在文件读写上
+    audit_log: Option<mpsc::Sender<String>>,
+}
+
+impl AccessPolicy {
+    /// 从环境变量读取配置。地址格式错误、或者按国家拒绝却没有可用的数据库时返回错误，
+    /// 避免运营者以为限制已经生效
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let blocked_countries: Vec<String> = var("POKER_EDEN_BLOCKED_COUNTRIES")
+            .map(|v| v.split(',').map(|c| c.trim().to_uppercase()).filter(|c| !c.is_empty()).collect())
+            .unwrap_or_default();
+        let geoip = match var("POKER_EDEN_GEOIP_DB") {
+            Some(path) => Some(Reader::open_readfile(&path).map_err(|e| format!("无法打开 GeoIP 数据库 {}: {}", path, e))?),
+            None if !blocked_countries.is_empty() => {
+                return Err("设置了 POKER_EDEN_BLOCKED_COUNTRIES，但没有设置 GeoIP 数据库 POKER_EDEN_GEOIP_DB".to_string());
+            }
+            None => None,
+        };
+        Ok(Self {
+            allow: parse_networks(var("POKER_EDEN_IP_ALLOW").as_deref())?,
+            deny: parse_networks(var("POKER_EDEN_IP_DENY").as_deref())?,
+            blocked_countries,
+            geoip,
+            audit_log: var("POKER_EDEN_ACCESS_LOG").map(|p| spawn_audit_writer(PathBuf::from(p))),
+        })
+    }
+
+    /// 是否配置了任何限制
+    pub fn is_enabled(&self) -> bool {
+        !self.allow.is_empty() || !self.deny.is_empty() || !self.blocked_countries.is_empty()
+    }
+
+    /// 检查一个来源地址，拒绝时写入审计日志。`endpoint` 是被请求的路径，只用于日志
+    pub fn check(&self, ip: IpAddr, endpoint: &str) -> Result<(), Rejection> {
+        let result = self.evaluate(ip);
+        if let Err(rejection) = &result {
+            self.audit(ip, endpoint, rejection);
+        }
+        result
+    }
+
+    fn evaluate(&self, ip: IpAddr) -> Result<(), Rejection> {
+        self.evaluate_with(ip, |ip| self.country(ip))
+    }
+
+    /// 按配置的规则判断，`country` 查询地址所在的国家或地区
+    fn evaluate_with(&self, ip: IpAddr, country: impl FnOnce(IpAddr) -> Option<String>) -> Result<(), Rejection> {
+        // IPv4 映射的 IPv6 地址按 IPv4 判断
+        let ip = ip.to_canonical();
+        if self.deny.iter().any(|net| net.contains(&ip)) {
+            return Err(Rejection::Denied);
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|net| net.contains(&ip)) {
+            return Err(Rejection::NotAllowed);
+        }
+        if !self.blocked_countries.is_empty()
+            && let Some(code) = country(ip)
+            && self.blocked_countries.contains(&code) {
+            return Err(Rejection::Country(code));
+        }
+        Ok(())
+    }
+
+    fn country(&self, ip: IpAddr) -> Option<String> {
+        let record: geoip2::Country = self.geoip.as_ref()?.lookup(ip).ok()?;
+        record.country.and_then(|c| c.iso_code).map(|code| code.to_uppercase())
+    }
+
+    fn audit(&self, ip: IpAddr, endpoint: &str, rejection: &Rejection) {
+        warn!("拒绝来自 {} 的连接 ({}): {}", ip, endpoint, rejection.reason());
+        let Some(path) = &self.audit_log else { return };
+        let entry = json!({
+            "at": Utc::now(),
+            "ip": ip.to_string(),
+            "endpoint": endpoint,
+            "reason": rejection.reason(),
+            "country": match rejection {
+                Rejection::Country(code) => Some(code.as_str()),
+                _ => None,
+            },
+        });
+        if path.try_send(format!("{}\n", entry)).is_err() {
+            warn!("访问审计日志写入不及，丢弃了一条记录");
+        }
+    }
+}
+
+/// 启动写入审计日志的任务。只有这一个任务写文件，并发的拒绝记录不会交错写在同一行
+fn spawn_audit_writer(path: PathBuf) -> mpsc::Sender<String> {
+    let (tx, mut rx) = mpsc::channel::<String>(AUDIT_QUEUE_SIZE);
+    tokio::spawn(async move {
+        while let Some(line) = rx.recv().await {
+            let written = match OpenOptions::new().create(true).append(true).open(&path).await {
+                Ok(mut file) => file.write_all(line.as_bytes()).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = written {
+                warn!("写入访问审计日志 {} 失败: {}", path.display(), e);
+            }
+        }
+    });
+    tx
+}
+
+/// 按访问限制拦截普通的 HTTP 请求 (如网页客户端的静态文件)
+pub async fn guard(
+    State(state): State<SharedState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if state.access.check(addr.ip(), request.uri().path()).is_err() {
+        return (StatusCode::FORBIDDEN, FORBIDDEN_MESSAGE).into_response();
+    }
+    next.run(request).await
+}
+
+/// 解析逗号分隔的 IP 或网段，单个 IP 视为只包含它自己的网段
+fn parse_networks(text: Option<&str>) -> Result<Vec<IpNet>, String> {
+    let Some(text) = text else { return Ok(vec![]) };
+    text.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            IpNet::from_str(s)
+                .or_else(|_| IpAddr::from_str(s).map(IpNet::from))
+                .map_err(|_| format!("无法解析的 IP 或网段: {}", s))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allow: &str, deny: &str, countries: &[&str]) -> AccessPolicy {
+        AccessPolicy {
+            allow: parse_networks(Some(allow)).unwrap(),
+            deny: parse_networks(Some(deny)).unwrap(),
+            blocked_countries: countries.iter().map(|c| c.to_string()).collect(),
+            geoip: None,
+            audit_log: None,
+        }
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_networks() {
+        assert_eq!(parse_networks(None), Ok(vec![]));
+        let nets = parse_networks(Some(" 203.0.113.0/24, ,198.51.100.7,::1 ")).unwrap();
+        assert_eq!(nets.len(), 3);
+        assert!(nets[1].contains(&ip("198.51.100.7")));
+        assert!(!nets[1].contains(&ip("198.51.100.8")));
+        assert!(parse_networks(Some("203.0.113.0/24,not-an-ip")).is_err());
+    }
+
+    #[test]
+    fn test_deny_and_allow_lists() {
+        let open = policy("", "", &[]);
+        assert!(!open.is_enabled());
+        assert_eq!(open.evaluate(ip("203.0.113.5")), Ok(()));
+
+        let strict = policy("10.0.0.0/8", "10.1.0.0/16", &[]);
+        assert!(strict.is_enabled());
+        assert_eq!(strict.evaluate(ip("10.2.3.4")), Ok(()));
+        // 拒绝列表优先于允许列表
+        assert_eq!(strict.evaluate(ip("10.1.2.3")), Err(Rejection::Denied));
+        assert_eq!(strict.evaluate(ip("192.168.1.1")), Err(Rejection::NotAllowed));
+        // IPv4 映射的 IPv6 地址按 IPv4 判断
+        assert_eq!(strict.evaluate(ip("::ffff:10.1.2.3")), Err(Rejection::Denied));
+        assert_eq!(strict.evaluate(ip("::ffff:10.2.3.4")), Ok(()));
+    }
+
+    #[test]
+    fn test_blocked_countries() {
+        let lookup = |ip: IpAddr| match ip.to_string().as_str() {
+            "203.0.113.5" => Some("KP".to_string()),
+            "198.51.100.7" => Some("FR".to_string()),
+            _ => None,
+        };
+        let open = policy("", "", &[]);
+        let strict = policy("", "198.51.100.0/24", &["KP", "US"]);
+        assert_eq!(strict.evaluate_with(ip("203.0.113.5"), lookup), Err(Rejection::Country("KP".to_string())));
+        // 拒绝列表先于国家判断
+        assert_eq!(strict.evaluate_with(ip("198.51.100.7"), lookup), Err(Rejection::Denied));
+        // 查不到国家的地址 (如局域网地址) 不按国家拒绝
+        assert_eq!(strict.evaluate_with(ip("192.168.1.1"), lookup), Ok(()));
+
+        // 没有限制国家时不查询数据库
+        assert_eq!(open.evaluate_with(ip("203.0.113.5"), |_| panic!("不应查询国家")), Ok(()));
+    }
+}
@@ -0,0 +1,364 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 玩家连接
+//!
+//! 每个 WebSocket 连接一个任务，负责收发消息，并把消息路由到玩家所在房间的 actor。
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::ops::Deref;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use axum::{
+    body::Bytes,
+    extract::{
+        ws::{Message, Utf8Bytes, WebSocket},
+        ConnectInfo, Query, State, WebSocketUpgrade,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use futures_util::{stream::StreamExt, SinkExt};
+use tokio::sync::mpsc;
+use tracing::info;
+use uuid::Uuid;
+
+use poker_eden_core::{deflate, ClientMessage, GameCheckIn, PlayerId, RoomId, ServerMessage, COMPRESS_THRESHOLD, MAX_CHECK_IN_GAMES};
+
+use crate::access::FORBIDDEN_MESSAGE;
+use crate::room::{Room, RoomHandle};
+use crate::SharedState;
+
+/// 序列化好的消息。广播时同一条消息只序列化一次，所有连接共享同一份 JSON；
+/// 压缩的版本在第一次发给声明支持压缩的连接时生成，之后的连接共享同一份压缩结果
+#[derive(Clone)]
+pub struct Payload(Arc<EncodedMessage>);
+
+struct EncodedMessage {
+    json: Utf8Bytes,
+    deflated: OnceLock<Bytes>,
+    // 发出这条消息后关闭连接
+    closing: bool,
+}
+
+impl Payload {
+    fn new(json: String, closing: bool) -> Self {
+        Payload(Arc::new(EncodedMessage { json: json.into(), deflated: OnceLock::new(), closing }))
+    }
+
+    /// 转换成 WebSocket 帧，开启压缩时大消息以压缩后的二进制帧发送
+    pub fn to_frame(&self, compress: bool) -> Message {
+        if compress && self.len() >= COMPRESS_THRESHOLD {
+            Message::Binary(self.0.deflated.get_or_init(|| deflate(self).into()).clone())
+        } else {
+            Message::Text(self.0.json.clone())
+        }
+    }
+
+    /// 发出后关闭连接的消息，如发给被移出房间的玩家的通知
+    pub fn closing(msg: &ServerMessage) -> Self {
+        Payload::new(serde_json::to_string(msg).unwrap(), true)
+    }
+
+    /// 发出这条消息后是否关闭连接
+    pub fn closes_connection(&self) -> bool {
+        self.0.closing
+    }
+}
+
+impl From<String> for Payload {
+    fn from(json: String) -> Self {
+        Payload::new(json, false)
+    }
+}
+
+impl Deref for Payload {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.0.json.as_str()
+    }
+}
+
+// 发往单个连接的消息通道。消息在放入通道前就序列化为 JSON
+#[derive(Clone)]
+pub struct MessageSender(mpsc::Sender<Payload>);
+
+type SendError = mpsc::error::SendError<Payload>;
+
+impl MessageSender {
+    /// 创建发往单个连接的消息通道
+    pub fn channel(buffer: usize) -> (MessageSender, mpsc::Receiver<Payload>) {
+        let (tx, rx) = mpsc::channel(buffer);
+        (MessageSender(tx), rx)
+    }
+
+    pub async fn send(&self, msg: ServerMessage) -> Result<(), SendError> {
+        self.send_encoded(encode(&msg)).await
+    }
+
+    pub fn try_send(&self, msg: ServerMessage) -> Result<(), mpsc::error::TrySendError<Payload>> {
+        self.0.try_send(encode(&msg))
+    }
+
+    pub async fn send_encoded(&self, payload: Payload) -> Result<(), SendError> {
+        self.0.send(payload).await
+    }
+
+    pub fn try_send_encoded(&self, payload: Payload) -> Result<(), mpsc::error::TrySendError<Payload>> {
+        self.0.try_send(payload)
+    }
+
+    /// 两个发送器是否属于同一个连接
+    pub fn same_connection(&self, other: &MessageSender) -> bool {
+        self.0.same_channel(&other.0)
+    }
+}
+
+pub fn encode(msg: &ServerMessage) -> Payload {
+    serde_json::to_string(msg).unwrap().into()
+}
+
+// 向客户端发送心跳的间隔
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 处理 WebSocket 连接请求，被访问限制拒绝的地址在升级前返回 403。
+/// 连接地址带有 `?compress=deflate` 时，之后发给该连接的大消息会压缩成二进制帧
+pub async fn websocket_handler(
+    ws: WebSocketUpgrade,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Query(query): Query<HashMap<String, String>>,
+    State(state): State<SharedState>,
+) -> Response {
+    if state.access.check(addr.ip(), "/ws").is_err() {
+        return (StatusCode::FORBIDDEN, FORBIDDEN_MESSAGE).into_response();
+    }
+    let compress = query.get("compress").is_some_and(|v| v == "deflate");
+    ws.on_upgrade(move |socket| handle_socket(socket, state, compress))
+}
+
+/// 处理单个 WebSocket 连接的生命周期
+async fn handle_socket(socket: WebSocket, state: SharedState, compress: bool) {
+    let (mut sender, mut receiver) = socket.split();
+
+    // 创建一个 MPSC 通道，用于从其他任务接收要发送的消息
+    let (tx, mut rx) = MessageSender::channel(32);
+
+    // 启动一个新任务，专门负责将 MPSC 通道中的消息发送到 WebSocket，
+    // 并定时发送心跳，让客户端能发现连接已经中断
+    let mut writer = tokio::spawn(async move {
+        let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+        loop {
+            let (ws_msg, closing) = tokio::select! {
+                msg = rx.recv() => match msg {
+                    Some(payload) => (payload.to_frame(compress), payload.closes_connection()),
+                    None => break,
+                },
+                _ = heartbeat.tick() => (Message::Ping(Default::default()), false),
+            };
+            if sender.send(ws_msg).await.is_err() {
+                // 发送失败，说明客户端已断开，退出任务
+                break;
+            }
+            if closing {
+                let _ = sender.send(Message::Close(None)).await;
+                break;
+            }
+        }
+    });
+
+    // 当前连接的上下文信息，在认证成功后填充
+    let mut player_context: Option<(RoomId, PlayerId)> = None;
+
+    // 主循环，处理从客户端接收到的消息。发送任务退出 (客户端已断开或服务器关闭了连接) 时一并结束
+    loop {
+        let msg = tokio::select! {
+            msg = receiver.next() => match msg {
+                Some(Ok(msg)) => msg,
+                _ => break,
+            },
+            _ = &mut writer => break,
+        };
+        if let Message::Text(text) = msg {
+            match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(client_msg) => {
+                    handle_client_message(
+                        client_msg,
+                        state.clone(),
+                        &tx,
+                        &mut player_context,
+                    ).await;
+                }
+                Err(e) => {
+                    tracing::warn!("解析消息失败: {}", e);
+                    let _ = tx.send(ServerMessage::Error { message: format!("无法解析的消息: {}", e) }).await;
+                }
+            }
+        }
+    }
+
+    // 客户端断开连接，执行清理工作
+    if let Some((room_id, player_id)) = player_context
+        && let Some(handle) = room_handle(&state, &room_id) {
+        handle.disconnect(player_id, tx).await;
+    }
+}
+
+/// 消息路由: 创建和加入房间在这里处理，其余消息转发给玩家所在房间的 actor
+async fn handle_client_message(
+    msg: ClientMessage,
+    state: SharedState,
+    tx: &MessageSender,
+    context: &mut Option<(RoomId, PlayerId)>,
+) {
+    match msg {
+        ClientMessage::CreateRoom { nickname, config } => {
+            if context.is_some() {
+                let _ = tx.send(ServerMessage::Error { message: "你已经在一个房间里了".to_string() }).await;
+                return;
+            }
+            if let Err(e) = config.validate() {
+                let _ = tx.send(e.into()).await;
+                return;
+            }
+
+            let room_id = Uuid::new_v4();
+            let player_id = Uuid::new_v4();
+            let player_secret = Uuid::new_v4();
+
+            let (room, join_msg) = Room::new(room_id, player_id, player_secret, nickname, config, tx.clone());
+            state.rooms.insert(room_id, room.spawn(state.clone()));
+
+            *context = Some((room_id, player_id));
+
+            let _ = tx.send(join_msg).await;
+            info!("玩家 {} 创建了新房间 {}", player_id, room_id);
+        }
+        ClientMessage::JoinRoom { room_id, nickname } => {
+            if let Some((old_room_id, old_player_id)) = context.take() {
+                // 加入申请被拒绝或仍在等待的玩家可以重新申请，已加入房间的玩家不行
+                if let Some(handle) = room_handle(&state, &old_room_id)
+                    && handle.cancel_join(old_player_id).await == Some(true) {
+                    *context = Some((old_room_id, old_player_id));
+                    let _ = tx.send(ServerMessage::Error { message: "你已经在一个房间里了".to_string() }).await;
+                    return;
+                }
+            }
+
+            let player_id = Uuid::new_v4();
+            let player_secret = Uuid::new_v4();
+
+            let joined = match room_handle(&state, &room_id) {
+                Some(handle) => handle.join(player_id, player_secret, nickname, tx.clone()).await,
+                None => None,
+            };
+            match joined {
+                Some(true) => *context = Some((room_id, player_id)),
+                Some(false) => {}
+                None => {
+                    let _ = tx.send(ServerMessage::Error { message: "房间不存在".to_string() }).await;
+                }
+            }
+        }
+        ClientMessage::Reconnect { room_id, player_id, secret, last_seq } => {
+            if context.is_some() {
+                let _ = tx.send(ServerMessage::Error { message: "你已经在一个房间里了".to_string() }).await;
+                return;
+            }
+
+            let reconnected = match room_handle(&state, &room_id) {
+                Some(handle) => handle.reconnect(player_id, secret, last_seq, tx.clone()).await,
+                None => None,
+            };
+            match reconnected {
+                Some(true) => *context = Some((room_id, player_id)),
+                Some(false) => {}
+                None => {
+                    let _ = tx.send(ServerMessage::Error { message: "房间不存在".to_string() }).await;
+                }
+            }
+        }
+        ClientMessage::CheckIn { games } => {
+            if games.len() > MAX_CHECK_IN_GAMES {
+                let message = format!("一次最多查询 {} 个对局", MAX_CHECK_IN_GAMES);
+                let _ = tx.send(ServerMessage::Error { message }).await;
+                return;
+            }
+            let mut statuses = Vec::with_capacity(games.len());
+            for (room_id, player_id, secret) in games {
+                let status = match room_handle(&state, &room_id) {
+                    Some(handle) => handle.check_in(player_id, secret).await,
+                    None => None,
+                };
+                statuses.push(status.unwrap_or(GameCheckIn { room_id, active: false, your_turn: false, deadline: None }));
+            }
+            let _ = tx.send(ServerMessage::CheckInStatus { games: statuses }).await;
+        }
+        ClientMessage::ClockSync { client_time } => {
+            let _ = tx.send(ServerMessage::ClockSync { client_time, server_time: Utc::now() }).await;
+        }
+        // ... 其他需要认证后才能执行的消息
+        _ => {
+            if let Some((room_id, player_id)) = context {
+                let sent = match room_handle(&state, room_id) {
+                    Some(handle) => handle.send(*player_id, msg).await,
+                    None => false,
+                };
+                if !sent {
+                    let _ = tx.send(ServerMessage::Error { message: "房间不存在".to_string() }).await;
+                }
+            } else {
+                let _ = tx.send(ServerMessage::Error { message: "请先加入或创建房间".to_string() }).await;
+            }
+        }
+    }
+}
+
+// 取出房间句柄的副本，避免在 await 期间持有房间表的锁
+pub fn room_handle(state: &SharedState, room_id: &RoomId) -> Option<RoomHandle> {
+    state.rooms.get(room_id).map(|r| r.clone())
+}
+
+/// 向房间内所有玩家广播消息
+pub async fn broadcast(
+    targets: &Vec<(PlayerId, MessageSender)>,
+    message: &ServerMessage,
+    exclude: Option<PlayerId>,
+) {
+    // 只序列化一次，所有连接共享同一份 JSON
+    broadcast_encoded(targets, encode(message), exclude).await;
+}
+
+/// 向房间内所有玩家广播已经序列化好的消息
+pub async fn broadcast_encoded(
+    targets: &Vec<(PlayerId, MessageSender)>,
+    payload: Payload,
+    exclude: Option<PlayerId>,
+) {
+    for (player_id, sender) in targets {
+        if Some(*player_id) == exclude {
+            continue;
+        }
+        if sender.send_encoded(payload.clone()).await.is_err() {
+            // 发送失败，说明该玩家也断开了，后续由其自己的 handle_socket 任务处理
+            tracing::warn!("向玩家 {} 发送消息失败（可能已断开）", player_id);
+        }
+    }
+}
@@ -0,0 +1,294 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 通信对局
+//!
+//! 开启 `RoomOption::Correspondence` 的房间发生变化后保存到 `POKER_EDEN_ROOM_DIR`
+//! (默认为 `saved_rooms`)，服务器重启时读回并恢复，玩家用原来的凭证重连即可继续。
+//! 房主结束游戏后存档移入其中的 `archive` 子目录，留作记录，不再恢复。
+//!
+//! 存档里没有可以直接冒充玩家或预知牌面的信息: 重连凭证只保存 SHA-256 摘要；剩余的牌堆和
+//! 复式对局的种子用 `POKER_EDEN_ROOM_KEY` (64 位十六进制的 256 位密钥) 加密后保存。
+//! 服务器没有配置密钥时不保存这两项，房间只在两局之间保存，恢复后的牌桌退出复式对局。
+//!
+//! 设置了环境变量 `POKER_EDEN_NOTIFY_WEBHOOK` 时，轮到断线的玩家行动，服务器会把玩家留下的
+//! 联系方式和截止时间 POST 到这个地址，由运营者自己的服务转成短信或邮件。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::digest::{digest, SHA256};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use poker_eden_core::*;
+
+/// 每个房间最多积压的提醒条数，超出后丢弃新的提醒
+const QUEUE_SIZE: usize = 16;
+
+/// 保存到磁盘的房间，只包含恢复房间所需的状态，撤回窗口和待确认的动作不保存
+#[derive(Serialize, Deserialize)]
+pub struct SavedRoom {
+    pub room_id: RoomId,
+    pub host_id: PlayerId,
+    /// 游戏状态。牌堆不会序列化，复式对局的种子清零，两者加密后保存在 `sealed`
+    pub game_state: GameState,
+    /// 所有房间成员重连凭证的摘要 (见 [`secret_digest`])，恢复后所有人都处于断线状态
+    pub secret_digests: HashMap<PlayerId, String>,
+    /// 加密后的牌局机密，服务器没有配置密钥时为 None
+    #[serde(default)]
+    pub sealed: Option<String>,
+    /// 玩家留下的联系方式，只用于提醒，不发给其他玩家
+    pub contacts: HashMap<PlayerId, String>,
+    pub timeline: RoomTimeline,
+}
+
+/// 存档中加密保存的牌局机密
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct RoomSecrets {
+    /// 进行中的牌局剩余的牌堆
+    pub deck: Vec<Card>,
+    /// 复式对局的种子
+    pub duplicate_seed: Option<u64>,
+}
+
+/// 重连凭证的 SHA-256 摘要 (十六进制)
+pub fn secret_digest(secret: &PlayerSecret) -> String {
+    to_hex(digest(&SHA256, secret.as_bytes()).as_ref())
+}
+
+/// 服务器是否配置了存档密钥
+pub fn can_seal() -> bool {
+    room_key().is_some()
+}
+
+/// 用存档密钥加密牌局机密，绑定到房间编号。没有配置密钥时返回 None
+pub fn seal(room_id: RoomId, secrets: &RoomSecrets) -> Option<String> {
+    seal_with(room_key()?, room_id, secrets)
+}
+
+/// 解密 [`seal`] 的结果，密钥不对、房间不符或内容被改动时返回 None
+pub fn unseal(room_id: RoomId, sealed: &str) -> Option<RoomSecrets> {
+    unseal_with(room_key()?, room_id, sealed)
+}
+
+fn room_key() -> Option<&'static LessSafeKey> {
+    static KEY: OnceLock<Option<LessSafeKey>> = OnceLock::new();
+    KEY.get_or_init(|| {
+        let hex = std::env::var("POKER_EDEN_ROOM_KEY").ok().filter(|k| !k.is_empty())?;
+        let key = parse_key(&hex);
+        if key.is_none() {
+            error!("POKER_EDEN_ROOM_KEY 应为 64 位十六进制数，存档中的牌堆和复式对局种子不会保存");
+        }
+        key
+    })
+    .as_ref()
+}
+
+fn parse_key(hex: &str) -> Option<LessSafeKey> {
+    let bytes = from_hex(hex.trim()).filter(|b| b.len() == 32)?;
+    Some(LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &bytes).ok()?))
+}
+
+// 输出为十六进制的 nonce 加密文 (含认证标签)
+fn seal_with(key: &LessSafeKey, room_id: RoomId, secrets: &RoomSecrets) -> Option<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce).ok()?;
+    let mut data = serde_json::to_vec(secrets).ok()?;
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::from(room_id.as_bytes()), &mut data).ok()?;
+    Some(to_hex(&[&nonce[..], &data].concat()))
+}
+
+fn unseal_with(key: &LessSafeKey, room_id: RoomId, sealed: &str) -> Option<RoomSecrets> {
+    let bytes = from_hex(sealed)?;
+    if bytes.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, data) = bytes.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+    let mut data = data.to_vec();
+    let plain = key.open_in_place(nonce, Aad::from(room_id.as_bytes()), &mut data).ok()?;
+    serde_json::from_slice(plain).ok()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok()).collect()
+}
+
+fn room_dir() -> PathBuf {
+    PathBuf::from(std::env::var("POKER_EDEN_ROOM_DIR").unwrap_or_else(|_| "saved_rooms".to_string()))
+}
+
+fn room_path(room_id: RoomId) -> PathBuf {
+    room_dir().join(format!("{}.json", room_id))
+}
+
+/// 保存房间。先写入临时文件再改名，服务器在写入途中退出也不会留下损坏的存档
+pub async fn save(room: &SavedRoom) -> std::io::Result<()> {
+    let path = room_path(room.room_id);
+    let tmp = path.with_extension("json.tmp");
+    let contents = serde_json::to_vec(room).map_err(std::io::Error::other)?;
+    tokio::fs::create_dir_all(room_dir()).await?;
+    tokio::fs::write(&tmp, contents).await?;
+    tokio::fs::rename(&tmp, &path).await
+}
+
+/// 把房间的存档移入 `archive` 子目录 (房主结束游戏时)，归档的房间不会在服务器启动时恢复。
+/// 返回归档后的路径
+pub async fn archive(room_id: RoomId) -> std::io::Result<PathBuf> {
+    let dir = room_dir().join("archive");
+    let path = dir.join(format!("{}.json", room_id));
+    tokio::fs::create_dir_all(&dir).await?;
+    tokio::fs::rename(room_path(room_id), &path).await?;
+    Ok(path)
+}
+
+/// 删除房间的存档 (关闭通信对局或房间关闭时)
+pub async fn remove(room_id: RoomId) {
+    match tokio::fs::remove_file(room_path(room_id)).await {
+        Ok(()) => info!("已删除房间 {} 的存档", room_id),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => warn!("删除房间 {} 的存档失败: {}", room_id, e),
+    }
+}
+
+/// 读回所有保存的房间，无法解析的存档跳过并记录日志
+pub fn load_all() -> Vec<SavedRoom> {
+    let Ok(entries) = std::fs::read_dir(room_dir()) else { return vec![] };
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| match load(&path) {
+            Ok(room) => Some(room),
+            Err(e) => {
+                error!("无法读取房间存档 {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+fn load(path: &Path) -> std::io::Result<SavedRoom> {
+    let contents = std::fs::read(path)?;
+    serde_json::from_slice(&contents).map_err(std::io::Error::other)
+}
+
+// 一条待发送的行动提醒
+struct TurnReminder {
+    room_id: RoomId,
+    player_id: PlayerId,
+    nickname: String,
+    contact: String,
+    deadline: Option<DateTime<Utc>>,
+}
+
+/// 行动提醒的发送者。丢弃后发送任务把积压的提醒发完再退出
+pub struct TurnNotifier {
+    tx: mpsc::Sender<TurnReminder>,
+}
+
+impl TurnNotifier {
+    /// 服务器设置了通知钩子时启动发送任务
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("POKER_EDEN_NOTIFY_WEBHOOK").ok().filter(|u| !u.is_empty())?;
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(deliver(url, rx));
+        Some(TurnNotifier { tx })
+    }
+
+    pub fn remind(&self, room_id: RoomId, player: &Player, contact: &str, deadline: Option<DateTime<Utc>>) {
+        let reminder = TurnReminder {
+            room_id,
+            player_id: player.id,
+            nickname: player.nickname.clone(),
+            contact: contact.to_string(),
+            deadline,
+        };
+        if self.tx.try_send(reminder).is_err() {
+            warn!("行动提醒积压过多，丢弃一条提醒");
+        }
+    }
+}
+
+async fn deliver(url: String, mut rx: mpsc::Receiver<TurnReminder>) {
+    let client = Client::new();
+    while let Some(reminder) = rx.recv().await {
+        let body = json!({
+            "room_id": reminder.room_id,
+            "player_id": reminder.player_id,
+            "nickname": reminder.nickname,
+            "contact": reminder.contact,
+            "deadline": reminder.deadline,
+            "message": format!("{}，房间 {} 轮到你行动了", reminder.nickname, reminder.room_id),
+        });
+        match client.post(&url).json(&body).send().await {
+            Ok(response) if !response.status().is_success() => {
+                warn!("通知钩子拒绝了行动提醒: {}", response.status());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("发送行动提醒失败: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_sealed_secrets_round_trip() {
+        let key = parse_key(&"ab".repeat(32)).unwrap();
+        let room_id = Uuid::new_v4();
+        let secrets = RoomSecrets { deck: generate_shuffled_deck(), duplicate_seed: Some(42) };
+        let sealed = seal_with(&key, room_id, &secrets).unwrap();
+        assert_eq!(unseal_with(&key, room_id, &sealed), Some(secrets));
+
+        // 换了房间、换了密钥或内容被改动都无法解密
+        assert_eq!(unseal_with(&key, Uuid::new_v4(), &sealed), None);
+        assert_eq!(unseal_with(&parse_key(&"cd".repeat(32)).unwrap(), room_id, &sealed), None);
+        let mut tampered = sealed.into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'0' { b'1' } else { b'0' };
+        assert_eq!(unseal_with(&key, room_id, &String::from_utf8(tampered).unwrap()), None);
+    }
+
+    #[test]
+    fn test_parse_key_and_digest() {
+        assert!(parse_key("abcd").is_none());
+        assert!(parse_key(&"zz".repeat(32)).is_none());
+        let secret = Uuid::new_v4();
+        assert_eq!(secret_digest(&secret), secret_digest(&secret));
+        assert_eq!(secret_digest(&secret).len(), 64);
+        assert!(!secret_digest(&secret).contains(&secret.simple().to_string()));
+    }
+}
@@ -0,0 +1,160 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! Discord 通知
+//!
+//! 房主输入 `discord <webhook 地址或频道 ID>` 给房间配置一个 Discord 频道后，服务器会把
+//! 开局提醒、每局的结果和房间关闭时的汇总发到这个频道。webhook 地址可以直接使用；
+//! 频道 ID 需要服务器设置环境变量 `POKER_EDEN_DISCORD_BOT_TOKEN`，以机器人的身份发送。
+//!
+//! 发送在独立的任务中进行，Discord 不可用或限流时丢弃通知，不会拖慢房间。
+
+use reqwest::Client;
+use serde_json::json;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use poker_eden_core::*;
+
+/// 每个房间最多积压的通知条数，超出后丢弃新的通知
+const QUEUE_SIZE: usize = 16;
+
+/// Discord 单条消息的长度上限
+const MAX_CONTENT_CHARS: usize = 2000;
+
+const WEBHOOK_PREFIXES: [&str; 2] = ["https://discord.com/api/webhooks/", "https://discordapp.com/api/webhooks/"];
+
+/// 通知发到哪里
+#[derive(Debug, Clone)]
+enum DiscordTarget {
+    Webhook(String),
+    /// 用机器人令牌发到指定 ID 的频道
+    Channel { channel_id: u64, bot_token: String },
+}
+
+impl DiscordTarget {
+    fn parse(target: &str) -> Result<Self, String> {
+        if WEBHOOK_PREFIXES.iter().any(|p| target.starts_with(p)) {
+            return Ok(DiscordTarget::Webhook(target.to_string()));
+        }
+        let Ok(channel_id) = target.parse::<u64>() else {
+            return Err("请输入 Discord webhook 地址或频道 ID".to_string());
+        };
+        match std::env::var("POKER_EDEN_DISCORD_BOT_TOKEN").ok().filter(|t| !t.is_empty()) {
+            Some(bot_token) => Ok(DiscordTarget::Channel { channel_id, bot_token }),
+            None => Err("服务器没有配置 Discord 机器人，请改用 webhook 地址".to_string()),
+        }
+    }
+}
+
+// 一条待发送的通知
+struct Notification {
+    content: String,
+    /// 是否允许 @here 提醒频道里的成员，只有开局提醒会用到
+    ping: bool,
+}
+
+/// 房间的 Discord 通知。丢弃后发送任务把积压的通知发完再退出
+pub struct DiscordNotifier {
+    tx: mpsc::Sender<Notification>,
+    /// 配置后已经开始的局数
+    hands: u32,
+    /// 最近一局结束时的汇总，房间关闭时发出
+    last_summary: Option<SessionSummary>,
+}
+
+impl DiscordNotifier {
+    /// 解析房主给出的 webhook 地址或频道 ID，并启动发送任务
+    pub fn spawn(target: &str) -> Result<Self, String> {
+        let target = DiscordTarget::parse(target)?;
+        let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(deliver(target, rx));
+        Ok(DiscordNotifier { tx, hands: 0, last_summary: None })
+    }
+
+    /// 根据房间广播的事件发送通知: 配置后的第一局开局时提醒，每局结束时发送结果
+    pub fn notify(&mut self, event: &ServerMessage, gs: &GameState) {
+        match event {
+            ServerMessage::HandStarted { hand_player_order, variant, .. } => {
+                self.hands += 1;
+                if self.hands == 1 {
+                    self.send(format!(
+                        "@here 房间 {} 的牌局开始了: {} 位玩家，{}，盲注 ${}/${}",
+                        gs.room_id, hand_player_order.len(), variant, gs.small_blind, gs.big_blind,
+                    ), true);
+                }
+            }
+            ServerMessage::Showdown { results, .. } => {
+                let mut lines = vec![format!("**第 {} 局结果**", self.hands.max(1))];
+                for result in results.iter().filter(|r| r.winnings > 0) {
+                    let nickname = gs.players.get(&result.player_id).map_or("已离开的玩家", |p| p.nickname.as_str());
+                    let rank = match (&result.cards, &result.hand_rank) {
+                        (Some(_), Some(rank)) => format!(" ({})", rank),
+                        _ => String::new(),
+                    };
+                    lines.push(format!("{} 赢得 ${}{}", nickname, result.winnings, rank));
+                }
+                self.send(lines.join("\n"), false);
+                self.last_summary = Some(gs.session_summary());
+            }
+            _ => {}
+        }
+    }
+
+    /// 房间关闭时发送本次游戏的汇总，一局都没打完时不发送
+    pub fn close(self, room_id: RoomId) {
+        let Some(summary) = &self.last_summary else { return };
+        let mut lines = vec![format!("**房间 {} 已关闭，共 {} 局**", room_id, self.hands)];
+        for p in &summary.players {
+            lines.push(format!("{}: ${} (赢 {} 局，输 {} 局)", p.nickname, p.stack, p.wins, p.losses));
+        }
+        self.send(lines.join("\n"), false);
+    }
+
+    fn send(&self, mut content: String, ping: bool) {
+        if let Some((idx, _)) = content.char_indices().nth(MAX_CONTENT_CHARS) {
+            content.truncate(idx);
+        }
+        if self.tx.try_send(Notification { content, ping }).is_err() {
+            warn!("Discord 通知积压过多，丢弃一条通知");
+        }
+    }
+}
+
+async fn deliver(target: DiscordTarget, mut rx: mpsc::Receiver<Notification>) {
+    let client = Client::new();
+    while let Some(notification) = rx.recv().await {
+        // 昵称由玩家填写，除了开局提醒的 @here 之外不解析任何提及
+        let body = json!({
+            "content": notification.content,
+            "allowed_mentions": { "parse": if notification.ping { vec!["everyone"] } else { vec![] } },
+        });
+        let request = match &target {
+            DiscordTarget::Webhook(url) => client.post(url),
+            DiscordTarget::Channel { channel_id, bot_token } => client
+                .post(format!("https://discord.com/api/v10/channels/{}/messages", channel_id))
+                .header("Authorization", format!("Bot {}", bot_token)),
+        };
+        match request.json(&body).send().await {
+            Ok(response) if !response.status().is_success() => {
+                warn!("Discord 拒绝了通知: {}", response.status());
+            }
+            Ok(_) => {}
+            Err(e) => warn!("发送 Discord 通知失败: {}", e),
+        }
+    }
+}
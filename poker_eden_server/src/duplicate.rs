@@ -0,0 +1,125 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 复式对局的配对
+//!
+//! 一场复式对局由一张牌桌的房主开始，服务器生成洗牌的种子并分配对局编号；其他牌桌的房主凭编号加入，
+//! 依次分到下一个牌桌序号。种子只保存在服务器上 (这里和各房间的游戏状态中)，不会发给客户端。
+//! 服务器重启后，从存档恢复的房间会把自己的对局重新登记回来。最后一张牌桌退出对局或关闭后，对局被删除。
+
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use uuid::Uuid;
+
+use poker_eden_core::{DuplicateDeal, MAX_SEATS};
+
+/// 一场复式对局
+struct DuplicateMatch {
+    seed: u64,
+    // 已经分配出去的牌桌数，退出的牌桌序号不会再分配
+    tables: u8,
+    // 还在对局中的牌桌数
+    active: u8,
+}
+
+/// 服务器上所有的复式对局，可以在房间之间随意克隆
+#[derive(Clone, Default)]
+pub struct DuplicateRegistry(Arc<DashMap<Uuid, DuplicateMatch>>);
+
+impl DuplicateRegistry {
+    /// 开始一场新的复式对局，返回第 0 桌的发牌安排
+    pub fn start(&self) -> DuplicateDeal {
+        let match_id = Uuid::new_v4();
+        // v4 UUID 的随机部分足以作为种子，不会被客户端猜到
+        let seed = Uuid::new_v4().as_u64_pair().0;
+        self.0.insert(match_id, DuplicateMatch { seed, tables: 1, active: 1 });
+        DuplicateDeal { match_id, seed, table: 0, hands: 0 }
+    }
+
+    /// 加入已有的复式对局，分配下一个牌桌序号。对局不存在或牌桌已满时返回 None
+    pub fn join(&self, match_id: Uuid) -> Option<DuplicateDeal> {
+        let mut entry = self.0.get_mut(&match_id)?;
+        if entry.tables >= MAX_SEATS {
+            return None;
+        }
+        let table = entry.tables;
+        entry.tables += 1;
+        entry.active += 1;
+        Some(DuplicateDeal { match_id, seed: entry.seed, table, hands: 0 })
+    }
+
+    /// 把从存档恢复的牌桌所在的对局重新登记，之后加入的牌桌不会和它重号
+    pub fn restore(&self, deal: &DuplicateDeal) {
+        let mut entry = self.0.entry(deal.match_id).or_insert(DuplicateMatch { seed: deal.seed, tables: 0, active: 0 });
+        entry.tables = entry.tables.max(deal.table + 1);
+        entry.active += 1;
+    }
+
+    /// 一张牌桌退出对局 (房主停止复式对局、换到其他对局或房间关闭)，最后一张牌桌退出后删除对局
+    pub fn leave(&self, match_id: Uuid) {
+        self.0.remove_if_mut(&match_id, |_, entry| {
+            entry.active = entry.active.saturating_sub(1);
+            entry.active == 0
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_assigns_table_numbers() {
+        let registry = DuplicateRegistry::default();
+        let first = registry.start();
+        assert_eq!(first.table, 0);
+        for table in 1..MAX_SEATS {
+            let deal = registry.join(first.match_id).unwrap();
+            assert_eq!((deal.table, deal.seed), (table, first.seed));
+        }
+        assert!(registry.join(first.match_id).is_none());
+        assert!(registry.join(Uuid::new_v4()).is_none());
+    }
+
+    #[test]
+    fn test_restore_keeps_table_numbers() {
+        let registry = DuplicateRegistry::default();
+        let deal = DuplicateDeal { match_id: Uuid::new_v4(), seed: 42, table: 2, hands: 5 };
+        registry.restore(&deal);
+        let joined = registry.join(deal.match_id).unwrap();
+        assert_eq!((joined.table, joined.seed, joined.hands), (3, 42, 0));
+        // 序号更小的牌桌后恢复时不影响下一个序号
+        registry.restore(&DuplicateDeal { table: 0, ..deal });
+        assert_eq!(registry.join(deal.match_id).unwrap().table, 4);
+    }
+
+    #[test]
+    fn test_match_removed_after_last_table_leaves() {
+        let registry = DuplicateRegistry::default();
+        let first = registry.start();
+        let second = registry.join(first.match_id).unwrap();
+        registry.leave(first.match_id);
+        // 退出的牌桌序号不会再分配
+        assert_eq!(registry.join(first.match_id).unwrap().table, 2);
+        registry.leave(second.match_id);
+        registry.leave(second.match_id);
+        assert!(registry.0.is_empty());
+        assert!(registry.join(first.match_id).is_none());
+        registry.leave(first.match_id);
+    }
+}
@@ -0,0 +1,141 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 房间事件流和直播叠加层
+//!
+//! 给直播叠加层、统计机器人等外部工具使用的只读接口，与玩家协议分开:
+//! - 事件流 (WebSocket): 订阅后先收到一份不含任何底牌的房间快照，之后是房间里所有公开广播的事件
+//! - 叠加层 (HTTP GET): 观众视角的牌桌摘要 JSON，每个事件后刷新，供 OBS 浏览器源轮询
+//!
+//! 只有设置了环境变量 `POKER_EDEN_EVENTS_TOKEN` 才会开启，请求需要带上这个令牌。
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        ConnectInfo, Path, Query, State, WebSocketUpgrade,
+    },
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures_util::SinkExt;
+use tracing::info;
+
+use poker_eden_core::RoomId;
+
+use crate::access::FORBIDDEN_MESSAGE;
+use crate::connection::{room_handle, MessageSender, HEARTBEAT_INTERVAL};
+use crate::SharedState;
+
+/// 处理事件流的订阅请求: `GET /rooms/{id}/events`
+pub async fn events_handler(
+    ws: WebSocketUpgrade,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(room_id): Path<RoomId>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    State(state): State<SharedState>,
+) -> Response {
+    if state.access.check(addr.ip(), "/rooms/{id}/events").is_err() {
+        return (StatusCode::FORBIDDEN, FORBIDDEN_MESSAGE).into_response();
+    }
+    if let Err(rejection) = authorize(&state, &headers, &query) {
+        return rejection.into_response();
+    }
+    if room_handle(&state, &room_id).is_none() {
+        return (StatusCode::NOT_FOUND, "房间不存在").into_response();
+    }
+    ws.on_upgrade(move |socket| stream_events(socket, state, room_id))
+}
+
+/// 返回直播叠加层 JSON: `GET /rooms/{id}/overlay`，令牌的传法与事件流相同。
+/// 允许跨域读取，本地的叠加层网页可以直接轮询
+pub async fn overlay_handler(
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    Path(room_id): Path<RoomId>,
+    Query(query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    State(state): State<SharedState>,
+) -> Response {
+    if state.access.check(addr.ip(), "/rooms/{id}/overlay").is_err() {
+        return (StatusCode::FORBIDDEN, FORBIDDEN_MESSAGE).into_response();
+    }
+    if let Err(rejection) = authorize(&state, &headers, &query) {
+        return rejection.into_response();
+    }
+    let Some(handle) = room_handle(&state, &room_id) else {
+        return (StatusCode::NOT_FOUND, "房间不存在").into_response();
+    };
+    let Some(overlay) = handle.overlay().await else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "叠加层暂时不可用").into_response();
+    };
+    let mut response = overlay.to_string().into_response();
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response_headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    response
+}
+
+/// 检查请求带的令牌: `Authorization: Bearer <令牌>` 请求头或 `token` 查询参数
+fn authorize(state: &SharedState, headers: &HeaderMap, query: &HashMap<String, String>) -> Result<(), (StatusCode, &'static str)> {
+    let Some(expected) = state.events_token.as_deref() else {
+        return Err((StatusCode::NOT_FOUND, "事件流未开启"));
+    };
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or(query.get("token").map(String::as_str));
+    if token != Some(expected) {
+        return Err((StatusCode::UNAUTHORIZED, "令牌无效"));
+    }
+    Ok(())
+}
+
+/// 把房间事件转发给订阅者，直到订阅者断开或房间关闭
+async fn stream_events(mut socket: WebSocket, state: SharedState, room_id: RoomId) {
+    let (tx, mut rx) = MessageSender::channel(64);
+    let Some(handle) = room_handle(&state, &room_id) else { return };
+    if !handle.subscribe(tx).await {
+        return;
+    }
+    info!("房间 {} 新增了一个事件流订阅", room_id);
+
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+    loop {
+        let ws_msg = tokio::select! {
+            msg = rx.recv() => match msg {
+                Some(payload) => payload.to_frame(false),
+                // 房间关闭或订阅者跟不上被移除
+                None => break,
+            },
+            _ = heartbeat.tick() => Message::Ping(Default::default()),
+            // 只读接口，忽略订阅者发来的消息，只用来发现连接关闭
+            msg = socket.recv() => match msg {
+                Some(Ok(_)) => continue,
+                _ => break,
+            },
+        };
+        if socket.send(ws_msg).await.is_err() {
+            break;
+        }
+    }
+    let _ = socket.close().await;
+}
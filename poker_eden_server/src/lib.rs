@@ -0,0 +1,125 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 扑克乐园的服务器: 房间 actor、玩家连接、事件流和网页客户端
+//!
+//! 可执行文件只负责初始化日志后调用 [`run`]。
+
+mod access;
+mod connection;
+mod correspondence;
+mod discord;
+mod duplicate;
+mod events;
+mod replay;
+mod room;
+
+// 基准测试需要直接使用连接的广播函数
+pub use connection::{broadcast_encoded, encode, MessageSender, Payload};
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::{middleware, routing::get, Router};
+use dashmap::DashMap;
+use tower_http::services::ServeDir;
+use tracing::{error, info};
+
+use poker_eden_core::{RoomCloseReason, RoomId};
+
+use crate::access::AccessPolicy;
+use crate::connection::websocket_handler;
+use crate::duplicate::DuplicateRegistry;
+use crate::events::{events_handler, overlay_handler};
+use crate::room::{Room, RoomHandle};
+
+// 服务器全局状态。房间表只保存各房间 actor 的句柄，
+// 房间状态由房间自己的任务独占，处理消息时不会锁住其他房间
+struct AppState {
+    rooms: DashMap<RoomId, RoomHandle>,
+    // 复式对局的配对，房间之间共享洗牌的种子
+    duplicates: DuplicateRegistry,
+    // 事件流的访问令牌，未设置时不开启事件流
+    events_token: Option<String>,
+    // 按 IP 和国家限制 WebSocket 连接
+    access: AccessPolicy,
+}
+
+type SharedState = Arc<AppState>;
+
+/// 手机网页客户端的静态文件目录
+fn web_dir() -> PathBuf {
+    PathBuf::from(std::env::var("POKER_EDEN_WEB_DIR").unwrap_or_else(|_| "poker_eden_web/www".to_string()))
+}
+
+/// 读取环境变量中的配置，恢复存档的房间，然后开始监听，直到收到 Ctrl+C
+pub async fn run() {
+    let access = AccessPolicy::from_env().unwrap_or_else(|e| {
+        error!("访问限制配置错误: {}", e);
+        std::process::exit(2);
+    });
+    if access.is_enabled() {
+        info!("已开启按 IP 和国家的连接限制");
+    }
+
+    let state = SharedState::new(AppState {
+        rooms: DashMap::new(),
+        duplicates: DuplicateRegistry::default(),
+        events_token: std::env::var("POKER_EDEN_EVENTS_TOKEN").ok().filter(|t| !t.is_empty()),
+        access,
+    });
+
+    // 恢复服务器重启前保存的通信对局
+    for saved in correspondence::load_all() {
+        let room_id = saved.room_id;
+        state.rooms.insert(room_id, Room::restore(saved).spawn(state.clone()));
+        info!("已从存档恢复通信对局房间 {}", room_id);
+    }
+
+    // 手机网页客户端 (poker_eden_web 的静态文件和编译出的 WebAssembly)，同样受访问限制
+    let play = Router::new()
+        .nest_service("/play", ServeDir::new(web_dir()))
+        .layer(middleware::from_fn_with_state(state.clone(), access::guard));
+    let app = Router::new()
+        .route("/ws", get(websocket_handler))
+        .route("/rooms/{id}/events", get(events_handler))
+        .route("/rooms/{id}/overlay", get(overlay_handler))
+        .merge(play)
+        .with_state(state.clone());
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], 25917));
+    info!("服务器正在监听 {}", addr);
+    // 记录连接的来源地址，供访问限制使用
+    let service = app.into_make_service_with_connect_info::<SocketAddr>();
+    axum::serve(tokio::net::TcpListener::bind(addr).await.unwrap(), service)
+        .with_graceful_shutdown(shutdown_signal(state.clone()))
+        .await
+        .unwrap();
+}
+
+/// 等待 Ctrl+C，然后通知所有房间服务器正在关闭。通信对局的存档会保留，重启后恢复
+async fn shutdown_signal(state: SharedState) {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        error!("监听关闭信号失败: {}", e);
+        std::future::pending::<()>().await;
+    }
+    info!("服务器正在关闭，通知所有房间");
+    // 先取出句柄再关闭，房间关闭时会从房间表中移除自己
+    let handles: Vec<RoomHandle> = state.rooms.iter().map(|r| r.clone()).collect();
+    futures_util::future::join_all(handles.iter().map(|h| h.close(RoomCloseReason::ServerShutdown))).await;
+}
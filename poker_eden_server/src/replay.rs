@@ -0,0 +1,111 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 断线重连时的事件补发
+//!
+//! 房间给每个广播的事件分配递增的序号 (`ServerMessage::Event`)，并为每位成员保留最近
+//! `REPLAY_BUFFER_LEN` 个发给他的事件，断线期间也继续缓冲。玩家重连时带上最后收到的序号，
+//! 缓冲区还能覆盖时补发之后的事件，客户端的日志和牌局历史不会出现空缺；否则退回发送状态快照。
+
+use std::collections::VecDeque;
+
+use crate::connection::Payload;
+
+/// 每位玩家最多缓冲的事件数
+pub const REPLAY_BUFFER_LEN: usize = 256;
+
+/// 一位玩家最近收到的事件 (已序列化的 JSON)
+pub struct ReplayBuffer {
+    events: VecDeque<(u64, Payload)>,
+    // 小于等于这个序号的事件已经不在缓冲区中 (被挤出，或者发生在玩家加入之前)
+    evicted_through: u64,
+}
+
+impl ReplayBuffer {
+    /// 从序号 `next_seq` 开始缓冲的空缓冲区
+    pub fn new(next_seq: u64) -> Self {
+        ReplayBuffer { events: VecDeque::new(), evicted_through: next_seq.saturating_sub(1) }
+    }
+
+    pub fn push(&mut self, seq: u64, payload: Payload) {
+        if self.events.len() >= REPLAY_BUFFER_LEN
+            && let Some((evicted, _)) = self.events.pop_front() {
+            self.evicted_through = evicted;
+        }
+        self.events.push_back((seq, payload));
+    }
+
+    /// 序号 `last_seq` 之后的所有事件，有事件已经被挤出缓冲区时返回 None
+    pub fn since(&self, last_seq: u64) -> Option<Vec<Payload>> {
+        if last_seq < self.evicted_through {
+            return None;
+        }
+        Some(self.events.iter().filter(|(seq, _)| *seq > last_seq).map(|(_, p)| p.clone()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_all(buffer: &mut ReplayBuffer, seqs: impl IntoIterator<Item = u64>) {
+        for seq in seqs {
+            buffer.push(seq, seq.to_string().into());
+        }
+    }
+
+    fn seqs(payloads: Option<Vec<Payload>>) -> Option<Vec<u64>> {
+        payloads.map(|ps| ps.iter().map(|p| p.parse().unwrap()).collect())
+    }
+
+    #[test]
+    fn test_since_skips_sequence_gaps() {
+        // 只发给其他成员的事件不进入这位玩家的缓冲区，序号会有空缺
+        let mut buffer = ReplayBuffer::new(1);
+        push_all(&mut buffer, [1, 3, 4, 7]);
+        assert_eq!(seqs(buffer.since(0)), Some(vec![1, 3, 4, 7]));
+        assert_eq!(seqs(buffer.since(2)), Some(vec![3, 4, 7]));
+        assert_eq!(seqs(buffer.since(5)), Some(vec![7]));
+        assert_eq!(seqs(buffer.since(7)), Some(vec![]));
+    }
+
+    #[test]
+    fn test_overflow_evicts_oldest() {
+        let mut buffer = ReplayBuffer::new(1);
+        let last = REPLAY_BUFFER_LEN as u64 + 2;
+        push_all(&mut buffer, 1..=last);
+        assert_eq!(buffer.events.len(), REPLAY_BUFFER_LEN);
+        // 序号 1 和 2 已被挤出，从更早的位置恢复只能退回快照
+        assert_eq!(seqs(buffer.since(0)), None);
+        assert_eq!(seqs(buffer.since(1)), None);
+        let replayed = seqs(buffer.since(2)).unwrap();
+        assert_eq!(replayed, (3..=last).collect::<Vec<_>>());
+        assert_eq!(seqs(buffer.since(last)), Some(vec![]));
+    }
+
+    #[test]
+    fn test_resume_from_join_seq() {
+        // 玩家加入前的事件不在缓冲区中
+        let mut buffer = ReplayBuffer::new(10);
+        assert_eq!(seqs(buffer.since(9)), Some(vec![]));
+        assert_eq!(seqs(buffer.since(8)), None);
+        push_all(&mut buffer, [10, 11]);
+        assert_eq!(seqs(buffer.since(9)), Some(vec![10, 11]));
+        assert_eq!(seqs(buffer.since(10)), Some(vec![11]));
+        assert_eq!(seqs(buffer.since(0)), None);
+    }
+}
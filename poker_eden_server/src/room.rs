@@ -0,0 +1,2339 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! 房间 actor
+//!
+//! 每个房间运行在自己的 tokio 任务中，独占房间状态，通过 mpsc 通道按顺序处理命令。
+//! 连接任务只持有房间的 `RoomHandle`，不再在处理消息期间锁住全局的房间表，
+//! 不同房间之间互不阻塞，也不会因为跨 await 持有锁而死锁。
+//!
+//! 房间任务会捕获处理命令时的 panic: 出错的房间被冻结并通知所有玩家，
+//! 房间状态写入转储文件用于事后排查，其他房间和服务器不受影响。
+
+use std::any::Any;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use futures_util::FutureExt;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use poker_eden_core::{Audience, ClientMessage, Describe, GameCheckIn, GameError, GamePhase, GameResult, GameState, HostAction, IntoMessages, Locale, Player, PlayerAction, PlayerId, PlayerSecret, PlayerState, RoomId, RoomCloseReason, RoomConfig, RoomOption, RoomTimeline, ServerMessage, TableOverlay, VoteProposal};
+
+use crate::connection::{broadcast, broadcast_encoded, encode, MessageSender, Payload};
+use crate::correspondence::{self, RoomSecrets, SavedRoom, TurnNotifier};
+use crate::discord::DiscordNotifier;
+use crate::duplicate::DuplicateRegistry;
+use crate::replay::ReplayBuffer;
+use crate::SharedState;
+
+/// 房间被冻结后发给玩家的提示
+const FROZEN_MESSAGE: &str = "房间发生内部错误，已被冻结，请离开房间后重新创建";
+
+/// 行动提醒联系方式的长度上限
+const MAX_CONTACT_CHARS: usize = 100;
+
+/// 刷屏保护: 每位玩家在 `CHAT_WINDOW` 内最多发言 `CHAT_BURST` 次
+const CHAT_BURST: usize = 5;
+const CHAT_WINDOW: Duration = Duration::from_secs(10);
+
+/// 房间超过这段时间没有玩家活动就会过期关闭 (通信对局除外)
+const ROOM_IDLE_TIMEOUT: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// 通信对局在牌局进行中最多每隔这段时间保存一次，期间的变化合并到下一次保存
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 发给房间 actor 的命令
+enum RoomCommand {
+    /// 新玩家申请加入房间，回复是否已登记 (加入或进入审批队列)
+    Join {
+        player_id: PlayerId,
+        secret: PlayerSecret,
+        nickname: String,
+        sender: MessageSender,
+        reply: oneshot::Sender<bool>,
+    },
+    /// 断线的玩家凭凭证重新连接，回复是否成功
+    Reconnect {
+        player_id: PlayerId,
+        secret: PlayerSecret,
+        last_seq: Option<u64>,
+        sender: MessageSender,
+        reply: oneshot::Sender<bool>,
+    },
+    /// 撤回尚未被批准的加入申请，回复该玩家是否已经是房间成员
+    CancelJoin {
+        player_id: PlayerId,
+        reply: oneshot::Sender<bool>,
+    },
+    /// 房间成员发来的消息
+    Client {
+        player_id: PlayerId,
+        msg: ClientMessage,
+    },
+    /// 撤回窗口结束，执行仍未被撤回的动作
+    CommitAction {
+        action_id: Uuid,
+    },
+    /// 行动计时结束，替仍未行动的玩家过牌或弃牌
+    TurnTimeout {
+        timer_id: Uuid,
+    },
+    /// 锦标赛按时间升级时，当前盲注级别到时
+    BlindLevelTimeout {
+        ends_at: DateTime<Utc>,
+    },
+    /// 投票到时，仍没有结果的投票被否决
+    VoteTimeout {
+        vote_id: Uuid,
+    },
+    /// 查询玩家是否轮到行动，凭证无效时回复 None
+    CheckIn {
+        player_id: PlayerId,
+        secret: PlayerSecret,
+        reply: oneshot::Sender<Option<GameCheckIn>>,
+    },
+    /// 读取直播叠加层 JSON，生成失败时回复 None
+    Overlay {
+        reply: oneshot::Sender<Option<Arc<str>>>,
+    },
+    /// 外部工具订阅房间的事件流
+    Subscribe {
+        sender: MessageSender,
+        reply: oneshot::Sender<bool>,
+    },
+    /// 玩家断开连接，`sender` 用于识别断开的是哪一个连接
+    Disconnect {
+        player_id: PlayerId,
+        sender: MessageSender,
+    },
+    /// 检查房间是否已经长时间没有活动
+    IdleTimeout,
+    /// 保存推迟到期，把期间的变化写入存档
+    Save,
+    /// 关闭房间，通知所有玩家后回复
+    Close {
+        reason: RoomCloseReason,
+        reply: oneshot::Sender<()>,
+    },
+}
+
+impl RoomCommand {
+    /// 命令的简要描述，写入转储文件
+    fn summary(&self) -> String {
+        match self {
+            RoomCommand::Join { player_id, nickname, .. } => format!("Join {{ player_id: {player_id}, nickname: {nickname:?} }}"),
+            RoomCommand::Reconnect { player_id, .. } => format!("Reconnect {{ player_id: {player_id} }}"),
+            RoomCommand::CancelJoin { player_id, .. } => format!("CancelJoin {{ player_id: {player_id} }}"),
+            RoomCommand::Client { player_id, msg } => format!("Client {{ player_id: {player_id}, msg: {msg:?} }}"),
+            RoomCommand::CommitAction { action_id } => format!("CommitAction {{ action_id: {action_id} }}"),
+            RoomCommand::TurnTimeout { timer_id } => format!("TurnTimeout {{ timer_id: {timer_id} }}"),
+            RoomCommand::VoteTimeout { vote_id } => format!("VoteTimeout {{ vote_id: {vote_id} }}"),
+            RoomCommand::BlindLevelTimeout { ends_at } => format!("BlindLevelTimeout {{ ends_at: {ends_at} }}"),
+            RoomCommand::CheckIn { player_id, .. } => format!("CheckIn {{ player_id: {player_id} }}"),
+            RoomCommand::Overlay { .. } => "Overlay".to_string(),
+            RoomCommand::Subscribe { .. } => "Subscribe".to_string(),
+            RoomCommand::Disconnect { player_id, .. } => format!("Disconnect {{ player_id: {player_id} }}"),
+            RoomCommand::IdleTimeout => "IdleTimeout".to_string(),
+            RoomCommand::Save => "Save".to_string(),
+            RoomCommand::Close { reason, .. } => format!("Close {{ reason: {reason:?} }}"),
+        }
+    }
+}
+
+/// 房间 actor 的句柄，可以在连接任务之间随意克隆
+#[derive(Clone)]
+pub struct RoomHandle(mpsc::Sender<RoomCommand>);
+
+impl RoomHandle {
+    /// 申请加入房间。返回 None 表示房间已经关闭
+    pub async fn join(&self, player_id: PlayerId, secret: PlayerSecret, nickname: String, sender: MessageSender) -> Option<bool> {
+        let (reply, rx) = oneshot::channel();
+        self.0.send(RoomCommand::Join { player_id, secret, nickname, sender, reply }).await.ok()?;
+        rx.await.ok()
+    }
+
+    /// 断线重连，`last_seq` 是客户端最后收到的事件序号。返回 None 表示房间已经关闭
+    pub async fn reconnect(&self, player_id: PlayerId, secret: PlayerSecret, last_seq: Option<u64>, sender: MessageSender) -> Option<bool> {
+        let (reply, rx) = oneshot::channel();
+        self.0.send(RoomCommand::Reconnect { player_id, secret, last_seq, sender, reply }).await.ok()?;
+        rx.await.ok()
+    }
+
+    /// 撤回加入申请。返回 Some(true) 表示该玩家已经是房间成员，不能撤回
+    pub async fn cancel_join(&self, player_id: PlayerId) -> Option<bool> {
+        let (reply, rx) = oneshot::channel();
+        self.0.send(RoomCommand::CancelJoin { player_id, reply }).await.ok()?;
+        rx.await.ok()
+    }
+
+    /// 转发玩家的消息。返回 false 表示房间已经关闭
+    pub async fn send(&self, player_id: PlayerId, msg: ClientMessage) -> bool {
+        self.0.send(RoomCommand::Client { player_id, msg }).await.is_ok()
+    }
+
+    /// 查询玩家在房间里是否轮到行动。返回 None 表示房间已经关闭或凭证无效
+    pub async fn check_in(&self, player_id: PlayerId, secret: PlayerSecret) -> Option<GameCheckIn> {
+        let (reply, rx) = oneshot::channel();
+        self.0.send(RoomCommand::CheckIn { player_id, secret, reply }).await.ok()?;
+        rx.await.ok().flatten()
+    }
+
+    /// 订阅事件流。返回 false 表示房间已经关闭或被冻结
+    pub async fn subscribe(&self, sender: MessageSender) -> bool {
+        let (reply, rx) = oneshot::channel();
+        if self.0.send(RoomCommand::Subscribe { sender, reply }).await.is_err() {
+            return false;
+        }
+        rx.await.unwrap_or(false)
+    }
+
+    /// 当前牌桌的直播叠加层 JSON。返回 None 表示房间已经关闭、被冻结或生成失败
+    pub async fn overlay(&self) -> Option<Arc<str>> {
+        let (reply, rx) = oneshot::channel();
+        self.0.send(RoomCommand::Overlay { reply }).await.ok()?;
+        rx.await.ok().flatten()
+    }
+
+    pub async fn disconnect(&self, player_id: PlayerId, sender: MessageSender) {
+        let _ = self.0.send(RoomCommand::Disconnect { player_id, sender }).await;
+    }
+
+    /// 关闭房间，等所有玩家都收到通知后返回
+    pub async fn close(&self, reason: RoomCloseReason) {
+        let (reply, rx) = oneshot::channel();
+        if self.0.send(RoomCommand::Close { reason, reply }).await.is_ok() {
+            let _ = rx.await;
+        }
+    }
+}
+
+// 单个房间的状态，只由房间自己的任务访问
+pub struct Room {
+    room_id: RoomId,
+    game_state: GameState,
+    host_id: PlayerId,
+    // 将 PlayerId 映射到具体的网络连接
+    players: HashMap<PlayerId, PlayerConnection>,
+    // 撤回窗口内等待生效的动作
+    pending_action: Option<PendingAction>,
+    // 开启行动计时时，当前行动玩家的倒计时
+    turn_timer: Option<TurnTimer>,
+    // 确认动作模式下，每个玩家等待确认的动作
+    pending_confirms: HashMap<PlayerId, PendingConfirm>,
+    // 开启筹码账户时，等待房主批准的存入申请
+    pending_deposits: HashMap<PlayerId, u32>,
+    // 开启加入审批时，等待房主批准的加入申请
+    pending_joins: HashMap<PlayerId, PendingJoin>,
+    // 已断线玩家重连凭证的摘要 (见 `correspondence::secret_digest`)
+    disconnected: HashMap<PlayerId, String>,
+    // 下一个广播事件的序号
+    next_seq: u64,
+    // 每位成员 (包括断线的) 最近收到的事件，重连时补发
+    replay: HashMap<PlayerId, ReplayBuffer>,
+    // 广播给整个房间的所有事件，房主可以导出存档
+    timeline: RoomTimeline,
+    // 事件流的订阅者，收到的事件与时间线相同
+    subscribers: Vec<MessageSender>,
+    // 直播叠加层 JSON 的缓存。记录新事件后作废，有人轮询时才重新生成
+    overlay: Option<Arc<str>>,
+    // 房主配置的 Discord 通知
+    discord: Option<DiscordNotifier>,
+    // 通信对局中玩家留下的联系方式，轮到断线的玩家行动时提醒他
+    contacts: HashMap<PlayerId, String>,
+    turn_notifier: Option<TurnNotifier>,
+    // 房间是否已经保存到磁盘，关闭通信对局或房间关闭时删除存档
+    saved: bool,
+    // 上次保存之后房间是否发生了变化
+    dirty: bool,
+    // 上次保存的时间，牌局进行中据此推迟保存
+    last_saved: Option<Instant>,
+    // 是否已经安排了一次推迟的保存
+    save_scheduled: bool,
+    // 每位玩家最近几次发言的时间，用于刷屏保护
+    chat_times: HashMap<PlayerId, VecDeque<Instant>>,
+    // 指向自己的命令通道，用于定时任务把命令发回房间；使用弱引用，不影响房间关闭
+    self_tx: Option<mpsc::WeakSender<RoomCommand>>,
+    // 处理命令时发生过 panic，房间状态不再可信，只允许玩家离开
+    frozen: bool,
+    // 最近一次玩家活动 (加入、重连或发来消息) 的时间，用于判断房间是否过期
+    last_activity: Instant,
+    // 房主要求结束本次游戏，等本局结束后关闭房间
+    ending_session: bool,
+    // 牌局中被移出的玩家，本局结束后从房间中删除
+    kicked: HashSet<PlayerId>,
+    // 服务器上所有的复式对局，房间启动时从服务器全局状态中取得
+    duplicates: DuplicateRegistry,
+}
+
+// 等待房主批准的加入申请，批准后用这些信息完成加入
+struct PendingJoin {
+    nickname: String,
+    secret: PlayerSecret,
+    sender: MessageSender,
+}
+
+// 提交后尚未生效、可以撤回的玩家动作
+struct PendingAction {
+    id: Uuid,
+    player_id: PlayerId,
+    action: PlayerAction,
+}
+
+// 行动计时，超时命令带着 `id` 发回房间，只有仍是当前计时才会生效
+struct TurnTimer {
+    id: Uuid,
+    player_id: PlayerId,
+    // 基本计时的截止时间，超出的部分从时间银行中扣除
+    base_deadline: DateTime<Utc>,
+    // 实际的截止时间，启用时间银行后包含剩余的时间银行
+    deadline: DateTime<Utc>,
+    time_bank: bool,
+}
+
+// 已回显给玩家、等待确认的动作，记录回显时的局面用于判断动作是否过期
+struct PendingConfirm {
+    id: Uuid,
+    action: PlayerAction,
+    phase: GamePhase,
+    max_bet: u32,
+}
+
+// 玩家的网络连接信息
+struct PlayerConnection {
+    // 断线重连凭证，断线后移入 `Room::disconnected`
+    secret: PlayerSecret,
+    // 用于向该玩家的 WebSocket 任务发送消息的通道
+    sender: MessageSender,
+}
+
+impl Room {
+    /// 按房主的配置创建一个只有房主的新房间，返回房间和发给房主的 RoomJoined
+    pub fn new(
+        room_id: RoomId,
+        host_id: PlayerId,
+        host_secret: PlayerSecret,
+        nickname: String,
+        config: RoomConfig,
+        sender: MessageSender,
+    ) -> (Self, ServerMessage) {
+        let mut game_state = GameState::default();
+        game_state.room_id = room_id;
+        game_state.configure(config);
+        let mut room = Room {
+            room_id,
+            timeline: RoomTimeline::new(game_state.for_client(Audience::Spectator), Utc::now()),
+            game_state,
+            host_id,
+            players: HashMap::new(),
+            pending_action: None,
+            turn_timer: None,
+            pending_confirms: HashMap::new(),
+            pending_deposits: HashMap::new(),
+            pending_joins: HashMap::new(),
+            disconnected: HashMap::new(),
+            next_seq: 1,
+            replay: HashMap::new(),
+            subscribers: vec![],
+            overlay: None,
+            discord: None,
+            contacts: HashMap::new(),
+            turn_notifier: None,
+            saved: false,
+            dirty: true,
+            last_saved: None,
+            save_scheduled: false,
+            chat_times: HashMap::new(),
+            self_tx: None,
+            frozen: false,
+            last_activity: Instant::now(),
+            ending_session: false,
+            kicked: HashSet::new(),
+            duplicates: DuplicateRegistry::default(),
+        };
+        let (join_broadcast_msg, join_msg) = room.admit_player(host_id, host_secret, nickname, sender);
+        room.timeline.record(join_broadcast_msg, Utc::now());
+        (room, join_msg)
+    }
+
+    /// 从存档恢复通信对局的房间，所有玩家都处于断线状态，用原来的凭证重连。
+    /// 解不开加密的牌局机密时，进行中的牌局作废 (退还本局的投入)，牌桌退出复式对局
+    pub fn restore(saved: SavedRoom) -> Self {
+        let turn_notifier = if saved.contacts.is_empty() { None } else { TurnNotifier::from_env() };
+        let mut game_state = saved.game_state;
+        let mut timeline = saved.timeline;
+        let secrets = saved.sealed.as_deref().and_then(|sealed| correspondence::unseal(saved.room_id, sealed));
+        let RoomSecrets { deck, duplicate_seed } = secrets.unwrap_or_default();
+        if game_state.hand_in_progress() && deck.is_empty() {
+            warn!("房间 {} 的存档没有可用的牌堆，作废进行中的牌局", saved.room_id);
+            for msg in game_state.void_hand().unwrap_or_default() {
+                timeline.record(msg, Utc::now());
+            }
+        }
+        game_state.restore_deck(deck);
+        match (duplicate_seed, &mut game_state.duplicate) {
+            (Some(seed), Some(duplicate)) => duplicate.seed = seed,
+            (None, Some(_)) => {
+                warn!("房间 {} 的存档没有可用的复式对局种子，牌桌退出复式对局", saved.room_id);
+                game_state.duplicate = None;
+                timeline.record(ServerMessage::DuplicateUpdated { duplicate: None }, Utc::now());
+            }
+            _ => {}
+        }
+        Room {
+            room_id: saved.room_id,
+            game_state,
+            host_id: saved.host_id,
+            players: HashMap::new(),
+            pending_action: None,
+            turn_timer: None,
+            pending_confirms: HashMap::new(),
+            pending_deposits: HashMap::new(),
+            pending_joins: HashMap::new(),
+            disconnected: saved.secret_digests,
+            next_seq: 1,
+            replay: HashMap::new(),
+            timeline,
+            subscribers: vec![],
+            overlay: None,
+            discord: None,
+            contacts: saved.contacts,
+            turn_notifier,
+            saved: true,
+            dirty: false,
+            last_saved: Some(Instant::now()),
+            save_scheduled: false,
+            chat_times: HashMap::new(),
+            self_tx: None,
+            frozen: false,
+            last_activity: Instant::now(),
+            ending_session: false,
+            kicked: HashSet::new(),
+            duplicates: DuplicateRegistry::default(),
+        }
+    }
+
+    /// 要写入存档的房间: 凭证换成摘要，牌堆和复式对局的种子加密后单独保存
+    fn saved_room(&self) -> SavedRoom {
+        let secret_digests = self.players.iter().map(|(id, conn)| (*id, correspondence::secret_digest(&conn.secret)))
+            .chain(self.disconnected.iter().map(|(id, digest)| (*id, digest.clone())))
+            .collect();
+        let mut game_state = self.game_state.clone();
+        let duplicate_seed = game_state.duplicate.as_mut().map(|d| std::mem::take(&mut d.seed));
+        let secrets = RoomSecrets { deck: self.game_state.deck().to_vec(), duplicate_seed };
+        SavedRoom {
+            room_id: self.room_id,
+            host_id: self.host_id,
+            game_state,
+            secret_digests,
+            sealed: correspondence::seal(self.room_id, &secrets),
+            contacts: self.contacts.clone(),
+            timeline: self.timeline.clone(),
+        }
+    }
+
+    /// 通信对局在房间发生变化后保存: 两局之间立即保存；牌局进行中最多每 `SAVE_INTERVAL` 保存一次，
+    /// 服务器没有配置存档密钥时 (牌堆无法保存) 等到这一局结束再保存。关闭通信对局后删除存档
+    async fn persist(&mut self) {
+        if !self.game_state.room_options.correspondence {
+            if self.saved {
+                correspondence::remove(self.room_id).await;
+                self.saved = false;
+            }
+            return;
+        }
+        if !self.dirty {
+            return;
+        }
+        if self.game_state.hand_in_progress() {
+            if !correspondence::can_seal() {
+                return;
+            }
+            if let Some(wait) = self.last_saved.and_then(|t| SAVE_INTERVAL.checked_sub(t.elapsed())) {
+                self.schedule_save(wait);
+                return;
+            }
+        }
+        match correspondence::save(&self.saved_room()).await {
+            Ok(()) => {
+                self.saved = true;
+                self.dirty = false;
+                self.last_saved = Some(Instant::now());
+            }
+            Err(e) => error!("保存房间 {} 失败: {}", self.room_id, e),
+        }
+    }
+
+    /// 推迟到 `delay` 之后保存，已经安排过时不重复安排
+    fn schedule_save(&mut self, delay: Duration) {
+        let Some(self_tx) = self.self_tx.clone() else { return };
+        if std::mem::replace(&mut self.save_scheduled, true) {
+            return;
+        }
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Some(tx) = self_tx.upgrade() {
+                let _ = tx.send(RoomCommand::Save).await;
+            }
+        });
+    }
+
+    /// 在独立的任务中运行房间，返回房间的句柄
+    pub fn spawn(mut self, state: SharedState) -> RoomHandle {
+        let (tx, rx) = mpsc::channel(64);
+        self.self_tx = Some(tx.downgrade());
+        self.duplicates = state.duplicates.clone();
+        if let Some(deal) = &self.game_state.duplicate {
+            self.duplicates.restore(deal);
+        }
+        self.resume_timers();
+        self.schedule_idle_check(ROOM_IDLE_TIMEOUT);
+        tokio::spawn(self.run(state, rx));
+        RoomHandle(tx)
+    }
+
+    async fn run(mut self, state: SharedState, mut rx: mpsc::Receiver<RoomCommand>) {
+        while let Some(cmd) = rx.recv().await {
+            if self.frozen {
+                if self.handle_frozen_command(cmd).await {
+                    break;
+                }
+                continue;
+            }
+            let summary = cmd.summary();
+            // 房间状态在 panic 后不再使用 (只会被转储)，可以安全地跨越 unwind 边界
+            match AssertUnwindSafe(self.handle_command(cmd)).catch_unwind().await {
+                Ok(true) => break,
+                Ok(false) => self.persist().await,
+                Err(panic) => self.freeze(&summary, panic).await,
+            }
+        }
+        if self.saved {
+            correspondence::remove(self.room_id).await;
+        }
+
+        // 房间已空，从房间表中移除；之后发来的命令会因为通道关闭而失败
+        state.rooms.remove(&self.room_id);
+        if let Some(deal) = &self.game_state.duplicate {
+            self.duplicates.leave(deal.match_id);
+        }
+        if let Some(discord) = self.discord.take() {
+            discord.close(self.room_id);
+        }
+        info!("房间 {} 已关闭，已被移除", self.room_id);
+    }
+
+    /// 处理一条命令，返回房间是否应当关闭 (已空或被关闭)
+    async fn handle_command(&mut self, cmd: RoomCommand) -> bool {
+        if matches!(cmd, RoomCommand::Join { .. } | RoomCommand::Reconnect { .. } | RoomCommand::Client { .. }) {
+            self.last_activity = Instant::now();
+        }
+        match cmd {
+            RoomCommand::Join { player_id, secret, nickname, sender, reply } => {
+                let registered = self.join(player_id, secret, nickname, sender).await;
+                let _ = reply.send(registered);
+            }
+            RoomCommand::Reconnect { player_id, secret, last_seq, sender, reply } => {
+                let reconnected = self.reconnect(player_id, secret, last_seq, sender).await;
+                let _ = reply.send(reconnected);
+            }
+            RoomCommand::CancelJoin { player_id, reply } => {
+                let is_member = self.players.contains_key(&player_id);
+                if !is_member {
+                    self.pending_joins.remove(&player_id);
+                }
+                let _ = reply.send(is_member);
+            }
+            RoomCommand::Client { player_id, msg } => self.handle_client_message(player_id, msg).await,
+            RoomCommand::CommitAction { action_id } => self.commit_pending_action(action_id).await,
+            RoomCommand::TurnTimeout { timer_id } => self.handle_turn_timeout(timer_id).await,
+            RoomCommand::BlindLevelTimeout { ends_at } => self.handle_blind_level_timeout(ends_at).await,
+            RoomCommand::VoteTimeout { vote_id } => {
+                if self.game_state.vote.as_ref().is_some_and(|v| v.id == vote_id) {
+                    self.end_vote(false).await;
+                }
+            }
+            RoomCommand::CheckIn { player_id, secret, reply } => {
+                let _ = reply.send(self.check_in(player_id, secret));
+            }
+            RoomCommand::Overlay { reply } => {
+                let _ = reply.send(self.overlay());
+            }
+            RoomCommand::Subscribe { sender, reply } => {
+                // 事件流从一份旁观者视角的快照开始，看不到任何底牌和玩家的私人设置
+                let snapshot = ServerMessage::SpectatorSnapshot(self.game_state.for_client(Audience::Spectator));
+                if sender.try_send(snapshot).is_ok() {
+                    self.subscribers.push(sender);
+                }
+                let _ = reply.send(true);
+            }
+            RoomCommand::Disconnect { player_id, sender } => {
+                self.handle_disconnect(player_id, &sender).await;
+                // 通信对局的房间在所有人断开后继续保留，等玩家回来
+                return self.players.is_empty() && !self.game_state.room_options.correspondence;
+            }
+            // 之后的 persist 会写入存档
+            RoomCommand::Save => self.save_scheduled = false,
+            RoomCommand::IdleTimeout => {
+                // 通信对局可以很久没人行动，不会过期
+                let idle = self.last_activity.elapsed();
+                if self.game_state.room_options.correspondence || idle < ROOM_IDLE_TIMEOUT {
+                    self.schedule_idle_check(ROOM_IDLE_TIMEOUT.saturating_sub(idle).max(Duration::from_secs(60)));
+                } else {
+                    self.close(RoomCloseReason::Expired).await;
+                    return true;
+                }
+            }
+            RoomCommand::Close { reason, reply } => {
+                self.close(reason).await;
+                let _ = reply.send(());
+                return true;
+            }
+        }
+        // 本局随任何一条命令 (玩家动作、撤回窗口结束或超时) 结束后，删除本局中被移出的玩家，
+        // 并完成房主要求的结束
+        if !self.kicked.is_empty() && !self.game_state.hand_in_progress() {
+            self.remove_kicked().await;
+        }
+        if self.ending_session && !self.game_state.hand_in_progress() {
+            self.end_session().await;
+            return true;
+        }
+        false
+    }
+
+    /// 结束本次游戏: 向所有人发出汇总和结算，通信对局的存档移入归档目录，然后关闭房间
+    async fn end_session(&mut self) {
+        let summary = ServerMessage::SessionSummary(self.game_state.session_summary());
+        broadcast(&self.msg_targets(), &summary, None).await;
+        if self.game_state.room_options.correspondence {
+            // 先保存最后的状态，归档后不再在启动时恢复
+            self.persist().await;
+            match correspondence::archive(self.room_id).await {
+                Ok(path) => {
+                    info!("房间 {} 的存档已归档到 {}", self.room_id, path.display());
+                    self.saved = false;
+                }
+                Err(e) => error!("归档房间 {} 的存档失败: {}", self.room_id, e),
+            }
+        }
+        self.close(RoomCloseReason::HostEnded).await;
+    }
+
+    /// 通知房间里的玩家和等待审批的申请者房间已关闭。
+    /// 服务器关闭时保留通信对局的存档，重启后恢复
+    async fn close(&mut self, reason: RoomCloseReason) {
+        info!("房间 {} 正在关闭: {}", self.room_id, reason);
+        let message = ServerMessage::RoomClosed { reason };
+        broadcast(&self.msg_targets(), &message, None).await;
+        for (_, pending) in self.pending_joins.drain() {
+            let _ = pending.sender.send(message.clone()).await;
+        }
+        if reason == RoomCloseReason::ServerShutdown {
+            self.saved = false;
+        }
+    }
+
+    /// 经过 `delay` 后让房间检查自己是否已经过期
+    fn schedule_idle_check(&self, delay: Duration) {
+        let Some(self_tx) = self.self_tx.clone() else { return };
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Some(tx) = self_tx.upgrade() {
+                let _ = tx.send(RoomCommand::IdleTimeout).await;
+            }
+        });
+    }
+
+    /// 冻结发生 panic 的房间: 记录日志、转储房间状态并通知所有玩家
+    async fn freeze(&mut self, command: &str, panic: Box<dyn Any + Send>) {
+        self.frozen = true;
+        self.pending_action = None;
+        let reason = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "未知错误".to_string());
+        error!("房间 {} 处理命令 {} 时发生 panic: {}，房间已被冻结", self.room_id, command, reason);
+
+        match self.dump_state(command, &reason).await {
+            Ok(path) => info!("房间 {} 的状态已转储到 {}", self.room_id, path.display()),
+            Err(e) => error!("转储房间 {} 的状态失败: {}", self.room_id, e),
+        }
+
+        let message = ServerMessage::Error { message: FROZEN_MESSAGE.to_string() };
+        broadcast(&self.msg_targets(), &message, None).await;
+    }
+
+    /// 把房间时间线和 `locale` 语言的文字版牌局历史写入导出目录
+    /// (环境变量 `POKER_EDEN_EXPORT_DIR`，默认为 `room_exports`)，返回两个文件的路径
+    async fn export_timeline(&self, locale: Locale) -> std::io::Result<(PathBuf, PathBuf)> {
+        let dir = PathBuf::from(std::env::var("POKER_EDEN_EXPORT_DIR").unwrap_or_else(|_| "room_exports".to_string()));
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+        let path = dir.join(format!("{}-timeline-{}.json", self.room_id, timestamp));
+        let hands_path = dir.join(format!("{}-hands-{}-{}.txt", self.room_id, timestamp, locale.code()));
+        let contents = serde_json::to_vec_pretty(&self.timeline).map_err(std::io::Error::other)?;
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&path, contents).await?;
+        tokio::fs::write(&hands_path, self.timeline.describe_hands(locale)).await?;
+        Ok((path, hands_path))
+    }
+
+    /// 把房间状态写入转储目录 (环境变量 `POKER_EDEN_DUMP_DIR`，默认为 `room_dumps`)
+    async fn dump_state(&self, command: &str, reason: &str) -> std::io::Result<PathBuf> {
+        let dir = PathBuf::from(std::env::var("POKER_EDEN_DUMP_DIR").unwrap_or_else(|_| "room_dumps".to_string()));
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+        let path = dir.join(format!("{}-{}.json", self.room_id, timestamp));
+        let dump = serde_json::json!({
+            "room_id": self.room_id,
+            "host_id": self.host_id,
+            "panic": reason,
+            "command": command,
+            "connected_players": self.players.keys().collect::<Vec<_>>(),
+            "game_state": &self.game_state,
+        });
+        let contents = serde_json::to_vec_pretty(&dump).map_err(std::io::Error::other)?;
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::write(&path, contents).await?;
+        Ok(path)
+    }
+
+    /// 冻结的房间只处理离开、撤回申请和关闭，其他命令一律回复错误。返回房间是否应当关闭
+    async fn handle_frozen_command(&mut self, cmd: RoomCommand) -> bool {
+        let error = || ServerMessage::Error { message: FROZEN_MESSAGE.to_string() };
+        match cmd {
+            RoomCommand::Join { sender, reply, .. } | RoomCommand::Reconnect { sender, reply, .. } => {
+                let _ = sender.send(error()).await;
+                let _ = reply.send(false);
+            }
+            RoomCommand::CancelJoin { player_id, reply } => {
+                let _ = reply.send(self.players.contains_key(&player_id));
+            }
+            RoomCommand::Client { player_id, .. } => {
+                if let Some(conn) = self.players.get(&player_id) {
+                    let _ = conn.sender.send(error()).await;
+                }
+            }
+            RoomCommand::CommitAction { .. }
+            | RoomCommand::TurnTimeout { .. }
+            | RoomCommand::BlindLevelTimeout { .. }
+            | RoomCommand::VoteTimeout { .. }
+            | RoomCommand::IdleTimeout
+            | RoomCommand::Save => {}
+            RoomCommand::Close { reason, reply } => {
+                self.close(reason).await;
+                let _ = reply.send(());
+                return true;
+            }
+            RoomCommand::Subscribe { reply, .. } => {
+                let _ = reply.send(false);
+            }
+            RoomCommand::Overlay { reply } => {
+                let _ = reply.send(None);
+            }
+            RoomCommand::CheckIn { reply, .. } => {
+                let _ = reply.send(None);
+            }
+            RoomCommand::Disconnect { player_id, .. } => {
+                self.pending_joins.remove(&player_id);
+                self.players.remove(&player_id);
+                info!("玩家 {} 离开了已冻结的房间 {}", player_id, self.room_id);
+            }
+        }
+        self.players.is_empty()
+    }
+
+    /// 观战人数 (在线但未入座的玩家) 是否已达上限
+    fn spectators_full(&self) -> bool {
+        let max = self.game_state.room_options.max_spectators;
+        max > 0 && self.players.keys()
+            .filter(|id| !self.game_state.seated_players.contains(id))
+            .count() >= max as usize
+    }
+
+    /// 广播消息，并把它记入房间时间线。轮到玩家行动时开始行动计时 (截止时间一并写入行动提示)，玩家行动后结束计时
+    async fn publish(&mut self, targets: &Vec<(PlayerId, MessageSender)>, mut message: ServerMessage, exclude: Option<PlayerId>) {
+        let mut turn_timer = None;
+        if let ServerMessage::NextToAct { player_id, context, .. } = &mut message {
+            turn_timer = self.start_turn_timer(*player_id);
+            context.deadline = self.turn_timer.as_ref().map(|t| t.deadline);
+            self.remind_turn(*player_id);
+        }
+        self.broadcast_event(targets, &message, exclude).await;
+        let follow_ups = match message {
+            ServerMessage::NextToAct { .. } => turn_timer.into_iter().collect(),
+            // 保险报价和轮到玩家行动一样计时，超时视为不买
+            ServerMessage::InsuranceOffered(offer) => self.start_turn_timer(offer.buyer).into_iter().collect(),
+            ServerMessage::PlayerActed { player_id, .. } | ServerMessage::CardsDrawn { player_id, .. }
+            | ServerMessage::InsuranceDecided { player_id, .. } => {
+                self.stop_turn_timer(player_id)
+            }
+            ServerMessage::BlindLevelRaised { level_ends_at: Some(ends_at), .. } => {
+                self.schedule_blind_level_timeout(ends_at);
+                vec![]
+            }
+            _ => vec![],
+        };
+        self.record(message);
+        for msg in follow_ups {
+            self.broadcast_event(targets, &msg, None).await;
+            self.record(msg);
+        }
+    }
+
+    /// 给事件分配序号后广播，并存入每位成员 (包括断线的) 的补发缓冲区。
+    /// 玩家的私人设置只发给玩家自己，其他人收到去掉私人设置的版本
+    async fn broadcast_event(&mut self, targets: &Vec<(PlayerId, MessageSender)>, message: &ServerMessage, exclude: Option<PlayerId>) {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        let event = |msg: &ServerMessage| encode(&ServerMessage::Event { seq, event: Box::new(msg.clone()) });
+        let Some((owner, public)) = message.public_view() else {
+            let payload = event(message);
+            for (player_id, buffer) in &mut self.replay {
+                if Some(*player_id) != exclude {
+                    buffer.push(seq, payload.clone());
+                }
+            }
+            broadcast_encoded(targets, payload, exclude).await;
+            return;
+        };
+
+        let (private, public) = (event(message), event(&public));
+        let payload_for = |player_id: PlayerId| if player_id == owner { private.clone() } else { public.clone() };
+        for (player_id, buffer) in &mut self.replay {
+            if Some(*player_id) != exclude {
+                buffer.push(seq, payload_for(*player_id));
+            }
+        }
+        for (player_id, sender) in targets {
+            if Some(*player_id) != exclude {
+                let _ = sender.send_encoded(payload_for(*player_id)).await;
+            }
+        }
+    }
+
+    /// 把广播过的事件记入时间线，并推送给事件流的订阅者。
+    /// 订阅者跟不上 (通道已满) 或已断开时直接移除，不拖慢房间
+    fn record(&mut self, message: ServerMessage) {
+        // 事件流和时间线只保存公开版本，不含玩家的私人设置
+        let message = message.public_view().map_or(message, |(_, public)| public);
+        if !self.subscribers.is_empty() {
+            let payload = encode(&message);
+            self.subscribers.retain(|s| s.try_send_encoded(payload.clone()).is_ok());
+        }
+        if let Some(discord) = &mut self.discord {
+            discord.notify(&message, &self.game_state);
+        }
+        self.timeline.record(message, Utc::now());
+        self.overlay = None;
+        self.dirty = true;
+    }
+
+    /// 直播叠加层 JSON，两次事件之间的轮询共用同一份
+    fn overlay(&mut self) -> Option<Arc<str>> {
+        if self.overlay.is_none() {
+            let overlay = TableOverlay::new(&self.game_state, self.timeline.entries.iter().map(|e| &e.event));
+            match serde_json::to_string(&overlay) {
+                Ok(json) => self.overlay = Some(json.into()),
+                Err(e) => error!("生成房间 {} 的直播叠加层失败: {}", self.room_id, e),
+            }
+        }
+        self.overlay.clone()
+    }
+
+    /// 开始行动计时。未开启计时返回 None
+    fn start_turn_timer(&mut self, player_id: PlayerId) -> Option<ServerMessage> {
+        let secs = self.game_state.room_options.turn_timer_secs;
+        if secs == 0 {
+            self.turn_timer = None;
+            return None;
+        }
+        let id = Uuid::new_v4();
+        let deadline = Utc::now() + chrono::Duration::seconds(secs as i64);
+        self.turn_timer = Some(TurnTimer { id, player_id, base_deadline: deadline, deadline, time_bank: false });
+        self.schedule_turn_timeout(id, deadline);
+        Some(ServerMessage::TurnTimer { player_id, deadline, time_bank: false, server_time: Utc::now() })
+    }
+
+    /// 启用当前行动玩家的时间银行，把截止时间延长剩余的时间银行。
+    /// 不是该玩家的计时、已经启用或时间银行已用完时返回 None
+    fn use_time_bank(&mut self, player_id: PlayerId) -> Option<ServerMessage> {
+        let bank = self.game_state.players.get(&player_id).map_or(0, |p| p.time_bank);
+        let timer = self.turn_timer.as_mut()
+            .filter(|t| t.player_id == player_id && !t.time_bank && bank > 0)?;
+        timer.id = Uuid::new_v4();
+        timer.time_bank = true;
+        timer.deadline = timer.base_deadline + chrono::Duration::seconds(bank as i64);
+        let (id, deadline) = (timer.id, timer.deadline);
+        self.schedule_turn_timeout(id, deadline);
+        Some(ServerMessage::TurnTimer { player_id, deadline, time_bank: true, server_time: Utc::now() })
+    }
+
+    /// 玩家行动后结束计时，从时间银行中扣除超出基本计时的时间
+    fn stop_turn_timer(&mut self, player_id: PlayerId) -> Vec<ServerMessage> {
+        let Some(timer) = self.turn_timer.take_if(|t| t.player_id == player_id) else { return vec![] };
+        if !timer.time_bank {
+            return vec![];
+        }
+        let used_ms = (Utc::now() - timer.base_deadline).num_milliseconds().max(0) as u64;
+        self.game_state.charge_time_bank(player_id, used_ms.div_ceil(1000) as u32).unwrap_or_default()
+    }
+
+    /// 到截止时间后把超时命令发回房间自己的队列
+    fn schedule_turn_timeout(&self, timer_id: Uuid, deadline: DateTime<Utc>) {
+        let Some(self_tx) = self.self_tx.clone() else { return };
+        let delay = (deadline - Utc::now()).to_std().unwrap_or_default();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Some(tx) = self_tx.upgrade() {
+                let _ = tx.send(RoomCommand::TurnTimeout { timer_id }).await;
+            }
+        });
+    }
+
+    /// 行动计时结束: 还有时间银行时自动启用，否则替仍未行动的玩家过牌或弃牌
+    async fn handle_turn_timeout(&mut self, timer_id: Uuid) {
+        let Some(player_id) = self.turn_timer.as_ref().filter(|t| t.id == timer_id).map(|t| t.player_id) else { return };
+        // 计时期间已经提交、正在撤回窗口内的动作不算超时。保留计时，动作被撤回时再重新开始
+        if self.pending_action.as_ref().is_some_and(|p| p.player_id == player_id) {
+            return;
+        }
+        let targets = self.msg_targets();
+        if let Some(msg) = self.use_time_bank(player_id) {
+            self.publish(&targets, msg, None).await;
+            return;
+        }
+        let now = Utc::now();
+        let Ok(messages) = self.game_state.time_out(player_id, now) else {
+            self.turn_timer = None;
+            return;
+        };
+        let messages = run_auto_actions(&mut self.game_state, messages, now).into_messages();
+        self.pending_confirms.remove(&player_id);
+        info!("房间 {} 的玩家 {} 行动超时", self.room_id, player_id);
+
+        for msg in messages {
+            if !matches!(msg, ServerMessage::Error { .. }) {
+                self.publish(&targets, msg, None).await;
+            }
+        }
+    }
+
+    /// 从存档恢复的房间重新开始行动计时和盲注级别计时
+    fn resume_timers(&mut self) {
+        if let Some(ServerMessage::NextToAct { player_id, .. }) = self.game_state.current_turn() {
+            self.start_turn_timer(player_id);
+            self.remind_turn(player_id);
+        }
+        if let Some(ends_at) = self.game_state.level_ends_at {
+            self.schedule_blind_level_timeout(ends_at);
+        }
+        if let Some(vote) = &self.game_state.vote {
+            self.schedule_vote_timeout(vote.id, vote.deadline);
+        }
+    }
+
+    /// 通信对局中轮到断线的玩家行动时，通过他留下的联系方式提醒他
+    fn remind_turn(&self, player_id: PlayerId) {
+        if !self.game_state.room_options.correspondence || self.players.contains_key(&player_id) {
+            return;
+        }
+        if let (Some(notifier), Some(contact), Some(player)) =
+            (&self.turn_notifier, self.contacts.get(&player_id), self.game_state.players.get(&player_id)) {
+            notifier.remind(self.room_id, player, contact, self.turn_timer.as_ref().map(|t| t.deadline));
+        }
+    }
+
+    fn schedule_blind_level_timeout(&self, ends_at: DateTime<Utc>) {
+        let Some(self_tx) = self.self_tx.clone() else { return };
+        let delay = (ends_at - Utc::now()).to_std().unwrap_or_default();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Some(tx) = self_tx.upgrade() {
+                let _ = tx.send(RoomCommand::BlindLevelTimeout { ends_at }).await;
+            }
+        });
+    }
+
+    fn schedule_vote_timeout(&self, vote_id: Uuid, deadline: DateTime<Utc>) {
+        let Some(self_tx) = self.self_tx.clone() else { return };
+        let delay = (deadline - Utc::now()).to_std().unwrap_or_default();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            if let Some(tx) = self_tx.upgrade() {
+                let _ = tx.send(RoomCommand::VoteTimeout { vote_id }).await;
+            }
+        });
+    }
+
+    /// 结束进行中的投票并广播结果，通过时执行投票的操作。操作已经无法执行时 (如牌局已经结束) 广播原因
+    async fn end_vote(&mut self, passed: bool) {
+        let Some(vote) = self.game_state.vote.take() else { return };
+        let targets = self.msg_targets();
+        info!("房间 {} 的投票 {:?} {}", self.room_id, vote.proposal, if passed { "通过" } else { "未通过" });
+        let ended = ServerMessage::VoteEnded { vote_id: vote.id, proposal: vote.proposal.clone(), passed };
+        self.publish(&targets, ended, None).await;
+        if !passed {
+            return;
+        }
+        let result = match vote.proposal {
+            VoteProposal::Kick(target) => {
+                self.kick(target).await;
+                return;
+            }
+            VoteProposal::VoidHand => {
+                let result = self.game_state.void_hand();
+                if result.is_ok() {
+                    self.pending_action = None;
+                    self.turn_timer = None;
+                    self.pending_confirms.clear();
+                }
+                result
+            }
+            VoteProposal::SetBlinds { small_blind, big_blind } => self.game_state.set_blinds(small_blind, big_blind),
+            VoteProposal::SetRoomOption(option) => self.set_room_option(option),
+            VoteProposal::BombPotNextHand => self.game_state.queue_bomb_pot(),
+        };
+        for msg in result.into_messages() {
+            let msg = match msg {
+                ServerMessage::Error { message } => ServerMessage::Info { message: format!("投票通过但无法执行: {}", message) },
+                msg => msg,
+            };
+            self.publish(&targets, msg, None).await;
+        }
+    }
+
+    /// 房主或投票把玩家移出房间: 通知他离开并关闭他的连接，断开他和房间的联系并作废重连凭证。
+    /// 两局之间直接从房间中删除；牌局中弃掉他的牌并标记为离线，本局结束后再删除
+    async fn kick(&mut self, player_id: PlayerId) {
+        if let Some(sender) = self.players.get(&player_id).map(|conn| conn.sender.clone()) {
+            let closed = ServerMessage::RoomClosed { reason: RoomCloseReason::Kicked };
+            let _ = sender.send_encoded(Payload::closing(&closed)).await;
+            self.handle_disconnect(player_id, &sender).await;
+        }
+        self.disconnected.remove(&player_id);
+        self.replay.remove(&player_id);
+        self.contacts.remove(&player_id);
+        self.pending_confirms.remove(&player_id);
+        info!("玩家 {} 被移出了房间 {}", player_id, self.room_id);
+
+        let targets = self.msg_targets();
+        let now = Utc::now();
+        let mut messages = vec![];
+        if let Some(p) = self.game_state.players.get_mut(&player_id)
+            && !p.is_offline {
+            p.is_offline = true;
+            messages.push(ServerMessage::PlayerUpdated { player: p.clone() });
+        }
+        messages.extend(self.game_state.force_fold(player_id, now).unwrap_or_default());
+        messages.extend(run_auto_actions(&mut self.game_state, vec![], now).into_messages());
+        match self.game_state.remove_player(player_id, now) {
+            Ok(msgs) => messages.extend(msgs),
+            Err(_) => { self.kicked.insert(player_id); }
+        }
+        for msg in messages {
+            if !matches!(msg, ServerMessage::Error { .. }) {
+                self.publish(&targets, msg, None).await;
+            }
+        }
+    }
+
+    /// 本局结束后删除牌局中被移出的玩家
+    async fn remove_kicked(&mut self) {
+        let targets = self.msg_targets();
+        for player_id in std::mem::take(&mut self.kicked) {
+            for msg in self.game_state.remove_player(player_id, Utc::now()).unwrap_or_default() {
+                self.publish(&targets, msg, None).await;
+            }
+        }
+    }
+
+    /// 修改房间选项 (房主直接修改或投票通过)
+    fn set_room_option(&mut self, option: RoomOption) -> GameResult {
+        if matches!(option, RoomOption::SitAndGo(_)) && self.game_state.sit_and_go.is_running() {
+            return Err(GameError::SitAndGoInProgress);
+        }
+        if let RoomOption::SitAndGo(Some(sng)) = &option {
+            sng.validate()?;
+        }
+        let reset_time_banks = matches!(option, RoomOption::TimeBank(_));
+        self.game_state.room_options.apply(option);
+        let mut msgs = vec![ServerMessage::RoomOptionsUpdated(self.game_state.room_options.clone())];
+        if reset_time_banks {
+            msgs.extend(self.game_state.reset_time_banks());
+        }
+        Ok(msgs)
+    }
+
+    /// 盲注级别到时: 两局之间立即升级，牌局进行中时等下一局开始再升级。
+    /// 级别计时重新开始过 (如房主修改了锦标赛结构) 的旧计时直接忽略
+    async fn handle_blind_level_timeout(&mut self, ends_at: DateTime<Utc>) {
+        if self.game_state.level_ends_at != Some(ends_at) {
+            return;
+        }
+        let messages = self.game_state.advance_blind_level(Utc::now()).into_messages();
+        let targets = self.msg_targets();
+        for msg in messages {
+            if !matches!(msg, ServerMessage::Error { .. }) {
+                info!("房间 {} 的盲注级别到时，已在两局之间升级", self.room_id);
+                self.publish(&targets, msg, None).await;
+            }
+        }
+    }
+
+    /// 玩家是否发言太频繁；没有超过限制时记下这次发言，只对会被广播的发言调用
+    fn chat_flooded(&mut self, player_id: PlayerId) -> bool {
+        let now = Instant::now();
+        let times = self.chat_times.entry(player_id).or_default();
+        while times.front().is_some_and(|t| now.duration_since(*t) >= CHAT_WINDOW) {
+            times.pop_front();
+        }
+        if times.len() >= CHAT_BURST {
+            return true;
+        }
+        times.push_back(now);
+        false
+    }
+
+    fn msg_targets(&self) -> Vec<(PlayerId, MessageSender)> {
+        self.players.iter().map(|(player_id, conn)|
+            (*player_id, conn.sender.clone())
+        ).collect()
+    }
+
+    /// 处理加入申请，返回玩家是否已登记到房间 (加入或进入审批队列)
+    async fn join(&mut self, player_id: PlayerId, secret: PlayerSecret, nickname: String, sender: MessageSender) -> bool {
+        if self.spectators_full() {
+            let max_spectators = self.game_state.room_options.max_spectators;
+            let _ = sender.send(ServerMessage::SpectatorsFull { max_spectators }).await;
+            return false;
+        }
+
+        // 开启加入审批时，先放入等待队列，由房主批准后再加入
+        if self.game_state.room_options.require_join_approval {
+            if let Some(conn) = self.players.get(&self.host_id) {
+                let _ = conn.sender.send(ServerMessage::JoinRequested { player_id, nickname: nickname.clone() }).await;
+            }
+            let _ = sender.send(ServerMessage::Info { message: "已申请加入房间，等待房主批准".to_string() }).await;
+            self.pending_joins.insert(player_id, PendingJoin { nickname, secret, sender });
+            info!("玩家 {} 申请加入房间 {}", player_id, self.room_id);
+            return true;
+        }
+
+        let targets = self.msg_targets();
+        let (join_broadcast_msg, join_msg) = self.admit_player(player_id, secret, nickname, sender.clone());
+        self.publish(&targets, join_broadcast_msg, Some(player_id)).await;
+        let _ = sender.send(join_msg).await;
+        info!("玩家 {} 加入了房间 {}", player_id, self.room_id);
+        true
+    }
+
+    /// 处理断线重连: 凭证正确时恢复玩家的连接，补发 `last_seq` 之后错过的事件，
+    /// 补发不了 (缓冲区已覆盖不到或服务器重启过) 时发送最新的游戏状态。返回是否成功
+    async fn reconnect(&mut self, player_id: PlayerId, secret: PlayerSecret, last_seq: Option<u64>, sender: MessageSender) -> bool {
+        // 服务器可能还没发现旧连接已经中断，凭证正确时由新连接接管
+        if !self.verify_secret(player_id, &secret) {
+            let _ = sender.send(ServerMessage::Error { message: "重连失败：凭证无效".to_string() }).await;
+            return false;
+        }
+        self.disconnected.remove(&player_id);
+        self.players.insert(player_id, PlayerConnection { secret, sender: sender.clone() });
+
+        let targets = self.msg_targets();
+        if let Some(p) = self.game_state.players.get_mut(&player_id) {
+            p.is_offline = false;
+            let msg = ServerMessage::PlayerUpdated { player: p.clone() };
+            self.publish(&targets, msg, Some(player_id)).await;
+        }
+        let missed = last_seq.filter(|&seq| seq < self.next_seq)
+            .and_then(|seq| self.replay.get(&player_id)?.since(seq));
+        match missed {
+            Some(events) => {
+                info!("向玩家 {} 补发 {} 个错过的事件", player_id, events.len());
+                for payload in events {
+                    let _ = sender.send_encoded(payload).await;
+                }
+            }
+            None => {
+                self.replay.insert(player_id, ReplayBuffer::new(self.next_seq));
+                let _ = sender.send(ServerMessage::GameStateSnapshot(self.game_state.for_client(Audience::Player(player_id)))).await;
+            }
+        }
+        // 轮到重连的玩家行动时，重新发送行动提示
+        if let Some(mut turn) = self.game_state.current_turn()
+            && let ServerMessage::NextToAct { player_id: actor, context, .. } = &mut turn
+            && *actor == player_id {
+            context.deadline = self.turn_timer.as_ref().filter(|t| t.player_id == player_id).map(|t| t.deadline);
+            let _ = sender.send(turn).await;
+        }
+        if let Some(timer) = &self.turn_timer {
+            let msg = ServerMessage::TurnTimer { player_id: timer.player_id, deadline: timer.deadline, time_bank: timer.time_bank, server_time: Utc::now() };
+            let _ = sender.send(msg).await;
+        }
+        let _ = sender.send(ServerMessage::Info { message: "已重新连接到房间".to_string() }).await;
+        info!("玩家 {} 重新连接到房间 {}", player_id, self.room_id);
+        true
+    }
+
+    /// 重连凭证是否正确。在线玩家的凭证直接比较，断线玩家的比较摘要
+    fn verify_secret(&self, player_id: PlayerId, secret: &PlayerSecret) -> bool {
+        match self.players.get(&player_id) {
+            Some(conn) => conn.secret == *secret,
+            None => self.disconnected.get(&player_id).is_some_and(|digest| *digest == correspondence::secret_digest(secret)),
+        }
+    }
+
+    fn check_in(&self, player_id: PlayerId, secret: PlayerSecret) -> Option<GameCheckIn> {
+        if !self.verify_secret(player_id, &secret) {
+            return None;
+        }
+        let your_turn = self.game_state.hand_in_progress() && self.game_state.current_player_id() == Some(player_id);
+        let deadline = self.turn_timer.as_ref().filter(|t| your_turn && t.player_id == player_id).map(|t| t.deadline);
+        Some(GameCheckIn { room_id: self.room_id, active: true, your_turn, deadline })
+    }
+
+    /// 处理房间成员发来的消息
+    async fn handle_client_message(&mut self, player_id: PlayerId, msg: ClientMessage) {
+        let Some(tx) = self.players.get(&player_id).map(|conn| conn.sender.clone()) else {
+            // 加入申请还没有被批准的玩家不能进行任何操作
+            if let Some(pending) = self.pending_joins.get(&player_id) {
+                let _ = pending.sender.send(ServerMessage::Error { message: "请等待房主批准加入".to_string() }).await;
+            }
+            return;
+        };
+        // 游戏逻辑的错误提示只发给当前玩家，使用他选择的语言
+        let locale = self.game_state.players.get(&player_id).map_or(Locale::default(), |p| p.profile.locale);
+        let targets = self.msg_targets();
+        let host_tx = self.players.get(&self.host_id).map(|conn| conn.sender.clone());
+        let mut only_messages = vec![];
+        // 只发给房主的消息
+        let mut host_messages = vec![];
+
+        // 游戏逻辑处理
+        let broadcast_messages = match msg {
+            ClientMessage::StartHand => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if self.game_state.hand_in_progress() {
+                    vec![ServerMessage::Error { message: GameError::HandInProgress.describe(locale) }]
+                } else {
+                    let result = self.game_state.start_new_hand(Utc::now());
+                    if result.is_ok() {
+                        self.timeline.audit(player_id, HostAction::StartHand, Utc::now());
+                    }
+                    result.into_localized_messages(locale)
+                }
+            }
+            ClientMessage::RequestSeat { seat_id, stack } => {
+                // 牌局进行中也可以入座空位，从下一局开始自动入局；但本局的参与者不能换座
+                let in_hand = !matches!(self.game_state.phase, GamePhase::WaitingForPlayers | GamePhase::Showdown)
+                    && self.game_state.hand_player_order.contains(&player_id);
+                if in_hand {
+                    only_messages.push(ServerMessage::Error { message: "入座失败：本局结束后才能换座".to_string() });
+                    vec![]
+                } else if !self.game_state.host_ledger.has_paid_fee(&player_id) && stack <= self.game_state.room_options.session_fee {
+                    only_messages.push(ServerMessage::Error {
+                        message: format!("入座失败：带入筹码需多于场地费 {}", self.game_state.room_options.session_fee),
+                    });
+                    vec![]
+                } else if seat_id >= self.game_state.seats {
+                    only_messages.push(ServerMessage::Error { message: "入座失败：座位号超出最大座位数".to_string() });
+                    vec![]
+                } else if self.game_state.players.values().any(|p| p.seat_id == Some(seat_id) && p.id != player_id) {
+                    only_messages.push(ServerMessage::Error { message: "入座失败：该位置已有玩家入座".to_string() });
+                    vec![]
+                } else if let Err(e) = self.game_state.take_seat_stack(player_id, stack, Utc::now()) {
+                    only_messages.push(e.into());
+                    vec![]
+                } else {
+                    if let Some(idx) = self.game_state.seated_players.iter().position(|p| *p == player_id) {
+                        self.game_state.seated_players.remove(idx);
+                    }
+                    let p = {
+                        let p = self.game_state.players.get_mut(&player_id).unwrap();
+                        p.seat_id = Some(seat_id);
+                        p.state = PlayerState::Waiting;
+                        p.is_offline = false;
+                        p.sit_out = false;
+                        p.clone()
+                    };
+                    let sid = self.game_state.find_insertion_index(seat_id).unwrap_or(self.game_state.seated_players.len());
+                    self.game_state.seated_players.insert(sid, p.id);
+
+                    let mut msgs = vec![ServerMessage::PlayerUpdated { player: p }];
+                    msgs.extend(self.game_state.charge_session_fee(player_id));
+                    msgs
+                }
+            }
+            ClientMessage::PerformAction(action) => {
+                if self.game_state.room_options.confirm_actions {
+                    // 新提交的动作会覆盖之前未确认的动作
+                    let id = Uuid::new_v4();
+                    let pending = PendingConfirm {
+                        id,
+                        action: action.clone(),
+                        phase: self.game_state.phase,
+                        max_bet: self.game_state.max_bet,
+                    };
+                    self.pending_confirms.insert(player_id, pending);
+                    only_messages.push(ServerMessage::ActionPendingConfirm { action_id: id, action });
+                    vec![]
+                } else {
+                    self.submit_action(player_id, action, &mut only_messages)
+                }
+            }
+            ClientMessage::ConfirmAction(action_id) => {
+                match self.pending_confirms.remove(&player_id) {
+                    Some(pending) if pending.id == action_id => {
+                        if pending.phase != self.game_state.phase || pending.max_bet != self.game_state.max_bet {
+                            vec![ServerMessage::Error { message: "局面已经变化，请重新选择动作".to_string() }]
+                        } else {
+                            self.submit_action(player_id, pending.action, &mut only_messages)
+                        }
+                    }
+                    _ => vec![ServerMessage::Error { message: "该动作已失效".to_string() }],
+                }
+            }
+            ClientMessage::UndoAction => {
+                if self.pending_action.as_ref().is_some_and(|p| p.player_id == player_id) {
+                    self.pending_action = None;
+                    only_messages.push(ServerMessage::Info { message: "已撤回动作".to_string() });
+                    // 撤回窗口内计时已经结束 (超时被推迟了)，重新开始计时，否则这个玩家永远不会超时
+                    let expired = self.turn_timer.as_ref().is_some_and(|t| t.player_id == player_id && t.deadline <= Utc::now());
+                    if expired { self.start_turn_timer(player_id).into_iter().collect() } else { vec![] }
+                } else {
+                    vec![ServerMessage::Error { message: "没有可以撤回的动作".to_string() }]
+                }
+            }
+            ClientMessage::GetMyHand => {
+                if self.game_state.phase != GamePhase::WaitingForPlayers {
+                    let p_idx = self.game_state.player_indices.get(&player_id);
+                    if let Some(idx) = p_idx {
+                        let hands = &self.game_state.player_cards[*idx];
+                        only_messages.push(ServerMessage::PlayerHand {
+                            hands: hands.iter().flatten().cloned().collect(),
+                        });
+                    }
+                }
+                vec![]
+            }
+            ClientMessage::DiscardCards(indices) => {
+                let now = Utc::now();
+                self.game_state
+                    .handle_discard(player_id, indices, now)
+                    .and_then(|msg| run_auto_actions(&mut self.game_state, msg, now))
+                    .into_localized_messages(locale)
+            }
+            ClientMessage::StartDuplicate | ClientMessage::JoinDuplicate { .. } | ClientMessage::StopDuplicate => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if self.game_state.hand_in_progress() {
+                    vec![ServerMessage::Error { message: GameError::HandInProgress.describe(locale) }]
+                } else {
+                    let deal = match msg {
+                        ClientMessage::StartDuplicate => Some(self.duplicates.start()),
+                        ClientMessage::JoinDuplicate { match_id } => self.duplicates.join(match_id),
+                        _ => None,
+                    };
+                    if deal.is_none() && !matches!(msg, ClientMessage::StopDuplicate) {
+                        vec![ServerMessage::Error { message: GameError::DuplicateUnavailable.describe(locale) }]
+                    } else {
+                        if let Some(old) = &self.game_state.duplicate {
+                            self.duplicates.leave(old.match_id);
+                        }
+                        self.timeline.audit(player_id, HostAction::SetDuplicate { match_id: deal.map(|d| d.match_id) }, Utc::now());
+                        self.game_state.set_duplicate(deal).into_localized_messages(locale)
+                    }
+                }
+            }
+            ClientMessage::ShuffleSeats => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else {
+                    let result = self.game_state.shuffle_seats();
+                    if result.is_ok() {
+                        self.timeline.audit(player_id, HostAction::ShuffleSeats, Utc::now());
+                    }
+                    result.into_localized_messages(locale)
+                }
+            }
+            ClientMessage::BombPotNextHand => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if self.game_state.room_options.democracy {
+                    vec![ServerMessage::Error { message: GameError::DemocracyRequiresVote.describe(locale) }]
+                } else {
+                    let result = self.game_state.queue_bomb_pot();
+                    if result.is_ok() {
+                        self.timeline.audit(player_id, HostAction::BombPotNextHand, Utc::now());
+                    }
+                    result.into_localized_messages(locale)
+                }
+            }
+            ClientMessage::DepositChips(amount) => {
+                if !self.game_state.room_options.chip_accounts {
+                    vec![ServerMessage::Error { message: "房间未开启筹码账户".to_string() }]
+                } else if amount == 0 {
+                    vec![ServerMessage::Error { message: "存入的筹码必须大于 0".to_string() }]
+                } else {
+                    // 新的申请会覆盖之前未处理的申请
+                    self.pending_deposits.insert(player_id, amount);
+                    host_messages.push(ServerMessage::DepositRequested { player_id, amount });
+                    only_messages.push(ServerMessage::Info { message: "已提交存入申请，等待房主批准".to_string() });
+                    vec![]
+                }
+            }
+            ClientMessage::ApproveDeposit(target_id) => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if let Some(amount) = self.pending_deposits.remove(&target_id) {
+                    let result = self.game_state.deposit_chips(target_id, amount);
+                    if result.is_ok() {
+                        self.timeline.audit(player_id, HostAction::ApproveDeposit { player_id: target_id, amount }, Utc::now());
+                    }
+                    result.into_localized_messages(locale)
+                } else {
+                    vec![ServerMessage::Error { message: "该玩家没有待处理的存入申请".to_string() }]
+                }
+            }
+            ClientMessage::RejectDeposit(target_id) => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if let Some(amount) = self.pending_deposits.remove(&target_id) {
+                    self.timeline.audit(player_id, HostAction::RejectDeposit { player_id: target_id, amount }, Utc::now());
+                    if let Some(conn) = self.players.get(&target_id) {
+                        let _ = conn.sender.try_send(ServerMessage::Error {
+                            message: format!("房主拒绝了你存入 {} 筹码的申请", amount),
+                        });
+                    }
+                    vec![]
+                } else {
+                    vec![ServerMessage::Error { message: "该玩家没有待处理的存入申请".to_string() }]
+                }
+            }
+            ClientMessage::ApproveJoin(target_id) => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if self.spectators_full() {
+                    let max_spectators = self.game_state.room_options.max_spectators;
+                    vec![ServerMessage::Error { message: format!("观战人数已达上限 {}，无法批准加入", max_spectators) }]
+                } else if let Some(pending) = self.pending_joins.remove(&target_id) {
+                    let sender = pending.sender.clone();
+                    self.timeline.audit(player_id, HostAction::ApproveJoin { player_id: target_id, nickname: pending.nickname.clone() }, Utc::now());
+                    let (join_broadcast_msg, join_msg) =
+                        self.admit_player(target_id, pending.secret, pending.nickname, pending.sender);
+                    let _ = sender.try_send(join_msg);
+                    info!("玩家 {} 经房主批准加入了房间 {}", target_id, self.room_id);
+                    vec![join_broadcast_msg]
+                } else {
+                    vec![ServerMessage::Error { message: "没有该玩家的加入申请".to_string() }]
+                }
+            }
+            ClientMessage::RejectJoin(target_id) => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if let Some(pending) = self.pending_joins.remove(&target_id) {
+                    self.timeline.audit(player_id, HostAction::RejectJoin { player_id: target_id, nickname: pending.nickname }, Utc::now());
+                    let _ = pending.sender.try_send(ServerMessage::Error { message: "房主拒绝了你的加入申请".to_string() });
+                    vec![]
+                } else {
+                    vec![ServerMessage::Error { message: "没有该玩家的加入申请".to_string() }]
+                }
+            }
+            ClientMessage::LeaveSeat => {
+                self.game_state.leave_seat(player_id, Utc::now()).into_localized_messages(locale)
+            }
+            ClientMessage::CashOut => {
+                self.game_state.cash_out(player_id, Utc::now()).into_localized_messages(locale)
+            }
+            ClientMessage::Tip(amount) => {
+                self.game_state.tip_host(player_id, amount).into_localized_messages(locale)
+            }
+            ClientMessage::SetPotTip(amount) => {
+                self.game_state.set_pot_tip(player_id, amount).into_localized_messages(locale)
+            }
+            ClientMessage::ExportTimeline(export_locale) => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else {
+                    match self.export_timeline(export_locale).await {
+                        Ok((path, hands_path)) => {
+                            self.timeline.audit(player_id, HostAction::ExportTimeline, Utc::now());
+                            info!("房间 {} 的时间线已导出到 {}", self.room_id, path.display());
+                            only_messages.push(ServerMessage::Info {
+                                message: format!("房间时间线已导出到服务器上的 {}，牌局历史 ({}) 导出到 {}", path.display(), export_locale, hands_path.display()),
+                            });
+                            vec![]
+                        }
+                        Err(e) => vec![ServerMessage::Error { message: format!("导出房间时间线失败: {}", e) }],
+                    }
+                }
+            }
+            ClientMessage::GetAuditLog => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else {
+                    only_messages.push(ServerMessage::AuditLog(self.timeline.audit_log.clone()));
+                    vec![]
+                }
+            }
+            ClientMessage::SetDiscordChannel(target) => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if let Some(target) = target {
+                    match DiscordNotifier::spawn(&target) {
+                        Ok(discord) => {
+                            self.discord = Some(discord);
+                            self.timeline.audit(player_id, HostAction::SetDiscordChannel { enabled: true }, Utc::now());
+                            info!("房间 {} 开启了 Discord 通知", self.room_id);
+                            only_messages.push(ServerMessage::Info { message: "已开启 Discord 通知".to_string() });
+                            vec![]
+                        }
+                        Err(message) => vec![ServerMessage::Error { message }],
+                    }
+                } else {
+                    self.discord = None;
+                    self.timeline.audit(player_id, HostAction::SetDiscordChannel { enabled: false }, Utc::now());
+                    only_messages.push(ServerMessage::Info { message: "已关闭 Discord 通知".to_string() });
+                    vec![]
+                }
+            }
+            ClientMessage::SetNotifyContact(contact) => {
+                let contact = contact.map(|c| c.trim().to_string()).filter(|c| !c.is_empty());
+                if self.turn_notifier.is_none() {
+                    self.turn_notifier = TurnNotifier::from_env();
+                }
+                match contact {
+                    _ if self.turn_notifier.is_none() => {
+                        vec![ServerMessage::Error { message: "服务器没有配置行动提醒".to_string() }]
+                    }
+                    Some(contact) if contact.chars().count() > MAX_CONTACT_CHARS => {
+                        vec![ServerMessage::Error { message: format!("联系方式不能超过 {} 个字符", MAX_CONTACT_CHARS) }]
+                    }
+                    Some(contact) => {
+                        let message = format!("通信对局中轮到你行动而你不在线时，会通过 {} 提醒你", contact);
+                        self.contacts.insert(player_id, contact);
+                        self.dirty = true;
+                        only_messages.push(ServerMessage::Info { message });
+                        vec![]
+                    }
+                    None => {
+                        self.contacts.remove(&player_id);
+                        self.dirty = true;
+                        only_messages.push(ServerMessage::Info { message: "已关闭行动提醒".to_string() });
+                        vec![]
+                    }
+                }
+            }
+            ClientMessage::GetSessionSummary => {
+                only_messages.push(ServerMessage::SessionSummary(self.game_state.session_summary()));
+                vec![]
+            }
+            ClientMessage::EndSession => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else {
+                    self.timeline.audit(player_id, HostAction::EndSession, Utc::now());
+                    self.ending_session = true;
+                    if self.game_state.hand_in_progress() {
+                        vec![ServerMessage::Info { message: "房主将在本局结束后结束本次游戏".to_string() }]
+                    } else {
+                        vec![]
+                    }
+                }
+            }
+            ClientMessage::KickPlayer { player_id: target } => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if target == player_id {
+                    vec![ServerMessage::Error { message: GameError::KickSelf.describe(locale) }]
+                } else if self.game_state.room_options.democracy {
+                    vec![ServerMessage::Error { message: GameError::KickRequiresVote.describe(locale) }]
+                } else if !self.game_state.players.contains_key(&target) {
+                    vec![ServerMessage::Error { message: GameError::PlayerNotFound.describe(locale) }]
+                } else {
+                    self.timeline.audit(player_id, HostAction::KickPlayer { player_id: target }, Utc::now());
+                    self.kick(target).await;
+                    vec![]
+                }
+            }
+            ClientMessage::TransferHost { player_id: target } => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if target == player_id {
+                    vec![ServerMessage::Error { message: GameError::AlreadyHost.describe(locale) }]
+                } else if !self.players.contains_key(&target) {
+                    vec![ServerMessage::Error { message: GameError::TransferToOffline.describe(locale) }]
+                } else {
+                    self.timeline.audit(player_id, HostAction::TransferHost { player_id: target }, Utc::now());
+                    self.host_id = target;
+                    info!("房间 {} 的房主已转移给 {}", self.room_id, target);
+                    vec![ServerMessage::HostChanged { host_id: target }]
+                }
+            }
+            ClientMessage::PostDeadBlind => {
+                self.game_state.request_dead_blind(player_id).into_localized_messages(locale)
+            }
+            ClientMessage::SitOut => {
+                self.game_state.sit_out(player_id).into_localized_messages(locale)
+            }
+            ClientMessage::SitIn => {
+                self.game_state.sit_in(player_id).into_localized_messages(locale)
+            }
+            ClientMessage::PostStraddle => {
+                self.game_state.request_straddle(player_id).into_localized_messages(locale)
+            }
+            ClientMessage::SetAutoMuck(auto_muck) => {
+                self.game_state.set_auto_muck(player_id, auto_muck).into_localized_messages(locale)
+            }
+            // 先检查发言内容，被拒绝的发言不计入刷屏限制
+            ClientMessage::Chat { text } => match self.game_state.chat(player_id, &text, Utc::now()) {
+                Ok(_) if self.chat_flooded(player_id) => {
+                    vec![ServerMessage::Error { message: "发言太频繁，请稍后再试".to_string() }]
+                }
+                result => result.into_localized_messages(locale),
+            },
+            ClientMessage::UseTimeBank => match self.use_time_bank(player_id) {
+                Some(msg) => vec![msg],
+                None => vec![ServerMessage::Error { message: "现在不能使用时间银行".to_string() }],
+            },
+            ClientMessage::SetAutoFold(below) => {
+                self.game_state.set_auto_fold(player_id, below).into_localized_messages(locale)
+            }
+            ClientMessage::SetProfile(profile) => {
+                self.game_state.set_profile(player_id, profile).into_localized_messages(locale)
+            }
+            ClientMessage::ChooseVariant(variant) => {
+                self.game_state.choose_variant(player_id, variant).into_localized_messages(locale)
+            }
+            ClientMessage::RevealFoldedHand => {
+                self.game_state.reveal_folded_hand(player_id).into_localized_messages(locale)
+            }
+            ClientMessage::ShowOrMuck { show } => {
+                self.game_state.show_or_muck(player_id, show).into_localized_messages(locale)
+            }
+            ClientMessage::ShowCards(positions) => {
+                self.game_state.show_cards(player_id, positions).into_localized_messages(locale)
+            }
+            ClientMessage::BuyInsurance(cover) => {
+                self.game_state.buy_insurance(player_id, cover).into_localized_messages(locale)
+            }
+            // 就座的玩家和房主可以查看兔子牌，观众不行
+            ClientMessage::RabbitHunt => {
+                if player_id != self.host_id && !self.game_state.seated_players.contains(&player_id) {
+                    vec![GameError::NotSeated.into()]
+                } else {
+                    self.game_state.rabbit_hunt(player_id).into_localized_messages(locale)
+                }
+            }
+            ClientMessage::SetRoomOption(option) => {
+                if player_id != self.host_id {
+                    vec![ServerMessage::Error { message: GameError::NotHost.describe(locale) }]
+                } else if self.game_state.room_options.democracy {
+                    vec![GameError::DemocracyRequiresVote.into()]
+                } else {
+                    let result = self.set_room_option(option.clone());
+                    if result.is_ok() {
+                        self.timeline.audit(player_id, HostAction::SetRoomOption(option), Utc::now());
+                    }
+                    result.into_localized_messages(locale)
+                }
+            }
+            ClientMessage::ProposeVote(proposal) => {
+                let result = self.game_state.propose_vote(player_id, proposal, Utc::now());
+                if result.is_ok()
+                    && let Some(vote) = &self.game_state.vote {
+                    self.schedule_vote_timeout(vote.id, vote.deadline);
+                }
+                result.into_localized_messages(locale)
+            }
+            ClientMessage::CastVote { vote_id, approve } => {
+                self.game_state.cast_vote(player_id, vote_id, approve).into_localized_messages(locale)
+            }
+            ClientMessage::GuessWinner(target) => {
+                self.game_state.guess_winner(player_id, target).into_localized_messages(locale)
+            }
+            _ => vec![ServerMessage::Error { message: "该功能暂未实现".to_string() }]
+        };
+
+        // 广播消息
+        for msg in broadcast_messages {
+            match &msg {
+                ServerMessage::Error { .. } => {
+                    // 错误消息只发给当前玩家
+                    let _ = tx.send(msg).await;
+                }
+                _ => self.publish(&targets, msg, None).await,
+            }
+        }
+        // 发送仅发给当前玩家的消息
+        for msg in only_messages {
+            let _ = tx.send(msg).await;
+        }
+        if let Some(host_tx) = host_tx {
+            for msg in host_messages {
+                let _ = host_tx.send(msg).await;
+            }
+        }
+        if let Some(passed) = self.game_state.vote_outcome() {
+            self.end_vote(passed).await;
+        }
+    }
+
+    /// 将新玩家加入房间，返回需要广播给其他玩家的消息和发给新玩家的 RoomJoined
+    fn admit_player(
+        &mut self,
+        player_id: PlayerId,
+        player_secret: PlayerSecret,
+        nickname: String,
+        sender: MessageSender,
+    ) -> (ServerMessage, ServerMessage) {
+        let mut player = Player::new(player_id, nickname);
+        player.time_bank = self.game_state.room_options.time_bank_secs;
+        self.game_state.players.insert(player_id, player.clone());
+        self.players.insert(player_id, PlayerConnection {
+            secret: player_secret,
+            sender,
+        });
+        self.replay.insert(player_id, ReplayBuffer::new(self.next_seq));
+
+        let join_msg = ServerMessage::RoomJoined {
+            your_id: player_id,
+            your_secret: player_secret,
+            game_state: self.game_state.for_client(Audience::Player(player_id)),
+            host_id: self.host_id,
+            config: self.game_state.config,
+        };
+        (ServerMessage::PlayerJoined { player }, join_msg)
+    }
+
+    /// 提交玩家动作: 开启撤回窗口时先放入等待区，否则立即执行
+    fn submit_action(
+        &mut self,
+        player_id: PlayerId,
+        action: PlayerAction,
+        only_messages: &mut Vec<ServerMessage>,
+    ) -> Vec<ServerMessage> {
+        let grace_ms = self.game_state.room_options.undo_grace_ms;
+        let locale = self.game_state.players.get(&player_id).map_or(Locale::default(), |p| p.profile.locale);
+        if grace_ms == 0 {
+            return perform_action(&mut self.game_state, player_id, action, Utc::now()).into_localized_messages(locale);
+        }
+        if self.pending_action.is_some() {
+            return vec![ServerMessage::Error { message: "已有动作等待生效，请稍候".to_string() }];
+        }
+
+        // 先在副本上试运行：非法动作立即报错，全下动作不可撤回，立即生效
+        let mut preview = self.game_state.clone();
+        if let Err(e) = preview.handle_player_action(player_id, action.clone(), Utc::now()) {
+            vec![ServerMessage::Error { message: e.describe(locale) }]
+        } else if preview.players.get(&player_id).is_some_and(|p| p.state == PlayerState::AllIn) {
+            perform_action(&mut self.game_state, player_id, action, Utc::now()).into_localized_messages(locale)
+        } else {
+            let id = Uuid::new_v4();
+            self.pending_action = Some(PendingAction { id, player_id, action });
+            // 撤回窗口结束后，把提交命令发回房间自己的队列
+            if let Some(self_tx) = self.self_tx.clone() {
+                tokio::spawn(async move {
+                    tokio::time::sleep(Duration::from_millis(grace_ms)).await;
+                    if let Some(tx) = self_tx.upgrade() {
+                        let _ = tx.send(RoomCommand::CommitAction { action_id: id }).await;
+                    }
+                });
+            }
+            only_messages.push(ServerMessage::Info {
+                message: format!("动作将在 {} 毫秒后生效，输入 undo 可撤回", grace_ms),
+            });
+            vec![]
+        }
+    }
+
+    /// 撤回窗口结束后，执行仍未被撤回的动作
+    async fn commit_pending_action(&mut self, action_id: Uuid) {
+        let Some(pending) = self.pending_action.take_if(|p| p.id == action_id) else { return };
+        let targets = self.msg_targets();
+        let actor = self.players.get(&pending.player_id).map(|conn| conn.sender.clone());
+        let locale = self.game_state.players.get(&pending.player_id).map_or(Locale::default(), |p| p.profile.locale);
+        let messages = perform_action(&mut self.game_state, pending.player_id, pending.action, Utc::now()).into_localized_messages(locale);
+
+        for msg in messages {
+            match &msg {
+                ServerMessage::Error { .. } => {
+                    if let Some(tx) = &actor {
+                        let _ = tx.send(msg).await;
+                    }
+                }
+                _ => self.publish(&targets, msg, None).await,
+            }
+        }
+    }
+
+    /// 玩家断开连接后的处理
+    async fn handle_disconnect(&mut self, player_id: PlayerId, sender: &MessageSender) {
+        // 加入申请未被处理的玩家断开时，只需要移出等待队列
+        if self.pending_joins.remove(&player_id).is_some() {
+            return;
+        }
+        // 已被重连接管的旧连接断开时不需要处理
+        if !self.players.get(&player_id).is_some_and(|conn| conn.sender.same_connection(sender)) {
+            return;
+        }
+        let Some(conn) = self.players.remove(&player_id) else { return };
+        // 保留凭证，玩家可以在房间关闭前重新连接
+        self.disconnected.insert(player_id, correspondence::secret_digest(&conn.secret));
+        let targets = self.msg_targets();
+        info!("玩家 {} 从房间 {} 断开连接", player_id, self.room_id);
+
+        // 更新游戏状态中的玩家为 Offline。通信对局中断线的玩家只是暂时离开，不离席也不被代打
+        if !self.game_state.room_options.correspondence
+            && let Some(p) = self.game_state.players.get_mut(&player_id) {
+            p.is_offline = true;
+            let msg = ServerMessage::PlayerUpdated { player: p.clone() };
+            self.publish(&targets, msg, None).await;
+        }
+
+        // 如果房主断开，转移房主权限
+        if player_id == self.host_id
+            && let Some(new_host_id) = self.players.keys().next().cloned() {
+            self.timeline.audit(player_id, HostAction::HostTransferred { new_host_id }, Utc::now());
+            self.host_id = new_host_id;
+            self.publish(&targets, ServerMessage::HostChanged { host_id: new_host_id }, None).await;
+            info!("房间 {} 的房主已转移给 {}", self.room_id, new_host_id);
+        }
+    }
+}
+
+/// 执行玩家动作，随后让轮到的离线玩家自动行动
+fn perform_action(game_state: &mut GameState, player_id: PlayerId, action: PlayerAction, now: DateTime<Utc>) -> GameResult {
+    let msg = game_state.handle_player_action(player_id, action, now)?;
+    run_auto_actions(game_state, msg, now)
+}
+
+/// 依次执行自动玩家 (离线或开启了自动弃牌) 的行动，直到轮到需要人类输入的玩家
+fn run_auto_actions(game_state: &mut GameState, mut msg: Vec<ServerMessage>, now: DateTime<Utc>) -> GameResult {
+    loop {
+        let (acted, rs) = game_state.tick(now)?;
+        if !acted {
+            return Ok(msg);
+        }
+        msg.extend(rs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poker_eden_core::{DuplicateDeal, MAX_CHAT_CHARS};
+
+    /// 创建一个有 `n` 位已入座玩家的房间，第一位是房主。返回房间、玩家 ID 和发给各玩家的消息
+    async fn seated_room(n: usize) -> (Room, Vec<PlayerId>, Vec<mpsc::Receiver<Payload>>) {
+        let host_id = Uuid::new_v4();
+        let (sender, rx) = MessageSender::channel(1024);
+        let (mut room, _) = Room::new(Uuid::new_v4(), host_id, Uuid::new_v4(), "p0".to_string(), RoomConfig::default(), sender);
+        let (mut ids, mut receivers) = (vec![host_id], vec![rx]);
+        for i in 1..n {
+            let (sender, rx) = MessageSender::channel(1024);
+            let id = Uuid::new_v4();
+            room.join(id, Uuid::new_v4(), format!("p{}", i), sender).await;
+            ids.push(id);
+            receivers.push(rx);
+        }
+        for (seat_id, id) in ids.iter().enumerate() {
+            room.handle_client_message(*id, ClientMessage::RequestSeat { seat_id: seat_id as u8, stack: 1000 }).await;
+        }
+        (room, ids, receivers)
+    }
+
+    /// 取出已经发给某位玩家的全部事件
+    fn received_events(rx: &mut mpsc::Receiver<Payload>) -> Vec<ServerMessage> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|payload| match serde_json::from_str(&payload).unwrap() {
+                ServerMessage::Event { event, .. } => Some(*event),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// 取出已经发给某位玩家的全部消息 (事件不拆开)
+    fn received_messages(rx: &mut mpsc::Receiver<Payload>) -> Vec<ServerMessage> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|payload| serde_json::from_str(&payload).unwrap())
+            .collect()
+    }
+
+    /// 取出已经发给某位玩家的全部错误提示
+    fn received_errors(rx: &mut mpsc::Receiver<Payload>) -> Vec<String> {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|payload| match serde_json::from_str(&payload).unwrap() {
+                ServerMessage::Error { message } => Some(message),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pending_action_undo_and_commit() {
+        let (mut room, ids, mut receivers) = seated_room(2).await;
+        room.game_state.room_options.undo_grace_ms = 1000;
+        room.handle_client_message(ids[0], ClientMessage::StartHand).await;
+        let actor = room.game_state.current_player_id().unwrap();
+        let rx = &mut receivers[ids.iter().position(|id| *id == actor).unwrap()];
+        let bets = room.game_state.bets.clone();
+
+        // 撤回窗口内动作只放入等待区，第二个动作被拒绝
+        room.handle_client_message(actor, ClientMessage::PerformAction(PlayerAction::Call)).await;
+        let first_id = room.pending_action.as_ref().unwrap().id;
+        assert_eq!(room.game_state.bets, bets);
+        received_errors(rx);
+        room.handle_client_message(actor, ClientMessage::PerformAction(PlayerAction::Fold)).await;
+        assert_eq!(received_errors(rx), vec!["已有动作等待生效，请稍候".to_string()]);
+        assert_eq!(room.pending_action.as_ref().unwrap().id, first_id);
+
+        // 窗口内撤回后，到期的提交命令不再生效
+        room.handle_client_message(actor, ClientMessage::UndoAction).await;
+        assert!(room.pending_action.is_none());
+        room.handle_command(RoomCommand::CommitAction { action_id: first_id }).await;
+        assert_eq!(room.game_state.bets, bets);
+        assert_eq!(room.game_state.current_player_id(), Some(actor));
+
+        // 窗口结束后提交的动作生效，之后不能再撤回
+        room.handle_client_message(actor, ClientMessage::PerformAction(PlayerAction::Call)).await;
+        let action_id = room.pending_action.as_ref().unwrap().id;
+        room.handle_command(RoomCommand::CommitAction { action_id }).await;
+        assert!(room.pending_action.is_none());
+        assert_ne!(room.game_state.current_player_id(), Some(actor));
+        assert_ne!(room.game_state.bets, bets);
+        room.handle_client_message(actor, ClientMessage::UndoAction).await;
+        assert_eq!(received_errors(rx), vec!["没有可以撤回的动作".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_all_in_skips_undo_window() {
+        let (mut room, ids, _receivers) = seated_room(2).await;
+        room.game_state.room_options.undo_grace_ms = 1000;
+        room.handle_client_message(ids[0], ClientMessage::StartHand).await;
+        let actor = room.game_state.current_player_id().unwrap();
+
+        let all_in = room.game_state.players[&actor].stack;
+
+        // 全下不能撤回，不经过等待区立即生效
+        room.handle_client_message(actor, ClientMessage::PerformAction(PlayerAction::BetOrRaise(all_in))).await;
+        assert!(room.pending_action.is_none());
+        assert_eq!(room.game_state.players[&actor].state, PlayerState::AllIn);
+        assert_ne!(room.game_state.current_player_id(), Some(actor));
+    }
+
+    #[tokio::test]
+    async fn test_confirm_action_rejects_stale_and_repeated_confirms() {
+        let (mut room, ids, mut receivers) = seated_room(2).await;
+        room.game_state.room_options.confirm_actions = true;
+        room.handle_client_message(ids[0], ClientMessage::StartHand).await;
+        let sb = room.game_state.current_player_id().unwrap();
+        let bb = *ids.iter().find(|id| **id != sb).unwrap();
+        let index = |id: PlayerId| ids.iter().position(|p| *p == id).unwrap();
+        receivers.iter_mut().for_each(|rx| { received_errors(rx); });
+        let stale = "局面已经变化，请重新选择动作".to_string();
+        let expired = "该动作已失效".to_string();
+
+        // 确认的编号不对时动作作废，不会执行
+        room.handle_client_message(sb, ClientMessage::PerformAction(PlayerAction::Call)).await;
+        room.handle_client_message(sb, ClientMessage::ConfirmAction(Uuid::new_v4())).await;
+        assert_eq!(received_errors(&mut receivers[index(sb)]), vec![expired.clone()]);
+        assert!(!room.pending_confirms.contains_key(&sb));
+        assert_eq!(room.game_state.current_player_id(), Some(sb));
+
+        // 确认后动作生效，重复发送的确认被拒绝
+        room.handle_client_message(sb, ClientMessage::PerformAction(PlayerAction::Call)).await;
+        let action_id = room.pending_confirms[&sb].id;
+        room.handle_client_message(sb, ClientMessage::ConfirmAction(action_id)).await;
+        assert_eq!(room.game_state.current_player_id(), Some(bb));
+        let bets = room.game_state.bets.clone();
+        room.handle_client_message(sb, ClientMessage::ConfirmAction(action_id)).await;
+        assert_eq!(received_errors(&mut receivers[index(sb)]), vec![expired]);
+        assert_eq!(room.game_state.bets, bets);
+
+        // 提交之后有人加注，最高下注额变了，确认被拒绝
+        room.handle_client_message(sb, ClientMessage::PerformAction(PlayerAction::Check)).await;
+        let stale_id = room.pending_confirms[&sb].id;
+        room.handle_client_message(bb, ClientMessage::PerformAction(PlayerAction::BetOrRaise(200))).await;
+        let raise_id = room.pending_confirms[&bb].id;
+        room.handle_client_message(bb, ClientMessage::ConfirmAction(raise_id)).await;
+        assert_eq!(room.game_state.current_player_id(), Some(sb));
+        room.handle_client_message(sb, ClientMessage::ConfirmAction(stale_id)).await;
+        assert_eq!(received_errors(&mut receivers[index(sb)]), vec![stale.clone()]);
+        assert_eq!(room.game_state.current_player_id(), Some(sb));
+
+        // 提交之后进入了下一轮，最高下注额没变也被拒绝
+        room.handle_client_message(bb, ClientMessage::PerformAction(PlayerAction::Check)).await;
+        let stale_id = room.pending_confirms[&bb].id;
+        let max_bet = room.game_state.max_bet;
+        room.handle_client_message(sb, ClientMessage::PerformAction(PlayerAction::Call)).await;
+        let call_id = room.pending_confirms[&sb].id;
+        room.handle_client_message(sb, ClientMessage::ConfirmAction(call_id)).await;
+        assert_eq!((room.game_state.phase, room.game_state.max_bet), (GamePhase::Flop, max_bet));
+        room.handle_client_message(bb, ClientMessage::ConfirmAction(stale_id)).await;
+        assert_eq!(received_errors(&mut receivers[index(bb)]), vec![stale]);
+        assert_eq!(room.game_state.current_player_id(), Some(bb));
+    }
+
+    #[tokio::test]
+    async fn test_join_approval_queue() {
+        let (mut room, ids, mut receivers) = seated_room(2).await;
+        room.game_state.room_options.require_join_approval = true;
+        receivers.iter_mut().for_each(|rx| { received_messages(rx); });
+        let mut joiners = vec![];
+        for i in 0..3 {
+            let (sender, rx) = MessageSender::channel(16);
+            let id = Uuid::new_v4();
+            assert!(room.join(id, Uuid::new_v4(), format!("j{}", i), sender.clone()).await);
+            joiners.push((id, sender, rx));
+        }
+        // 申请只进入等待队列，由房主收到
+        let requests = received_messages(&mut receivers[0]).into_iter()
+            .filter(|m| matches!(m, ServerMessage::JoinRequested { .. }))
+            .count();
+        assert_eq!(requests, 3);
+        assert!(joiners.iter().all(|(id, ..)| room.pending_joins.contains_key(id) && !room.players.contains_key(id)));
+        assert!(received_events(&mut receivers[1]).is_empty());
+
+        // 批准后加入房间，其他玩家收到加入的消息
+        let (approved, _, approved_rx) = &mut joiners[0];
+        room.handle_client_message(ids[0], ClientMessage::ApproveJoin(*approved)).await;
+        assert!(room.players.contains_key(approved) && room.game_state.players.contains_key(approved));
+        assert!(received_messages(approved_rx).iter().any(|m| matches!(m, ServerMessage::RoomJoined { .. })));
+        assert!(received_events(&mut receivers[1]).iter()
+            .any(|m| matches!(m, ServerMessage::PlayerJoined { player } if player.id == *approved)));
+
+        // 拒绝后移出队列，申请人收到通知
+        let (rejected, _, rejected_rx) = &mut joiners[1];
+        room.handle_client_message(ids[0], ClientMessage::RejectJoin(*rejected)).await;
+        assert!(!room.pending_joins.contains_key(rejected) && !room.players.contains_key(rejected));
+        assert_eq!(received_errors(rejected_rx), vec!["房主拒绝了你的加入申请".to_string()]);
+
+        // 等待中的申请人断开连接后移出队列，房主不能再批准
+        let (left, left_sender, _) = &joiners[2];
+        room.handle_disconnect(*left, left_sender).await;
+        assert!(!room.pending_joins.contains_key(left));
+        room.handle_client_message(ids[0], ClientMessage::ApproveJoin(*left)).await;
+        assert!(!room.players.contains_key(left));
+        assert_eq!(received_errors(&mut receivers[0]), vec!["没有该玩家的加入申请".to_string()]);
+        assert_eq!(room.players.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_spectators_full_at_cap() {
+        let (mut room, _ids, _receivers) = seated_room(2).await;
+        room.game_state.room_options.max_spectators = 2;
+        // 入座的玩家不算观众，上限之内的观众都可以加入
+        for i in 0..2 {
+            assert!(!room.spectators_full());
+            let (sender, mut rx) = MessageSender::channel(16);
+            assert!(room.join(Uuid::new_v4(), Uuid::new_v4(), format!("s{}", i), sender).await);
+            assert!(received_messages(&mut rx).iter().any(|m| matches!(m, ServerMessage::RoomJoined { .. })));
+        }
+
+        // 达到上限后收到专门的错误，没有加入房间
+        assert!(room.spectators_full());
+        let (sender, mut rx) = MessageSender::channel(16);
+        let id = Uuid::new_v4();
+        assert!(!room.join(id, Uuid::new_v4(), "s2".to_string(), sender).await);
+        assert!(matches!(received_messages(&mut rx)[..], [ServerMessage::SpectatorsFull { max_spectators: 2 }]));
+        assert!(!room.players.contains_key(&id));
+    }
+
+    #[tokio::test]
+    async fn test_seat_during_hand_waits_for_next_hand() {
+        let (mut room, ids, _receivers) = seated_room(2).await;
+        room.handle_client_message(ids[0], ClientMessage::StartHand).await;
+        let (sender, _rx) = MessageSender::channel(1024);
+        let late = Uuid::new_v4();
+        room.join(late, Uuid::new_v4(), "late".to_string(), sender).await;
+        room.handle_client_message(late, ClientMessage::RequestSeat { seat_id: 2, stack: 1000 }).await;
+
+        // 坐下后不参与进行中的牌局
+        assert!(room.game_state.seated_players.contains(&late));
+        assert!(!room.game_state.hand_player_order.contains(&late));
+        assert!(!room.game_state.player_indices.contains_key(&late));
+        let folder = room.game_state.current_player_id().unwrap();
+        room.handle_client_message(folder, ClientMessage::PerformAction(PlayerAction::Fold)).await;
+        assert_eq!(room.game_state.phase, GamePhase::Showdown);
+        let stacks: Vec<u32> = ids.iter().map(|id| room.game_state.players[id].stack).collect();
+        assert_eq!(stacks.iter().sum::<u32>(), 2000);
+        assert_ne!(stacks, vec![1000, 1000]);
+        assert_eq!(room.game_state.players[&late].stack, 1000);
+
+        // 下一局正常发牌
+        room.handle_client_message(ids[0], ClientMessage::StartHand).await;
+        assert!(room.game_state.hand_player_order.contains(&late));
+        assert_eq!(room.game_state.players[&late].state, PlayerState::Playing);
+    }
+
+    #[tokio::test]
+    async fn test_transfer_host() {
+        let (mut room, ids, mut receivers) = seated_room(3).await;
+        receivers.iter_mut().for_each(|rx| { received_messages(rx); });
+        let transfer = |player_id| ClientMessage::TransferHost { player_id };
+
+        // 不是房主、转给自己、转给不在房间里的人都被拒绝
+        room.handle_client_message(ids[1], transfer(ids[2])).await;
+        assert_eq!(received_errors(&mut receivers[1]), vec![GameError::NotHost.describe(Locale::Chinese)]);
+        room.handle_client_message(ids[0], transfer(ids[0])).await;
+        room.handle_client_message(ids[0], transfer(Uuid::new_v4())).await;
+        assert_eq!(received_errors(&mut receivers[0]), vec![
+            GameError::AlreadyHost.describe(Locale::Chinese),
+            GameError::TransferToOffline.describe(Locale::Chinese),
+        ]);
+        assert_eq!(room.host_id, ids[0]);
+
+        // 转移成功后广播给所有人，原房主不再能做房主的操作
+        room.handle_client_message(ids[0], transfer(ids[1])).await;
+        assert_eq!(room.host_id, ids[1]);
+        for rx in receivers.iter_mut() {
+            let events = received_events(rx);
+            assert!(events.iter().any(|m| matches!(m, ServerMessage::HostChanged { host_id } if *host_id == ids[1])));
+        }
+        room.handle_client_message(ids[0], transfer(ids[2])).await;
+        assert_eq!(received_errors(&mut receivers[0]), vec![GameError::NotHost.describe(Locale::Chinese)]);
+        let entry = room.timeline.audit_log.last().unwrap();
+        assert_eq!(entry.host_id, ids[0]);
+        assert!(matches!(entry.action, HostAction::TransferHost { player_id } if player_id == ids[1]));
+    }
+
+    #[tokio::test]
+    async fn test_undo_after_deferred_timeout_rearms_timer() {
+        let (mut room, ids, _) = seated_room(2).await;
+        room.game_state.room_options.turn_timer_secs = 30;
+        room.game_state.room_options.undo_grace_ms = 1000;
+        room.handle_client_message(ids[0], ClientMessage::StartHand).await;
+        let Some(ServerMessage::NextToAct { player_id, .. }) = room.game_state.current_turn() else { panic!("没有轮到任何人") };
+        let timer_id = room.turn_timer.as_ref().unwrap().id;
+
+        room.handle_client_message(player_id, ClientMessage::PerformAction(PlayerAction::Fold)).await;
+        assert!(room.pending_action.is_some());
+        // 撤回窗口内计时结束: 不算超时，计时保留
+        room.turn_timer.as_mut().unwrap().deadline = Utc::now();
+        room.handle_turn_timeout(timer_id).await;
+        assert_eq!(room.game_state.current_player_id(), Some(player_id));
+        assert_eq!(room.game_state.players[&player_id].state, PlayerState::Playing);
+
+        // 撤回后重新开始计时，新的计时可以让玩家超时
+        room.handle_client_message(player_id, ClientMessage::UndoAction).await;
+        let timer = room.turn_timer.as_ref().unwrap();
+        assert_ne!(timer.id, timer_id);
+        assert!(timer.deadline > Utc::now());
+        let new_id = timer.id;
+        room.handle_turn_timeout(new_id).await;
+        assert_eq!(room.game_state.players[&player_id].state, PlayerState::Folded);
+    }
+
+    #[tokio::test]
+    async fn test_overlay_rebuilt_only_after_new_events() {
+        let (mut room, ids, _) = seated_room(2).await;
+        let first = room.overlay().unwrap();
+        // 两次事件之间的轮询共用同一份 JSON
+        assert!(Arc::ptr_eq(&first, &room.overlay().unwrap()));
+
+        room.handle_client_message(ids[0], ClientMessage::StartHand).await;
+        let second = room.overlay().unwrap();
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_ne!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_saved_room_round_trip() {
+        let (mut room, ids, _) = seated_room(2).await;
+        room.game_state.room_options.correspondence = true;
+        let seed = 0x1234_5678_9abc_def0;
+        room.game_state.duplicate = Some(DuplicateDeal { match_id: Uuid::new_v4(), seed, table: 0, hands: 0 });
+        let secrets: Vec<PlayerSecret> = ids.iter().map(|id| room.players[id].secret).collect();
+        let sender = room.players[&ids[1]].sender.clone();
+        room.handle_disconnect(ids[1], &sender).await;
+        room.handle_client_message(ids[0], ClientMessage::StartHand).await;
+        let stacks: Vec<u32> = ids.iter().map(|id| room.game_state.players[id].stack).collect();
+
+        // 存档里没有凭证原文、复式对局的种子和牌堆
+        let json = serde_json::to_string(&room.saved_room()).unwrap();
+        for secret in secrets.iter() {
+            assert!(!json.contains(&secret.to_string()) && !json.contains(&secret.simple().to_string()));
+        }
+        assert!(!json.contains(&seed.to_string()));
+        assert!(!json.contains("\"deck\""));
+
+        let restored = Room::restore(serde_json::from_str(&json).unwrap());
+        // 所有人都处于断线状态，用原来的凭证重连
+        assert!(restored.players.is_empty());
+        for (id, secret) in ids.iter().zip(secrets.iter()) {
+            assert!(restored.verify_secret(*id, secret));
+            assert!(!restored.verify_secret(*id, &Uuid::new_v4()));
+        }
+        if correspondence::can_seal() {
+            // 配置了存档密钥: 牌局和复式对局原样继续
+            assert_eq!(restored.game_state.deck(), room.game_state.deck());
+            assert_eq!(restored.game_state.duplicate.map(|d| d.seed), Some(seed));
+            assert_eq!(restored.game_state.current_player_id(), room.game_state.current_player_id());
+        } else {
+            // 没有密钥时牌堆没有保存，进行中的牌局作废并退还投入，牌桌退出复式对局
+            assert!(!restored.game_state.hand_in_progress());
+            assert!(restored.game_state.duplicate.is_none());
+            assert!(ids.iter().all(|id| restored.game_state.players[id].stack == 1000));
+            assert!(stacks.iter().any(|&stack| stack < 1000));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_private_settings_only_sent_to_owner() {
+        let (mut room, ids, mut receivers) = seated_room(2).await;
+        receivers.iter_mut().for_each(|rx| { received_events(rx); });
+        room.handle_client_message(ids[1], ClientMessage::SetAutoMuck(true)).await;
+
+        let auto_muck = |events: Vec<ServerMessage>| events.into_iter().find_map(|e| match e {
+            ServerMessage::PlayerUpdated { player } if player.id == ids[1] => Some(player.auto_muck),
+            _ => None,
+        });
+        assert_eq!(auto_muck(received_events(&mut receivers[1])), Some(true));
+        assert_eq!(auto_muck(received_events(&mut receivers[0])), Some(false));
+        // 时间线和补发缓冲区中也只有所有者能拿到私人设置
+        assert!(room.timeline.entries.iter().all(|e| !matches!(&e.event, ServerMessage::PlayerUpdated { player } if player.auto_muck)));
+        let replay = |id: PlayerId| room.replay[&id].since(0).unwrap().iter().any(|p| p.contains("\"auto_muck\":true"));
+        assert!(replay(ids[1]) && !replay(ids[0]));
+    }
+
+    #[tokio::test]
+    async fn test_auto_fold_threshold_only_sent_to_owner() {
+        let (mut room, ids, mut receivers) = seated_room(2).await;
+        receivers.iter_mut().for_each(|rx| { received_events(rx); });
+        room.handle_client_message(ids[1], ClientMessage::SetAutoFold(30)).await;
+
+        let auto_fold = |events: Vec<ServerMessage>| events.into_iter().find_map(|e| match e {
+            ServerMessage::PlayerUpdated { player } if player.id == ids[1] => Some(player.auto_fold_below),
+            _ => None,
+        });
+        assert_eq!(auto_fold(received_events(&mut receivers[1])), Some(30));
+        assert_eq!(auto_fold(received_events(&mut receivers[0])), Some(0));
+
+        // 之后加入的玩家拿到的初始状态、时间线和叠加层中都看不到这个阈值
+        let (sender, mut rx) = MessageSender::channel(16);
+        room.join(Uuid::new_v4(), Uuid::new_v4(), "late".to_string(), sender).await;
+        let joined = std::iter::from_fn(|| rx.try_recv().ok())
+            .find_map(|payload| match serde_json::from_str(&payload).unwrap() {
+                ServerMessage::RoomJoined { game_state, .. } => Some(game_state),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(joined.players[&ids[1]].auto_fold_below, 0);
+        assert!(room.timeline.entries.iter().all(|e| !matches!(&e.event, ServerMessage::PlayerUpdated { player } if player.auto_fold_below != 0)));
+        assert!(!room.overlay().unwrap().contains("auto_fold"));
+        // 服务器自己的状态仍然保留阈值，轮到该玩家时照常生效
+        assert_eq!(room.game_state.players[&ids[1]].auto_fold_below, 30);
+    }
+
+    #[tokio::test]
+    async fn test_kicked_player_removed_after_hand() {
+        let (mut room, ids, mut receivers) = seated_room(3).await;
+        room.handle_client_message(ids[0], ClientMessage::StartHand).await;
+        receivers.iter_mut().for_each(|rx| { received_events(rx); });
+        let client = |player_id, msg| RoomCommand::Client { player_id, msg };
+
+        room.handle_command(client(ids[0], ClientMessage::KickPlayer { player_id: ids[2] })).await;
+        // 被移出的玩家收到通知后连接随之关闭
+        let closing = std::iter::from_fn(|| receivers[2].try_recv().ok()).last().unwrap();
+        assert!(closing.closes_connection());
+        assert!(closing.contains("Kicked"));
+        // 本局还没结束，先保留他的弃牌
+        assert!(room.game_state.hand_in_progress());
+        assert!(room.game_state.players.contains_key(&ids[2]));
+
+        let actor = room.game_state.current_player_id().unwrap();
+        room.handle_command(client(actor, ClientMessage::PerformAction(PlayerAction::Fold))).await;
+        assert!(!room.game_state.hand_in_progress());
+        assert!(!room.game_state.players.contains_key(&ids[2]));
+        assert!(received_events(&mut receivers[0]).iter().any(|e| matches!(e, ServerMessage::PlayerLeft { player_id } if *player_id == ids[2])));
+        assert!(room.game_state.session_summary().players.iter().all(|p| p.player_id != ids[2]));
+    }
+
+    #[tokio::test]
+    async fn test_host_only_errors_are_localized() {
+        let (mut room, ids, mut receivers) = seated_room(2).await;
+        room.game_state.players.get_mut(&ids[1]).unwrap().profile.locale = Locale::English;
+        receivers.iter_mut().for_each(|rx| { received_events(rx); });
+        let host_only = [
+            ClientMessage::StartHand,
+            ClientMessage::StartDuplicate,
+            ClientMessage::ShuffleSeats,
+            ClientMessage::BombPotNextHand,
+            ClientMessage::ApproveDeposit(ids[1]),
+            ClientMessage::RejectDeposit(ids[1]),
+            ClientMessage::ApproveJoin(ids[1]),
+            ClientMessage::RejectJoin(ids[1]),
+            ClientMessage::ExportTimeline(Locale::Chinese),
+            ClientMessage::GetAuditLog,
+            ClientMessage::SetDiscordChannel(None),
+            ClientMessage::EndSession,
+            ClientMessage::KickPlayer { player_id: ids[0] },
+            ClientMessage::TransferHost { player_id: ids[1] },
+            ClientMessage::SetRoomOption(RoomOption::Ante(10)),
+        ];
+        let count = host_only.len();
+        for msg in host_only {
+            room.handle_client_message(ids[1], msg).await;
+        }
+        // 每一项只有房主能做的操作都按玩家的语言回复同一个错误
+        assert_eq!(received_errors(&mut receivers[1]), vec![GameError::NotHost.describe(Locale::English); count]);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_chat_does_not_count_toward_flood_limit() {
+        let (mut room, ids, mut receivers) = seated_room(2).await;
+        receivers.iter_mut().for_each(|rx| { received_events(rx); });
+        let chat = |text: &str| ClientMessage::Chat { text: text.to_string() };
+        // 空白和过长的发言被拒绝，不占用发言次数
+        for _ in 0..CHAT_BURST {
+            room.handle_client_message(ids[1], chat("   ")).await;
+            room.handle_client_message(ids[1], chat(&"a".repeat(MAX_CHAT_CHARS + 1))).await;
+        }
+        for i in 0..CHAT_BURST {
+            room.handle_client_message(ids[1], chat(&format!("hello {}", i))).await;
+        }
+        let broadcasts = received_events(&mut receivers[0]).iter()
+            .filter(|e| matches!(e, ServerMessage::ChatBroadcast { .. }))
+            .count();
+        assert_eq!(broadcasts, CHAT_BURST);
+
+        // 正常的发言达到上限后才被限制
+        room.handle_client_message(ids[1], chat("one more")).await;
+        assert!(received_events(&mut receivers[0]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stop_duplicate_releases_match() {
+        let (mut room, ids, _receivers) = seated_room(2).await;
+        room.handle_client_message(ids[0], ClientMessage::StartDuplicate).await;
+        let first = room.game_state.duplicate.unwrap().match_id;
+        // 换到新的对局时退出原来的对局，原对局没有牌桌后被删除
+        room.handle_client_message(ids[0], ClientMessage::StartDuplicate).await;
+        let second = room.game_state.duplicate.unwrap().match_id;
+        assert!(room.duplicates.join(first).is_none());
+        room.handle_client_message(ids[0], ClientMessage::StopDuplicate).await;
+        assert!(room.game_state.duplicate.is_none());
+        assert!(room.duplicates.join(second).is_none());
+    }
+}
@@ -0,0 +1,442 @@
+// This file is part of poker_eden.
+//
+// poker_eden is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// poker_eden is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with poker_eden. If not, see <https://www.gnu.org/licenses/>.
+//
+// Copyright (C) 2025 Peilin Fan <peilin.fan@foxmail.com>
+
+//! # 手机网页客户端
+//!
+//! 编译成 WebAssembly 后由服务器在 `/play/` 下提供，牌友用手机浏览器打开房主分享的链接即可入座，
+//! 不需要安装任何东西。页面上的 JavaScript 只负责 WebSocket 连接和绘制界面；
+//! 消息的解析、游戏状态的同步 (`GameState::apply_event`) 和每个按钮对应的指令都在这里完成，
+//! 与其他客户端使用同一套核心逻辑。
+
+use poker_eden_core::*;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::str::FromStr;
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+/// 页面上显示的最近日志条数
+const LOG_LEN: usize = 30;
+
+/// 网页客户端与一个房间的会话
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WebClient {
+    game_state: Option<GameState>,
+    my_id: Option<PlayerId>,
+    my_secret: Option<PlayerSecret>,
+    host_id: Option<PlayerId>,
+    /// 最后收到的房间事件序号，重连时服务器从这里开始补发
+    last_seq: Option<u64>,
+    /// 轮到自己行动时的合法动作
+    valid_actions: Vec<PlayerActionType>,
+    /// 确认动作模式下等待确认的动作
+    pending_confirm: Option<Uuid>,
+    /// 服务器最后发来的错误或提示
+    message: Option<String>,
+    offline: bool,
+    log: VecDeque<String>,
+}
+
+/// 一张牌在页面上的写法
+#[derive(Serialize)]
+struct CardView {
+    rank: String,
+    suit: &'static str,
+    red: bool,
+}
+
+impl From<&Card> for CardView {
+    fn from(card: &Card) -> Self {
+        let (suit, red) = match card.suit {
+            Suit::Spade => ("♠", false),
+            Suit::Heart => ("♥", true),
+            Suit::Club => ("♣", false),
+            Suit::Diamond => ("♦", true),
+        };
+        let rank = match card.rank {
+            Rank::Ten => "10".to_string(),
+            rank => rank.to_string(),
+        };
+        CardView { rank, suit, red }
+    }
+}
+
+/// 一个就座的玩家
+#[derive(Serialize)]
+struct SeatView {
+    seat: Option<u8>,
+    nickname: String,
+    stack: u32,
+    /// 本轮主动下注的部分
+    bet: u32,
+    /// 本轮的盲注或抓位
+    forced: u32,
+    straddle: bool,
+    /// 前注和死盲
+    dead: u32,
+    /// 本局投入奖池的全部筹码
+    invested: u32,
+    /// 本局每一轮的下注，第一轮下注时只有一项
+    streets: Vec<u32>,
+    state: String,
+    is_me: bool,
+    is_dealer: bool,
+    is_acting: bool,
+    /// 看不到的底牌为 null，弃牌后为空
+    cards: Vec<Option<CardView>>,
+}
+
+/// 动作按钮: 只有轮到自己时才有内容
+#[derive(Serialize, Default)]
+struct ActionsView {
+    fold: bool,
+    check: bool,
+    call: Option<u32>,
+    /// 下注或加注 (到) 的范围
+    raise: Option<(u32, u32)>,
+    is_bet: bool,
+    /// 换牌阶段最多可以换的张数
+    discard: Option<usize>,
+    confirm: bool,
+}
+
+/// 页面需要显示的全部内容，每次收到消息后整体重绘
+#[derive(Serialize, Default)]
+struct View {
+    in_room: bool,
+    offline: bool,
+    room_id: Option<String>,
+    is_host: bool,
+    seated: bool,
+    can_start: bool,
+    phase: String,
+    game: String,
+    blinds: String,
+    /// 带入范围，不限时为空
+    buy_in: String,
+    pot: u32,
+    boards: Vec<Vec<Option<CardView>>>,
+    my_cards: Vec<CardView>,
+    players: Vec<SeatView>,
+    actions: ActionsView,
+    message: Option<String>,
+    log: Vec<String>,
+}
+
+fn encode(msg: &ClientMessage) -> String {
+    serde_json::to_string(msg).expect("客户端消息总能序列化")
+}
+
+#[wasm_bindgen]
+impl WebClient {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> WebClient {
+        WebClient::default()
+    }
+
+    /// 处理服务器发来的一条消息 (JSON 文本)，返回需要立即回复服务器的消息
+    pub fn handle(&mut self, text: &str) -> Vec<String> {
+        match serde_json::from_str::<ServerMessage>(text) {
+            Ok(msg) => self.handle_message(msg).iter().map(encode).collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// 连接断开
+    pub fn disconnected(&mut self) {
+        self.offline = true;
+        self.valid_actions.clear();
+        self.message = Some("已与服务器断开连接".to_string());
+    }
+
+    /// 当前界面需要显示的内容 (JSON)
+    pub fn view(&self) -> String {
+        serde_json::to_string(&self.build_view()).expect("界面内容总能序列化")
+    }
+
+    pub fn create_room(&self, nickname: &str) -> String {
+        encode(&ClientMessage::CreateRoom { nickname: nickname.trim().to_string(), config: RoomConfig::default() })
+    }
+
+    /// 房间ID无法识别时返回 undefined
+    pub fn join_room(&self, room_id: &str, nickname: &str) -> Option<String> {
+        let room_id = Uuid::from_str(room_id.trim()).ok()?;
+        Some(encode(&ClientMessage::JoinRoom { room_id, nickname: nickname.trim().to_string() }))
+    }
+
+    /// 断线后凭加入房间时拿到的凭证回到原来的座位，新的连接建立后第一条发送
+    pub fn reconnect(&mut self) -> Option<String> {
+        let (Some(gs), Some(player_id), Some(secret)) = (&self.game_state, self.my_id, self.my_secret) else { return None };
+        self.offline = false;
+        self.message = None;
+        Some(encode(&ClientMessage::Reconnect { room_id: gs.room_id, player_id, secret, last_seq: self.last_seq }))
+    }
+
+    pub fn seat(&self, seat_id: u8, stack: u32) -> String {
+        encode(&ClientMessage::RequestSeat { seat_id, stack })
+    }
+
+    pub fn leave_seat(&self) -> String {
+        encode(&ClientMessage::LeaveSeat)
+    }
+
+    pub fn start_hand(&self) -> String {
+        encode(&ClientMessage::StartHand)
+    }
+
+    pub fn fold(&self) -> String {
+        encode(&PlayerAction::Fold.into())
+    }
+
+    /// 过牌或跟注，取决于当前哪个动作合法
+    pub fn check_or_call(&self) -> Option<String> {
+        self.valid_actions.iter().find_map(|a| match a {
+            PlayerActionType::Check => Some(encode(&PlayerAction::Check.into())),
+            PlayerActionType::Call(_) => Some(encode(&PlayerAction::Call.into())),
+            _ => None,
+        })
+    }
+
+    /// 下注或加注到 `amount`，超出允许的范围时返回 undefined
+    pub fn bet_or_raise(&self, amount: u32) -> Option<String> {
+        let allowed = self.valid_actions.iter().any(|a| matches!(a,
+            PlayerActionType::Bet { min, max } | PlayerActionType::Raise { min, max } if (*min..=*max).contains(&amount)));
+        allowed.then(|| encode(&PlayerAction::BetOrRaise(amount).into()))
+    }
+
+    /// 换掉选中的底牌，序号从 0 开始
+    pub fn discard(&self, indices: Vec<u32>) -> String {
+        encode(&ClientMessage::DiscardCards(indices.into_iter().map(|i| i as usize).collect()))
+    }
+
+    pub fn confirm(&self) -> Option<String> {
+        self.pending_confirm.map(|id| encode(&ClientMessage::ConfirmAction(id)))
+    }
+
+    pub fn chat(&self, text: &str) -> Option<String> {
+        let text = text.trim();
+        (!text.is_empty()).then(|| encode(&ClientMessage::Chat { text: text.to_string() }))
+    }
+}
+
+impl WebClient {
+    fn push_log(&mut self, line: String) {
+        if self.log.len() == LOG_LEN {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+    }
+
+    fn nickname(&self, player_id: &PlayerId) -> String {
+        self.game_state.as_ref()
+            .and_then(|gs| gs.players.get(player_id))
+            .map_or_else(|| "未知玩家".to_string(), |p| p.nickname.clone())
+    }
+
+    fn handle_message(&mut self, msg: ServerMessage) -> Vec<ClientMessage> {
+        if let ServerMessage::Event { seq, event } = msg {
+            self.last_seq = Some(seq);
+            return self.handle_message(*event);
+        }
+        self.message = None;
+
+        match &msg {
+            ServerMessage::RoomJoined { your_id, your_secret, game_state, host_id, .. } => {
+                self.my_id = Some(*your_id);
+                self.my_secret = Some(*your_secret);
+                self.host_id = Some(*host_id);
+                self.last_seq = None;
+                self.game_state = Some(game_state.clone());
+                return vec![];
+            }
+            ServerMessage::HostChanged { host_id } => {
+                self.host_id = Some(*host_id);
+                return vec![];
+            }
+            ServerMessage::Error { message } | ServerMessage::Info { message } => {
+                self.message = Some(message.clone());
+                return vec![];
+            }
+            // 房间关闭后回到登录界面，显示关闭的原因
+            ServerMessage::RoomClosed { reason } => {
+                *self = WebClient { message: Some(format!("房间已关闭: {}", reason)), ..WebClient::default() };
+                return vec![];
+            }
+            _ => {}
+        }
+
+        let Some(gs) = &mut self.game_state else { return vec![] };
+        gs.apply_event(&msg, self.my_id);
+
+        let mut replies = vec![];
+        match msg {
+            ServerMessage::HandStarted { variant, .. } => {
+                self.valid_actions.clear();
+                self.push_log(format!("新的一局开始: {}", variant));
+                replies.push(ClientMessage::GetMyHand);
+            }
+            ServerMessage::NextToAct { player_id, valid_actions, .. } => {
+                self.pending_confirm = None;
+                self.valid_actions = if self.my_id == Some(player_id) { valid_actions } else { vec![] };
+            }
+            ServerMessage::PlayerActed { player_id, action, total_bet, .. } => {
+                let text = match action {
+                    PlayerAction::Fold => "弃牌".to_string(),
+                    PlayerAction::Check => "过牌".to_string(),
+                    PlayerAction::Call => format!("跟注到 ${}", total_bet),
+                    PlayerAction::BetOrRaise(_) => format!("下注到 ${}", total_bet),
+                };
+                let line = format!("{} {}", self.nickname(&player_id), text);
+                self.push_log(line);
+                if self.my_id == Some(player_id) {
+                    self.valid_actions.clear();
+                }
+            }
+            ServerMessage::Showdown { results, .. } => {
+                self.valid_actions.clear();
+                for result in results.iter().filter(|r| r.winnings > 0) {
+                    let line = format!("{} 赢得 ${}", self.nickname(&result.player_id), result.winnings);
+                    self.push_log(line);
+                }
+            }
+            ServerMessage::ActionPendingConfirm { action_id, .. } => self.pending_confirm = Some(action_id),
+            ServerMessage::ChatBroadcast { player_id, text, .. } => {
+                let line = format!("{}: {}", self.nickname(&player_id), text);
+                self.push_log(line);
+            }
+            _ => {}
+        }
+        replies
+    }
+
+    fn build_view(&self) -> View {
+        let Some(gs) = &self.game_state else {
+            return View { message: self.message.clone(), ..View::default() };
+        };
+        let seated = self.my_id.is_some_and(|id| gs.seated_players.contains(&id));
+        let is_host = self.my_id.is_some() && self.my_id == self.host_id;
+        let waiting = matches!(gs.phase, GamePhase::WaitingForPlayers | GamePhase::Showdown);
+        let my_idx = self.my_id.and_then(|id| gs.player_indices.get(&id).copied());
+
+        let players = gs.seated_players.iter().filter_map(|id| gs.players.get(id)).map(|p| {
+            let idx = gs.player_indices.get(&p.id).copied();
+            let cards = match idx.and_then(|i| gs.player_cards.get(i)) {
+                Some(cards) if p.state != PlayerState::Folded => cards.iter().map(|c| c.as_ref().map(CardView::from)).collect(),
+                _ => vec![],
+            };
+            let bet = idx.map_or_else(StreetBet::default, |i| gs.street_bet(i));
+            SeatView {
+                seat: p.seat_id,
+                nickname: p.nickname.clone(),
+                stack: p.stack,
+                bet: bet.voluntary,
+                forced: bet.forced,
+                straddle: bet.straddle,
+                dead: bet.dead,
+                invested: idx.map_or(0, |i| gs.hand_invested(i)),
+                streets: idx.map_or_else(Vec::new, |i| gs.street_history(i)),
+                state: p.state.to_string(),
+                is_me: self.my_id == Some(p.id),
+                is_dealer: gs.dealer_id() == Some(p.id),
+                is_acting: gs.hand_in_progress() && gs.current_player_id() == Some(p.id),
+                cards,
+            }
+        }).collect();
+
+        let mut actions = ActionsView { confirm: self.pending_confirm.is_some(), ..ActionsView::default() };
+        for action in &self.valid_actions {
+            match *action {
+                PlayerActionType::Fold => actions.fold = true,
+                PlayerActionType::Check => actions.check = true,
+                PlayerActionType::Call(amount) => actions.call = Some(amount),
+                PlayerActionType::Bet { min, max } => (actions.raise, actions.is_bet) = (Some((min, max)), true),
+                PlayerActionType::Raise { min, max } => actions.raise = Some((min, max)),
+                PlayerActionType::Discard(max) => actions.discard = Some(max),
+            }
+        }
+
+        View {
+            in_room: true,
+            offline: self.offline,
+            room_id: Some(gs.room_id.to_string()),
+            is_host,
+            seated,
+            can_start: is_host && seated && waiting,
+            phase: gs.phase.to_string(),
+            game: format!("{} {}", gs.variant, gs.betting_structure),
+            blinds: format!("{}/{}", gs.small_blind, gs.big_blind),
+            buy_in: gs.config.buy_in_range().unwrap_or_default(),
+            pot: gs.pot,
+            boards: gs.community_cards.iter()
+                .map(|board| board.iter().map(|c| c.as_ref().map(CardView::from)).collect())
+                .collect(),
+            my_cards: my_idx.and_then(|i| gs.player_cards.get(i))
+                .map_or(vec![], |cards| cards.iter().flatten().map(CardView::from).collect()),
+            players,
+            actions,
+            message: self.message.clone(),
+            log: self.log.iter().cloned().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_join_and_act() {
+        let mut state = GameState::default();
+        let alice = Uuid::new_v4();
+        let mut client = WebClient::new();
+        let joined = ServerMessage::RoomJoined { your_id: alice, your_secret: Uuid::new_v4(), game_state: state.for_client(Audience::Player(alice)), host_id: alice, config: RoomConfig::default() };
+        assert!(client.handle(&serde_json::to_string(&joined).unwrap()).is_empty());
+        assert!(client.build_view().in_room);
+        assert!(client.check_or_call().is_none());
+
+        // 轮到自己时才有动作按钮，超出范围的加注在本地拒绝
+        let valid_actions = vec![PlayerActionType::Fold, PlayerActionType::Call(10), PlayerActionType::Raise { min: 20, max: 1000 }];
+        client.handle_message(ServerMessage::NextToAct { player_id: alice, valid_actions, context: TurnContext::default() });
+        let view = client.build_view();
+        assert!(view.actions.fold);
+        assert_eq!((view.actions.call, view.actions.raise), (Some(10), Some((20, 1000))));
+        assert!(client.check_or_call().unwrap().contains("Call"));
+        assert!(client.bet_or_raise(10).is_none());
+        assert!(client.bet_or_raise(20).is_some());
+
+        // 房主交给别人后不再显示房主的按钮
+        assert!(client.build_view().is_host);
+        client.handle_message(ServerMessage::HostChanged { host_id: Uuid::new_v4() });
+        assert!(!client.build_view().is_host);
+
+        // 断线后凭凭证重连，带上最后收到的事件序号
+        state.room_options.ante = 1;
+        client.handle_message(ServerMessage::Event { seq: 7, event: Box::new(ServerMessage::RoomOptionsUpdated(state.room_options.clone())) });
+        client.disconnected();
+        assert!(client.build_view().offline);
+        let reconnect: ClientMessage = serde_json::from_str(&client.reconnect().unwrap()).unwrap();
+        assert!(matches!(reconnect, ClientMessage::Reconnect { player_id, last_seq: Some(7), .. } if player_id == alice));
+
+        // 房间关闭后回到登录界面，显示关闭的原因
+        client.handle_message(ServerMessage::RoomClosed { reason: RoomCloseReason::Expired });
+        let view = client.build_view();
+        assert!(!view.in_room);
+        assert_eq!(view.message.as_deref(), Some("房间已关闭: 房间长时间没有操作，已过期"));
+        assert!(client.reconnect().is_none());
+    }
+}
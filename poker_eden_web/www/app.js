@@ -0,0 +1,187 @@
+// Poker Eden 手机网页客户端
+//
+// 这里只负责 WebSocket 连接和绘制界面，消息的解析、游戏状态的同步和按钮对应的指令
+// 都由编译成 WebAssembly 的 poker_eden_web (pkg/) 完成。
+
+import init, { WebClient } from './pkg/poker_eden_web.js';
+
+await init();
+const client = new WebClient();
+let socket = null;
+let view = JSON.parse(client.view());
+// 换牌阶段选中的底牌序号
+const selected = new Set();
+
+const $ = (id) => document.getElementById(id);
+
+// 房间ID来自分享链接的 # 部分，昵称记在本地
+$('room-id').value = decodeURIComponent(location.hash.slice(1));
+$('nickname').value = localStorage.getItem('poker-eden-nickname') ?? '';
+
+function connect(firstMessage) {
+  const scheme = location.protocol === 'https:' ? 'wss' : 'ws';
+  socket = new WebSocket(`${scheme}://${location.host}/ws`);
+  socket.onopen = () => socket.send(firstMessage);
+  socket.onmessage = (event) => {
+    const wasInRoom = view.in_room;
+    for (const reply of client.handle(event.data)) {
+      socket.send(reply);
+    }
+    render();
+    // 房间已关闭: 断开连接，保留登录界面上显示的关闭原因
+    if (wasInRoom && !view.in_room) {
+      socket.onclose = null;
+      socket.close();
+    }
+  };
+  socket.onclose = () => {
+    client.disconnected();
+    render();
+  };
+}
+
+function send(message) {
+  if (message && socket?.readyState === WebSocket.OPEN) {
+    socket.send(message);
+  }
+}
+
+function cardElement(card, small) {
+  const el = document.createElement('div');
+  el.className = 'card' + (small ? ' small' : '');
+  if (card === undefined) {
+    el.classList.add('empty');
+  } else if (card === null) {
+    el.classList.add('back');
+  } else {
+    if (card.red) el.classList.add('red');
+    el.innerHTML = `<span>${card.rank}</span><span>${card.suit}</span>`;
+  }
+  return el;
+}
+
+function render() {
+  view = JSON.parse(client.view());
+  $('login').hidden = view.in_room;
+  $('room').hidden = !view.in_room;
+  $('login-message').textContent = view.message ?? '';
+  if (!view.in_room) return;
+
+  history.replaceState(null, '', `#${view.room_id}`);
+  $('info').innerHTML = `<span>${view.game}</span><span>盲注 ${view.blinds}</span>`
+    + (view.buy_in ? `<span>带入 ${view.buy_in}</span>` : '') + `<span>${view.phase}</span>`;
+  $('pot').textContent = `奖池 $${view.pot}`;
+  $('message').textContent = view.message ?? '';
+
+  $('board').replaceChildren(...view.boards.flat().map((card) => cardElement(card ?? undefined)));
+
+  $('players').replaceChildren(...view.players.map((p) => {
+    const li = document.createElement('li');
+    li.classList.toggle('me', p.is_me);
+    li.classList.toggle('acting', p.is_acting);
+    const name = document.createElement('strong');
+    name.textContent = `${p.seat ?? '-'}. ${p.nickname}${p.is_dealer ? ' (D)' : ''}`;
+    const stack = document.createElement('span');
+    // 前注、盲注 (抓位) 和主动下注分开显示
+    stack.innerHTML = `$${p.stack} · ${p.state}`
+      + (p.dead > 0 ? ` <span class="dead">前注 $${p.dead}</span>` : '')
+      + (p.forced > 0 ? ` <span class="forced">${p.straddle ? '抓位' : '盲注'} $${p.forced}</span>` : '')
+      + (p.bet > 0 ? ` <span class="bet">下注 $${p.bet}</span>` : '')
+      + (p.streets.length > 1 && p.invested > 0 ? ` <span class="invested">本局 $${p.invested} (${p.streets.join('/')})</span>` : '');
+    const cards = document.createElement('div');
+    cards.className = 'cards';
+    cards.replaceChildren(...p.cards.map((card) => cardElement(card, true)));
+    li.replaceChildren(name, stack, cards);
+    return li;
+  }));
+
+  const actions = view.actions;
+  if (actions.discard === null) selected.clear();
+  $('my-cards').replaceChildren(...view.my_cards.map((card, i) => {
+    const el = cardElement(card);
+    el.classList.toggle('selected', selected.has(i));
+    el.onclick = () => {
+      if (actions.discard === null) return;
+      if (!selected.delete(i) && selected.size < actions.discard) selected.add(i);
+      render();
+    };
+    return el;
+  }));
+
+  // 只显示当前能用的按钮
+  const show = (id, visible) => { $(id).hidden = !visible; };
+  show('seat-controls', !view.seated && !view.offline);
+  show('fold', actions.fold);
+  show('call', actions.check || actions.call !== null);
+  $('call').textContent = actions.check ? '过牌' : `跟注 $${actions.call}`;
+  show('raise-controls', actions.raise !== null);
+  show('raise', actions.raise !== null);
+  if (actions.raise !== null) {
+    const [min, max] = actions.raise;
+    const amount = $('amount');
+    amount.min = min;
+    amount.max = max;
+    amount.step = 1;
+    if (+amount.value < min || +amount.value > max) amount.value = min;
+    updateAmount();
+    $('raise').dataset.verb = actions.is_bet ? '下注' : '加注到';
+  }
+  show('discard', actions.discard !== null);
+  $('discard').textContent = selected.size === 0 ? '不换' : `换 ${selected.size} 张`;
+  show('confirm', actions.confirm);
+  show('start', view.can_start);
+  show('leave', view.seated && !view.offline && !actions.fold && actions.discard === null);
+  show('reconnect', view.offline);
+
+  $('log').replaceChildren(...view.log.map((line) => {
+    const li = document.createElement('li');
+    li.textContent = line;
+    return li;
+  }));
+}
+
+function updateAmount() {
+  const amount = $('amount').value;
+  $('amount-label').textContent = `$${amount}`;
+  $('raise').textContent = `${$('raise').dataset.verb ?? '加注到'} $${amount}`;
+}
+
+$('enter').onclick = () => {
+  const nickname = $('nickname').value.trim();
+  if (!nickname) return;
+  localStorage.setItem('poker-eden-nickname', nickname);
+  const roomId = $('room-id').value.trim();
+  const first = roomId ? client.join_room(roomId, nickname) : client.create_room(nickname);
+  if (first === undefined) {
+    $('login-message').textContent = '无法识别的房间ID';
+    return;
+  }
+  connect(first);
+};
+$('seat').onclick = () => {
+  const seat = parseInt($('seat-id').value, 10);
+  const stack = parseInt($('stack').value, 10);
+  if (seat >= 0 && stack > 0) send(client.seat(seat, stack));
+};
+$('fold').onclick = () => send(client.fold());
+$('call').onclick = () => send(client.check_or_call());
+$('amount').oninput = updateAmount;
+$('raise').onclick = () => send(client.bet_or_raise(+$('amount').value));
+$('discard').onclick = () => {
+  send(client.discard(Uint32Array.from(selected)));
+  selected.clear();
+};
+$('confirm').onclick = () => send(client.confirm());
+$('start').onclick = () => send(client.start_hand());
+$('leave').onclick = () => send(client.leave_seat());
+$('reconnect').onclick = () => {
+  const first = client.reconnect();
+  if (first) connect(first);
+};
+$('chat-form').onsubmit = (event) => {
+  event.preventDefault();
+  send(client.chat($('chat').value));
+  $('chat').value = '';
+};
+
+render();